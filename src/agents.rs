@@ -0,0 +1,161 @@
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Substrings (lowercase) of self-identified search and indexing crawlers
+const CRAWLER_MARKERS: [&str; 9] = [
+    "googlebot",
+    "bingbot",
+    "slurp",
+    "duckduckbot",
+    "baiduspider",
+    "yandexbot",
+    "applebot",
+    "crawler",
+    "spider",
+];
+
+/// Substrings (lowercase) of HTTP libraries, CLI tools and monitors
+const BOT_MARKERS: [&str; 12] = [
+    "curl/",
+    "wget/",
+    "python-requests",
+    "python-urllib",
+    "go-http-client",
+    "okhttp",
+    "java/",
+    "libwww-perl",
+    "httpclient",
+    "headlesschrome",
+    "uptime",
+    "bot",
+];
+
+/// What kind of client a user agent belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentClass {
+    Browser,
+    /// Search-engine and other self-identified crawlers
+    Crawler,
+    /// Scripts, HTTP libraries, headless browsers and monitors
+    Bot,
+    Other,
+}
+
+/// Classify a user agent by well-known markers; crawlers are checked before bots
+/// since most crawler agents also say "bot"
+pub fn classify(user_agent: &str) -> AgentClass {
+    let ua = user_agent.to_ascii_lowercase();
+    if CRAWLER_MARKERS.iter().any(|m| ua.contains(m)) {
+        AgentClass::Crawler
+    } else if BOT_MARKERS.iter().any(|m| ua.contains(m)) {
+        AgentClass::Bot
+    } else if ua.starts_with("mozilla/") || ua.starts_with("opera/") {
+        AgentClass::Browser
+    } else {
+        AgentClass::Other
+    }
+}
+
+/// A user agent string and how often it was seen
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentItem {
+    pub value: String,
+    pub class: AgentClass,
+    pub count: usize,
+    pub percentage: f64,
+}
+
+/// Requests per agent class, as shares of requests that carried a user agent
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassShare {
+    pub class: AgentClass,
+    pub count: usize,
+    pub percentage: f64,
+}
+
+/// Top user agents and the browser/crawler/bot split
+#[derive(Debug, Clone, Serialize)]
+pub struct UserAgentReport {
+    /// Requests whose line carried a user agent
+    pub requests: usize,
+    pub classes: Vec<ClassShare>,
+    pub top_agents: Vec<AgentItem>,
+}
+
+/// Rank user agents and summarise them by class.
+///
+/// Returns `None` when no entry carries a user agent, as for formats that do not log one.
+pub fn analyze(entries: &[LogEntry], top_n: usize) -> Option<UserAgentReport> {
+    let mut agents: HashMap<&str, usize> = HashMap::new();
+    for agent in entries.iter().filter_map(|e| e.user_agent.as_deref()) {
+        *agents.entry(agent).or_insert(0) += 1;
+    }
+    let requests: usize = agents.values().sum();
+    if requests == 0 {
+        return None;
+    }
+    let pct = |n: usize| (n as f64 / requests as f64 * 10000.0).round() / 100.0;
+
+    let mut per_class: HashMap<AgentClass, usize> = HashMap::new();
+    for (agent, count) in &agents {
+        *per_class.entry(classify(agent)).or_insert(0) += count;
+    }
+    let classes = [AgentClass::Browser, AgentClass::Crawler, AgentClass::Bot, AgentClass::Other]
+        .into_iter()
+        .filter_map(|class| {
+            let count = per_class.get(&class).copied()?;
+            Some(ClassShare { class, count, percentage: pct(count) })
+        })
+        .collect();
+
+    let mut ranked: Vec<(&str, usize)> = agents.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    let top_agents = ranked
+        .into_iter()
+        .take(top_n)
+        .map(|(agent, count)| AgentItem {
+            value: agent.to_string(),
+            class: classify(agent),
+            count,
+            percentage: pct(count),
+        })
+        .collect();
+
+    Some(UserAgentReport { requests, classes, top_agents })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_line, LogFormat};
+
+    #[test]
+    fn classifies_common_agents() {
+        assert_eq!(classify("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36"), AgentClass::Browser);
+        assert_eq!(classify("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"), AgentClass::Crawler);
+        assert_eq!(classify("Googlebot/2.1 (+http://www.google.com/bot.html)"), AgentClass::Crawler);
+        assert_eq!(classify("curl/8.4.0"), AgentClass::Bot);
+        assert_eq!(classify("Mozilla/5.0 (X11; Linux x86_64) HeadlessChrome/120.0"), AgentClass::Bot);
+        assert_eq!(classify("MyApp/1.2"), AgentClass::Other);
+    }
+
+    #[test]
+    fn ranks_agents_and_summarises_classes() {
+        let line = |agent: &str| {
+            let line = format!(r#"10.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET / HTTP/1.1" 200 5 "-" "{}""#, agent);
+            parse_line(&line, &LogFormat::NginxCombined).unwrap()
+        };
+        let entries = vec![line("curl/8.4.0"), line("curl/8.4.0"), line("Mozilla/5.0"), line("-")];
+        let report = analyze(&entries, 10).unwrap();
+
+        assert_eq!(report.requests, 3);
+        assert_eq!(report.top_agents[0].value, "curl/8.4.0");
+        assert_eq!(report.top_agents[0].class, AgentClass::Bot);
+        assert_eq!(report.classes[0].class, AgentClass::Browser);
+        assert_eq!(report.classes[1].class, AgentClass::Bot);
+        assert_eq!(report.classes[1].percentage, 66.67);
+        assert!(analyze(&entries[3..], 10).is_none());
+    }
+}
@@ -1,20 +1,99 @@
+use crate::agents::{self, UserAgentReport};
+use crate::anomalies::AnomalyReport;
+use crate::apdex::ApdexReport;
+use crate::asn::{AsnReport, Network};
+use crate::attacks::AttackReport;
+use crate::bandwidth::{self, BandwidthReport};
+use crate::bots::BotReport;
+use crate::browsers::BrowserReport;
+use crate::bursts::BurstReport;
+use crate::cohorts::CohortComparison;
+use crate::cost::CostAttribution;
+use crate::detect::Detection;
+use crate::endpoint_errors::EndpointErrorReport;
+use crate::forecast::TrafficForecast;
+use crate::geo::{CountryReport, TravelReport};
+use crate::inputs::FileSummary;
 use crate::parser::{LogEntry, LogLevel};
-use serde::Serialize;
-use std::collections::HashMap;
+use crate::query::{self, QueryParamReport};
+use crate::redirects::RedirectReport;
+use crate::referrers::{self, ReferrerReport};
+use crate::response_flags::{self, ResponseFlagReport};
+use crate::robots::RobotsCompliance;
+use crate::routers::RouterReport;
+use crate::routes::{EndpointCoverage, SpecCorrelation};
+use crate::scanners::ScannerReport;
+use crate::sessions::SessionReport;
+use crate::slo::SloReport;
+use crate::timeline::{RateStats, Timeline};
+use crate::traces::TraceReport;
+use crate::upstream::{self, UpstreamReport};
+use crate::users::{self, UserReport};
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::IpAddr;
 
-/// A count + percentage pair, used for level breakdowns
+/// A count + percentage pair, used for level breakdowns.
+///
+/// `INFO`, `WARN` and `ERROR` are always present; `TRACE`, `DEBUG` and `FATAL` only
+/// when the log contains them.
 #[derive(Debug, Clone, Serialize)]
 pub struct LevelCount {
     pub count: usize,
     pub percentage: f64,
 }
 
+/// Requests per status class, for logs with a status code
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusClassRollup {
+    /// `2xx` to `5xx` always, other classes when logged; shares are of the requests
+    /// with a status code
+    pub classes: BTreeMap<String, LevelCount>,
+    /// Share of requests with a status code that got a 2xx or 3xx
+    pub success_rate: f64,
+    /// Share that got a 4xx or 5xx
+    pub error_rate: f64,
+}
+
+/// What counts as an error when flagging IPs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagBasis {
+    /// Entries logged as `ERROR` or `FATAL`
+    #[default]
+    Level,
+    /// Responses with a 5xx status, whatever the level
+    ServerErrors,
+}
+
+impl FlagBasis {
+    /// Whether this is the default basis, the log level
+    pub fn is_level(&self) -> bool {
+        *self == FlagBasis::Level
+    }
+}
+
 /// Statistics for a single ranked item (IP or endpoint)
 #[derive(Debug, Clone, Serialize)]
 pub struct RankedItem {
     pub value: String,
     pub count: usize,
     pub percentage: f64,
+    /// When the item was active, filled in by `annotate_activity`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activity: Option<Activity>,
+}
+
+/// First/last sighting and busiest minute of a ranked item
+#[derive(Debug, Clone, Serialize)]
+pub struct Activity {
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Start of the minute with the most requests (earliest on ties)
+    pub peak_minute: DateTime<Utc>,
+    pub peak_minute_requests: usize,
 }
 
 /// An IP that exceeded the error threshold
@@ -24,57 +103,364 @@ pub struct FlaggedIp {
     pub error_count: usize,
     pub total_requests: usize,
     pub error_rate: f64,
+    /// Network owner, filled in by main when `--asn` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<Network>,
+}
+
+/// How a sampled analysis (`--sample`, `--every-nth`) relates to the whole input
+#[derive(Debug, Clone, Serialize)]
+pub struct SampleSummary {
+    /// Share of lines parsed, between 0 and 1; the core counts are scaled by its inverse
+    pub rate: f64,
+    pub lines_read: usize,
+    pub lines_sampled: usize,
 }
 
 /// The complete analysis output
 #[derive(Debug, Serialize)]
 pub struct AnalysisStats {
     pub total_entries: usize,
+    /// Distinct client IPs, kept by main when `--unique` or `--approx` is given; not
+    /// scaled up for a sample
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unique_ips: Option<usize>,
+    /// Distinct endpoints, kept by main when `--unique` or `--approx` is given; not
+    /// scaled up for a sample
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unique_endpoints: Option<usize>,
+    /// Relative standard error of `unique_ips` and `unique_endpoints` when they are
+    /// HyperLogLog estimates, filled in by main when `--approx` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cardinality_error: Option<f64>,
     pub malformed_entries: usize,
+    /// Why the input was not read to the end, filled in by main when ingestion stopped early
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub incomplete: Option<String>,
+    /// Format picked by `--format auto`, filled in by main
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detected_format: Option<Detection>,
+    /// Interleaved non-access lines set aside in `--mixed` mode, filled in by main
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub non_access_lines: Option<usize>,
+    /// Continuation lines attached to entries in `--multiline` mode, filled in by main
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continuation_lines: Option<usize>,
+    /// Entries kept in `--lenient` mode despite a missing level or status, filled in by main
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial_entries: Option<usize>,
+    /// Near-miss lines rescued in `--recover` mode, filled in by main
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recovered_entries: Option<usize>,
+    /// Entries whose client was taken from `X-Forwarded-For` under `--trust-proxy`,
+    /// filled in by main
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forwarded_clients: Option<usize>,
+    /// Per-file counts when several input files were merged, filled in by main
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<FileSummary>>,
+    /// Set when only a sample of lines was analyzed, filled in by main; the core
+    /// counts are then estimates for the whole input
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample: Option<SampleSummary>,
     pub level_counts: HashMap<String, LevelCount>,
     pub top_ips: Vec<RankedItem>,
     pub top_endpoints: Vec<RankedItem>,
+    /// Query parameter names across endpoints, unless query strings were stripped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_params: Option<QueryParamReport>,
+    /// Endpoints ranked by their 4xx and 5xx responses and their own 5xx rate, filled in
+    /// by main when `--endpoint-errors` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint_errors: Option<EndpointErrorReport>,
     pub flagged_ips: Vec<FlaggedIp>,
+    /// Clients over a request rate, filled in by main when `--burst-threshold` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bursts: Option<BurstReport>,
+    /// Requests matching attack signatures and the IPs that sent them, filled in by main when `--attacks` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attacks: Option<AttackReport>,
+    /// Clients getting 404s on many distinct paths, filled in by main when `--scanner-threshold` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scanners: Option<ScannerReport>,
     pub status_code_distribution: HashMap<String, usize>,
+    /// Requests per status class with success and error rates, for logs with a status
+    /// code; kept by main when `--status-classes` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_classes: Option<StatusClassRollup>,
+    /// Requests per HTTP method, most used first; kept by main when `--methods` is given
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub method_distribution: Vec<RankedItem>,
+    /// Requests per method for each of the top endpoints, filled in by main when
+    /// `--methods` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint_methods: Option<BTreeMap<String, BTreeMap<String, usize>>>,
+    /// Requests per HTTP version (`HTTP/1.1`, `HTTP/2.0`, …), for formats that log it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol_distribution: Option<HashMap<String, usize>>,
     pub error_threshold: usize,
+    /// What the error counts of flagged IPs count; left out when it is the log level
+    #[serde(skip_serializing_if = "FlagBasis::is_level")]
+    pub flag_on: FlagBasis,
     pub top_n: usize,
+    /// Latency and backend statuses, for formats (such as ALB) that log them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream: Option<UpstreamReport>,
+    /// Apdex against a target response time, filled in by main when `--apdex-t` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apdex: Option<ApdexReport>,
+    /// Availability and error budget against an SLO, filled in by main when `--slo` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slo: Option<SloReport>,
+    /// Proxy response flags and upstream hosts, for formats (such as Envoy) that log them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_flags: Option<ResponseFlagReport>,
+    /// Bytes served per endpoint and client, for logs with a response size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bandwidth: Option<BandwidthReport>,
+    /// Top user agents and their browser/crawler/bot split, for formats that log them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agents: Option<UserAgentReport>,
+    /// Browser, browser version and operating system distributions of browser requests,
+    /// filled in by main when `--browsers` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub browsers: Option<BrowserReport>,
+    /// Requests from clients taken for bots by user agent, robots.txt requests or
+    /// cadence, filled in by main when `--bots` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bots: Option<BotReport>,
+    /// Top authenticated users and their errors, for formats that log them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub users: Option<UserReport>,
+    /// Top referrers split into internal and external, for formats that log them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub referrers: Option<ReferrerReport>,
+    /// Expected-vs-logged endpoint comparison, filled in by main when routes are supplied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint_coverage: Option<EndpointCoverage>,
+    /// Per-operation traffic for an OpenAPI spec, filled in by main when `--openapi` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spec_correlation: Option<SpecCorrelation>,
+    /// Flagged vs non-flagged IP behavior, filled in by main when `--compare-cohorts` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cohort_comparison: Option<CohortComparison>,
+    /// Estimated cost per endpoint and tenant, filled in by main when unit costs are given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_attribution: Option<CostAttribution>,
+    /// Next-day request and error forecast, filled in by main when `--forecast` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forecast: Option<TrafficForecast>,
+    /// Redirect edges, loops and long chains, filled in by main when `--redirects` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirects: Option<RedirectReport>,
+    /// Crawler requests to paths robots.txt disallows, filled in by main when `--robots` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub robots_compliance: Option<RobotsCompliance>,
+    /// Requests and errors per client country, filled in by main when `--geoip` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub countries: Option<CountryReport>,
+    /// Users seen from implausibly distant places, filled in by main when `--impossible-travel` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub travel: Option<TravelReport>,
+    /// Requests and flagged IPs per network owner, filled in by main when `--asn` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub networks: Option<AsnReport>,
+    /// Requests per proxy router, filled in by main when `--by-router` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub routers: Option<RouterReport>,
+    /// Requests per second across buckets of time, filled in by main when `--bucket` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests_per_second: Option<RateStats>,
+    /// Requests per bucket of time, filled in by main when `--bucket` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeline: Option<Timeline>,
+    /// Buckets of time that strayed from their baseline, filled in by main when `--anomalies` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anomalies: Option<AnomalyReport>,
+    /// Traces that contained errors, filled in by main when `--by-trace` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub traces: Option<TraceReport>,
+    /// Requests grouped into per-client sessions, filled in by main when `--sessions` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<SessionReport>,
 }
 
-/// Analyze a slice of log entries and return aggregated statistics.
-pub fn analyze(entries: &[LogEntry], top_n: usize, error_threshold: usize) -> AnalysisStats {
-    let total = entries.len();
+impl AnalysisStats {
+    /// The flagged IPs as addresses, for matching against entries
+    pub fn flagged_ip_set(&self) -> HashSet<IpAddr> {
+        self.flagged_ips.iter().filter_map(|f| f.ip.parse().ok()).collect()
+    }
 
-    // ── Level counts ─────────────────────────────────────────────────────────
-    let mut info_count = 0usize;
-    let mut warn_count = 0usize;
-    let mut error_count = 0usize;
+    /// Replace the core sections with ones built from `counts`, such as counts that
+    /// also cover earlier runs; the other sections are kept as they are
+    pub fn set_counts(&mut self, counts: &Counts) {
+        let mut core = analyze_counts(counts, self.top_n, self.error_threshold);
+        core.flagged_ips = flag_ips(counts, self.error_threshold, self.flag_on);
+        self.total_entries = core.total_entries;
+        self.unique_ips = core.unique_ips;
+        self.unique_endpoints = core.unique_endpoints;
+        self.cardinality_error = None;
+        self.level_counts = core.level_counts;
+        self.top_ips = core.top_ips;
+        self.top_endpoints = core.top_endpoints;
+        self.flagged_ips = core.flagged_ips;
+        self.status_code_distribution = core.status_code_distribution;
+        self.status_classes = core.status_classes;
+        self.method_distribution = core.method_distribution;
+        self.protocol_distribution = core.protocol_distribution;
+    }
+}
 
-    // ── IP tracking ──────────────────────────────────────────────────────────
-    // ip → (total_requests, error_requests)
-    let mut ip_totals: HashMap<&str, usize> = HashMap::new();
-    let mut ip_errors: HashMap<&str, usize> = HashMap::new();
+/// Requests and errors from one client IP
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IpCount {
+    pub requests: usize,
+    /// Entries logged as `ERROR` or `FATAL`
+    pub errors: usize,
+    /// Responses with a 5xx status
+    pub server_errors: usize,
+}
 
-    // ── Endpoint frequency ───────────────────────────────────────────────────
-    let mut endpoint_counts: HashMap<&str, usize> = HashMap::new();
+impl IpCount {
+    /// The errors that count under `basis`
+    pub fn errors_by(&self, basis: FlagBasis) -> usize {
+        match basis {
+            FlagBasis::Level => self.errors,
+            FlagBasis::ServerErrors => self.server_errors,
+        }
+    }
+}
 
-    // ── Status code distribution ─────────────────────────────────────────────
-    let mut status_counts: HashMap<u16, usize> = HashMap::new();
+/// The full counters behind the core sections of a report (levels, IPs, endpoints,
+/// status codes, methods, protocols), before ranking and truncation to the top N.
+///
+/// Unlike [`AnalysisStats`], counts can be added up, so a report can be built over
+/// entries read at different times (see `--state-file`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counts {
+    pub entries: usize,
+    pub levels: BTreeMap<LogLevel, usize>,
+    pub ips: HashMap<IpAddr, IpCount>,
+    pub endpoints: HashMap<String, usize>,
+    pub statuses: HashMap<u16, usize>,
+    pub methods: HashMap<String, usize>,
+    pub protocols: HashMap<String, usize>,
+}
 
-    for entry in entries {
-        match entry.level {
-            LogLevel::Info => info_count += 1,
-            LogLevel::Warn => warn_count += 1,
-            LogLevel::Error => {
-                error_count += 1;
-                *ip_errors.entry(entry.ip.as_str()).or_insert(0) += 1;
+impl Counts {
+    /// Count `entries` in
+    pub fn add(&mut self, entries: &[LogEntry]) {
+        self.entries += entries.len();
+        for entry in entries {
+            *self.levels.entry(entry.level.clone()).or_insert(0) += 1;
+            let ip = self.ips.entry(entry.ip).or_default();
+            ip.requests += 1;
+            if entry.level.is_error() {
+                ip.errors += 1;
+            }
+            if entry.status_code.is_some_and(|code| code >= 500) {
+                ip.server_errors += 1;
+            }
+            bump(&mut self.endpoints, &entry.endpoint);
+            if let Some(code) = entry.status_code {
+                *self.statuses.entry(code).or_insert(0) += 1;
+            }
+            bump(&mut self.methods, entry.method.as_str());
+            if let Some(protocol) = &entry.protocol {
+                bump(&mut self.protocols, protocol);
             }
         }
+    }
 
-        *ip_totals.entry(entry.ip.as_str()).or_insert(0) += 1;
-        *endpoint_counts.entry(entry.endpoint.as_str()).or_insert(0) += 1;
-        *status_counts.entry(entry.status_code).or_insert(0) += 1;
+    /// Every count multiplied by `factor` and rounded, e.g. to estimate the counts of
+    /// a whole input from those of a sample
+    pub fn scaled(&self, factor: f64) -> Counts {
+        let scale = |count: usize| (count as f64 * factor).round() as usize;
+        Counts {
+            entries: scale(self.entries),
+            levels: self.levels.iter().map(|(level, &n)| (level.clone(), scale(n))).collect(),
+            ips: self
+                .ips
+                .iter()
+                .map(|(&ip, count)| {
+                    let scaled = IpCount {
+                        requests: scale(count.requests),
+                        errors: scale(count.errors),
+                        server_errors: scale(count.server_errors),
+                    };
+                    (ip, scaled)
+                })
+                .collect(),
+            endpoints: self.endpoints.iter().map(|(endpoint, &n)| (endpoint.clone(), scale(n))).collect(),
+            statuses: self.statuses.iter().map(|(&code, &n)| (code, scale(n))).collect(),
+            methods: self.methods.iter().map(|(method, &n)| (method.clone(), scale(n))).collect(),
+            protocols: self.protocols.iter().map(|(protocol, &n)| (protocol.clone(), scale(n))).collect(),
+        }
     }
 
+    /// Add the counts of `other` to these
+    pub fn merge(&mut self, other: Counts) {
+        self.entries += other.entries;
+        for (level, count) in other.levels {
+            *self.levels.entry(level).or_insert(0) += count;
+        }
+        for (ip, count) in other.ips {
+            let ip = self.ips.entry(ip).or_default();
+            ip.requests += count.requests;
+            ip.errors += count.errors;
+            ip.server_errors += count.server_errors;
+        }
+        for (endpoint, count) in other.endpoints {
+            *self.endpoints.entry(endpoint).or_insert(0) += count;
+        }
+        for (code, count) in other.statuses {
+            *self.statuses.entry(code).or_insert(0) += count;
+        }
+        for (method, count) in other.methods {
+            *self.methods.entry(method).or_insert(0) += count;
+        }
+        for (protocol, count) in other.protocols {
+            *self.protocols.entry(protocol).or_insert(0) += count;
+        }
+    }
+}
+
+impl From<&[LogEntry]> for Counts {
+    fn from(entries: &[LogEntry]) -> Self {
+        let mut counts = Counts::default();
+        counts.add(entries);
+        counts
+    }
+}
+
+/// Increment a string-keyed counter without allocating for keys already present
+fn bump(counter: &mut HashMap<String, usize>, key: &str) {
+    match counter.get_mut(key) {
+        Some(count) => *count += 1,
+        None => {
+            counter.insert(key.to_string(), 1);
+        }
+    }
+}
+
+/// Analyze a slice of log entries and return aggregated statistics.
+pub fn analyze(entries: &[LogEntry], top_n: usize, error_threshold: usize) -> AnalysisStats {
+    let mut stats = analyze_counts(&Counts::from(entries), top_n, error_threshold);
+    stats.query_params = query::analyze(entries, top_n);
+    stats.upstream = upstream::analyze(entries, top_n);
+    stats.response_flags = response_flags::analyze(entries, top_n);
+    stats.bandwidth = bandwidth::analyze(entries, top_n);
+    stats.user_agents = agents::analyze(entries, top_n);
+    stats.users = users::analyze(entries, top_n);
+    stats.referrers = referrers::analyze(entries, &[], &[], top_n);
+    stats
+}
+
+/// The core sections of a report from counts alone; the sections that need the
+/// entries themselves (latency, bandwidth, user agents, …) are left empty.
+pub fn analyze_counts(counts: &Counts, top_n: usize, error_threshold: usize) -> AnalysisStats {
+    let total = counts.entries;
+
     let pct = |n: usize| -> f64 {
         if total == 0 {
             0.0
@@ -84,21 +470,16 @@ pub fn analyze(entries: &[LogEntry], top_n: usize, error_threshold: usize) -> An
     };
 
     let mut level_counts = HashMap::new();
-    level_counts.insert(
-        "INFO".to_string(),
-        LevelCount { count: info_count, percentage: pct(info_count) },
-    );
-    level_counts.insert(
-        "WARN".to_string(),
-        LevelCount { count: warn_count, percentage: pct(warn_count) },
-    );
-    level_counts.insert(
-        "ERROR".to_string(),
-        LevelCount { count: error_count, percentage: pct(error_count) },
-    );
+    for level in &LogLevel::ALL {
+        let count = counts.levels.get(level).copied().unwrap_or(0);
+        if count > 0 || matches!(level, LogLevel::Info | LogLevel::Warn | LogLevel::Error) {
+            level_counts.insert(level.to_string(), LevelCount { count, percentage: pct(count) });
+        }
+    }
 
     // ── Top N IPs ────────────────────────────────────────────────────────────
-    let mut ip_vec: Vec<(&str, usize)> = ip_totals.iter().map(|(&k, &v)| (k, v)).collect();
+    // Ties are broken by address order, so IPv4 clients sort before IPv6 ones
+    let mut ip_vec: Vec<(IpAddr, usize)> = counts.ips.iter().map(|(&k, v)| (k, v.requests)).collect();
     ip_vec.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
     let top_ips = ip_vec
         .iter()
@@ -107,13 +488,14 @@ pub fn analyze(entries: &[LogEntry], top_n: usize, error_threshold: usize) -> An
             value: ip.to_string(),
             count: *count,
             percentage: pct(*count),
+            activity: None,
         })
         .collect();
 
     // ── Top N Endpoints ───────────────────────────────────────────────────────
     let mut ep_vec: Vec<(&str, usize)> =
-        endpoint_counts.iter().map(|(&k, &v)| (k, v)).collect();
-    ep_vec.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts.endpoints.iter().map(|(k, &v)| (k.as_str(), v)).collect();
+    ep_vec.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
     let top_endpoints = ep_vec
         .iter()
         .take(top_n)
@@ -121,47 +503,207 @@ pub fn analyze(entries: &[LogEntry], top_n: usize, error_threshold: usize) -> An
             value: ep.to_string(),
             count: *count,
             percentage: pct(*count),
+            activity: None,
         })
         .collect();
 
     // ── Flagged IPs ───────────────────────────────────────────────────────────
-    let mut flagged: Vec<FlaggedIp> = ip_errors
+    let flagged = flag_ips(counts, error_threshold, FlagBasis::Level);
+
+    // ── Status code distribution ──────────────────────────────────────────────
+    let status_code_distribution: HashMap<String, usize> = counts
+        .statuses
         .iter()
-        .filter(|(_, &err)| err > error_threshold)
-        .map(|(&ip, &err)| {
-            let total_req = *ip_totals.get(ip).unwrap_or(&0);
-            let error_rate = if total_req == 0 {
-                0.0
-            } else {
-                (err as f64 / total_req as f64) * 100.0
-            };
-            FlaggedIp {
-                ip: ip.to_string(),
-                error_count: err,
-                total_requests: total_req,
-                error_rate,
-            }
-        })
+        .map(|(code, &count)| (code.to_string(), count))
         .collect();
-    flagged.sort_unstable_by(|a, b| b.error_count.cmp(&a.error_count).then(a.ip.cmp(&b.ip)));
+    let status_classes = status_classes(&counts.statuses);
 
-    // ── Status code distribution ──────────────────────────────────────────────
-    let status_code_distribution: HashMap<String, usize> = status_counts
-        .into_iter()
-        .map(|(code, count)| (code.to_string(), count))
+    // ── Method distribution ───────────────────────────────────────────────────
+    let mut method_vec: Vec<(&str, usize)> = counts.methods.iter().map(|(k, &v)| (k.as_str(), v)).collect();
+    method_vec.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let method_distribution = method_vec
+        .iter()
+        .map(|(method, count)| RankedItem {
+            value: method.to_string(),
+            count: *count,
+            percentage: pct(*count),
+            activity: None,
+        })
         .collect();
 
+    let protocol_distribution = (!counts.protocols.is_empty()).then(|| counts.protocols.clone());
+
     AnalysisStats {
         total_entries: total,
+        unique_ips: Some(counts.ips.len()),
+        unique_endpoints: Some(counts.endpoints.len()),
+        cardinality_error: None,
         malformed_entries: 0, // filled in by main after parsing
+        incomplete: None,
+        detected_format: None,
+        non_access_lines: None,
+        continuation_lines: None,
+        partial_entries: None,
+        recovered_entries: None,
+        forwarded_clients: None,
+        files: None,
+        sample: None,
         level_counts,
         top_ips,
         top_endpoints,
+        query_params: None,
+        endpoint_errors: None,
         flagged_ips: flagged,
+        bursts: None,
+        attacks: None,
+        scanners: None,
+        apdex: None,
+        slo: None,
         status_code_distribution,
+        status_classes,
+        method_distribution,
+        endpoint_methods: None,
+        protocol_distribution,
         error_threshold,
+        flag_on: FlagBasis::Level,
         top_n,
+        upstream: None,
+        response_flags: None,
+        bandwidth: None,
+        user_agents: None,
+        browsers: None,
+        bots: None,
+        users: None,
+        referrers: None,
+        endpoint_coverage: None,
+        spec_correlation: None,
+        cohort_comparison: None,
+        cost_attribution: None,
+        forecast: None,
+        redirects: None,
+        robots_compliance: None,
+        countries: None,
+        travel: None,
+        networks: None,
+        routers: None,
+        requests_per_second: None,
+        timeline: None,
+        anomalies: None,
+        traces: None,
+        sessions: None,
+    }
+}
+
+/// Fill in `activity` for each ranked item, keyed by the value `key` extracts from an entry.
+///
+/// Items that no entry maps to keep `None`.
+pub fn annotate_activity(
+    entries: &[LogEntry],
+    items: &mut [RankedItem],
+    key: impl Fn(&LogEntry) -> Cow<'_, str>,
+) {
+    let index: HashMap<&str, usize> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| (item.value.as_str(), i))
+        .collect();
+    let mut spans: Vec<Option<(DateTime<Utc>, DateTime<Utc>)>> = vec![None; items.len()];
+    let mut minutes: Vec<HashMap<DateTime<Utc>, usize>> = vec![HashMap::new(); items.len()];
+
+    for entry in entries {
+        let Some(&i) = index.get(&*key(entry)) else { continue };
+        let ts = entry.timestamp;
+        spans[i] = Some(match spans[i] {
+            Some((first, last)) => (first.min(ts), last.max(ts)),
+            None => (ts, ts),
+        });
+        let minute = ts.duration_trunc(TimeDelta::minutes(1)).unwrap_or(ts);
+        *minutes[i].entry(minute).or_insert(0) += 1;
+    }
+
+    for ((item, span), per_minute) in items.iter_mut().zip(spans).zip(minutes) {
+        let Some((first_seen, last_seen)) = span else { continue };
+        let (peak_minute, peak_minute_requests) = per_minute
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+            .unwrap_or((first_seen, 0));
+        item.activity = Some(Activity {
+            first_seen,
+            last_seen,
+            peak_minute,
+            peak_minute_requests,
+        });
+    }
+}
+
+/// IPs with more than `error_threshold` errors of the kind `basis` counts, most first
+pub fn flag_ips(counts: &Counts, error_threshold: usize, basis: FlagBasis) -> Vec<FlaggedIp> {
+    let mut flagged_vec: Vec<(IpAddr, usize)> = counts
+        .ips
+        .iter()
+        .map(|(&ip, count)| (ip, count.errors_by(basis)))
+        .filter(|&(_, errors)| errors > error_threshold)
+        .collect();
+    flagged_vec.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    flagged_vec
+        .into_iter()
+        .map(|(ip, err)| {
+            let total_req = counts.ips.get(&ip).map_or(0, |count| count.requests);
+            let error_rate = if total_req == 0 {
+                0.0
+            } else {
+                (err as f64 / total_req as f64) * 100.0
+            };
+            FlaggedIp {
+                ip: ip.to_string(),
+                error_count: err,
+                total_requests: total_req,
+                error_rate,
+                network: None,
+            }
+        })
+        .collect()
+}
+
+/// Roll status codes up into classes; `None` if there are none
+fn status_classes(statuses: &HashMap<u16, usize>) -> Option<StatusClassRollup> {
+    let total: usize = statuses.values().sum();
+    if total == 0 {
+        return None;
+    }
+    let mut per_class: BTreeMap<u16, usize> = (2..=5).map(|class| (class, 0)).collect();
+    for (&code, &count) in statuses {
+        *per_class.entry(code / 100).or_insert(0) += count;
     }
+    let pct = |n: usize| n as f64 / total as f64 * 100.0;
+    let share = |classes: &[u16]| pct(classes.iter().map(|c| per_class[c]).sum());
+    Some(StatusClassRollup {
+        success_rate: share(&[2, 3]),
+        error_rate: share(&[4, 5]),
+        classes: per_class
+            .iter()
+            .map(|(class, &count)| (format!("{}xx", class), LevelCount { count, percentage: pct(count) }))
+            .collect(),
+    })
+}
+
+/// Requests per method for each endpoint in `top_endpoints`; `None` if there are none
+pub fn endpoint_methods(entries: &[LogEntry], top_endpoints: &[RankedItem]) -> Option<BTreeMap<String, BTreeMap<String, usize>>> {
+    if top_endpoints.is_empty() {
+        return None;
+    }
+    let mut matrix: BTreeMap<String, BTreeMap<String, usize>> =
+        top_endpoints.iter().map(|item| (item.value.clone(), BTreeMap::new())).collect();
+    for entry in entries {
+        let Some(methods) = matrix.get_mut(&entry.endpoint) else { continue };
+        match methods.get_mut(entry.method.as_str()) {
+            Some(count) => *count += 1,
+            None => {
+                methods.insert(entry.method.to_string(), 1);
+            }
+        }
+    }
+    Some(matrix)
 }
 
 #[cfg(test)]
@@ -171,12 +713,26 @@ mod tests {
 
     fn make_entry(ip: &str, level: LogLevel, endpoint: &str, status: u16) -> LogEntry {
         LogEntry {
-            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            timestamp: "2024-01-01T00:00:00Z".parse().unwrap(),
             level,
-            ip: ip.to_string(),
+            ip: ip.parse().unwrap(),
+            user: None,
+            forwarded_for: None,
             method: HttpMethod::Get,
             endpoint: endpoint.to_string(),
-            status_code: status,
+            status_code: Some(status),
+            protocol: None,
+            bytes: None,
+            referrer: None,
+            user_agent: None,
+            duration: None,
+            backend_status: None,
+            response_flags: None,
+            upstream_host: None,
+            router: None,
+            service: None,
+            trace_id: None,
+            extra: None,
         }
     }
 
@@ -193,6 +749,20 @@ mod tests {
         assert_eq!(stats.level_counts["INFO"].count, 2);
         assert_eq!(stats.level_counts["WARN"].count, 1);
         assert_eq!(stats.level_counts["ERROR"].count, 1);
+        assert!(!stats.level_counts.contains_key("DEBUG"));
+    }
+
+    #[test]
+    fn counts_extra_levels_and_fatal_as_error() {
+        let mut entries: Vec<LogEntry> = (0..2).map(|_| make_entry("9.9.9.9", LogLevel::Fatal, "/", 500)).collect();
+        entries.push(make_entry("9.9.9.9", LogLevel::Error, "/", 500));
+        entries.push(make_entry("1.1.1.1", LogLevel::Debug, "/", 200));
+        let stats = analyze(&entries, 5, 2);
+        assert_eq!(stats.level_counts["FATAL"].count, 2);
+        assert_eq!(stats.level_counts["DEBUG"].count, 1);
+        assert_eq!(stats.level_counts["WARN"].count, 0);
+        assert!(!stats.level_counts.contains_key("TRACE"));
+        assert_eq!(stats.flagged_ips[0].error_count, 3);
     }
 
     #[test]
@@ -207,6 +777,7 @@ mod tests {
         assert_eq!(stats.top_ips[0].value, "1.1.1.1");
         assert_eq!(stats.top_ips[0].count, 3);
         assert_eq!(stats.top_ips[1].value, "1.1.1.2");
+        assert_eq!(stats.unique_ips, Some(2));
     }
 
     #[test]
@@ -223,11 +794,105 @@ mod tests {
         assert_eq!(stats.flagged_ips[0].error_count, 6);
     }
 
+    #[test]
+    fn counts_methods_overall_and_per_top_endpoint() {
+        let mut entries = vec![
+            make_entry("1.1.1.1", LogLevel::Info, "/api", 200),
+            make_entry("1.1.1.1", LogLevel::Info, "/api", 201),
+            make_entry("1.1.1.1", LogLevel::Info, "/api", 200),
+            make_entry("1.1.1.1", LogLevel::Info, "/", 200),
+        ];
+        entries[1].method = HttpMethod::Post;
+        entries[2].method = HttpMethod::Other("PROPFIND".to_string());
+
+        let stats = analyze(&entries, 1, 3);
+        let methods: Vec<_> = stats.method_distribution.iter().map(|m| (m.value.as_str(), m.count, m.percentage)).collect();
+        assert_eq!(methods, [("GET", 2, 50.0), ("POST", 1, 25.0), ("PROPFIND", 1, 25.0)]);
+
+        // Only the top endpoint gets a row
+        let matrix = endpoint_methods(&entries, &stats.top_endpoints).unwrap();
+        assert_eq!(matrix.keys().collect::<Vec<_>>(), ["/api"]);
+        assert_eq!(matrix["/api"].iter().map(|(m, &n)| (m.as_str(), n)).collect::<Vec<_>>(), [("GET", 1), ("POST", 1), ("PROPFIND", 1)]);
+    }
+
+    #[test]
+    fn rolls_statuses_up_and_flags_on_5xx() {
+        let entries = vec![
+            make_entry("1.1.1.1", LogLevel::Info, "/", 200),
+            make_entry("1.1.1.1", LogLevel::Info, "/", 301),
+            make_entry("1.1.1.1", LogLevel::Info, "/", 503),
+            make_entry("1.1.1.1", LogLevel::Info, "/", 502),
+            make_entry("2.2.2.2", LogLevel::Error, "/", 404),
+            make_entry("2.2.2.2", LogLevel::Error, "/", 404),
+            make_entry("2.2.2.2", LogLevel::Error, "/", 101),
+        ];
+        let stats = analyze(&entries, 5, 1);
+        let rollup = stats.status_classes.unwrap();
+        let classes: Vec<_> = rollup.classes.iter().map(|(class, lc)| (class.as_str(), lc.count)).collect();
+        assert_eq!(classes, [("1xx", 1), ("2xx", 1), ("3xx", 1), ("4xx", 2), ("5xx", 2)]);
+        assert!((rollup.success_rate - 200.0 / 7.0).abs() < 1e-9);
+        assert!((rollup.error_rate - 400.0 / 7.0).abs() < 1e-9);
+
+        // By level only 2.2.2.2 errs; by status only 1.1.1.1 does
+        assert_eq!(stats.flagged_ips.iter().map(|f| f.ip.as_str()).collect::<Vec<_>>(), ["2.2.2.2"]);
+        let flagged = flag_ips(&Counts::from(&entries[..]), 1, FlagBasis::ServerErrors);
+        assert_eq!((flagged[0].ip.as_str(), flagged[0].error_count, flagged[0].error_rate), ("1.1.1.1", 2, 50.0));
+        assert_eq!(flagged.len(), 1);
+    }
+
+    #[test]
+    fn annotates_first_last_and_peak_minute() {
+        let mut entries = vec![
+            make_entry("1.1.1.1", LogLevel::Info, "/a", 200),
+            make_entry("1.1.1.1", LogLevel::Info, "/a", 200),
+            make_entry("1.1.1.1", LogLevel::Info, "/a", 200),
+        ];
+        entries[0].timestamp = "2024-01-01T10:00:05Z".parse().unwrap();
+        entries[1].timestamp = "2024-01-01T10:05:10Z".parse().unwrap();
+        entries[2].timestamp = "2024-01-01T10:05:50Z".parse().unwrap();
+
+        let mut stats = analyze(&entries, 5, 3);
+        annotate_activity(&entries, &mut stats.top_endpoints, |e| Cow::from(e.endpoint.as_str()));
+        let activity = stats.top_endpoints[0].activity.as_ref().unwrap();
+        assert_eq!(activity.first_seen.to_rfc3339(), "2024-01-01T10:00:05+00:00");
+        assert_eq!(activity.last_seen.to_rfc3339(), "2024-01-01T10:05:50+00:00");
+        assert_eq!(activity.peak_minute.to_rfc3339(), "2024-01-01T10:05:00+00:00");
+        assert_eq!(activity.peak_minute_requests, 2);
+    }
+
+    #[test]
+    fn latency_overall_and_per_endpoint_from_trailing_response_times() {
+        let mut lines: Vec<String> = (1..=100)
+            .map(|ms| format!("2024-01-15T10:00:00Z [INFO] 1.1.1.1 GET /api 200 {}ms", ms))
+            .collect();
+        lines.push("2024-01-15T10:00:01Z [INFO] 1.1.1.2 GET /report 200 1.5s".to_string());
+        lines.push("2024-01-15T10:00:02Z [INFO] 1.1.1.2 GET /report 200 500ms".to_string());
+        lines.push("2024-01-15T10:00:03Z [INFO] 1.1.1.2 GET /health 200".to_string());
+        let entries: Vec<LogEntry> = lines.iter().map(|l| crate::parser::parse_log_line(l).unwrap()).collect();
+
+        let latency = analyze(&entries, 5, 3).upstream.unwrap();
+        let overall = latency.latency.unwrap();
+        assert_eq!(overall.requests, 102);
+        assert_eq!((overall.min_ms, overall.max_ms), (1.0, 1500.0));
+        assert_eq!(overall.mean_ms, 69.118);
+        assert_eq!((overall.p50_ms, overall.p95_ms, overall.p99_ms), (51.0, 97.0, 500.0));
+
+        // Highest p95 first; entries without a response time are left out
+        let endpoints: Vec<_> = latency.slowest_endpoints.iter().map(|e| e.endpoint.as_str()).collect();
+        assert_eq!(endpoints, ["/report", "/api"]);
+        let api = &latency.slowest_endpoints[1].latency;
+        assert_eq!((api.min_ms, api.mean_ms, api.max_ms), (1.0, 50.5, 100.0));
+        assert_eq!((api.p50_ms, api.p95_ms, api.p99_ms), (50.0, 95.0, 99.0));
+    }
+
     #[test]
     fn empty_entries_returns_zero_stats() {
         let stats = analyze(&[], 5, 3);
         assert_eq!(stats.total_entries, 0);
         assert!(stats.top_ips.is_empty());
         assert!(stats.flagged_ips.is_empty());
+        assert!(stats.method_distribution.is_empty());
+        assert!(stats.status_classes.is_none());
+        assert!(endpoint_methods(&[], &stats.top_endpoints).is_none());
     }
 }
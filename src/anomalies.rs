@@ -0,0 +1,394 @@
+use crate::timeline::{TimeBucket, Timeline};
+use crate::units;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Standard deviations from the baseline that make a bucket anomalous, unless
+/// `--anomaly-threshold` says otherwise
+pub const DEFAULT_THRESHOLD: f64 = 3.0;
+
+/// Earlier buckets the rolling baseline is taken over, unless `--baseline-window` says otherwise
+pub const DEFAULT_WINDOW: usize = 12;
+
+/// Layout version of baseline files; a file from another version is refused
+pub const BASELINE_VERSION: u32 = 1;
+
+/// Smallest spread a deviation is measured in: one request, or one percentage point
+/// of error rate. A perfectly flat baseline would otherwise flag any change at all.
+const MIN_STD_DEV: f64 = 1.0;
+
+/// Mean and population standard deviation of one measure across buckets
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Spread {
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+/// What a normal bucket looked like in an earlier run (`--save-baseline`), for later
+/// runs to compare against with `--baseline`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub version: u32,
+    /// Width of the buckets it was recorded from
+    pub bucket_seconds: u64,
+    pub buckets: usize,
+    pub first_bucket: DateTime<Utc>,
+    pub last_bucket: DateTime<Utc>,
+    /// Requests per bucket, empty buckets included
+    pub requests: Spread,
+    /// Errors as a percentage of requests, over the buckets with requests; `None` if
+    /// none had any
+    pub error_rate: Option<Spread>,
+}
+
+/// Why a baseline file could not be used
+#[derive(Debug)]
+pub enum BaselineError {
+    Io { path: PathBuf, error: io::Error },
+    Invalid { path: PathBuf, error: serde_json::Error },
+    /// Written by a version of the tool with a different layout
+    Version { path: PathBuf, found: u32 },
+    /// Recorded with buckets of another width than this run's
+    BucketWidth { path: PathBuf, found: String },
+    /// The run it would be recorded from had no buckets
+    Empty { path: PathBuf },
+}
+
+impl fmt::Display for BaselineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BaselineError::Io { path, error } => write!(f, "baseline file '{}': {}", path.display(), error),
+            BaselineError::Invalid { path, error } => {
+                write!(f, "baseline file '{}' is not valid: {}", path.display(), error)
+            }
+            BaselineError::Version { path, found } => write!(
+                f,
+                "baseline file '{}' has version {}, but this version of log_analyzer reads version {}; save it again",
+                path.display(),
+                found,
+                BASELINE_VERSION
+            ),
+            BaselineError::BucketWidth { path, found } => write!(
+                f,
+                "baseline file '{}' was recorded with {} buckets; analyze with --bucket {} to compare against it",
+                path.display(),
+                found,
+                found
+            ),
+            BaselineError::Empty { path } => {
+                write!(f, "no baseline saved to '{}': there were no entries to record", path.display())
+            }
+        }
+    }
+}
+
+impl Baseline {
+    /// Record the spread of requests and error rates across a timeline's buckets;
+    /// `None` without buckets
+    pub fn from_timeline(timeline: &Timeline) -> Option<Baseline> {
+        let mut requests = Moments::default();
+        let mut error_rate = Moments::default();
+        for bucket in &timeline.buckets {
+            requests.add(bucket.requests as f64);
+            if let Some(rate) = error_rate_of(bucket) {
+                error_rate.add(rate);
+            }
+        }
+        Some(Baseline {
+            version: BASELINE_VERSION,
+            bucket_seconds: timeline.bucket_seconds,
+            buckets: timeline.buckets.len(),
+            first_bucket: timeline.buckets.first()?.start,
+            last_bucket: timeline.buckets.last()?.start,
+            requests: requests.spread()?,
+            error_rate: error_rate.spread(),
+        })
+    }
+
+    /// Read the baseline saved at `path`, refusing one recorded with buckets of
+    /// another width than `bucket_seconds`
+    pub fn load(path: &Path, bucket_seconds: u64) -> Result<Baseline, BaselineError> {
+        let text = fs::read_to_string(path).map_err(|error| BaselineError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let invalid = |error| BaselineError::Invalid {
+            path: path.to_path_buf(),
+            error,
+        };
+        let value: serde_json::Value = serde_json::from_str(&text).map_err(invalid)?;
+        let found = value.get("version").and_then(serde_json::Value::as_u64).unwrap_or(0) as u32;
+        if found != BASELINE_VERSION {
+            return Err(BaselineError::Version {
+                path: path.to_path_buf(),
+                found,
+            });
+        }
+        let baseline: Baseline = serde_json::from_value(value).map_err(invalid)?;
+        if baseline.bucket_seconds != bucket_seconds {
+            return Err(BaselineError::BucketWidth {
+                path: path.to_path_buf(),
+                found: units::format_duration(Duration::from_secs(baseline.bucket_seconds)),
+            });
+        }
+        Ok(baseline)
+    }
+
+    /// Write the baseline to `path` as pretty-printed JSON
+    pub fn save(&self, path: &Path) -> Result<(), BaselineError> {
+        let io_error = |error| BaselineError::Io {
+            path: path.to_path_buf(),
+            error,
+        };
+        let json = serde_json::to_string_pretty(self).map_err(|e| io_error(io::Error::other(e)))?;
+        fs::write(path, json).map_err(io_error)
+    }
+}
+
+/// What the buckets were compared against
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum BaselineSource {
+    /// The buckets just before each one
+    Rolling { buckets: usize },
+    /// A baseline file from an earlier run, recorded from this many buckets
+    Stored { buckets: usize },
+}
+
+/// The measure of a bucket that strayed from the baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Metric {
+    Requests,
+    /// Errors as a percentage of the bucket's requests
+    ErrorRate,
+}
+
+impl Metric {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Metric::Requests => "requests",
+            Metric::ErrorRate => "error rate",
+        }
+    }
+}
+
+/// A bucket whose requests or error rate lay further from the baseline than the threshold
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Anomaly {
+    pub start: DateTime<Utc>,
+    pub metric: Metric,
+    pub value: f64,
+    /// Baseline mean
+    pub expected: f64,
+    /// Baseline standard deviation, at least one request or percentage point
+    pub std_dev: f64,
+    /// Standard deviations above (positive) or below (negative) the mean
+    pub deviation: f64,
+}
+
+/// Buckets of the timeline that deviated from their baseline (`--anomalies`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnomalyReport {
+    pub baseline: BaselineSource,
+    /// Standard deviations a bucket had to be away from the mean
+    pub threshold: f64,
+    /// Buckets compared; under a rolling baseline, the first ones have too little before them
+    pub checked: usize,
+    /// Anomalies found, of which the largest are listed
+    pub total: usize,
+    /// The largest deviations, in time order
+    pub anomalies: Vec<Anomaly>,
+}
+
+/// Compare each bucket with the `window` buckets before it, so the baseline follows
+/// slow changes in traffic. Buckets with fewer than `window` before them are not
+/// checked; a window is at least one bucket.
+pub fn rolling(timeline: &Timeline, window: usize, threshold: f64, top_n: usize) -> AnomalyReport {
+    let window = window.max(1);
+    let buckets = &timeline.buckets;
+    let mut requests = Moments::default();
+    let mut error_rate = Moments::default();
+    let mut found = Vec::new();
+    for (i, bucket) in buckets.iter().enumerate() {
+        if i >= window {
+            if let Some(spread) = requests.spread() {
+                check(bucket, &spread, error_rate.spread().as_ref(), threshold, &mut found);
+            }
+            let leaving = &buckets[i - window];
+            requests.remove(leaving.requests as f64);
+            if let Some(rate) = error_rate_of(leaving) {
+                error_rate.remove(rate);
+            }
+        }
+        requests.add(bucket.requests as f64);
+        if let Some(rate) = error_rate_of(bucket) {
+            error_rate.add(rate);
+        }
+    }
+    report(BaselineSource::Rolling { buckets: window }, threshold, buckets.len().saturating_sub(window), found, top_n)
+}
+
+/// Compare every bucket with a baseline recorded by an earlier run
+pub fn against(timeline: &Timeline, baseline: &Baseline, threshold: f64, top_n: usize) -> AnomalyReport {
+    let mut found = Vec::new();
+    for bucket in &timeline.buckets {
+        check(bucket, &baseline.requests, baseline.error_rate.as_ref(), threshold, &mut found);
+    }
+    report(BaselineSource::Stored { buckets: baseline.buckets }, threshold, timeline.buckets.len(), found, top_n)
+}
+
+fn report(baseline: BaselineSource, threshold: f64, checked: usize, mut found: Vec<Anomaly>, top_n: usize) -> AnomalyReport {
+    let total = found.len();
+    found.sort_by(|a, b| b.deviation.abs().total_cmp(&a.deviation.abs()).then(a.start.cmp(&b.start)));
+    found.truncate(top_n);
+    found.sort_by_key(|a| (a.start, a.metric == Metric::ErrorRate));
+    AnomalyReport {
+        baseline,
+        threshold,
+        checked,
+        total,
+        anomalies: found,
+    }
+}
+
+/// Flag the bucket's requests, and its error rate if it had requests, where they lie
+/// more than `threshold` standard deviations from the baseline, in either direction
+fn check(bucket: &TimeBucket, requests: &Spread, error_rate: Option<&Spread>, threshold: f64, found: &mut Vec<Anomaly>) {
+    let mut measure = |metric: Metric, value: f64, spread: &Spread| {
+        let std_dev = spread.std_dev.max(MIN_STD_DEV);
+        let deviation = (value - spread.mean) / std_dev;
+        if deviation.abs() > threshold {
+            found.push(Anomaly {
+                start: bucket.start,
+                metric,
+                value,
+                expected: spread.mean,
+                std_dev,
+                deviation,
+            });
+        }
+    };
+    measure(Metric::Requests, bucket.requests as f64, requests);
+    if let (Some(rate), Some(spread)) = (error_rate_of(bucket), error_rate) {
+        measure(Metric::ErrorRate, rate, spread);
+    }
+}
+
+/// Errors as a percentage of the bucket's requests; `None` for an empty bucket
+fn error_rate_of(bucket: &TimeBucket) -> Option<f64> {
+    (bucket.requests > 0).then(|| bucket.errors as f64 / bucket.requests as f64 * 100.0)
+}
+
+/// Running count, sum and sum of squares, values can be taken out again
+#[derive(Default)]
+struct Moments {
+    count: usize,
+    sum: f64,
+    squares: f64,
+}
+
+impl Moments {
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.squares += value * value;
+    }
+
+    fn remove(&mut self, value: f64) {
+        self.count -= 1;
+        self.sum -= value;
+        self.squares -= value * value;
+    }
+
+    fn spread(&self) -> Option<Spread> {
+        if self.count == 0 {
+            return None;
+        }
+        let n = self.count as f64;
+        let mean = self.sum / n;
+        // Rounding can leave a hair below zero where every value was the same
+        let variance = (self.squares / n - mean * mean).max(0.0);
+        Some(Spread {
+            mean,
+            std_dev: variance.sqrt(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{test_entry, LogEntry, LogLevel};
+    use crate::timeline::{self, ClientCounting};
+
+    /// A minute-by-minute timeline with the given requests and errors per minute
+    fn timeline(minutes: &[(usize, usize)]) -> Timeline {
+        let mut entries: Vec<LogEntry> = Vec::new();
+        for (minute, &(requests, errors)) in minutes.iter().enumerate() {
+            for i in 0..requests {
+                let level = if i < errors { LogLevel::Error } else { LogLevel::Info };
+                let time = format!("2024-01-15T10:{:02}:{:02}Z", minute, i % 60);
+                entries.push(LogEntry { level, ..test_entry(&time, "1.2.3.4", "/", 200) });
+            }
+        }
+        timeline::bucket(&entries, Duration::from_secs(60), ClientCounting::Off).unwrap()
+    }
+
+    #[test]
+    fn flags_buckets_that_stray_from_the_rolling_baseline() {
+        let minutes = [(10, 0), (12, 0), (8, 0), (10, 0), (11, 0), (9, 0), (40, 0), (10, 9), (10, 0), (10, 1)];
+        let report = rolling(&timeline(&minutes), 4, 3.0, 10);
+        assert_eq!(report.baseline, BaselineSource::Rolling { buckets: 4 });
+        assert_eq!((report.checked, report.total), (6, 2));
+        let found: Vec<_> = report.anomalies.iter().map(|a| (a.start.format("%M").to_string(), a.metric)).collect();
+        assert_eq!(found, [("06".to_string(), Metric::Requests), ("07".to_string(), Metric::ErrorRate)]);
+        // Minutes 2 to 5 average 9.5 requests
+        assert_eq!(report.anomalies[0].expected, 9.5);
+        assert!(report.anomalies[0].deviation > 3.0);
+        // Minute 9's one error in ten does not stand out from a window holding minute 7
+        assert!(!report.anomalies.iter().any(|a| a.start.format("%M").to_string() == "09"));
+
+        // Only the largest deviation is listed, but both are counted
+        let top = rolling(&timeline(&minutes), 4, 3.0, 1);
+        assert_eq!((top.total, top.anomalies.len()), (2, 1));
+        assert_eq!(rolling(&timeline(&minutes), 20, 3.0, 10).checked, 0);
+    }
+
+    #[test]
+    fn compares_with_a_saved_baseline() {
+        let earlier = timeline(&[(10, 1), (10, 0), (10, 1), (10, 0)]);
+        let baseline = Baseline::from_timeline(&earlier).unwrap();
+        assert_eq!(baseline.requests, Spread { mean: 10.0, std_dev: 0.0 });
+        assert_eq!(baseline.error_rate.unwrap().mean, 5.0);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("baseline.json");
+        baseline.save(&path).unwrap();
+        let loaded = Baseline::load(&path, 60).unwrap();
+        assert_eq!(loaded, baseline);
+        assert!(matches!(Baseline::load(&path, 300), Err(BaselineError::BucketWidth { found, .. }) if found == "1m"));
+
+        // A flat baseline still allows a request's worth of noise per standard deviation
+        let report = against(&timeline(&[(12, 0), (9, 0), (20, 0)]), &loaded, 3.0, 10);
+        assert_eq!(report.baseline, BaselineSource::Stored { buckets: 4 });
+        let found: Vec<_> = report.anomalies.iter().map(|a| (a.value, a.deviation)).collect();
+        assert_eq!(found, [(20.0, 10.0)]);
+    }
+
+    #[test]
+    fn refuses_baselines_of_another_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("baseline.json");
+        fs::write(&path, r#"{"version": 99}"#).unwrap();
+        assert!(matches!(Baseline::load(&path, 60), Err(BaselineError::Version { found: 99, .. })));
+        fs::write(&path, "not json").unwrap();
+        assert!(matches!(Baseline::load(&path, 60), Err(BaselineError::Invalid { .. })));
+        assert!(Baseline::from_timeline(&timeline(&[])).is_none());
+    }
+}
@@ -0,0 +1,148 @@
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Apdex counts and score over a set of timed requests
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ApdexScore {
+    /// Requests with a response time
+    pub requests: usize,
+    /// Served within the target time T
+    pub satisfied: usize,
+    /// Served within 4T
+    pub tolerating: usize,
+    /// Slower than 4T, or failed with a 5xx
+    pub frustrated: usize,
+    /// `(satisfied + tolerating / 2) / requests`, from 0 to 1, to two decimals
+    pub score: f64,
+}
+
+impl ApdexScore {
+    fn record(&mut self, duration: Duration, status: Option<u16>, target: Duration) {
+        self.requests += 1;
+        if status.is_some_and(|code| code >= 500) || duration > target * 4 {
+            self.frustrated += 1;
+        } else if duration > target {
+            self.tolerating += 1;
+        } else {
+            self.satisfied += 1;
+        }
+    }
+
+    fn finish(mut self) -> Self {
+        let points = self.satisfied as f64 + self.tolerating as f64 / 2.0;
+        self.score = (points / self.requests as f64 * 100.0).round() / 100.0;
+        self
+    }
+
+    /// The standard rating band of the score
+    pub fn rating(&self) -> &'static str {
+        match self.score {
+            s if s >= 0.94 => "excellent",
+            s if s >= 0.85 => "good",
+            s if s >= 0.70 => "fair",
+            s if s >= 0.50 => "poor",
+            _ => "unacceptable",
+        }
+    }
+}
+
+/// Apdex of a single endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointApdex {
+    pub endpoint: String,
+    #[serde(flatten)]
+    pub apdex: ApdexScore,
+}
+
+/// User satisfaction with response times against a target (`--apdex-t`)
+#[derive(Debug, Clone, Serialize)]
+pub struct ApdexReport {
+    /// Target response time T, in milliseconds
+    pub target_ms: f64,
+    pub overall: ApdexScore,
+    /// Per-endpoint scores, lowest first
+    pub endpoints: Vec<EndpointApdex>,
+}
+
+/// Score the timed requests against the target response time `target`: satisfied
+/// within T, tolerating within 4T, frustrated beyond that. Server errors count as
+/// frustrated however fast they were.
+///
+/// Returns `None` when no entry logs a response time.
+pub fn compute(entries: &[LogEntry], target: Duration, top_n: usize) -> Option<ApdexReport> {
+    let mut overall = ApdexScore::default();
+    let mut by_endpoint: HashMap<&str, ApdexScore> = HashMap::new();
+    for entry in entries {
+        let Some(duration) = entry.duration else { continue };
+        overall.record(duration, entry.status_code, target);
+        by_endpoint
+            .entry(entry.endpoint.as_str())
+            .or_default()
+            .record(duration, entry.status_code, target);
+    }
+    if overall.requests == 0 {
+        return None;
+    }
+
+    let mut endpoints: Vec<EndpointApdex> = by_endpoint
+        .into_iter()
+        .map(|(endpoint, apdex)| EndpointApdex { endpoint: endpoint.to_string(), apdex: apdex.finish() })
+        .collect();
+    endpoints.sort_by(|a, b| {
+        a.apdex
+            .score
+            .total_cmp(&b.apdex.score)
+            .then(b.apdex.requests.cmp(&a.apdex.requests))
+            .then_with(|| a.endpoint.cmp(&b.endpoint))
+    });
+    endpoints.truncate(top_n);
+
+    Some(ApdexReport {
+        target_ms: target.as_secs_f64() * 1000.0,
+        overall: overall.finish(),
+        endpoints,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_entry;
+
+    fn entry(endpoint: &str, status: u16, latency_ms: Option<u64>) -> LogEntry {
+        LogEntry {
+            duration: latency_ms.map(Duration::from_millis),
+            ..test_entry("2024-01-15T10:00:00Z", "1.1.1.1", endpoint, status)
+        }
+    }
+
+    #[test]
+    fn scores_overall_and_per_endpoint() {
+        let entries = vec![
+            entry("/fast", 200, Some(100)),
+            entry("/fast", 200, Some(500)),
+            entry("/fast", 503, Some(20)),
+            entry("/slow", 200, Some(900)),
+            entry("/slow", 200, Some(2000)),
+            entry("/slow", 200, Some(2001)),
+            entry("/untimed", 200, None),
+        ];
+        let report = compute(&entries, Duration::from_millis(500), 10).unwrap();
+
+        assert_eq!(report.target_ms, 500.0);
+        let overall = &report.overall;
+        assert_eq!((overall.requests, overall.satisfied, overall.tolerating, overall.frustrated), (6, 2, 2, 2));
+        assert_eq!(overall.score, 0.5);
+        assert_eq!(overall.rating(), "poor");
+
+        let scores: Vec<_> = report.endpoints.iter().map(|e| (e.endpoint.as_str(), e.apdex.score)).collect();
+        assert_eq!(scores, [("/slow", 0.33), ("/fast", 0.67)]);
+    }
+
+    #[test]
+    fn needs_response_times() {
+        assert!(compute(&[entry("/", 200, None)], Duration::from_millis(500), 10).is_none());
+    }
+}
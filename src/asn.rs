@@ -0,0 +1,170 @@
+use crate::analyzer::FlaggedIp;
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+/// The autonomous system an IP is announced from, and who runs it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Network {
+    pub asn: u32,
+    /// Network owner, e.g. a hosting provider or ISP
+    pub organization: String,
+}
+
+impl Network {
+    /// `AS64500 Example Hosting`, as the report shows it
+    pub fn label(&self) -> String {
+        format!("AS{} {}", self.asn, self.organization)
+    }
+}
+
+/// Resolves client IPs to the network they belong to
+pub trait NetworkLocator {
+    fn network(&self, ip: IpAddr) -> Option<Network>;
+}
+
+/// A fixed IP → network table, mainly for tests and small hand-maintained lists
+impl NetworkLocator for HashMap<IpAddr, Network> {
+    fn network(&self, ip: IpAddr) -> Option<Network> {
+        self.get(&ip).cloned()
+    }
+}
+
+/// Traffic from one autonomous system
+#[derive(Debug, Clone, Serialize)]
+pub struct AsnCount {
+    pub asn: u32,
+    pub organization: String,
+    pub requests: usize,
+    /// Share of resolved requests
+    pub percentage: f64,
+    /// Requests logged as ERROR or FATAL
+    pub errors: usize,
+    pub error_rate: f64,
+    /// Distinct client IPs
+    pub clients: usize,
+    /// Of those, the ones flagged for errors
+    pub flagged_clients: usize,
+}
+
+/// Requests broken down by the network owner of their client (`--asn`)
+#[derive(Debug, Clone, Serialize)]
+pub struct AsnReport {
+    /// Requests whose client's network is known
+    pub resolved: usize,
+    /// Requests from clients the database does not cover, such as private addresses
+    pub unresolved: usize,
+    /// Networks with the most requests
+    pub top_networks: Vec<AsnCount>,
+}
+
+/// Resolve each client IP once and count requests, errors, clients and flagged
+/// clients per autonomous system, busiest first.
+pub fn breakdown(
+    entries: &[LogEntry],
+    locator: &dyn NetworkLocator,
+    flagged: &HashSet<IpAddr>,
+    top_n: usize,
+) -> AsnReport {
+    #[derive(Default)]
+    struct Tally {
+        organization: String,
+        requests: usize,
+        errors: usize,
+        clients: usize,
+        flagged_clients: usize,
+    }
+
+    let mut networks: HashMap<IpAddr, Option<Network>> = HashMap::new();
+    let mut tallies: HashMap<u32, Tally> = HashMap::new();
+    let mut resolved = 0;
+    for entry in entries {
+        let first_seen = !networks.contains_key(&entry.ip);
+        let Some(network) = networks.entry(entry.ip).or_insert_with(|| locator.network(entry.ip)) else { continue };
+        resolved += 1;
+        let tally = tallies.entry(network.asn).or_default();
+        if first_seen {
+            tally.clients += 1;
+            tally.flagged_clients += usize::from(flagged.contains(&entry.ip));
+            tally.organization.clone_from(&network.organization);
+        }
+        tally.requests += 1;
+        if entry.level.is_error() {
+            tally.errors += 1;
+        }
+    }
+    let pct = |n: usize, of: usize| (n as f64 / of as f64 * 10000.0).round() / 100.0;
+
+    let mut ranked: Vec<AsnCount> = tallies
+        .into_iter()
+        .map(|(asn, tally)| AsnCount {
+            asn,
+            organization: tally.organization,
+            requests: tally.requests,
+            percentage: pct(tally.requests, resolved),
+            errors: tally.errors,
+            error_rate: pct(tally.errors, tally.requests),
+            clients: tally.clients,
+            flagged_clients: tally.flagged_clients,
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.requests.cmp(&a.requests).then(a.asn.cmp(&b.asn)));
+    ranked.truncate(top_n);
+
+    AsnReport {
+        resolved,
+        unresolved: entries.len() - resolved,
+        top_networks: ranked,
+    }
+}
+
+/// Fill in the network owner of each flagged IP the locator knows
+pub fn annotate_flagged(flagged: &mut [FlaggedIp], locator: &dyn NetworkLocator) {
+    for item in flagged {
+        item.network = item.ip.parse().ok().and_then(|ip| locator.network(ip));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::{self, Counts, FlagBasis};
+    use crate::parser::parse_log_line;
+
+    fn network(asn: u32, organization: &str) -> Network {
+        Network { asn, organization: organization.to_string() }
+    }
+
+    #[test]
+    fn ranks_networks_and_annotates_flagged_ips() {
+        let networks = HashMap::from([
+            ("1.1.1.1".parse().unwrap(), network(64500, "Example Hosting")),
+            ("1.1.1.2".parse().unwrap(), network(64500, "Example Hosting")),
+            ("2.2.2.2".parse().unwrap(), network(64501, "Example ISP")),
+        ]);
+        let lines = [
+            "2024-01-15T10:00:00Z [ERROR] 1.1.1.1 GET /login 401",
+            "2024-01-15T10:00:01Z [ERROR] 1.1.1.1 GET /login 401",
+            "2024-01-15T10:00:02Z [INFO] 1.1.1.2 GET / 200",
+            "2024-01-15T10:00:03Z [INFO] 2.2.2.2 GET / 200",
+            "2024-01-15T10:00:04Z [INFO] 10.0.0.1 GET / 200",
+        ];
+        let entries: Vec<LogEntry> = lines.iter().map(|l| parse_log_line(l).unwrap()).collect();
+        let mut flagged_ips = analyzer::flag_ips(&Counts::from(&entries[..]), 1, FlagBasis::Level);
+        let flagged: HashSet<IpAddr> = flagged_ips.iter().map(|f| f.ip.parse().unwrap()).collect();
+
+        let report = breakdown(&entries, &networks, &flagged, 10);
+        assert_eq!((report.resolved, report.unresolved), (4, 1));
+        let top: Vec<_> = report
+            .top_networks
+            .iter()
+            .map(|n| (n.asn, n.requests, n.errors, n.clients, n.flagged_clients))
+            .collect();
+        assert_eq!(top, [(64500, 3, 2, 2, 1), (64501, 1, 0, 1, 0)]);
+        assert_eq!(report.top_networks[0].percentage, 75.0);
+
+        annotate_flagged(&mut flagged_ips, &networks);
+        assert_eq!(flagged_ips[0].network.as_ref().map(Network::label).as_deref(), Some("AS64500 Example Hosting"));
+    }
+}
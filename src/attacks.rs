@@ -0,0 +1,274 @@
+use crate::parser::LogEntry;
+use regex::RegexSet;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+/// What an attack request tried to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AttackKind {
+    SqlInjection,
+    /// Climbing out of the web root with `../`, or asking for system files
+    PathTraversal,
+    /// Looking for files and admin pages that are often left exposed, such as
+    /// `.env` or `wp-login.php`
+    Probe,
+    ShellInjection,
+}
+
+impl AttackKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AttackKind::SqlInjection => "SQL injection",
+            AttackKind::PathTraversal => "path traversal",
+            AttackKind::Probe => "probe",
+            AttackKind::ShellInjection => "shell injection",
+        }
+    }
+}
+
+/// A pattern attack requests are known by
+struct Signature {
+    kind: AttackKind,
+    name: &'static str,
+    /// Matched against the decoded, lowercased endpoint
+    pattern: &'static str,
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature { kind: AttackKind::SqlInjection, name: "UNION SELECT", pattern: r"\bunion\b[\s(/*]+(all[\s(/*]+)?select\b" },
+    Signature { kind: AttackKind::SqlInjection, name: "quoted tautology", pattern: r#"['"]\s*\)?\s*(or|and)\s+['"(]?\w+['"]?\s*(=|like)\s*['"(]?\w+"# },
+    Signature { kind: AttackKind::SqlInjection, name: "numeric tautology", pattern: r"\b(or|and)\s+\d+\s*=\s*\d+\b" },
+    Signature { kind: AttackKind::SqlInjection, name: "quote and comment", pattern: r#"['"]\s*\)?\s*(--|#|/\*)"# },
+    Signature { kind: AttackKind::SqlInjection, name: "stacked query", pattern: r";\s*(drop|delete|insert|update|truncate|select)\s" },
+    Signature { kind: AttackKind::SqlInjection, name: "time delay", pattern: r"\b(sleep|pg_sleep|benchmark)\s*\(|\bwaitfor\s+delay\b" },
+    Signature { kind: AttackKind::SqlInjection, name: "schema enumeration", pattern: r"\binformation_schema\b" },
+    Signature { kind: AttackKind::PathTraversal, name: "../", pattern: r"(^|[/\\=])\.\.[/\\]" },
+    Signature { kind: AttackKind::PathTraversal, name: "system file", pattern: r"/etc/(passwd|shadow|hosts)\b|\b(win|boot)\.ini\b" },
+    Signature { kind: AttackKind::PathTraversal, name: "null byte", pattern: r"\x00" },
+    Signature { kind: AttackKind::Probe, name: ".env", pattern: r"/\.env(\.\w+)?($|[/?#])" },
+    Signature { kind: AttackKind::Probe, name: "wp-login.php", pattern: r"/(wp-login|xmlrpc)\.php\b" },
+    Signature { kind: AttackKind::Probe, name: ".git", pattern: r"/\.git($|[/?#])" },
+    Signature { kind: AttackKind::Probe, name: "phpMyAdmin", pattern: r"/(phpmyadmin|pma)($|[/?#])" },
+    Signature { kind: AttackKind::Probe, name: "credentials file", pattern: r"/\.(aws/credentials|ssh/|htpasswd\b)" },
+    Signature { kind: AttackKind::ShellInjection, name: "command chaining", pattern: r"(;|\|\|?|&&)\s*(cat|ls|id|whoami|uname|wget|curl|nc|ncat|bash|sh|ping)(\s|$|[;|&])" },
+    Signature { kind: AttackKind::ShellInjection, name: "command substitution", pattern: r"\$\([^)]*\)|`[^`]+`" },
+    Signature { kind: AttackKind::ShellInjection, name: "shell path", pattern: r"/bin/(ba)?sh\b" },
+    Signature { kind: AttackKind::ShellInjection, name: "shellshock", pattern: r"\(\)\s*\{\s*:\s*;\s*\}" },
+];
+
+fn signature_set() -> &'static RegexSet {
+    static SET: OnceLock<RegexSet> = OnceLock::new();
+    SET.get_or_init(|| {
+        RegexSet::new(SIGNATURES.iter().map(|s| s.pattern)).expect("hard-coded signatures should always compile")
+    })
+}
+
+/// The endpoint as the application would see it: escapes decoded, twice over to see
+/// through double encoding such as `%252e`, `+` read as a space, and lowercased
+fn decode(endpoint: &str) -> String {
+    let mut bytes = endpoint.as_bytes().to_vec();
+    for _ in 0..2 {
+        if !bytes.contains(&b'%') {
+            break;
+        }
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+            match hex.filter(|_| bytes[i] == b'%').and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                Some(b) => {
+                    decoded.push(b);
+                    i += 3;
+                }
+                None => {
+                    decoded.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        bytes = decoded;
+    }
+    String::from_utf8_lossy(&bytes).replace('+', " ").to_lowercase()
+}
+
+/// Kind and name of every signature the endpoint matches
+pub fn signatures(endpoint: &str) -> Vec<(AttackKind, &'static str)> {
+    signature_set()
+        .matches(&decode(endpoint))
+        .into_iter()
+        .map(|i| (SIGNATURES[i].kind, SIGNATURES[i].name))
+        .collect()
+}
+
+/// Requests and clients of one kind of attack
+#[derive(Debug, Clone, Serialize)]
+pub struct KindCount {
+    pub kind: AttackKind,
+    pub requests: usize,
+    pub ips: usize,
+}
+
+/// A client that sent attack requests
+#[derive(Debug, Clone, Serialize)]
+pub struct Attacker {
+    pub ip: String,
+    pub requests: usize,
+    pub kinds: Vec<AttackKind>,
+    /// Names of the signatures its requests matched
+    pub signatures: Vec<String>,
+    /// Of its attack requests, those answered with a 2xx status, so not blocked
+    pub successful: usize,
+    /// Its first attack request's endpoint, as counted
+    pub example: String,
+}
+
+/// Requests whose endpoint matched an attack signature, and the clients that sent
+/// them (`--attacks`)
+#[derive(Debug, Clone, Serialize)]
+pub struct AttackReport {
+    pub requests: usize,
+    /// Clients that sent any, of which the busiest are listed
+    pub ips: usize,
+    /// Attack requests answered with a 2xx status
+    pub successful: usize,
+    /// A request can be of several kinds
+    pub by_kind: Vec<KindCount>,
+    pub attackers: Vec<Attacker>,
+}
+
+/// Scan each distinct endpoint once for the signatures of SQL injection, path
+/// traversal, probes for exposed files and shell injection, and tally the requests
+/// that matched by kind and by client
+pub fn scan(entries: &[LogEntry], top_n: usize) -> AttackReport {
+    #[derive(Default)]
+    struct Tally<'a> {
+        requests: usize,
+        kinds: BTreeSet<AttackKind>,
+        signatures: BTreeSet<&'static str>,
+        successful: usize,
+        example: &'a str,
+    }
+
+    let mut matched: HashMap<&str, Vec<(AttackKind, &'static str)>> = HashMap::new();
+    let mut clients: HashMap<IpAddr, Tally> = HashMap::new();
+    let mut kinds: HashMap<AttackKind, (usize, BTreeSet<IpAddr>)> = HashMap::new();
+    let mut requests = 0;
+    let mut successful = 0;
+    for entry in entries {
+        let found = matched.entry(entry.endpoint.as_str()).or_insert_with_key(|endpoint| signatures(endpoint));
+        if found.is_empty() {
+            continue;
+        }
+        requests += 1;
+        let success = entry.status_code.is_some_and(|status| (200..300).contains(&status));
+        successful += usize::from(success);
+        let client = clients.entry(entry.ip).or_default();
+        if client.requests == 0 {
+            client.example = &entry.endpoint;
+        }
+        client.requests += 1;
+        client.successful += usize::from(success);
+        let request_kinds: BTreeSet<AttackKind> = found.iter().map(|&(kind, _)| kind).collect();
+        for &kind in &request_kinds {
+            let tally = kinds.entry(kind).or_default();
+            tally.0 += 1;
+            tally.1.insert(entry.ip);
+        }
+        client.kinds.extend(request_kinds);
+        client.signatures.extend(found.iter().map(|&(_, name)| name));
+    }
+
+    let mut by_kind: Vec<KindCount> = kinds
+        .into_iter()
+        .map(|(kind, (requests, ips))| KindCount { kind, requests, ips: ips.len() })
+        .collect();
+    by_kind.sort_by(|a, b| b.requests.cmp(&a.requests).then(a.kind.cmp(&b.kind)));
+
+    let ips = clients.len();
+    let mut attackers: Vec<(IpAddr, Tally)> = clients.into_iter().collect();
+    attackers.sort_by(|a, b| b.1.requests.cmp(&a.1.requests).then(a.0.cmp(&b.0)));
+    AttackReport {
+        requests,
+        ips,
+        successful,
+        by_kind,
+        attackers: attackers
+            .into_iter()
+            .take(top_n)
+            .map(|(ip, tally)| Attacker {
+                ip: ip.to_string(),
+                requests: tally.requests,
+                kinds: tally.kinds.into_iter().collect(),
+                signatures: tally.signatures.into_iter().map(str::to_string).collect(),
+                successful: tally.successful,
+                example: tally.example.to_string(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_log_line;
+
+    fn names(endpoint: &str) -> Vec<&'static str> {
+        signatures(endpoint).into_iter().map(|(_, name)| name).collect()
+    }
+
+    #[test]
+    fn matches_signatures_through_encoding() {
+        assert_eq!(names("/search?q=1%20UNION%20SELECT%20password%20FROM%20users"), ["UNION SELECT"]);
+        assert_eq!(names("/item?id=1'+OR+'1'='1"), ["quoted tautology"]);
+        assert_eq!(names("/login?user=admin'--"), ["quote and comment"]);
+        assert_eq!(names("/item?id=1%20AND%201=1"), ["numeric tautology"]);
+        assert_eq!(names("/q?id=1;SELECT%20sleep(5)"), ["stacked query", "time delay"]);
+        assert_eq!(names("/static/..%2F..%2Fetc/passwd"), ["../", "system file"]);
+        assert_eq!(names("/download?file=%252e%252e%252fsecret"), ["../"]);
+        assert_eq!(names("/.env"), [".env"]);
+        assert_eq!(names("/blog/wp-login.php"), ["wp-login.php"]);
+        assert_eq!(names("/.git/config"), [".git"]);
+        assert_eq!(names("/ping?host=127.0.0.1;cat%20/etc/hosts"), ["system file", "command chaining"]);
+        assert_eq!(names("/cgi-bin/test?x=$(whoami)"), ["command substitution"]);
+
+        // Ordinary requests that share words with attacks
+        for clean in [
+            "/api/users?sort=name&order=desc",
+            "/blog/union-select-committee",
+            "/environment",
+            "/static/app.env.js",
+            "/search?q=it's+fine",
+            "/api/identity",
+        ] {
+            assert!(names(clean).is_empty(), "{} matched {:?}", clean, names(clean));
+        }
+    }
+
+    #[test]
+    fn tallies_attacks_by_kind_and_client() {
+        let lines = [
+            "2024-01-15T10:00:00Z [WARN] 10.0.0.9 GET /.env 404",
+            "2024-01-15T10:00:01Z [WARN] 10.0.0.9 GET /wp-login.php 404",
+            "2024-01-15T10:00:02Z [INFO] 10.0.0.9 GET /item?id=1%27%20OR%20%27a%27=%27a 200",
+            "2024-01-15T10:00:03Z [WARN] 10.0.0.8 GET /files/..%2F..%2Fetc%2Fpasswd 403",
+            "2024-01-15T10:00:04Z [INFO] 10.0.0.1 GET /api/users 200",
+        ];
+        let entries: Vec<LogEntry> = lines.iter().map(|l| parse_log_line(l).unwrap()).collect();
+        let report = scan(&entries, 10);
+        assert_eq!((report.requests, report.ips, report.successful), (4, 2, 1));
+        let kinds: Vec<_> = report.by_kind.iter().map(|k| (k.kind, k.requests, k.ips)).collect();
+        assert_eq!(
+            kinds,
+            [(AttackKind::Probe, 2, 1), (AttackKind::SqlInjection, 1, 1), (AttackKind::PathTraversal, 1, 1)]
+        );
+        let first = &report.attackers[0];
+        assert_eq!((first.ip.as_str(), first.requests, first.successful), ("10.0.0.9", 3, 1));
+        assert_eq!(first.kinds, [AttackKind::SqlInjection, AttackKind::Probe]);
+        assert_eq!(first.signatures, [".env", "quoted tautology", "wp-login.php"]);
+        assert_eq!(first.example, "/.env");
+        assert_eq!(report.attackers[1].signatures, ["../", "system file"]);
+    }
+}
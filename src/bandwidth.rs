@@ -0,0 +1,114 @@
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::net::IpAddr;
+
+/// Bytes served to one endpoint or client
+#[derive(Debug, Clone, Serialize)]
+pub struct BandwidthItem {
+    pub value: String,
+    pub requests: usize,
+    pub bytes: u64,
+    /// Share of all bytes served
+    pub percentage: f64,
+}
+
+/// Response bytes in total and by the endpoints and clients that consumed the most
+#[derive(Debug, Clone, Serialize)]
+pub struct BandwidthReport {
+    pub total_bytes: u64,
+    /// Requests whose line carried no response size
+    pub requests_without_bytes: usize,
+    pub top_endpoints: Vec<BandwidthItem>,
+    pub top_ips: Vec<BandwidthItem>,
+}
+
+/// Sum response sizes per endpoint and per client IP.
+///
+/// Returns `None` when no entry carries a size, so logs without a `BYTES`
+/// column get no section.
+pub fn analyze(entries: &[LogEntry], top_n: usize) -> Option<BandwidthReport> {
+    let mut endpoints: HashMap<&str, (usize, u64)> = HashMap::new();
+    let mut ips: HashMap<IpAddr, (usize, u64)> = HashMap::new();
+    let mut total_bytes = 0u64;
+    let mut without_bytes = 0usize;
+
+    for entry in entries {
+        let Some(bytes) = entry.bytes else {
+            without_bytes += 1;
+            continue;
+        };
+        total_bytes += bytes;
+        let endpoint = endpoints.entry(entry.endpoint.as_str()).or_default();
+        endpoint.0 += 1;
+        endpoint.1 += bytes;
+        let ip = ips.entry(entry.ip).or_default();
+        ip.0 += 1;
+        ip.1 += bytes;
+    }
+    if without_bytes == entries.len() {
+        return None;
+    }
+
+    Some(BandwidthReport {
+        total_bytes,
+        requests_without_bytes: without_bytes,
+        top_endpoints: rank(endpoints, total_bytes, top_n),
+        top_ips: rank(ips, total_bytes, top_n),
+    })
+}
+
+/// Heaviest keys first; ties broken by key for stable output
+fn rank<K: Ord + Display>(usage: HashMap<K, (usize, u64)>, total_bytes: u64, top_n: usize) -> Vec<BandwidthItem> {
+    let mut usage: Vec<(K, (usize, u64))> = usage.into_iter().collect();
+    usage.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then_with(|| a.0.cmp(&b.0)));
+    usage
+        .into_iter()
+        .take(top_n)
+        .map(|(key, (requests, bytes))| BandwidthItem {
+            value: key.to_string(),
+            requests,
+            bytes,
+            percentage: if total_bytes == 0 {
+                0.0
+            } else {
+                (bytes as f64 / total_bytes as f64 * 10000.0).round() / 100.0
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_entry;
+
+    const AT: &str = "2024-01-15T10:30:00Z";
+
+    #[test]
+    fn ranks_endpoints_and_ips_by_bytes() {
+        let entries = vec![
+            LogEntry { bytes: Some(6000), ..test_entry(AT, "1.1.1.1", "/video", 200) },
+            LogEntry { bytes: Some(2000), ..test_entry(AT, "1.1.1.1", "/video", 200) },
+            LogEntry { bytes: Some(1000), ..test_entry(AT, "2.2.2.2", "/index", 200) },
+            LogEntry { bytes: Some(1000), ..test_entry(AT, "3.3.3.3", "/index", 200) },
+            test_entry(AT, "3.3.3.3", "/health", 200),
+        ];
+        let report = analyze(&entries, 10).unwrap();
+
+        assert_eq!(report.total_bytes, 10_000);
+        assert_eq!(report.requests_without_bytes, 1);
+        assert_eq!(report.top_endpoints[0].value, "/video");
+        assert_eq!(report.top_endpoints[0].percentage, 80.0);
+        assert_eq!(report.top_endpoints.len(), 2);
+        assert_eq!(report.top_ips[0].value, "1.1.1.1");
+        assert_eq!(report.top_ips[0].requests, 2);
+        assert_eq!(report.top_ips[1].value, "2.2.2.2"); // tie with 3.3.3.3, broken by address
+    }
+
+    #[test]
+    fn omitted_without_sizes() {
+        assert!(analyze(&[test_entry(AT, "1.1.1.1", "/", 200)], 10).is_none());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::agents::{classify, AgentClass};
+use crate::parser::LogEntry;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// Requests a client needs before the regularity of its requests is judged
+pub const CADENCE_MIN_REQUESTS: usize = 10;
+
+/// Largest spread of the time between a client's requests, as a share of the mean
+/// time between them (coefficient of variation), still taken as machine-regular
+pub const CADENCE_MAX_VARIATION: f64 = 0.1;
+
+/// Why a client was taken for a bot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BotSignal {
+    /// A crawler or bot user agent (see [`classify`])
+    UserAgent,
+    /// Asked for `/robots.txt`, which browsers never do
+    RobotsTxt,
+    /// Sent at least [`CADENCE_MIN_REQUESTS`] requests at near-constant intervals
+    Cadence,
+}
+
+impl BotSignal {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BotSignal::UserAgent => "user agent",
+            BotSignal::RobotsTxt => "robots.txt",
+            BotSignal::Cadence => "cadence",
+        }
+    }
+}
+
+/// A client (an IP with one user agent) taken for a bot
+#[derive(Debug, Clone, Serialize)]
+pub struct BotClient {
+    pub ip: String,
+    pub user_agent: Option<String>,
+    pub signals: Vec<BotSignal>,
+    pub requests: usize,
+    /// Share of all requests
+    pub percentage: f64,
+}
+
+/// Traffic split into human and bot clients
+#[derive(Debug, Clone, Serialize)]
+pub struct BotReport {
+    pub requests: usize,
+    pub bot_requests: usize,
+    /// Share of requests sent by bots
+    pub bot_share: f64,
+    /// Distinct IP and user agent pairs
+    pub clients: usize,
+    pub bot_clients: usize,
+    /// Bot clients with each signal; a client can have several
+    pub by_user_agent: usize,
+    pub by_robots_txt: usize,
+    pub by_cadence: usize,
+    /// The bots that sent the most requests
+    pub top_bots: Vec<BotClient>,
+}
+
+/// Requests of one client, with running statistics of the time between them so no
+/// timestamps need to be kept
+#[derive(Default)]
+struct Tally {
+    requests: usize,
+    last: Option<DateTime<Utc>>,
+    /// Mean gap in milliseconds and the sum of squared deviations from it (Welford)
+    gap_mean: f64,
+    gap_m2: f64,
+    robots_txt: bool,
+}
+
+impl Tally {
+    /// Count a request, taking the gap from the client's previous one in log order
+    fn record(&mut self, time: DateTime<Utc>) {
+        self.requests += 1;
+        if let Some(last) = self.last.replace(time) {
+            let gap = (time - last).num_milliseconds().abs() as f64;
+            let gaps = (self.requests - 1) as f64;
+            let delta = gap - self.gap_mean;
+            self.gap_mean += delta / gaps;
+            self.gap_m2 += delta * (gap - self.gap_mean);
+        }
+    }
+
+    /// Whether the requests came at near-constant, non-zero intervals
+    fn is_regular(&self) -> bool {
+        if self.requests < CADENCE_MIN_REQUESTS || self.gap_mean <= 0.0 {
+            return false;
+        }
+        let variance = self.gap_m2 / (self.requests - 1) as f64;
+        variance.sqrt() / self.gap_mean <= CADENCE_MAX_VARIATION
+    }
+}
+
+/// Take each client, an IP together with the user agent it sent, for a bot when its
+/// user agent says so, when it asked for `/robots.txt`, or when its requests came at
+/// machine-regular intervals; every other client counts as human. Formats without a
+/// user agent are judged on behavior alone. Cadence is judged on the gaps between a
+/// client's requests in log order, which is time order for a single log.
+///
+/// Returns `None` when there are no entries.
+pub fn analyze(entries: &[LogEntry], top_n: usize) -> Option<BotReport> {
+    let mut clients: HashMap<(IpAddr, Option<&str>), Tally> = HashMap::new();
+    for entry in entries {
+        let tally = clients.entry((entry.ip, entry.user_agent.as_deref())).or_default();
+        tally.record(entry.timestamp);
+        if entry.endpoint.split('?').next() == Some("/robots.txt") {
+            tally.robots_txt = true;
+        }
+    }
+    if clients.is_empty() {
+        return None;
+    }
+    let pct = |n: usize| (n as f64 / entries.len() as f64 * 10000.0).round() / 100.0;
+
+    let client_count = clients.len();
+    let mut bots: Vec<BotClient> = clients
+        .into_iter()
+        .filter_map(|((ip, user_agent), tally)| {
+            let mut signals = Vec::new();
+            if user_agent.is_some_and(|ua| matches!(classify(ua), AgentClass::Crawler | AgentClass::Bot)) {
+                signals.push(BotSignal::UserAgent);
+            }
+            if tally.robots_txt {
+                signals.push(BotSignal::RobotsTxt);
+            }
+            if tally.is_regular() {
+                signals.push(BotSignal::Cadence);
+            }
+            (!signals.is_empty()).then(|| BotClient {
+                ip: ip.to_string(),
+                user_agent: user_agent.map(str::to_string),
+                signals,
+                requests: tally.requests,
+                percentage: pct(tally.requests),
+            })
+        })
+        .collect();
+    bots.sort_by(|a, b| {
+        b.requests
+            .cmp(&a.requests)
+            .then_with(|| a.ip.cmp(&b.ip))
+            .then_with(|| a.user_agent.cmp(&b.user_agent))
+    });
+
+    let with = |signal: BotSignal| bots.iter().filter(|bot| bot.signals.contains(&signal)).count();
+    let bot_requests = bots.iter().map(|bot| bot.requests).sum();
+    Some(BotReport {
+        requests: entries.len(),
+        bot_requests,
+        bot_share: pct(bot_requests),
+        clients: client_count,
+        bot_clients: bots.len(),
+        by_user_agent: with(BotSignal::UserAgent),
+        by_robots_txt: with(BotSignal::RobotsTxt),
+        by_cadence: with(BotSignal::Cadence),
+        top_bots: bots.into_iter().take(top_n).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_entry;
+
+    fn entry(ip: &str, seconds: u32, endpoint: &str, agent: &str) -> LogEntry {
+        LogEntry {
+            user_agent: Some(agent.to_string()),
+            ..test_entry(&format!("2024-01-15T10:{:02}:{:02}Z", seconds / 60, seconds % 60), ip, endpoint, 200)
+        }
+    }
+
+    const BROWSER: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64)";
+
+    #[test]
+    fn classifies_by_user_agent_robots_txt_and_cadence() {
+        let mut entries = vec![
+            entry("10.0.0.1", 0, "/", "curl/8.4.0"),
+            entry("10.0.0.1", 1, "/", "curl/8.4.0"),
+            entry("10.0.0.2", 3, "/robots.txt", BROWSER),
+            entry("10.0.0.2", 4, "/", BROWSER),
+        ];
+        // A browser agent polling every 30s
+        for i in 0..12 {
+            entries.push(entry("10.0.0.3", i * 30, "/status", BROWSER));
+        }
+        // A person: same number of requests, at uneven intervals
+        for second in [0, 2, 3, 40, 41, 45, 100, 180, 181, 300, 302, 500] {
+            entries.push(entry("10.0.0.4", second, "/page", BROWSER));
+        }
+        // The same IP with another agent is another client
+        entries.push(entry("10.0.0.1", 5, "/", BROWSER));
+
+        let report = analyze(&entries, 10).unwrap();
+        assert_eq!((report.clients, report.bot_clients), (5, 3));
+        assert_eq!((report.requests, report.bot_requests), (29, 16));
+        assert_eq!(report.bot_share, 55.17);
+        assert_eq!((report.by_user_agent, report.by_robots_txt, report.by_cadence), (1, 1, 1));
+        let bots: Vec<_> = report.top_bots.iter().map(|bot| (bot.ip.as_str(), bot.requests, bot.signals.clone())).collect();
+        assert_eq!(
+            bots,
+            [
+                ("10.0.0.3", 12, vec![BotSignal::Cadence]),
+                ("10.0.0.1", 2, vec![BotSignal::UserAgent]),
+                ("10.0.0.2", 2, vec![BotSignal::RobotsTxt]),
+            ]
+        );
+    }
+
+    #[test]
+    fn requests_in_the_same_second_are_not_a_cadence() {
+        let mut tally = Tally::default();
+        for _ in 0..CADENCE_MIN_REQUESTS {
+            tally.record(DateTime::UNIX_EPOCH);
+        }
+        assert!(!tally.is_regular());
+        assert!(analyze(&[], 10).is_none());
+    }
+}
@@ -0,0 +1,193 @@
+use crate::agents::{classify, AgentClass};
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+use woothee::parser::Parser;
+use woothee::woothee::VALUE_UNKNOWN;
+
+/// Browser and operating system a user agent reports, as far as it can be told
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Platform {
+    /// Browser family, e.g. `Chrome`; `None` when the agent does not name one
+    pub browser: Option<String>,
+    /// Major version of the browser, e.g. `121`
+    pub version: Option<String>,
+    /// Operating system family, e.g. `Windows 10`, `macOS`, `iOS` or `Android`
+    pub os: Option<String>,
+}
+
+/// Parse a user agent into its browser family, major version and operating system.
+///
+/// Returns `None` for crawlers, HTTP libraries and other bots, which are not browsers
+/// whatever they claim. Agents the parser does not recognize leave every field empty.
+pub fn parse(user_agent: &str) -> Option<Platform> {
+    if matches!(classify(user_agent), AgentClass::Crawler | AgentClass::Bot) {
+        return None;
+    }
+    let Some(result) = Parser::new().parse(user_agent) else {
+        return Some(Platform::default());
+    };
+    if result.category == "crawler" {
+        return None;
+    }
+    let known = |value: &str| (value != VALUE_UNKNOWN).then(|| value.to_string());
+    let os = match result.os {
+        "iPhone" | "iPad" | "iPod" => "iOS",
+        "Mac OSX" => "macOS",
+        os => os,
+    };
+    Some(Platform {
+        browser: known(result.name),
+        version: known(result.version).and_then(|version| version.split('.').next().map(str::to_string)),
+        os: known(os),
+    })
+}
+
+/// Requests from one browser family or operating system
+#[derive(Debug, Clone, Serialize)]
+pub struct FamilyShare {
+    pub name: String,
+    pub count: usize,
+    /// Share of browser requests
+    pub percentage: f64,
+}
+
+/// Requests from one major version of a browser
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionShare {
+    pub browser: String,
+    pub version: String,
+    pub count: usize,
+    /// Share of browser requests
+    pub percentage: f64,
+}
+
+/// Browser, browser version and operating system distributions of the requests made
+/// by browsers, crawlers and bots aside
+#[derive(Debug, Clone, Serialize)]
+pub struct BrowserReport {
+    /// Requests whose user agent parsed as a browser
+    pub requests: usize,
+    /// Requests from crawlers and bots, left out of the distributions
+    pub automated: usize,
+    /// Browser families; agents naming none count as `Other`
+    pub browsers: Vec<FamilyShare>,
+    /// Browser families by major version
+    pub versions: Vec<VersionShare>,
+    /// Operating systems; agents naming none count as `Other`
+    pub operating_systems: Vec<FamilyShare>,
+}
+
+/// Rank counts, most requests first and then by name, keeping the top `top_n`
+fn ranked<K: Ord>(counts: HashMap<K, usize>, top_n: usize) -> Vec<(K, usize)> {
+    let mut ranked: Vec<(K, usize)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(top_n);
+    ranked
+}
+
+/// Parse each distinct user agent once and break the browser requests down by
+/// browser family, major version and operating system.
+///
+/// Returns `None` when no entry carries a user agent, as for formats that do not log one.
+pub fn analyze(entries: &[LogEntry], top_n: usize) -> Option<BrowserReport> {
+    let mut agents: HashMap<&str, usize> = HashMap::new();
+    for agent in entries.iter().filter_map(|e| e.user_agent.as_deref()) {
+        *agents.entry(agent).or_insert(0) += 1;
+    }
+    if agents.is_empty() {
+        return None;
+    }
+
+    let mut automated = 0;
+    let mut browsers: HashMap<String, usize> = HashMap::new();
+    let mut versions: HashMap<(String, String), usize> = HashMap::new();
+    let mut operating_systems: HashMap<String, usize> = HashMap::new();
+    for (agent, count) in agents {
+        let Some(platform) = parse(agent) else {
+            automated += count;
+            continue;
+        };
+        let other = || "Other".to_string();
+        if let (Some(browser), Some(version)) = (&platform.browser, &platform.version) {
+            *versions.entry((browser.clone(), version.clone())).or_insert(0) += count;
+        }
+        *browsers.entry(platform.browser.unwrap_or_else(other)).or_insert(0) += count;
+        *operating_systems.entry(platform.os.unwrap_or_else(other)).or_insert(0) += count;
+    }
+    let requests: usize = browsers.values().sum();
+    let pct = |n: usize| (n as f64 / requests as f64 * 10000.0).round() / 100.0;
+    let families = |counts| {
+        ranked(counts, top_n)
+            .into_iter()
+            .map(|(name, count)| FamilyShare { name, count, percentage: pct(count) })
+            .collect()
+    };
+
+    Some(BrowserReport {
+        requests,
+        automated,
+        browsers: families(browsers),
+        versions: ranked(versions, top_n)
+            .into_iter()
+            .map(|((browser, version), count)| VersionShare { browser, version, count, percentage: pct(count) })
+            .collect(),
+        operating_systems: families(operating_systems),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_line, LogFormat};
+
+    const CHROME_WINDOWS: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/121.0.0.0 Safari/537.36";
+    const SAFARI_IPHONE: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_3 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.3 Mobile/15E148 Safari/604.1";
+
+    #[test]
+    fn parses_browser_version_and_os() {
+        let platform = |browser: &str, version: &str, os: &str| Platform {
+            browser: Some(browser.to_string()),
+            version: Some(version.to_string()),
+            os: Some(os.to_string()),
+        };
+        assert_eq!(parse(CHROME_WINDOWS), Some(platform("Chrome", "121", "Windows 10")));
+        assert_eq!(parse(SAFARI_IPHONE), Some(platform("Safari", "17", "iOS")));
+        assert_eq!(
+            parse("Mozilla/5.0 (X11; Linux x86_64; rv:123.0) Gecko/20100101 Firefox/123.0"),
+            Some(platform("Firefox", "123", "Linux"))
+        );
+        let bare = parse("Mozilla/5.0 (Macintosh; Intel Mac OS X 14_3)").unwrap();
+        assert_eq!((bare.browser, bare.os.as_deref()), (None, Some("macOS")));
+        assert!(parse("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)").is_none());
+        assert!(parse("curl/8.4.0").is_none());
+        assert_eq!(parse("MyApp/1.2"), Some(Platform::default()));
+    }
+
+    #[test]
+    fn breaks_browser_requests_down() {
+        let line = |agent: &str| {
+            let line = format!(r#"10.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET / HTTP/1.1" 200 5 "-" "{}""#, agent);
+            parse_line(&line, &LogFormat::NginxCombined).unwrap()
+        };
+        let entries = vec![
+            line(CHROME_WINDOWS),
+            line(CHROME_WINDOWS),
+            line(SAFARI_IPHONE),
+            line("MyApp/1.2"),
+            line("curl/8.4.0"),
+            line("-"),
+        ];
+        let report = analyze(&entries, 10).unwrap();
+
+        assert_eq!((report.requests, report.automated), (4, 1));
+        let browsers: Vec<_> = report.browsers.iter().map(|b| (b.name.as_str(), b.count)).collect();
+        assert_eq!(browsers, [("Chrome", 2), ("Other", 1), ("Safari", 1)]);
+        assert_eq!(report.browsers[0].percentage, 50.0);
+        let versions: Vec<_> = report.versions.iter().map(|v| (v.browser.as_str(), v.version.as_str())).collect();
+        assert_eq!(versions, [("Chrome", "121"), ("Safari", "17")]);
+        let systems: Vec<_> = report.operating_systems.iter().map(|os| (os.name.as_str(), os.count)).collect();
+        assert_eq!(systems, [("Windows 10", 2), ("Other", 1), ("iOS", 1)]);
+        assert!(analyze(&entries[5..], 10).is_none());
+    }
+}
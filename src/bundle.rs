@@ -0,0 +1,455 @@
+use crate::analyzer::AnalysisStats;
+use crate::inputs;
+use crate::parser::{self, LogFormat};
+use crate::report;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use regex::Regex;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::hash::BuildHasher;
+use std::io::{self, BufRead};
+use std::net::Ipv6Addr;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Name of the directory every file in the bundle is placed under
+const BUNDLE_DIR: &str = "log_analyzer-bundle";
+
+static IPV4_REGEX: OnceLock<Regex> = OnceLock::new();
+static IPV6_REGEX: OnceLock<Regex> = OnceLock::new();
+static CLF_USER_REGEX: OnceLock<Regex> = OnceLock::new();
+static USER_KEY_REGEX: OnceLock<Regex> = OnceLock::new();
+
+fn ipv4_regex() -> &'static Regex {
+    IPV4_REGEX.get_or_init(|| {
+        Regex::new(r"\b\d{1,3}(?:\.\d{1,3}){3}\b").expect("hard-coded regex should always compile")
+    })
+}
+
+/// Candidate IPv6 addresses (with an optional zone id); matches are validated
+/// before redaction so times like `10:30:00` are left alone
+fn ipv6_regex() -> &'static Regex {
+    IPV6_REGEX.get_or_init(|| {
+        Regex::new(r"[0-9A-Fa-f]*:[0-9A-Fa-f:.]*:[0-9A-Fa-f.]*(?:%[\w.-]+)?")
+            .expect("hard-coded regex should always compile")
+    })
+}
+
+/// The `%u` auth-user token of CLF-style lines (`host ident user [time] …`)
+fn clf_user_regex() -> &'static Regex {
+    CLF_USER_REGEX.get_or_init(|| {
+        Regex::new(r"^(\S+ \S+ )([^\s\[]+)( \[)").expect("hard-coded regex should always compile")
+    })
+}
+
+/// `user=…` and `"user":"…"` values in key-value and JSON lines, under the keys the
+/// parsers read a user from
+fn user_key_regex() -> &'static Regex {
+    USER_KEY_REGEX.get_or_init(|| {
+        Regex::new(r#"(^|[\s{,])("?(?:user|username|remote_user|user_id)"?\s*[=:]\s*"?)([^\s",}]+)"#)
+            .expect("hard-coded regex should always compile")
+    })
+}
+
+/// Replaces identifying values with stable pseudonyms.
+///
+/// Pseudonyms are keyed with a per-run random seed, so the same IP maps to the
+/// same token everywhere inside one bundle but cannot be correlated across bundles.
+pub struct Redactor {
+    seed: RandomState,
+    ips: HashMap<String, String>,
+    users: HashMap<String, String>,
+}
+
+impl Redactor {
+    pub fn new() -> Self {
+        Redactor {
+            seed: RandomState::new(),
+            ips: HashMap::new(),
+            users: HashMap::new(),
+        }
+    }
+
+    /// Pseudonymize a single IP address.
+    ///
+    /// Addresses are keyed by their parsed form, so `[::1]`, `::1%lo0` and `::1`
+    /// share a token.
+    pub fn ip(&mut self, ip: &str) -> String {
+        let key = parser::parse_ip(ip).map_or_else(|_| ip.to_string(), |addr| addr.to_string());
+        if let Some(token) = self.ips.get(&key) {
+            return token.clone();
+        }
+        let token = format!("ip-{:08x}", self.seed.hash_one(&key) as u32);
+        self.ips.insert(key, token.clone());
+        token
+    }
+
+    /// Pseudonymize an authenticated user name
+    pub fn user(&mut self, user: &str) -> String {
+        if let Some(token) = self.users.get(user) {
+            return token.clone();
+        }
+        let token = format!("user-{:08x}", self.seed.hash_one(("user", user)) as u32);
+        self.users.insert(user.to_string(), token.clone());
+        token
+    }
+
+    /// Strip the query string from an endpoint, which frequently carries tokens or PII
+    pub fn endpoint(&self, endpoint: &str) -> String {
+        match endpoint.split_once('?') {
+            Some((path, _)) => format!("{}?<redacted>", path),
+            None => endpoint.to_string(),
+        }
+    }
+
+    /// Redact every IP address, authenticated user and query string appearing in a
+    /// raw log line
+    pub fn line(&mut self, line: &str) -> String {
+        let with_ips = ipv4_regex()
+            .replace_all(line, |caps: &regex::Captures| self.ip(&caps[0]))
+            .into_owned();
+        let with_ips = ipv6_regex()
+            .replace_all(&with_ips, |caps: &regex::Captures| {
+                let candidate = &caps[0];
+                let addr = candidate.split('%').next().unwrap_or(candidate);
+                if addr.parse::<Ipv6Addr>().is_ok() {
+                    self.ip(candidate)
+                } else {
+                    candidate.to_string()
+                }
+            })
+            .into_owned();
+        let with_users = clf_user_regex()
+            .replace(&with_ips, |caps: &regex::Captures| match &caps[2] {
+                "-" => caps[0].to_string(),
+                user => format!("{}{}{}", &caps[1], self.user(user), &caps[3]),
+            })
+            .into_owned();
+        let with_users = user_key_regex()
+            .replace_all(&with_users, |caps: &regex::Captures| {
+                format!("{}{}{}", &caps[1], &caps[2], self.user(&caps[3]))
+            })
+            .into_owned();
+        with_users
+            .split(' ')
+            .map(|token| {
+                if token.contains('?') {
+                    self.endpoint(token)
+                } else {
+                    token.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Redact identifying values in place across the aggregated statistics
+    pub fn stats(&mut self, stats: &mut AnalysisStats) {
+        for item in &mut stats.top_ips {
+            item.value = self.ip(&item.value);
+        }
+        for item in &mut stats.top_endpoints {
+            item.value = self.endpoint(&item.value);
+        }
+        for flagged in &mut stats.flagged_ips {
+            flagged.ip = self.ip(&flagged.ip);
+            // The network owner narrows a pseudonymized client down to its provider
+            flagged.network = None;
+        }
+        if let Some(files) = &mut stats.files {
+            for file in files {
+                file.path = Path::new(&file.path)
+                    .file_name()
+                    .map_or_else(|| file.path.clone(), |name| name.to_string_lossy().into_owned());
+            }
+        }
+        if let Some(errors) = &mut stats.endpoint_errors {
+            for item in errors.top_4xx.iter_mut().chain(&mut errors.top_5xx) {
+                item.value = self.endpoint(&item.value);
+            }
+            for item in &mut errors.endpoints {
+                item.endpoint = self.endpoint(&item.endpoint);
+            }
+        }
+        if let Some(matrix) = stats.endpoint_methods.take() {
+            let mut redacted: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+            for (endpoint, methods) in matrix {
+                let row = redacted.entry(self.endpoint(&endpoint)).or_default();
+                for (method, count) in methods {
+                    *row.entry(method).or_insert(0) += count;
+                }
+            }
+            stats.endpoint_methods = Some(redacted);
+        }
+        if let Some(bursts) = &mut stats.bursts {
+            for item in &mut bursts.ips {
+                item.ip = self.ip(&item.ip);
+            }
+        }
+        if let Some(attacks) = &mut stats.attacks {
+            for attacker in &mut attacks.attackers {
+                attacker.ip = self.ip(&attacker.ip);
+                attacker.example = self.endpoint(&attacker.example);
+            }
+        }
+        if let Some(scanners) = &mut stats.scanners {
+            for item in &mut scanners.ips {
+                item.ip = self.ip(&item.ip);
+            }
+        }
+        if let Some(sessions) = &mut stats.sessions {
+            for item in sessions.top_entry_endpoints.iter_mut().chain(&mut sessions.top_exit_endpoints) {
+                item.value = self.endpoint(&item.value);
+            }
+        }
+        if let Some(traces) = &mut stats.traces {
+            for trace in &mut traces.top_error_traces {
+                for endpoint in &mut trace.failed_endpoints {
+                    *endpoint = self.endpoint(endpoint);
+                }
+            }
+        }
+        if let Some(travel) = &mut stats.travel {
+            for hop in &mut travel.anomalies {
+                hop.subject = self.user(&hop.subject);
+                hop.from_ip = self.ip(&hop.from_ip);
+                hop.to_ip = self.ip(&hop.to_ip);
+            }
+        }
+        if let Some(countries) = &mut stats.countries {
+            // Cities can single out the few clients in them; countries are kept
+            countries.top_cities.clear();
+        }
+        if let Some(apdex) = &mut stats.apdex {
+            for item in &mut apdex.endpoints {
+                item.endpoint = self.endpoint(&item.endpoint);
+            }
+        }
+        if let Some(upstream) = &mut stats.upstream {
+            for item in &mut upstream.slowest_endpoints {
+                item.endpoint = self.endpoint(&item.endpoint);
+            }
+        }
+        if let Some(flags) = &mut stats.response_flags {
+            for item in &mut flags.top_upstream_hosts {
+                item.value = self.line(&item.value);
+            }
+        }
+        if let Some(bandwidth) = &mut stats.bandwidth {
+            for item in &mut bandwidth.top_endpoints {
+                item.value = self.endpoint(&item.value);
+            }
+            for item in &mut bandwidth.top_ips {
+                item.value = self.ip(&item.value);
+            }
+        }
+        if let Some(bots) = &mut stats.bots {
+            for bot in &mut bots.top_bots {
+                bot.ip = self.ip(&bot.ip);
+            }
+        }
+        if let Some(users) = &mut stats.users {
+            for item in &mut users.users {
+                item.user = self.user(&item.user);
+            }
+        }
+        if let Some(referrers) = &mut stats.referrers {
+            for item in &mut referrers.top_referrers {
+                item.value = self.endpoint(&item.value);
+            }
+        }
+        if let Some(costs) = &mut stats.cost_attribution {
+            for line in &mut costs.top_endpoints {
+                line.key = self.endpoint(&line.key);
+            }
+            for line in &mut costs.top_tenants {
+                line.key = self.ip(&line.key);
+            }
+        }
+        if let Some(robots) = &mut stats.robots_compliance {
+            for crawler in &mut robots.top_violators {
+                crawler.ip = self.ip(&crawler.ip);
+            }
+            for item in &mut robots.top_disallowed_paths {
+                item.value = self.endpoint(&item.value);
+            }
+        }
+        if let Some(redirects) = &mut stats.redirects {
+            redirects.edges.clear();
+            for edge in &mut redirects.top_edges {
+                edge.source = self.endpoint(&edge.source);
+                edge.target = self.endpoint(&edge.target);
+            }
+            for chain in redirects.loops.iter_mut().chain(&mut redirects.long_chains) {
+                for step in &mut chain.path {
+                    *step = self.endpoint(step);
+                }
+            }
+        }
+        if let Some(coverage) = &mut stats.endpoint_coverage {
+            for item in &mut coverage.top_unknown_endpoints {
+                item.value = self.endpoint(&item.value);
+            }
+        }
+        if let Some(correlation) = &mut stats.spec_correlation {
+            for item in &mut correlation.top_unknown_endpoints {
+                item.value = self.endpoint(&item.value);
+            }
+        }
+    }
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Write an anonymized support bundle (`.tar.gz`) to `output`.
+///
+/// The bundle contains the redacted stats JSON, an HTML report, a sample of
+/// redacted evidence lines and the configuration the analysis was run with.
+/// Evidence is read from `source` with `format`, the format the analysis used.
+pub fn write_bundle(
+    output: &Path,
+    source: &Path,
+    format: &LogFormat,
+    mut stats: AnalysisStats,
+    config: &serde_json::Value,
+    evidence_limit: usize,
+) -> Result<(), io::Error> {
+    let flagged: HashSet<String> = stats.flagged_ips.iter().map(|f| f.ip.clone()).collect();
+    let mut redactor = Redactor::new();
+
+    // Evidence must be sampled before the stats are redacted, while the
+    // flagged IPs can still be matched against the raw lines.
+    let evidence = sample_evidence(source, format, &flagged, evidence_limit, &mut redactor)?;
+    redactor.stats(&mut stats);
+
+    let source_name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "log".to_string());
+
+    let stats_json = serde_json::to_string_pretty(&stats).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("serialization failed: {}", e))
+    })?;
+    let config_json = serde_json::to_string_pretty(config).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("serialization failed: {}", e))
+    })?;
+    let html = report::render_html(&stats, &source_name);
+
+    let encoder = GzEncoder::new(File::create(output)?, Compression::default());
+    let mut archive = tar::Builder::new(encoder);
+    append_file(&mut archive, "stats.json", stats_json.as_bytes())?;
+    append_file(&mut archive, "report.html", html.as_bytes())?;
+    append_file(&mut archive, "evidence.log", evidence.as_bytes())?;
+    append_file(&mut archive, "config.json", config_json.as_bytes())?;
+    archive.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Collect up to `limit` redacted lines, preferring lines from flagged IPs and
+/// falling back to `[ERROR]` lines when nothing was flagged.
+fn sample_evidence(
+    source: &Path,
+    format: &LogFormat,
+    flagged: &HashSet<String>,
+    limit: usize,
+    redactor: &mut Redactor,
+) -> Result<String, io::Error> {
+    let reader = inputs::open(source)?;
+    let mut evidence = String::new();
+    let mut taken = 0usize;
+
+    for line in reader.lines() {
+        if taken >= limit {
+            break;
+        }
+        let line = line?;
+        let Ok(entry) = parser::parse_line(&line, format) else {
+            continue;
+        };
+        let relevant = if flagged.is_empty() {
+            entry.level.is_error()
+        } else {
+            flagged.contains(&entry.ip.to_string())
+        };
+        if relevant {
+            evidence.push_str(&redactor.line(line.trim()));
+            evidence.push('\n');
+            taken += 1;
+        }
+    }
+
+    Ok(evidence)
+}
+
+fn append_file<W: io::Write>(
+    archive: &mut tar::Builder<W>,
+    name: &str,
+    contents: &[u8],
+) -> Result<(), io::Error> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive.append_data(&mut header, format!("{}/{}", BUNDLE_DIR, name), contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_ip_maps_to_same_pseudonym() {
+        let mut redactor = Redactor::new();
+        let a = redactor.ip("10.0.0.5");
+        let b = redactor.ip("10.0.0.5");
+        let c = redactor.ip("10.0.0.6");
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(a.starts_with("ip-"));
+    }
+
+    #[test]
+    fn redacts_ips_and_query_strings_in_lines() {
+        let mut redactor = Redactor::new();
+        let line = "2024-01-15T10:30:00Z [INFO] 192.168.1.1 GET /login?token=abc 200";
+        let redacted = redactor.line(line);
+        assert!(!redacted.contains("192.168.1.1"));
+        assert!(!redacted.contains("token=abc"));
+        assert!(redacted.contains("/login?<redacted>"));
+        assert!(redacted.contains(&redactor.ip("192.168.1.1")));
+    }
+
+    #[test]
+    fn redacts_authenticated_users_in_lines() {
+        let mut redactor = Redactor::new();
+        let clf = r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326"#;
+        let redacted = redactor.line(clf);
+        assert!(!redacted.contains("frank"), "{}", redacted);
+        assert!(redacted.contains(&format!(" - {} [", redactor.user("frank"))));
+        let anonymous = r#"127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 2326"#;
+        assert!(redactor.line(anonymous).contains(" - - ["));
+
+        let json = r#"{"remote_addr":"10.0.0.5","user":"svc-billing","user_agent":"curl/8.4.0"}"#;
+        let redacted = redactor.line(json);
+        assert!(!redacted.contains("svc-billing"));
+        assert!(redacted.contains("curl/8.4.0"));
+        assert!(!redactor.line("ts=1 username=alice path=/").contains("alice"));
+    }
+
+    #[test]
+    fn redacts_ipv6_but_not_times() {
+        let mut redactor = Redactor::new();
+        let line = "2024-01-15T10:30:00Z [INFO] 2001:db8::1 GET / 200 from fe80::1%eth0";
+        let redacted = redactor.line(line);
+        assert!(!redacted.contains("2001:db8::1"));
+        assert!(!redacted.contains("fe80::1"));
+        assert!(redacted.contains("10:30:00Z"));
+        assert_eq!(redactor.ip("[2001:db8::1]"), redactor.ip("2001:db8::1"));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::parser::LogEntry;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Window `--burst-threshold` counts requests in, unless `--burst-window` says otherwise
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(10);
+
+/// A client that sent more requests within one window than the threshold allows
+#[derive(Debug, Clone, Serialize)]
+pub struct BurstingIp {
+    pub ip: String,
+    /// Most requests the client sent within any one window
+    pub peak_requests: usize,
+    /// Time of the first request of that window
+    pub peak_start: DateTime<Utc>,
+    /// Times the client went over the threshold; consecutive requests that each end
+    /// an over-threshold window are one burst
+    pub bursts: usize,
+    pub total_requests: usize,
+    /// Requests logged as ERROR or FATAL, to tell abusive clients from busy healthy ones
+    pub errors: usize,
+}
+
+/// Clients whose request rate went over `threshold` requests per `window`, whether
+/// or not their requests succeeded (`--burst-threshold`)
+#[derive(Debug, Clone, Serialize)]
+pub struct BurstReport {
+    pub threshold: usize,
+    pub window_seconds: u64,
+    /// Clients over the threshold, of which the top ones are listed
+    pub flagged: usize,
+    /// Highest peak first
+    pub ips: Vec<BurstingIp>,
+}
+
+/// Slide a window of `window` over each client's requests and flag the clients that
+/// sent more than `threshold` within it. A window covers requests from its first one
+/// up to but excluding `window` later, so with a `1s` window and logs timed to the
+/// second it counts the requests of one second. Windows are at least a second long.
+pub fn detect(entries: &[LogEntry], threshold: usize, window: Duration, top_n: usize) -> BurstReport {
+    let window = window.max(Duration::from_secs(1));
+    let width = TimeDelta::from_std(window).unwrap_or(TimeDelta::MAX);
+    let mut clients: HashMap<IpAddr, (Vec<DateTime<Utc>>, usize)> = HashMap::new();
+    for entry in entries {
+        let client = clients.entry(entry.ip).or_default();
+        client.0.push(entry.timestamp);
+        if entry.level.is_error() {
+            client.1 += 1;
+        }
+    }
+
+    let mut flagged: Vec<BurstingIp> = clients
+        .into_iter()
+        .filter_map(|(ip, (mut times, errors))| {
+            times.sort_unstable();
+            let mut peak = (0, times[0]);
+            let mut bursts = 0;
+            let mut in_burst = false;
+            let mut first = 0;
+            for (last, &time) in times.iter().enumerate() {
+                while time - times[first] >= width {
+                    first += 1;
+                }
+                let count = last - first + 1;
+                if count > peak.0 {
+                    peak = (count, times[first]);
+                }
+                let over = count > threshold;
+                if over && !in_burst {
+                    bursts += 1;
+                }
+                in_burst = over;
+            }
+            (peak.0 > threshold).then(|| BurstingIp {
+                ip: ip.to_string(),
+                peak_requests: peak.0,
+                peak_start: peak.1,
+                bursts,
+                total_requests: times.len(),
+                errors,
+            })
+        })
+        .collect();
+    flagged.sort_by(|a, b| {
+        b.peak_requests
+            .cmp(&a.peak_requests)
+            .then(a.peak_start.cmp(&b.peak_start))
+            .then_with(|| a.ip.cmp(&b.ip))
+    });
+    let count = flagged.len();
+    flagged.truncate(top_n);
+
+    BurstReport {
+        threshold,
+        window_seconds: window.as_secs(),
+        flagged: count,
+        ips: flagged,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{test_entry, LogLevel};
+
+    fn entry(ip: &str, seconds: u32, level: LogLevel) -> LogEntry {
+        LogEntry { level, ..test_entry(&format!("2024-01-15T10:{:02}:{:02}Z", seconds / 60, seconds % 60), ip, "/api", 200) }
+    }
+
+    #[test]
+    fn flags_clients_over_the_threshold_in_any_window() {
+        let mut entries = Vec::new();
+        // Six successful requests within 3s, then six more a minute later
+        for second in [0, 1, 1, 2, 2, 3, 60, 61, 61, 62, 62, 63] {
+            entries.push(entry("10.0.0.1", second, LogLevel::Info));
+        }
+        // Steady: one request every 2s
+        for second in (0..40).step_by(2) {
+            entries.push(entry("10.0.0.2", second, LogLevel::Error));
+        }
+        // Out of order in the log, still one window
+        for second in [30, 5, 6, 7, 8, 9] {
+            entries.push(entry("10.0.0.3", second, LogLevel::Error));
+        }
+
+        let report = detect(&entries, 4, Duration::from_secs(5), 10);
+        assert_eq!((report.threshold, report.window_seconds, report.flagged), (4, 5, 2));
+        let flagged: Vec<_> = report.ips.iter().map(|ip| (ip.ip.as_str(), ip.peak_requests, ip.bursts)).collect();
+        assert_eq!(flagged, [("10.0.0.1", 6, 2), ("10.0.0.3", 5, 1)]);
+        assert_eq!(report.ips[0].peak_start, entries[0].timestamp);
+        assert_eq!((report.ips[0].total_requests, report.ips[0].errors), (12, 0));
+        assert_eq!((report.ips[1].total_requests, report.ips[1].errors), (6, 6));
+
+        // A window ends before the request a full window later: 0s and 3s are apart
+        let report = detect(&entries, 4, Duration::from_secs(3), 10);
+        assert_eq!((report.flagged, report.ips[0].peak_requests), (1, 5));
+        assert_eq!(detect(&entries, 4, Duration::from_secs(2), 10).flagged, 0);
+    }
+}
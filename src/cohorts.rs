@@ -0,0 +1,139 @@
+use crate::parser::LogEntry;
+use chrono::Timelike;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+/// Share of a cohort's requests falling into each status class
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatusMix {
+    pub success_2xx: f64,
+    pub redirect_3xx: f64,
+    pub client_error_4xx: f64,
+    pub server_error_5xx: f64,
+}
+
+/// Behavioral profile of a group of IPs
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortProfile {
+    pub ips: usize,
+    pub requests: usize,
+    pub requests_per_ip: f64,
+    /// Percentage of the cohort's requests in each UTC hour of the day
+    pub hourly_share: Vec<f64>,
+    /// UTC hour with the largest share of requests, if the cohort made any request
+    pub peak_hour: Option<u32>,
+    pub status_mix: StatusMix,
+    /// Mean number of distinct endpoints requested per IP
+    pub avg_distinct_endpoints: f64,
+}
+
+/// Flagged IPs profiled side by side with every other IP
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortComparison {
+    pub flagged: CohortProfile,
+    pub others: CohortProfile,
+}
+
+#[derive(Default)]
+struct Accumulator<'a> {
+    requests: usize,
+    hourly: [usize; 24],
+    status: [usize; 4],
+    endpoints_per_ip: HashMap<IpAddr, HashSet<&'a str>>,
+}
+
+impl<'a> Accumulator<'a> {
+    fn add(&mut self, entry: &'a LogEntry) {
+        self.requests += 1;
+        self.hourly[entry.timestamp.hour() as usize] += 1;
+        if let Some(code @ 200..=599) = entry.status_code {
+            self.status[(code / 100 - 2) as usize] += 1;
+        }
+        self.endpoints_per_ip
+            .entry(entry.ip)
+            .or_default()
+            .insert(entry.endpoint.as_str());
+    }
+
+    fn finish(self) -> CohortProfile {
+        let ips = self.endpoints_per_ip.len();
+        let share = |n: usize, total: usize| {
+            if total == 0 {
+                0.0
+            } else {
+                (n as f64 / total as f64) * 100.0
+            }
+        };
+        let requests: usize = self.hourly.iter().sum();
+        let peak_hour = (requests > 0).then(|| {
+            (0..24u32)
+                .max_by_key(|&h| (self.hourly[h as usize], std::cmp::Reverse(h)))
+                .unwrap_or(0)
+        });
+        let distinct: usize = self.endpoints_per_ip.values().map(HashSet::len).sum();
+
+        CohortProfile {
+            ips,
+            requests: self.requests,
+            requests_per_ip: if ips == 0 { 0.0 } else { self.requests as f64 / ips as f64 },
+            hourly_share: self.hourly.iter().map(|&n| share(n, requests)).collect(),
+            peak_hour,
+            status_mix: StatusMix {
+                success_2xx: share(self.status[0], self.requests),
+                redirect_3xx: share(self.status[1], self.requests),
+                client_error_4xx: share(self.status[2], self.requests),
+                server_error_5xx: share(self.status[3], self.requests),
+            },
+            avg_distinct_endpoints: if ips == 0 { 0.0 } else { distinct as f64 / ips as f64 },
+        }
+    }
+}
+
+/// Split entries into the flagged-IP cohort and everyone else, and profile each
+pub fn compare(entries: &[LogEntry], flagged_ips: &HashSet<IpAddr>) -> CohortComparison {
+    let mut flagged = Accumulator::default();
+    let mut others = Accumulator::default();
+    for entry in entries {
+        if flagged_ips.contains(&entry.ip) {
+            flagged.add(entry);
+        } else {
+            others.add(entry);
+        }
+    }
+    CohortComparison {
+        flagged: flagged.finish(),
+        others: others.finish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_entry;
+
+    #[test]
+    fn profiles_flagged_and_other_ips_separately() {
+        let entries = vec![
+            test_entry("2024-01-15T03:00:00Z", "9.9.9.9", "/a", 500),
+            test_entry("2024-01-15T03:10:00Z", "9.9.9.9", "/b", 500),
+            test_entry("2024-01-15T03:20:00Z", "9.9.9.9", "/c", 404),
+            test_entry("2024-01-15T14:00:00Z", "1.1.1.1", "/a", 200),
+            test_entry("2024-01-15T15:00:00Z", "1.1.1.2", "/a", 301),
+        ];
+        let flagged: HashSet<IpAddr> = ["9.9.9.9".parse().unwrap()].into_iter().collect();
+        let cmp = compare(&entries, &flagged);
+
+        assert_eq!(cmp.flagged.ips, 1);
+        assert_eq!(cmp.flagged.requests, 3);
+        assert_eq!(cmp.flagged.peak_hour, Some(3));
+        assert_eq!(cmp.flagged.avg_distinct_endpoints, 3.0);
+        assert!((cmp.flagged.status_mix.server_error_5xx - 66.666).abs() < 0.01);
+
+        assert_eq!(cmp.others.ips, 2);
+        assert_eq!(cmp.others.requests_per_ip, 1.0);
+        assert_eq!(cmp.others.peak_hour, Some(14));
+        assert_eq!(cmp.others.status_mix.success_2xx, 50.0);
+        assert_eq!(cmp.others.avg_distinct_endpoints, 1.0);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::net::IpAddr;
+
+/// Bytes per billed gigabyte; cloud egress is priced in decimal units
+const BYTES_PER_GB: f64 = 1_000_000_000.0;
+
+/// Unit prices used to estimate what the logged traffic costs
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct UnitCosts {
+    pub per_gb_egress: f64,
+    pub per_million_requests: f64,
+}
+
+/// Estimated cost of one endpoint, tenant or the whole log
+#[derive(Debug, Clone, Serialize)]
+pub struct CostLine {
+    pub key: String,
+    pub requests: usize,
+    pub bytes: u64,
+    pub egress_cost: f64,
+    pub request_cost: f64,
+    pub total_cost: f64,
+}
+
+impl CostLine {
+    fn new(key: String, requests: usize, bytes: u64, costs: &UnitCosts) -> Self {
+        let egress_cost = bytes as f64 / BYTES_PER_GB * costs.per_gb_egress;
+        let request_cost = requests as f64 / 1_000_000.0 * costs.per_million_requests;
+        CostLine {
+            key,
+            requests,
+            bytes,
+            egress_cost,
+            request_cost,
+            total_cost: egress_cost + request_cost,
+        }
+    }
+}
+
+/// Estimated cost broken down by endpoint and by tenant (client IP)
+#[derive(Debug, Clone, Serialize)]
+pub struct CostAttribution {
+    pub unit_costs: UnitCosts,
+    pub total: CostLine,
+    /// Requests whose line carried no response size; they only incur request cost
+    pub requests_without_bytes: usize,
+    pub top_endpoints: Vec<CostLine>,
+    pub top_tenants: Vec<CostLine>,
+}
+
+/// Attribute estimated egress and request costs to endpoints and tenants
+pub fn attribute(entries: &[LogEntry], costs: UnitCosts, top_n: usize) -> CostAttribution {
+    let mut endpoints: HashMap<&str, (usize, u64)> = HashMap::new();
+    let mut tenants: HashMap<IpAddr, (usize, u64)> = HashMap::new();
+    let mut total_bytes = 0u64;
+    let mut without_bytes = 0usize;
+
+    for entry in entries {
+        let bytes = entry.bytes.unwrap_or_else(|| {
+            without_bytes += 1;
+            0
+        });
+        total_bytes += bytes;
+        let endpoint = endpoints.entry(entry.endpoint.as_str()).or_default();
+        endpoint.0 += 1;
+        endpoint.1 += bytes;
+        let tenant = tenants.entry(entry.ip).or_default();
+        tenant.0 += 1;
+        tenant.1 += bytes;
+    }
+
+    CostAttribution {
+        unit_costs: costs,
+        total: CostLine::new("total".to_string(), entries.len(), total_bytes, &costs),
+        requests_without_bytes: without_bytes,
+        top_endpoints: rank(endpoints, &costs, top_n),
+        top_tenants: rank(tenants, &costs, top_n),
+    }
+}
+
+/// Most expensive keys first; ties broken by key for stable output
+fn rank<K: Ord + Display>(usage: HashMap<K, (usize, u64)>, costs: &UnitCosts, top_n: usize) -> Vec<CostLine> {
+    let mut lines: Vec<(K, CostLine)> = usage
+        .into_iter()
+        .map(|(key, (requests, bytes))| {
+            let line = CostLine::new(key.to_string(), requests, bytes, costs);
+            (key, line)
+        })
+        .collect();
+    lines.sort_by(|a, b| b.1.total_cost.total_cmp(&a.1.total_cost).then_with(|| a.0.cmp(&b.0)));
+    lines.into_iter().take(top_n).map(|(_, line)| line).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_entry;
+
+    #[test]
+    fn attributes_egress_and_request_costs() {
+        let at = "2024-01-15T10:30:00Z";
+        let entries = vec![
+            LogEntry { bytes: Some(2_000_000_000), ..test_entry(at, "1.1.1.1", "/video", 200) },
+            LogEntry { bytes: Some(1000), ..test_entry(at, "1.1.1.1", "/api", 200) },
+            test_entry(at, "2.2.2.2", "/api", 200),
+        ];
+        let costs = UnitCosts {
+            per_gb_egress: 0.09,
+            per_million_requests: 1_000_000.0,
+        };
+        let result = attribute(&entries, costs, 10);
+
+        assert_eq!(result.requests_without_bytes, 1);
+        assert_eq!(result.total.requests, 3);
+        assert!((result.total.egress_cost - 0.18).abs() < 1e-6);
+        assert!((result.total.request_cost - 3.0).abs() < 1e-9);
+
+        // /api wins on request count despite /video's egress
+        assert_eq!(result.top_endpoints[0].key, "/api");
+        assert_eq!(result.top_endpoints[0].requests, 2);
+        assert_eq!(result.top_tenants[0].key, "1.1.1.1");
+        assert_eq!(result.top_tenants[0].bytes, 2_000_001_000);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::parser::{self, LogFormat};
+use serde::Serialize;
+use std::io::{self, BufRead};
+
+/// Lines read from the start of the input when detecting its format
+pub const SAMPLE_LINES: usize = 100;
+
+/// The candidate format that parsed the most lines of a sample
+#[derive(Debug, Clone, Serialize)]
+pub struct Detection {
+    /// Name of the winning candidate, e.g. `nginx-combined`
+    pub format: String,
+    /// Sample lines the format parsed
+    pub matched: usize,
+    /// Sample lines tried, excluding blank lines, W3C directives and CSV headers
+    pub sampled: usize,
+    /// The parser to read the whole input with
+    #[serde(skip)]
+    pub log_format: LogFormat,
+}
+
+impl Detection {
+    /// Percentage of sampled lines the format parsed
+    pub fn match_rate(&self) -> f64 {
+        if self.sampled == 0 {
+            0.0
+        } else {
+            self.matched as f64 / self.sampled as f64 * 100.0
+        }
+    }
+}
+
+/// Read up to `limit` lines from the start of `reader`, replacing invalid UTF-8
+pub fn sample_lines(mut reader: impl BufRead, limit: usize) -> io::Result<Vec<String>> {
+    let mut lines = Vec::new();
+    let mut buf = Vec::new();
+    while lines.len() < limit {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        let line = String::from_utf8_lossy(&buf);
+        lines.push(line.trim_end_matches(['\n', '\r']).to_string());
+    }
+    Ok(lines)
+}
+
+/// Try every candidate on `sample` and pick the one that parses the most lines.
+///
+/// Earlier candidates win ties. W3C `#` directives and CSV header rows set up
+/// their candidate's columns as they would during ingestion. Returns `None` when no candidate
+/// parses a single line.
+pub fn detect(sample: &[String], candidates: impl IntoIterator<Item = (String, LogFormat)>) -> Option<Detection> {
+    let mut best: Option<Detection> = None;
+    for (name, format) in candidates {
+        let mut parsing = format.clone();
+        let (mut matched, mut sampled) = (0, 0);
+        for line in sample {
+            let directive = match &mut parsing {
+                LogFormat::W3c(fields) => fields.read_directive(line),
+                LogFormat::Csv(columns) => columns.read_header(line),
+                _ => false,
+            };
+            if directive || line.trim().is_empty() {
+                continue;
+            }
+            sampled += 1;
+            if parser::parse_line(line, &parsing).is_ok() {
+                matched += 1;
+            }
+        }
+        if matched > best.as_ref().map_or(0, |b| b.matched) {
+            best = Some(Detection {
+                format: name,
+                matched,
+                sampled,
+                log_format: format,
+            });
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::W3cFields;
+
+    fn candidates() -> Vec<(String, LogFormat)> {
+        vec![
+            ("native".to_string(), LogFormat::Native),
+            ("clf".to_string(), LogFormat::Clf),
+            ("nginx-combined".to_string(), LogFormat::NginxCombined),
+            ("iis".to_string(), LogFormat::W3c(W3cFields::iis())),
+        ]
+    }
+
+    fn sample(text: &str) -> Vec<String> {
+        sample_lines(text.as_bytes(), SAMPLE_LINES).unwrap()
+    }
+
+    #[test]
+    fn picks_the_format_with_most_matches() {
+        let lines = sample(
+            "10.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \"GET / HTTP/1.1\" 200 5 \"-\" \"curl/8.4.0\"\n\
+             10.0.0.2 - - [10/Oct/2000:13:55:37 +0000] \"GET /a HTTP/1.1\" 404 0 \"-\" \"-\"\n\
+             not a log line\n",
+        );
+        let detection = detect(&lines, candidates()).unwrap();
+        assert_eq!(detection.format, "nginx-combined");
+        assert_eq!((detection.matched, detection.sampled), (2, 3));
+        assert_eq!(detection.log_format, LogFormat::NginxCombined);
+    }
+
+    #[test]
+    fn reads_w3c_directives_before_data() {
+        let lines = sample(
+            "#Version: 1.0\n\
+             #Fields: date time c-ip cs-method cs-uri-stem sc-status\n\
+             2024-01-15 10:30:00 1.2.3.4 GET / 200\n",
+        );
+        let detection = detect(&lines, candidates()).unwrap();
+        assert_eq!(detection.format, "iis");
+        assert_eq!(detection.match_rate(), 100.0);
+    }
+
+    #[test]
+    fn none_when_nothing_parses() {
+        assert!(detect(&sample("hello\nworld\n"), candidates()).is_none());
+        assert_eq!(sample_lines("a\nb\nc\n".as_bytes(), 2).unwrap(), ["a", "b"]);
+    }
+}
@@ -0,0 +1,303 @@
+use crate::ingest::LiveLines;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+
+/// Where the Docker daemon listens unless `DOCKER_HOST` names another Unix socket
+pub const DEFAULT_SOCKET: &str = "/var/run/docker.sock";
+
+/// Environment variable the Docker CLI also reads the daemon's address from
+pub const HOST_ENV_VAR: &str = "DOCKER_HOST";
+
+/// The Docker daemon's socket: `DOCKER_HOST` if it is a `unix://` address, else
+/// [`DEFAULT_SOCKET`]
+pub fn socket_path() -> PathBuf {
+    std::env::var(HOST_ENV_VAR)
+        .ok()
+        .and_then(|host| host.strip_prefix("unix://").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SOCKET))
+}
+
+/// Stream the stdout and stderr of `container` (a name or ID) from the Docker daemon
+/// listening on `socket` (`--docker --container`): everything the container has
+/// logged so far, then, if `follow`, each new line until the container stops.
+///
+/// Lines are handed over as the records Docker's `json-file` driver writes, e.g.
+///   {"log":"GET / 200\n","stream":"stdout","time":"2024-01-15T10:30:00.123456789Z"}
+/// for [`crate::parser::LogFormat::Docker`], whatever log driver the container uses,
+/// as long as the daemon can read it back. An unknown container, or any other answer
+/// than the log, is an error here rather than while reading.
+pub fn container_logs(socket: &Path, container: &str, follow: bool) -> io::Result<LiveLines> {
+    if container.is_empty() || !container.chars().all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c)) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("not a container name or ID: {}", container)));
+    }
+    let mut stream = UnixStream::connect(socket)
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot connect to the Docker daemon at {}: {}", socket.display(), e)))?;
+    write!(
+        stream,
+        "GET /containers/{}/logs?stdout=1&stderr=1&timestamps=1{} HTTP/1.1\r\nHost: docker\r\nConnection: close\r\n\r\n",
+        container,
+        if follow { "&follow=1" } else { "" }
+    )?;
+
+    let mut reader = BufReader::new(stream);
+    let head = ResponseHead::read(&mut reader)?;
+    let mut body: Box<dyn Read + Send> = if head.chunked {
+        Box::new(ChunkedReader::new(reader))
+    } else {
+        Box::new(reader)
+    };
+    if head.status != 200 {
+        let mut answer = String::new();
+        body.read_to_string(&mut answer)?;
+        // The daemon explains errors as {"message": "No such container: web"}
+        let message = serde_json::from_str::<serde_json::Value>(&answer)
+            .ok()
+            .and_then(|v| v.get("message")?.as_str().map(String::from))
+            .unwrap_or(answer);
+        return Err(io::Error::other(format!("Docker answered {}: {}", head.status, message.trim())));
+    }
+
+    let (sender, lines) = mpsc::channel();
+    std::thread::spawn(move || {
+        let read = if head.multiplexed {
+            read_multiplexed(body, &sender)
+        } else {
+            read_raw(BufReader::new(body), &sender)
+        };
+        if let Err(e) = read {
+            let _ = sender.send(Err(e));
+        }
+    });
+    Ok(LiveLines::new(lines, follow))
+}
+
+/// What the log response starts with
+struct ResponseHead {
+    status: u16,
+    chunked: bool,
+    /// Whether stdout and stderr come in frames, rather than as the raw output of a
+    /// container with a TTY
+    multiplexed: bool,
+}
+
+impl ResponseHead {
+    fn read(reader: &mut impl BufRead) -> io::Result<Self> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Docker sent {}", what));
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let status = line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| invalid("no HTTP status"))?;
+        let mut head = ResponseHead {
+            status,
+            chunked: false,
+            multiplexed: true,
+        };
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(invalid("an incomplete HTTP response"));
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                return Ok(head);
+            }
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim().to_ascii_lowercase();
+            match name.to_ascii_lowercase().as_str() {
+                "transfer-encoding" => head.chunked = value.contains("chunked"),
+                "content-type" => head.multiplexed = !value.starts_with("application/vnd.docker.raw-stream"),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// An HTTP body sent in chunks, read as the data in them
+struct ChunkedReader<R> {
+    inner: R,
+    /// Bytes left in the current chunk
+    left: usize,
+    done: bool,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    fn new(inner: R) -> Self {
+        ChunkedReader { inner, left: 0, done: false }
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.left == 0 {
+            let mut size = String::new();
+            if self.inner.read_line(&mut size)? == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+            // Each chunk's data is followed by a CRLF of its own, before the next size
+            if size.trim().is_empty() {
+                size.clear();
+                self.inner.read_line(&mut size)?;
+            }
+            let size = size.trim().split(';').next().unwrap_or_default();
+            self.left = usize::from_str_radix(size, 16)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("Docker sent a bad chunk size: {}", size)))?;
+            if self.left == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+        }
+        let want = buf.len().min(self.left);
+        let n = self.inner.read(&mut buf[..want])?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.left -= n;
+        Ok(n)
+    }
+}
+
+/// Read the frames of a multiplexed log: an 8-byte header (the stream, 1 for stdout
+/// or 2 for stderr, then the size as 4 big-endian bytes at the end) before each part
+fn read_multiplexed(mut body: impl Read, sender: &Sender<io::Result<Vec<u8>>>) -> io::Result<()> {
+    let mut partial = [Vec::new(), Vec::new()];
+    let mut header = [0; 8];
+    loop {
+        match body.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+        let size = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let mut frame = vec![0; size];
+        body.read_exact(&mut frame)?;
+        let (stream, name) = match header[0] {
+            2 => (1, "stderr"),
+            _ => (0, "stdout"),
+        };
+        partial[stream].extend_from_slice(&frame);
+        let records = take_records(&mut partial[stream], name);
+        if !records.is_empty() && sender.send(Ok(records)).is_err() {
+            return Ok(());
+        }
+    }
+    // A last line without a newline
+    for (stream, name) in [(0, "stdout"), (1, "stderr")] {
+        if !partial[stream].is_empty() {
+            partial[stream].push(b'\n');
+            let _ = sender.send(Ok(take_records(&mut partial[stream], name)));
+        }
+    }
+    Ok(())
+}
+
+/// Read the output of a container with a TTY, where stdout and stderr are one stream
+fn read_raw(mut body: impl BufRead, sender: &Sender<io::Result<Vec<u8>>>) -> io::Result<()> {
+    let mut line = Vec::new();
+    while body.read_until(b'\n', &mut line)? > 0 {
+        if !line.ends_with(b"\n") {
+            line.push(b'\n');
+        }
+        if sender.send(Ok(take_records(&mut line, "stdout"))).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Turn the whole lines at the start of `output`, each after its timestamp, into
+/// Docker JSON records, leaving any unfinished line in `output`
+fn take_records(output: &mut Vec<u8>, stream: &str) -> Vec<u8> {
+    let Some(end) = output.iter().rposition(|&b| b == b'\n') else {
+        return Vec::new();
+    };
+    let mut records = Vec::new();
+    for line in output.drain(..=end).as_slice().split_inclusive(|&b| b == b'\n') {
+        let line = String::from_utf8_lossy(line);
+        let (time, log) = line.split_once(' ').unwrap_or(("", &line));
+        let mut record = serde_json::json!({ "log": log, "stream": stream });
+        if !time.is_empty() {
+            record["time"] = time.into();
+        }
+        records.extend_from_slice(record.to_string().as_bytes());
+        records.push(b'\n');
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    /// A daemon on a socket in `dir` that answers one request with `response`
+    fn daemon(dir: &Path, response: Vec<u8>) -> PathBuf {
+        let socket = dir.join("docker.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while request.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            stream.write_all(&response).unwrap();
+        });
+        socket
+    }
+
+    fn frame(stream: u8, data: &str) -> Vec<u8> {
+        let mut frame = vec![stream, 0, 0, 0];
+        frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        frame.extend_from_slice(data.as_bytes());
+        frame
+    }
+
+    #[test]
+    fn streams_container_output_as_docker_records() {
+        let mut body = frame(1, "2024-01-15T10:30:00.5Z GET / 200\n2024-01-15T10:30:01Z GET /a");
+        body.extend(frame(2, "2024-01-15T10:30:02Z oops\n"));
+        body.extend(frame(1, "pi 404\n"));
+        let mut response = b"HTTP/1.1 200 OK\r\nContent-Type: application/vnd.docker.multiplexed-stream\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        // Chunk boundaries need not fall on frame boundaries
+        for chunk in body.chunks(10) {
+            response.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+            response.extend_from_slice(chunk);
+            response.extend_from_slice(b"\r\n");
+        }
+        response.extend_from_slice(b"0\r\n\r\n");
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket = daemon(dir.path(), response);
+        let lines: Vec<String> = container_logs(&socket, "web", false).unwrap().lines().collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            lines,
+            [
+                r#"{"log":"GET / 200\n","stream":"stdout","time":"2024-01-15T10:30:00.5Z"}"#,
+                r#"{"log":"oops\n","stream":"stderr","time":"2024-01-15T10:30:02Z"}"#,
+                r#"{"log":"GET /api 404\n","stream":"stdout","time":"2024-01-15T10:30:01Z"}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_what_the_daemon_objects_to() {
+        let body = r#"{"message":"No such container: web"}"#;
+        let response = format!("HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+        let dir = tempfile::tempdir().unwrap();
+        let socket = daemon(dir.path(), response.into_bytes());
+        let error = container_logs(&socket, "web", false).err().unwrap();
+        assert_eq!(error.to_string(), "Docker answered 404: No such container: web");
+        assert!(container_logs(&socket, "../images", false).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use crate::analyzer::RankedItem;
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Requests an endpoint needs before its error rate is ranked, unless
+/// `--min-endpoint-requests` says otherwise
+pub const DEFAULT_MIN_REQUESTS: usize = 10;
+
+/// Server errors of one endpoint as a share of its own traffic
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointErrorRate {
+    pub endpoint: String,
+    pub requests: usize,
+    /// Responses with a 5xx status
+    pub server_errors: usize,
+    pub error_rate: f64,
+}
+
+/// Endpoints ranked by the share of their requests that got a 5xx, and by their
+/// number of 4xx and of 5xx responses, for logs with a status code
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointErrorReport {
+    /// Responses with a 4xx status
+    pub client_errors: usize,
+    /// Responses with a 5xx status
+    pub server_errors: usize,
+    /// Endpoints with the most 4xx responses, whatever the level logged; the share is
+    /// of all 4xx responses
+    pub top_4xx: Vec<RankedItem>,
+    /// Endpoints with the most 5xx responses, likewise
+    pub top_5xx: Vec<RankedItem>,
+    /// Requests an endpoint needed to be ranked
+    pub min_requests: usize,
+    /// Endpoints with at least `min_requests` requests
+    pub ranked: usize,
+    /// Endpoints left out for having fewer
+    pub below_floor: usize,
+    /// The ranked endpoints that returned any 5xx, highest rate first
+    pub endpoints: Vec<EndpointErrorRate>,
+}
+
+/// Requests, 4xx and 5xx responses of one endpoint
+#[derive(Default)]
+struct Tally {
+    requests: usize,
+    client_errors: usize,
+    server_errors: usize,
+}
+
+/// Rank endpoints by their number of 4xx and of 5xx responses, and those with at
+/// least `min_requests` requests by their 5xx rate; of equal rates the one with more
+/// errors comes first.
+///
+/// Returns `None` when no entry has a status code.
+pub fn analyze(entries: &[LogEntry], top_n: usize, min_requests: usize) -> Option<EndpointErrorReport> {
+    let mut endpoints: HashMap<&str, Tally> = HashMap::new();
+    for entry in entries {
+        let Some(code) = entry.status_code else { continue };
+        let tally = endpoints.entry(&entry.endpoint).or_default();
+        tally.requests += 1;
+        match code {
+            400..=499 => tally.client_errors += 1,
+            500.. => tally.server_errors += 1,
+            _ => {}
+        }
+    }
+    if endpoints.is_empty() {
+        return None;
+    }
+    let pct = |n: usize, of: usize| (n as f64 / of as f64 * 10000.0).round() / 100.0;
+
+    let client_errors = endpoints.values().map(|t| t.client_errors).sum();
+    let server_errors = endpoints.values().map(|t| t.server_errors).sum();
+    let top = |count: fn(&Tally) -> usize, total: usize| {
+        let mut ranked: Vec<(&str, usize)> =
+            endpoints.iter().map(|(&endpoint, tally)| (endpoint, count(tally))).filter(|&(_, n)| n > 0).collect();
+        ranked.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        ranked
+            .into_iter()
+            .take(top_n)
+            .map(|(endpoint, n)| RankedItem {
+                value: endpoint.to_string(),
+                count: n,
+                percentage: pct(n, total),
+                activity: None,
+            })
+            .collect()
+    };
+    let top_4xx = top(|t| t.client_errors, client_errors);
+    let top_5xx = top(|t| t.server_errors, server_errors);
+
+    let ranked = endpoints.values().filter(|t| t.requests >= min_requests).count();
+    let mut failing: Vec<EndpointErrorRate> = endpoints
+        .iter()
+        .filter(|(_, t)| t.requests >= min_requests && t.server_errors > 0)
+        .map(|(&endpoint, &Tally { requests, server_errors: errors, .. })| EndpointErrorRate {
+            endpoint: endpoint.to_string(),
+            requests,
+            server_errors: errors,
+            error_rate: pct(errors, requests),
+        })
+        .collect();
+    failing.sort_by(|a, b| {
+        b.error_rate
+            .total_cmp(&a.error_rate)
+            .then(b.server_errors.cmp(&a.server_errors))
+            .then_with(|| a.endpoint.cmp(&b.endpoint))
+    });
+    failing.truncate(top_n);
+
+    Some(EndpointErrorReport {
+        client_errors,
+        server_errors,
+        top_4xx,
+        top_5xx,
+        min_requests,
+        ranked,
+        below_floor: endpoints.len() - ranked,
+        endpoints: failing,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_entry;
+
+    fn entries(requests: &[(&str, u16, usize)]) -> Vec<LogEntry> {
+        let mut entries = Vec::new();
+        for &(endpoint, status, times) in requests {
+            entries.extend(std::iter::repeat_n(test_entry("2024-01-15T10:30:00Z", "10.0.0.1", endpoint, status), times));
+        }
+        entries
+    }
+
+    #[test]
+    fn ranks_endpoints_by_their_own_5xx_share() {
+        let entries = entries(&[
+            ("/api", 200, 90),
+            ("/api", 503, 10),
+            ("/pay", 200, 3),
+            ("/pay", 500, 2),
+            ("/once", 500, 1),
+            ("/ok", 404, 6),
+            ("/ok", 200, 6),
+        ]);
+        let report = analyze(&entries, 10, 5).unwrap();
+        assert_eq!((report.ranked, report.below_floor), (3, 1));
+        let rates: Vec<_> = report.endpoints.iter().map(|e| (e.endpoint.as_str(), e.server_errors, e.error_rate)).collect();
+        // /api has the most errors but the lowest rate; /once is under the floor, and
+        // 4xx responses are not server errors
+        assert_eq!(rates, [("/pay", 2, 40.0), ("/api", 10, 10.0)]);
+
+        let top: Vec<_> = report.top_5xx.iter().map(|e| (e.value.as_str(), e.count, e.percentage)).collect();
+        assert_eq!(top, [("/api", 10, 76.92), ("/pay", 2, 15.38), ("/once", 1, 7.69)]);
+        let top: Vec<_> = report.top_4xx.iter().map(|e| (e.value.as_str(), e.count)).collect();
+        assert_eq!(top, [("/ok", 6)]);
+        assert_eq!((report.client_errors, report.server_errors), (6, 13));
+
+        let floorless = analyze(&entries, 1, 1).unwrap();
+        assert_eq!(floorless.endpoints[0].endpoint, "/once");
+        assert_eq!(floorless.endpoints.len(), 1);
+    }
+
+    #[test]
+    fn needs_status_codes() {
+        let mut entries = entries(&[("/api", 500, 2)]);
+        for entry in &mut entries {
+            entry.status_code = None;
+        }
+        assert!(analyze(&entries, 10, 1).is_none());
+    }
+}
@@ -0,0 +1,191 @@
+use crate::parser::LogEntry;
+use chrono::{DateTime, Timelike, Utc};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::net::IpAddr;
+
+/// Engineered per-IP features for training anomaly-detection models
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpFeatures {
+    pub ip: IpAddr,
+    pub requests: usize,
+    pub distinct_endpoints: usize,
+    /// Shannon entropy (bits) of the endpoint distribution; low for scripted clients
+    pub endpoint_entropy: f64,
+    pub client_error_rate: f64,
+    pub server_error_rate: f64,
+    /// Seconds between the first and last request
+    pub active_seconds: i64,
+    pub mean_requests_per_minute: f64,
+    pub max_requests_per_minute: usize,
+    pub mean_interarrival_seconds: f64,
+    /// Share of requests in each 6-hour UTC block: 00-06, 06-12, 12-18, 18-24
+    pub time_of_day_share: [f64; 4],
+    /// Label: whether the IP exceeded the error threshold
+    pub flagged: bool,
+    /// Class-balancing weight, so flagged and unflagged rows contribute equally when sampled
+    pub sample_weight: f64,
+}
+
+const CSV_HEADER: &str = "ip,requests,distinct_endpoints,endpoint_entropy,client_error_rate,\
+server_error_rate,active_seconds,mean_requests_per_minute,max_requests_per_minute,\
+mean_interarrival_seconds,share_00_06,share_06_12,share_12_18,share_18_24,flagged,sample_weight";
+
+/// Build one feature row per IP, labeled by membership in `flagged`, ordered by IP
+pub fn extract(entries: &[LogEntry], flagged: &HashSet<IpAddr>) -> Vec<IpFeatures> {
+    let mut by_ip: HashMap<IpAddr, Vec<&LogEntry>> = HashMap::new();
+    for entry in entries {
+        by_ip.entry(entry.ip).or_default().push(entry);
+    }
+
+    let flagged_count = by_ip.keys().filter(|ip| flagged.contains(*ip)).count();
+    let weight_for = |is_flagged: bool| {
+        if flagged_count == 0 || flagged_count == by_ip.len() {
+            return 1.0;
+        }
+        let class_size = if is_flagged { flagged_count } else { by_ip.len() - flagged_count };
+        // Inverse class frequency, scaled so the weights sum to the row count
+        by_ip.len() as f64 / (2.0 * class_size as f64)
+    };
+
+    let mut rows: Vec<IpFeatures> = by_ip
+        .iter()
+        .map(|(ip, requests)| {
+            let is_flagged = flagged.contains(ip);
+            let mut row = features_for(*ip, requests);
+            row.flagged = is_flagged;
+            row.sample_weight = weight_for(is_flagged);
+            row
+        })
+        .collect();
+    rows.sort_by_key(|r| r.ip);
+    rows
+}
+
+fn features_for(ip: IpAddr, requests: &[&LogEntry]) -> IpFeatures {
+    let n = requests.len() as f64;
+
+    let mut endpoints: HashMap<&str, usize> = HashMap::new();
+    for entry in requests {
+        *endpoints.entry(entry.endpoint.as_str()).or_insert(0) += 1;
+    }
+    let endpoint_entropy = endpoints
+        .values()
+        .map(|&c| {
+            let p = c as f64 / n;
+            -p * p.log2()
+        })
+        .sum::<f64>()
+        .abs(); // a single endpoint sums to -0.0
+
+    let client_errors = requests.iter().filter(|e| matches!(e.status_code, Some(400..=499))).count();
+    let server_errors = requests.iter().filter(|e| matches!(e.status_code, Some(500..))).count();
+
+    let mut times: Vec<DateTime<Utc>> = requests.iter().map(|e| e.timestamp).collect();
+    times.sort();
+
+    let mut per_minute: HashMap<i64, usize> = HashMap::new();
+    let mut blocks = [0usize; 4];
+    for t in &times {
+        *per_minute.entry(t.timestamp() / 60).or_insert(0) += 1;
+        blocks[(t.hour() / 6) as usize] += 1;
+    }
+    let active_seconds = match (times.first(), times.last()) {
+        (Some(first), Some(last)) => (*last - *first).num_seconds(),
+        _ => 0,
+    };
+    let mean_interarrival_seconds = if times.len() > 1 {
+        active_seconds as f64 / (times.len() - 1) as f64
+    } else {
+        0.0
+    };
+    let timed = times.len().max(1) as f64;
+
+    IpFeatures {
+        ip,
+        requests: requests.len(),
+        distinct_endpoints: endpoints.len(),
+        endpoint_entropy,
+        client_error_rate: client_errors as f64 / n,
+        server_error_rate: server_errors as f64 / n,
+        active_seconds,
+        mean_requests_per_minute: if per_minute.is_empty() {
+            0.0
+        } else {
+            times.len() as f64 / per_minute.len() as f64
+        },
+        max_requests_per_minute: per_minute.values().copied().max().unwrap_or(0),
+        mean_interarrival_seconds,
+        time_of_day_share: blocks.map(|b| b as f64 / timed),
+        flagged: false,
+        sample_weight: 1.0,
+    }
+}
+
+/// Write feature rows as CSV with a header line
+pub fn write_csv<W: Write>(mut out: W, rows: &[IpFeatures]) -> io::Result<()> {
+    writeln!(out, "{}", CSV_HEADER)?;
+    for r in rows {
+        writeln!(
+            out,
+            "{},{},{},{:.4},{:.4},{:.4},{},{:.4},{},{:.4},{:.4},{:.4},{:.4},{:.4},{},{:.4}",
+            r.ip,
+            r.requests,
+            r.distinct_endpoints,
+            r.endpoint_entropy,
+            r.client_error_rate,
+            r.server_error_rate,
+            r.active_seconds,
+            r.mean_requests_per_minute,
+            r.max_requests_per_minute,
+            r.mean_interarrival_seconds,
+            r.time_of_day_share[0],
+            r.time_of_day_share[1],
+            r.time_of_day_share[2],
+            r.time_of_day_share[3],
+            u8::from(r.flagged),
+            r.sample_weight
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_entry;
+
+    #[test]
+    fn extracts_labeled_weighted_features() {
+        let entries = vec![
+            test_entry("2024-01-15T03:00:00Z", "9.9.9.9", "/login", 401),
+            test_entry("2024-01-15T03:00:30Z", "9.9.9.9", "/login", 401),
+            test_entry("2024-01-15T10:00:00Z", "1.1.1.1", "/a", 200),
+            test_entry("2024-01-15T13:00:00Z", "1.1.1.1", "/b", 500),
+            test_entry("2024-01-15T14:00:00Z", "2.2.2.2", "/a", 200),
+        ];
+        let rows = extract(&entries, &HashSet::from(["9.9.9.9".parse().unwrap()]));
+        assert_eq!(rows.iter().map(|r| r.ip.to_string()).collect::<Vec<_>>(), ["1.1.1.1", "2.2.2.2", "9.9.9.9"]);
+
+        let bot = &rows[2];
+        assert!(bot.flagged);
+        assert_eq!(bot.endpoint_entropy, 0.0);
+        assert_eq!(bot.client_error_rate, 1.0);
+        assert_eq!(bot.max_requests_per_minute, 2);
+        assert_eq!(bot.time_of_day_share, [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(bot.sample_weight, 1.5);
+
+        let human = &rows[0];
+        assert!(!human.flagged);
+        assert_eq!(human.endpoint_entropy, 1.0);
+        assert_eq!(human.server_error_rate, 0.5);
+        assert_eq!(human.active_seconds, 3 * 3600);
+        assert_eq!(human.sample_weight, 0.75);
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &rows).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 4);
+        assert!(csv.lines().nth(3).unwrap().starts_with("9.9.9.9,2,1,0.0000,1.0000,"));
+    }
+}
@@ -0,0 +1,223 @@
+use crate::parser::LogEntry;
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use serde::Serialize;
+
+/// Hours per seasonal cycle (daily seasonality)
+const SEASON: usize = 24;
+
+/// Hours forecast beyond the end of the log
+pub const HORIZON_HOURS: usize = 24;
+
+/// z-score for a ~95% confidence band
+const Z_95: f64 = 1.96;
+
+/// Smoothing parameters tried when fitting; the combination with the lowest
+/// one-step-ahead squared error wins
+const ALPHAS: [f64; 5] = [0.05, 0.2, 0.4, 0.6, 0.8];
+const BETAS: [f64; 3] = [0.01, 0.05, 0.2];
+const GAMMAS: [f64; 4] = [0.05, 0.2, 0.4, 0.6];
+
+/// Hourly request and error counts, one bucket per hour with no gaps
+#[derive(Debug, Clone)]
+pub struct HourlySeries {
+    pub start: DateTime<Utc>,
+    pub requests: Vec<f64>,
+    pub errors: Vec<f64>,
+}
+
+impl HourlySeries {
+    /// Bucket entries by UTC hour
+    pub fn from_entries(entries: &[LogEntry]) -> Option<Self> {
+        let hour = TimeDelta::hours(1);
+        let stamped: Vec<(DateTime<Utc>, bool)> = entries
+            .iter()
+            .filter_map(|e| {
+                let t = e.timestamp.duration_trunc(hour).ok()?;
+                Some((t, e.level.is_error()))
+            })
+            .collect();
+        let start = stamped.iter().map(|s| s.0).min()?;
+        let end = stamped.iter().map(|s| s.0).max()?;
+        let len = (end - start).num_hours() as usize + 1;
+
+        let mut series = HourlySeries {
+            start,
+            requests: vec![0.0; len],
+            errors: vec![0.0; len],
+        };
+        for (t, is_error) in stamped {
+            let i = (t - start).num_hours() as usize;
+            series.requests[i] += 1.0;
+            if is_error {
+                series.errors[i] += 1.0;
+            }
+        }
+        Some(series)
+    }
+}
+
+/// One forecast hour with its confidence band
+#[derive(Debug, Clone, Serialize)]
+pub struct ForecastPoint {
+    pub hour: DateTime<Utc>,
+    pub expected: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+/// Forecast of a single series over the horizon
+#[derive(Debug, Clone, Serialize)]
+pub struct SeriesForecast {
+    pub alpha: f64,
+    pub beta: f64,
+    pub gamma: f64,
+    pub total: f64,
+    pub total_lower: f64,
+    pub total_upper: f64,
+    pub hourly: Vec<ForecastPoint>,
+}
+
+/// Next-day request and error forecast
+#[derive(Debug, Clone, Serialize)]
+pub struct TrafficForecast {
+    pub history_hours: usize,
+    pub horizon_hours: usize,
+    pub requests: SeriesForecast,
+    pub errors: SeriesForecast,
+}
+
+/// Forecast the next 24 hours of requests and errors.
+///
+/// Needs at least two full days of history to estimate daily seasonality;
+/// returns `None` otherwise.
+pub fn forecast(entries: &[LogEntry]) -> Option<TrafficForecast> {
+    let series = HourlySeries::from_entries(entries)?;
+    let first_hour = series.start + TimeDelta::hours(series.requests.len() as i64);
+    Some(TrafficForecast {
+        history_hours: series.requests.len(),
+        horizon_hours: HORIZON_HOURS,
+        requests: forecast_series(&series.requests, first_hour)?,
+        errors: forecast_series(&series.errors, first_hour)?,
+    })
+}
+
+/// Result of running additive Holt-Winters over a series
+struct Fit {
+    level: f64,
+    trend: f64,
+    seasonal: Vec<f64>,
+    sse: f64,
+}
+
+/// Additive Holt-Winters with a 24-hour season, initialised from the first two days
+fn fit(y: &[f64], alpha: f64, beta: f64, gamma: f64) -> Fit {
+    let first: f64 = y[..SEASON].iter().sum::<f64>() / SEASON as f64;
+    let second: f64 = y[SEASON..2 * SEASON].iter().sum::<f64>() / SEASON as f64;
+    let mut level = first;
+    let mut trend = (second - first) / SEASON as f64;
+    let mut seasonal: Vec<f64> = y[..SEASON].iter().map(|v| v - first).collect();
+    let mut sse = 0.0;
+
+    for (t, &obs) in y.iter().enumerate().skip(SEASON) {
+        let s = seasonal[t % SEASON];
+        let predicted = level + trend + s;
+        sse += (obs - predicted).powi(2);
+
+        let prev_level = level;
+        level = alpha * (obs - s) + (1.0 - alpha) * (level + trend);
+        trend = beta * (level - prev_level) + (1.0 - beta) * trend;
+        seasonal[t % SEASON] = gamma * (obs - level) + (1.0 - gamma) * s;
+    }
+    Fit { level, trend, seasonal, sse }
+}
+
+fn forecast_series(y: &[f64], first_hour: DateTime<Utc>) -> Option<SeriesForecast> {
+    if y.len() < 2 * SEASON {
+        return None;
+    }
+
+    let mut best: Option<(f64, f64, f64, Fit)> = None;
+    for &alpha in &ALPHAS {
+        for &beta in &BETAS {
+            for &gamma in &GAMMAS {
+                let candidate = fit(y, alpha, beta, gamma);
+                if best.as_ref().is_none_or(|b| candidate.sse < b.3.sse) {
+                    best = Some((alpha, beta, gamma, candidate));
+                }
+            }
+        }
+    }
+    let (alpha, beta, gamma, model) = best?;
+
+    // Residual spread of the one-step-ahead predictions; the band widens with the
+    // horizon as level uncertainty accumulates (trend/season terms are ignored)
+    let sigma = (model.sse / (y.len() - SEASON) as f64).sqrt();
+    let mut hourly = Vec::with_capacity(HORIZON_HOURS);
+    let mut variance_sum = 0.0;
+    for h in 1..=HORIZON_HOURS {
+        let s = model.seasonal[(y.len() + h - 1) % SEASON];
+        let expected = (model.level + h as f64 * model.trend + s).max(0.0);
+        let variance = sigma.powi(2) * (1.0 + (h - 1) as f64 * alpha.powi(2));
+        variance_sum += variance;
+        let half_width = Z_95 * variance.sqrt();
+        hourly.push(ForecastPoint {
+            hour: first_hour + TimeDelta::hours(h as i64 - 1),
+            expected,
+            lower: (expected - half_width).max(0.0),
+            upper: expected + half_width,
+        });
+    }
+
+    let total: f64 = hourly.iter().map(|p| p.expected).sum();
+    let total_half_width = Z_95 * variance_sum.sqrt();
+    Some(SeriesForecast {
+        alpha,
+        beta,
+        gamma,
+        total,
+        total_lower: (total - total_half_width).max(0.0),
+        total_upper: total + total_half_width,
+        hourly,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn learns_daily_seasonality() {
+        // Three days where every hour h sees 10 + h requests
+        let y: Vec<f64> = (0..72).map(|i| 10.0 + (i % 24) as f64).collect();
+        let start = DateTime::parse_from_rfc3339("2024-01-04T00:00:00Z").unwrap().with_timezone(&Utc);
+        let f = forecast_series(&y, start).unwrap();
+
+        assert_eq!(f.hourly.len(), HORIZON_HOURS);
+        for (h, point) in f.hourly.iter().enumerate() {
+            assert!((point.expected - (10.0 + h as f64)).abs() < 0.5, "hour {}: {}", h, point.expected);
+            assert!(point.lower <= point.expected && point.expected <= point.upper);
+        }
+        assert!((f.total - (24.0 * 10.0 + 276.0)).abs() < 5.0);
+    }
+
+    #[test]
+    fn needs_two_days_of_history() {
+        let start = DateTime::parse_from_rfc3339("2024-01-04T00:00:00Z").unwrap().with_timezone(&Utc);
+        assert!(forecast_series(&[1.0; 47], start).is_none());
+    }
+
+    #[test]
+    fn buckets_entries_by_hour_without_gaps() {
+        let entries: Vec<LogEntry> = [
+            "2024-01-15T10:05:00Z [INFO] 1.1.1.1 GET / 200",
+            "2024-01-15T10:55:00Z [ERROR] 1.1.1.1 GET / 500",
+            "2024-01-15T13:00:00Z [INFO] 1.1.1.1 GET / 200",
+        ]
+        .iter()
+        .map(|l| crate::parser::parse_log_line(l).unwrap())
+        .collect();
+        let series = HourlySeries::from_entries(&entries).unwrap();
+        assert_eq!(series.requests, vec![2.0, 0.0, 0.0, 1.0]);
+        assert_eq!(series.errors, vec![1.0, 0.0, 0.0, 0.0]);
+    }
+}
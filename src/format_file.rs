@@ -0,0 +1,275 @@
+use crate::parser::{CustomPattern, LevelAliases, LogLevel, OPTIONAL_GROUPS, REQUIRED_GROUPS};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Where a field is read from: a 1-based column, or a regex capture group by number or name
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Source {
+    Index(usize),
+    Name(String),
+}
+
+/// A format definition as written in TOML
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FormatFile {
+    /// Line regex; fields default to the groups named after them
+    regex: Option<String>,
+    /// Column separator, used when there is no regex; whitespace when omitted
+    delimiter: Option<String>,
+    /// strftime layout of the timestamp field
+    timestamp_format: Option<String>,
+    #[serde(default)]
+    fields: HashMap<String, Source>,
+    /// Level spellings of the format and the levels they stand for
+    #[serde(default)]
+    levels: HashMap<String, String>,
+}
+
+/// Errors that can occur while loading a format definition file
+#[derive(Debug)]
+pub enum FormatFileError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    /// The file is valid TOML but does not describe a usable format
+    Invalid(String),
+}
+
+impl fmt::Display for FormatFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatFileError::Io(e) => write!(f, "{}", e),
+            FormatFileError::Toml(e) => write!(f, "invalid TOML: {}", e),
+            FormatFileError::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<std::io::Error> for FormatFileError {
+    fn from(e: std::io::Error) -> Self {
+        FormatFileError::Io(e)
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> FormatFileError {
+    FormatFileError::Invalid(msg.into())
+}
+
+/// Load a TOML format definition (`--format-file`)
+pub fn load_format_file(path: &Path) -> Result<CustomPattern, FormatFileError> {
+    parse_format_file(&fs::read_to_string(path)?)
+}
+
+/// Compile a TOML format definition into the pattern that parses its lines.
+///
+/// A definition either gives a `regex`, whose groups are mapped to fields by
+/// number or name, or splits lines on a `delimiter` and maps fields to 1-based
+/// columns:
+///
+/// ```toml
+/// delimiter = "|"
+/// timestamp_format = "%Y-%m-%d %H:%M:%S"
+///
+/// [fields]
+/// timestamp = 1
+/// ip = 3
+/// method = 4
+/// endpoint = 5
+/// status = 6
+///
+/// [levels]
+/// NOTICE = "info"
+/// CRIT = "fatal"
+/// ```
+///
+/// The optional `[levels]` table maps the format's own level spellings onto
+/// `trace`, `debug`, `info`, `warn`, `error` and `fatal`.
+pub fn parse_format_file(text: &str) -> Result<CustomPattern, FormatFileError> {
+    let def: FormatFile = toml::from_str(text).map_err(FormatFileError::Toml)?;
+
+    let mut fields = Vec::new();
+    for (name, source) in def.fields {
+        let Some(&field) = REQUIRED_GROUPS.iter().chain(&OPTIONAL_GROUPS).find(|f| **f == name) else {
+            return Err(invalid(format!(
+                "unknown field '{}' (expected one of: {}, {})",
+                name,
+                REQUIRED_GROUPS.join(", "),
+                OPTIONAL_GROUPS.join(", ")
+            )));
+        };
+        fields.push((field, source));
+    }
+
+    let (regex, groups) = match (def.regex, def.delimiter) {
+        (Some(_), Some(_)) => return Err(invalid("set either `regex` or `delimiter`, not both")),
+        (Some(pattern), None) => regex_groups(&pattern, fields)?,
+        (None, delimiter) => column_groups(delimiter.as_deref(), fields)?,
+    };
+    if let Some(missing) = REQUIRED_GROUPS.iter().find(|f| !groups.contains_key(*f)) {
+        return Err(invalid(format!("no source for required field '{}'", missing)));
+    }
+    let mut levels = LevelAliases::default();
+    for (alias, level) in def.levels {
+        let level: LogLevel = level.parse().map_err(|_| {
+            invalid(format!(
+                "level '{}' maps to unknown level '{}' (expected trace, debug, info, warn, error or fatal)",
+                alias, level
+            ))
+        })?;
+        levels.insert(&alias, level);
+    }
+    CustomPattern::with_groups(regex, groups, def.timestamp_format)
+        .map(|pattern| pattern.with_levels(levels))
+        .map_err(|e| invalid(e.to_string()))
+}
+
+/// Map fields onto the groups of a user regex, starting from same-named groups
+fn regex_groups(
+    pattern: &str,
+    fields: Vec<(&'static str, Source)>,
+) -> Result<(Regex, HashMap<&'static str, usize>), FormatFileError> {
+    let regex = Regex::new(pattern).map_err(|e| invalid(format!("invalid regex: {}", e)))?;
+    let mut groups: HashMap<&'static str, usize> = REQUIRED_GROUPS
+        .iter()
+        .chain(&OPTIONAL_GROUPS)
+        .filter_map(|&field| Some((field, regex.capture_names().position(|n| n == Some(field))?)))
+        .collect();
+    for (field, source) in fields {
+        let index = match source {
+            Source::Index(i) if (1..regex.captures_len()).contains(&i) => i,
+            Source::Index(i) => {
+                return Err(invalid(format!(
+                    "field '{}' refers to group {}, but the regex has {} groups",
+                    field,
+                    i,
+                    regex.captures_len() - 1
+                )))
+            }
+            Source::Name(name) => regex
+                .capture_names()
+                .position(|n| n == Some(name.as_str()))
+                .ok_or_else(|| invalid(format!("field '{}' refers to group '{}', which the regex does not define", field, name)))?,
+        };
+        groups.insert(field, index);
+    }
+    Ok((regex, groups))
+}
+
+/// Build a regex capturing each column up to the highest one a field uses;
+/// later columns are ignored
+fn column_groups(
+    delimiter: Option<&str>,
+    fields: Vec<(&'static str, Source)>,
+) -> Result<(Regex, HashMap<&'static str, usize>), FormatFileError> {
+    let mut groups = HashMap::new();
+    for (field, source) in fields {
+        match source {
+            Source::Index(i) if i >= 1 => {
+                groups.insert(field, i);
+            }
+            _ => {
+                return Err(invalid(format!(
+                    "field '{}' must be a column number (counting from 1) when lines are split on a delimiter",
+                    field
+                )))
+            }
+        }
+    }
+    let columns = groups.values().copied().max().unwrap_or(0);
+
+    let (column, separator) = match delimiter {
+        None => (r"(\S+)".to_string(), r"\s+".to_string()),
+        Some("") => return Err(invalid("`delimiter` must not be empty")),
+        Some(d) => (r"\s*(.*?)\s*".to_string(), regex::escape(d)),
+    };
+    let pattern = format!(
+        "^{}(?:{}.*)?$",
+        vec![column; columns].join(&separator),
+        separator
+    );
+    let regex = Regex::new(&pattern).map_err(|e| invalid(format!("invalid delimiter: {}", e)))?;
+    Ok((regex, groups))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_line, LogFormat};
+
+    fn parse(definition: &str, line: &str) -> crate::parser::LogEntry {
+        let pattern = parse_format_file(definition).unwrap();
+        parse_line(line, &LogFormat::Pattern(pattern)).unwrap()
+    }
+
+    #[test]
+    fn splits_delimited_columns() {
+        let definition = r#"
+            delimiter = "|"
+            timestamp_format = "%Y-%m-%d %H:%M:%S"
+
+            [fields]
+            timestamp = 1
+            ip = 3
+            method = 4
+            endpoint = 5
+            status = 6
+        "#;
+        let entry = parse(definition, "2024-01-15 10:30:00 | web1 | 10.0.0.1 | GET | /cart | 503 | extra");
+        assert_eq!(entry.timestamp.to_rfc3339(), "2024-01-15T10:30:00+00:00");
+        assert_eq!(entry.ip.to_string(), "10.0.0.1");
+        assert_eq!(entry.endpoint, "/cart");
+        assert_eq!(entry.status_code, Some(503));
+    }
+
+    #[test]
+    fn maps_regex_groups_by_number_and_name() {
+        let definition = r#"
+            regex = '^(\S+) (?P<addr>\S+) (\w+) (?P<endpoint>\S+) (\d{3})$'
+
+            [fields]
+            timestamp = 1
+            ip = "addr"
+            method = 3
+            status = 5
+        "#;
+        let entry = parse(definition, "2024-01-15T10:30:00Z 10.0.0.1 post /login 401");
+        assert_eq!(entry.ip.to_string(), "10.0.0.1");
+        assert_eq!(entry.endpoint, "/login");
+        assert_eq!(entry.status_code, Some(401));
+    }
+
+    #[test]
+    fn whitespace_columns_by_default() {
+        let definition = "[fields]\ntimestamp = 1\nip = 2\nmethod = 3\nendpoint = 4\nstatus = 5\nbytes = 6\n";
+        let entry = parse(definition, "2024-01-15T10:30:00Z  10.0.0.1 GET /   200 512");
+        assert_eq!(entry.bytes, Some(512));
+    }
+
+    #[test]
+    fn maps_level_spellings() {
+        let fields = "[fields]\ntimestamp = 1\nlevel = 2\nip = 3\nmethod = 4\nendpoint = 5\nstatus = 6\n";
+        let entry = parse(
+            &format!("{}[levels]\nSEVERE = \"error\"\n", fields),
+            "2024-01-15T10:30:00Z severe 10.0.0.1 GET / 200",
+        );
+        assert_eq!(entry.level, LogLevel::Error);
+        let err = parse_format_file(&format!("{}[levels]\nI = \"informational\"\n", fields)).unwrap_err();
+        assert!(err.to_string().contains("unknown level 'informational'"));
+    }
+
+    #[test]
+    fn rejects_unusable_definitions() {
+        let err = |definition: &str| parse_format_file(definition).unwrap_err().to_string();
+        assert!(err("delimiter = \",\"\n[fields]\nip = 1\nhost = 2\n").contains("unknown field 'host'"));
+        assert!(err("delimiter = \",\"\n[fields]\nip = 1\n").contains("required field 'timestamp'"));
+        assert!(err("regex = '(a)'\ndelimiter = ','\n").contains("not both"));
+        assert!(err("[fields]\nip = \"addr\"\n").contains("column number"));
+        assert!(err("regex = '(a)'\n[fields]\nip = 2\n").contains("regex has 1 groups"));
+        assert!(err("delimter = ','\n").starts_with("invalid TOML"));
+    }
+}
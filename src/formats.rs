@@ -0,0 +1,438 @@
+use crate::parser::{CsvColumns, JsonKeys, LogFormat, W3cFields};
+
+/// Command-line settings that some presets read
+#[derive(Debug, Clone, Default)]
+pub struct FormatOptions {
+    /// Where JSON, logfmt and CSV fields are found (`--json-key`)
+    pub keys: JsonKeys,
+    /// CSV and TSV column names in order (`--columns`); empty to read them from a header row
+    pub columns: Vec<String>,
+}
+
+/// A named log format that `--format` accepts.
+///
+/// Several presets may share a parser (Apache and gunicorn write nginx's
+/// `combined` layout by default); only one of them is tried by `--format auto`.
+pub trait Preset: Sync {
+    /// Canonical name, as passed to `--format` and shown when detected
+    fn name(&self) -> &'static str;
+
+    /// Other names `--format` accepts for this preset
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// One-line summary for `log_analyzer formats`
+    fn description(&self) -> &'static str;
+
+    /// Whether `--format auto` tries this preset
+    fn detectable(&self) -> bool {
+        true
+    }
+
+    /// The parser for this format, configured by `options` where it applies
+    fn log_format(&self, options: &FormatOptions) -> LogFormat;
+}
+
+pub struct Native;
+
+impl Preset for Native {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn description(&self) -> &'static str {
+        "TIMESTAMP [LEVEL] IP METHOD ENDPOINT STATUS [BYTES]"
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::Native
+    }
+}
+
+pub struct Clf;
+
+impl Preset for Clf {
+    fn name(&self) -> &'static str {
+        "clf"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["common", "apache-common"]
+    }
+
+    fn description(&self) -> &'static str {
+        "Common Log Format (%h %l %u %t \"%r\" %>s %b)"
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::Clf
+    }
+}
+
+pub struct NginxCombined;
+
+impl Preset for NginxCombined {
+    fn name(&self) -> &'static str {
+        "nginx-combined"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["nginx", "combined"]
+    }
+
+    fn description(&self) -> &'static str {
+        "nginx combined: CLF plus \"referrer\" \"user agent\""
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::NginxCombined
+    }
+}
+
+pub struct Apache;
+
+impl Preset for Apache {
+    fn name(&self) -> &'static str {
+        "apache"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["apache-combined"]
+    }
+
+    fn description(&self) -> &'static str {
+        "Apache httpd's combined LogFormat (same layout as nginx-combined)"
+    }
+
+    fn detectable(&self) -> bool {
+        false
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::NginxCombined
+    }
+}
+
+pub struct Gunicorn;
+
+impl Preset for Gunicorn {
+    fn name(&self) -> &'static str {
+        "gunicorn"
+    }
+
+    fn description(&self) -> &'static str {
+        "gunicorn's default access_log_format (same layout as nginx-combined)"
+    }
+
+    fn detectable(&self) -> bool {
+        false
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::NginxCombined
+    }
+}
+
+pub struct Alb;
+
+impl Preset for Alb {
+    fn name(&self) -> &'static str {
+        "alb"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["elb"]
+    }
+
+    fn description(&self) -> &'static str {
+        "AWS Application/Classic Load Balancer access logs"
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::Alb
+    }
+}
+
+pub struct S3Access;
+
+impl Preset for S3Access {
+    fn name(&self) -> &'static str {
+        "s3-access"
+    }
+
+    fn description(&self) -> &'static str {
+        "Amazon S3 server access logs"
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::S3Access
+    }
+}
+
+pub struct Envoy;
+
+impl Preset for Envoy {
+    fn name(&self) -> &'static str {
+        "envoy"
+    }
+
+    fn description(&self) -> &'static str {
+        "Envoy default access logs"
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::Envoy
+    }
+}
+
+pub struct Cloudfront;
+
+impl Preset for Cloudfront {
+    fn name(&self) -> &'static str {
+        "cloudfront"
+    }
+
+    fn description(&self) -> &'static str {
+        "CloudFront standard logs (tab-separated, laid out by their #Fields: header)"
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::W3c(W3cFields::cloudfront())
+    }
+}
+
+pub struct Iis;
+
+impl Preset for Iis {
+    fn name(&self) -> &'static str {
+        "iis"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["w3c"]
+    }
+
+    fn description(&self) -> &'static str {
+        "IIS W3C extended logs (laid out by their #Fields: header)"
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::W3c(W3cFields::iis())
+    }
+}
+
+pub struct Caddy;
+
+impl Preset for Caddy {
+    fn name(&self) -> &'static str {
+        "caddy"
+    }
+
+    fn description(&self) -> &'static str {
+        "Caddy structured JSON access logs"
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::Caddy
+    }
+}
+
+pub struct Heroku;
+
+impl Preset for Heroku {
+    fn name(&self) -> &'static str {
+        "heroku"
+    }
+
+    fn description(&self) -> &'static str {
+        "Heroku router logs (heroku[router]: at=info method=... fwd=...)"
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::Heroku
+    }
+}
+
+pub struct Traefik;
+
+impl Preset for Traefik {
+    fn name(&self) -> &'static str {
+        "traefik"
+    }
+
+    fn description(&self) -> &'static str {
+        "Traefik access logs, common or JSON (with router and service names)"
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::Traefik
+    }
+}
+
+pub struct Cef;
+
+impl Preset for Cef {
+    fn name(&self) -> &'static str {
+        "cef"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["arcsight"]
+    }
+
+    fn description(&self) -> &'static str {
+        "ArcSight Common Event Format from firewalls and WAFs (CEF:0|...|src=... request=...)"
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::Cef
+    }
+}
+
+pub struct Gelf;
+
+impl Preset for Gelf {
+    fn name(&self) -> &'static str {
+        "gelf"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["graylog"]
+    }
+
+    fn description(&self) -> &'static str {
+        "Graylog GELF messages, one per line, with the request in _http_* fields"
+    }
+
+    fn log_format(&self, _: &FormatOptions) -> LogFormat {
+        LogFormat::Gelf
+    }
+}
+
+pub struct Csv;
+
+impl Preset for Csv {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn description(&self) -> &'static str {
+        "Comma-separated rows, laid out by --columns or a header row"
+    }
+
+    fn log_format(&self, options: &FormatOptions) -> LogFormat {
+        LogFormat::Csv(CsvColumns::new(b',', &options.columns, options.keys.clone()))
+    }
+}
+
+pub struct Tsv;
+
+impl Preset for Tsv {
+    fn name(&self) -> &'static str {
+        "tsv"
+    }
+
+    fn description(&self) -> &'static str {
+        "Tab-separated rows, laid out by --columns or a header row"
+    }
+
+    fn log_format(&self, options: &FormatOptions) -> LogFormat {
+        LogFormat::Csv(CsvColumns::new(b'\t', &options.columns, options.keys.clone()))
+    }
+}
+
+pub struct Json;
+
+impl Preset for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["jsonl"]
+    }
+
+    fn description(&self) -> &'static str {
+        "One JSON object per line (see --json-key)"
+    }
+
+    fn log_format(&self, options: &FormatOptions) -> LogFormat {
+        LogFormat::Json(options.keys.clone())
+    }
+}
+
+pub struct Logfmt;
+
+impl Preset for Logfmt {
+    fn name(&self) -> &'static str {
+        "logfmt"
+    }
+
+    fn description(&self) -> &'static str {
+        "key=value pairs per line (see --json-key)"
+    }
+
+    fn log_format(&self, options: &FormatOptions) -> LogFormat {
+        LogFormat::Logfmt(options.keys.clone())
+    }
+}
+
+/// Every preset, in the order `--format auto` tries them (earlier ones win ties)
+static PRESETS: [&dyn Preset; 19] = [
+    &Native,
+    &Clf,
+    &NginxCombined,
+    &Apache,
+    &Gunicorn,
+    &Alb,
+    &S3Access,
+    &Envoy,
+    &Cloudfront,
+    &Iis,
+    &Caddy,
+    &Heroku,
+    &Traefik,
+    &Cef,
+    &Gelf,
+    &Csv,
+    &Tsv,
+    &Json,
+    &Logfmt,
+];
+
+/// Every preset, in detection order
+pub fn all() -> &'static [&'static dyn Preset] {
+    &PRESETS
+}
+
+/// The preset called `name`, by its canonical name or an alias
+pub fn find(name: &str) -> Option<&'static dyn Preset> {
+    PRESETS
+        .iter()
+        .copied()
+        .find(|preset| preset.name() == name || preset.aliases().contains(&name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_aliases_are_unique() {
+        let mut names: Vec<&str> = all().iter().flat_map(|p| std::iter::once(p.name()).chain(p.aliases().iter().copied())).collect();
+        let count = names.len();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), count);
+        assert!(!names.contains(&"auto"));
+    }
+
+    #[test]
+    fn finds_presets_by_alias() {
+        assert_eq!(find("nginx").unwrap().name(), "nginx-combined");
+        assert_eq!(find("elb").unwrap().name(), "alb");
+        assert_eq!(find("gunicorn").unwrap().log_format(&FormatOptions::default()), LogFormat::NginxCombined);
+        assert!(find("goaccess").is_none());
+    }
+}
@@ -0,0 +1,271 @@
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// The two bytes that open every chunk of a chunked GELF message
+pub const CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+
+/// Most chunks a GELF message may be split into
+pub const MAX_CHUNKS: u8 = 128;
+
+/// How long the chunks of one message may take to arrive before it is dropped
+pub const CHUNK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest UDP payload
+const MAX_DATAGRAM: usize = 65_535;
+
+/// Why a received datagram did not yield a message
+#[derive(Debug)]
+pub enum GelfError {
+    /// A chunk header with a zero or out-of-range sequence count or number
+    InvalidChunk(String),
+    /// A gzip or zlib payload that failed to decompress
+    Decompress(io::Error),
+    /// A payload that is not UTF-8 text
+    InvalidUtf8,
+}
+
+impl fmt::Display for GelfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GelfError::InvalidChunk(msg) => write!(f, "invalid GELF chunk: {}", msg),
+            GelfError::Decompress(e) => write!(f, "could not decompress GELF message: {}", e),
+            GelfError::InvalidUtf8 => write!(f, "GELF message is not valid UTF-8"),
+        }
+    }
+}
+
+/// A chunked message still waiting for some of its chunks
+struct Pending {
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+    first_seen: Instant,
+}
+
+/// Joins chunked GELF messages back together.
+///
+/// A chunk is the magic bytes, an 8-byte message id, the chunk's sequence number and
+/// the sequence count, then a slice of the (usually compressed) payload. Chunks may
+/// arrive in any order; messages still incomplete after [`CHUNK_TIMEOUT`] are dropped.
+#[derive(Default)]
+pub struct Reassembler {
+    pending: HashMap<[u8; 8], Pending>,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Reassembler::default()
+    }
+
+    /// Take one datagram, returning a message payload once it is complete.
+    ///
+    /// Datagrams without the chunk magic are whole messages and are returned as is.
+    pub fn push(&mut self, datagram: &[u8], now: Instant) -> Result<Option<Vec<u8>>, GelfError> {
+        self.pending.retain(|_, p| now.duration_since(p.first_seen) < CHUNK_TIMEOUT);
+        if !datagram.starts_with(&CHUNK_MAGIC) {
+            return Ok(Some(datagram.to_vec()));
+        }
+        if datagram.len() < 12 {
+            return Err(GelfError::InvalidChunk(format!("header is {} bytes, expected 12", datagram.len())));
+        }
+        let id: [u8; 8] = datagram[2..10].try_into().expect("slice is 8 bytes");
+        let (sequence, count) = (datagram[10], datagram[11]);
+        if count == 0 || count > MAX_CHUNKS || sequence >= count {
+            return Err(GelfError::InvalidChunk(format!("chunk {} of {}", sequence, count)));
+        }
+
+        let pending = self.pending.entry(id).or_insert_with(|| Pending {
+            chunks: vec![None; count as usize],
+            received: 0,
+            first_seen: now,
+        });
+        if pending.chunks.len() != count as usize {
+            return Err(GelfError::InvalidChunk(format!(
+                "sequence count changed from {} to {}",
+                pending.chunks.len(),
+                count
+            )));
+        }
+        let slot = &mut pending.chunks[sequence as usize];
+        if slot.is_none() {
+            *slot = Some(datagram[12..].to_vec());
+            pending.received += 1;
+        }
+        if pending.received < pending.chunks.len() {
+            return Ok(None);
+        }
+        let pending = self.pending.remove(&id).expect("message is pending");
+        Ok(Some(pending.chunks.into_iter().flatten().flatten().collect()))
+    }
+}
+
+/// Decompress a message payload into its JSON text; gzip and zlib payloads are
+/// recognized by their magic bytes, anything else is taken as uncompressed
+pub fn decode(payload: &[u8]) -> Result<String, GelfError> {
+    let mut text = Vec::new();
+    match payload {
+        [0x1f, 0x8b, ..] => {
+            GzDecoder::new(payload).read_to_end(&mut text).map_err(GelfError::Decompress)?;
+        }
+        [0x78, ..] => {
+            ZlibDecoder::new(payload).read_to_end(&mut text).map_err(GelfError::Decompress)?;
+        }
+        _ => text.extend_from_slice(payload),
+    }
+    String::from_utf8(text).map_err(|_| GelfError::InvalidUtf8)
+}
+
+/// A UDP socket read as a stream of GELF messages, one per line, so they can be
+/// ingested like a file with [`crate::parser::LogFormat::Gelf`].
+///
+/// Reading ends (returns end of input) at the deadline, if one is set; without one
+/// it blocks until the next message arrives. Datagrams that are not valid GELF are
+/// skipped with a warning.
+pub struct GelfListener {
+    socket: UdpSocket,
+    reassembler: Reassembler,
+    deadline: Option<Instant>,
+    /// The current message and how much of it has been read
+    line: Vec<u8>,
+    consumed: usize,
+}
+
+impl GelfListener {
+    /// Listen on `addr` until `deadline`
+    pub fn bind(addr: impl ToSocketAddrs, deadline: Option<Instant>) -> io::Result<Self> {
+        Ok(GelfListener {
+            socket: UdpSocket::bind(addr)?,
+            reassembler: Reassembler::new(),
+            deadline,
+            line: Vec::new(),
+            consumed: 0,
+        })
+    }
+
+    /// The address the socket is bound to, e.g. to learn the port after binding port 0
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Wait for the next complete message, or `None` once the deadline has passed
+    fn next_message(&mut self) -> io::Result<Option<String>> {
+        let mut datagram = vec![0u8; MAX_DATAGRAM];
+        loop {
+            let timeout = match self.deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(left) if !left.is_zero() => Some(left),
+                    _ => return Ok(None),
+                },
+                None => None,
+            };
+            self.socket.set_read_timeout(timeout)?;
+            let (len, from) = match self.socket.recv_from(&mut datagram) {
+                Ok(received) => received,
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => continue,
+                Err(e) => return Err(e),
+            };
+            let message = match self.reassembler.push(&datagram[..len], Instant::now()) {
+                Ok(Some(payload)) => decode(&payload),
+                Ok(None) => continue,
+                Err(e) => Err(e),
+            };
+            match message {
+                Ok(message) => return Ok(Some(message)),
+                Err(e) => tracing::warn!(%from, "skipping datagram: {}", e),
+            }
+        }
+    }
+}
+
+impl Read for GelfListener {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.consumed == self.line.len() {
+            let Some(message) = self.next_message()? else {
+                return Ok(0);
+            };
+            // Keep pretty-printed messages on one line
+            let message = if message.contains('\n') {
+                serde_json::from_str::<serde_json::Value>(&message)
+                    .map_or_else(|_| message.replace(['\r', '\n'], " "), |value| value.to_string())
+            } else {
+                message
+            };
+            self.line = message.into_bytes();
+            self.line.push(b'\n');
+            self.consumed = 0;
+        }
+        let n = buf.len().min(self.line.len() - self.consumed);
+        buf[..n].copy_from_slice(&self.line[self.consumed..self.consumed + n]);
+        self.consumed += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::{BufRead, BufReader, Write};
+
+    const MESSAGE: &str = r#"{"version":"1.1","host":"web1","short_message":"GET /","timestamp":1705314600,"_http_client_ip":"10.0.0.1","_http_method":"GET","_http_path":"/","_http_status":200}"#;
+
+    fn chunk(id: u8, sequence: u8, count: u8, data: &[u8]) -> Vec<u8> {
+        let mut chunk = CHUNK_MAGIC.to_vec();
+        chunk.extend_from_slice(&[id; 8]);
+        chunk.extend_from_slice(&[sequence, count]);
+        chunk.extend_from_slice(data);
+        chunk
+    }
+
+    #[test]
+    fn decodes_gzip_zlib_and_plain_payloads() {
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(MESSAGE.as_bytes()).unwrap();
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(MESSAGE.as_bytes()).unwrap();
+
+        assert_eq!(decode(&gzip.finish().unwrap()).unwrap(), MESSAGE);
+        assert_eq!(decode(&zlib.finish().unwrap()).unwrap(), MESSAGE);
+        assert_eq!(decode(MESSAGE.as_bytes()).unwrap(), MESSAGE);
+        assert!(matches!(decode(&[0x1f, 0x8b, 0, 0]), Err(GelfError::Decompress(_))));
+    }
+
+    #[test]
+    fn reassembles_chunks_in_any_order() {
+        let now = Instant::now();
+        let (head, tail) = MESSAGE.as_bytes().split_at(40);
+        let mut reassembler = Reassembler::new();
+
+        assert_eq!(reassembler.push(&chunk(1, 1, 2, tail), now).unwrap(), None);
+        assert_eq!(reassembler.push(&chunk(2, 0, 2, b"other"), now).unwrap(), None);
+        assert_eq!(reassembler.push(&chunk(1, 0, 2, head), now).unwrap().unwrap(), MESSAGE.as_bytes());
+        assert!(matches!(reassembler.push(&chunk(3, 2, 2, head), now), Err(GelfError::InvalidChunk(_))));
+
+        // Message 2 never completes and is dropped after the timeout
+        let later = now + CHUNK_TIMEOUT;
+        assert_eq!(reassembler.push(&chunk(2, 1, 2, b"!"), later).unwrap(), None);
+        assert_eq!(reassembler.pending[&[2; 8]].received, 1);
+    }
+
+    #[test]
+    fn listener_yields_one_message_per_line() {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let listener = GelfListener::bind("127.0.0.1:0", Some(deadline)).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let (head, tail) = MESSAGE.as_bytes().split_at(40);
+        sender.send_to(&chunk(7, 0, 2, head), addr).unwrap();
+        sender.send_to(&chunk(7, 1, 2, tail), addr).unwrap();
+        sender.send_to(b"{\n  \"version\": \"1.1\"\n}", addr).unwrap();
+
+        let mut lines = BufReader::new(listener).lines();
+        assert_eq!(lines.next().unwrap().unwrap(), MESSAGE);
+        assert_eq!(lines.next().unwrap().unwrap(), r#"{"version":"1.1"}"#);
+        assert!(lines.next().is_none());
+    }
+}
@@ -0,0 +1,374 @@
+use crate::analyzer::RankedItem;
+use crate::parser::LogEntry;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+/// Mean Earth radius used for great-circle distances
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// A latitude/longitude pair in decimal degrees
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        GeoPoint { lat, lon }
+    }
+
+    /// Great-circle distance to `other` in kilometres (haversine formula)
+    pub fn distance_km(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.lon - self.lon).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
+/// Resolves client IPs to approximate locations
+pub trait GeoLocator {
+    fn locate(&self, ip: IpAddr) -> Option<GeoPoint>;
+}
+
+/// A fixed IP → location table, mainly for tests and small hand-maintained lists
+impl GeoLocator for HashMap<IpAddr, GeoPoint> {
+    fn locate(&self, ip: IpAddr) -> Option<GeoPoint> {
+        self.get(&ip).copied()
+    }
+}
+
+/// Where an IP is, by country and, when known, city
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Place {
+    /// ISO 3166-1 alpha-2 code, e.g. `DE`
+    pub country_code: String,
+    pub country: String,
+    pub city: Option<String>,
+}
+
+/// Resolves client IPs to the country and city they are in
+pub trait PlaceLocator {
+    fn place(&self, ip: IpAddr) -> Option<Place>;
+}
+
+/// A fixed IP → place table, mainly for tests and small hand-maintained lists
+impl PlaceLocator for HashMap<IpAddr, Place> {
+    fn place(&self, ip: IpAddr) -> Option<Place> {
+        self.get(&ip).cloned()
+    }
+}
+
+/// Requests and errors from one country
+#[derive(Debug, Clone, Serialize)]
+pub struct CountryCount {
+    pub country_code: String,
+    pub country: String,
+    pub requests: usize,
+    /// Share of located requests
+    pub percentage: f64,
+    /// Requests logged as ERROR or FATAL
+    pub errors: usize,
+    pub error_rate: f64,
+    /// Distinct client IPs
+    pub clients: usize,
+}
+
+/// Requests broken down by the country (and city) of their client (`--geoip`)
+#[derive(Debug, Clone, Serialize)]
+pub struct CountryReport {
+    /// Requests whose client could be placed
+    pub located: usize,
+    /// Requests from clients the locator does not know, such as private addresses
+    pub unlocated: usize,
+    /// Countries with the most requests
+    pub countries: Vec<CountryCount>,
+    /// Cities with the most requests, as `City, CC`; the share is of located requests
+    pub top_cities: Vec<RankedItem>,
+}
+
+/// Place each client IP once and count requests, errors and clients per country and
+/// requests per city, busiest first.
+pub fn country_breakdown(entries: &[LogEntry], locator: &dyn PlaceLocator, top_n: usize) -> CountryReport {
+    #[derive(Default)]
+    struct Tally {
+        country: String,
+        requests: usize,
+        errors: usize,
+        clients: usize,
+    }
+
+    let mut places: HashMap<IpAddr, Option<Place>> = HashMap::new();
+    let mut countries: HashMap<String, Tally> = HashMap::new();
+    let mut cities: HashMap<String, usize> = HashMap::new();
+    let mut located = 0;
+    for entry in entries {
+        let first_seen = !places.contains_key(&entry.ip);
+        let Some(place) = places.entry(entry.ip).or_insert_with(|| locator.place(entry.ip)) else { continue };
+        located += 1;
+        let tally = countries.entry(place.country_code.clone()).or_default();
+        if first_seen {
+            tally.clients += 1;
+            tally.country.clone_from(&place.country);
+        }
+        tally.requests += 1;
+        if entry.level.is_error() {
+            tally.errors += 1;
+        }
+        if let Some(city) = &place.city {
+            *cities.entry(format!("{}, {}", city, place.country_code)).or_insert(0) += 1;
+        }
+    }
+    let pct = |n: usize, of: usize| (n as f64 / of as f64 * 10000.0).round() / 100.0;
+
+    let mut ranked: Vec<CountryCount> = countries
+        .into_iter()
+        .map(|(country_code, tally)| CountryCount {
+            country_code,
+            country: tally.country,
+            requests: tally.requests,
+            percentage: pct(tally.requests, located),
+            errors: tally.errors,
+            error_rate: pct(tally.errors, tally.requests),
+            clients: tally.clients,
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.country_code.cmp(&b.country_code)));
+    ranked.truncate(top_n);
+
+    let mut top_cities: Vec<(String, usize)> = cities.into_iter().collect();
+    top_cities.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let top_cities = top_cities
+        .into_iter()
+        .take(top_n)
+        .map(|(city, count)| RankedItem { value: city, count, percentage: pct(count, located), activity: None })
+        .collect();
+
+    CountryReport {
+        located,
+        unlocated: entries.len() - located,
+        countries: ranked,
+        top_cities,
+    }
+}
+
+/// Thresholds for impossible-travel detection
+#[derive(Debug, Clone, Copy)]
+pub struct VelocityConfig {
+    /// Fastest plausible travel speed; roughly a commercial flight by default
+    pub max_speed_kmh: f64,
+    /// Hops shorter than this are ignored, since IP geolocation is imprecise
+    pub min_distance_km: f64,
+}
+
+impl Default for VelocityConfig {
+    fn default() -> Self {
+        VelocityConfig {
+            max_speed_kmh: 900.0,
+            min_distance_km: 100.0,
+        }
+    }
+}
+
+/// One located request: when, from which IP, and where that IP is
+type Sighting = (DateTime<Utc>, IpAddr, GeoPoint);
+
+/// Two consecutive sightings of the same identity that are too far apart to be genuine
+#[derive(Debug, Clone, Serialize)]
+pub struct TravelAnomaly {
+    pub subject: String,
+    pub from_ip: String,
+    pub to_ip: String,
+    pub from_time: DateTime<Utc>,
+    pub to_time: DateTime<Utc>,
+    pub distance_km: f64,
+    /// Implied travel speed; infinite when both sightings share a timestamp
+    pub speed_kmh: f64,
+}
+
+/// Flag identities seen from widely separated locations within implausibly short intervals.
+///
+/// `subject` picks the identity (user, session) for each entry; entries without one
+/// or whose IP cannot be located are skipped. Results are
+/// ordered fastest first.
+pub fn impossible_travel<'a>(
+    entries: &'a [LogEntry],
+    subject: impl Fn(&'a LogEntry) -> Option<&'a str>,
+    locator: &dyn GeoLocator,
+    config: VelocityConfig,
+) -> Vec<TravelAnomaly> {
+    let mut sightings: HashMap<&str, Vec<Sighting>> = HashMap::new();
+    for entry in entries {
+        let Some(who) = subject(entry) else { continue };
+        if let Some(point) = locator.locate(entry.ip) {
+            sightings.entry(who).or_default().push((entry.timestamp, entry.ip, point));
+        }
+    }
+
+    let mut anomalies = Vec::new();
+    for (who, mut seen) in sightings {
+        seen.sort_by_key(|s| s.0);
+        for pair in seen.windows(2) {
+            let ((t1, ip1, p1), (t2, ip2, p2)) = (pair[0], pair[1]);
+            let distance = p1.distance_km(&p2);
+            if distance < config.min_distance_km {
+                continue;
+            }
+            let hours = (t2 - t1).num_seconds() as f64 / 3600.0;
+            let speed = if hours > 0.0 { distance / hours } else { f64::INFINITY };
+            if speed > config.max_speed_kmh {
+                anomalies.push(TravelAnomaly {
+                    subject: who.to_string(),
+                    from_ip: ip1.to_string(),
+                    to_ip: ip2.to_string(),
+                    from_time: t1,
+                    to_time: t2,
+                    distance_km: (distance * 10.0).round() / 10.0,
+                    speed_kmh: (speed * 10.0).round() / 10.0,
+                });
+            }
+        }
+    }
+
+    anomalies.sort_by(|a, b| {
+        b.speed_kmh
+            .total_cmp(&a.speed_kmh)
+            .then_with(|| a.subject.cmp(&b.subject))
+    });
+    anomalies
+}
+
+/// Authenticated users seen from places too far apart for the time between their
+/// requests (`--impossible-travel`)
+#[derive(Debug, Clone, Serialize)]
+pub struct TravelReport {
+    /// Fastest plausible speed hops were held against, in km/h
+    pub max_speed_kmh: f64,
+    /// Hops shorter than this were ignored, in km
+    pub min_distance_km: f64,
+    /// Distinct authenticated users checked
+    pub users: usize,
+    /// Implausible hops, fastest first
+    pub anomalies: Vec<TravelAnomaly>,
+}
+
+/// Run [`impossible_travel`] over the authenticated user of each entry, keeping the
+/// `top_n` fastest hops. Requests without a user are skipped.
+pub fn user_travel(entries: &[LogEntry], locator: &dyn GeoLocator, config: VelocityConfig, top_n: usize) -> TravelReport {
+    let users: HashSet<&str> = entries.iter().filter_map(|e| e.user.as_deref()).collect();
+    let mut anomalies = impossible_travel(entries, |e| e.user.as_deref(), locator, config);
+    anomalies.truncate(top_n);
+    TravelReport {
+        max_speed_kmh: config.max_speed_kmh,
+        min_distance_km: config.min_distance_km,
+        users: users.len(),
+        anomalies,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_line, parse_log_line, LogFormat};
+
+    const LONDON: GeoPoint = GeoPoint { lat: 51.5074, lon: -0.1278 };
+    const NEW_YORK: GeoPoint = GeoPoint { lat: 40.7128, lon: -74.0060 };
+    const PARIS: GeoPoint = GeoPoint { lat: 48.8566, lon: 2.3522 };
+
+    fn locator() -> HashMap<IpAddr, GeoPoint> {
+        HashMap::from([
+            ("1.1.1.1".parse().unwrap(), LONDON),
+            ("2.2.2.2".parse().unwrap(), NEW_YORK),
+            ("3.3.3.3".parse().unwrap(), PARIS),
+        ])
+    }
+
+    #[test]
+    fn breaks_requests_down_by_country_and_city() {
+        let place = |code: &str, country: &str, city: Option<&str>| Place {
+            country_code: code.to_string(),
+            country: country.to_string(),
+            city: city.map(str::to_string),
+        };
+        let places = HashMap::from([
+            ("1.1.1.1".parse().unwrap(), place("GB", "United Kingdom", Some("London"))),
+            ("3.3.3.3".parse().unwrap(), place("FR", "France", Some("Paris"))),
+            ("4.4.4.4".parse().unwrap(), place("FR", "France", None)),
+        ]);
+        let lines = [
+            "2024-01-15T10:00:00Z [INFO] 1.1.1.1 GET / 200",
+            "2024-01-15T10:00:01Z [INFO] 3.3.3.3 GET / 200",
+            "2024-01-15T10:00:02Z [ERROR] 3.3.3.3 GET /pay 500",
+            "2024-01-15T10:00:03Z [INFO] 4.4.4.4 GET / 200",
+            "2024-01-15T10:00:04Z [INFO] 10.0.0.1 GET / 200",
+        ];
+        let entries: Vec<LogEntry> = lines.iter().map(|l| parse_log_line(l).unwrap()).collect();
+        let report = country_breakdown(&entries, &places, 10);
+
+        assert_eq!((report.located, report.unlocated), (4, 1));
+        let countries: Vec<_> = report
+            .countries
+            .iter()
+            .map(|c| (c.country_code.as_str(), c.country.as_str(), c.requests, c.errors, c.clients))
+            .collect();
+        assert_eq!(countries, [("FR", "France", 3, 1, 2), ("GB", "United Kingdom", 1, 0, 1)]);
+        assert_eq!((report.countries[0].percentage, report.countries[0].error_rate), (75.0, 33.33));
+        let cities: Vec<_> = report.top_cities.iter().map(|c| (c.value.as_str(), c.count)).collect();
+        assert_eq!(cities, [("Paris, FR", 2), ("London, GB", 1)]);
+    }
+
+    #[test]
+    fn haversine_matches_known_distance() {
+        let d = LONDON.distance_km(&NEW_YORK);
+        assert!((d - 5570.0).abs() < 10.0, "got {}", d);
+        assert_eq!(LONDON.distance_km(&LONDON), 0.0);
+    }
+
+    #[test]
+    fn flags_only_implausible_hops() {
+        let lines = [
+            // London → New York in 30 minutes: impossible
+            "2024-01-15T10:00:00Z [INFO] 1.1.1.1 GET / 200",
+            "2024-01-15T10:30:00Z [INFO] 2.2.2.2 GET / 200",
+            // London → Paris in 2 hours: plausible
+            "2024-01-15T10:00:00Z [INFO] 1.1.1.1 GET /b 200",
+            "2024-01-15T12:00:00Z [INFO] 3.3.3.3 GET /b 200",
+        ];
+        let entries: Vec<LogEntry> = lines.iter().map(|l| parse_log_line(l).unwrap()).collect();
+        // Use the endpoint as a stand-in identity so both journeys stay separate
+        let anomalies =
+            impossible_travel(&entries, |e| Some(e.endpoint.as_str()), &locator(), VelocityConfig::default());
+
+        assert_eq!(anomalies.len(), 1);
+        let a = &anomalies[0];
+        assert_eq!(a.subject, "/");
+        assert_eq!((a.from_ip.as_str(), a.to_ip.as_str()), ("1.1.1.1", "2.2.2.2"));
+        assert!(a.speed_kmh > 10_000.0);
+    }
+
+    #[test]
+    fn follows_authenticated_users() {
+        let line = |ip: &str, user: &str, time: &str| {
+            let line = format!(r#"{} - {} [15/Jan/2024:{} +0000] "GET / HTTP/1.1" 200 5"#, ip, user, time);
+            parse_line(&line, &LogFormat::Clf).unwrap()
+        };
+        let entries = vec![
+            line("1.1.1.1", "alice", "10:00:00"),
+            line("2.2.2.2", "alice", "10:30:00"),
+            line("1.1.1.1", "bob", "10:00:00"),
+            line("3.3.3.3", "bob", "12:00:00"),
+            line("1.1.1.1", "-", "10:00:00"),
+            line("2.2.2.2", "-", "10:01:00"),
+        ];
+        let report = user_travel(&entries, &locator(), VelocityConfig::default(), 10);
+
+        assert_eq!((report.users, report.max_speed_kmh), (2, 900.0));
+        let hops: Vec<_> = report.anomalies.iter().map(|a| (a.subject.as_str(), a.to_ip.as_str())).collect();
+        assert_eq!(hops, [("alice", "2.2.2.2")]);
+        assert!(user_travel(&entries, &locator(), VelocityConfig::default(), 0).anomalies.is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::asn::{Network, NetworkLocator};
+use crate::geo::{GeoLocator, GeoPoint, Place, PlaceLocator};
+use maxminddb::{geoip2, Reader};
+use std::fmt;
+use std::net::IpAddr;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum GeoIpError {
+    Io(std::io::Error),
+    /// The file is not a MaxMind database, or a damaged one
+    InvalidDatabase(String),
+    /// A MaxMind database of another kind than the option reads, e.g. an ASN database
+    /// given to `--geoip`
+    WrongKind { kind: String, expected: &'static str },
+}
+
+impl fmt::Display for GeoIpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeoIpError::Io(e) => write!(f, "{}", e),
+            GeoIpError::InvalidDatabase(msg) => write!(f, "invalid MaxMind database: {}", msg),
+            GeoIpError::WrongKind { kind, expected } => {
+                write!(f, "{} is not the kind of database needed here; use a GeoLite2/GeoIP2 {} database", kind, expected)
+            }
+        }
+    }
+}
+
+impl From<std::io::Error> for GeoIpError {
+    fn from(e: std::io::Error) -> Self {
+        GeoIpError::Io(e)
+    }
+}
+
+/// A MaxMind GeoLite2 or GeoIP2 City or Country database (`--geoip`), read into
+/// memory once and then queried per client IP
+pub struct GeoIpDatabase {
+    reader: Reader<Vec<u8>>,
+}
+
+/// Read a database whose type (e.g. `GeoLite2-City`) names one of `editions`
+fn open_reader(path: &Path, editions: &[&str], expected: &'static str) -> Result<Reader<Vec<u8>>, GeoIpError> {
+    let reader =
+        Reader::from_source(std::fs::read(path)?).map_err(|e| GeoIpError::InvalidDatabase(e.to_string()))?;
+    let kind = &reader.metadata.database_type;
+    if !editions.iter().any(|edition| kind.contains(edition)) {
+        return Err(GeoIpError::WrongKind { kind: kind.clone(), expected });
+    }
+    Ok(reader)
+}
+
+impl GeoIpDatabase {
+    pub fn open(path: &Path) -> Result<Self, GeoIpError> {
+        Ok(GeoIpDatabase { reader: open_reader(path, &["City", "Country"], "City or Country")? })
+    }
+
+    /// Database edition, e.g. `GeoLite2-City`
+    pub fn database_type(&self) -> &str {
+        &self.reader.metadata.database_type
+    }
+
+    fn lookup(&self, ip: IpAddr) -> Option<geoip2::City<'_>> {
+        self.reader.lookup(ip).ok()
+    }
+}
+
+/// A MaxMind GeoLite2 or GeoIP2 ASN database (`--asn`), read into memory once
+pub struct AsnDatabase {
+    reader: Reader<Vec<u8>>,
+}
+
+impl AsnDatabase {
+    pub fn open(path: &Path) -> Result<Self, GeoIpError> {
+        Ok(AsnDatabase { reader: open_reader(path, &["ASN"], "ASN")? })
+    }
+
+    /// Database edition, e.g. `GeoLite2-ASN`
+    pub fn database_type(&self) -> &str {
+        &self.reader.metadata.database_type
+    }
+}
+
+/// Number and owner of the autonomous system announcing an IP
+impl NetworkLocator for AsnDatabase {
+    fn network(&self, ip: IpAddr) -> Option<Network> {
+        let record: geoip2::Asn = self.reader.lookup(ip).ok()?;
+        Some(Network {
+            asn: record.autonomous_system_number?,
+            organization: record.autonomous_system_organization.unwrap_or_default().to_string(),
+        })
+    }
+}
+
+/// The English name, if the database has one
+fn english(names: &Option<std::collections::BTreeMap<&str, &str>>) -> Option<String> {
+    names.as_ref()?.get("en").map(|name| name.to_string())
+}
+
+/// Country and city of an IP; where the database knows no country, the country the
+/// network is registered in
+impl PlaceLocator for GeoIpDatabase {
+    fn place(&self, ip: IpAddr) -> Option<Place> {
+        let record = self.lookup(ip)?;
+        let country = record.country.or(record.registered_country)?;
+        let country_code = country.iso_code?.to_string();
+        Some(Place {
+            country: english(&country.names).unwrap_or_else(|| country_code.clone()),
+            country_code,
+            city: record.city.and_then(|city| english(&city.names)),
+        })
+    }
+}
+
+/// Approximate coordinates, from City databases only
+impl GeoLocator for GeoIpDatabase {
+    fn locate(&self, ip: IpAddr) -> Option<GeoPoint> {
+        let location = self.lookup(ip)?.location?;
+        Some(GeoPoint::new(location.latitude?, location.longitude?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn places_and_locates_ips() {
+        let db = GeoIpDatabase::open(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/geoip.mmdb"))).unwrap();
+        assert_eq!(db.database_type(), "GeoLite2-City");
+        let place = db.place("1.1.1.1".parse().unwrap()).unwrap();
+        assert_eq!(
+            (place.country_code.as_str(), place.country.as_str(), place.city.as_deref()),
+            ("GB", "United Kingdom", Some("London"))
+        );
+        assert_eq!(db.place("4.4.4.4".parse().unwrap()).unwrap().city, None);
+        assert!(db.place("8.8.8.8".parse().unwrap()).is_none());
+        assert_eq!(db.locate("3.3.3.3".parse().unwrap()), Some(GeoPoint::new(48.85, 2.35)));
+    }
+
+    #[test]
+    fn resolves_networks() {
+        let db = AsnDatabase::open(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/asn.mmdb"))).unwrap();
+        assert_eq!(db.database_type(), "GeoLite2-ASN");
+        let network = db.network("1.1.1.1".parse().unwrap()).unwrap();
+        assert_eq!((network.asn, network.organization.as_str()), (64500, "Example Hosting"));
+        assert!(db.network("8.8.8.8".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn each_option_takes_its_own_kind_of_database() {
+        let fixture = |name: &str| Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+        let err = GeoIpDatabase::open(&fixture("asn.mmdb")).err().unwrap();
+        assert!(matches!(&err, GeoIpError::WrongKind { kind, .. } if kind == "GeoLite2-ASN"), "{:?}", err);
+        assert!(matches!(AsnDatabase::open(&fixture("geoip.mmdb")), Err(GeoIpError::WrongKind { expected: "ASN", .. })));
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_databases() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"2024-01-15T10:00:00Z [INFO] 1.1.1.1 GET / 200\n").unwrap();
+        let err = GeoIpDatabase::open(file.path()).err().unwrap();
+        assert!(matches!(err, GeoIpError::InvalidDatabase(_)), "{:?}", err);
+        assert!(matches!(GeoIpDatabase::open(Path::new("/nonexistent.mmdb")), Err(GeoIpError::Io(_))));
+    }
+}
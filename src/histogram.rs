@@ -0,0 +1,161 @@
+/// Samples kept as they are before a histogram switches to buckets; below this,
+/// percentiles are exact
+pub const EXACT_SAMPLES: usize = 1000;
+
+/// Buckets per power of two once bucketed, so a bucket is at most 1/64 of its
+/// values wide and a value reported from its middle is within 0.8% of any value in it
+const SUB_BUCKETS: u64 = 64;
+
+/// Response times in microseconds, in bounded memory: the first [`EXACT_SAMPLES`]
+/// are kept as they are, after which every sample goes into log-linear buckets
+/// (exact below 128µs, then 64 per power of two, as in an HDR histogram), at most a
+/// few thousand counters however many requests there are.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    samples: Vec<u64>,
+    /// Counts per bucket once bucketed, empty before
+    buckets: Vec<u64>,
+    count: u64,
+    sum: u128,
+    min: u64,
+    max: u64,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a response time in
+    pub fn record(&mut self, micros: u64) {
+        self.min = if self.count == 0 { micros } else { self.min.min(micros) };
+        self.max = self.max.max(micros);
+        self.count += 1;
+        self.sum += micros as u128;
+        if self.buckets.is_empty() && self.samples.len() < EXACT_SAMPLES {
+            self.samples.push(micros);
+            return;
+        }
+        for sample in std::mem::take(&mut self.samples) {
+            self.bump(sample);
+        }
+        self.bump(micros);
+    }
+
+    fn bump(&mut self, micros: u64) {
+        let index = bucket_index(micros);
+        if index >= self.buckets.len() {
+            self.buckets.resize(index + 1, 0);
+        }
+        self.buckets[index] += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn min(&self) -> u64 {
+        self.min
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.sum as f64 / self.count.max(1) as f64
+    }
+
+    /// Nearest-rank percentile `p` (between 0 and 1): exact while the samples are
+    /// kept, else the middle of the bucket it falls in, kept within the min and max
+    /// (and the max itself for the last rank)
+    pub fn percentile(&mut self, p: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((p * self.count as f64).ceil() as u64).clamp(1, self.count);
+        if rank == self.count {
+            return self.max;
+        }
+        if self.buckets.is_empty() {
+            self.samples.sort_unstable();
+            return self.samples[rank as usize - 1];
+        }
+        let mut seen = 0;
+        for (index, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                let (low, width) = bucket_range(index);
+                return (low + (width - 1) / 2).clamp(self.min, self.max);
+            }
+        }
+        self.max
+    }
+}
+
+/// Bucket of a value: the value itself below 2 × [`SUB_BUCKETS`], then
+/// [`SUB_BUCKETS`] buckets for each power of two
+fn bucket_index(value: u64) -> usize {
+    if value < 2 * SUB_BUCKETS {
+        return value as usize;
+    }
+    // Shift that leaves the top 7 bits, between 64 and 127
+    let shift = 63 - value.leading_zeros() - SUB_BUCKETS.trailing_zeros();
+    (2 * SUB_BUCKETS + (shift as u64 - 1) * SUB_BUCKETS + ((value >> shift) - SUB_BUCKETS)) as usize
+}
+
+/// Lowest value and width of a bucket
+fn bucket_range(index: usize) -> (u64, u64) {
+    let index = index as u64;
+    if index < 2 * SUB_BUCKETS {
+        return (index, 1);
+    }
+    let k = index - 2 * SUB_BUCKETS;
+    let shift = k / SUB_BUCKETS + 1;
+    ((k % SUB_BUCKETS + SUB_BUCKETS) << shift, 1 << shift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_tile_the_values() {
+        let mut expected = 0;
+        for index in 0..bucket_index(u64::MAX) {
+            let (low, width) = bucket_range(index);
+            assert_eq!(low, expected, "bucket {}", index);
+            assert_eq!(bucket_index(low), index);
+            assert_eq!(bucket_index(low + width - 1), index);
+            assert!(width == 1 || width * SUB_BUCKETS <= low);
+            expected = low + width;
+        }
+    }
+
+    #[test]
+    fn exact_until_bucketed_then_close() {
+        let mut small = LatencyHistogram::new();
+        for ms in (1..=100).rev() {
+            small.record(ms * 1000);
+        }
+        assert_eq!((small.percentile(0.5), small.percentile(0.9), small.percentile(0.99)), (50_000, 90_000, 99_000));
+
+        let mut large = LatencyHistogram::new();
+        for micros in 1..=1_000_000 {
+            large.record(micros);
+        }
+        assert_eq!(large.len(), 1_000_000);
+        assert!(large.buckets.len() < 1000 && large.samples.is_empty());
+        for p in [0.5, 0.9, 0.95, 0.99] {
+            let exact = p * 1_000_000.0;
+            let error = (large.percentile(p) as f64 - exact).abs() / exact;
+            assert!(error < 0.008, "p{} off by {}", p * 100.0, error);
+        }
+        assert_eq!((large.min(), large.max(), large.mean()), (1, 1_000_000, 500_000.5));
+        assert_eq!(large.percentile(1.0), 1_000_000);
+    }
+}
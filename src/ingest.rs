@@ -0,0 +1,1306 @@
+use crate::parser::{self, LevelAliases, LogEntry, LogFormat, LogLevel, ParseError};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Default number of lines between progress callbacks
+pub const DEFAULT_PROGRESS_INTERVAL: usize = 10_000;
+
+/// Lines between checks of the time limit
+const CLOCK_INTERVAL: usize = 256;
+
+/// Bytes read at a time when looking for where the last lines of a file start
+const TAIL_CHUNK: u64 = 64 * 1024;
+
+/// How long a followed file is left alone after reaching its end before it is read again
+const FOLLOW_POLL: Duration = Duration::from_millis(200);
+
+/// Longest a followed [`LiveLines`] waits for a line before reporting that there is none yet
+const LIVE_POLL: Duration = Duration::from_millis(100);
+
+/// Running totals reported to progress callbacks while a stream is ingested
+#[derive(Debug, Clone, Default)]
+pub struct PartialStats {
+    /// Bytes consumed from the reader so far
+    pub bytes_read: u64,
+    /// Total size of the input, when known up front (e.g. a regular file)
+    pub total_bytes: Option<u64>,
+    /// Lines consumed so far, including blank and malformed ones
+    pub lines_read: usize,
+    /// Lines successfully parsed into entries
+    pub entries: usize,
+    /// Lines that failed to read or parse
+    pub malformed: usize,
+    /// Non-access lines set aside in mixed mode instead of counting as malformed
+    pub non_access: usize,
+    /// Continuation lines attached to the entry before them in multi-line mode
+    pub continuation: usize,
+    /// Entries kept in lenient mode although they lack a level or status code
+    pub partial: usize,
+    /// Near-miss lines rescued in recovery mode
+    pub recovered: usize,
+    /// Lines passed over by sampling without being parsed
+    pub sampled_out: usize,
+    /// Lines at the start of the input passed over without being parsed
+    pub skipped: usize,
+    pub level_counts: HashMap<LogLevel, usize>,
+    pub status_counts: HashMap<u16, usize>,
+}
+
+impl PartialStats {
+    /// Percentage of the input consumed, or `None` if the total size is unknown
+    pub fn percent_complete(&self) -> Option<f64> {
+        match self.total_bytes {
+            Some(0) => Some(100.0),
+            Some(total) => Some((self.bytes_read as f64 / total as f64 * 100.0).min(100.0)),
+            None => None,
+        }
+    }
+}
+
+/// A line that could not be turned into a `LogEntry`
+#[derive(Debug)]
+pub struct MalformedLine<'a> {
+    /// 1-based line number within the stream
+    pub line_number: usize,
+    pub reason: MalformedReason,
+    /// The raw line, or an empty string when it could not be decoded
+    pub line: &'a str,
+}
+
+/// Why a line was counted as malformed
+#[derive(Debug)]
+pub enum MalformedReason {
+    /// The bytes were not valid UTF-8
+    InvalidUtf8,
+    Parse(ParseError),
+}
+
+impl fmt::Display for MalformedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MalformedReason::InvalidUtf8 => write!(f, "line is not valid UTF-8"),
+            MalformedReason::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Entries and counters produced by a completed ingestion
+#[derive(Debug)]
+pub struct IngestResult {
+    pub entries: Vec<LogEntry>,
+    pub malformed: usize,
+    /// Final totals; identical to the last value passed to the progress callback
+    pub stats: PartialStats,
+    /// The I/O error that ended the stream early, if any
+    pub read_error: Option<std::io::Error>,
+    /// Why ingestion stopped before consuming all of the input, if it did
+    pub incomplete: Option<String>,
+    /// How many times a rotated input file was reopened
+    pub reopened: usize,
+}
+
+impl IngestResult {
+    /// Add the entries and counters of another ingestion, such as of the next of
+    /// several files; the first read error and reason to stop are kept
+    pub fn append(&mut self, other: IngestResult) {
+        self.entries.extend(other.entries);
+        self.malformed += other.malformed;
+        self.read_error = self.read_error.take().or(other.read_error);
+        self.incomplete = self.incomplete.take().or(other.incomplete);
+        self.reopened += other.reopened;
+
+        let (stats, more) = (&mut self.stats, other.stats);
+        stats.bytes_read += more.bytes_read;
+        stats.total_bytes = stats.total_bytes.zip(more.total_bytes).map(|(a, b)| a + b);
+        stats.lines_read += more.lines_read;
+        stats.entries += more.entries;
+        stats.malformed += more.malformed;
+        stats.non_access += more.non_access;
+        stats.continuation += more.continuation;
+        stats.partial += more.partial;
+        stats.recovered += more.recovered;
+        stats.sampled_out += more.sampled_out;
+        stats.skipped += more.skipped;
+        for (level, count) in more.level_counts {
+            *stats.level_counts.entry(level).or_insert(0) += count;
+        }
+        for (status, count) in more.status_counts {
+            *stats.status_counts.entry(status).or_insert(0) += count;
+        }
+    }
+}
+
+/// What to do when a watched input file is rotated away mid-read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RotationPolicy {
+    /// Finish the already-open file, then stop and flag the result as incomplete
+    #[default]
+    Stop,
+    /// Finish the already-open file, then continue with the new file at the same path
+    Reopen,
+}
+
+/// Which lines to parse when only a sample of a large input is analyzed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+    /// Each line with this probability (0 < p ≤ 1), chosen by a hash of its line
+    /// number, so the same lines are picked on every run
+    Fraction(f64),
+    /// Lines 1, n + 1, 2n + 1, …
+    EveryNth(usize),
+}
+
+impl Sampling {
+    /// Whether line `line_number` (counted from 1) is in the sample
+    pub fn keeps(&self, line_number: usize) -> bool {
+        match *self {
+            Sampling::Fraction(rate) => rate >= 1.0 || (splitmix64(line_number as u64) as f64) < rate * u64::MAX as f64,
+            Sampling::EveryNth(n) => (line_number - 1).is_multiple_of(n.max(1)),
+        }
+    }
+
+    /// The share of lines sampled, between 0 and 1
+    pub fn rate(&self) -> f64 {
+        match *self {
+            Sampling::Fraction(rate) => rate,
+            Sampling::EveryNth(n) => 1.0 / n.max(1) as f64,
+        }
+    }
+}
+
+/// A well-mixed 64-bit hash, so consecutive line numbers are picked independently
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A live source read in windows of time, such as a network listener or a topic
+/// consumer. Reading reaches end of input at the end of each window, and carries on
+/// with the next window once it is moved.
+pub trait Windowed: BufRead {
+    /// End the stream at `end`, until the window is moved again
+    fn end_window_at(&mut self, end: Instant);
+
+    /// Wait up to `wait` for `limit` lines and return them without consuming them,
+    /// so the format can be detected from the first ones received
+    fn peek_lines(&mut self, limit: usize, wait: Duration) -> Vec<String>;
+}
+
+/// How a watched file changed underneath the reader
+enum FileChange {
+    Unchanged,
+    /// Same file, but now shorter than what has already been read
+    Truncated { size: u64 },
+    /// The path now names a different file, or no file at all
+    Rotated,
+}
+
+/// Identifies the file a handle refers to, independent of its path: its device and
+/// inode, or `None` where these are not available
+#[cfg(unix)]
+pub fn file_identity(meta: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+pub fn file_identity(_meta: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Where the last `count` lines of `input` start, found by reading back from its end
+/// rather than through all of it. A last line without a newline counts as a line.
+pub fn tail_offset(input: &mut (impl Read + Seek), count: usize) -> io::Result<u64> {
+    let size = input.seek(SeekFrom::End(0))?;
+    if count == 0 {
+        return Ok(size);
+    }
+    let mut end = size;
+    let mut chunk = Vec::new();
+    let mut newlines = 0;
+    while end > 0 {
+        let start = end.saturating_sub(TAIL_CHUNK);
+        chunk.resize((end - start) as usize, 0);
+        input.seek(SeekFrom::Start(start))?;
+        input.read_exact(&mut chunk)?;
+        for (i, _) in chunk.iter().enumerate().rev().filter(|&(_, &b)| b == b'\n') {
+            let line_start = start + i as u64 + 1;
+            // The newline ending the last line does not start another one
+            if line_start == size {
+                continue;
+            }
+            newlines += 1;
+            if newlines == count {
+                return Ok(line_start);
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// The last `count` lines of `reader`, for input that can only be read forwards:
+/// everything before them is read through and dropped
+pub fn last_lines(reader: &mut impl BufRead, count: usize) -> io::Result<Vec<u8>> {
+    let mut lines: VecDeque<Vec<u8>> = VecDeque::new();
+    loop {
+        let mut line = Vec::new();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        if !line.ends_with(b"\n") {
+            line.push(b'\n');
+        }
+        if lines.len() == count {
+            lines.pop_front();
+        }
+        if count > 0 {
+            lines.push_back(line);
+        }
+    }
+    Ok(lines.into_iter().flatten().collect())
+}
+
+/// The lines at the start of `reader` that describe the rows after them in `format`
+/// (W3C directives, or a CSV header row), so they can be read ahead of a later part
+/// of the input. Empty for other formats.
+pub fn header_lines(reader: &mut impl BufRead, format: &LogFormat) -> io::Result<Vec<u8>> {
+    let mut header = Vec::new();
+    match format {
+        LogFormat::W3c(_) => {
+            while reader.fill_buf()?.first() == Some(&b'#') {
+                reader.read_until(b'\n', &mut header)?;
+            }
+        }
+        LogFormat::Csv(_) => {
+            reader.read_until(b'\n', &mut header)?;
+        }
+        _ => {}
+    }
+    Ok(header)
+}
+
+/// Lines read on another thread, such as from a child process or a socket that blocks
+/// until there is more, handed over as they arrive.
+///
+/// Each message is one or more whole lines, or the error that stopped the sender.
+/// Once the sender hangs up, or nothing has arrived for the
+/// [`idle_timeout`](Self::idle_timeout), the input ends. When following, reading past
+/// the last line received yields nothing until the next one arrives, as for a file
+/// that has not grown yet, so [`Ingest::follow`] can refresh meanwhile; the idle
+/// timeout then ends it with a [`io::ErrorKind::TimedOut`] error, which `Ingest`
+/// reports as what stopped the following.
+pub struct LiveLines {
+    lines: Receiver<io::Result<Vec<u8>>>,
+    follow: bool,
+    idle_timeout: Option<Duration>,
+    last_received: Instant,
+    /// Received lines, and how much of them has been read
+    pending: Vec<u8>,
+    consumed: usize,
+    /// Whether the sender has hung up
+    ended: bool,
+    /// The error that stopped the sender, returned once the lines before it are read
+    error: Option<io::Error>,
+}
+
+impl LiveLines {
+    pub fn new(lines: Receiver<io::Result<Vec<u8>>>, follow: bool) -> Self {
+        LiveLines {
+            lines,
+            follow,
+            idle_timeout: None,
+            last_received: Instant::now(),
+            pending: Vec::new(),
+            consumed: 0,
+            ended: false,
+            error: None,
+        }
+    }
+
+    /// Read `reader` line by line on a thread of its own, so that waiting for it to
+    /// be written to does not hold up the reading
+    pub fn spawn(reader: impl Read + Send + 'static, follow: bool) -> Self {
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            loop {
+                let mut line = Vec::new();
+                match reader.read_until(b'\n', &mut line) {
+                    Ok(0) => return,
+                    Ok(_) => {
+                        if sender.send(Ok(line)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        let _ = sender.send(Err(e));
+                        return;
+                    }
+                }
+            }
+        });
+        LiveLines::new(lines, follow)
+    }
+
+    /// End the input once nothing has arrived for `limit`, even though the sender
+    /// may still send more
+    pub fn idle_timeout(mut self, limit: Option<Duration>) -> Self {
+        self.idle_timeout = limit;
+        self
+    }
+
+    /// Whether the sender has hung up, so nothing more will arrive
+    pub fn ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Wait for the next lines, at most `timeout` if given, and buffer them
+    fn poll(&mut self, timeout: Option<Duration>) {
+        let received = match timeout {
+            Some(timeout) => self.lines.recv_timeout(timeout),
+            None => self.lines.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(Ok(lines)) => {
+                self.last_received = Instant::now();
+                if self.consumed == self.pending.len() {
+                    self.pending.clear();
+                    self.consumed = 0;
+                }
+                self.pending.extend_from_slice(&lines);
+            }
+            Ok(Err(e)) => {
+                self.error = Some(e);
+                self.ended = true;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => self.ended = true,
+        }
+    }
+
+    fn unread(&self) -> &[u8] {
+        &self.pending[self.consumed..]
+    }
+
+    /// Up to `limit` of the next lines, without reading them, waiting at most `wait`
+    /// (or the idle timeout) for them to arrive; e.g. to detect their format
+    pub fn peek_lines(&mut self, limit: usize, wait: Duration) -> Vec<String> {
+        let until = Instant::now() + wait;
+        loop {
+            let lines = self.unread().iter().filter(|&&b| b == b'\n').count();
+            let left = until.saturating_duration_since(Instant::now());
+            let idle = self.idle_timeout.is_some_and(|limit| self.last_received.elapsed() >= limit);
+            if lines >= limit || left.is_zero() || idle || self.ended {
+                break;
+            }
+            self.poll(Some(left.min(LIVE_POLL)));
+        }
+        String::from_utf8_lossy(self.unread()).lines().take(limit).map(str::to_string).collect()
+    }
+}
+
+impl Read for LiveLines {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = buf.len().min(available.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for LiveLines {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.consumed == self.pending.len() && !self.ended {
+            let idle_left = self.idle_timeout.map(|limit| limit.saturating_sub(self.last_received.elapsed()));
+            let wait = match (self.follow.then_some(LIVE_POLL), idle_left) {
+                (Some(poll), Some(left)) => Some(poll.min(left)),
+                (poll, left) => poll.or(left),
+            };
+            if idle_left != Some(Duration::ZERO) {
+                self.poll(wait);
+            }
+            if let Some(limit) = self.idle_timeout.filter(|_| self.consumed == self.pending.len()) {
+                if self.last_received.elapsed() >= limit {
+                    let idle = format!("nothing was written for {}", crate::units::format_duration(limit));
+                    tracing::debug!("{}; ending the input", idle);
+                    self.ended = true;
+                    // An empty read is no end when following, so that ends with this error
+                    if self.follow {
+                        self.error = Some(io::Error::new(io::ErrorKind::TimedOut, idle));
+                    }
+                }
+            }
+        }
+        if self.consumed == self.pending.len() {
+            if let Some(e) = self.error.take() {
+                return Err(e);
+            }
+        }
+        Ok(self.unread())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.consumed = (self.consumed + amt).min(self.pending.len());
+    }
+}
+
+type Reopen<'a, R> = Box<dyn FnMut(&Path) -> io::Result<(R, Option<(u64, u64)>)> + 'a>;
+
+/// Tracks the identity and consumed size of the file being read
+struct FileWatch<'a, R> {
+    path: PathBuf,
+    identity: Option<(u64, u64)>,
+    /// Bytes consumed from the currently open handle
+    file_bytes: u64,
+    policy: RotationPolicy,
+    reopen: Reopen<'a, R>,
+    /// Whether the reader's buffer already holds the whole next line, so reading it
+    /// does not read from the file
+    line_buffered: fn(&R) -> bool,
+}
+
+impl<R> FileWatch<'_, R> {
+    fn check(&self) -> FileChange {
+        let Ok(meta) = fs::metadata(&self.path) else {
+            return FileChange::Rotated;
+        };
+        let identity = file_identity(&meta);
+        if identity.is_some() && identity != self.identity {
+            FileChange::Rotated
+        } else if meta.len() < self.file_bytes {
+            FileChange::Truncated { size: meta.len() }
+        } else {
+            FileChange::Unchanged
+        }
+    }
+
+    fn truncation_note(&self, size: u64) -> String {
+        format!(
+            "input was truncated to {} bytes after {} bytes had been read; stats cover the lines read before truncation",
+            size, self.file_bytes
+        )
+    }
+}
+
+type ProgressCallback<'a> = Box<dyn FnMut(&PartialStats) + 'a>;
+type MalformedCallback<'a> = Box<dyn FnMut(&MalformedLine<'_>) + 'a>;
+type RefreshCallback<'a> = Box<dyn FnMut(&[LogEntry], &PartialStats) + 'a>;
+
+/// Keeps reading a file as it grows, like `tail -f`
+struct Follow<'a> {
+    /// Least time between two refreshes
+    every: Duration,
+    on_refresh: RefreshCallback<'a>,
+    /// When the last refresh happened, and how many lines it had seen
+    last: Option<(Instant, usize)>,
+}
+
+impl Follow<'_> {
+    /// Refresh if new lines arrived since the last refresh and it is long enough ago
+    fn refresh(&mut self, entries: &[LogEntry], stats: &PartialStats) {
+        let due = match self.last {
+            None => true,
+            Some((at, lines)) => stats.lines_read > lines && at.elapsed() >= self.every,
+        };
+        if due {
+            (self.on_refresh)(entries, stats);
+            self.last = Some((Instant::now(), stats.lines_read));
+        }
+    }
+}
+
+/// Streams lines from a reader into parsed `LogEntry` values.
+///
+/// Embedding applications can register a progress callback to display a
+/// percent-complete indicator and partial results during long analyses:
+///
+/// ```no_run
+/// use log_analyzer::ingest::Ingest;
+/// use std::fs::File;
+/// use std::io::BufReader;
+///
+/// let file = File::open("access.log").unwrap();
+/// let size = file.metadata().unwrap().len();
+/// let result = Ingest::new(BufReader::new(file))
+///     .total_bytes(size)
+///     .on_progress(50_000, |p| {
+///         if let Some(pct) = p.percent_complete() {
+///             eprintln!("{:5.1}% — {} entries", pct, p.entries);
+///         }
+///     })
+///     .run();
+/// let stats = log_analyzer::analyzer::analyze(&result.entries, 10, 5);
+/// ```
+pub struct Ingest<'a, R> {
+    reader: R,
+    total_bytes: Option<u64>,
+    progress_interval: usize,
+    format: LogFormat,
+    mixed: bool,
+    multiline: bool,
+    lenient: bool,
+    recover: bool,
+    levels: LevelAliases,
+    on_progress: Option<ProgressCallback<'a>>,
+    on_malformed: Option<MalformedCallback<'a>>,
+    watch: Option<FileWatch<'a, R>>,
+    follow: Option<Follow<'a>>,
+    max_lines: Option<usize>,
+    time_limit: Option<Duration>,
+    sampling: Option<Sampling>,
+    skip_lines: usize,
+    first_line: usize,
+}
+
+impl<'a> Ingest<'a, BufReader<File>> {
+    /// Watch `path` (the file this reader was opened from) for truncation and
+    /// rotation while it is being read.
+    ///
+    /// Truncation always stops ingestion, since later reads would mix old and
+    /// new data. Rotation is handled according to `policy` once the original
+    /// file has been read to the end.
+    pub fn watch_file(mut self, path: impl Into<PathBuf>, policy: RotationPolicy) -> Self {
+        let identity = self
+            .reader
+            .get_ref()
+            .metadata()
+            .ok()
+            .and_then(|m| file_identity(&m));
+        self.watch = Some(FileWatch {
+            path: path.into(),
+            identity,
+            file_bytes: 0,
+            policy,
+            reopen: Box::new(|path| {
+                let file = File::open(path)?;
+                let identity = file.metadata().ok().and_then(|m| file_identity(&m));
+                Ok((BufReader::new(file), identity))
+            }),
+            line_buffered: |reader| reader.buffer().contains(&b'\n'),
+        });
+        self
+    }
+}
+
+impl<'a, R: BufRead> Ingest<'a, R> {
+    pub fn new(reader: R) -> Self {
+        Ingest {
+            reader,
+            total_bytes: None,
+            progress_interval: DEFAULT_PROGRESS_INTERVAL,
+            format: LogFormat::Native,
+            mixed: false,
+            multiline: false,
+            lenient: false,
+            recover: false,
+            levels: LevelAliases::default(),
+            on_progress: None,
+            on_malformed: None,
+            watch: None,
+            follow: None,
+            max_lines: None,
+            time_limit: None,
+            sampling: None,
+            skip_lines: 0,
+            first_line: 1,
+        }
+    }
+
+    /// Declare the input size so progress can be reported as a percentage
+    pub fn total_bytes(mut self, total: u64) -> Self {
+        self.total_bytes = Some(total);
+        self
+    }
+
+    /// Parse lines as `format` instead of the native format
+    pub fn format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Classify each line by whether the format's parser accepts it: lines it
+    /// rejects are interleaved application output, counted in `non_access`
+    /// rather than reported as malformed
+    pub fn mixed(mut self, mixed: bool) -> Self {
+        self.mixed = mixed;
+        self
+    }
+
+    /// Attach continuation lines (see [`parser::is_continuation_line`]) that fail
+    /// to parse to the entry before them as its `extra` text, counting them in
+    /// `continuation` rather than as malformed
+    pub fn multiline(mut self, multiline: bool) -> Self {
+        self.multiline = multiline;
+        self
+    }
+
+    /// Keep lines that only fail to parse for lack of a level or status code (see
+    /// [`parser::parse_line_lenient`]), counting them in `partial`
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Give lines that fail to parse a second, token-based attempt (see
+    /// [`parser::recover_line`]) before counting them as malformed, counting
+    /// those rescued in `recovered`
+    pub fn recover(mut self, recover: bool) -> Self {
+        self.recover = recover;
+        self
+    }
+
+    /// Accept the level spellings in `levels` as well as the ones each format
+    /// understands (see [`parser::parse_line_with_levels`])
+    pub fn level_aliases(mut self, levels: LevelAliases) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    /// Invoke `callback` every `every_lines` lines and once more when the stream ends
+    pub fn on_progress(mut self, every_lines: usize, callback: impl FnMut(&PartialStats) + 'a) -> Self {
+        self.progress_interval = every_lines.max(1);
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Stop after reading this many lines (not counting those passed over by
+    /// [`skip_lines`](Self::skip_lines)), flagging the result as incomplete
+    pub fn max_lines(mut self, limit: Option<usize>) -> Self {
+        self.max_lines = limit;
+        self
+    }
+
+    /// Stop once this much time has passed since `run` started, flagging the
+    /// result as incomplete
+    pub fn time_limit(mut self, limit: Option<Duration>) -> Self {
+        self.time_limit = limit;
+        self
+    }
+
+    /// Keep reading as lines are appended to the input instead of stopping at its end,
+    /// until the line or time limit, calling `on_refresh` with everything read so far
+    /// once the existing lines are in and then at most every `every` while new ones
+    /// arrive. The end of the input is where the reader has nothing more to read for
+    /// now, such as the end of a file that has not grown yet.
+    ///
+    /// A line is only parsed once its newline has been written. Like `tail -F`, a
+    /// rotated file is followed to the new file at the path, and a truncated one is
+    /// read again from its start, whatever the [`RotationPolicy`]; this needs
+    /// [`watch_file`](Self::watch_file).
+    pub fn follow(mut self, every: Duration, on_refresh: impl FnMut(&[LogEntry], &PartialStats) + 'a) -> Self {
+        self.follow = Some(Follow {
+            every,
+            on_refresh: Box::new(on_refresh),
+            last: None,
+        });
+        self
+    }
+
+    /// Only parse the lines `sampling` picks, counting the others in `sampled_out`.
+    ///
+    /// W3C directives and a CSV header row are always read, since they describe the
+    /// lines after them. Continuation lines are sampled like any other, so this is
+    /// not meant for [`multiline`](Self::multiline) input.
+    pub fn sample(mut self, sampling: Option<Sampling>) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Pass over the first `count` lines without parsing them, counting them in
+    /// `skipped`. As with sampling, W3C directives and a CSV header row are still read.
+    pub fn skip_lines(mut self, count: usize) -> Self {
+        self.skip_lines = count;
+        self
+    }
+
+    /// Number the lines from `first` instead of 1, for a reader over part of a larger
+    /// input. Line numbers are what malformed lines are reported with, and what
+    /// [`sample`](Self::sample) and [`skip_lines`](Self::skip_lines) go by.
+    pub fn first_line(mut self, first: usize) -> Self {
+        self.first_line = first.max(1);
+        self
+    }
+
+    /// Invoke `callback` for every line that fails to decode or parse
+    pub fn on_malformed(mut self, callback: impl FnMut(&MalformedLine<'_>) + 'a) -> Self {
+        self.on_malformed = Some(Box::new(callback));
+        self
+    }
+
+    /// Consume the stream, returning every parsed entry.
+    ///
+    /// Reading ends early, with `incomplete` set, if a line or time limit is hit.
+    pub fn run(mut self) -> IngestResult {
+        let started = Instant::now();
+        let mut stats = PartialStats {
+            total_bytes: self.total_bytes,
+            ..PartialStats::default()
+        };
+        let mut entries = Vec::new();
+        let mut buf = Vec::new();
+        let mut read_error = None;
+        let mut incomplete = None;
+        let mut reopened = 0usize;
+        // Whether the previous non-blank line was an entry (or one of its continuation lines)
+        let mut attachable = false;
+
+        loop {
+            if let Some(limit) = self.time_limit {
+                // A followed file waiting for lines checks on every pass
+                let waiting = self.follow.as_ref().is_some_and(|f| f.last.is_some());
+                if (waiting || stats.lines_read.is_multiple_of(CLOCK_INTERVAL)) && started.elapsed() >= limit {
+                    incomplete = Some(format!(
+                        "time limit of {} reached after {} lines",
+                        crate::units::format_duration(limit),
+                        stats.lines_read
+                    ));
+                    break;
+                }
+            }
+
+            // A watched file is checked whenever the next line is not buffered yet, before
+            // reading on from the file, so nothing written after a truncation is read. A
+            // followed file is only checked once it runs dry, and read again if truncated.
+            let unbuffered = |watch: &&FileWatch<R>| !(watch.line_buffered)(&self.reader);
+            if let Some(watch) = self.watch.as_ref().filter(|_| self.follow.is_none()).filter(unbuffered) {
+                if let FileChange::Truncated { size } = watch.check() {
+                    incomplete = Some(watch.truncation_note(size));
+                    break;
+                }
+            }
+
+            // A followed file may end mid-line; the start of that line is kept until
+            // the rest is written
+            if self.follow.is_none() || buf.ends_with(b"\n") {
+                buf.clear();
+            }
+            let n = match self.reader.read_until(b'\n', &mut buf) {
+                Ok(0) if self.follow.is_some() => {
+                    if let Some(watch) = self.watch.as_mut() {
+                        if !matches!(watch.check(), FileChange::Unchanged) {
+                            // A rotated file's replacement may not exist yet; it is
+                            // looked for again on the next pass
+                            if let Ok((reader, identity)) = (watch.reopen)(&watch.path) {
+                                tracing::debug!(path = %watch.path.display(), "followed input rotated or truncated; reopening");
+                                watch.identity = identity;
+                                watch.file_bytes = 0;
+                                self.reader = reader;
+                                reopened += 1;
+                                buf.clear();
+                                continue;
+                            }
+                        }
+                    }
+                    if let Some(follow) = self.follow.as_mut() {
+                        follow.refresh(&entries, &stats);
+                    }
+                    std::thread::sleep(FOLLOW_POLL);
+                    continue;
+                }
+                Ok(0) => {
+                    // End of the open handle: check whether the path moved on without us
+                    let Some(watch) = self.watch.as_mut() else { break };
+                    match watch.check() {
+                        FileChange::Unchanged => {}
+                        FileChange::Truncated { size } => {
+                            incomplete = Some(watch.truncation_note(size));
+                        }
+                        FileChange::Rotated if watch.policy == RotationPolicy::Reopen => {
+                            match (watch.reopen)(&watch.path) {
+                                Ok((reader, identity)) => {
+                                    tracing::debug!(path = %watch.path.display(), "input rotated; reopening");
+                                    watch.identity = identity;
+                                    watch.file_bytes = 0;
+                                    self.reader = reader;
+                                    reopened += 1;
+                                    continue;
+                                }
+                                Err(e) => {
+                                    incomplete = Some(format!(
+                                        "input was rotated and the new file could not be opened: {}",
+                                        e
+                                    ));
+                                }
+                            }
+                        }
+                        FileChange::Rotated => {
+                            incomplete = Some(
+                                "input was rotated during analysis; lines written to the new file were not read"
+                                    .to_string(),
+                            );
+                        }
+                    }
+                    break;
+                }
+                // The rest of a followed line is still to be written
+                Ok(_) if self.follow.is_some() && !buf.ends_with(b"\n") => continue,
+                Ok(_) => buf.len(),
+                // A followed input that went quiet for too long ends like a time limit
+                Err(e) if e.kind() == io::ErrorKind::TimedOut && self.follow.is_some() => {
+                    incomplete = Some(format!("{} after {} lines; stopped following", e, stats.lines_read));
+                    break;
+                }
+                // An I/O error mid-stream ends ingestion with the entries read so far
+                Err(e) => {
+                    read_error = Some(e);
+                    break;
+                }
+            };
+            // Only flag the line limit once there is actually more input
+            if let Some(limit) = self.max_lines {
+                if stats.lines_read - stats.skipped >= limit {
+                    incomplete = Some(format!("line limit of {} reached", limit));
+                    break;
+                }
+            }
+            stats.bytes_read += n as u64;
+            stats.lines_read += 1;
+            let line_number = stats.lines_read + self.first_line - 1;
+            if let Some(watch) = self.watch.as_mut() {
+                watch.file_bytes += n as u64;
+            }
+            let describes_rows = match self.format {
+                LogFormat::W3c(_) => buf.starts_with(b"#"),
+                LogFormat::Csv(_) => line_number == 1,
+                _ => false,
+            };
+            let skipped = !describes_rows && line_number <= self.skip_lines;
+            let sampled_out = !describes_rows && !skipped && self.sampling.is_some_and(|s| !s.keeps(line_number));
+
+            match std::str::from_utf8(&buf) {
+                _ if skipped => stats.skipped += 1,
+                _ if sampled_out => stats.sampled_out += 1,
+                Err(_) => {
+                    stats.malformed += 1;
+                    if let Some(cb) = self.on_malformed.as_mut() {
+                        cb(&MalformedLine {
+                            line_number,
+                            reason: MalformedReason::InvalidUtf8,
+                            line: "",
+                        });
+                    }
+                }
+                Ok(line) => {
+                    let line = line.trim_end_matches(['\n', '\r']);
+                    // W3C directives (`#Version:`, `#Fields:`, …) and CSV header rows
+                    // describe the rows that follow
+                    let directive = match &mut self.format {
+                        LogFormat::W3c(fields) => fields.read_directive(line),
+                        LogFormat::Csv(columns) => columns.read_header(line),
+                        _ => false,
+                    };
+                    if directive {
+                        attachable = false;
+                    } else if !line.trim().is_empty() {
+                        let mut parsed = parser::parse_line_with_levels(line, &self.format, false, &self.levels);
+                        if self.lenient && parsed.is_err() {
+                            if let Ok(entry) = parser::parse_line_with_levels(line, &self.format, true, &self.levels) {
+                                stats.partial += 1;
+                                parsed = Ok(entry);
+                            }
+                        }
+                        if self.recover && parsed.is_err() {
+                            if let Some(entry) = parser::recover_line(line, &self.format, self.lenient, &self.levels) {
+                                stats.recovered += 1;
+                                parsed = Ok(entry);
+                            }
+                        }
+                        attachable = match parsed {
+                            Ok(_) => true,
+                            Err(_) => attachable && self.multiline && parser::is_continuation_line(line),
+                        };
+                        match parsed {
+                            Ok(entry) => {
+                                stats.entries += 1;
+                                *stats.level_counts.entry(entry.level.clone()).or_insert(0) += 1;
+                                if let Some(code) = entry.status_code {
+                                    *stats.status_counts.entry(code).or_insert(0) += 1;
+                                }
+                                entries.push(entry);
+                            }
+                            Err(_) if attachable => {
+                                stats.continuation += 1;
+                                if let Some(entry) = entries.last_mut() {
+                                    let extra = entry.extra.get_or_insert_with(String::new);
+                                    if !extra.is_empty() {
+                                        extra.push('\n');
+                                    }
+                                    extra.push_str(line);
+                                }
+                            }
+                            Err(_) if self.mixed => {
+                                stats.non_access += 1;
+                            }
+                            Err(e) => {
+                                stats.malformed += 1;
+                                if let Some(cb) = self.on_malformed.as_mut() {
+                                    cb(&MalformedLine {
+                                        line_number,
+                                        reason: MalformedReason::Parse(e),
+                                        line,
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            if line_number.is_multiple_of(self.progress_interval) {
+                if let Some(cb) = self.on_progress.as_mut() {
+                    cb(&stats);
+                }
+            }
+
+            // A busy followed file may never run dry, so it is refreshed between lines too
+            if let Some(follow) = self.follow.as_mut() {
+                if follow.last.is_some() && line_number.is_multiple_of(CLOCK_INTERVAL) {
+                    follow.refresh(&entries, &stats);
+                }
+            }
+        }
+
+        if let Some(cb) = self.on_progress.as_mut() {
+            cb(&stats);
+        }
+
+        IngestResult {
+            entries,
+            malformed: stats.malformed,
+            stats,
+            read_error,
+            incomplete,
+            reopened,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{HttpMethod, W3cFields};
+    use std::io::Cursor;
+
+    const INPUT: &str = "2024-01-15T10:30:00Z [INFO] 1.2.3.4 GET / 200\n\
+                         garbage\n\
+                         \n\
+                         2024-01-15T10:30:01Z [ERROR] 1.2.3.4 GET / 500\n";
+
+    #[test]
+    fn counts_entries_and_malformed_lines() {
+        let result = Ingest::new(Cursor::new(INPUT)).run();
+        assert_eq!(result.entries.len(), 2);
+        assert_eq!(result.malformed, 1);
+        assert_eq!(result.stats.lines_read, 4);
+        assert_eq!(result.stats.level_counts[&LogLevel::Error], 1);
+    }
+
+    #[test]
+    fn reports_progress_until_complete() {
+        let mut percents = Vec::new();
+        let mut malformed_lines = Vec::new();
+        Ingest::new(Cursor::new(INPUT))
+            .total_bytes(INPUT.len() as u64)
+            .on_progress(2, |p| percents.push(p.percent_complete().unwrap()))
+            .on_malformed(|m| malformed_lines.push(m.line_number))
+            .run();
+        assert_eq!(percents.len(), 3);
+        assert!(percents[0] < percents[1]);
+        assert_eq!(*percents.last().unwrap(), 100.0);
+        assert_eq!(malformed_lines, vec![2]);
+    }
+
+    #[test]
+    fn mixed_mode_sets_aside_application_lines() {
+        let input = "2024-01-15T10:30:00Z [INFO] 1.2.3.4 GET / 200\n\
+                     Starting worker pool\n\
+                     2024-01-15T10:30:01Z [INFO] bad_ip GET / 200\n\
+                     10.0.0.1 - - [15/Jan/2024:10:30:02 +0000] \"GET / HTTP/1.1\" 200 5\n";
+        let result = Ingest::new(Cursor::new(input)).mixed(true).run();
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.stats.non_access, 3);
+        assert_eq!(result.malformed, 0);
+
+        // The format decides: read as CLF, the native line is the odd one out
+        let result = Ingest::new(Cursor::new(input)).format(LogFormat::Clf).mixed(true).run();
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].timestamp.to_rfc3339(), "2024-01-15T10:30:02+00:00");
+        assert_eq!(result.stats.non_access, 3);
+    }
+
+    #[test]
+    fn multiline_mode_attaches_stack_traces() {
+        let input = "2024-01-15T10:30:00Z [ERROR] 1.2.3.4 GET /checkout 500\n\
+                     java.lang.IllegalStateException: cart is empty\n\
+                     \x20   at com.example.Cart.total(Cart.java:42)\n\
+                     Caused by: java.io.IOException: broken pipe\n\
+                     \x20   ... 12 more\n\
+                     2024-01-15T10:30:01Z [INFO] 1.2.3.4 GET / 200\n";
+
+        let result = Ingest::new(Cursor::new(input)).run();
+        assert_eq!(result.malformed, 4);
+
+        let result = Ingest::new(Cursor::new(input)).multiline(true).run();
+        assert_eq!(result.entries.len(), 2);
+        assert_eq!(result.stats.continuation, 4);
+        assert_eq!(result.malformed, 0);
+        let extra = result.entries[0].extra.as_deref().unwrap();
+        assert!(extra.starts_with("java.lang.IllegalStateException"));
+        assert_eq!(extra.lines().count(), 4);
+        assert_eq!(result.entries[1].extra, None);
+    }
+
+    #[test]
+    fn lenient_mode_keeps_partial_entries() {
+        let input = "2024-01-15T10:30:00Z [INFO] 1.2.3.4 GET / 200\n\
+                     2024-01-15T10:30:01Z [ERROR] 1.2.3.4 GET /cart\n\
+                     2024-01-15T10:30:02Z 1.2.3.4 GET / 404\n\
+                     garbage\n";
+        let result = Ingest::new(Cursor::new(input)).run();
+        assert_eq!(result.entries.len(), 1);
+
+        let result = Ingest::new(Cursor::new(input)).lenient(true).run();
+        assert_eq!(result.entries.len(), 3);
+        assert_eq!(result.stats.partial, 2);
+        assert_eq!(result.malformed, 1);
+        assert_eq!(result.stats.level_counts[&LogLevel::Error], 1);
+        assert_eq!(result.stats.level_counts[&LogLevel::Warn], 1);
+        assert_eq!(result.stats.status_counts.values().sum::<usize>(), 2);
+    }
+
+    #[test]
+    fn sampling_parses_only_the_picked_lines() {
+        let input: String = (0..10)
+            .map(|i| format!("2024-01-15T10:30:0{}Z [INFO] 1.2.3.4 GET /{} 200\n", i, i))
+            .collect();
+        let result = Ingest::new(Cursor::new(&input)).sample(Some(Sampling::EveryNth(4))).run();
+        let endpoints: Vec<&str> = result.entries.iter().map(|e| e.endpoint.as_str()).collect();
+        assert_eq!(endpoints, ["/0", "/4", "/8"]);
+        assert_eq!((result.stats.lines_read, result.stats.sampled_out), (10, 7));
+
+        let kept = (1..=100_000).filter(|&n| Sampling::Fraction(0.01).keeps(n)).count();
+        assert!((900..1100).contains(&kept), "{}", kept);
+        assert!((1..=1000).all(|n| Sampling::Fraction(1.0).keeps(n)));
+    }
+
+    #[test]
+    fn skips_leading_lines_and_finds_the_last_ones() {
+        let input: String = (0..10)
+            .map(|i| format!("2024-01-15T10:30:0{}Z [INFO] 1.2.3.4 GET /{} 200\n", i, i))
+            .collect();
+        let result = Ingest::new(Cursor::new(&input)).skip_lines(6).max_lines(Some(3)).run();
+        let endpoints: Vec<&str> = result.entries.iter().map(|e| e.endpoint.as_str()).collect();
+        assert_eq!(endpoints, ["/6", "/7", "/8"]);
+        assert_eq!((result.stats.lines_read, result.stats.skipped), (9, 6));
+
+        let tail = |count| {
+            let offset = tail_offset(&mut Cursor::new(&input), count).unwrap() as usize;
+            let last = last_lines(&mut Cursor::new(&input), count).unwrap();
+            assert_eq!(&input.as_bytes()[offset..], &last[..]);
+            input[offset..].lines().count()
+        };
+        assert_eq!((tail(0), tail(2), tail(10), tail(20)), (0, 2, 10, 10));
+        let unterminated = "first\nsecond\nthird";
+        assert_eq!(tail_offset(&mut Cursor::new(unterminated), 1).unwrap(), 13);
+        assert_eq!(last_lines(&mut Cursor::new(unterminated), 2).unwrap(), b"second\nthird\n");
+
+        let w3c = "#Version: 1.0\n#Fields: date time c-ip\n2024-01-15 10:30:00 10.0.0.1\n";
+        let header = header_lines(&mut Cursor::new(w3c), &LogFormat::W3c(W3cFields::cloudfront())).unwrap();
+        assert_eq!(header, b"#Version: 1.0\n#Fields: date time c-ip\n");
+    }
+
+    #[test]
+    fn recovery_mode_rescues_near_miss_lines() {
+        let input = "2024-01-15T10:30:00Z [INFO] 1.2.3.4 GET / 200\n\
+                     2024-01-15T10:30:01Z ERROR 1.2.3.4 get /cart 500\n\
+                     2024-01-15T10:30:02Z 1.2.3.4 GET / 404\n\
+                     garbage\n";
+        let result = Ingest::new(Cursor::new(input)).recover(true).run();
+        assert_eq!(result.entries.len(), 2);
+        assert_eq!(result.stats.recovered, 1);
+        assert_eq!(result.malformed, 2);
+        assert_eq!(result.entries[1].method, HttpMethod::Get);
+
+        // With lenient parsing the line without a level is partial, not recovered
+        let result = Ingest::new(Cursor::new(input)).recover(true).lenient(true).run();
+        assert_eq!((result.stats.partial, result.stats.recovered, result.malformed), (1, 1, 1));
+    }
+
+    #[test]
+    fn multiline_mode_keeps_orphans_and_near_misses_malformed() {
+        let input = "    at com.example.Boot.main(Boot.java:7)\n\
+                     2024-01-15T10:30:00Z [INFO] 1.2.3.4 GET / 200\n\
+                     2024-01-15T10:30:01Z [INFO] bad_ip GET / 200\n\
+                     \x20   at com.example.Cart.total(Cart.java:42)\n";
+        let result = Ingest::new(Cursor::new(input)).multiline(true).run();
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.stats.continuation, 0);
+        assert_eq!(result.malformed, 3);
+    }
+
+    #[test]
+    fn w3c_directives_set_columns_and_are_not_malformed() {
+        let input = "#Version: 1.0\n\
+                     #Fields: date time c-ip cs-method cs-uri-stem sc-status\n\
+                     2024-01-15\t10:30:00\t1.2.3.4\tGET\t/\t200\n";
+        let result = Ingest::new(Cursor::new(input))
+            .format(LogFormat::W3c(W3cFields::cloudfront()))
+            .run();
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].ip.to_string(), "1.2.3.4");
+        assert_eq!(result.malformed, 0);
+    }
+
+    #[test]
+    fn stops_at_line_and_time_limits() {
+        let input = "2024-01-15T10:30:00Z [INFO] 1.2.3.4 GET / 200\n".repeat(1_000);
+
+        let result = Ingest::new(input.as_bytes()).max_lines(Some(10)).run();
+        assert_eq!(result.entries.len(), 10);
+        assert_eq!(result.incomplete.as_deref(), Some("line limit of 10 reached"));
+
+        let result = Ingest::new(input.as_bytes()).time_limit(Some(Duration::ZERO)).run();
+        assert!(result.entries.is_empty());
+        assert!(result.incomplete.unwrap().starts_with("time limit"));
+
+        let result = Ingest::new(input.as_bytes()).max_lines(Some(1_000)).run();
+        assert_eq!(result.entries.len(), 1_000);
+        assert!(result.incomplete.is_none());
+    }
+
+    fn write_lines(path: &Path, n: usize) {
+        let line = "2024-01-15T10:30:00Z [INFO] 1.2.3.4 GET / 200\n";
+        fs::write(path, line.repeat(n)).unwrap();
+    }
+
+    fn watched(path: &Path, policy: RotationPolicy, on_line_500: impl Fn() + 'static) -> IngestResult {
+        let mut fired = false;
+        Ingest::new(BufReader::new(File::open(path).unwrap()))
+            .watch_file(path, policy)
+            .on_progress(500, move |p| {
+                if p.lines_read == 500 && !fired {
+                    fired = true;
+                    on_line_500();
+                }
+            })
+            .run()
+    }
+
+    #[test]
+    fn stops_cleanly_when_input_is_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("access.log");
+        write_lines(&path, 5_000);
+        let target = path.clone();
+        let result = watched(&path, RotationPolicy::Reopen, move || {
+            fs::OpenOptions::new().write(true).open(&target).unwrap().set_len(0).unwrap();
+            fs::write(&target, "2024-01-15T10:30:00Z [INFO] 1.2.3.4 GET /fresh 200\n".repeat(100)).unwrap();
+        });
+        // Lines still buffered from before the truncation are read, but nothing written
+        // after it
+        assert!((500..5_000).contains(&result.entries.len()));
+        assert!(result.entries.iter().all(|e| e.endpoint != "/fresh"));
+        assert!(result.incomplete.unwrap().contains("truncated"));
+    }
+
+    #[test]
+    fn reopens_or_stops_after_rotation() {
+        for policy in [RotationPolicy::Reopen, RotationPolicy::Stop] {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("access.log");
+            write_lines(&path, 2_000);
+            let target = path.clone();
+            let result = watched(&path, policy, move || {
+                fs::rename(&target, target.with_extension("log.1")).unwrap();
+                write_lines(&target, 3);
+            });
+            if policy == RotationPolicy::Reopen {
+                assert_eq!(result.entries.len(), 2_003);
+                assert_eq!(result.reopened, 1);
+                assert!(result.incomplete.is_none());
+            } else {
+                assert_eq!(result.entries.len(), 2_000);
+                assert!(result.incomplete.unwrap().contains("rotated"));
+            }
+        }
+    }
+
+    #[test]
+    fn follows_appended_lines_through_truncation() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("access.log");
+        write_lines(&path, 3);
+        let target = path.clone();
+        let writer = std::thread::spawn(move || {
+            let pause = || std::thread::sleep(Duration::from_millis(300));
+            let mut file = fs::OpenOptions::new().append(true).open(&target).unwrap();
+            pause();
+            // A line written in two parts is parsed once, whole
+            file.write_all(b"2024-01-15T10:30:01Z [ERROR] 1.2.3.4 ").unwrap();
+            pause();
+            file.write_all(b"GET /late 500\n").unwrap();
+            pause();
+            file.set_len(0).unwrap();
+            file.write_all(b"2024-01-15T10:30:02Z [INFO] 1.2.3.4 GET /fresh 200\n").unwrap();
+        });
+
+        let mut refreshes = Vec::new();
+        let result = Ingest::new(BufReader::new(File::open(&path).unwrap()))
+            .watch_file(&path, RotationPolicy::Stop)
+            .follow(Duration::ZERO, |entries, _| refreshes.push(entries.len()))
+            .time_limit(Some(Duration::from_millis(2_000)))
+            .run();
+        writer.join().unwrap();
+
+        let endpoints: Vec<&str> = result.entries.iter().map(|e| e.endpoint.as_str()).collect();
+        assert_eq!(endpoints, ["/", "/", "/", "/late", "/fresh"]);
+        assert_eq!(result.malformed, 0);
+        assert_eq!(result.reopened, 1);
+        assert!(result.incomplete.unwrap().starts_with("time limit"));
+        assert_eq!(refreshes.first(), Some(&3));
+        assert_eq!(refreshes.last(), Some(&5));
+    }
+
+    #[test]
+    fn quiet_live_input_ends_after_the_idle_timeout() {
+        let line = b"2024-01-15T10:30:00Z [INFO] 1.2.3.4 GET / 200\n";
+        for follow in [false, true] {
+            // The sender stays open, as a writer that keeps a pipe open without writing
+            let (sender, lines) = mpsc::channel();
+            sender.send(Ok(line.repeat(2))).unwrap();
+            let source = LiveLines::new(lines, follow).idle_timeout(Some(Duration::from_millis(300)));
+            let ingest = Ingest::new(source);
+            let ingest = match follow {
+                true => ingest.follow(Duration::ZERO, |_, _| {}),
+                false => ingest,
+            };
+            let result = ingest.time_limit(Some(Duration::from_secs(5))).run();
+            assert_eq!(result.entries.len(), 2);
+            assert!(result.read_error.is_none());
+            match follow {
+                true => assert!(result.incomplete.unwrap().starts_with("nothing was written")),
+                false => assert!(result.incomplete.is_none()),
+            }
+            drop(sender);
+        }
+    }
+
+    #[test]
+    fn invalid_utf8_is_malformed() {
+        let bytes: &[u8] = b"\xff\xfe\n2024-01-15T10:30:00Z [INFO] 1.2.3.4 GET / 200\n";
+        let result = Ingest::new(Cursor::new(bytes)).run();
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.malformed, 1);
+    }
+}
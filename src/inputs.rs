@@ -0,0 +1,733 @@
+use serde::Serialize;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+/// Compression formats recognized in input files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    pub const ALL: [Compression; 4] = [Compression::Gzip, Compression::Zstd, Compression::Bzip2, Compression::Xz];
+
+    /// The bytes every file (or member) in this format starts with
+    fn magic(self) -> &'static [u8] {
+        match self {
+            Compression::Gzip => &[0x1f, 0x8b],
+            Compression::Zstd => &[0x28, 0xb5, 0x2f, 0xfd],
+            Compression::Bzip2 => b"BZh",
+            Compression::Xz => &[0xfd, b'7', b'z', b'X', b'Z', 0x00],
+        }
+    }
+
+    /// The file name extension this format is usually written with
+    fn extension(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gz",
+            Compression::Zstd => "zst",
+            Compression::Bzip2 => "bz2",
+            Compression::Xz => "xz",
+        }
+    }
+
+    /// The cargo feature that reads this format
+    pub fn feature(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Bzip2 => "bzip2",
+            Compression::Xz => "xz",
+        }
+    }
+
+    /// The format a file is compressed in, from its first bytes or else its extension
+    pub fn detect(header: &[u8], path: &Path) -> Option<Compression> {
+        Compression::ALL
+            .into_iter()
+            .find(|c| header.starts_with(c.magic()))
+            .or_else(|| {
+                let extension = path.extension()?.to_str()?;
+                Compression::ALL.into_iter().find(|c| c.extension().eq_ignore_ascii_case(extension))
+            })
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.feature())
+    }
+}
+
+/// An opened log file, decompressed as it is read when compressed
+pub enum LogFile {
+    /// An uncompressed file, which can be watched for rotation while it is read
+    Plain(BufReader<File>),
+    /// A compressed file with its decoder. Every member or frame is read, so
+    /// concatenated archives work too.
+    Compressed(Compression, Box<dyn BufRead>),
+    /// An uncompressed stream that is not a local file, such as an HTTP response body
+    Stream(Box<dyn BufRead>),
+}
+
+/// Open a log file, decompressing gzip, zstd, bzip2 and xz files on the fly.
+///
+/// The format is recognized by the file's magic bytes whatever its name, so a
+/// compressed file without the usual suffix works too; a file with a suffix but
+/// none of the magic bytes is decoded by its suffix, and fails to. Compressed
+/// files whose cargo feature this build lacks are an error.
+pub fn open(path: &Path) -> io::Result<LogFile> {
+    let mut reader = BufReader::new(File::open(path)?);
+    match Compression::detect(reader.fill_buf()?, path) {
+        Some(compression) => Ok(LogFile::Compressed(compression, decompress(compression, reader)?)),
+        None => Ok(LogFile::Plain(reader)),
+    }
+}
+
+/// Read a stream that is not a local file, such as a download, decompressing it as
+/// [`open`] does a file; `name` stands in for the file name
+pub fn open_stream(mut reader: impl BufRead + 'static, name: &Path) -> io::Result<LogFile> {
+    match Compression::detect(reader.fill_buf()?, name) {
+        Some(compression) => Ok(LogFile::Compressed(compression, decompress(compression, reader)?)),
+        None => Ok(LogFile::Stream(Box::new(reader))),
+    }
+}
+
+/// Whether `path` is a named pipe (FIFO) or a character device such as `/dev/stdin`:
+/// input that can only be read once, from its start, and that may pause for as long
+/// as its writer likes without ending
+pub fn is_pipe(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        fs::metadata(path).is_ok_and(|meta| meta.file_type().is_fifo() || meta.file_type().is_char_device())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// A named pipe or character device ([`is_pipe`]), opened when first read, since
+/// opening a named pipe waits for a writer to open its other end.
+///
+/// When `reopen`, a named pipe is opened again each time its writer closes it, to
+/// wait for the next one, so it never ends; as a character device does not wait,
+/// it ends as usual.
+pub struct PipeReader {
+    path: PathBuf,
+    file: Option<File>,
+    reopen: bool,
+}
+
+impl PipeReader {
+    pub fn new(path: impl Into<PathBuf>, reopen: bool) -> Self {
+        let path = path.into();
+        #[cfg(unix)]
+        let reopen = {
+            use std::os::unix::fs::FileTypeExt;
+            reopen && fs::metadata(&path).is_ok_and(|meta| meta.file_type().is_fifo())
+        };
+        PipeReader { path, file: None, reopen }
+    }
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let file = match &mut self.file {
+                Some(file) => file,
+                None => self.file.insert(File::open(&self.path)?),
+            };
+            let n = file.read(buf)?;
+            if n > 0 || buf.is_empty() || !self.reopen {
+                return Ok(n);
+            }
+            self.file = None;
+        }
+    }
+}
+
+/// Wrap `reader` in the decoder for `compression`
+#[cfg_attr(
+    not(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz")),
+    allow(unused_variables)
+)]
+fn decompress(compression: Compression, reader: impl BufRead + 'static) -> io::Result<Box<dyn BufRead>> {
+    match compression {
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Ok(Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader)))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(Box::new(BufReader::new(ZstdFrames::new(reader)))),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Ok(Box::new(BufReader::new(bzip2::bufread::MultiBzDecoder::new(reader)))),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Ok(Box::new(BufReader::new(lzma_rust2::XzReader::new(reader, true)))),
+        #[allow(unreachable_patterns)]
+        other => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "file is {}-compressed, but this build lacks the `{}` feature; decompress it first",
+                other,
+                other.feature()
+            ),
+        )),
+    }
+}
+
+/// Decodes every frame of a zstd stream in turn; the decoder stops after one
+#[cfg(feature = "zstd")]
+struct ZstdFrames<R: BufRead> {
+    source: Option<R>,
+    frame: Option<ruzstd::decoding::StreamingDecoder<R, ruzstd::decoding::FrameDecoder>>,
+    decoder: Option<ruzstd::decoding::FrameDecoder>,
+}
+
+#[cfg(feature = "zstd")]
+impl<R: BufRead> ZstdFrames<R> {
+    fn new(source: R) -> Self {
+        ZstdFrames {
+            source: Some(source),
+            frame: None,
+            decoder: Some(ruzstd::decoding::FrameDecoder::new()),
+        }
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<R: BufRead> Read for ZstdFrames<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(frame) = &mut self.frame {
+                let n = frame.read(buf)?;
+                if n > 0 || buf.is_empty() {
+                    return Ok(n);
+                }
+                let (source, decoder) = self.frame.take().expect("frame is set").into_parts();
+                self.source = Some(source);
+                self.decoder = Some(decoder);
+            }
+            let Some(mut source) = self.source.take() else { return Ok(0) };
+            if source.fill_buf()?.is_empty() {
+                return Ok(0);
+            }
+            let decoder = self.decoder.take().unwrap_or_default();
+            let frame = ruzstd::decoding::StreamingDecoder::new_with_decoder(source, decoder).map_err(io::Error::other)?;
+            self.frame = Some(frame);
+        }
+    }
+}
+
+impl Read for LogFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            LogFile::Plain(r) => r.read(buf),
+            LogFile::Compressed(_, r) | LogFile::Stream(r) => r.read(buf),
+        }
+    }
+}
+
+impl BufRead for LogFile {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            LogFile::Plain(r) => r.fill_buf(),
+            LogFile::Compressed(_, r) | LogFile::Stream(r) => r.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self {
+            LogFile::Plain(r) => r.consume(amt),
+            LogFile::Compressed(_, r) | LogFile::Stream(r) => r.consume(amt),
+        }
+    }
+}
+
+/// What one of several input files contributed to a merged analysis
+#[derive(Debug, Clone, Serialize)]
+pub struct FileSummary {
+    pub path: String,
+    /// Format the file was read as, when picked by `--format auto`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Lines parsed into entries
+    pub parsed: usize,
+    pub malformed: usize,
+    /// Why the file was not read to the end, if it wasn't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub incomplete: Option<String>,
+}
+
+/// Why a `LOG_FILE` pattern could not be expanded
+#[derive(Debug)]
+pub enum GlobError {
+    /// No file matched the pattern
+    NoMatch(String),
+    /// A directory held no file that `--include` and `--exclude` keep
+    EmptyDir(PathBuf),
+    /// A directory named by the pattern could not be listed
+    Io { path: PathBuf, error: io::Error },
+}
+
+impl fmt::Display for GlobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GlobError::NoMatch(pattern) => write!(f, "no files match '{}'", pattern),
+            GlobError::EmptyDir(dir) => write!(f, "no log files found under '{}'", dir.display()),
+            GlobError::Io { path, error } => write!(f, "could not list '{}': {}", path.display(), error),
+        }
+    }
+}
+
+/// Whether a `LOG_FILE` argument is an `http://` or `https://` URL rather than a path
+pub fn is_url(s: &str) -> bool {
+    let scheme = s.split_once("://").map_or("", |(scheme, _)| scheme);
+    scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+}
+
+/// Whether a `LOG_FILE` argument is an `s3://bucket/prefix` URL
+pub fn is_s3_url(s: &str) -> bool {
+    s.get(..5).is_some_and(|scheme| scheme.eq_ignore_ascii_case("s3://"))
+}
+
+/// Whether `s` uses any of the wildcards `*`, `?` and `[...]`
+pub fn is_glob(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+/// The sources listed in a manifest (`--files-from`), one path, pattern or URL per
+/// line. Surrounding whitespace is trimmed, and blank lines and `#` comments skipped.
+pub fn read_manifest(reader: impl BufRead) -> io::Result<Vec<String>> {
+    let mut sources = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            sources.push(line.to_string());
+        }
+    }
+    Ok(sources)
+}
+
+/// Expand a shell-style pattern such as `access.log*` or `logs/*/app-[0-9].log`
+/// into the files it matches, sorted by path within each wildcard component.
+///
+/// Wildcards may appear in any path component; `*` and `?` do not match a leading
+/// `.`, as in the shell. A path without wildcards is returned as is, whether or not
+/// it exists, so opening it reports the error.
+pub fn expand(pattern: &str) -> Result<Vec<PathBuf>, GlobError> {
+    if !is_glob(pattern) {
+        return Ok(vec![PathBuf::from(pattern)]);
+    }
+    let mut paths = vec![PathBuf::new()];
+    for component in Path::new(pattern).components() {
+        let name = match component {
+            Component::Normal(name) => name.to_string_lossy(),
+            other => {
+                paths.iter_mut().for_each(|p| p.push(other));
+                continue;
+            }
+        };
+        if !is_glob(&name) {
+            paths.iter_mut().for_each(|p| p.push(&*name));
+            continue;
+        }
+        let pattern: Vec<char> = name.chars().collect();
+        let mut expanded = Vec::new();
+        for base in paths {
+            let dir = if base.as_os_str().is_empty() { Path::new(".") } else { base.as_path() };
+            let listing = match fs::read_dir(dir) {
+                Ok(listing) => listing,
+                // Earlier wildcards may match plain files, which have nothing to list
+                Err(_) if !dir.is_dir() => continue,
+                Err(error) => return Err(GlobError::Io { path: dir.to_path_buf(), error }),
+            };
+            let mut names: Vec<String> = listing
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .filter(|name| matches_name(&pattern, name))
+                .collect();
+            names.sort();
+            expanded.extend(names.into_iter().map(|name| base.join(name)));
+        }
+        paths = expanded;
+    }
+
+    paths.retain(|p| p.is_file());
+    if paths.is_empty() {
+        return Err(GlobError::NoMatch(pattern.to_string()));
+    }
+    Ok(paths)
+}
+
+/// How a rotated copy of a log is named, in the order copies are read
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Rotation {
+    /// `access.log-20240615` (logrotate's `dateext`): later dates are newer
+    Dated(String),
+    /// `access.log.2`: higher numbers are older, so they sort first
+    Numbered(std::cmp::Reverse<u32>),
+}
+
+/// Recognize `name` as a rotated copy of `base`, optionally compressed:
+/// `access.log.1`, `access.log.2.gz` or `access.log-20240615.xz`
+fn rotation(base: &str, name: &str) -> Option<Rotation> {
+    let rest = name.strip_prefix(base)?;
+    let rest = Compression::ALL
+        .into_iter()
+        .find_map(|c| rest.strip_suffix(c.extension())?.strip_suffix('.'))
+        .unwrap_or(rest);
+    let (separator, token) = (rest.chars().next()?, rest.get(1..)?);
+    let digits = token.chars().filter(char::is_ascii_digit).count();
+    if !token.starts_with(|c: char| c.is_ascii_digit()) || !token.chars().all(|c| c.is_ascii_digit() || c == '-' || c == '_') {
+        return None;
+    }
+    match separator {
+        '.' if digits == token.len() && digits <= 6 => token.parse().ok().map(|n| Rotation::Numbered(std::cmp::Reverse(n))),
+        '.' | '-' if digits >= 8 => Some(Rotation::Dated(token.to_string())),
+        _ => None,
+    }
+}
+
+/// The rotated copies of `path` next to it, oldest first: dated copies
+/// (`access.log-20240614`, …) by date, then numbered ones from the highest number
+/// down to `access.log.1`. Compressed copies count too. `path` itself is not
+/// included, and a directory that cannot be listed has no copies.
+pub fn rotated(path: &Path) -> Vec<PathBuf> {
+    let Some(base) = path.file_name().and_then(|n| n.to_str()) else {
+        return Vec::new();
+    };
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let Ok(listing) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut copies: Vec<(Rotation, String)> = listing
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            Some((rotation(base, &name)?, name))
+        })
+        .collect();
+    copies.sort();
+    copies.into_iter().map(|(_, name)| path.with_file_name(name)).filter(|p| p.is_file()).collect()
+}
+
+/// Find the files under `dir`, recursively, sorted by path within each directory.
+///
+/// With `include` patterns, only files matching one of them are kept; files and
+/// directories matching an `exclude` pattern are skipped. A pattern without a `/`
+/// matches a file or directory name at any depth (`*.log*`, `archive`), one with a
+/// `/` the whole path relative to `dir` (`nginx/*.log`). Hidden files and
+/// directories are skipped, as are symbolic links to directories, which could loop.
+pub fn walk(dir: &Path, include: &[String], exclude: &[String]) -> Result<Vec<PathBuf>, GlobError> {
+    let mut files = Vec::new();
+    walk_into(dir, Path::new(""), include, exclude, &mut files)?;
+    if files.is_empty() {
+        return Err(GlobError::EmptyDir(dir.to_path_buf()));
+    }
+    Ok(files)
+}
+
+fn walk_into(
+    root: &Path,
+    relative: &Path,
+    include: &[String],
+    exclude: &[String],
+    files: &mut Vec<PathBuf>,
+) -> Result<(), GlobError> {
+    let dir = root.join(relative);
+    let listing = fs::read_dir(&dir).map_err(|error| GlobError::Io { path: dir.clone(), error })?;
+    let mut children: Vec<(String, fs::FileType)> = listing
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            Some((entry.file_name().into_string().ok()?, entry.file_type().ok()?))
+        })
+        .filter(|(name, _)| !name.starts_with('.'))
+        .collect();
+    children.sort_by(|a, b| a.0.cmp(&b.0));
+    for (name, kind) in children {
+        let path = relative.join(name);
+        if exclude.iter().any(|pattern| path_matches(pattern, &path)) {
+            continue;
+        }
+        if kind.is_dir() {
+            walk_into(root, &path, include, exclude, files)?;
+        } else if (kind.is_file() || kind.is_symlink() && root.join(&path).is_file())
+            && (include.is_empty() || include.iter().any(|pattern| path_matches(pattern, &path)))
+        {
+            files.push(root.join(path));
+        }
+    }
+    Ok(())
+}
+
+/// Whether a file at `relative` below a listed location, such as an S3 prefix, is
+/// kept by `--include` and `--exclude` as it would be by [`walk`], where an excluded
+/// directory excludes everything under it
+pub fn is_included(relative: &Path, include: &[String], exclude: &[String]) -> bool {
+    let excluded = relative
+        .ancestors()
+        .filter(|p| !p.as_os_str().is_empty())
+        .any(|p| exclude.iter().any(|pattern| path_matches(pattern, p)));
+    !excluded && (include.is_empty() || include.iter().any(|pattern| path_matches(pattern, relative)))
+}
+
+/// Match a path relative to a walked directory against an `--include` or
+/// `--exclude` pattern, as described for [`walk`]
+fn path_matches(pattern: &str, relative: &Path) -> bool {
+    let names: Vec<String> = relative.iter().map(|name| name.to_string_lossy().into_owned()).collect();
+    let parts: Vec<Vec<char>> = pattern.trim_matches('/').split('/').map(|part| part.chars().collect()).collect();
+    match parts.as_slice() {
+        [name_pattern] => names.last().is_some_and(|name| matches_name(name_pattern, name)),
+        _ => parts.len() == names.len() && parts.iter().zip(&names).all(|(part, name)| matches_name(part, name)),
+    }
+}
+
+/// Match a file name against one component of a pattern
+fn matches_name(pattern: &[char], name: &str) -> bool {
+    if name.starts_with('.') && pattern.first() != Some(&'.') {
+        return false;
+    }
+    matches(pattern, &name.chars().collect::<Vec<_>>())
+}
+
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| matches(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && matches(rest, &name[1..]),
+        Some(('[', rest)) => match name.split_first() {
+            None => false,
+            Some((&c, tail)) => match bracket(rest, c) {
+                Some((matched, after)) => matched && matches(after, tail),
+                // An unclosed bracket is an ordinary character
+                None => c == '[' && matches(rest, tail),
+            },
+        },
+        Some((c, rest)) => name.first() == Some(c) && matches(rest, &name[1..]),
+    }
+}
+
+/// Match `c` against the bracket expression that `rest` starts (just after its `[`),
+/// such as `[0-9]`, `[!~]` or `[]ab]`.
+///
+/// Returns whether it matched and the pattern after the closing `]`, or `None`
+/// when the bracket is never closed.
+fn bracket(rest: &[char], c: char) -> Option<(bool, &[char])> {
+    let (negated, body) = match rest.first() {
+        Some('!' | '^') => (true, &rest[1..]),
+        _ => (false, rest),
+    };
+    let mut matched = false;
+    let mut i = 0;
+    loop {
+        let first = *body.get(i)?;
+        if first == ']' && i > 0 {
+            break;
+        }
+        match (body.get(i + 1), body.get(i + 2)) {
+            (Some('-'), Some(&last)) if last != ']' => {
+                matched |= (first..=last).contains(&c);
+                i += 3;
+            }
+            _ => {
+                matched |= first == c;
+                i += 1;
+            }
+        }
+    }
+    Some((matched != negated, &body[i + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob_matches(pattern: &str, name: &str) -> bool {
+        matches_name(&pattern.chars().collect::<Vec<_>>(), name)
+    }
+
+    #[test]
+    fn matches_wildcards_and_brackets() {
+        assert!(glob_matches("access.log*", "access.log"));
+        assert!(glob_matches("access.log*", "access.log.2.gz"));
+        assert!(!glob_matches("access.log*", "error.log"));
+        assert!(glob_matches("app-?.log", "app-1.log"));
+        assert!(!glob_matches("app-?.log", "app-10.log"));
+        assert!(glob_matches("app-[0-9][0-9].log", "app-10.log"));
+        assert!(!glob_matches("app-[!0-9].log", "app-1.log"));
+        assert!(glob_matches("[]x].log", "].log"));
+        assert!(glob_matches("a[b.log", "a[b.log"));
+        assert!(!glob_matches("*.log", ".hidden.log"));
+        assert!(glob_matches(".*.log", ".hidden.log"));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn decompresses_gzipped_files_by_content() {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        // Two members, as left by appending to a rotated archive
+        let mut compressed = Vec::new();
+        for text in ["first line\n", "second line\n"] {
+            let mut gzip = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            gzip.write_all(text.as_bytes()).unwrap();
+            compressed.extend(gzip.finish().unwrap());
+        }
+        let path = dir.path().join("access.log.1");
+        fs::write(&path, compressed).unwrap();
+        let file = open(&path).unwrap();
+        assert!(matches!(file, LogFile::Compressed(Compression::Gzip, _)));
+        let lines: Vec<String> = file.lines().collect::<Result<_, _>>().unwrap();
+        assert_eq!(lines, ["first line", "second line"]);
+
+        fs::write(&path, "plain line\n").unwrap();
+        assert!(matches!(open(&path).unwrap(), LogFile::Plain(_)));
+    }
+
+    #[test]
+    fn detects_compression_by_magic_then_extension() {
+        let plain = Path::new("access.log");
+        assert_eq!(Compression::detect(&[0x1f, 0x8b, 8, 0], plain), Some(Compression::Gzip));
+        assert_eq!(Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd, 0], plain), Some(Compression::Zstd));
+        assert_eq!(Compression::detect(b"BZh91AY&SY", plain), Some(Compression::Bzip2));
+        assert_eq!(Compression::detect(b"\xfd7zXZ\x00\x00", plain), Some(Compression::Xz));
+        assert_eq!(Compression::detect(b"127.0.0.1 - -", plain), None);
+        // The magic bytes win over a misleading suffix
+        assert_eq!(Compression::detect(b"BZh91AY&SY", Path::new("a.log.gz")), Some(Compression::Bzip2));
+        assert_eq!(Compression::detect(b"", Path::new("a.log.XZ")), Some(Compression::Xz));
+        assert_eq!(Compression::detect(b"", Path::new("a.log.zst")), Some(Compression::Zstd));
+    }
+
+    #[test]
+    fn walks_directories_with_include_and_exclude() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for sub in ["nginx", "nginx/archive", "app", ".git"] {
+            fs::create_dir(root.join(sub)).unwrap();
+        }
+        for name in [
+            "nginx/access.log",
+            "nginx/access.log.1.gz",
+            "nginx/archive/access.log.9",
+            "nginx/README",
+            "app/app.log",
+            "app/.app.log.swp",
+            ".git/HEAD",
+            "top.log",
+        ] {
+            fs::write(root.join(name), "").unwrap();
+        }
+        let relative = |files: Vec<PathBuf>| -> Vec<String> {
+            files.iter().map(|p| p.strip_prefix(root).unwrap().display().to_string()).collect()
+        };
+        let strings = |patterns: &[&str]| -> Vec<String> { patterns.iter().map(|p| p.to_string()).collect() };
+
+        let all = walk(root, &[], &[]).unwrap();
+        assert_eq!(
+            relative(all),
+            [
+                "app/app.log",
+                "nginx/README",
+                "nginx/access.log",
+                "nginx/access.log.1.gz",
+                "nginx/archive/access.log.9",
+                "top.log"
+            ]
+        );
+        let logs = walk(root, &strings(&["*.log*"]), &strings(&["archive"])).unwrap();
+        assert_eq!(relative(logs), ["app/app.log", "nginx/access.log", "nginx/access.log.1.gz", "top.log"]);
+        let nginx = walk(root, &strings(&["nginx/*.log*"]), &strings(&["*.gz"])).unwrap();
+        assert_eq!(relative(nginx), ["nginx/access.log"]);
+        assert!(matches!(walk(root, &strings(&["*.txt"]), &[]), Err(GlobError::EmptyDir(_))));
+    }
+
+    #[test]
+    fn filters_listed_keys_like_a_walk() {
+        let strings = |patterns: &[&str]| -> Vec<String> { patterns.iter().map(|p| p.to_string()).collect() };
+        let (include, exclude) = (strings(&["*.log*"]), strings(&["archive"]));
+        assert!(is_included(Path::new("nginx/access.log.1.gz"), &include, &exclude));
+        assert!(!is_included(Path::new("nginx/archive/access.log.9"), &include, &exclude));
+        assert!(!is_included(Path::new("nginx/README"), &include, &exclude));
+        assert!(is_included(Path::new("nginx/README"), &[], &[]));
+        assert!(is_s3_url("S3://logs/nginx/") && !is_s3_url("s3:/logs"));
+    }
+
+    #[test]
+    fn finds_rotated_copies_oldest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "access.log",
+            "access.log.1",
+            "access.log.2.gz",
+            "access.log.10.zst",
+            "access.log-20240614.xz",
+            "access.log-20240613",
+            "access.log.bak",
+            "access.log.1.swp",
+            "access.logger.1",
+            "error.log.1",
+        ] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        let copies = rotated(&dir.path().join("access.log"));
+        let names: Vec<_> = copies.iter().map(|p| p.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(
+            names,
+            [
+                "access.log-20240613",
+                "access.log-20240614.xz",
+                "access.log.10.zst",
+                "access.log.2.gz",
+                "access.log.1"
+            ]
+        );
+        assert!(rotated(&dir.path().join("missing.log")).is_empty());
+    }
+
+    #[test]
+    fn expands_patterns_into_sorted_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["access.log", "access.log.2", "access.log.1", "error.log"] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        fs::create_dir(dir.path().join("access.log.d")).unwrap();
+        let root = dir.path().display().to_string();
+
+        let files = expand(&format!("{}/access.log*", root)).unwrap();
+        let names: Vec<_> = files.iter().map(|p| p.file_name().unwrap().to_str().unwrap()).collect();
+        assert_eq!(names, ["access.log", "access.log.1", "access.log.2"]);
+        assert!(matches!(expand(&format!("{}/*.txt", root)), Err(GlobError::NoMatch(_))));
+        assert_eq!(expand("missing.log").unwrap(), [PathBuf::from("missing.log")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tells_pipes_and_devices_from_files() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        assert!(!is_pipe(file.path()));
+        assert!(is_pipe(Path::new("/dev/null")));
+        assert!(!is_pipe(Path::new("missing.log")));
+        let mut empty = String::new();
+        PipeReader::new("/dev/null", true).read_to_string(&mut empty).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn reads_manifest_entries() {
+        let manifest = "# batch 42\n/var/log/nginx/access.log\n\n  https://logs.example.com/edge.log.gz \r\ns3://logs/2024/06/\n";
+        assert_eq!(
+            read_manifest(io::Cursor::new(manifest)).unwrap(),
+            ["/var/log/nginx/access.log", "https://logs.example.com/edge.log.gz", "s3://logs/2024/06/"]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::io::{self, BufRead, Read};
+
+/// Reads `journalctl -o export` output as one JSON object per journal entry, the
+/// layout `journalctl -o json` prints, so entries can be ingested line by line with
+/// [`crate::parser::LogFormat::Journal`].
+///
+/// Export entries are `KEY=value` lines ended by a blank line. Fields holding
+/// newlines or binary data are written as the key alone on its line, then the
+/// value's length as a little-endian `u64`, the value and a newline. Values that
+/// are not UTF-8 become arrays of bytes, as `journalctl -o json` prints them.
+/// Input that is already `-o json` passes through unchanged.
+pub struct ExportReader<R> {
+    inner: R,
+    /// The current entry as a JSON line, and how much of it has been read
+    line: Vec<u8>,
+    consumed: usize,
+}
+
+impl<R: BufRead> ExportReader<R> {
+    pub fn new(inner: R) -> Self {
+        ExportReader {
+            inner,
+            line: Vec::new(),
+            consumed: 0,
+        }
+    }
+
+    /// Read the next entry as a JSON line, or `None` at the end of the input
+    fn next_entry(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut fields = serde_json::Map::new();
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            if self.inner.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            if buf.last() == Some(&b'\n') {
+                buf.pop();
+            }
+            if buf.is_empty() {
+                if fields.is_empty() {
+                    continue;
+                }
+                break;
+            }
+            if fields.is_empty() && buf.starts_with(b"{") {
+                buf.push(b'\n');
+                return Ok(Some(buf));
+            }
+            let (key, value) = match buf.iter().position(|&b| b == b'=') {
+                Some(eq) => (&buf[..eq], buf[eq + 1..].to_vec()),
+                None => (&buf[..], self.binary_value()?),
+            };
+            let value = match String::from_utf8(value) {
+                Ok(text) => serde_json::Value::from(text),
+                Err(e) => serde_json::Value::from(e.into_bytes()),
+            };
+            fields.entry(String::from_utf8_lossy(key)).or_insert(value);
+        }
+        if fields.is_empty() {
+            return Ok(None);
+        }
+        let mut line = serde_json::Value::Object(fields).to_string().into_bytes();
+        line.push(b'\n');
+        Ok(Some(line))
+    }
+
+    /// Read a length-prefixed field value and the newline after it
+    fn binary_value(&mut self) -> io::Result<Vec<u8>> {
+        let mut size = [0u8; 8];
+        self.inner.read_exact(&mut size)?;
+        let size = u64::from_le_bytes(size);
+        let mut value = Vec::new();
+        (&mut self.inner).take(size).read_to_end(&mut value)?;
+        if value.len() as u64 != size {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "binary journal field is truncated"));
+        }
+        let mut newline = [0u8; 1];
+        self.inner.read_exact(&mut newline)?;
+        if newline != [b'\n'] {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "binary journal field is not followed by a newline"));
+        }
+        Ok(value)
+    }
+}
+
+impl<R: BufRead> Read for ExportReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = buf.len().min(available.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for ExportReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.consumed == self.line.len() {
+            self.line = self.next_entry()?.unwrap_or_default();
+            self.consumed = 0;
+        }
+        Ok(&self.line[self.consumed..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.consumed = (self.consumed + amount).min(self.line.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(input: &[u8]) -> Vec<String> {
+        ExportReader::new(input).lines().map(|l| l.unwrap()).collect()
+    }
+
+    #[test]
+    fn joins_export_entries_into_json_lines() {
+        let mut input = b"__REALTIME_TIMESTAMP=1705314600000000\nPRIORITY=6\nMESSAGE=GET / 200\n\n".to_vec();
+        input.extend_from_slice(b"PRIORITY=3\nMESSAGE\n");
+        input.extend_from_slice(&11u64.to_le_bytes());
+        input.extend_from_slice(b"line\nbroken\n_SYSTEMD_UNIT=app.service\n");
+
+        assert_eq!(
+            lines(&input),
+            [
+                r#"{"MESSAGE":"GET / 200","PRIORITY":"6","__REALTIME_TIMESTAMP":"1705314600000000"}"#,
+                r#"{"MESSAGE":"line\nbroken","PRIORITY":"3","_SYSTEMD_UNIT":"app.service"}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_binary_values_and_json_input() {
+        let mut input = b"MESSAGE\n".to_vec();
+        input.extend_from_slice(&2u64.to_le_bytes());
+        input.extend_from_slice(&[0xff, b'a', b'\n']);
+        assert_eq!(lines(&input), [r#"{"MESSAGE":[255,97]}"#]);
+
+        let json = "{\"MESSAGE\":\"a\"}\n{\"MESSAGE\":\"b\"}\n";
+        assert_eq!(lines(json.as_bytes()), [r#"{"MESSAGE":"a"}"#, r#"{"MESSAGE":"b"}"#]);
+
+        let mut truncated = b"MESSAGE\n".to_vec();
+        truncated.extend_from_slice(&64u64.to_le_bytes());
+        truncated.extend_from_slice(b"short");
+        assert!(ExportReader::new(&truncated[..]).lines().next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::ingest::LiveLines;
+use crate::journal::ExportReader;
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+/// The local systemd journal of some units, read live rather than from an export
+/// (`--journal --unit`).
+///
+/// `journalctl` is run in the background, so whatever the journal can be read with,
+/// including its access rules, applies as is. Entries are read as one JSON line each,
+/// like [`ExportReader`] makes of an export, for [`crate::parser::LogFormat::Journal`].
+/// Without following, reading ends after the last entry already written; when
+/// following, reading past the last entry yields nothing until the next one is
+/// written, as for a file that has not grown yet.
+pub struct JournalSource {
+    child: Child,
+    entries: LiveLines,
+    follow: bool,
+    /// Whether journalctl has been waited for, once every entry was received
+    exited: bool,
+}
+
+impl JournalSource {
+    /// Read the journal entries of `units`, then keep reading new ones if `follow`
+    pub fn open(units: &[String], follow: bool) -> io::Result<Self> {
+        let mut command = Command::new("journalctl");
+        command.args(["--output=export", "--no-pager"]);
+        command.args(units.iter().map(|unit| format!("--unit={}", unit)));
+        if follow {
+            command.arg("--follow");
+        }
+        Self::spawn(command, follow)
+    }
+
+    /// Run `command`, which writes journal export entries, reading them in the background
+    fn spawn(mut command: Command, follow: bool) -> io::Result<Self> {
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        Ok(JournalSource {
+            child,
+            entries: LiveLines::spawn(ExportReader::new(BufReader::new(stdout)), follow),
+            follow,
+            exited: false,
+        })
+    }
+
+    /// Up to `limit` of the next entries, without reading them, waiting at most `wait`
+    /// for them to be written; e.g. to detect the format of the messages
+    pub fn peek_lines(&mut self, limit: usize, wait: Duration) -> Vec<String> {
+        self.entries.peek_lines(limit, wait)
+    }
+}
+
+impl Read for JournalSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = buf.len().min(available.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for JournalSource {
+    /// Once every entry is read, an error if journalctl failed or was following
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.entries.fill_buf()?.is_empty() && self.entries.ended() && !self.exited {
+            self.exited = true;
+            let status = self.child.wait()?;
+            if !status.success() || self.follow {
+                return Err(io::Error::other(format!("journalctl stopped ({})", status)));
+            }
+        }
+        self.entries.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.entries.consume(amt);
+    }
+}
+
+impl Drop for JournalSource {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        command
+    }
+
+    #[test]
+    fn reads_entries_until_journalctl_is_done() {
+        let script = r"printf 'PRIORITY=6\nMESSAGE=first\n\nPRIORITY=3\nMESSAGE=second\n\n'";
+        let mut source = JournalSource::spawn(shell(script), false).unwrap();
+        assert_eq!(source.peek_lines(1, Duration::from_secs(5)), [r#"{"MESSAGE":"first","PRIORITY":"6"}"#]);
+        let lines: Vec<String> = (&mut source).lines().collect::<Result<_, _>>().unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1], r#"{"MESSAGE":"second","PRIORITY":"3"}"#);
+
+        let mut failed = JournalSource::spawn(shell("exit 1"), false).unwrap();
+        assert!(failed.fill_buf().is_err());
+    }
+
+    #[test]
+    fn followed_journal_has_nothing_until_an_entry_is_written() {
+        let script = r"printf 'MESSAGE=first\n\n'; sleep 1; printf 'MESSAGE=second\n\n'; sleep 5";
+        let mut source = JournalSource::spawn(shell(script), true).unwrap();
+        let mut line = String::new();
+        while line.is_empty() {
+            source.read_line(&mut line).unwrap();
+        }
+        assert_eq!(line, "{\"MESSAGE\":\"first\"}\n");
+        line.clear();
+        assert_eq!(source.read_line(&mut line).unwrap(), 0);
+        while line.is_empty() {
+            source.read_line(&mut line).unwrap();
+        }
+        assert_eq!(line, "{\"MESSAGE\":\"second\"}\n");
+    }
+}
@@ -0,0 +1,221 @@
+use crate::ingest::Windowed;
+use rdkafka::client::ClientContext;
+use rdkafka::config::{ClientConfig, RDKafkaLogLevel};
+use rdkafka::consumer::{BaseConsumer, Consumer, ConsumerContext};
+use rdkafka::error::KafkaError;
+use rdkafka::message::Message;
+use std::io::{self, BufRead, Read};
+use std::time::{Duration, Instant};
+
+/// Longest a single poll of the consumer blocks, so window ends are noticed promptly
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The cluster, topics and consumer group to consume from
+#[derive(Debug, Clone)]
+pub struct KafkaConfig {
+    /// Bootstrap brokers, e.g. `broker1:9092,broker2:9092`
+    pub brokers: String,
+    pub topics: Vec<String>,
+    /// Consumer group; its committed offsets let a restarted consumer carry on
+    pub group: String,
+    /// Where a group without committed offsets starts: the oldest retained
+    /// messages instead of only new ones
+    pub from_beginning: bool,
+}
+
+/// Messages consumed from Kafka topics, read as a stream of log lines so they can
+/// be ingested like a file.
+///
+/// Each message payload is one or more lines. Reading ends at the end of the current
+/// window (see [`Windowed`]). Errors
+/// reported by the client, such as an unreachable broker, are logged and the
+/// consumer keeps trying.
+pub struct KafkaSource {
+    consumer: BaseConsumer<Diagnostics>,
+    pending: Pending,
+    window_end: Option<Instant>,
+    /// The last error polling reported, so a broker that stays down is warned about once
+    last_error: Option<String>,
+}
+
+/// Received messages, newline-terminated, and how much of them has been read
+#[derive(Default)]
+struct Pending {
+    data: Vec<u8>,
+    consumed: usize,
+}
+
+impl Pending {
+    fn unread(&self) -> &[u8] {
+        &self.data[self.consumed..]
+    }
+
+    fn push(&mut self, payload: &[u8]) {
+        if self.consumed == self.data.len() {
+            self.data.clear();
+            self.consumed = 0;
+        }
+        self.data.extend_from_slice(payload);
+        if !payload.ends_with(b"\n") {
+            self.data.push(b'\n');
+        }
+    }
+}
+
+impl KafkaSource {
+    /// Join the consumer group and subscribe to the topics. Brokers are only
+    /// contacted once messages are polled.
+    pub fn connect(config: &KafkaConfig) -> io::Result<Self> {
+        let consumer: BaseConsumer<Diagnostics> = ClientConfig::new()
+            .set("bootstrap.servers", &config.brokers)
+            .set("group.id", &config.group)
+            .set("auto.offset.reset", if config.from_beginning { "earliest" } else { "latest" })
+            .set("client.id", concat!("log_analyzer/", env!("CARGO_PKG_VERSION")))
+            // Deliver the client's logs to `Diagnostics` when polled, rather than to stderr
+            .set("log.queue", "true")
+            .create_with_context(Diagnostics)
+            .map_err(io::Error::other)?;
+        let topics: Vec<&str> = config.topics.iter().map(String::as_str).collect();
+        consumer.subscribe(&topics).map_err(io::Error::other)?;
+        Ok(KafkaSource {
+            consumer,
+            pending: Pending::default(),
+            window_end: None,
+            last_error: None,
+        })
+    }
+
+    /// Wait up to `timeout` for a message and buffer it
+    fn poll(&mut self, timeout: Duration) {
+        match self.consumer.poll(timeout) {
+            Some(Ok(message)) => {
+                if let Some(payload) = message.payload() {
+                    self.pending.push(payload);
+                }
+                self.last_error = None;
+            }
+            Some(Err(e)) => {
+                let error = e.to_string();
+                if self.last_error.as_ref() != Some(&error) {
+                    tracing::warn!("kafka: {}", error);
+                    self.last_error = Some(error);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+impl Windowed for KafkaSource {
+    fn end_window_at(&mut self, end: Instant) {
+        self.window_end = Some(end);
+    }
+
+    fn peek_lines(&mut self, limit: usize, wait: Duration) -> Vec<String> {
+        let until = Instant::now() + wait;
+        loop {
+            let lines = self.pending.unread().iter().filter(|&&b| b == b'\n').count();
+            let left = until.saturating_duration_since(Instant::now());
+            if lines >= limit || left.is_zero() {
+                break;
+            }
+            self.poll(left.min(POLL_INTERVAL));
+        }
+        String::from_utf8_lossy(self.pending.unread())
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .take(limit)
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+impl Read for KafkaSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = buf.len().min(available.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for KafkaSource {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pending.unread().is_empty() {
+            let left = match self.window_end {
+                Some(end) => end.saturating_duration_since(Instant::now()),
+                None => POLL_INTERVAL,
+            };
+            if left.is_zero() {
+                return Ok(&[]);
+            }
+            self.poll(left.min(POLL_INTERVAL));
+        }
+        Ok(self.pending.unread())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pending.consumed = (self.pending.consumed + amt).min(self.pending.data.len());
+    }
+}
+
+/// Routes the client's own logs and errors (such as an unreachable broker) through
+/// `tracing`, like the tool's other diagnostics
+struct Diagnostics;
+
+impl ClientContext for Diagnostics {
+    fn log(&self, level: RDKafkaLogLevel, facility: &str, message: &str) {
+        match level {
+            RDKafkaLogLevel::Emerg
+            | RDKafkaLogLevel::Alert
+            | RDKafkaLogLevel::Critical
+            | RDKafkaLogLevel::Error
+            | RDKafkaLogLevel::Warning => tracing::debug!(facility, "kafka: {}", message),
+            _ => tracing::trace!(facility, "kafka: {}", message),
+        }
+    }
+
+    // Polling returns the same errors, and they are warned about there
+    fn error(&self, error: KafkaError, reason: &str) {
+        tracing::debug!("kafka: {}: {}", error, reason);
+    }
+}
+
+impl ConsumerContext for Diagnostics {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A consumer for a broker that is never reached; nothing is received
+    fn unreachable_source() -> KafkaSource {
+        KafkaSource::connect(&KafkaConfig {
+            brokers: "127.0.0.1:1".to_string(),
+            topics: vec!["access-logs".to_string()],
+            group: "log_analyzer-test".to_string(),
+            from_beginning: false,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn reads_buffered_messages_as_lines_then_ends_the_window() {
+        let mut source = unreachable_source();
+        source.pending.push(b"first line");
+        source.pending.push(b"second line\nthird line\n");
+        assert_eq!(source.peek_lines(2, Duration::ZERO), ["first line", "second line"]);
+
+        source.end_window_at(Instant::now() + Duration::from_millis(200));
+        let lines: Vec<String> = (&mut source).lines().collect::<Result<_, _>>().unwrap();
+        assert_eq!(lines, ["first line", "second line", "third line"]);
+
+        // The next window starts empty and ends on time
+        let started = Instant::now();
+        source.end_window_at(started + Duration::from_millis(200));
+        let mut rest = String::new();
+        source.read_to_string(&mut rest).unwrap();
+        assert!(rest.is_empty());
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,95 @@
+//! Library API for parsing and aggregating structured web server logs.
+//!
+//! The `log_analyzer` binary is a thin CLI over these modules; embedding
+//! applications can drive the same pipeline directly:
+//!
+//! - [`ingest::Ingest`] streams lines into [`parser::LogEntry`] values, with
+//!   progress callbacks carrying incremental [`ingest::PartialStats`]
+//! - [`analyzer::analyze`] aggregates entries into [`analyzer::AnalysisStats`]
+//! - [`report`] renders the stats for terminals, JSON and HTML
+
+pub mod agents;
+pub mod analyzer;
+pub mod anomalies;
+pub mod apdex;
+pub mod asn;
+pub mod attacks;
+pub mod bandwidth;
+pub mod bots;
+pub mod browsers;
+pub mod bursts;
+#[cfg(feature = "bundle")]
+pub mod bundle;
+pub mod cohorts;
+pub mod cost;
+pub mod detect;
+#[cfg(all(feature = "docker", unix))]
+pub mod docker;
+pub mod endpoint_errors;
+pub mod features;
+pub mod forecast;
+pub mod format_file;
+pub mod formats;
+#[cfg(feature = "gelf")]
+pub mod gelf;
+pub mod geo;
+#[cfg(feature = "geoip")]
+pub mod geoip;
+pub mod histogram;
+pub mod hll;
+pub mod ingest;
+pub mod inputs;
+pub mod journal;
+#[cfg(all(feature = "journald", target_os = "linux"))]
+pub mod journald;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod parser;
+pub mod paths;
+pub mod proxy;
+pub mod query;
+pub mod redirects;
+pub mod referrers;
+#[cfg(feature = "http")]
+pub mod remote;
+pub mod report;
+pub mod response_flags;
+pub mod robots;
+pub mod routers;
+pub mod routes;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod scanners;
+pub mod sessions;
+pub mod slo;
+pub mod state;
+pub mod syslog;
+pub mod templates;
+pub mod timeline;
+pub mod traces;
+pub mod units;
+pub mod upstream;
+pub mod users;
+#[cfg(feature = "self-update")]
+pub mod update;
+
+/// Optional subsystems (cargo features) and whether this build includes them
+pub const CAPABILITIES: [(&str, bool); 15] = [
+    ("bundle", cfg!(feature = "bundle")),
+    ("bzip2", cfg!(feature = "bzip2")),
+    ("docker", cfg!(all(feature = "docker", unix))),
+    ("gelf", cfg!(feature = "gelf")),
+    ("geoip", cfg!(feature = "geoip")),
+    ("gzip", cfg!(feature = "gzip")),
+    ("http", cfg!(feature = "http")),
+    ("journald", cfg!(all(feature = "journald", target_os = "linux"))),
+    ("kafka", cfg!(feature = "kafka")),
+    ("mmap", cfg!(feature = "mmap")),
+    ("s3", cfg!(feature = "s3")),
+    ("self-update", cfg!(feature = "self-update")),
+    ("xz", cfg!(feature = "xz")),
+    ("yaml", cfg!(feature = "yaml")),
+    ("zstd", cfg!(feature = "zstd")),
+];
@@ -1,11 +1,44 @@
-mod analyzer;
-mod parser;
-mod report;
-
-use clap::Parser;
+use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use log_analyzer::analyzer::FlagBasis;
+use log_analyzer::ingest::{self, Ingest, IngestResult, RotationPolicy, Sampling, Windowed};
+use log_analyzer::inputs::{self, FileSummary, LogFile};
+use log_analyzer::parser::{csv_field, CustomPattern, JsonKeys, LogEntry, LogFormat, LogLevel};
+use log_analyzer::proxy::{ClientIpStrategy, IpNet, NetError, ProxyPolicy};
+use log_analyzer::query::{self, QueryMode};
+use log_analyzer::state::{self, Checkpoint};
+use log_analyzer::timeline::ClientCounting;
+#[cfg(feature = "bundle")]
+use log_analyzer::bundle;
+#[cfg(all(feature = "docker", unix))]
+use log_analyzer::docker;
+#[cfg(feature = "gelf")]
+use log_analyzer::gelf;
+#[cfg(feature = "geoip")]
+use log_analyzer::{asn, geo, geoip};
+#[cfg(feature = "http")]
+use log_analyzer::remote::{self, HttpAuth};
+#[cfg(feature = "kafka")]
+use log_analyzer::kafka::{KafkaConfig, KafkaSource};
+#[cfg(all(feature = "journald", target_os = "linux"))]
+use log_analyzer::journald;
+#[cfg(feature = "mmap")]
+use log_analyzer::mmap;
+#[cfg(feature = "s3")]
+use log_analyzer::s3;
+#[cfg(feature = "self-update")]
+use log_analyzer::update;
+use log_analyzer::{analyzer, anomalies, apdex, attacks, bots, browsers, bursts, cohorts, cost, detect, endpoint_errors, features, forecast, format_file, formats, hll, journal, paths, redirects, referrers, report, robots, routers, routes, scanners, sessions, slo, syslog, templates, timeline, traces, units};
+use serde::Serialize;
+use std::borrow::Cow;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Cursor, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, warn};
 
 /// A high-performance CLI tool for analyzing structured web server logs
 #[derive(Parser, Debug)]
@@ -13,13 +46,401 @@ use std::path::PathBuf;
     name = "log_analyzer",
     author,
     version,
-    about = "Analyzes structured web server logs and generates aggregated insights"
+    about = "Analyzes structured web server logs and generates aggregated insights",
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true
 )]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Log files to analyze together; shell-style patterns such as 'access.log*' are
+    /// expanded, directories are searched recursively, and http(s) URLs are fetched
+    #[arg(value_name = "LOG_FILE")]
+    #[cfg_attr(not(feature = "gelf"), arg(required_unless_present_any = ["files_from", "units", "container"]))]
+    #[cfg_attr(feature = "gelf", arg(required_unless_present_any = ["listen_gelf", "files_from", "units", "container"]))]
+    files: Vec<String>,
+
+    /// Also analyze the files and URLs listed in this manifest, one per line ('-' reads
+    /// the list from stdin)
+    #[arg(long = "files-from", value_name = "MANIFEST")]
+    files_from: Option<PathBuf>,
+
+    /// With --journal, read the systemd journal of this unit (e.g. nginx.service)
+    /// directly instead of a LOG_FILE export (repeatable; Linux, `journald` feature)
+    #[arg(
+        long = "unit",
+        value_name = "UNIT",
+        requires = "journal",
+        conflicts_with_all = ["files", "files_from", "include_rotated", "state_file", "last_lines"]
+    )]
+    units: Vec<String>,
+
+    /// With --docker, stream the logs of this container (name or ID) from the local
+    /// Docker daemon instead of reading a LOG_FILE (Unix, `docker` feature)
+    #[arg(
+        long = "container",
+        value_name = "CONTAINER",
+        requires = "docker",
+        conflicts_with_all = ["files", "files_from", "include_rotated", "state_file", "last_lines"]
+    )]
+    container: Option<String>,
+
+    /// In LOG_FILE directories, only read files matching this pattern, e.g. '*.log*'
+    /// (repeatable)
+    #[arg(long = "include", value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// In LOG_FILE directories, skip files and directories matching this pattern, e.g.
+    /// 'archive' (repeatable)
+    #[arg(long = "exclude", value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Also read the rotated copies of each LOG_FILE (access.log.1, access.log.2.gz,
+    /// access.log-20240614, …), oldest first
+    #[arg(long = "include-rotated")]
+    include_rotated: bool,
+
+    /// Remember how far LOG_FILE was read and what it counted in this file, so the next
+    /// run only reads new lines and reports on everything read so far
+    #[cfg_attr(feature = "mmap", arg(conflicts_with = "mmap"))]
+    #[arg(long = "state-file", value_name = "STATE_FILE", conflicts_with_all = ["follow", "journal", "include_rotated", "sample", "every_nth", "skip_lines", "last_lines"])]
+    state_file: Option<PathBuf>,
+
+    /// Receive GELF messages on this UDP address (e.g. 0.0.0.0:12201) instead of reading
+    /// a file, until --timeout or --max-lines
+    #[cfg(feature = "gelf")]
+    #[arg(
+        long = "listen-gelf",
+        value_name = "ADDR",
+        conflicts_with_all = ["files", "files_from", "format", "pattern", "format_file", "syslog", "docker", "journal", "follow", "state_file", "skip_lines", "last_lines", "units", "container"]
+    )]
+    listen_gelf: Option<SocketAddr>,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    /// Export results as JSON to the specified file path
+    #[arg(short = 'j', long = "json-output", value_name = "OUTPUT_FILE")]
+    json_output: Option<PathBuf>,
+
+    /// Export results as a self-contained HTML page to the specified file path
+    #[arg(long = "html-output", value_name = "OUTPUT_FILE")]
+    html_output: Option<PathBuf>,
+
+    /// Export a labeled per-IP feature dataset as CSV for model training
+    #[arg(long = "features-csv", value_name = "OUTPUT_FILE")]
+    features_csv: Option<PathBuf>,
+
+    /// Export inferred redirect edges as source,target,value CSV for sankey/flow tools
+    #[arg(long = "redirect-flow", value_name = "OUTPUT_FILE")]
+    redirect_flow: Option<PathBuf>,
+
+    /// Save the spread of requests and error rates across the --bucket buckets, for a
+    /// later run's --baseline
+    #[arg(long = "save-baseline", value_name = "OUTPUT_FILE", requires = "bucket")]
+    save_baseline: Option<PathBuf>,
+
+    /// Reproducible output for golden tests: sorted JSON keys, no color, no update notice
+    #[arg(long = "deterministic")]
+    deterministic: bool,
+
+    /// Increase diagnostic verbosity (-v for debug, -vv for trace)
+    #[arg(short = 'v', long = "verbose", action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Emit the tool's own diagnostics as JSON lines on stderr
+    #[arg(long = "log-json", global = true)]
+    log_json: bool,
+}
+
+impl Args {
+    /// The files or listener named on the command line or in the `--files-from`
+    /// manifest, with patterns expanded, directories searched and, with
+    /// `--include-rotated`, rotated copies added.
+    ///
+    /// Exits if a pattern matches no file, or the manifest cannot be read.
+    fn input(&self) -> Input {
+        #[cfg(feature = "gelf")]
+        if let Some(addr) = self.listen_gelf {
+            return Input::Gelf(addr);
+        }
+        if !self.units.is_empty() {
+            #[cfg(all(feature = "journald", target_os = "linux"))]
+            return Input::Journal(self.units.clone());
+            #[cfg(not(all(feature = "journald", target_os = "linux")))]
+            {
+                error!("reading the systemd journal with --unit needs the `journald` feature, on Linux");
+                std::process::exit(1);
+            }
+        }
+        if let Some(container) = &self.container {
+            #[cfg(all(feature = "docker", unix))]
+            return Input::Container(container.clone());
+            #[cfg(not(all(feature = "docker", unix)))]
+            {
+                error!(container = %container, "reading a container's logs with --container needs the `docker` feature, on Unix");
+                std::process::exit(1);
+            }
+        }
+        let mut patterns = self.files.clone();
+        if let Some(manifest) = &self.files_from {
+            let listed = if manifest == Path::new("-") {
+                inputs::read_manifest(std::io::stdin().lock())
+            } else {
+                File::open(manifest).and_then(|file| inputs::read_manifest(BufReader::new(file)))
+            };
+            match listed {
+                Ok(listed) if listed.is_empty() => {
+                    error!(manifest = %manifest.display(), "the manifest lists no files");
+                    std::process::exit(1);
+                }
+                Ok(listed) => patterns.extend(listed),
+                Err(e) => {
+                    error!(manifest = %manifest.display(), "could not read the manifest: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        let mut files: Vec<PathBuf> = Vec::new();
+        for pattern in &patterns {
+            // A URL's `?` starts its query string rather than being a wildcard
+            let expanded = if inputs::is_url(pattern) {
+                Ok(vec![PathBuf::from(pattern)])
+            } else if inputs::is_s3_url(pattern) {
+                #[cfg(feature = "s3")]
+                let objects = s3::list(pattern, &self.include, &self.exclude);
+                #[cfg(not(feature = "s3"))]
+                let objects = Err(inputs::GlobError::Io {
+                    path: PathBuf::from(pattern),
+                    error: std::io::Error::new(
+                        std::io::ErrorKind::Unsupported,
+                        "reading s3:// inputs needs the `s3` feature",
+                    ),
+                });
+                objects
+            } else if Path::new(pattern).is_dir() {
+                inputs::walk(Path::new(pattern), &self.include, &self.exclude)
+            } else if self.include_rotated && !inputs::is_glob(pattern) {
+                let mut copies = inputs::rotated(Path::new(pattern));
+                // The live file may have just been rotated away; a name with no copies
+                // is still read, so that a typo is reported
+                if copies.is_empty() || Path::new(pattern).exists() {
+                    copies.push(PathBuf::from(pattern));
+                }
+                Ok(copies)
+            } else {
+                inputs::expand(pattern)
+            };
+            match expanded {
+                Ok(paths) => {
+                    for path in paths {
+                        // A file named twice, or matched by two patterns, is read once
+                        if !files.contains(&path) {
+                            files.push(path);
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        if let Some(state) = &self.state_file {
+            let local = files.iter().all(|f| f.to_str().is_none_or(|f| !inputs::is_url(f) && !inputs::is_s3_url(f)));
+            if files.len() != 1 || !local {
+                error!(files = files.len(), "--state-file tracks a single local LOG_FILE");
+                std::process::exit(1);
+            }
+            return Input::Incremental {
+                file: files.remove(0),
+                state: state.clone(),
+            };
+        }
+        Input::Files(files)
+    }
+}
+
+/// Where the entries of a report are read from
+enum Input {
+    /// One or more files, analyzed together
+    Files(Vec<PathBuf>),
+    /// A file read from where the previous run left off (`--state-file`)
+    Incremental { file: PathBuf, state: PathBuf },
+    /// GELF messages received on a UDP socket (`--listen-gelf`)
+    #[cfg(feature = "gelf")]
+    Gelf(SocketAddr),
+    /// The systemd journal of these units (`--journal --unit`)
+    #[cfg(all(feature = "journald", target_os = "linux"))]
+    Journal(Vec<String>),
+    /// The logs of this Docker container (`--docker --container`)
+    #[cfg(all(feature = "docker", unix))]
+    Container(String),
+}
+
+impl Input {
+    /// How the input is named in reports
+    fn source(&self) -> PathBuf {
+        match self {
+            Input::Files(files) if files.len() == 1 => files[0].clone(),
+            Input::Files(files) => PathBuf::from(format!("{} (+{} more)", files[0].display(), files.len() - 1)),
+            Input::Incremental { file, .. } => file.clone(),
+            #[cfg(feature = "gelf")]
+            Input::Gelf(addr) => PathBuf::from(format!("gelf+udp://{}", addr)),
+            #[cfg(all(feature = "journald", target_os = "linux"))]
+            Input::Journal(units) => PathBuf::from(format!("journal:{}", units.join(","))),
+            #[cfg(all(feature = "docker", unix))]
+            Input::Container(container) => PathBuf::from(format!("docker:{}", container)),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Produce an anonymized .tar.gz bundle suitable for attaching to a support ticket
+    #[cfg(feature = "bundle")]
+    Bundle(Box<BundleArgs>),
+    /// Consume log lines from Kafka topics and print aggregated stats for each interval
+    #[cfg(feature = "kafka")]
+    Consume(Box<ConsumeArgs>),
+    /// Receive syslog-forwarded log lines over UDP/TCP and print aggregated stats for each interval
+    Listen(Box<ListenArgs>),
+    /// Download and install the latest release binary for this platform
+    #[cfg(feature = "self-update")]
+    SelfUpdate(SelfUpdateArgs),
+    /// List the optional subsystems compiled into this binary
+    Capabilities,
+    /// List the log formats accepted by --format
+    Formats,
+}
+
+#[cfg(feature = "self-update")]
+#[derive(ClapArgs, Debug)]
+struct SelfUpdateArgs {
+    /// Only report whether a newer release exists; don't install it
+    #[arg(long = "check")]
+    check: bool,
+}
+
+#[cfg(feature = "kafka")]
+#[derive(ClapArgs, Debug)]
+struct ConsumeArgs {
+    /// Kafka bootstrap brokers, comma-separated (e.g. broker:9092)
+    #[arg(long = "kafka", value_name = "BROKERS")]
+    brokers: String,
+
+    /// Topic to consume; repeatable
+    #[arg(long = "topic", value_name = "TOPIC", required = true)]
+    topics: Vec<String>,
+
+    /// Consumer group; consumers in one group share the topics' partitions
+    #[arg(long = "group", default_value = "log_analyzer", value_name = "GROUP")]
+    group: String,
+
+    /// Start from the oldest retained messages when the group has no committed offsets,
+    /// instead of only new ones
+    #[arg(long = "from-beginning")]
+    from_beginning: bool,
+
+    /// Length of each aggregation window (e.g. 30s, 5m)
+    #[arg(long = "interval", default_value = "1m", value_name = "DURATION", value_parser = units::parse_duration)]
+    interval: Duration,
+
+    /// How each window's stats are printed
+    #[arg(long = "output", value_enum, default_value_t = WindowOutput::Text)]
+    output: WindowOutput,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+}
+
+#[derive(ClapArgs, Debug)]
+#[command(group(clap::ArgGroup::new("bind").args(["udp", "tcp"]).required(true).multiple(true)))]
+struct ListenArgs {
+    /// Receive syslog messages as UDP datagrams on this address (e.g. 0.0.0.0:514)
+    #[arg(long = "udp", value_name = "ADDR")]
+    udp: Option<SocketAddr>,
+
+    /// Accept syslog messages over TCP on this address, newline- or octet-count-framed
+    #[arg(long = "tcp", value_name = "ADDR")]
+    tcp: Option<SocketAddr>,
+
+    /// Length of each aggregation window (e.g. 30s, 5m)
+    #[arg(long = "interval", default_value = "1m", value_name = "DURATION", value_parser = units::parse_duration)]
+    interval: Duration,
+
+    /// How each window's stats are printed
+    #[arg(long = "output", value_enum, default_value_t = WindowOutput::Text)]
+    output: WindowOutput,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+}
+
+#[cfg(feature = "bundle")]
+#[derive(ClapArgs, Debug)]
+struct BundleArgs {
     /// Path to the log file to analyze
     #[arg(value_name = "LOG_FILE")]
     file: PathBuf,
 
+    /// Path of the .tar.gz bundle to write
+    #[arg(short = 'o', long = "output", value_name = "BUNDLE_FILE")]
+    output: PathBuf,
+
+    /// Maximum number of redacted evidence lines to include
+    #[arg(long = "evidence-lines", default_value_t = 100, value_name = "N")]
+    evidence_lines: usize,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+}
+
+/// Options shared by every command that runs an analysis
+#[derive(ClapArgs, Debug, Serialize)]
+struct AnalysisArgs {
+    /// Input log format (see `log_analyzer formats`); `auto` picks the format that parses most of the first lines
+    #[arg(long = "format", default_value = "auto", value_name = "FORMAT", value_parser = parse_format)]
+    format: String,
+
+    /// JSON or logfmt key (dotted path for nested objects) holding a field, e.g. ip=client.addr; repeatable
+    #[arg(long = "json-key", alias = "logfmt-key", value_name = "FIELD=KEY", value_parser = parse_json_key)]
+    json_keys: Vec<(String, String)>,
+
+    /// Count a nonstandard level spelling as one of trace, debug, info, warn, error
+    /// or fatal, e.g. NOTICE=info (repeatable; matched in any case)
+    #[arg(long = "level-alias", value_name = "ALIAS=LEVEL", value_delimiter = ',', value_parser = parse_level_alias)]
+    level_aliases: Vec<(String, LogLevel)>,
+
+    /// CSV/TSV column names in order, e.g. timestamp,level,ip,method,endpoint,status (`-` skips a column)
+    #[arg(long = "columns", value_name = "NAMES", value_delimiter = ',', value_parser = parse_column)]
+    columns: Vec<String>,
+
+    /// Custom line regex with named groups timestamp, ip, method, endpoint, status (and optionally level, bytes)
+    #[arg(long = "pattern", value_name = "REGEX", conflicts_with = "format")]
+    pattern: Option<String>,
+
+    /// TOML file describing an in-house format by regex or delimiter and field positions
+    #[arg(long = "format-file", value_name = "TOML_FILE", conflicts_with_all = ["format", "pattern"])]
+    format_file: Option<PathBuf>,
+
+    /// Strip an RFC 5424/3164 syslog header from each line before applying the format
+    #[arg(long = "syslog")]
+    syslog: bool,
+
+    /// Unwrap Docker/Kubernetes JSON log records ({"log": ..., "time": ...}) before applying the format
+    #[arg(long = "docker", conflicts_with = "syslog")]
+    docker: bool,
+
+    /// With --docker, timestamp entries with the record's `time` rather than the line's own
+    #[arg(long = "docker-time", requires = "docker")]
+    docker_time: bool,
+
+    /// Read systemd journal entries (journalctl -o export or -o json) and apply the format to each MESSAGE
+    #[arg(long = "journal", conflicts_with_all = ["syslog", "docker"])]
+    journal: bool,
+
     /// Number of top IPs and endpoints to display
     #[arg(short = 'n', long = "top", default_value_t = 10, value_name = "N")]
     top_n: usize,
@@ -28,87 +449,1932 @@ struct Args {
     #[arg(short = 'e', long = "error-threshold", default_value_t = 5, value_name = "COUNT")]
     error_threshold: usize,
 
-    /// Export results as JSON to the specified file path
-    #[arg(short = 'j', long = "json-output", value_name = "OUTPUT_FILE")]
-    json_output: Option<PathBuf>,
+    /// What counts as an error when flagging IPs: lines logged as ERROR or FATAL, or 5xx responses
+    #[arg(long = "flag-on", value_enum, default_value_t = FlagOn::Level, value_name = "BASIS")]
+    flag_on: FlagOn,
+
+    /// Count distinct client IPs and endpoints, and the new and returning clients of each time bucket
+    #[arg(long = "unique")]
+    unique: bool,
+
+    /// Roll status codes up into classes with success and error rates
+    #[arg(long = "status-classes")]
+    status_classes: bool,
+
+    /// Count requests per HTTP method, with a method breakdown of the top endpoints in the JSON export
+    #[arg(long = "methods")]
+    methods: bool,
+
+    /// Rank endpoints by their 4xx and 5xx responses and by their own 5xx rate
+    #[arg(long = "endpoint-errors")]
+    endpoint_errors: bool,
+
+    /// Flag IPs that sent more than N requests within any --burst-window, whether or not they succeeded
+    #[arg(long = "burst-threshold", value_name = "N")]
+    burst_threshold: Option<usize>,
+
+    /// Length of the sliding window for --burst-threshold, at least 1s [default: 10s]
+    #[arg(long = "burst-window", value_name = "DURATION", value_parser = parse_window, requires = "burst_threshold")]
+    #[serde(serialize_with = "serialize_duration")]
+    burst_window: Option<Duration>,
+
+    /// Scan endpoints for SQL injection, path traversal, probes such as .env or wp-login.php,
+    /// and shell injection, and list the IPs that sent them
+    #[arg(long = "attacks")]
+    attacks: bool,
+
+    /// Flag IPs that got 404s on at least N distinct paths, mostly 404s, as vulnerability scanners
+    #[arg(long = "scanner-threshold", value_name = "N")]
+    scanner_threshold: Option<usize>,
+
+    /// Share of an IP's requests that must be 404s for --scanner-threshold [default: 50]
+    #[arg(long = "scanner-404-share", value_name = "PERCENT", value_parser = parse_percent, requires = "scanner_threshold")]
+    scanner_404_share: Option<f64>,
+
+    /// Score response times with Apdex against this target T, e.g. 500ms or 1.5s
+    #[arg(long = "apdex-t", value_name = "LATENCY", value_parser = units::parse_latency)]
+    #[serde(serialize_with = "serialize_latency")]
+    apdex_t: Option<Duration>,
+
+    /// Availability objective in percent, e.g. 99.9; reports the non-5xx share and the error budget left
+    #[arg(long = "slo", value_name = "PERCENT", value_parser = parse_slo)]
+    slo: Option<f64>,
 
     /// Suppress warnings for malformed log lines
     #[arg(short = 'q', long = "quiet")]
     quiet: bool,
+
+    /// Add first-seen, last-seen and peak-minute columns to the IP and endpoint rankings
+    #[arg(long = "time-columns")]
+    time_columns: bool,
+
+    /// Profile flagged IPs side by side with all other IPs
+    #[arg(long = "compare-cohorts")]
+    compare_cohorts: bool,
+
+    /// Reconstruct redirect chains and report loops and long chains
+    #[arg(long = "redirects")]
+    redirects: bool,
+
+    /// Count requests and server errors per proxy router (Traefik)
+    #[arg(long = "by-router")]
+    by_router: bool,
+
+    /// Break browser traffic down by browser, major version and operating system
+    #[arg(long = "browsers")]
+    browsers: bool,
+
+    /// Tell bots from humans by user agent, robots.txt requests and request cadence
+    #[arg(long = "bots")]
+    bots: bool,
+
+    /// Chart requests and errors over time in buckets of this width (e.g. 1m, 5m, 1h, 1d)
+    #[arg(long = "bucket", value_name = "DURATION", value_parser = units::parse_duration)]
+    #[serde(serialize_with = "serialize_duration")]
+    bucket: Option<Duration>,
+
+    /// Count distinct client IPs and endpoints as --unique does, but estimate them with HyperLogLog sketches instead of exact sets
+    #[arg(long)]
+    approx: bool,
+
+    /// Flag time buckets whose requests or error rate stray from a rolling baseline of the buckets before them
+    #[arg(long = "anomalies", requires = "bucket")]
+    anomalies: bool,
+
+    /// Standard deviations from the baseline that make a bucket anomalous [default: 3]
+    #[arg(long = "anomaly-threshold", value_name = "SIGMAS", value_parser = parse_sigmas, requires = "anomalies")]
+    anomaly_threshold: Option<f64>,
+
+    /// Earlier buckets the rolling baseline is taken over [default: 12]
+    #[arg(long = "baseline-window", value_name = "N", requires = "anomalies")]
+    baseline_window: Option<usize>,
+
+    /// Compare buckets against a baseline saved by an earlier run (--save-baseline) instead
+    #[arg(long = "baseline", value_name = "BASELINE_FILE", requires = "anomalies", conflicts_with = "baseline_window")]
+    baseline: Option<PathBuf>,
+
+    /// Group requests by trace or request id and list the traces that contained errors
+    #[arg(long = "by-trace")]
+    by_trace: bool,
+
+    /// Group each IP's requests into sessions and report their length and entry and exit endpoints
+    #[arg(long = "sessions")]
+    sessions: bool,
+
+    /// Inactivity that ends a session (e.g. 15m, 1h) [default: 30m]
+    #[arg(long = "session-gap", value_name = "DURATION", value_parser = units::parse_duration, requires = "sessions")]
+    #[serde(serialize_with = "serialize_duration")]
+    session_gap: Option<Duration>,
+
+    /// Forecast the next 24 hours of requests and errors (needs 48h+ of history)
+    #[arg(long = "forecast")]
+    forecast: bool,
+
+    /// Input interleaves access-log lines with application output; count lines
+    /// the format does not parse separately instead of as malformed
+    #[arg(long = "mixed")]
+    mixed: bool,
+
+    /// Attach indented continuation lines such as stack traces to the entry
+    /// before them instead of counting them as malformed
+    #[arg(long = "multiline")]
+    multiline: bool,
+
+    /// Keep lines missing a level or status code instead of counting them as
+    /// malformed (native, JSON, logfmt and --pattern formats)
+    #[arg(long = "lenient")]
+    lenient: bool,
+
+    /// Retry native-format lines that narrowly fail to parse (stray spaces, an
+    /// unbracketed or lowercase level, a lowercase method) before counting them as malformed
+    #[arg(long = "recover")]
+    recover: bool,
+
+    /// Count endpoints exactly as logged, without decoding percent-escapes such as `%20` or normalizing Unicode
+    #[arg(long = "raw-paths")]
+    raw_paths: bool,
+
+    /// How query strings count toward endpoints: keep them, strip them, or bucket them by parameter name
+    #[arg(long = "query-strings", value_enum, default_value_t = QueryStrings::Keep, value_name = "MODE")]
+    query_strings: QueryStrings,
+
+    /// Collapse numeric ids, UUIDs and hashes in endpoints into placeholders (`/users/123` → `/users/:id`)
+    #[arg(long = "collapse-paths")]
+    collapse_paths: bool,
+
+    /// Routes file or OpenAPI spec (.json/.yaml) whose templates endpoints are counted under (`/users/{id}`)
+    #[arg(long = "route-patterns", value_name = "ROUTES_FILE")]
+    route_patterns: Option<PathBuf>,
+
+    /// Take each client from the X-Forwarded-For chain, for formats that log one:
+    /// the first public hop, or the rightmost hop that is not a trusted proxy
+    #[arg(long = "trust-proxy", value_enum, value_name = "MODE")]
+    trust_proxy: Option<TrustProxy>,
+
+    /// Network of proxies trusted by --trust-proxy rightmost-untrusted, e.g. 10.0.0.0/8
+    /// (repeatable; default: private, loopback and link-local addresses)
+    #[arg(long = "trusted-proxy", value_name = "CIDR", value_delimiter = ',', requires = "trust_proxy", value_parser = parse_trusted_proxy)]
+    trusted_proxies: Vec<IpNet>,
+
+    /// Egress price per GB, for the cost attribution section
+    #[arg(long = "cost-per-gb", value_name = "PRICE")]
+    cost_per_gb: Option<f64>,
+
+    /// Price per million requests, for the cost attribution section
+    #[arg(long = "cost-per-million-requests", value_name = "PRICE")]
+    cost_per_million_requests: Option<f64>,
+
+    /// Routes file or OpenAPI spec (.json/.yaml) listing the endpoints expected to receive traffic
+    #[arg(long = "expected-endpoints", value_name = "ROUTES_FILE")]
+    expected_endpoints: Option<PathBuf>,
+
+    /// OpenAPI/Swagger spec (.json/.yaml) to map logged requests onto operations
+    #[arg(long = "openapi", value_name = "SPEC_FILE")]
+    openapi: Option<PathBuf>,
+
+    /// Requests an endpoint needs before it is ranked by error rate [default: 10]
+    #[arg(long = "min-endpoint-requests", value_name = "N", requires = "endpoint_errors")]
+    min_endpoint_requests: Option<usize>,
+
+    /// Host of the analyzed site, so referrers from it count as internal; repeatable, subdomains match
+    #[arg(long = "site-host", value_name = "HOST")]
+    site_hosts: Vec<String>,
+
+    /// Referrer spam domains to flag besides the built-in list, one per line
+    #[arg(long = "referrer-spam", value_name = "DOMAINS_FILE")]
+    referrer_spam: Option<PathBuf>,
+
+    /// robots.txt to check crawler requests against
+    #[arg(long = "robots", value_name = "ROBOTS_FILE")]
+    robots: Option<PathBuf>,
+
+    /// MaxMind GeoLite2/GeoIP2 City or Country database (.mmdb) to break requests down by country
+    #[cfg(feature = "geoip")]
+    #[arg(long = "geoip", value_name = "MMDB_FILE")]
+    geoip: Option<PathBuf>,
+
+    /// Flag users whose consecutive requests come from places too far apart to travel between in the time (needs --geoip)
+    #[cfg(feature = "geoip")]
+    #[arg(long = "impossible-travel", requires = "geoip")]
+    impossible_travel: bool,
+
+    /// MaxMind GeoLite2/GeoIP2 ASN database (.mmdb) to name the network owner of flagged IPs and rank networks by traffic
+    #[cfg(feature = "geoip")]
+    #[arg(long = "asn", value_name = "MMDB_FILE")]
+    asn: Option<PathBuf>,
+
+    /// Stop reading after this long (e.g. 30s, 10m, 1h) and report partial results
+    #[arg(long = "timeout", value_name = "DURATION", value_parser = units::parse_duration)]
+    #[serde(serialize_with = "serialize_duration")]
+    timeout: Option<Duration>,
+
+    /// Stop reading a named pipe or character device LOG_FILE once nothing has been
+    /// written to it for this long (e.g. 30s), even though its writer keeps it open
+    #[arg(long = "idle-timeout", value_name = "DURATION", value_parser = units::parse_duration)]
+    #[serde(serialize_with = "serialize_duration")]
+    idle_timeout: Option<Duration>,
+
+    /// Stop reading after this many lines and report partial results
+    #[arg(long = "max-lines", value_name = "N")]
+    max_lines: Option<usize>,
+
+    /// Pass over the first N lines of each file without parsing them
+    #[arg(long = "skip-lines", value_name = "N", conflicts_with = "last_lines")]
+    skip_lines: Option<usize>,
+
+    /// Only analyze the last N lines of each file; an uncompressed file is read back
+    /// from its end rather than through
+    #[arg(long = "last-lines", value_name = "N", conflicts_with = "follow")]
+    last_lines: Option<usize>,
+
+    /// Memory-map uncompressed files and parse them in chunks on every core, which is
+    /// faster for very large files on fast disks; the files must not be truncated meanwhile
+    #[cfg(feature = "mmap")]
+    #[arg(long = "mmap", conflicts_with_all = ["follow", "last_lines", "journal"])]
+    mmap: bool,
+
+    /// Only parse this share of lines (e.g. 0.01), picked the same way on every run, and
+    /// scale the counts up to estimate the whole input
+    #[arg(long = "sample", value_name = "RATE", value_parser = parse_rate, conflicts_with_all = ["every_nth", "multiline"])]
+    sample: Option<f64>,
+
+    /// Only parse every Nth line, and scale the counts up to estimate the whole input
+    #[arg(long = "every-nth", value_name = "N", conflicts_with = "multiline")]
+    every_nth: Option<NonZeroUsize>,
+
+    /// What to do if the log file is rotated while it is being read
+    #[arg(long = "on-rotate", value_enum, default_value_t = OnRotate::Stop, value_name = "ACTION")]
+    on_rotate: OnRotate,
+
+    /// Keep reading as lines are appended, like tail -f, re-rendering the report (text)
+    /// or printing the stats as one JSON line (json) on every refresh
+    #[arg(
+        long = "follow",
+        value_enum,
+        value_name = "OUTPUT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text"
+    )]
+    follow: Option<FollowOutput>,
+
+    /// Least time between two refreshes with --follow (e.g. 1s, 30s) [default: 2s]
+    #[arg(long = "refresh", value_name = "DURATION", value_parser = units::parse_duration, requires = "follow")]
+    #[serde(serialize_with = "serialize_duration")]
+    refresh: Option<Duration>,
+
+    /// Basic-auth credentials for an http(s):// LOG_FILE
+    #[cfg(feature = "http")]
+    #[arg(long = "http-user", value_name = "USER:PASSWORD", conflicts_with = "http_token")]
+    #[serde(skip)]
+    http_user: Option<String>,
+
+    /// Bearer token for an http(s):// LOG_FILE; also read from LOG_ANALYZER_HTTP_TOKEN
+    #[cfg(feature = "http")]
+    #[arg(long = "http-token", value_name = "TOKEN")]
+    #[serde(skip)]
+    http_token: Option<String>,
 }
 
-fn main() {
-    let args = Args::parse();
+impl AnalysisArgs {
+    /// Open a `LOG_FILE`, fetching it when it is an `http://`, `https://` or `s3://` URL
+    fn open_input(&self, path: &Path) -> std::io::Result<LogFile> {
+        match path.to_str() {
+            #[cfg(feature = "http")]
+            Some(url) if inputs::is_url(url) => remote::open(url, self.http_auth().as_ref()),
+            #[cfg(not(feature = "http"))]
+            Some(url) if inputs::is_url(url) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "reading from a URL needs the `http` feature",
+            )),
+            #[cfg(feature = "s3")]
+            Some(url) if inputs::is_s3_url(url) => s3::open(url),
+            _ => inputs::open(path),
+        }
+    }
 
-    // Open the log file
-    let file = match File::open(&args.file) {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!(
-                "error: could not open file '{}': {}",
-                args.file.display(),
-                e
-            );
-            std::process::exit(1);
+    /// Credentials for log URLs from `--http-user`, `--http-token` or the token variable
+    #[cfg(feature = "http")]
+    fn http_auth(&self) -> Option<HttpAuth> {
+        if let Some(credentials) = &self.http_user {
+            return Some(HttpAuth::basic(credentials));
         }
-    };
+        let token = self.http_token.clone().or_else(|| std::env::var(remote::TOKEN_ENV_VAR).ok());
+        token.filter(|t| !t.is_empty()).map(HttpAuth::Bearer)
+    }
 
-    let reader = BufReader::new(file);
-    let mut entries = Vec::new();
-    let mut malformed_count = 0usize;
+    /// The parser format selected by `--format`, `--json-key`, `--pattern`, `--format-file`,
+    /// `--syslog`, `--docker` and `--journal`
+    fn log_format(&self) -> LogFormat {
+        self.with_envelope(self.line_format())
+    }
 
-    // Stream through file line-by-line for memory efficiency
-    for (line_num, line_result) in reader.lines().enumerate() {
-        let line = match line_result {
-            Ok(l) => l,
+    /// Wrap `format` in a syslog envelope, Docker record or journal entry when `--syslog`,
+    /// `--docker` or `--journal` is given
+    fn with_envelope(&self, format: LogFormat) -> LogFormat {
+        if self.syslog {
+            LogFormat::Syslog(Box::new(format))
+        } else if self.docker {
+            LogFormat::Docker {
+                inner: Box::new(format),
+                use_time: self.docker_time,
+            }
+        } else if self.journal {
+            LogFormat::Journal(Box::new(format))
+        } else {
+            format
+        }
+    }
+
+    /// Whether the format is to be detected: `--format auto`, without `--pattern`
+    /// or `--format-file`
+    fn auto_format(&self) -> bool {
+        self.format == "auto" && self.pattern.is_none() && self.format_file.is_none()
+    }
+
+    /// With `--format auto`, the format that parses most of the first lines of `path`.
+    ///
+    /// Returns `None` for an explicit format or `--pattern`, and when no format
+    /// parses any sampled line (the native format is then used).
+    fn detect_format(&self, path: &Path) -> Option<detect::Detection> {
+        if !self.auto_format() {
+            return None;
+        }
+        let sample = self.open_input(path).and_then(|f| {
+            if self.journal {
+                detect::sample_lines(journal::ExportReader::new(f), detect::SAMPLE_LINES)
+            } else {
+                detect::sample_lines(f, detect::SAMPLE_LINES)
+            }
+        });
+        let sample = match sample {
+            Ok(lines) => lines,
             Err(e) => {
-                if !args.quiet {
-                    eprintln!("warning: could not read line {}: {}", line_num + 1, e);
-                }
-                malformed_count += 1;
-                continue;
+                error!(path = %path.display(), "could not open file: {}", e);
+                std::process::exit(1);
             }
         };
+        self.detect_sample(&sample, path)
+    }
 
-        if line.trim().is_empty() {
-            continue;
+    /// With `--format auto`, the format of the first lines of a live source, which
+    /// `peek` waits for: a followed source may be quiet, so at most [`SAMPLE_WAIT`],
+    /// and at most half of any time limit, so the rest is left for reading
+    fn detect_live(&self, name: &Path, peek: impl FnOnce(usize, Duration) -> Vec<String>) -> Option<detect::Detection> {
+        if !self.auto_format() {
+            return None;
         }
+        let wait = self.timeout.map_or(SAMPLE_WAIT, |limit| (limit / 2).min(SAMPLE_WAIT));
+        self.detect_sample(&peek(detect::SAMPLE_LINES, wait), name)
+    }
 
-        match parser::parse_log_line(&line) {
-            Ok(entry) => entries.push(entry),
-            Err(e) => {
-                malformed_count += 1;
-                if !args.quiet {
-                    eprintln!(
-                        "warning: malformed line {} — {}: {:?}",
-                        line_num + 1,
-                        e,
-                        &line[..line.len().min(80)]
-                    );
+    /// Detect the log format of lines sampled from `source`, warning if it is unclear
+    fn detect_sample(&self, sample: &[String], path: &Path) -> Option<detect::Detection> {
+        let options = self.format_options();
+        let candidates = formats::all()
+            .iter()
+            .filter(|preset| preset.detectable())
+            .map(|preset| (preset.name().to_string(), self.with_envelope(preset.log_format(&options))));
+        let detection = detect::detect(sample, candidates);
+        match &detection {
+            None => warn!(path = %path.display(), "could not detect the log format; assuming native (see --format)"),
+            Some(d) if d.match_rate() < 50.0 => warn!(
+                path = %path.display(),
+                "detected format {} matched only {} of {} sampled lines; pass --format or --pattern if this is wrong",
+                d.format,
+                d.matched,
+                d.sampled
+            ),
+            Some(d) => debug!(format = %d.format, matched = d.matched, sampled = d.sampled, "detected log format"),
+        }
+        detection
+    }
+
+    /// The format of a line (or of the syslog message or Docker record that carries it)
+    fn line_format(&self) -> LogFormat {
+        if let Some(pattern) = &self.pattern {
+            return match CustomPattern::new(pattern) {
+                Ok(p) => LogFormat::Pattern(p),
+                Err(e) => {
+                    error!("invalid --pattern: {}", e);
+                    std::process::exit(1);
+                }
+            };
+        }
+        if let Some(path) = &self.format_file {
+            return match format_file::load_format_file(path) {
+                Ok(p) => LogFormat::Pattern(p),
+                Err(e) => {
+                    error!(path = %path.display(), "invalid --format-file: {}", e);
+                    std::process::exit(1);
                 }
+            };
+        }
+        match formats::find(&self.format) {
+            Some(preset) => preset.log_format(&self.format_options()),
+            None => LogFormat::Native,
+        }
+    }
+
+    /// The lines `--sample` or `--every-nth` pick, if only a sample is analyzed
+    fn sampling(&self) -> Option<Sampling> {
+        match (self.sample, self.every_nth) {
+            (Some(rate), _) => Some(Sampling::Fraction(rate)),
+            (None, Some(n)) => Some(Sampling::EveryNth(n.get())),
+            (None, None) => None,
+        }
+    }
+
+    /// The `--json-key` and `--columns` settings presets are built with
+    fn format_options(&self) -> formats::FormatOptions {
+        let mut keys = JsonKeys::default();
+        for (field, key) in &self.json_keys {
+            if let Err(e) = keys.set(field, key) {
+                error!("invalid --json-key: {}", e);
+                std::process::exit(1);
             }
         }
+        formats::FormatOptions {
+            keys,
+            columns: self.columns.clone(),
+        }
     }
+}
 
-    if entries.is_empty() {
-        eprintln!("error: no valid log entries found in '{}'", args.file.display());
-        std::process::exit(1);
+/// Check a `--columns` name: a field, or `-` (or nothing) for a column to ignore
+fn parse_column(s: &str) -> Result<String, String> {
+    if s.is_empty() || s == "-" || csv_field(s).is_some() {
+        Ok(s.to_string())
+    } else {
+        Err(format!(
+            "unknown column '{}' (expected a field such as timestamp, level, ip, method, endpoint, status, bytes, or - to skip it)",
+            s
+        ))
     }
+}
 
-    // Analyze parsed entries
-    let stats = analyzer::analyze(&entries, args.top_n, args.error_threshold);
+/// Accept `auto` or a preset name or alias, resolving aliases to the canonical name
+fn parse_format(s: &str) -> Result<String, String> {
+    if s == "auto" {
+        return Ok(s.to_string());
+    }
+    formats::find(s)
+        .map(|preset| preset.name().to_string())
+        .ok_or_else(|| format!("unknown format '{}' (run `log_analyzer formats` for the list)", s))
+}
 
-    // Print terminal report
-    report::print_report(&stats, malformed_count, &args.file);
+/// Accept a `--sample` rate above 0 and at most 1
+fn parse_rate(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate <= 1.0 => Ok(rate),
+        _ => Err(format!("invalid rate '{}' (expected a share of lines such as 0.01, above 0 and at most 1)", s)),
+    }
+}
 
-    // Optionally export JSON
-    if let Some(json_path) = &args.json_output {
-        match report::export_json(&stats, json_path) {
-            Ok(_) => println!("\n✓ JSON report saved to '{}'", json_path.display()),
-            Err(e) => {
-                eprintln!("error: failed to write JSON output: {}", e);
-                std::process::exit(1);
-            }
+/// Accept a `--anomaly-threshold` above 0
+fn parse_sigmas(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(sigmas) if sigmas > 0.0 && sigmas.is_finite() => Ok(sigmas),
+        _ => Err(format!("invalid threshold '{}' (expected a number of standard deviations above 0, such as 3)", s)),
+    }
+}
+
+/// Accept a `--scanner-404-share` above 0 and at most 100
+fn parse_percent(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(percent) if percent > 0.0 && percent <= 100.0 => Ok(percent),
+        _ => Err(format!("invalid share '{}' (expected a percentage above 0 and at most 100)", s)),
+    }
+}
+
+/// Accept an `--slo` above 0 and below 100, as a 100% objective leaves no error budget
+fn parse_slo(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(objective) if objective > 0.0 && objective < 100.0 => Ok(objective),
+        _ => Err(format!("invalid SLO '{}' (expected a percentage above 0 and below 100, such as 99.9)", s)),
+    }
+}
+
+/// Accept a `--burst-window` of at least a second
+fn parse_window(s: &str) -> Result<Duration, String> {
+    match units::parse_duration(s)? {
+        window if window.is_zero() => Err(format!("invalid window '{}' (must be at least 1s)", s)),
+        window => Ok(window),
+    }
+}
+
+/// Parse a `--trusted-proxy` network
+fn parse_trusted_proxy(s: &str) -> Result<IpNet, String> {
+    s.parse().map_err(|e: NetError| e.to_string())
+}
+
+/// Split a `--level-alias ALIAS=LEVEL` argument
+fn parse_level_alias(s: &str) -> Result<(String, LogLevel), String> {
+    match s.split_once('=') {
+        Some((alias, level)) if !alias.trim().is_empty() => match level.parse() {
+            Ok(level) => Ok((alias.trim().to_string(), level)),
+            Err(_) => Err(format!("unknown level '{}' (expected trace, debug, info, warn, error or fatal)", level)),
+        },
+        _ => Err(format!("expected ALIAS=LEVEL, got '{}'", s)),
+    }
+}
+
+/// Split a `--json-key FIELD=KEY` argument
+fn parse_json_key(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((field, key)) if !field.is_empty() && !key.is_empty() => Ok((field.to_string(), key.to_string())),
+        _ => Err(format!("expected FIELD=KEY, got '{}'", s)),
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Serialize)]
+enum FlagOn {
+    /// Entries logged as ERROR or FATAL
+    #[serde(rename = "level")]
+    Level,
+    /// Responses with a 5xx status, whatever the level
+    #[value(name = "5xx")]
+    #[serde(rename = "5xx")]
+    ServerErrors,
+}
+
+impl From<FlagOn> for FlagBasis {
+    fn from(basis: FlagOn) -> Self {
+        match basis {
+            FlagOn::Level => FlagBasis::Level,
+            FlagOn::ServerErrors => FlagBasis::ServerErrors,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum QueryStrings {
+    /// Count each distinct query string as its own endpoint
+    Keep,
+    /// Drop query strings and count paths only
+    Strip,
+    /// Replace parameter values with `*`, e.g. /search?q=*
+    Bucket,
+}
+
+impl From<QueryStrings> for QueryMode {
+    fn from(mode: QueryStrings) -> Self {
+        match mode {
+            QueryStrings::Keep => QueryMode::Keep,
+            QueryStrings::Strip => QueryMode::Strip,
+            QueryStrings::Bucket => QueryMode::Bucket,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum TrustProxy {
+    /// The leftmost hop that is not a private address
+    FirstPublic,
+    /// The rightmost hop that is not a trusted proxy
+    RightmostUntrusted,
+}
+
+impl From<TrustProxy> for ClientIpStrategy {
+    fn from(mode: TrustProxy) -> Self {
+        match mode {
+            TrustProxy::FirstPublic => ClientIpStrategy::FirstPublic,
+            TrustProxy::RightmostUntrusted => ClientIpStrategy::RightmostUntrusted,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum OnRotate {
+    /// Finish the original file and report partial results
+    Stop,
+    /// Finish the original file, then continue with the new one
+    Reopen,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum FollowOutput {
+    /// Clear the screen and print the whole report again
+    Text,
+    /// Print the report's JSON on a single line, one line per refresh
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum WindowOutput {
+    /// Print the report of each window in turn
+    Text,
+    /// Print each window's bounds and stats as a single JSON line
+    Json,
+}
+
+impl From<OnRotate> for RotationPolicy {
+    fn from(action: OnRotate) -> Self {
+        match action {
+            OnRotate::Stop => RotationPolicy::Stop,
+            OnRotate::Reopen => RotationPolicy::Reopen,
         }
     }
 }
+
+fn serialize_duration<S: serde::Serializer>(d: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+    match d {
+        Some(d) => s.serialize_some(&units::format_duration(*d)),
+        None => s.serialize_none(),
+    }
+}
+
+fn serialize_latency<S: serde::Serializer>(d: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+    match d {
+        Some(d) => s.serialize_some(&units::format_latency(*d)),
+        None => s.serialize_none(),
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    init_logging(args.verbose, args.log_json);
+
+    match args.command {
+        #[cfg(feature = "bundle")]
+        Some(Command::Bundle(bundle_args)) => run_bundle(&bundle_args),
+        #[cfg(feature = "kafka")]
+        Some(Command::Consume(consume_args)) => run_consume(&consume_args),
+        Some(Command::Listen(mut listen_args)) => {
+            // Every line received carries a syslog header
+            listen_args.analysis.syslog = true;
+            run_listen(&listen_args);
+        }
+        #[cfg(feature = "self-update")]
+        Some(Command::SelfUpdate(update_args)) => run_self_update(&update_args),
+        Some(Command::Capabilities) => print_capabilities(),
+        Some(Command::Formats) => print_formats(),
+        None => {
+            if args.deterministic {
+                colored::control::set_override(false);
+            }
+            let exports = Exports {
+                json: args.json_output.as_deref(),
+                html: args.html_output.as_deref(),
+                features_csv: args.features_csv.as_deref(),
+                redirect_flow: args.redirect_flow.as_deref(),
+                save_baseline: args.save_baseline.as_deref(),
+                deterministic: args.deterministic,
+            };
+            run_report(args.input(), &args.analysis, &exports);
+            #[cfg(feature = "self-update")]
+            if !args.deterministic {
+                update::notify_if_outdated();
+            }
+        }
+    }
+}
+
+/// Route the tool's own diagnostics through `tracing` on stderr
+fn init_logging(verbose: u8, json: bool) {
+    let level = match verbose {
+        0 => tracing::Level::WARN,
+        1 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_target(false);
+    if json {
+        builder.json().init();
+    } else {
+        builder.without_time().init();
+    }
+}
+
+/// Output files requested alongside the terminal report
+struct Exports<'a> {
+    json: Option<&'a Path>,
+    html: Option<&'a Path>,
+    features_csv: Option<&'a Path>,
+    redirect_flow: Option<&'a Path>,
+    save_baseline: Option<&'a Path>,
+    deterministic: bool,
+}
+
+/// Default mode: analyze the input, print the terminal report and write any requested exports
+fn run_report(input: Input, opts: &AnalysisArgs, exports: &Exports) {
+    let (stats, entries) = match input {
+        Input::Files(ref files) => analyze_files(files, opts),
+        Input::Incremental { ref file, ref state } => analyze_incremental(file, state, opts),
+        #[cfg(feature = "gelf")]
+        Input::Gelf(addr) => analyze_gelf(addr, opts),
+        #[cfg(all(feature = "journald", target_os = "linux"))]
+        Input::Journal(ref units) => analyze_journal(units, opts),
+        #[cfg(all(feature = "docker", unix))]
+        Input::Container(ref container) => analyze_container(container, opts),
+    };
+    let source = input.source();
+
+    // Print terminal report, or the last line of a followed JSON stream
+    match opts.follow {
+        Some(FollowOutput::Json) => print_followed(&stats, &source, FollowOutput::Json),
+        _ => report::print_report(&stats, stats.malformed_entries, &source),
+    }
+
+    // Optionally export JSON
+    if let Some(json_path) = exports.json {
+        let written = if exports.deterministic {
+            report::export_json_deterministic(&stats, json_path)
+        } else {
+            report::export_json(&stats, json_path)
+        };
+        match written {
+            Ok(_) => println!("\n✓ JSON report saved to '{}'", json_path.display()),
+            Err(e) => {
+                error!(path = %json_path.display(), "failed to write JSON output: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Optionally export HTML
+    if let Some(html_path) = exports.html {
+        let source = source.display().to_string();
+        match std::fs::write(html_path, report::render_html(&stats, &source)) {
+            Ok(_) => println!("\n✓ HTML report saved to '{}'", html_path.display()),
+            Err(e) => {
+                error!(path = %html_path.display(), "failed to write HTML output: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Optionally export the per-IP feature dataset
+    if let Some(csv_path) = exports.features_csv {
+        let rows = features::extract(&entries, &stats.flagged_ip_set());
+        match File::create(csv_path).and_then(|f| features::write_csv(std::io::BufWriter::new(f), &rows)) {
+            Ok(_) => println!("\n✓ Feature dataset ({} IPs) saved to '{}'", rows.len(), csv_path.display()),
+            Err(e) => {
+                error!(path = %csv_path.display(), "failed to write feature CSV: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Optionally export the redirect flow graph
+    if let Some(flow_path) = exports.redirect_flow {
+        let computed;
+        let report = match &stats.redirects {
+            Some(r) => r,
+            None => {
+                computed = redirects::analyze(&entries, opts.top_n);
+                &computed
+            }
+        };
+        match File::create(flow_path).and_then(|f| redirects::write_flow_csv(std::io::BufWriter::new(f), &report.edges)) {
+            Ok(_) => println!("\n✓ Redirect flow ({} edges) saved to '{}'", report.edges.len(), flow_path.display()),
+            Err(e) => {
+                error!(path = %flow_path.display(), "failed to write redirect flow: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Optionally save the timeline's spread as a baseline for later runs
+    if let Some(baseline_path) = exports.save_baseline {
+        let baseline = stats.timeline.as_ref().and_then(anomalies::Baseline::from_timeline);
+        let saved = match &baseline {
+            Some(baseline) => baseline.save(baseline_path),
+            None => Err(anomalies::BaselineError::Empty { path: baseline_path.to_path_buf() }),
+        };
+        match saved {
+            Ok(_) => println!("\n✓ Baseline saved to '{}'", baseline_path.display()),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// `capabilities` subcommand: show which optional features this build has
+fn print_capabilities() {
+    println!("log_analyzer {}", env!("CARGO_PKG_VERSION"));
+    for (name, enabled) in log_analyzer::CAPABILITIES {
+        let mark = if enabled { "✓" } else { "✗" };
+        println!("  {} {}", mark, name);
+    }
+}
+
+/// `formats` subcommand: list what `--format` accepts
+fn print_formats() {
+    println!("  {:<16} Detect the format from the first lines of the file", "auto");
+    for preset in formats::all() {
+        let mut line = format!("  {:<16} {}", preset.name(), preset.description());
+        if !preset.aliases().is_empty() {
+            line.push_str(&format!(" (also: {})", preset.aliases().join(", ")));
+        }
+        if !preset.detectable() {
+            line.push_str(" [not auto-detected]");
+        }
+        println!("{}", line);
+    }
+}
+
+/// `bundle` subcommand: analyze a file and package redacted artifacts into a tarball
+#[cfg(feature = "bundle")]
+fn run_bundle(args: &BundleArgs) {
+    if args.file.to_str().is_some_and(|f| inputs::is_url(f) || inputs::is_s3_url(f)) {
+        error!(url = %args.file.display(), "a bundle samples evidence from a local LOG_FILE; download it first");
+        std::process::exit(1);
+    }
+    let (stats, _) = analyze_file(&args.file, &args.analysis);
+    let format = stats
+        .detected_format
+        .as_ref()
+        .map_or_else(|| args.analysis.log_format(), |d| d.log_format.clone());
+
+    let config = serde_json::json!({
+        "tool_version": env!("CARGO_PKG_VERSION"),
+        "evidence_lines": args.evidence_lines,
+        "analysis": &args.analysis,
+    });
+
+    match bundle::write_bundle(&args.output, &args.file, &format, stats, &config, args.evidence_lines) {
+        Ok(_) => println!("✓ Support bundle saved to '{}'", args.output.display()),
+        Err(e) => {
+            error!(path = %args.output.display(), "failed to write bundle: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `self-update` subcommand: replace this binary with the latest release
+#[cfg(feature = "self-update")]
+fn run_self_update(args: &SelfUpdateArgs) {
+    let timeout = Duration::from_secs(60);
+    let current = env!("CARGO_PKG_VERSION");
+    let release = match update::latest_release(timeout) {
+        Ok(r) => r,
+        Err(e) => {
+            error!("could not check for updates: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if !update::is_newer(release.version(), current) {
+        println!("✓ log_analyzer {} is up to date", current);
+        return;
+    }
+    if args.check {
+        println!("A newer version is available: {} (running {})", release.version(), current);
+        return;
+    }
+
+    let (arch, os) = (std::env::consts::ARCH, std::env::consts::OS);
+    let Some(asset) = release.asset_for(arch, os) else {
+        error!("{}", update::UpdateError::NoAsset { arch, os });
+        std::process::exit(1);
+    };
+    match update::install(&release, asset, timeout) {
+        Ok(path) => println!(
+            "✓ Updated log_analyzer {} → {} at '{}'",
+            current,
+            release.version(),
+            path.display()
+        ),
+        Err(e) => {
+            error!("self-update failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Detect the format of a file, then load and analyze it.
+///
+/// The parsed entries are returned alongside the stats for exports that need them.
+fn analyze_file(file: &Path, opts: &AnalysisArgs) -> (analyzer::AnalysisStats, Vec<LogEntry>) {
+    if inputs::is_pipe(file) {
+        return analyze_pipe(file, opts);
+    }
+    let detection = opts.detect_format(file);
+    let format = detection.as_ref().map_or_else(|| opts.log_format(), |d| d.log_format.clone());
+    let loaded = match opts.follow {
+        Some(output) => follow_file(file, opts, format, detection.as_ref(), output),
+        None => load_entries(file, opts, format),
+    };
+    analyze_loaded(loaded, detection, opts)
+}
+
+/// Read what was appended to `file` since the run that saved `state`, and report on
+/// everything read over all runs (`--state-file`).
+///
+/// The core sections (levels, IPs, endpoints, status codes) cover every run; the
+/// sections that need the entries themselves cover this run's. A file that was
+/// rotated or truncated since is read from its start, and a line still being written
+/// is left for the next run.
+fn analyze_incremental(file: &Path, state: &Path, opts: &AnalysisArgs) -> (analyzer::AnalysisStats, Vec<LogEntry>) {
+    if inputs::is_pipe(file) {
+        error!(path = %file.display(), "--state-file needs a regular file, not a named pipe or device");
+        std::process::exit(1);
+    }
+    let checkpoint = match Checkpoint::load(state) {
+        Ok(checkpoint) => checkpoint,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(previous) = checkpoint.as_ref().filter(|c| c.path != file) {
+        error!(
+            state = %state.display(),
+            "the state file tracks '{}', not '{}'",
+            previous.path.display(),
+            file.display()
+        );
+        std::process::exit(1);
+    }
+    let opened = opts.open_input(file).and_then(|input| match input {
+        LogFile::Plain(reader) => {
+            let handle = reader.into_inner();
+            let meta = handle.metadata()?;
+            Ok((handle, meta))
+        }
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "--state-file needs an uncompressed file",
+        )),
+    });
+    let (mut handle, meta) = match opened {
+        Ok(opened) => opened,
+        Err(e) => {
+            error!(path = %file.display(), "could not open file: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let identity = ingest::file_identity(&meta);
+    let offset = match &checkpoint {
+        Some(previous) => previous.resume_offset(identity, meta.len()).unwrap_or_else(|| {
+            warn!(path = %file.display(), "file was rotated or truncated since the last run; reading it from the start");
+            0
+        }),
+        None => 0,
+    };
+    let positioned = state::complete_length(&mut handle, offset, meta.len())
+        .and_then(|end| handle.seek(SeekFrom::Start(offset)).map(|_| end));
+    let end = match positioned {
+        Ok(end) => end,
+        Err(e) => {
+            error!(path = %file.display(), "could not read file: {}", e);
+            std::process::exit(1);
+        }
+    };
+    debug!(path = %file.display(), offset, bytes = end - offset, "reading lines appended since the last run");
+
+    let detection = opts.detect_format(file);
+    let format = detection.as_ref().map_or_else(|| opts.log_format(), |d| d.log_format.clone());
+    let result = with_line_options(Ingest::new(BufReader::new(handle.take(end - offset))), opts)
+        .format(format)
+        .time_limit(opts.timeout)
+        .run();
+    log_loading(file, &result);
+    let (bytes_read, malformed) = (result.stats.bytes_read, result.malformed);
+    let (mut stats, entries) = analyze_loaded(result, detection, opts);
+
+    let mut checkpoint = checkpoint.unwrap_or_else(|| Checkpoint::new(file));
+    checkpoint.counts.add(&entries);
+    checkpoint.malformed += malformed;
+    checkpoint.identity = identity;
+    checkpoint.offset = offset + bytes_read;
+    if checkpoint.counts.entries == 0 {
+        error!(path = %file.display(), "no valid log entries found");
+        std::process::exit(1);
+    }
+    stats.set_counts(&checkpoint.counts);
+    keep_requested_sections(&mut stats, opts);
+    stats.malformed_entries = checkpoint.malformed;
+    if opts.time_columns {
+        analyzer::annotate_activity(&entries, &mut stats.top_ips, |e| Cow::from(e.ip.to_string()));
+        analyzer::annotate_activity(&entries, &mut stats.top_endpoints, |e| Cow::from(e.endpoint.as_str()));
+    }
+    if let Err(e) = checkpoint.save(state) {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+    (stats, entries)
+}
+
+/// Time between refreshes of a `--follow` report without `--refresh`
+const DEFAULT_REFRESH: Duration = Duration::from_secs(2);
+
+/// Read a file as it grows (`--follow`) until `--timeout` or `--max-lines`, showing
+/// the report on everything read so far on every refresh
+fn follow_file(
+    path: &Path,
+    opts: &AnalysisArgs,
+    format: LogFormat,
+    detection: Option<&detect::Detection>,
+    output: FollowOutput,
+) -> IngestResult {
+    if opts.journal {
+        error!(path = %path.display(), "--follow cannot read a journal export; follow the journal itself with --unit");
+        std::process::exit(1);
+    }
+    let reader = match opts.open_input(path) {
+        Ok(LogFile::Plain(reader)) => reader,
+        Ok(LogFile::Compressed(compression, _)) => {
+            error!(path = %path.display(), "--follow cannot read a {}-compressed file", compression);
+            std::process::exit(1);
+        }
+        Ok(LogFile::Stream(_)) => {
+            error!(path = %path.display(), "--follow needs a local file");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            error!(path = %path.display(), "could not open file: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let refresh = opts.refresh.unwrap_or(DEFAULT_REFRESH);
+    let ingest = Ingest::new(reader)
+        .watch_file(path, opts.on_rotate.into())
+        .follow(refresh, refresh_report(path, opts, detection, output));
+    let result = with_line_options(ingest, opts).format(format).time_limit(opts.timeout).run();
+    finish_loading(path, result)
+}
+
+/// What a followed input does on every refresh: analyze everything read so far and
+/// show the report
+fn refresh_report<'a>(
+    source: &'a Path,
+    opts: &'a AnalysisArgs,
+    detection: Option<&'a detect::Detection>,
+    output: FollowOutput,
+) -> impl FnMut(&[LogEntry], &ingest::PartialStats) + 'a {
+    move |entries, progress| {
+        if entries.is_empty() {
+            return;
+        }
+        let loaded = IngestResult {
+            entries: entries.to_vec(),
+            malformed: progress.malformed,
+            stats: progress.clone(),
+            read_error: None,
+            incomplete: None,
+            reopened: 0,
+        };
+        let (stats, _) = analyze_loaded(loaded, detection.cloned(), opts);
+        print_followed(&stats, source, output);
+    }
+}
+
+/// Read the systemd journal of `units` (`--journal --unit`) and analyze the entries,
+/// following it with `--follow` as [`follow_file`] does a file
+#[cfg(all(feature = "journald", target_os = "linux"))]
+fn analyze_journal(units: &[String], opts: &AnalysisArgs) -> (analyzer::AnalysisStats, Vec<LogEntry>) {
+    let started = Instant::now();
+    let source = match journald::JournalSource::open(units, opts.follow.is_some()) {
+        Ok(source) => source,
+        Err(e) => {
+            error!(units = %units.join(","), "could not run journalctl: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let name = Input::Journal(units.to_vec()).source();
+    analyze_live(&name, source, journald::JournalSource::peek_lines, started, opts)
+}
+
+/// Stream the logs of a Docker container (`--docker --container`) and analyze them,
+/// following them with `--follow` until the container stops
+#[cfg(all(feature = "docker", unix))]
+fn analyze_container(container: &str, opts: &AnalysisArgs) -> (analyzer::AnalysisStats, Vec<LogEntry>) {
+    let started = Instant::now();
+    let socket = docker::socket_path();
+    let source = match docker::container_logs(&socket, container, opts.follow.is_some()) {
+        Ok(source) => source,
+        Err(e) => {
+            error!(container = %container, "could not read the container's logs: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let name = Input::Container(container.to_string()).source();
+    analyze_live(&name, source, ingest::LiveLines::peek_lines, started, opts)
+}
+
+/// Read a named pipe or character device ([`inputs::is_pipe`]) as it is written to,
+/// without sampling or seeking it first, and analyze it; with `--follow`, a named
+/// pipe is read across writers, until interrupted or `--idle-timeout`
+fn analyze_pipe(file: &Path, opts: &AnalysisArgs) -> (analyzer::AnalysisStats, Vec<LogEntry>) {
+    let started = Instant::now();
+    analyze_live(file, open_pipe(file, opts), ingest::LiveLines::peek_lines, started, opts)
+}
+
+/// Start reading a named pipe or character device on a thread of its own, exiting if
+/// it cannot be read that way
+fn open_pipe(file: &Path, opts: &AnalysisArgs) -> ingest::LiveLines {
+    if opts.last_lines.is_some() {
+        error!(path = %file.display(), "--last-lines cannot read a named pipe or device back from its end");
+        std::process::exit(1);
+    }
+    let follow = opts.follow.is_some();
+    let pipe = inputs::PipeReader::new(file, follow);
+    let lines = if opts.journal {
+        ingest::LiveLines::spawn(journal::ExportReader::new(BufReader::new(pipe)), follow)
+    } else {
+        ingest::LiveLines::spawn(pipe, follow)
+    };
+    lines.idle_timeout(opts.idle_timeout)
+}
+
+/// Analyze what a live source (`started` when it was opened) has written and, with
+/// `--follow`, keeps writing; `peek` shows its first lines to detect the format from
+fn analyze_live<R: BufRead>(
+    name: &Path,
+    mut source: R,
+    peek: fn(&mut R, usize, Duration) -> Vec<String>,
+    started: Instant,
+    opts: &AnalysisArgs,
+) -> (analyzer::AnalysisStats, Vec<LogEntry>) {
+    let detection = opts.detect_live(name, |limit, wait| peek(&mut source, limit, wait));
+    let format = detection.as_ref().map_or_else(|| opts.log_format(), |d| d.log_format.clone());
+    let ingest = match opts.follow {
+        Some(output) => {
+            let refresh = opts.refresh.unwrap_or(DEFAULT_REFRESH);
+            Ingest::new(source).follow(refresh, refresh_report(name, opts, detection.as_ref(), output))
+        }
+        None => Ingest::new(source),
+    };
+    // Waiting for lines to detect the format counts toward the time limit
+    let time_limit = opts.timeout.map(|limit| limit.saturating_sub(started.elapsed()));
+    let result = with_line_options(ingest, opts).format(format).time_limit(time_limit).run();
+    analyze_loaded(finish_loading(name, result), detection, opts)
+}
+
+/// Show one refresh of a `--follow` report
+fn print_followed(stats: &analyzer::AnalysisStats, source: &Path, output: FollowOutput) {
+    match output {
+        FollowOutput::Text => {
+            if std::io::stdout().is_terminal() {
+                // Clear the screen and move to its top, so the report redraws in place
+                print!("\x1b[2J\x1b[H");
+            }
+            report::print_report(stats, stats.malformed_entries, source);
+        }
+        FollowOutput::Json => match serde_json::to_string(stats) {
+            Ok(line) => println!("{}", line),
+            Err(e) => error!("failed to serialize the report: {}", e),
+        },
+    }
+    let _ = std::io::stdout().flush();
+}
+
+/// A file read by [`analyze_files`], with the format detected for it
+type LoadedFile = (Option<detect::Detection>, IngestResult);
+
+/// Load several files and analyze their entries as one, in timestamp order, with
+/// the parsed and malformed lines of each file in the report.
+///
+/// Each file's format is detected on its own. Files are read on several threads at
+/// once, except under `--max-lines`, which takes the first lines of the files in the
+/// order given. `--max-lines` and `--timeout` apply to the files together, and files
+/// not started once either runs out are not read.
+fn analyze_files(files: &[PathBuf], opts: &AnalysisArgs) -> (analyzer::AnalysisStats, Vec<LogEntry>) {
+    if let [file] = files {
+        return analyze_file(file, opts);
+    }
+    if opts.follow.is_some() {
+        error!(files = files.len(), "--follow reads a single file");
+        std::process::exit(1);
+    }
+    let loaded = match opts.max_lines {
+        Some(_) => load_in_turn(files, opts),
+        None => load_in_parallel(files, opts),
+    };
+
+    // Merged in the order given, so the report does not depend on which thread finished first
+    let mut merged: Option<IngestResult> = None;
+    let mut summaries = Vec::new();
+    for (file, (detection, loaded)) in files.iter().zip(loaded).filter_map(|(file, loaded)| Some((file, loaded?))) {
+        summaries.push(FileSummary {
+            path: file.display().to_string(),
+            format: detection.map(|d| d.format),
+            parsed: loaded.entries.len(),
+            malformed: loaded.malformed,
+            incomplete: loaded.incomplete.clone(),
+        });
+        match &mut merged {
+            Some(merged) => merged.append(loaded),
+            None => merged = Some(loaded),
+        }
+    }
+
+    let mut merged = merged.expect("at least one file is read");
+    if summaries.len() < files.len() {
+        merged
+            .incomplete
+            .get_or_insert_with(|| format!("read {} of {} files", summaries.len(), files.len()));
+    }
+    if merged.entries.is_empty() {
+        error!(files = files.len(), "no valid log entries found in any file");
+        std::process::exit(1);
+    }
+    merged.entries.sort_by_key(|e| e.timestamp);
+    let (mut stats, entries) = analyze_loaded(merged, None, opts);
+    stats.files = Some(summaries);
+    (stats, entries)
+}
+
+/// Read `files` one after the other, until `--max-lines` or `--timeout` runs out.
+/// Files not read are `None`.
+fn load_in_turn(files: &[PathBuf], opts: &AnalysisArgs) -> Vec<Option<LoadedFile>> {
+    let started = Instant::now();
+    let mut lines_read = 0;
+    let mut loaded = Vec::with_capacity(files.len());
+    for file in files {
+        let max_lines = opts.max_lines.map(|limit| limit.saturating_sub(lines_read));
+        let time_limit = opts.timeout.map(|limit| limit.saturating_sub(started.elapsed()));
+        if max_lines == Some(0) || time_limit == Some(Duration::ZERO) {
+            loaded.push(None);
+            continue;
+        }
+        let file = load_file(file, opts, max_lines, time_limit);
+        lines_read += file.1.stats.lines_read - file.1.stats.skipped;
+        loaded.push(Some(file));
+    }
+    loaded
+}
+
+/// Read `files` on as many threads as there are cores, each taking the next file not
+/// yet started and sending what it read back over a channel, until `--timeout` runs
+/// out. Files not read are `None`.
+fn load_in_parallel(files: &[PathBuf], opts: &AnalysisArgs) -> Vec<Option<LoadedFile>> {
+    let started = Instant::now();
+    let workers = std::thread::available_parallelism().map_or(1, NonZeroUsize::get).min(files.len());
+    let next = AtomicUsize::new(0);
+    let (sender, received) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let (sender, next) = (sender.clone(), &next);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(file) = files.get(index) else { break };
+                let time_limit = opts.timeout.map(|limit| limit.saturating_sub(started.elapsed()));
+                if time_limit == Some(Duration::ZERO) {
+                    break;
+                }
+                if sender.send((index, load_file(file, opts, None, time_limit))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+        let mut loaded: Vec<Option<LoadedFile>> = files.iter().map(|_| None).collect();
+        for (index, file) in received {
+            loaded[index] = Some(file);
+        }
+        loaded
+    })
+}
+
+/// Detect the format of one of several files and read it within the given limits
+fn load_file(file: &Path, opts: &AnalysisArgs, max_lines: Option<usize>, time_limit: Option<Duration>) -> LoadedFile {
+    let (detection, loaded) = if inputs::is_pipe(file) {
+        let mut source = open_pipe(file, opts);
+        let detection = opts.detect_live(file, |limit, wait| source.peek_lines(limit, wait));
+        let format = detection.as_ref().map_or_else(|| opts.log_format(), |d| d.log_format.clone());
+        let ingest = with_line_options(Ingest::new(source), opts).max_lines(max_lines);
+        (detection, ingest.format(format).time_limit(time_limit).run())
+    } else {
+        let detection = opts.detect_format(file);
+        let format = detection.as_ref().map_or_else(|| opts.log_format(), |d| d.log_format.clone());
+        (detection, read_entries(file, opts, format, max_lines, time_limit))
+    };
+    log_loading(file, &loaded);
+    if loaded.entries.is_empty() {
+        warn!(path = %file.display(), "no valid log entries found");
+    }
+    (detection, loaded)
+}
+
+/// Listen for GELF messages until `--timeout` or `--max-lines`, then analyze them as
+/// [`analyze_file`] does a file
+#[cfg(feature = "gelf")]
+fn analyze_gelf(addr: SocketAddr, opts: &AnalysisArgs) -> (analyzer::AnalysisStats, Vec<LogEntry>) {
+    if opts.timeout.is_none() && opts.max_lines.is_none() {
+        error!("--listen-gelf needs --timeout or --max-lines to know when to stop");
+        std::process::exit(1);
+    }
+    let deadline = opts.timeout.map(|limit| std::time::Instant::now() + limit);
+    let listener = match gelf::GelfListener::bind(addr, deadline) {
+        Ok(l) => l,
+        Err(e) => {
+            error!(%addr, "could not listen for GELF messages: {}", e);
+            std::process::exit(1);
+        }
+    };
+    debug!(%addr, "listening for GELF messages");
+
+    // The listener stops at the deadline itself, so no time limit is set here
+    let result = with_line_options(Ingest::new(BufReader::new(listener)), opts)
+        .format(LogFormat::Gelf)
+        .run();
+    analyze_loaded(finish_loading(&Input::Gelf(addr).source(), result), None, opts)
+}
+
+/// Longest `consume`, `listen`, `--unit` and `--container` wait for lines to detect the log format from
+const SAMPLE_WAIT: Duration = Duration::from_secs(10);
+
+/// `consume` subcommand: read Kafka topics in windows of `--interval`
+#[cfg(feature = "kafka")]
+fn run_consume(args: &ConsumeArgs) {
+    let opts = &args.analysis;
+    if opts.follow.is_some() || opts.journal || opts.skip_lines.is_some() || opts.last_lines.is_some() {
+        error!("consume reads each Kafka message as log lines; --follow, --journal, --skip-lines and --last-lines do not apply");
+        std::process::exit(1);
+    }
+    let config = KafkaConfig {
+        brokers: args.brokers.clone(),
+        topics: args.topics.clone(),
+        group: args.group.clone(),
+        from_beginning: args.from_beginning,
+    };
+    let mut source = match KafkaSource::connect(&config) {
+        Ok(source) => source,
+        Err(e) => {
+            error!(brokers = %args.brokers, "could not create the Kafka consumer: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let name = PathBuf::from(format!("kafka://{}/{}", args.brokers, args.topics.join(",")));
+    analyze_windows(&mut source, &name, opts, args.interval, args.output);
+}
+
+/// `listen` subcommand: receive syslog messages over UDP and/or TCP in windows of
+/// `--interval`
+fn run_listen(args: &ListenArgs) {
+    let opts = &args.analysis;
+    if opts.follow.is_some() || opts.journal || opts.docker || opts.skip_lines.is_some() || opts.last_lines.is_some() {
+        error!("listen reads syslog messages; --follow, --journal, --docker, --skip-lines and --last-lines do not apply");
+        std::process::exit(1);
+    }
+    let mut listener = match syslog::SyslogListener::bind(args.udp, args.tcp) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(udp = ?args.udp, tcp = ?args.tcp, "could not listen for syslog messages: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let bound: Vec<String> = [("udp", listener.udp_addr()), ("tcp", listener.tcp_addr())]
+        .into_iter()
+        .filter_map(|(protocol, addr)| addr.map(|addr| format!("syslog+{}://{}", protocol, addr)))
+        .collect();
+    debug!(on = %bound.join(", "), "listening for syslog messages");
+    analyze_windows(&mut listener, Path::new(&bound.join(", ")), opts, args.interval, args.output);
+}
+
+/// Read a live source in windows of `interval` and print the stats of each window
+/// that received entries, until `--timeout` or `--max-lines` (or until interrupted).
+///
+/// With `--format auto`, the format is detected from the first lines received.
+fn analyze_windows(
+    source: &mut impl Windowed,
+    name: &Path,
+    opts: &AnalysisArgs,
+    interval: Duration,
+    output: WindowOutput,
+) {
+    let deadline = opts.timeout.map(|limit| Instant::now() + limit);
+    let detection = if opts.auto_format() {
+        let wait = opts.timeout.map_or(SAMPLE_WAIT, |limit| limit.min(SAMPLE_WAIT));
+        let sample = source.peek_lines(detect::SAMPLE_LINES, wait);
+        opts.detect_sample(&sample, name)
+    } else {
+        None
+    };
+    let format = detection.as_ref().map_or_else(|| opts.log_format(), |d| d.log_format.clone());
+
+    let mut lines_read = 0;
+    loop {
+        let window_start = chrono::Utc::now();
+        let end = Instant::now() + interval;
+        source.end_window_at(deadline.map_or(end, |deadline| end.min(deadline)));
+        let max_lines = opts.max_lines.map(|limit| limit.saturating_sub(lines_read));
+        let result = with_line_options(Ingest::new(&mut *source), opts)
+            .max_lines(max_lines)
+            .format(format.clone())
+            .run();
+        lines_read += result.stats.lines_read;
+        log_loading(name, &result);
+
+        if result.entries.is_empty() {
+            debug!(source = %name.display(), "no entries in this window");
+        } else {
+            let (stats, _) = analyze_loaded(result, detection.clone(), opts);
+            print_window(&stats, name, window_start, chrono::Utc::now(), output);
+        }
+        if opts.max_lines.is_some_and(|limit| lines_read >= limit)
+            || deadline.is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            break;
+        }
+    }
+}
+
+/// Show the stats of one window of a live source
+fn print_window(
+    stats: &analyzer::AnalysisStats,
+    source: &Path,
+    start: chrono::DateTime<chrono::Utc>,
+    end: chrono::DateTime<chrono::Utc>,
+    output: WindowOutput,
+) {
+    let (start, end) = (
+        start.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        end.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    );
+    match output {
+        WindowOutput::Text => {
+            println!("\n=== Window {} – {} ===", start, end);
+            report::print_report(stats, stats.malformed_entries, source);
+        }
+        WindowOutput::Json => {
+            let window = serde_json::json!({ "window_start": start, "window_end": end, "stats": stats });
+            println!("{}", window);
+        }
+    }
+    let _ = std::io::stdout().flush();
+}
+
+/// Drop the core sections that are reported only when asked for: the unique counts
+/// (`--unique`, `--approx`), the status classes (`--status-classes`) and the method
+/// breakdown (`--methods`). The core counts build them all.
+fn keep_requested_sections(stats: &mut analyzer::AnalysisStats, opts: &AnalysisArgs) {
+    if !opts.unique && !opts.approx {
+        stats.unique_ips = None;
+        stats.unique_endpoints = None;
+    }
+    if !opts.status_classes {
+        stats.status_classes = None;
+    }
+    if !opts.methods {
+        stats.method_distribution.clear();
+    }
+}
+
+/// Analyze loaded entries, filling in every optional section requested by `opts`
+fn analyze_loaded(
+    loaded: IngestResult,
+    detection: Option<detect::Detection>,
+    opts: &AnalysisArgs,
+) -> (analyzer::AnalysisStats, Vec<LogEntry>) {
+    let mut entries = loaded.entries;
+    let forwarded_clients = opts.trust_proxy.map(|mode| {
+        ProxyPolicy::new(mode.into(), opts.trusted_proxies.clone()).resolve_clients(&mut entries)
+    });
+    if !opts.raw_paths {
+        paths::normalize_entries(&mut entries);
+    }
+    query::normalize_entries(&mut entries, opts.query_strings.into());
+    if opts.collapse_paths || opts.route_patterns.is_some() {
+        let patterns = match &opts.route_patterns {
+            Some(path) => routes::load_routes(path).unwrap_or_else(|e| {
+                error!(path = %path.display(), "could not load route patterns: {}", e);
+                std::process::exit(1);
+            }),
+            None => Vec::new(),
+        };
+        templates::template_entries(&mut entries, &patterns, opts.collapse_paths);
+    }
+
+    // Analyze parsed entries
+    let mut stats = analyzer::analyze(&entries, opts.top_n, opts.error_threshold);
+    stats.detected_format = detection;
+    stats.malformed_entries = loaded.malformed;
+    stats.forwarded_clients = forwarded_clients;
+    stats.incomplete = loaded.incomplete;
+    stats.flag_on = opts.flag_on.into();
+    if let Some(sampling) = opts.sampling() {
+        // The core counts estimate the whole input; the other sections describe the sample
+        let factor = 1.0 / sampling.rate();
+        stats.set_counts(&analyzer::Counts::from(&entries[..]).scaled(factor));
+        stats.malformed_entries = (loaded.malformed as f64 * factor).round() as usize;
+        stats.sample = Some(analyzer::SampleSummary {
+            rate: sampling.rate(),
+            lines_read: loaded.stats.lines_read - loaded.stats.skipped,
+            lines_sampled: loaded.stats.lines_read - loaded.stats.skipped - loaded.stats.sampled_out,
+        });
+    } else if stats.flag_on != FlagBasis::Level {
+        stats.flagged_ips = analyzer::flag_ips(&analyzer::Counts::from(&entries[..]), opts.error_threshold, stats.flag_on);
+    }
+    if opts.approx {
+        stats.unique_ips = Some(hll::count_distinct(entries.iter().map(|e| &e.ip)));
+        stats.unique_endpoints = Some(hll::count_distinct(entries.iter().map(|e| e.endpoint.as_str())));
+        stats.cardinality_error = Some(hll::STANDARD_ERROR);
+    }
+    if opts.methods {
+        stats.endpoint_methods = analyzer::endpoint_methods(&entries, &stats.top_endpoints);
+    }
+    keep_requested_sections(&mut stats, opts);
+    if opts.mixed {
+        stats.non_access_lines = Some(loaded.stats.non_access);
+    }
+    if opts.multiline {
+        stats.continuation_lines = Some(loaded.stats.continuation);
+    }
+    if opts.lenient {
+        stats.partial_entries = Some(loaded.stats.partial);
+    }
+    if opts.recover {
+        stats.recovered_entries = Some(loaded.stats.recovered);
+    }
+
+    if opts.time_columns {
+        analyzer::annotate_activity(&entries, &mut stats.top_ips, |e| Cow::from(e.ip.to_string()));
+        analyzer::annotate_activity(&entries, &mut stats.top_endpoints, |e| Cow::from(e.endpoint.as_str()));
+    }
+
+    if opts.endpoint_errors {
+        let min_requests = opts.min_endpoint_requests.unwrap_or(endpoint_errors::DEFAULT_MIN_REQUESTS);
+        stats.endpoint_errors = endpoint_errors::analyze(&entries, opts.top_n, min_requests);
+        if stats.endpoint_errors.is_none() {
+            warn!("endpoint error ranking skipped: no entry logs a status code");
+        }
+    }
+
+    if !opts.site_hosts.is_empty() || opts.referrer_spam.is_some() {
+        let spam_domains = match &opts.referrer_spam {
+            Some(path) => match referrers::load_spam_domains(path) {
+                Ok(domains) => domains,
+                Err(e) => {
+                    error!(path = %path.display(), "could not read referrer spam list: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            None => Vec::new(),
+        };
+        stats.referrers = referrers::analyze(&entries, &opts.site_hosts, &spam_domains, opts.top_n);
+    }
+
+    if let Some(threshold) = opts.burst_threshold {
+        let window = opts.burst_window.unwrap_or(bursts::DEFAULT_WINDOW);
+        stats.bursts = Some(bursts::detect(&entries, threshold, window, opts.top_n));
+    }
+
+    if opts.attacks {
+        stats.attacks = Some(attacks::scan(&entries, opts.top_n));
+    }
+
+    if let Some(min_paths) = opts.scanner_threshold {
+        let share = opts.scanner_404_share.unwrap_or(scanners::DEFAULT_NOT_FOUND_SHARE);
+        stats.scanners = scanners::detect(&entries, min_paths, share, opts.top_n);
+        if stats.scanners.is_none() {
+            warn!("scanner detection skipped: no entry logs a status code");
+        }
+    }
+
+    if let Some(target) = opts.apdex_t {
+        stats.apdex = apdex::compute(&entries, target, opts.top_n);
+        if stats.apdex.is_none() {
+            warn!("Apdex skipped: no entry logs a response time");
+        }
+    }
+
+    if let Some(objective) = opts.slo {
+        stats.slo = slo::evaluate(&entries, objective);
+        if stats.slo.is_none() {
+            warn!("SLO skipped: no entry logs a status code");
+        }
+    }
+
+    if opts.compare_cohorts {
+        stats.cohort_comparison = Some(cohorts::compare(&entries, &stats.flagged_ip_set()));
+    }
+
+    if opts.redirects {
+        stats.redirects = Some(redirects::analyze(&entries, opts.top_n));
+    }
+
+    if opts.by_router {
+        stats.routers = routers::analyze(&entries, opts.top_n);
+        if stats.routers.is_none() {
+            warn!("router breakdown skipped: no entry logs a router");
+        }
+    }
+
+    if opts.browsers {
+        stats.browsers = browsers::analyze(&entries, opts.top_n);
+        if stats.browsers.is_none() {
+            warn!("browser breakdown skipped: no entry logs a user agent");
+        }
+    }
+
+    if opts.bots {
+        stats.bots = bots::analyze(&entries, opts.top_n);
+    }
+
+    if let Some(width) = opts.bucket {
+        let clients = match (opts.approx, opts.unique) {
+            (true, _) => ClientCounting::Approx,
+            (false, true) => ClientCounting::Exact,
+            (false, false) => ClientCounting::Off,
+        };
+        match timeline::bucket(&entries, width, clients) {
+            Ok(timeline) => {
+                if opts.anomalies {
+                    let threshold = opts.anomaly_threshold.unwrap_or(anomalies::DEFAULT_THRESHOLD);
+                    stats.anomalies = Some(match &opts.baseline {
+                        Some(path) => match anomalies::Baseline::load(path, timeline.bucket_seconds) {
+                            Ok(baseline) => anomalies::against(&timeline, &baseline, threshold, opts.top_n),
+                            Err(e) => {
+                                error!("{}", e);
+                                std::process::exit(1);
+                            }
+                        },
+                        None => {
+                            let window = opts.baseline_window.unwrap_or(anomalies::DEFAULT_WINDOW);
+                            anomalies::rolling(&timeline, window, threshold, opts.top_n)
+                        }
+                    });
+                }
+                stats.requests_per_second = timeline.rate_stats();
+                stats.timeline = Some(timeline);
+            }
+            Err(e) => warn!("requests over time skipped: {}", e),
+        }
+    }
+
+    if opts.by_trace {
+        stats.traces = traces::analyze(&entries, opts.top_n);
+        if stats.traces.is_none() {
+            warn!("trace grouping skipped: no entry logs a trace id");
+        }
+    }
+
+    if opts.sessions {
+        stats.sessions = sessions::analyze(&entries, opts.session_gap.unwrap_or(sessions::DEFAULT_GAP), opts.top_n);
+    }
+
+    if opts.forecast {
+        stats.forecast = forecast::forecast(&entries);
+        if stats.forecast.is_none() {
+            warn!("forecast skipped: needs at least 48 hours of entries");
+        }
+    }
+
+    if opts.cost_per_gb.is_some() || opts.cost_per_million_requests.is_some() {
+        let costs = cost::UnitCosts {
+            per_gb_egress: opts.cost_per_gb.unwrap_or(0.0),
+            per_million_requests: opts.cost_per_million_requests.unwrap_or(0.0),
+        };
+        stats.cost_attribution = Some(cost::attribute(&entries, costs, opts.top_n));
+    }
+
+    if let Some(routes_path) = &opts.expected_endpoints {
+        let routes = match routes::load_routes(routes_path) {
+            Ok(r) => r,
+            Err(e) => {
+                error!(path = %routes_path.display(), "could not load expected endpoints: {}", e);
+                std::process::exit(1);
+            }
+        };
+        debug!(routes = routes.len(), "loaded expected endpoints");
+        stats.endpoint_coverage = Some(routes::coverage(&entries, &routes, opts.top_n));
+    }
+
+    if let Some(spec_path) = &opts.openapi {
+        let operations = match routes::load_spec(spec_path) {
+            Ok(r) => r,
+            Err(e) => {
+                error!(path = %spec_path.display(), "could not load OpenAPI spec: {}", e);
+                std::process::exit(1);
+            }
+        };
+        debug!(operations = operations.len(), "loaded OpenAPI spec");
+        stats.spec_correlation = Some(routes::correlate(&entries, &operations, opts.top_n));
+    }
+
+    if let Some(robots_path) = &opts.robots {
+        let rules = match robots::load_robots(robots_path) {
+            Ok(r) => r,
+            Err(e) => {
+                error!(path = %robots_path.display(), "{}", e);
+                std::process::exit(1);
+            }
+        };
+        stats.robots_compliance = Some(robots::compliance(&entries, &rules, opts.top_n));
+    }
+
+    #[cfg(feature = "geoip")]
+    if let Some(db_path) = &opts.geoip {
+        let database = match geoip::GeoIpDatabase::open(db_path) {
+            Ok(db) => db,
+            Err(e) => {
+                error!(path = %db_path.display(), "could not open GeoIP database: {}", e);
+                std::process::exit(1);
+            }
+        };
+        debug!(database = database.database_type(), "loaded GeoIP database");
+        stats.countries = Some(geo::country_breakdown(&entries, &database, opts.top_n));
+        if opts.impossible_travel {
+            let config = geo::VelocityConfig::default();
+            stats.travel = Some(geo::user_travel(&entries, &database, config, opts.top_n));
+        }
+    }
+
+    #[cfg(feature = "geoip")]
+    if let Some(db_path) = &opts.asn {
+        let database = match geoip::AsnDatabase::open(db_path) {
+            Ok(db) => db,
+            Err(e) => {
+                error!(path = %db_path.display(), "could not open ASN database: {}", e);
+                std::process::exit(1);
+            }
+        };
+        debug!(database = database.database_type(), "loaded ASN database");
+        asn::annotate_flagged(&mut stats.flagged_ips, &database);
+        stats.networks = Some(asn::breakdown(&entries, &database, &stats.flagged_ip_set(), opts.top_n));
+    }
+
+    (stats, entries)
+}
+
+/// Stream a log file line-by-line, returning the parsed entries and line counters.
+///
+/// Exits the process if the file cannot be opened or contains no valid entries.
+fn load_entries(path: &Path, opts: &AnalysisArgs, format: LogFormat) -> IngestResult {
+    finish_loading(path, read_entries(path, opts, format, opts.max_lines, opts.timeout))
+}
+
+/// Stream a log file line-by-line within the given limits, exiting if it cannot be opened
+fn read_entries(
+    path: &Path,
+    opts: &AnalysisArgs,
+    format: LogFormat,
+    max_lines: Option<usize>,
+    time_limit: Option<Duration>,
+) -> IngestResult {
+    // Open or fetch the log file, decompressing it if it is compressed
+    let file = match opts.open_input(path) {
+        Ok(f) => f,
+        Err(e) => {
+            error!(path = %path.display(), "could not open file: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(count) = opts.last_lines {
+        return match tail_input(file, opts, &format, count) {
+            Ok(tail) => {
+                with_line_options(Ingest::new(tail), opts).max_lines(max_lines).format(format).time_limit(time_limit).run()
+            }
+            Err(e) => {
+                error!(path = %path.display(), "could not read the last lines of the file: {}", e);
+                std::process::exit(1);
+            }
+        };
+    }
+
+    #[cfg(feature = "mmap")]
+    if opts.mmap && matches!(file, LogFile::Plain(_)) {
+        match mmap::MappedFile::open(path) {
+            Ok(mapped) => return read_mapped(&mapped, opts, format, max_lines, time_limit),
+            Err(e) => warn!(path = %path.display(), "could not memory-map the file, reading it instead: {}", e),
+        }
+    }
+
+    // Stream through file line-by-line for memory efficiency
+    match file {
+        // Journal export entries span several lines and are joined first, so the
+        // joined stream is not watched for rotation
+        file if opts.journal => {
+            let ingest = Ingest::new(journal::ExportReader::new(file));
+            with_line_options(ingest, opts).max_lines(max_lines).format(format).time_limit(time_limit).run()
+        }
+        LogFile::Plain(reader) => {
+            let ingest = Ingest::new(reader).watch_file(path, opts.on_rotate.into());
+            with_line_options(ingest, opts).max_lines(max_lines).format(format).time_limit(time_limit).run()
+        }
+        // Compressed archives and downloads are not written to, so there is nothing to watch
+        file @ (LogFile::Compressed(..) | LogFile::Stream(_)) => {
+            let ingest = Ingest::new(file);
+            with_line_options(ingest, opts).max_lines(max_lines).format(format).time_limit(time_limit).run()
+        }
+    }
+}
+
+/// Parse a memory-mapped file (`--mmap`) in chunks on every core.
+///
+/// Input whose lines depend on the ones before them (W3C and CSV headers, continuation
+/// lines) or that is cut short by `--max-lines` is parsed in one piece, on one thread.
+#[cfg(feature = "mmap")]
+fn read_mapped(
+    mapped: &mmap::MappedFile,
+    opts: &AnalysisArgs,
+    format: LogFormat,
+    max_lines: Option<usize>,
+    time_limit: Option<Duration>,
+) -> IngestResult {
+    let in_order = opts.multiline || max_lines.is_some() || matches!(format, LogFormat::W3c(_) | LogFormat::Csv(_));
+    let cores = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let count = match in_order {
+        true => 1,
+        false => cores.min(mapped.bytes().len() / mmap::MIN_CHUNK).max(1),
+    };
+    let chunks = mmap::split_lines(mapped.bytes(), count);
+    debug!(chunks = chunks.len(), bytes = mapped.bytes().len(), "parsing memory-mapped file");
+    mmap::ingest_chunks(&chunks, |ingest| {
+        with_line_options(ingest, opts)
+            .max_lines(max_lines)
+            .format(format.clone())
+            .time_limit(time_limit)
+    })
+}
+
+/// The last `count` lines of an opened file (`--last-lines`), after the header lines
+/// its format needs. An uncompressed file is read back from its end; anything else is
+/// read through, keeping only the last lines.
+fn tail_input(file: LogFile, opts: &AnalysisArgs, format: &LogFormat, count: usize) -> std::io::Result<impl BufRead> {
+    let (header, tail): (Vec<u8>, Box<dyn BufRead>) = match file {
+        LogFile::Plain(mut reader) if !opts.journal => {
+            let header = ingest::header_lines(&mut reader, format)?;
+            let offset = ingest::tail_offset(reader.get_mut(), count)?.max(header.len() as u64);
+            reader.seek(SeekFrom::Start(offset))?;
+            (header, Box::new(reader))
+        }
+        file => {
+            let mut reader: Box<dyn BufRead> = match opts.journal {
+                true => Box::new(journal::ExportReader::new(file)),
+                false => Box::new(file),
+            };
+            let header = ingest::header_lines(&mut reader, format)?;
+            let tail = ingest::last_lines(&mut reader, count)?;
+            (header, Box::new(Cursor::new(tail)))
+        }
+    };
+    Ok(Cursor::new(header).chain(tail))
+}
+
+/// Apply the line limit and the line-handling options of `opts` to an ingestion
+fn with_line_options<'a, R: BufRead>(ingest: Ingest<'a, R>, opts: &AnalysisArgs) -> Ingest<'a, R> {
+    let quiet = opts.quiet;
+    ingest
+        .max_lines(opts.max_lines)
+        .mixed(opts.mixed)
+        .multiline(opts.multiline)
+        .lenient(opts.lenient)
+        .recover(opts.recover)
+        .level_aliases(opts.level_aliases.iter().cloned().collect())
+        .sample(opts.sampling())
+        .skip_lines(opts.skip_lines.unwrap_or(0))
+        .on_malformed(move |m| {
+            if !quiet {
+                warn!(
+                    line = m.line_number,
+                    "malformed line — {}: {:?}",
+                    m.reason,
+                    &m.line[..m.line.len().min(80)]
+                );
+            }
+        })
+}
+
+/// Report how reading `path` ended, exiting if it produced no entries
+fn finish_loading(path: &Path, result: IngestResult) -> IngestResult {
+    log_loading(path, &result);
+    if result.entries.is_empty() {
+        error!(path = %path.display(), "no valid log entries found");
+        std::process::exit(1);
+    }
+    result
+}
+
+/// Log how reading `path` ended
+fn log_loading(path: &Path, result: &IngestResult) {
+    if let Some(e) = &result.read_error {
+        warn!(
+            path = %path.display(),
+            line = result.stats.lines_read,
+            "stopped reading early: {}",
+            e
+        );
+    }
+
+    if let Some(reason) = &result.incomplete {
+        warn!(path = %path.display(), line = result.stats.lines_read, "{}", reason);
+    }
+
+    debug!(
+        path = %path.display(),
+        reopened = result.reopened,
+        lines = result.stats.lines_read,
+        entries = result.entries.len(),
+        malformed = result.malformed,
+        non_access = result.stats.non_access,
+        continuation = result.stats.continuation,
+        partial = result.stats.partial,
+        recovered = result.stats.recovered,
+        "finished reading input"
+    );
+}
@@ -0,0 +1,132 @@
+use crate::ingest::{Ingest, IngestResult};
+use memmap2::Mmap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Smallest chunk worth a thread of its own
+pub const MIN_CHUNK: usize = 16 * 1024 * 1024;
+
+/// An uncompressed log file mapped into memory (`--mmap`), so it can be parsed in
+/// chunks on several threads at once without being copied through a read buffer.
+///
+/// The file must not be truncated while it is mapped: reading the pages cut off
+/// would crash the process. Lines appended meanwhile are not seen.
+pub struct MappedFile {
+    /// `None` for an empty file, which cannot be mapped
+    map: Option<Mmap>,
+}
+
+impl MappedFile {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Ok(MappedFile { map: None });
+        }
+        // SAFETY: the mapping is only read, and the caller is told not to truncate the
+        // file meanwhile; appended data is outside the mapped length
+        let map = unsafe { Mmap::map(&file)? };
+        #[cfg(unix)]
+        let _ = map.advise(memmap2::Advice::Sequential);
+        Ok(MappedFile { map: Some(map) })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or_default()
+    }
+}
+
+/// Split `data` into at most `count` chunks of about the same size, each ending just
+/// after a newline (the last one at the end of `data`), so no line is cut in two
+pub fn split_lines(data: &[u8], count: usize) -> Vec<&[u8]> {
+    let target = data.len().div_ceil(count.max(1)).max(1);
+    let mut chunks = Vec::with_capacity(count);
+    let mut rest = data;
+    while !rest.is_empty() {
+        let end = match rest.get(target..).and_then(|tail| tail.iter().position(|&b| b == b'\n')) {
+            Some(newline) => target + newline + 1,
+            None => rest.len(),
+        };
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
+/// Parse `chunks` (of one input, in order) on a thread each, and combine the results
+/// as if the input had been read in one go: entries stay in input order, and lines
+/// keep their numbers within the whole input.
+///
+/// `configure` sets each chunk's ingestion up the same way (format, line options,
+/// limits); a limit is applied to each chunk on its own.
+pub fn ingest_chunks<'a, 'd, F>(chunks: &[&'d [u8]], configure: F) -> IngestResult
+where
+    F: Fn(Ingest<'a, &'d [u8]>) -> Ingest<'a, &'d [u8]> + Sync,
+{
+    // Each chunk's first line number follows from the lines in the chunks before it
+    let mut first_lines = Vec::with_capacity(chunks.len());
+    let mut lines = 0;
+    for chunk in chunks {
+        first_lines.push(lines + 1);
+        lines += chunk.iter().filter(|&&b| b == b'\n').count();
+    }
+
+    let configure = &configure;
+    let results: Vec<IngestResult> = std::thread::scope(|scope| {
+        let workers: Vec<_> = chunks
+            .iter()
+            .zip(first_lines)
+            .map(|(&chunk, first_line)| scope.spawn(move || configure(Ingest::new(chunk)).first_line(first_line).run()))
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+            .collect()
+    });
+    let mut results = results.into_iter();
+    let mut merged = results.next().unwrap_or_else(|| Ingest::new(&[][..]).run());
+    for result in results {
+        merged.append(result);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_at_line_ends() {
+        let data = b"first line\nsecond\nthird line here\nlast";
+        let chunks = split_lines(data, 3);
+        assert_eq!(chunks.concat(), data);
+        assert!(chunks.len() <= 3);
+        assert!(chunks[..chunks.len() - 1].iter().all(|c| c.ends_with(b"\n")));
+        assert_eq!(split_lines(data, 1), [&data[..]]);
+        assert!(split_lines(b"", 4).is_empty());
+    }
+
+    #[test]
+    fn chunked_ingestion_matches_reading_in_one_go() {
+        let input: String = (0..100)
+            .map(|i| match i % 7 {
+                3 => "garbage\n".to_string(),
+                _ => format!("2024-01-15T10:30:00Z [INFO] 1.2.3.4 GET /{} 200\n", i),
+            })
+            .collect();
+        let malformed_at = |chunks: &[&[u8]]| {
+            let lines = std::sync::Mutex::new(Vec::new());
+            let result = ingest_chunks(chunks, |ingest| ingest.on_malformed(|m| lines.lock().unwrap().push(m.line_number)));
+            let mut lines = lines.into_inner().unwrap();
+            lines.sort();
+            (result, lines)
+        };
+        let (whole, whole_lines) = malformed_at(&[input.as_bytes()]);
+        let (chunked, chunked_lines) = malformed_at(&split_lines(input.as_bytes(), 5));
+        assert_eq!(chunked.entries, whole.entries);
+        assert_eq!((chunked.stats.lines_read, chunked.malformed), (100, whole.malformed));
+        assert_eq!(chunked_lines, whole_lines);
+        assert_eq!(whole_lines[0], 4);
+    }
+}
@@ -1,32 +1,586 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
 use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
 use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
 use std::sync::OnceLock;
+use std::time::Duration;
 
 /// Represents a single parsed log entry
 #[derive(Debug, Clone, PartialEq)]
 pub struct LogEntry {
-    pub timestamp: String,
+    pub timestamp: DateTime<Utc>,
     pub level: LogLevel,
-    pub ip: String,
+    pub ip: IpAddr,
+    /// Authenticated user (`%u` in CLF), for formats that log one
+    pub user: Option<String>,
+    /// `X-Forwarded-For` chain as logged, client first, for formats that log one;
+    /// see [`crate::proxy`] for picking the real client from it
+    pub forwarded_for: Option<String>,
     pub method: HttpMethod,
     pub endpoint: String,
-    pub status_code: u16,
+    /// Absent for entries kept by [`parse_line_lenient`], and for lines that log no
+    /// response (Envoy's status `0`, CEF events without an HTTP outcome)
+    pub status_code: Option<u16>,
+    /// HTTP version from the request line, e.g. `HTTP/1.1`, for formats that log it
+    pub protocol: Option<String>,
+    /// Response size in bytes, when the line carries one
+    pub bytes: Option<u64>,
+    /// `Referer` request header, for formats that log it
+    pub referrer: Option<String>,
+    /// `User-Agent` request header, for formats that log it
+    pub user_agent: Option<String>,
+    /// Time taken to serve the request, for formats that log it
+    pub duration: Option<Duration>,
+    /// Status the backend returned, for proxies that log it apart from their own
+    pub backend_status: Option<u16>,
+    /// Envoy response flags as logged, e.g. `UF,URX`, or `-` when none were set
+    pub response_flags: Option<String>,
+    /// Upstream host the proxy forwarded to, for proxies that log it
+    pub upstream_host: Option<String>,
+    /// Router (routing rule) that matched the request, for proxies such as Traefik
+    pub router: Option<String>,
+    /// Backend service the router sent the request to, for proxies that log it
+    pub service: Option<String>,
+    /// Request or trace id, for formats that log one (Envoy's `x-request-id`, ALB's
+    /// `X-Amzn-Trace-Id`, `trace_id` keys), to cross-reference with APM tools
+    pub trace_id: Option<String>,
+    /// Continuation lines (e.g. a stack trace) attached in multi-line mode, joined by newlines
+    pub extra: Option<String>,
 }
 
-/// Log severity levels
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Supported access-log line formats
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// `TIMESTAMP [LEVEL] IP METHOD ENDPOINT STATUS_CODE [BYTES]`
+    #[default]
+    Native,
+    /// Apache Common Log Format: `%h %l %u %t "%r" %>s %b`
+    Clf,
+    /// nginx `combined`: CLF followed by `"$http_referer" "$http_user_agent"`
+    NginxCombined,
+    /// AWS Application (or Classic) Load Balancer access log
+    Alb,
+    /// Amazon S3 server access log
+    S3Access,
+    /// Envoy's default access log format
+    Envoy,
+    /// W3C extended log (CloudFront, IIS) laid out by [`W3cFields`]
+    W3c(W3cFields),
+    /// Caddy's structured JSON access log, with the request in a nested object
+    Caddy,
+    /// Heroku router log: a `heroku[router]:` prefix followed by logfmt pairs
+    Heroku,
+    /// Traefik access log, in its `common` layout or as JSON
+    Traefik,
+    /// CSV or TSV rows laid out by [`CsvColumns`]
+    Csv(CsvColumns),
+    /// ArcSight Common Event Format: a `CEF:0|...|` header followed by `key=value` extensions
+    Cef,
+    /// Graylog Extended Log Format: one GELF message per line, with the request in
+    /// `_http_*` additional fields
+    Gelf,
+    /// One JSON object per line, with fields located by [`JsonKeys`]
+    Json(JsonKeys),
+    /// `key=value` pairs per line, with fields located by [`JsonKeys`]
+    Logfmt(JsonKeys),
+    /// A user-supplied regex with named capture groups
+    Pattern(CustomPattern),
+    /// The inner format wrapped in an RFC 5424 or RFC 3164 syslog envelope
+    Syslog(Box<LogFormat>),
+    /// The inner format carried in the `log` field of Docker's JSON log records
+    Docker {
+        inner: Box<LogFormat>,
+        /// Timestamp entries with the record's `time` instead of the line's own
+        use_time: bool,
+    },
+    /// The inner format carried in the `MESSAGE` of systemd journal entries, one JSON
+    /// object per entry as printed by `journalctl -o json`
+    Journal(Box<LogFormat>),
+}
+
+/// Named groups a custom pattern must define
+pub const REQUIRED_GROUPS: [&str; 5] = ["timestamp", "ip", "method", "endpoint", "status"];
+
+/// Named groups a custom pattern may define
+pub const OPTIONAL_GROUPS: [&str; 8] =
+    ["level", "user", "protocol", "bytes", "referrer", "user_agent", "trace_id", "forwarded_for"];
+
+/// A user-supplied line regex, validated to define the groups a `LogEntry` needs.
+///
+/// Required groups: `timestamp`, `ip`, `method`, `endpoint`, `status`. Optional:
+/// `level` (derived from the status when absent), `user`, `protocol`, `bytes`,
+/// `referrer`, `user_agent`, `trace_id`, `forwarded_for`.
+#[derive(Debug, Clone)]
+pub struct CustomPattern {
+    regex: Regex,
+    /// Capture group index holding each field
+    groups: HashMap<&'static str, usize>,
+    /// strftime layout of the timestamp, instead of the layouts [`parse_timestamp`] accepts
+    timestamp_format: Option<String>,
+    /// Level spellings particular to this format, e.g. from a format file's `[levels]`
+    levels: LevelAliases,
+}
+
+impl CustomPattern {
+    /// A pattern whose fields are captured by groups named after them
+    pub fn new(pattern: &str) -> Result<Self, PatternError> {
+        let regex = Regex::new(pattern).map_err(PatternError::Regex)?;
+        let groups = REQUIRED_GROUPS
+            .iter()
+            .chain(&OPTIONAL_GROUPS)
+            .filter_map(|&field| {
+                let index = regex.capture_names().position(|name| name == Some(field))?;
+                Some((field, index))
+            })
+            .collect();
+        CustomPattern::with_groups(regex, groups, None)
+    }
+
+    /// A pattern whose fields are captured by the given group indexes, e.g. one
+    /// described by a format file
+    pub fn with_groups(
+        regex: Regex,
+        groups: HashMap<&'static str, usize>,
+        timestamp_format: Option<String>,
+    ) -> Result<Self, PatternError> {
+        if let Some(missing) = REQUIRED_GROUPS.iter().find(|g| !groups.contains_key(*g)) {
+            return Err(PatternError::MissingGroup(missing));
+        }
+        Ok(CustomPattern { regex, groups, timestamp_format, levels: LevelAliases::default() })
+    }
+
+    /// The same pattern, mapping the level spellings in `levels` onto levels
+    pub fn with_levels(mut self, levels: LevelAliases) -> Self {
+        self.levels = levels;
+        self
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.regex.as_str()
+    }
+}
+
+impl PartialEq for CustomPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+            && self.groups == other.groups
+            && self.timestamp_format == other.timestamp_format
+            && self.levels == other.levels
+    }
+}
+
+impl Eq for CustomPattern {}
+
+/// Why a custom pattern was rejected
+#[derive(Debug)]
+pub enum PatternError {
+    /// The pattern is not a valid regex
+    Regex(regex::Error),
+    /// A required named group is not defined
+    MissingGroup(&'static str),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Regex(e) => write!(f, "invalid regex: {}", e),
+            PatternError::MissingGroup(group) => write!(
+                f,
+                "pattern has no named group '{}' (required: {}; write it as (?P<{}>...))",
+                group,
+                REQUIRED_GROUPS.join(", "),
+                group
+            ),
+        }
+    }
+}
+
+/// `LogEntry` fields that can be read from a JSON log object
+const JSON_FIELDS: [&str; 13] = [
+    "timestamp",
+    "level",
+    "ip",
+    "user",
+    "method",
+    "path",
+    "status",
+    "protocol",
+    "bytes",
+    "referrer",
+    "user_agent",
+    "trace_id",
+    "forwarded_for",
+];
+
+/// Keys tried, in order, when no explicit key is configured for a field
+fn default_json_keys(field: &str) -> &'static [&'static str] {
+    match field {
+        "timestamp" => &["timestamp", "@timestamp", "time", "ts"],
+        "level" => &["level", "severity", "lvl"],
+        "ip" => &["ip", "client_ip", "remote_addr", "remote_ip", "clientip"],
+        "user" => &["user", "username", "remote_user", "user_id"],
+        "method" => &["method", "http_method", "request_method", "verb"],
+        "path" => &["path", "endpoint", "uri", "request_uri", "url"],
+        "status" => &["status", "status_code", "statusCode", "response_status"],
+        "protocol" => &["protocol", "proto", "http_version", "server_protocol"],
+        "bytes" => &["bytes", "body_bytes_sent", "bytes_sent", "response_size", "size"],
+        "referrer" => &["referrer", "referer", "http_referer"],
+        "user_agent" => &["user_agent", "userAgent", "http_user_agent", "agent"],
+        "trace_id" => &["trace_id", "traceId", "request_id", "requestId", "x_request_id"],
+        "forwarded_for" => &["forwarded_for", "x_forwarded_for", "http_x_forwarded_for", "xff"],
+        _ => &[],
+    }
+}
+
+/// Which JSON (or logfmt) keys hold each `LogEntry` field.
+///
+/// Fields without an explicit key fall back to common names (`ip`, `client_ip`,
+/// `remote_addr`, …). Keys may be dotted paths into nested objects, e.g.
+/// `http.request.method`; a key that literally contains dots matches first.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct JsonKeys {
+    overrides: Vec<(&'static str, String)>,
+}
+
+impl JsonKeys {
+    /// Read `field` (one of `timestamp`, `level`, `ip`, `user`, `method`, `path`,
+    /// `status`, `protocol`, `bytes`, `referrer`, `user_agent`, `trace_id`,
+    /// `forwarded_for`) from `key`
+    pub fn set(&mut self, field: &str, key: &str) -> Result<(), String> {
+        let Some(field) = JSON_FIELDS.iter().find(|f| **f == field) else {
+            return Err(format!(
+                "unknown field '{}' (expected one of: {})",
+                field,
+                JSON_FIELDS.join(", ")
+            ));
+        };
+        self.overrides.retain(|(f, _)| f != field);
+        self.overrides.push((field, key.to_string()));
+        Ok(())
+    }
+
+    /// Look up `field` in `object`, ignoring JSON nulls
+    fn lookup<'v>(&self, object: &'v serde_json::Value, field: &str) -> Option<&'v serde_json::Value> {
+        let found = match self.overrides.iter().find(|(f, _)| *f == field) {
+            Some((_, key)) => object
+                .get(key)
+                .or_else(|| key.split('.').try_fold(object, |v, part| v.get(part))),
+            None => default_json_keys(field).iter().find_map(|key| object.get(*key)),
+        };
+        found.filter(|v| !v.is_null())
+    }
+}
+
+/// Columns of a CloudFront standard log (version 1.0), used until a `#Fields:` directive is seen
+const CLOUDFRONT_FIELDS: [&str; 33] = [
+    "date",
+    "time",
+    "x-edge-location",
+    "sc-bytes",
+    "c-ip",
+    "cs-method",
+    "cs(Host)",
+    "cs-uri-stem",
+    "sc-status",
+    "cs(Referer)",
+    "cs(User-Agent)",
+    "cs-uri-query",
+    "cs(Cookie)",
+    "x-edge-result-type",
+    "x-edge-request-id",
+    "x-host-header",
+    "cs-protocol",
+    "cs-bytes",
+    "time-taken",
+    "x-forwarded-for",
+    "ssl-protocol",
+    "ssl-cipher",
+    "x-edge-response-result-type",
+    "cs-protocol-version",
+    "fle-status",
+    "fle-encrypted-fields",
+    "c-port",
+    "time-to-first-byte",
+    "x-edge-detailed-result-type",
+    "sc-content-type",
+    "sc-content-len",
+    "sc-range-start",
+    "sc-range-end",
+];
+
+/// Columns IIS logs by default, used until a `#Fields:` directive is seen
+const IIS_FIELDS: [&str; 15] = [
+    "date",
+    "time",
+    "s-ip",
+    "cs-method",
+    "cs-uri-stem",
+    "cs-uri-query",
+    "s-port",
+    "cs-username",
+    "c-ip",
+    "cs(User-Agent)",
+    "cs(Referer)",
+    "sc-status",
+    "sc-substatus",
+    "sc-win32-status",
+    "time-taken",
+];
+
+/// Producers of W3C extended logs, which differ in delimiters, escaping and units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum W3cDialect {
+    /// Tab-separated, `%XX`-escaped, `time-taken` in seconds
+    CloudFront,
+    /// Space-separated with `+` for spaces in values, `time-taken` in milliseconds
+    Iis,
+}
+
+/// Column layout of a W3C extended log, as declared by its `#Fields:` directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct W3cFields {
+    dialect: W3cDialect,
+    columns: HashMap<String, usize>,
+}
+
+impl W3cFields {
+    /// CloudFront standard logs, starting from the standard column order
+    pub fn cloudfront() -> Self {
+        W3cFields::new(W3cDialect::CloudFront, CLOUDFRONT_FIELDS)
+    }
+
+    /// IIS logs, starting from IIS's default column selection
+    pub fn iis() -> Self {
+        W3cFields::new(W3cDialect::Iis, IIS_FIELDS)
+    }
+
+    pub fn new<'f>(dialect: W3cDialect, names: impl IntoIterator<Item = &'f str>) -> Self {
+        W3cFields {
+            dialect,
+            columns: names.into_iter().enumerate().map(|(i, name)| (name.to_string(), i)).collect(),
+        }
+    }
+
+    /// Handle a `#`-prefixed directive line, adopting the layout of `#Fields:`.
+    ///
+    /// Returns false for lines that are not directives, which should be parsed as data.
+    pub fn read_directive(&mut self, line: &str) -> bool {
+        let Some(directive) = line.strip_prefix('#') else {
+            return false;
+        };
+        if let Some(names) = directive.strip_prefix("Fields:") {
+            *self = W3cFields::new(self.dialect, names.split_whitespace());
+        }
+        true
+    }
+
+    /// The value of `name` in a row, with `-` read as absent
+    fn get<'l>(&self, row: &[&'l str], name: &str) -> Option<&'l str> {
+        self.columns
+            .get(name)
+            .and_then(|&i| row.get(i).copied())
+            .filter(|v| *v != "-" && !v.is_empty())
+    }
+}
+
+/// The `LogEntry` field a CSV column name stands for, e.g. `path` for `endpoint`
+/// or `URL`.
+///
+/// Names are compared case-insensitively, with spaces and dashes read as
+/// underscores, against the field names and the keys JSON Lines falls back to.
+pub fn csv_field(name: &str) -> Option<&'static str> {
+    let name = name.trim().replace([' ', '-'], "_");
+    JSON_FIELDS.iter().copied().find(|field| {
+        field.eq_ignore_ascii_case(&name) || default_json_keys(field).iter().any(|key| key.eq_ignore_ascii_case(&name))
+    })
+}
+
+/// Column layout of a CSV or TSV log: a name per column, from `--columns` or
+/// the file's header row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvColumns {
+    delimiter: u8,
+    /// Column names; `-` or empty for columns to ignore
+    names: Vec<String>,
+    /// Whether header rows replace `names`, i.e. no layout was given
+    from_header: bool,
+    /// Where fields are found among the named columns (`--json-key`)
+    keys: JsonKeys,
+}
+
+impl CsvColumns {
+    /// A layout of `names`, or one read from the first header row when `names` is empty.
+    ///
+    /// Names that stand for a field (see [`csv_field`]) are stored as that field.
+    pub fn new(delimiter: u8, names: &[String], keys: JsonKeys) -> Self {
+        CsvColumns {
+            delimiter,
+            names: names.iter().map(|name| csv_column_name(name)).collect(),
+            from_header: names.is_empty(),
+            keys,
+        }
+    }
+
+    /// Skip a header row, adopting its column names when no layout was given.
+    ///
+    /// A header row names at least one field and, unlike every data row, holds no
+    /// IP address. Returns false for data rows.
+    pub fn read_header(&mut self, line: &str) -> bool {
+        let Ok(cells) = csv_cells(line, self.delimiter) else {
+            return false;
+        };
+        if cells.iter().any(|cell| parse_ip(cell.trim()).is_ok()) || !cells.iter().any(|cell| csv_field(cell).is_some()) {
+            return false;
+        }
+        if self.from_header {
+            self.names = cells.iter().map(|cell| csv_column_name(cell)).collect();
+        }
+        true
+    }
+
+    /// A row's non-empty cells keyed by column name, for [`entry_from_object`]
+    fn object(&self, line: &str) -> Result<serde_json::Value, ParseError> {
+        if self.names.is_empty() {
+            return Err(ParseError::InvalidFormat(
+                "no CSV column names: pass --columns or start the file with a header row".to_string(),
+            ));
+        }
+        let object = self
+            .names
+            .iter()
+            .zip(csv_cells(line, self.delimiter)?)
+            .filter(|(name, cell)| !name.is_empty() && *name != "-" && !cell.trim().is_empty())
+            .map(|(name, cell)| (name.clone(), serde_json::Value::String(cell.trim().to_string())))
+            .collect();
+        Ok(serde_json::Value::Object(object))
+    }
+}
+
+/// A column name as stored: the field it stands for, or else the name as written
+fn csv_column_name(name: &str) -> String {
+    csv_field(name).map_or_else(|| name.trim().to_string(), String::from)
+}
+
+/// Split one CSV row, honouring quoted fields and `""` escapes
+fn csv_cells(line: &str, delimiter: u8) -> Result<Vec<String>, ParseError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(line.as_bytes());
+    let mut record = csv::StringRecord::new();
+    match reader.read_record(&mut record) {
+        Ok(true) => Ok(record.iter().map(String::from).collect()),
+        Ok(false) => Err(ParseError::InvalidFormat("empty CSV row".to_string())),
+        Err(e) => Err(ParseError::InvalidFormat(format!("invalid CSV row: {}", e))),
+    }
+}
+
+/// Log severity levels, ordered least severe first
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum LogLevel {
+    Trace,
+    Debug,
     Info,
     Warn,
     Error,
+    Fatal,
+}
+
+impl LogLevel {
+    /// Every level, least severe first
+    pub const ALL: [LogLevel; 6] = [
+        LogLevel::Trace,
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warn,
+        LogLevel::Error,
+        LogLevel::Fatal,
+    ];
+
+    /// Whether the line reports a failure: `ERROR` or `FATAL`
+    pub fn is_error(&self) -> bool {
+        matches!(self, LogLevel::Error | LogLevel::Fatal)
+    }
+}
+
+impl Serialize for LogLevel {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for LogLevel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = ParseError;
+
+    /// A level by its name in any case, e.g. `warn` or `ERROR`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LogLevel::ALL
+            .into_iter()
+            .find(|level| level.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| ParseError::InvalidField {
+                field: "level",
+                value: s.to_string(),
+            })
+    }
+}
+
+/// Extra severity spellings and the levels they stand for (`--level-alias NOTICE=info`).
+///
+/// Aliases match in any case and take precedence over the spellings a format
+/// already understands, so they can also remap those (`DEBUG=trace`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LevelAliases {
+    aliases: HashMap<String, LogLevel>,
+}
+
+impl LevelAliases {
+    pub fn insert(&mut self, alias: &str, level: LogLevel) {
+        self.aliases.insert(alias.trim().to_ascii_lowercase(), level);
+    }
+
+    /// The level `s` is an alias for
+    pub fn get(&self, s: &str) -> Option<LogLevel> {
+        if self.aliases.is_empty() {
+            return None;
+        }
+        self.aliases.get(&s.trim().to_ascii_lowercase()).cloned()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+}
+
+impl FromIterator<(String, LogLevel)> for LevelAliases {
+    fn from_iter<I: IntoIterator<Item = (String, LogLevel)>>(iter: I) -> Self {
+        let mut aliases = LevelAliases::default();
+        for (alias, level) in iter {
+            aliases.insert(&alias, level);
+        }
+        aliases
+    }
 }
 
 impl fmt::Display for LogLevel {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            LogLevel::Trace => write!(f, "TRACE"),
+            LogLevel::Debug => write!(f, "DEBUG"),
             LogLevel::Info => write!(f, "INFO"),
             LogLevel::Warn => write!(f, "WARN"),
             LogLevel::Error => write!(f, "ERROR"),
+            LogLevel::Fatal => write!(f, "FATAL"),
         }
     }
 }
@@ -44,21 +598,28 @@ pub enum HttpMethod {
     Other(String),
 }
 
-impl fmt::Display for HttpMethod {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl HttpMethod {
+    /// The method as logged, e.g. `GET`
+    pub fn as_str(&self) -> &str {
         match self {
-            HttpMethod::Get => write!(f, "GET"),
-            HttpMethod::Post => write!(f, "POST"),
-            HttpMethod::Put => write!(f, "PUT"),
-            HttpMethod::Delete => write!(f, "DELETE"),
-            HttpMethod::Patch => write!(f, "PATCH"),
-            HttpMethod::Head => write!(f, "HEAD"),
-            HttpMethod::Options => write!(f, "OPTIONS"),
-            HttpMethod::Other(s) => write!(f, "{}", s),
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Delete => "DELETE",
+            HttpMethod::Patch => "PATCH",
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Options => "OPTIONS",
+            HttpMethod::Other(s) => s,
         }
     }
 }
 
+impl fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Errors that can occur during log parsing
 #[derive(Debug)]
 pub enum ParseError {
@@ -66,6 +627,10 @@ pub enum ParseError {
     InvalidFormat(String),
     /// A field was present but couldn't be converted
     InvalidField { field: &'static str, value: String },
+    /// A required field was absent from a structured (JSON) line
+    MissingField(&'static str),
+    /// The timestamp is in none of the accepted layouts, see [`parse_timestamp`]
+    InvalidTimestamp(String),
 }
 
 impl fmt::Display for ParseError {
@@ -75,68 +640,1870 @@ impl fmt::Display for ParseError {
             ParseError::InvalidField { field, value } => {
                 write!(f, "invalid value for field '{}': '{}'", field, value)
             }
+            ParseError::MissingField(field) => write!(f, "missing field '{}'", field),
+            ParseError::InvalidTimestamp(value) => write!(
+                f,
+                "invalid timestamp '{}': expected RFC 3339, %d/%b/%Y:%H:%M:%S %z or epoch seconds",
+                value
+            ),
         }
     }
 }
 
 /// Expected log format:
-///   TIMESTAMP [LEVEL] IP METHOD ENDPOINT STATUS_CODE
+///   TIMESTAMP [LEVEL] IP METHOD ENDPOINT STATUS_CODE [BYTES]
 ///
 /// Example:
 ///   2024-01-15T10:30:00Z [INFO] 192.168.1.1 GET /api/users 200
-///   2024-01-15T10:30:01Z [ERROR] 10.0.0.5 POST /login 500
+///   2024-01-15T10:30:01Z [ERROR] 10.0.0.5 POST /login 500 1532
+///
+/// BYTES is optional; `-` means the size is unknown, as in Apache's `%b`. An
+/// optional trailing DURATION carries a unit (`123ms`, `1.5s`, `850us`). An
+/// Apache-style TIMESTAMP keeps its zone offset after a single space.
 static LOG_REGEX: OnceLock<Regex> = OnceLock::new();
 
-fn get_regex() -> &'static Regex {
-    LOG_REGEX.get_or_init(|| {
-        Regex::new(
-            r#"^(?P<timestamp>\S+)\s+\[(?P<level>INFO|WARN|ERROR)\]\s+(?P<ip>\d{1,3}(?:\.\d{1,3}){3})\s+(?P<method>[A-Z]+)\s+(?P<endpoint>\S+)\s+(?P<status>\d{3})\s*$"#,
-        )
-        .expect("hard-coded regex should always compile")
-    })
+fn get_regex() -> &'static Regex {
+    LOG_REGEX.get_or_init(|| {
+        Regex::new(
+            r#"^(?P<timestamp>\S+(?: [+-]\d{4})?)\s+\[(?P<level>[^\]\s]+)\]\s+(?P<ip>\S+)\s+(?P<method>[A-Z]+)\s+(?P<endpoint>\S+)\s+(?P<status>\d{3})(?:\s+(?:(?P<bytes>\d+)|-))?(?:\s+(?P<duration>\d+(?:\.\d+)?(?:ms|us|µs|s)))?\s*$"#,
+        )
+        .expect("hard-coded regex should always compile")
+    })
+}
+
+/// The native format with the level and status made optional, for lenient parsing
+static LENIENT_LOG_REGEX: OnceLock<Regex> = OnceLock::new();
+
+fn get_lenient_regex() -> &'static Regex {
+    LENIENT_LOG_REGEX.get_or_init(|| {
+        Regex::new(
+            r#"^(?P<timestamp>\S+(?: [+-]\d{4})?)\s+(?:\[(?P<level>[^\]\s]+)\]\s+)?(?P<ip>\S+)\s+(?P<method>[A-Z]+)\s+(?P<endpoint>\S+)(?:\s+(?P<status>\d{3}))?(?:\s+(?:(?P<bytes>\d+)|-))?(?:\s+(?P<duration>\d+(?:\.\d+)?(?:ms|us|µs|s)))?\s*$"#,
+        )
+        .expect("hard-coded regex should always compile")
+    })
+}
+
+/// Apache Common Log Format, e.g.
+///   127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326
+static CLF_REGEX: OnceLock<Regex> = OnceLock::new();
+static ALB_REGEX: OnceLock<Regex> = OnceLock::new();
+static S3_ACCESS_REGEX: OnceLock<Regex> = OnceLock::new();
+
+fn get_clf_regex() -> &'static Regex {
+    CLF_REGEX.get_or_init(|| {
+        Regex::new(
+            r#"^(?P<host>\S+)\s+\S+\s+(?P<user>\S+)\s+\[(?P<time>[^\]]+)\]\s+"(?P<request>[^"]*)"\s+(?P<status>\d{3})\s+(?:(?P<bytes>\d+)|-)\s*$"#,
+        )
+        .expect("hard-coded regex should always compile")
+    })
+}
+
+/// nginx `combined` format: CLF plus quoted referrer and user agent, e.g.
+///   10.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET / HTTP/1.1" 200 612 "https://example.com/" "curl/8.0"
+static COMBINED_REGEX: OnceLock<Regex> = OnceLock::new();
+
+fn get_combined_regex() -> &'static Regex {
+    COMBINED_REGEX.get_or_init(|| {
+        Regex::new(
+            r#"^(?P<host>\S+)\s+\S+\s+(?P<user>\S+)\s+\[(?P<time>[^\]]+)\]\s+"(?P<request>[^"]*)"\s+(?P<status>\d{3})\s+(?:(?P<bytes>\d+)|-)\s+"(?P<referrer>[^"]*)"\s+"(?P<agent>[^"]*)"(?:\s+"(?P<xff>[^"]*)")?\s*$"#,
+        )
+        .expect("hard-coded regex should always compile")
+    })
+}
+
+/// Parse a single line in the given format into a structured `LogEntry`
+pub fn parse_line(line: &str, format: &LogFormat) -> Result<LogEntry, ParseError> {
+    parse_line_with(line, format, false, &LevelAliases::default())
+}
+
+/// Like [`parse_line`], but keep lines that lack a level or status code.
+///
+/// Applies to the native format, JSON Lines, logfmt and custom patterns. A missing
+/// level is derived from the status code, or `INFO` when that is missing too; a
+/// missing status code leaves `status_code` empty. Other formats always carry a
+/// status, so they parse as with [`parse_line`].
+pub fn parse_line_lenient(line: &str, format: &LogFormat) -> Result<LogEntry, ParseError> {
+    parse_line_with(line, format, true, &LevelAliases::default())
+}
+
+/// Like [`parse_line`], or [`parse_line_lenient`] with `lenient`, also accepting
+/// the level spellings in `levels`.
+///
+/// Applies to formats that log a level by name: the native format, JSON Lines,
+/// logfmt, CSV and custom patterns.
+pub fn parse_line_with_levels(
+    line: &str,
+    format: &LogFormat,
+    lenient: bool,
+    levels: &LevelAliases,
+) -> Result<LogEntry, ParseError> {
+    parse_line_with(line, format, lenient, levels)
+}
+
+/// Try to rescue a native-format line that [`parse_line`] rejected over a near miss.
+///
+/// The line is split into tokens and put back together in the expected layout:
+/// a date and time separated by a space are joined, the level may be unbracketed,
+/// bracketed apart from its name or in any case (`info`, `[ Warn ]`, `[warning]`),
+/// and the method may be lowercase. Runs of whitespace are collapsed on the way.
+/// The rebuilt line is parsed strictly, or as by [`parse_line_lenient`] when
+/// `lenient` is set; aliases in `levels` are accepted in either case. Returns
+/// `None` for other formats and for lines that still don't parse.
+pub fn recover_line(line: &str, format: &LogFormat, lenient: bool, levels: &LevelAliases) -> Option<LogEntry> {
+    if *format != LogFormat::Native {
+        return None;
+    }
+    let mut tokens = line.split_whitespace().peekable();
+    let mut timestamp = tokens.next()?.to_string();
+    if let Some(time) = tokens.peek() {
+        let joined = format!("{}T{}", timestamp, time);
+        if parse_timestamp(&timestamp).is_err() && parse_timestamp(&joined).is_ok() {
+            timestamp = joined;
+            tokens.next();
+        }
+    }
+    if let Some(offset) = tokens.next_if(|t| t.len() == 5 && t.starts_with(['+', '-']) && t[1..].bytes().all(|b| b.is_ascii_digit())) {
+        timestamp = format!("{} {}", timestamp, offset);
+    }
+
+    // The level may be spread over `[`, `INFO` and `]` tokens
+    let mut rest: Vec<&str> = tokens.collect();
+    let bracketed = rest.first()?.starts_with('[');
+    let level_tokens = if bracketed {
+        rest.iter().take(3).position(|t| t.ends_with(']'))? + 1
+    } else {
+        1
+    };
+    let level_text = rest[..level_tokens].concat();
+    let level_text = level_text.trim_start_matches('[').trim_end_matches(']');
+    let level = levels.get(level_text).or_else(|| parse_level_lenient(level_text));
+    if level.is_some() {
+        rest.drain(..level_tokens);
+    } else if bracketed {
+        return None;
+    }
+
+    let [ip, method, tail @ ..] = rest.as_slice() else {
+        return None;
+    };
+    if !method.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return None;
+    }
+    let mut rebuilt = timestamp;
+    if let Some(level) = level {
+        rebuilt.push_str(&format!(" [{}]", level));
+    }
+    rebuilt.push_str(&format!(" {} {}", ip, method.to_ascii_uppercase()));
+    for token in tail {
+        rebuilt.push(' ');
+        rebuilt.push_str(token);
+    }
+    native_entry(&rebuilt, if lenient { get_lenient_regex() } else { get_regex() }, levels).ok()
+}
+
+fn parse_line_with(line: &str, format: &LogFormat, lenient: bool, levels: &LevelAliases) -> Result<LogEntry, ParseError> {
+    match format {
+        LogFormat::Native => native_entry(line, if lenient { get_lenient_regex() } else { get_regex() }, levels),
+        LogFormat::Clf => parse_clf_line(line),
+        LogFormat::NginxCombined => parse_combined_line(line),
+        LogFormat::Alb => parse_alb_line(line),
+        LogFormat::S3Access => parse_s3_access_line(line),
+        LogFormat::Envoy => parse_envoy_line(line),
+        LogFormat::W3c(fields) => parse_w3c_line(line, fields),
+        LogFormat::Caddy => parse_caddy_line(line),
+        LogFormat::Heroku => parse_heroku_line(line),
+        LogFormat::Traefik => parse_traefik_line(line),
+        LogFormat::Csv(columns) => entry_from_object(&columns.object(line)?, &columns.keys, None, lenient, levels),
+        LogFormat::Cef => cef_entry(line, None),
+        LogFormat::Gelf => parse_gelf_line(line),
+        LogFormat::Json(keys) => entry_from_object(&json_object(line)?, keys, None, lenient, levels),
+        LogFormat::Logfmt(keys) => entry_from_object(&logfmt_object(line)?, keys, None, lenient, levels),
+        LogFormat::Pattern(pattern) => pattern_entry(line, pattern, lenient, levels),
+        LogFormat::Syslog(inner) => {
+            let message = parse_syslog(line)?;
+            // RFC 3164 headers carry no year, so only RFC 5424 timestamps qualify
+            let received = message
+                .timestamp
+                .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+                .map(|at| at.with_timezone(&Utc));
+            parse_wrapped(message.message, inner, received, lenient, levels)
+        }
+        LogFormat::Docker { inner, use_time } => {
+            let record = parse_docker_record(line)?;
+            let mut entry = parse_wrapped(record.log.trim_end_matches(['\n', '\r']), inner, record.time, lenient, levels)?;
+            if *use_time {
+                entry.timestamp = record.time.ok_or(ParseError::MissingField("time"))?;
+            }
+            Ok(entry)
+        }
+        LogFormat::Journal(inner) => {
+            let record = parse_journal_record(line)?;
+            let mut entry = parse_wrapped(record.message.trim_end_matches(['\n', '\r']), inner, record.time, lenient, levels)?;
+            if let Some(level) = record.priority.and_then(syslog_severity_level) {
+                entry.level = entry.level.max(level);
+            }
+            Ok(entry)
+        }
+    }
+}
+
+/// Parse the message of an envelope (syslog, Docker) with the inner format.
+///
+/// Structured bodies without their own timestamp take `received`, the envelope's.
+fn parse_wrapped(
+    message: &str,
+    inner: &LogFormat,
+    received: Option<DateTime<Utc>>,
+    lenient: bool,
+    levels: &LevelAliases,
+) -> Result<LogEntry, ParseError> {
+    match (inner, received) {
+        (LogFormat::Json(keys), Some(at)) => entry_from_object(&json_object(message)?, keys, Some(at), lenient, levels),
+        (LogFormat::Logfmt(keys), Some(at)) => entry_from_object(&logfmt_object(message)?, keys, Some(at), lenient, levels),
+        // Log drains deliver router lines as bare pairs, without the timestamp prefix
+        (LogFormat::Heroku, Some(at)) if !message.contains("heroku[router]:") => heroku_entry(message, at),
+        (LogFormat::Cef, received) => cef_entry(message, received),
+        _ => parse_line_with(message, inner, lenient, levels),
+    }
+}
+
+/// One record of Docker's `json-file` log driver, as also kept by Kubernetes nodes
+/// running Docker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DockerRecord {
+    /// The line the container wrote, usually ending in a newline
+    pub log: String,
+    /// `stdout` or `stderr`
+    pub stream: Option<String>,
+    /// When the runtime received the line
+    pub time: Option<DateTime<Utc>>,
+}
+
+/// Split a Docker JSON log record, e.g.
+///   {"log":"10.0.0.1 - - [15/Jan/2024:10:30:00 +0000] \"GET / HTTP/1.1\" 200 612\n","stream":"stdout","time":"2024-01-15T10:30:00.123456789Z"}
+pub fn parse_docker_record(line: &str) -> Result<DockerRecord, ParseError> {
+    let object = json_object(line)?;
+    let log = object.get("log").ok_or(ParseError::MissingField("log"))?;
+    let log = log.as_str().ok_or_else(|| ParseError::InvalidField {
+        field: "log",
+        value: log.to_string(),
+    })?;
+    let time = match object.get("time") {
+        None => None,
+        Some(serde_json::Value::String(s)) => Some(
+            DateTime::parse_from_rfc3339(s)
+                .map_err(|_| ParseError::InvalidTimestamp(s.clone()))?
+                .with_timezone(&Utc),
+        ),
+        Some(other) => return Err(ParseError::InvalidTimestamp(other.to_string())),
+    };
+    Ok(DockerRecord {
+        log: log.to_string(),
+        stream: object.get("stream").and_then(|s| s.as_str()).map(String::from),
+        time,
+    })
+}
+
+/// The fields of a systemd journal entry that entries are built from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalRecord {
+    /// `MESSAGE`: the line the service logged
+    pub message: String,
+    /// `PRIORITY`: the syslog severity, 0 (emergency) through 7 (debug)
+    pub priority: Option<u8>,
+    /// `_SOURCE_REALTIME_TIMESTAMP` (when the service logged the line), else
+    /// `__REALTIME_TIMESTAMP` (when journald received it)
+    pub time: Option<DateTime<Utc>>,
+    /// `_SYSTEMD_UNIT`, e.g. `nginx.service`
+    pub unit: Option<String>,
+}
+
+/// Read a journal entry printed by `journalctl -o json`, e.g.
+///   {"__REALTIME_TIMESTAMP":"1705314600123456","PRIORITY":"6","_SYSTEMD_UNIT":"nginx.service","MESSAGE":"10.0.0.1 - - [15/Jan/2024:10:30:00 +0000] \"GET / HTTP/1.1\" 200 612"}
+///
+/// Timestamps are microseconds since the epoch. A `MESSAGE` that is not valid UTF-8
+/// is printed as an array of bytes and decoded lossily.
+pub fn parse_journal_record(line: &str) -> Result<JournalRecord, ParseError> {
+    let object = json_object(line)?;
+    let text = |key: &str| object.get(key).and_then(|v| v.as_str());
+    let message = match object.get("MESSAGE").ok_or(ParseError::MissingField("MESSAGE"))? {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(bytes) => {
+            let bytes: Option<Vec<u8>> = bytes.iter().map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok())).collect();
+            String::from_utf8_lossy(&bytes.ok_or_else(|| ParseError::InvalidField {
+                field: "MESSAGE",
+                value: "non-byte array".to_string(),
+            })?)
+            .into_owned()
+        }
+        other => {
+            return Err(ParseError::InvalidField {
+                field: "MESSAGE",
+                value: other.to_string(),
+            })
+        }
+    };
+    let priority = match text("PRIORITY") {
+        Some(p) => Some(p.parse::<u8>().ok().filter(|p| *p <= 7).ok_or_else(|| ParseError::InvalidField {
+            field: "PRIORITY",
+            value: p.to_string(),
+        })?),
+        None => None,
+    };
+    let time = match text("_SOURCE_REALTIME_TIMESTAMP").or_else(|| text("__REALTIME_TIMESTAMP")) {
+        Some(micros) => Some(
+            micros
+                .parse::<i64>()
+                .ok()
+                .and_then(DateTime::from_timestamp_micros)
+                .ok_or_else(|| ParseError::InvalidTimestamp(micros.to_string()))?,
+        ),
+        None => None,
+    };
+    Ok(JournalRecord {
+        message,
+        priority,
+        time,
+        unit: text("_SYSTEMD_UNIT").map(String::from),
+    })
+}
+
+/// The envelope fields of a syslog line and the message it carries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyslogMessage<'a> {
+    /// RFC 3339 for RFC 5424; `Mmm dd hh:mm:ss` (no year or zone) for RFC 3164
+    pub timestamp: Option<&'a str>,
+    pub hostname: Option<&'a str>,
+    pub app_name: Option<&'a str>,
+    pub message: &'a str,
+}
+
+static SYSLOG_5424_REGEX: OnceLock<Regex> = OnceLock::new();
+static SYSLOG_3164_REGEX: OnceLock<Regex> = OnceLock::new();
+
+fn get_syslog_5424_regex() -> &'static Regex {
+    SYSLOG_5424_REGEX.get_or_init(|| {
+        Regex::new(r"^<\d{1,3}>\d{1,2} (?P<time>\S+) (?P<host>\S+) (?P<app>\S+) \S+ \S+ (?P<rest>.*)$")
+            .expect("hard-coded regex should always compile")
+    })
+}
+
+fn get_syslog_3164_regex() -> &'static Regex {
+    SYSLOG_3164_REGEX.get_or_init(|| {
+        // The PRI is optional: files written by a local syslog daemon drop it
+        Regex::new(
+            r"^(?:<\d{1,3}>)?(?P<time>[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}) (?P<host>\S+) (?P<app>[^:\s\[]+)(?:\[\d+\])?: ?(?P<rest>.*)$",
+        )
+        .expect("hard-coded regex should always compile")
+    })
+}
+
+/// Split a syslog line into its envelope and message.
+///
+/// Accepts RFC 5424 (`<165>1 2024-01-15T10:30:00Z host app - - [sd] MSG`) and
+/// RFC 3164 (`<34>Jan 15 10:30:00 host app[123]: MSG`, PRI optional).
+pub fn parse_syslog(line: &str) -> Result<SyslogMessage<'_>, ParseError> {
+    let line = line.trim_end();
+
+    if let Some(caps) = get_syslog_5424_regex().captures(line) {
+        let rest = caps.name("rest").map_or("", |m| m.as_str());
+        let message = skip_structured_data(rest)
+            .ok_or_else(|| ParseError::InvalidFormat("malformed syslog structured data".to_string()))?;
+        return Ok(SyslogMessage {
+            timestamp: nil_value(caps.name("time")),
+            hostname: nil_value(caps.name("host")),
+            app_name: nil_value(caps.name("app")),
+            message: message.trim_start_matches('\u{feff}'),
+        });
+    }
+    if let Some(caps) = get_syslog_3164_regex().captures(line) {
+        return Ok(SyslogMessage {
+            timestamp: caps.name("time").map(|m| m.as_str()),
+            hostname: caps.name("host").map(|m| m.as_str()),
+            app_name: caps.name("app").map(|m| m.as_str()),
+            message: caps.name("rest").map_or("", |m| m.as_str()),
+        });
+    }
+    Err(ParseError::InvalidFormat(format!(
+        "no RFC 5424 or RFC 3164 syslog header: {:?}",
+        &line[..line.len().min(100)]
+    )))
+}
+
+/// An RFC 5424 header field, where `-` means no value
+fn nil_value(m: Option<regex::Match<'_>>) -> Option<&str> {
+    m.map(|m| m.as_str()).filter(|v| *v != "-")
+}
+
+/// Skip RFC 5424 STRUCTURED-DATA (`-` or `[id k="v"]...`), returning the message after it
+fn skip_structured_data(rest: &str) -> Option<&str> {
+    if let Some(after) = rest.strip_prefix('-') {
+        return Some(after.strip_prefix(' ').unwrap_or(after));
+    }
+    let mut in_element = false;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in rest.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' if in_element => in_quotes = !in_quotes,
+            '[' if !in_element => in_element = true,
+            ']' if in_element && !in_quotes => in_element = false,
+            ' ' if !in_element => return Some(&rest[i + 1..]),
+            _ if !in_element => return None,
+            _ => {}
+        }
+    }
+    (!in_element && rest.starts_with('[')).then_some("")
+}
+
+/// Parse a single log line into a structured `LogEntry`.
+///
+/// Returns `Err(ParseError)` if the line is malformed or contains invalid field values.
+pub fn parse_log_line(line: &str) -> Result<LogEntry, ParseError> {
+    native_entry(line, get_regex(), &LevelAliases::default())
+}
+
+/// A native `GET` entry logged at `INFO`, for the unit tests of other modules, which
+/// set any other field they need with struct update syntax
+#[cfg(test)]
+pub(crate) fn test_entry(timestamp: &str, ip: &str, endpoint: &str, status: u16) -> LogEntry {
+    parse_log_line(&format!("{} [INFO] {} GET {} {}", timestamp, ip, endpoint, status)).unwrap()
+}
+
+/// Build an entry from a native line matched by `re`, which may leave out the
+/// level and status (see [`parse_line_lenient`]). The level is one of the six
+/// names in capitals, or an alias from `levels`.
+fn native_entry(line: &str, re: &Regex, levels: &LevelAliases) -> Result<LogEntry, ParseError> {
+    let caps = re.captures(line.trim()).ok_or_else(|| {
+        ParseError::InvalidFormat(format!(
+            "line does not match expected pattern: {:?}",
+            &line[..line.len().min(100)]
+        ))
+    })?;
+
+    let timestamp = parse_timestamp(&caps["timestamp"])?;
+    let ip = parse_ip(&caps["ip"])?;
+    let endpoint = caps["endpoint"].to_string();
+
+    let method = parse_method(&caps["method"]);
+
+    let status_code = match caps.name("status") {
+        Some(m) => Some(m.as_str().parse::<u16>().map_err(|_| ParseError::InvalidField {
+            field: "status_code",
+            value: m.as_str().to_string(),
+        })?),
+        None => None,
+    };
+    let level = match caps.name("level") {
+        Some(m) => match levels.get(m.as_str()) {
+            Some(level) => level,
+            None => parse_level(m.as_str())?,
+        },
+        None => status_code.map_or(LogLevel::Info, level_for_status),
+    };
+
+    let bytes = match caps.name("bytes") {
+        Some(m) => Some(m.as_str().parse::<u64>().map_err(|_| ParseError::InvalidField {
+            field: "bytes",
+            value: m.as_str().to_string(),
+        })?),
+        None => None,
+    };
+    let duration = caps.name("duration").map(|m| parse_response_time(m.as_str())).transpose()?;
+
+    Ok(LogEntry {
+        timestamp,
+        level,
+        ip,
+        user: None,
+        forwarded_for: None,
+        method,
+        endpoint,
+        status_code,
+        protocol: None,
+        bytes,
+        referrer: None,
+        user_agent: None,
+        duration,
+        backend_status: None,
+        response_flags: None,
+        upstream_host: None,
+        router: None,
+        service: None,
+        trace_id: None,
+        extra: None,
+    })
+}
+
+/// Parse an Apache Common Log Format line.
+///
+/// CLF has no severity, so the level is derived from the status code: 5xx is
+/// `ERROR`, 4xx is `WARN`, anything else `INFO`.
+pub fn parse_clf_line(line: &str) -> Result<LogEntry, ParseError> {
+    let caps = get_clf_regex().captures(line.trim()).ok_or_else(|| {
+        ParseError::InvalidFormat(format!(
+            "line does not match Common Log Format: {:?}",
+            &line[..line.len().min(100)]
+        ))
+    })?;
+    clf_entry(&caps)
+}
+
+/// Parse an nginx `combined` line: CLF plus referrer and user agent (`-` means absent),
+/// optionally followed by `"$http_x_forwarded_for"` as in nginx's `main` format
+pub fn parse_combined_line(line: &str) -> Result<LogEntry, ParseError> {
+    let caps = get_combined_regex().captures(line.trim()).ok_or_else(|| {
+        ParseError::InvalidFormat(format!(
+            "line does not match nginx combined format: {:?}",
+            &line[..line.len().min(100)]
+        ))
+    })?;
+    let mut entry = clf_entry(&caps)?;
+    let optional = |name: &str| Some(caps[name].to_string()).filter(|v| v != "-" && !v.is_empty());
+    entry.referrer = optional("referrer");
+    entry.user_agent = optional("agent");
+    entry.forwarded_for = caps
+        .name("xff")
+        .map(|xff| xff.as_str())
+        .filter(|xff| *xff != "-" && !xff.is_empty())
+        .map(String::from);
+    Ok(entry)
+}
+
+/// Build an entry from the fields shared by CLF and the formats derived from it
+fn clf_entry(caps: &regex::Captures<'_>) -> Result<LogEntry, ParseError> {
+    let timestamp = parse_timestamp(&caps["time"])?;
+
+    let request = &caps["request"];
+    let mut parts = request.split_whitespace();
+    let (Some(method), Some(endpoint)) = (parts.next(), parts.next()) else {
+        return Err(ParseError::InvalidField {
+            field: "request",
+            value: request.to_string(),
+        });
+    };
+    let protocol = parts.next().and_then(parse_protocol);
+
+    let status_str = &caps["status"];
+    let status_code = status_str.parse::<u16>().map_err(|_| ParseError::InvalidField {
+        field: "status_code",
+        value: status_str.to_string(),
+    })?;
+    let level = level_for_status(status_code);
+
+    let bytes = match caps.name("bytes") {
+        Some(m) => Some(m.as_str().parse::<u64>().map_err(|_| ParseError::InvalidField {
+            field: "bytes",
+            value: m.as_str().to_string(),
+        })?),
+        None => None,
+    };
+
+    Ok(LogEntry {
+        timestamp,
+        level,
+        ip: parse_ip(&caps["host"])?,
+        user: caps.name("user").map(|u| u.as_str()).filter(|u| *u != "-").map(String::from),
+        forwarded_for: None,
+        method: parse_method(method),
+        endpoint: endpoint.to_string(),
+        status_code: Some(status_code),
+        protocol,
+        bytes,
+        referrer: None,
+        user_agent: None,
+        duration: None,
+        backend_status: None,
+        response_flags: None,
+        upstream_host: None,
+        router: None,
+        service: None,
+        trace_id: None,
+        extra: None,
+    })
+}
+
+fn get_alb_regex() -> &'static Regex {
+    ALB_REGEX.get_or_init(|| {
+        // ALB lines start with the request type; Classic ELB lines start with the time.
+        // Of the fields after the user agent only the trace id (after the TLS cipher,
+        // protocol and target group) is needed; Classic ELB lines end before it.
+        Regex::new(
+            r#"^(?:(?:https?|h2|grpcs?|wss?) )?(?P<time>\d{4}-\d{2}-\d{2}T\S+) \S+ (?P<client>\S+) \S+ (?P<request_time>-?[\d.]+) (?P<target_time>-?[\d.]+) (?P<response_time>-?[\d.]+) (?P<status>\d{3}|-) (?P<backend_status>\d{3}|-) \d+ (?P<bytes>\d+) "(?P<request>[^"]*)" "(?P<agent>(?:[^"\\]|\\.)*)"(?: \S+ \S+ \S+ "(?P<trace>[^"]*)")?"#,
+        )
+        .expect("hard-coded regex should always compile")
+    })
+}
+
+/// Parse an AWS Application Load Balancer (or Classic ELB) access log line.
+///
+/// Latency is the sum of the request, target and response processing times; it is
+/// left unset when the load balancer logged `-1` because no target answered. The
+/// request URL is reduced to its path and query. The trace id is the `Root` of
+/// `X-Amzn-Trace-Id`, as X-Ray shows it.
+pub fn parse_alb_line(line: &str) -> Result<LogEntry, ParseError> {
+    let caps = get_alb_regex().captures(line.trim()).ok_or_else(|| {
+        ParseError::InvalidFormat(format!(
+            "not an ALB/ELB access log line: {:?}",
+            &line[..line.len().min(100)]
+        ))
+    })?;
+    let invalid = |field: &'static str, value: &str| ParseError::InvalidField {
+        field,
+        value: value.to_string(),
+    };
+
+    let timestamp = parse_timestamp(&caps["time"])?;
+
+    // `ip:port`, with IPv6 clients logged without brackets
+    let client = &caps["client"];
+    let ip = parse_ip(client.rsplit_once(':').map_or(client, |(ip, _)| ip))?;
+
+    let status_code = caps["status"].parse::<u16>().map_err(|_| invalid("status_code", &caps["status"]))?;
+    let backend_status = caps["backend_status"].parse::<u16>().ok();
+
+    let times: Vec<f64> = ["request_time", "target_time", "response_time"]
+        .iter()
+        .map(|name| caps[*name].parse::<f64>().map_err(|_| invalid("processing_time", &caps[*name])))
+        .collect::<Result<_, _>>()?;
+    let duration = times
+        .iter()
+        .all(|t| *t >= 0.0)
+        .then(|| duration_from_secs(times.iter().sum()));
+
+    let request = &caps["request"];
+    let mut parts = request.split_whitespace();
+    let (Some(method), Some(url)) = (parts.next(), parts.next()) else {
+        return Err(invalid("request", request));
+    };
+    let protocol = parts.next().and_then(parse_protocol);
+    let endpoint = match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |i| &rest[i..]),
+        None => url,
+    };
+    let agent = &caps["agent"];
+
+    Ok(LogEntry {
+        timestamp,
+        level: level_for_status(status_code),
+        ip,
+        user: None,
+        forwarded_for: None,
+        method: parse_method(method),
+        endpoint: endpoint.to_string(),
+        status_code: Some(status_code),
+        protocol,
+        bytes: caps["bytes"].parse().ok(),
+        referrer: None,
+        user_agent: (agent != "-").then(|| agent.to_string()),
+        duration,
+        backend_status,
+        response_flags: None,
+        upstream_host: None,
+        router: None,
+        service: None,
+        trace_id: caps.name("trace").map(|t| alb_trace_root(t.as_str())).filter(|t| !t.is_empty() && *t != "-").map(String::from),
+        extra: None,
+    })
+}
+
+/// The `Root` of an `X-Amzn-Trace-Id` value such as `Root=1-65a5…;Self=1-…`, or
+/// the value itself when it has none
+fn alb_trace_root(trace: &str) -> &str {
+    trace
+        .split(';')
+        .find_map(|part| part.strip_prefix("Root="))
+        .unwrap_or(trace)
+}
+
+fn get_s3_access_regex() -> &'static Regex {
+    S3_ACCESS_REGEX.get_or_init(|| {
+        // bucket_owner bucket [time] remote_ip requester request_id operation key
+        // "request_uri" status error_code bytes_sent object_size total_time
+        // turnaround_time "referer" "user_agent" …; later fields are not needed
+        Regex::new(
+            r#"^(?P<owner>\S+) (?P<bucket>\S+) \[(?P<time>[^\]]+)\] (?P<ip>\S+) (?P<requester>\S+) \S+ (?P<operation>\S+) (?P<key>\S+) "(?P<request>[^"]*)" (?P<status>\d{3}|-) \S+ (?P<bytes>\d+|-) \S+ (?:\d+|-) (?P<turnaround>\d+|-) "(?P<referrer>[^"]*)" "(?P<agent>(?:[^"\\]|\\.)*)""#,
+        )
+        .expect("hard-coded regex should always compile")
+    })
+}
+
+/// Parse an Amazon S3 server access log line.
+///
+/// The endpoint is the object key (as `/key`), or the request path for bucket-level
+/// operations. Latency is the turnaround time, S3's own processing time excluding
+/// the transfer. The user is the requester, a canonical ID or an IAM ARN; anonymous
+/// requests have none.
+pub fn parse_s3_access_line(line: &str) -> Result<LogEntry, ParseError> {
+    let caps = get_s3_access_regex().captures(line.trim()).ok_or_else(|| {
+        ParseError::InvalidFormat(format!(
+            "not an S3 server access log line: {:?}",
+            &line[..line.len().min(100)]
+        ))
+    })?;
+    let invalid = |field: &'static str, value: &str| ParseError::InvalidField {
+        field,
+        value: value.to_string(),
+    };
+
+    let timestamp = parse_timestamp(&caps["time"])?;
+    let status_code = caps["status"].parse::<u16>().map_err(|_| invalid("status_code", &caps["status"]))?;
+
+    // REST.GET.OBJECT → GET, for entries logged without a request URI
+    let request = &caps["request"];
+    let mut parts = request.split_whitespace();
+    let (method, uri, protocol) = (parts.next(), parts.next(), parts.next());
+    let method = method
+        .filter(|m| *m != "-")
+        .or_else(|| caps["operation"].split('.').nth(1))
+        .ok_or_else(|| invalid("request", request))?;
+    let endpoint = match &caps["key"] {
+        "-" => uri.unwrap_or("/").to_string(),
+        key => format!("/{}", key),
+    };
+    let optional = |name: &str| Some(caps[name].to_string()).filter(|v| v != "-" && !v.is_empty());
+
+    Ok(LogEntry {
+        timestamp,
+        level: level_for_status(status_code),
+        ip: parse_ip(&caps["ip"])?,
+        user: optional("requester"),
+        forwarded_for: None,
+        method: parse_method(method),
+        endpoint,
+        status_code: Some(status_code),
+        protocol: protocol.and_then(parse_protocol),
+        bytes: caps["bytes"].parse().ok(),
+        referrer: optional("referrer"),
+        user_agent: optional("agent"),
+        duration: caps["turnaround"].parse().ok().map(Duration::from_millis),
+        backend_status: None,
+        response_flags: None,
+        upstream_host: None,
+        router: None,
+        service: None,
+        trace_id: None,
+        extra: None,
+    })
+}
+
+/// Envoy's default access log format, e.g.
+///   [2024-01-15T10:30:00.310Z] "POST /api/v1/locations HTTP/2" 204 - 154 0 226 100 "10.0.35.28" "nsq2http" "cc21d9b0-cf5c-432b-8c7e-98aeb7988cd2" "locations" "tcp://10.0.2.1:80"
+///
+/// Fields after the upstream host, as added by custom formats, are ignored.
+static ENVOY_REGEX: OnceLock<Regex> = OnceLock::new();
+
+fn get_envoy_regex() -> &'static Regex {
+    ENVOY_REGEX.get_or_init(|| {
+        Regex::new(
+            r#"^\[(?P<time>[^\]]+)\]\s+"(?P<request>[^"]*)"\s+(?P<status>\d{1,3})\s+(?P<flags>\S+)\s+(?:\d+|-)\s+(?P<bytes>\d+|-)\s+(?P<duration>\d+|-)\s+(?:\d+|-)\s+"(?P<xff>[^"]*)"\s+"(?P<agent>[^"]*)"\s+"(?P<request_id>[^"]*)"\s+"[^"]*"\s+"(?P<upstream>[^"]*)"(?:\s.*)?$"#,
+        )
+        .expect("hard-coded regex should always compile")
+    })
+}
+
+/// Parse a line in Envoy's default access log format.
+///
+/// The format logs no peer address, so the client is the first `X-Forwarded-For`
+/// hop; lines without one are rejected. Envoy logs status `0` when no response was
+/// sent (e.g. the client went away), which leaves `status_code` empty. `DURATION`
+/// (milliseconds) is the latency, and `x-request-id` the trace id.
+pub fn parse_envoy_line(line: &str) -> Result<LogEntry, ParseError> {
+    let caps = get_envoy_regex().captures(line.trim()).ok_or_else(|| {
+        ParseError::InvalidFormat(format!(
+            "not an Envoy access log line: {:?}",
+            &line[..line.len().min(100)]
+        ))
+    })?;
+    let invalid = |field: &'static str, value: &str| ParseError::InvalidField {
+        field,
+        value: value.to_string(),
+    };
+    let optional = |name: &str| Some(caps[name].to_string()).filter(|v| v != "-" && !v.is_empty());
+
+    let timestamp = parse_timestamp(&caps["time"])?;
+    let status_code = caps["status"].parse::<u16>().map_err(|_| invalid("status_code", &caps["status"]))?;
+    let status_code = (status_code != 0).then_some(status_code);
+
+    let request = &caps["request"];
+    let mut parts = request.split_whitespace();
+    let (Some(method), Some(endpoint)) = (parts.next(), parts.next()) else {
+        return Err(invalid("request", request));
+    };
+    let protocol = parts.next().and_then(parse_protocol);
+
+    let client = caps["xff"].split(',').next().unwrap_or("").trim();
+    if client.is_empty() || client == "-" {
+        return Err(ParseError::MissingField("x-forwarded-for"));
+    }
+
+    Ok(LogEntry {
+        timestamp,
+        level: status_code.map_or(LogLevel::Info, level_for_status),
+        ip: parse_ip(client)?,
+        user: None,
+        forwarded_for: optional("xff"),
+        method: parse_method(method),
+        endpoint: endpoint.to_string(),
+        status_code,
+        protocol,
+        bytes: caps["bytes"].parse().ok(),
+        referrer: None,
+        user_agent: optional("agent"),
+        duration: caps["duration"].parse().ok().map(Duration::from_millis),
+        backend_status: None,
+        response_flags: Some(caps["flags"].to_string()),
+        upstream_host: optional("upstream"),
+        router: None,
+        service: None,
+        trace_id: optional("request_id"),
+        extra: None,
+    })
+}
+
+/// Traefik's `common` access log: nginx combined plus request count, router, server
+/// URL and duration, e.g.
+///   10.0.0.1 - - [15/Jan/2024:10:30:00 +0000] "GET / HTTP/1.1" 200 512 "-" "curl/8.4.0" 42 "api@docker" "http://172.18.0.3:8080" 7ms
+static TRAEFIK_REGEX: OnceLock<Regex> = OnceLock::new();
+
+fn get_traefik_regex() -> &'static Regex {
+    TRAEFIK_REGEX.get_or_init(|| {
+        Regex::new(
+            r#"^(?P<host>\S+)\s+\S+\s+(?P<user>\S+)\s+\[(?P<time>[^\]]+)\]\s+"(?P<request>[^"]*)"\s+(?P<status>\d{3})\s+(?:(?P<bytes>\d+)|-)\s+"(?P<referrer>[^"]*)"\s+"(?P<agent>[^"]*)"\s+(?:\d+|-)\s+"(?P<router>[^"]*)"\s+"(?P<server>[^"]*)"\s+(?P<duration>\d+)ms\s*$"#,
+        )
+        .expect("hard-coded regex should always compile")
+    })
+}
+
+/// Parse a Traefik access log line, in the `common` layout or as JSON.
+///
+/// JSON lines (`format: json`) are read by their field names: `ClientHost`,
+/// `RequestMethod`, `RequestPath`, `DownstreamStatus`, `StartUTC`, `Duration`
+/// (nanoseconds), `RouterName`, `ServiceName`, `ServiceAddr`, and `OriginStatus`
+/// as the backend status. The `common` layout logs the router and the server URL
+/// but no service. The upstream host is the server's `host:port` either way.
+pub fn parse_traefik_line(line: &str) -> Result<LogEntry, ParseError> {
+    if line.trim_start().starts_with('{') {
+        return traefik_json_entry(&json_object(line)?);
+    }
+    let caps = get_traefik_regex().captures(line.trim()).ok_or_else(|| {
+        ParseError::InvalidFormat(format!(
+            "line does not match Traefik's common format: {:?}",
+            &line[..line.len().min(100)]
+        ))
+    })?;
+    let mut entry = clf_entry(&caps)?;
+    let optional = |name: &str| Some(caps[name].to_string()).filter(|v| v != "-" && !v.is_empty());
+    entry.referrer = optional("referrer");
+    entry.user_agent = optional("agent");
+    entry.router = optional("router");
+    entry.upstream_host = optional("server").map(|url| url_authority(&url).to_string());
+    entry.duration = caps["duration"].parse().ok().map(Duration::from_millis);
+    Ok(entry)
+}
+
+/// Build an entry from a Traefik JSON access log object
+fn traefik_json_entry(object: &serde_json::Value) -> Result<LogEntry, ParseError> {
+    let text = |key: &str| object.get(key).and_then(|v| v.as_str()).filter(|v| !v.is_empty() && *v != "-");
+    let required = |key: &'static str| text(key).ok_or(ParseError::MissingField(key));
+    let number = |key: &str| object.get(key).and_then(|v| v.as_u64());
+
+    let status = object.get("DownstreamStatus").ok_or(ParseError::MissingField("DownstreamStatus"))?;
+    let status_code = status
+        .as_u64()
+        .and_then(|code| u16::try_from(code).ok())
+        .ok_or_else(|| ParseError::InvalidField {
+            field: "status_code",
+            value: status.to_string(),
+        })?;
+    let timestamp = parse_timestamp(text("StartUTC").or_else(|| text("time")).ok_or(ParseError::MissingField("StartUTC"))?)?;
+
+    Ok(LogEntry {
+        timestamp,
+        level: level_for_status(status_code),
+        ip: parse_ip(required("ClientHost")?)?,
+        user: text("ClientUsername").map(String::from),
+        forwarded_for: text("request_X-Forwarded-For").map(String::from),
+        method: parse_method(required("RequestMethod")?),
+        endpoint: required("RequestPath")?.to_string(),
+        status_code: Some(status_code),
+        protocol: text("RequestProtocol").and_then(parse_protocol),
+        bytes: number("DownstreamContentSize"),
+        referrer: text("request_Referer").map(String::from),
+        user_agent: text("request_User-Agent").map(String::from),
+        duration: number("Duration").map(Duration::from_nanos),
+        backend_status: number("OriginStatus").and_then(|code| u16::try_from(code).ok()).filter(|code| *code != 0),
+        response_flags: None,
+        upstream_host: text("ServiceAddr")
+            .or_else(|| text("ServiceURL").map(url_authority))
+            .map(String::from),
+        router: text("RouterName").map(String::from),
+        service: text("ServiceName").map(String::from),
+        trace_id: text("request_X-Request-Id").map(String::from),
+        extra: None,
+    })
+}
+
+/// The `host:port` of an absolute URL; other values are returned unchanged
+fn url_authority(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((_, rest)) => rest.split(['/', '?']).next().unwrap_or(rest),
+        None => url,
+    }
+}
+
+/// Additional fields a GELF message may carry each request field in, by preference
+const GELF_FIELDS: [(&str, &[&str]); 13] = [
+    ("ip", &["_http_client_ip", "_http_remote_addr"]),
+    ("user", &["_http_user", "_http_remote_user"]),
+    ("method", &["_http_method"]),
+    ("endpoint", &["_http_path", "_http_url"]),
+    ("status", &["_http_status", "_http_status_code"]),
+    ("bytes", &["_http_bytes", "_http_response_size"]),
+    ("protocol", &["_http_protocol", "_http_version"]),
+    ("referrer", &["_http_referer", "_http_referrer"]),
+    ("user_agent", &["_http_user_agent"]),
+    ("duration", &["_http_duration_ms", "_http_response_time_ms"]),
+    ("upstream", &["_http_upstream_addr"]),
+    ("trace_id", &["_trace_id", "_request_id", "_http_request_id"]),
+    ("forwarded_for", &["_http_x_forwarded_for", "_http_forwarded_for"]),
+];
+
+/// Parse a GELF 1.1 message, as sent to Graylog, e.g.
+///   {"version":"1.1","host":"web1","short_message":"GET /","timestamp":1705314600.123,"level":6,"_http_client_ip":"10.0.0.1","_http_method":"GET","_http_path":"/","_http_status":200}
+///
+/// The request comes from `_http_*` additional fields (see [`GELF_FIELDS`]), which may
+/// hold strings or numbers; `_http_url` is reduced to its path. The timestamp is
+/// `timestamp` in epoch seconds. The level is the more severe of the message's
+/// syslog `level` and the one the status implies.
+pub fn parse_gelf_line(line: &str) -> Result<LogEntry, ParseError> {
+    let object = json_object(line)?;
+    let value = |field: &str| -> Option<String> {
+        let (_, keys) = GELF_FIELDS.iter().find(|(f, _)| *f == field)?;
+        let text = keys.iter().find_map(|key| match object.get(*key)? {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        })?;
+        Some(text).filter(|v| !v.is_empty() && v != "-")
+    };
+    let required = |field: &'static str| value(field).ok_or(ParseError::MissingField(field));
+    let invalid = |field: &'static str, value: &str| ParseError::InvalidField {
+        field,
+        value: value.to_string(),
+    };
+
+    let timestamp = match object.get("timestamp").ok_or(ParseError::MissingField("timestamp"))? {
+        serde_json::Value::Number(n) => parse_timestamp(&n.to_string())?,
+        other => return Err(invalid("timestamp", &other.to_string())),
+    };
+    let status = required("status")?;
+    let status_code = status.parse::<u16>().map_err(|_| invalid("status_code", &status))?;
+    let level = match object.get("level").and_then(|l| l.as_u64()) {
+        Some(severity) => {
+            let level = u8::try_from(severity)
+                .ok()
+                .and_then(syslog_severity_level)
+                .ok_or_else(|| invalid("level", &severity.to_string()))?;
+            level.max(level_for_status(status_code))
+        }
+        None => level_for_status(status_code),
+    };
+    let endpoint = required("endpoint")?;
+    let duration = match value("duration") {
+        Some(ms) => Some(
+            ms.parse::<f64>()
+                .ok()
+                .filter(|ms| ms.is_finite() && *ms >= 0.0)
+                .map(|ms| Duration::from_micros((ms * 1000.0).round() as u64))
+                .ok_or_else(|| invalid("duration", &ms))?,
+        ),
+        None => None,
+    };
+
+    Ok(LogEntry {
+        timestamp,
+        level,
+        ip: parse_ip(&required("ip")?)?,
+        user: value("user"),
+        forwarded_for: value("forwarded_for"),
+        method: parse_method(&required("method")?),
+        endpoint: url_path(&endpoint).to_string(),
+        status_code: Some(status_code),
+        protocol: value("protocol").as_deref().and_then(parse_protocol),
+        bytes: value("bytes").and_then(|b| b.parse().ok()),
+        referrer: value("referrer"),
+        user_agent: value("user_agent"),
+        duration,
+        backend_status: None,
+        response_flags: None,
+        upstream_host: value("upstream"),
+        router: None,
+        service: None,
+        trace_id: value("trace_id"),
+        extra: None,
+    })
+}
+
+/// The level for a syslog severity (0 emergency through 7 debug), as used by GELF
+/// and journald; `None` above 7
+pub fn syslog_severity_level(severity: u8) -> Option<LogLevel> {
+    match severity {
+        0..=2 => Some(LogLevel::Fatal),
+        3 => Some(LogLevel::Error),
+        4 => Some(LogLevel::Warn),
+        5 | 6 => Some(LogLevel::Info),
+        7 => Some(LogLevel::Debug),
+        _ => None,
+    }
+}
+
+/// Parse a W3C extended log row (CloudFront or IIS) using the column layout in `fields`.
+///
+/// `date`, `time`, `c-ip`, `cs-method`, `cs-uri-stem` and `sc-status` are required.
+/// Latency comes from `time-taken`; escaped user agents and referrers are decoded.
+pub fn parse_w3c_line(line: &str, fields: &W3cFields) -> Result<LogEntry, ParseError> {
+    if line.starts_with('#') {
+        return Err(ParseError::InvalidFormat("W3C directive, not a log row".to_string()));
+    }
+    let line = line.trim_end_matches(['\r', '\n']);
+    let row: Vec<&str> = match fields.dialect {
+        W3cDialect::CloudFront => line.split('\t').collect(),
+        W3cDialect::Iis => line.split_whitespace().collect(),
+    };
+    let required = |name: &'static str| fields.get(&row, name).ok_or(ParseError::MissingField(name));
+    let invalid = |field: &'static str, value: &str| ParseError::InvalidField {
+        field,
+        value: value.to_string(),
+    };
+
+    let (date, time) = (required("date")?, required("time")?);
+    let timestamp = chrono::NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S")
+        .map_err(|_| ParseError::InvalidTimestamp(format!("{} {}", date, time)))?
+        .and_utc();
+
+    let status = required("sc-status")?;
+    let status_code = status.parse::<u16>().map_err(|_| invalid("status_code", status))?;
+    let mut endpoint = required("cs-uri-stem")?.to_string();
+    if let Some(query) = fields.get(&row, "cs-uri-query") {
+        endpoint = format!("{}?{}", endpoint, query);
+    }
+    let duration = match fields.get(&row, "time-taken") {
+        Some(taken) => {
+            let value = taken.parse::<f64>().map_err(|_| invalid("time-taken", taken))?;
+            Some(match fields.dialect {
+                W3cDialect::CloudFront => duration_from_secs(value),
+                W3cDialect::Iis => duration_from_secs(value / 1000.0),
+            })
+        }
+        None => None,
+    };
+    let decode = |value: &str| match fields.dialect {
+        W3cDialect::CloudFront => percent_decode(value),
+        W3cDialect::Iis => value.replace('+', " "),
+    };
+
+    Ok(LogEntry {
+        timestamp,
+        level: level_for_status(status_code),
+        ip: parse_ip(required("c-ip")?)?,
+        user: fields.get(&row, "cs-username").map(String::from),
+        forwarded_for: fields
+            .get(&row, "x-forwarded-for")
+            .or_else(|| fields.get(&row, "cs(X-Forwarded-For)"))
+            .map(String::from),
+        method: parse_method(required("cs-method")?),
+        endpoint,
+        status_code: Some(status_code),
+        protocol: fields
+            .get(&row, "cs-protocol-version")
+            .or_else(|| fields.get(&row, "cs-version"))
+            .and_then(parse_protocol),
+        bytes: fields.get(&row, "sc-bytes").and_then(|b| b.parse().ok()),
+        referrer: fields.get(&row, "cs(Referer)").map(decode),
+        user_agent: fields.get(&row, "cs(User-Agent)").map(decode),
+        duration,
+        backend_status: None,
+        response_flags: None,
+        upstream_host: None,
+        router: None,
+        service: None,
+        trace_id: fields.get(&row, "x-edge-request-id").map(String::from),
+        extra: None,
+    })
+}
+
+/// Decode `%XX` escapes; invalid escapes are kept as they are
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Parse one JSON object per line, locating fields with `keys`.
+///
+/// `timestamp`, `ip`, `method`, `path` and `status` are required. A missing level
+/// is derived from the status code as for CLF; numeric timestamps are taken as Unix
+/// epoch seconds.
+pub fn parse_json_line(line: &str, keys: &JsonKeys) -> Result<LogEntry, ParseError> {
+    entry_from_object(&json_object(line)?, keys, None, false, &LevelAliases::default())
+}
+
+fn json_object(line: &str) -> Result<serde_json::Value, ParseError> {
+    let object: serde_json::Value = serde_json::from_str(line.trim())
+        .map_err(|e| ParseError::InvalidFormat(format!("not a JSON object: {}", e)))?;
+    if !object.is_object() {
+        return Err(ParseError::InvalidFormat("not a JSON object".to_string()));
+    }
+    Ok(object)
+}
+
+/// Parse a logfmt line (`ts=... level=error ip=... method=GET path=/x status=500`),
+/// locating fields with `keys` exactly as for JSON Lines.
+///
+/// Values may be double-quoted with `\"` escapes; empty values count as absent.
+pub fn parse_logfmt_line(line: &str, keys: &JsonKeys) -> Result<LogEntry, ParseError> {
+    entry_from_object(&logfmt_object(line)?, keys, None, false, &LevelAliases::default())
+}
+
+/// Collect logfmt pairs into a JSON object so they share [`entry_from_object`]
+fn logfmt_object(line: &str) -> Result<serde_json::Value, ParseError> {
+    let mut object = serde_json::Map::new();
+    let mut chars = line.trim().chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+        let key: String = std::iter::from_fn(|| chars.next_if(|c| *c != '=' && !c.is_whitespace())).collect();
+        if chars.next_if_eq(&'=').is_none() {
+            continue; // a bare key is a flag with no value
+        }
+        let mut value = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => value.extend(chars.next()),
+                    Some(c) => value.push(c),
+                    None => {
+                        return Err(ParseError::InvalidFormat(format!(
+                            "unterminated quoted value for '{}'",
+                            key
+                        )))
+                    }
+                }
+            }
+        } else {
+            value.extend(std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace())));
+        }
+        if !key.is_empty() && !value.is_empty() {
+            object.insert(key, serde_json::Value::String(value));
+        }
+    }
+    if object.is_empty() {
+        return Err(ParseError::InvalidFormat("no key=value pairs".to_string()));
+    }
+    Ok(serde_json::Value::Object(object))
+}
+
+/// Build an entry from a JSON object (or logfmt pairs), see [`parse_json_line`].
+///
+/// `received` stands in for a missing timestamp, e.g. a syslog envelope's. With
+/// `lenient`, the status may be missing too.
+fn entry_from_object(
+    object: &serde_json::Value,
+    keys: &JsonKeys,
+    received: Option<DateTime<Utc>>,
+    lenient: bool,
+    levels: &LevelAliases,
+) -> Result<LogEntry, ParseError> {
+    let required = |field: &'static str| keys.lookup(object, field).ok_or(ParseError::MissingField(field));
+    let invalid = |field: &'static str, value: &serde_json::Value| ParseError::InvalidField {
+        field,
+        value: value.to_string(),
+    };
+    let text = |field: &'static str| -> Result<Option<String>, ParseError> {
+        match keys.lookup(object, field) {
+            None => Ok(None),
+            Some(serde_json::Value::String(s)) => Ok(Some(s.clone())),
+            Some(other) => Err(invalid(field, other)),
+        }
+    };
+
+    let status_code = match keys.lookup(object, "status") {
+        None if lenient => None,
+        None => return Err(ParseError::MissingField("status")),
+        Some(status) => Some(
+            match status {
+                serde_json::Value::Number(n) => n.as_u64(),
+                serde_json::Value::String(s) => s.parse().ok(),
+                _ => None,
+            }
+            .and_then(|code| u16::try_from(code).ok())
+            .filter(|code| (100..=599).contains(code))
+            .ok_or_else(|| invalid("status_code", status))?,
+        ),
+    };
+
+    let level = match keys.lookup(object, "level") {
+        None => status_code.map_or(LogLevel::Info, level_for_status),
+        Some(value) => value
+            .as_str()
+            .and_then(|s| levels.get(s).or_else(|| parse_level_lenient(s)))
+            .ok_or_else(|| invalid("level", value))?,
+    };
+
+    let ip = required("ip")?;
+    let ip = parse_ip(ip.as_str().ok_or_else(|| invalid("ip", ip))?)?;
+    let method = required("method")?;
+    let method = method.as_str().ok_or_else(|| invalid("method", method))?;
+    let path = required("path")?;
+    let endpoint = path.as_str().ok_or_else(|| invalid("path", path))?.to_string();
+
+    let timestamp = match keys.lookup(object, "timestamp") {
+        None => received.ok_or(ParseError::MissingField("timestamp"))?,
+        Some(serde_json::Value::String(s)) => parse_timestamp(s)?,
+        Some(serde_json::Value::Number(n)) => parse_timestamp(&n.to_string())?,
+        Some(other) => return Err(ParseError::InvalidTimestamp(other.to_string())),
+    };
+
+    let bytes = match keys.lookup(object, "bytes") {
+        None => None,
+        Some(value) => Some(
+            value
+                .as_u64()
+                .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+                .ok_or_else(|| invalid("bytes", value))?,
+        ),
+    };
+    // Numeric ids are kept as text; objects (such as ECS `user`) are not a user name
+    let id = |field: &str| {
+        match keys.lookup(object, field) {
+            Some(serde_json::Value::String(s)) => Some(s.clone()),
+            Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+            _ => None,
+        }
+        .filter(|id| !id.is_empty() && id != "-")
+    };
+
+    Ok(LogEntry {
+        timestamp,
+        level,
+        ip,
+        user: id("user"),
+        forwarded_for: id("forwarded_for"),
+        method: parse_method(&method.to_ascii_uppercase()),
+        endpoint,
+        status_code,
+        protocol: text("protocol")?.as_deref().and_then(parse_protocol),
+        bytes,
+        referrer: text("referrer")?,
+        user_agent: text("user_agent")?,
+        duration: None,
+        backend_status: None,
+        response_flags: None,
+        upstream_host: None,
+        router: None,
+        service: None,
+        trace_id: id("trace_id"),
+        extra: None,
+    })
+}
+
+/// Parse a Caddy access log line (`http.log.access`), e.g.
+///   {"level":"info","ts":1705314600.52,"msg":"handled request","request":{"remote_ip":"10.0.0.1","client_ip":"10.0.0.1","proto":"HTTP/2.0","method":"GET","uri":"/","headers":{"User-Agent":["curl/8.4.0"]}},"duration":0.0009,"size":612,"status":200}
+///
+/// The client is `request.client_ip` (the address behind trusted proxies) or else
+/// `request.remote_ip`. `ts` may be epoch seconds or a formatted time, and
+/// `duration` seconds or a string such as `1.5ms`. Caddy logs 5xx responses at
+/// error level regardless, so the level is derived from the status as for CLF. The
+/// user is `user_id`, set by authentication handlers, and the trace id the
+/// `X-Request-Id` request header.
+pub fn parse_caddy_line(line: &str) -> Result<LogEntry, ParseError> {
+    let object = json_object(line)?;
+    let request = object.get("request").ok_or(ParseError::MissingField("request"))?;
+    let invalid = |field: &'static str, value: &serde_json::Value| ParseError::InvalidField {
+        field,
+        value: value.to_string(),
+    };
+    fn text<'v>(value: Option<&'v serde_json::Value>, field: &'static str) -> Result<&'v str, ParseError> {
+        let value = value.ok_or(ParseError::MissingField(field))?;
+        value.as_str().ok_or_else(|| ParseError::InvalidField {
+            field,
+            value: value.to_string(),
+        })
+    }
+    // Request headers are canonically named lists of values
+    let header = |name: &str| {
+        request
+            .get("headers")
+            .and_then(|h| h.get(name))
+            .and_then(|values| values.get(0))
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .map(String::from)
+    };
+
+    let timestamp = match object.get("ts") {
+        None => return Err(ParseError::MissingField("ts")),
+        Some(serde_json::Value::Number(n)) => parse_timestamp(&n.to_string())?,
+        Some(serde_json::Value::String(s)) => parse_timestamp(s)?,
+        Some(other) => return Err(ParseError::InvalidTimestamp(other.to_string())),
+    };
+    let status = object.get("status").ok_or(ParseError::MissingField("status"))?;
+    let status_code = status
+        .as_u64()
+        .and_then(|code| u16::try_from(code).ok())
+        .ok_or_else(|| invalid("status_code", status))?;
+    let ip = match request.get("client_ip").filter(|ip| ip.as_str().is_some_and(|s| !s.is_empty())) {
+        Some(ip) => text(Some(ip), "client_ip")?,
+        None => text(request.get("remote_ip"), "remote_ip")?,
+    };
+    let duration = match object.get("duration") {
+        None => None,
+        Some(serde_json::Value::Number(n)) => n.as_f64().map(duration_from_secs),
+        Some(serde_json::Value::String(s)) => Some(parse_response_time(s)?),
+        Some(other) => return Err(invalid("duration", other)),
+    };
+
+    Ok(LogEntry {
+        timestamp,
+        level: level_for_status(status_code),
+        ip: parse_ip(ip)?,
+        user: object.get("user_id").and_then(|u| u.as_str()).filter(|u| !u.is_empty()).map(String::from),
+        forwarded_for: header("X-Forwarded-For"),
+        method: parse_method(&text(request.get("method"), "method")?.to_ascii_uppercase()),
+        endpoint: text(request.get("uri"), "uri")?.to_string(),
+        status_code: Some(status_code),
+        protocol: request.get("proto").and_then(|p| p.as_str()).and_then(parse_protocol),
+        bytes: object.get("size").and_then(|s| s.as_u64()),
+        referrer: header("Referer"),
+        user_agent: header("User-Agent"),
+        duration,
+        backend_status: None,
+        response_flags: None,
+        upstream_host: None,
+        router: None,
+        service: None,
+        trace_id: header("X-Request-Id"),
+        extra: None,
+    })
+}
+
+/// Parse a Heroku router log line, as printed by `heroku logs`, e.g.
+///   2024-01-15T10:30:00.123456+00:00 heroku[router]: at=info method=GET path="/" host=app.herokuapp.com fwd="10.0.0.1" dyno=web.1 connect=1ms service=18ms status=200 bytes=612 protocol=https
+///
+/// The client is the first `fwd` hop, the latency is `connect` plus `service` and
+/// the trace id is `request_id`.
+/// Router errors (`at=error`, e.g. H12 request timeouts) are logged at error level;
+/// other lines take their level from the status. Lines from app dynos are rejected.
+pub fn parse_heroku_line(line: &str) -> Result<LogEntry, ParseError> {
+    let not_router = || {
+        ParseError::InvalidFormat(format!(
+            "not a Heroku router line: {:?}",
+            &line[..line.len().min(100)]
+        ))
+    };
+    let (timestamp, rest) = line.trim().split_once(' ').ok_or_else(not_router)?;
+    let pairs = rest.strip_prefix("heroku[router]:").ok_or_else(not_router)?;
+    heroku_entry(pairs, parse_timestamp(timestamp)?)
+}
+
+/// Build an entry from the logfmt pairs of a router line
+fn heroku_entry(pairs: &str, timestamp: DateTime<Utc>) -> Result<LogEntry, ParseError> {
+    let object = logfmt_object(pairs)?;
+    let field = |key: &'static str| object.get(key).and_then(|v| v.as_str());
+    let required = |key: &'static str| field(key).ok_or(ParseError::MissingField(key));
+    let invalid = |field: &'static str, value: &str| ParseError::InvalidField {
+        field,
+        value: value.to_string(),
+    };
+
+    let status = required("status")?;
+    let status_code = status.parse::<u16>().map_err(|_| invalid("status_code", status))?;
+    let client = required("fwd")?.split(',').next().unwrap_or("").trim();
+    let duration = match (field("connect"), field("service")) {
+        (Some(connect), Some(service)) => Some(parse_response_time(connect)? + parse_response_time(service)?),
+        (Some(only), None) | (None, Some(only)) => Some(parse_response_time(only)?),
+        (None, None) => None,
+    };
+    let level = if field("at") == Some("error") {
+        LogLevel::Error
+    } else {
+        level_for_status(status_code)
+    };
+
+    Ok(LogEntry {
+        timestamp,
+        level,
+        ip: parse_ip(client)?,
+        user: None,
+        forwarded_for: field("fwd").map(String::from),
+        method: parse_method(required("method")?),
+        endpoint: required("path")?.to_string(),
+        status_code: Some(status_code),
+        protocol: None,
+        bytes: field("bytes").and_then(|b| b.parse().ok()),
+        referrer: None,
+        user_agent: None,
+        duration,
+        backend_status: None,
+        response_flags: None,
+        upstream_host: None,
+        router: None,
+        service: None,
+        trace_id: field("request_id").map(String::from),
+        extra: None,
+    })
+}
+
+/// Parse an ArcSight CEF event, e.g. from a WAF:
+///   CEF:0|Imperva Inc.|WAF|14.1|20001|SQL injection|8|rt=1705314600000 src=203.0.113.7 requestMethod=POST request=https://shop.example.com/login?id=1 outcome=blocked
+///
+/// Text before `CEF:` (usually a syslog header) is ignored. The client is `src`
+/// and the endpoint the path of `request`, or the event name for events without a
+/// URL such as firewall denies. The timestamp is `rt` (epoch milliseconds or
+/// `MMM dd yyyy HH:mm:ss`), else `end` or `start`. A numeric `outcome` is the
+/// status code; other outcomes leave it empty. The level follows the event
+/// severity (0-3 info, 4-6 warn, 7-8 error, 9-10 fatal), raised to error for
+/// `failure`, `blocked` and `denied` outcomes so the flagged-IP analysis counts them.
+pub fn parse_cef_line(line: &str) -> Result<LogEntry, ParseError> {
+    cef_entry(line, None)
+}
+
+/// Build an entry from a CEF event; `received` stands in for a missing event time
+fn cef_entry(line: &str, received: Option<DateTime<Utc>>) -> Result<LogEntry, ParseError> {
+    let start = line.find("CEF:").ok_or_else(|| {
+        ParseError::InvalidFormat(format!("not a CEF event: {:?}", &line[..line.len().min(100)]))
+    })?;
+    let header = cef_header(&line[start..])
+        .ok_or_else(|| ParseError::InvalidFormat("CEF header has fewer than 7 fields".to_string()))?;
+    let [_version, _vendor, _product, _device_version, _signature, name, severity, extension] = header;
+    let fields = cef_extension(&extension);
+    let field = |key: &str| fields.get(key).map(String::as_str).filter(|v| !v.is_empty());
+    let invalid = |field: &'static str, value: &str| ParseError::InvalidField {
+        field,
+        value: value.to_string(),
+    };
+
+    let timestamp = match ["rt", "end", "start"].into_iter().find_map(field) {
+        Some(time) => parse_cef_time(time)?,
+        None => received.ok_or(ParseError::MissingField("rt"))?,
+    };
+    let outcome = field("outcome");
+    let status_code = outcome.and_then(|o| o.parse::<u16>().ok()).filter(|code| (100..600).contains(code));
+    let mut level = match severity.trim().to_ascii_lowercase().as_str() {
+        "low" | "unknown" | "" => LogLevel::Info,
+        "medium" => LogLevel::Warn,
+        "high" => LogLevel::Error,
+        "very-high" => LogLevel::Fatal,
+        other => match other.parse::<u8>().map_err(|_| invalid("severity", &severity))? {
+            0..=3 => LogLevel::Info,
+            4..=6 => LogLevel::Warn,
+            7..=8 => LogLevel::Error,
+            _ => LogLevel::Fatal,
+        },
+    };
+    if let Some(code) = status_code {
+        level = level.max(level_for_status(code));
+    } else if outcome.is_some_and(|o| {
+        matches!(o.to_ascii_lowercase().as_str(), "failure" | "failed" | "blocked" | "block" | "denied" | "deny")
+    }) {
+        level = level.max(LogLevel::Error);
+    }
+    let endpoint = match field("request") {
+        Some(url) => url_path(url).to_string(),
+        None => name.to_string(),
+    };
+
+    Ok(LogEntry {
+        timestamp,
+        level,
+        ip: parse_ip(field("src").ok_or(ParseError::MissingField("src"))?)?,
+        user: field("suser").map(String::from),
+        forwarded_for: None,
+        method: parse_method(&field("requestMethod").unwrap_or("-").to_ascii_uppercase()),
+        endpoint,
+        status_code,
+        protocol: None,
+        bytes: field("out").and_then(|b| b.parse().ok()),
+        referrer: field("requestContext").map(String::from),
+        user_agent: field("requestClientApplication").map(String::from),
+        duration: None,
+        backend_status: None,
+        response_flags: None,
+        upstream_host: None,
+        router: None,
+        service: None,
+        trace_id: None,
+        extra: None,
+    })
+}
+
+/// Split a CEF header into its seven `|`-separated fields and the extension,
+/// honouring `\|` and `\\` escapes
+fn cef_header(event: &str) -> Option<[String; 8]> {
+    let mut fields: Vec<String> = Vec::with_capacity(8);
+    let mut current = String::new();
+    let mut chars = event.strip_prefix("CEF:")?.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next().map(|(_, c)| c)),
+            '|' => {
+                fields.push(std::mem::take(&mut current));
+                if fields.len() == 7 {
+                    fields.push(event["CEF:".len() + i + 1..].to_string());
+                    return fields.try_into().ok();
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    None
+}
+
+/// Collect CEF extension pairs. Values may contain spaces and run up to the next
+/// `key=`; `\=`, `\\`, `\n` and `\r` are unescaped.
+fn cef_extension(extension: &str) -> HashMap<String, String> {
+    let key_start = |eq: usize| extension[..eq].rfind(' ').map_or(0, |space| space + 1);
+    // Byte offsets of the `=` signs that end a key. Unescaped `=` inside a value
+    // (common in URLs) has no space before it since the previous key's.
+    let mut equals: Vec<usize> = Vec::new();
+    let mut escaped = false;
+    for (i, c) in extension.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '=' => {
+                let start = key_start(i);
+                let key = &extension[start..i];
+                let after_previous = equals.last().is_none_or(|&prev| start > prev);
+                if after_previous && !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+                    equals.push(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut fields = HashMap::new();
+    for (n, &eq) in equals.iter().enumerate() {
+        let key = &extension[key_start(eq)..eq];
+        let end = equals.get(n + 1).map_or(extension.len(), |&next| key_start(next));
+        let raw = extension[eq + 1..end].trim_end();
+        let mut value = String::with_capacity(raw.len());
+        let mut chars = raw.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                value.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('r') => value.push('\r'),
+                Some(other) => value.push(other),
+                None => value.push('\\'),
+            }
+        }
+        if !key.is_empty() {
+            fields.insert(key.to_string(), value);
+        }
+    }
+    fields
+}
+
+/// A CEF time: epoch milliseconds, `MMM dd yyyy HH:mm:ss[.SSS] [zone]`, or RFC 3339
+fn parse_cef_time(s: &str) -> Result<DateTime<Utc>, ParseError> {
+    if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+        return s
+            .parse()
+            .ok()
+            .and_then(DateTime::from_timestamp_millis)
+            .ok_or_else(|| ParseError::InvalidTimestamp(s.to_string()));
+    }
+    let (local, zone) = match s.rsplit_once(' ') {
+        Some((local, zone)) if zone.starts_with(['+', '-']) || zone.eq_ignore_ascii_case("utc") || zone == "GMT" => {
+            (local, zone)
+        }
+        _ => (s, "UTC"),
+    };
+    if let Ok(naive) = NaiveDateTime::parse_from_str(local, "%b %d %Y %H:%M:%S%.f") {
+        return match zone.parse::<chrono::FixedOffset>() {
+            Ok(offset) => naive
+                .and_local_timezone(offset)
+                .single()
+                .map(|dt| dt.with_timezone(&Utc))
+                .ok_or_else(|| ParseError::InvalidTimestamp(s.to_string())),
+            Err(_) => Ok(naive.and_utc()),
+        };
+    }
+    parse_timestamp(s)
+}
+
+/// The path and query of an absolute URL; other values are returned unchanged
+fn url_path(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((_, rest)) => rest.find(['/', '?']).map_or("/", |i| &rest[i..]),
+        None => url,
+    }
 }
 
-/// Parse a single log line into a structured `LogEntry`.
-///
-/// Returns `Err(ParseError)` if the line is malformed or contains invalid field values.
-pub fn parse_log_line(line: &str) -> Result<LogEntry, ParseError> {
-    let re = get_regex();
+/// Parse a line with a user-supplied pattern
+pub fn parse_pattern_line(line: &str, pattern: &CustomPattern) -> Result<LogEntry, ParseError> {
+    pattern_entry(line, pattern, false, &LevelAliases::default())
+}
 
-    let caps = re.captures(line.trim()).ok_or_else(|| {
+/// Build an entry from a pattern match; with `lenient`, the status group may be
+/// unmatched. Aliases in `levels` take precedence over the pattern's own.
+fn pattern_entry(
+    line: &str,
+    pattern: &CustomPattern,
+    lenient: bool,
+    levels: &LevelAliases,
+) -> Result<LogEntry, ParseError> {
+    let caps = pattern.regex.captures(line.trim()).ok_or_else(|| {
         ParseError::InvalidFormat(format!(
-            "line does not match expected pattern: {:?}",
+            "line does not match --pattern: {:?}",
             &line[..line.len().min(100)]
         ))
     })?;
 
-    let timestamp = caps["timestamp"].to_string();
-    let ip = caps["ip"].to_string();
-    let endpoint = caps["endpoint"].to_string();
-
-    let level = parse_level(&caps["level"])?;
-    let method = parse_method(&caps["method"]);
+    let field = |name: &str| pattern.groups.get(name).and_then(|&i| caps.get(i)).map(|m| m.as_str());
+    let required = |name: &'static str| field(name).ok_or(ParseError::MissingField(name));
 
-    let status_str = &caps["status"];
-    let status_code = status_str.parse::<u16>().map_err(|_| ParseError::InvalidField {
-        field: "status_code",
-        value: status_str.to_string(),
-    })?;
+    let status_code = match field("status") {
+        None if lenient => None,
+        None => return Err(ParseError::MissingField("status")),
+        Some(s) => Some(s.parse::<u16>().map_err(|_| ParseError::InvalidField {
+            field: "status_code",
+            value: s.to_string(),
+        })?),
+    };
+    let level = match field("level") {
+        Some(l) => levels
+            .get(l)
+            .or_else(|| pattern.levels.get(l))
+            .or_else(|| parse_level_lenient(l))
+            .ok_or_else(|| ParseError::InvalidField {
+                field: "level",
+                value: l.to_string(),
+            })?,
+        None => status_code.map_or(LogLevel::Info, level_for_status),
+    };
+    let bytes = match field("bytes") {
+        None | Some("-") | Some("") => None,
+        Some(b) => Some(b.parse::<u64>().map_err(|_| ParseError::InvalidField {
+            field: "bytes",
+            value: b.to_string(),
+        })?),
+    };
+    let optional = |name: &str| field(name).filter(|v| *v != "-" && !v.is_empty()).map(String::from);
+    let timestamp = match &pattern.timestamp_format {
+        Some(layout) => parse_timestamp_with(required("timestamp")?, layout)?,
+        None => parse_timestamp(required("timestamp")?)?,
+    };
 
     Ok(LogEntry {
         timestamp,
         level,
-        ip,
-        method,
-        endpoint,
+        ip: parse_ip(required("ip")?)?,
+        user: optional("user"),
+        forwarded_for: optional("forwarded_for"),
+        method: parse_method(&required("method")?.to_ascii_uppercase()),
+        endpoint: required("endpoint")?.to_string(),
         status_code,
+        protocol: field("protocol").and_then(parse_protocol),
+        bytes,
+        referrer: optional("referrer"),
+        user_agent: optional("user_agent"),
+        duration: None,
+        backend_status: None,
+        response_flags: None,
+        upstream_host: None,
+        router: None,
+        service: None,
+        trace_id: optional("trace_id"),
+        extra: None,
     })
 }
 
+/// Read an HTTP protocol token, upper-cased (`http/1.1` → `HTTP/1.1`); a bare
+/// version such as `2.0` gets the `HTTP/` prefix. Anything else is not a protocol.
+fn parse_protocol(s: &str) -> Option<String> {
+    let token = s.trim().to_ascii_uppercase();
+    if token.starts_with("HTTP/") {
+        Some(token)
+    } else if token.starts_with(|c: char| c.is_ascii_digit()) && token.parse::<f32>().is_ok() {
+        Some(format!("HTTP/{}", token))
+    } else {
+        None
+    }
+}
+
+/// Parse a response time with its unit: `123ms`, `1.5s`, `850us` or `850µs`
+pub fn parse_response_time(s: &str) -> Result<Duration, ParseError> {
+    let invalid = || ParseError::InvalidField {
+        field: "duration",
+        value: s.to_string(),
+    };
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').ok_or_else(invalid)?;
+    let (value, unit) = s.split_at(split);
+    let value: f64 = value.parse().map_err(|_| invalid())?;
+    let micros = match unit {
+        "s" => value * 1_000_000.0,
+        "ms" => value * 1_000.0,
+        "us" | "µs" => value,
+        _ => return Err(invalid()),
+    };
+    Ok(Duration::from_micros(micros.round() as u64))
+}
+
+/// A duration from fractional seconds, kept to microsecond precision
+fn duration_from_secs(secs: f64) -> Duration {
+    Duration::from_micros((secs * 1_000_000.0).round() as u64)
+}
+
+/// Parse a timestamp in RFC 3339, Apache `%d/%b/%Y:%H:%M:%S %z` or Unix epoch
+/// seconds (optionally fractional), as a UTC instant
+pub fn parse_timestamp(s: &str) -> Result<DateTime<Utc>, ParseError> {
+    let s = s.trim();
+    DateTime::parse_from_rfc3339(s)
+        .or_else(|_| DateTime::parse_from_str(s, "%d/%b/%Y:%H:%M:%S %z"))
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+        .or_else(|| parse_epoch(s))
+        .ok_or_else(|| ParseError::InvalidTimestamp(s.to_string()))
+}
+
+/// Parse a timestamp in a strftime `layout`; layouts without a zone are read as UTC
+pub fn parse_timestamp_with(s: &str, layout: &str) -> Result<DateTime<Utc>, ParseError> {
+    let s = s.trim();
+    DateTime::parse_from_str(s, layout)
+        .map(|dt| dt.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(s, layout).map(|dt| dt.and_utc()))
+        .map_err(|_| ParseError::InvalidField {
+            field: "timestamp",
+            value: s.to_string(),
+        })
+}
+
+fn parse_epoch(s: &str) -> Option<DateTime<Utc>> {
+    let (secs, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if secs.is_empty() || !secs.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let nanos = format!("{:0<9}", &fraction[..fraction.len().min(9)]).parse().ok()?;
+    DateTime::from_timestamp(secs.parse().ok()?, nanos)
+}
+
+/// Parse a client address: IPv4, IPv6, or bracketed IPv6 with an optional port
+/// (`[2001:db8::1]:443`).
+///
+/// A `%zone` suffix is dropped, and IPv4-mapped IPv6 addresses (`::ffff:10.0.0.1`)
+/// are reported as IPv4 so one client is not counted under two forms.
+pub fn parse_ip(s: &str) -> Result<IpAddr, ParseError> {
+    let invalid = || ParseError::InvalidField {
+        field: "ip",
+        value: s.to_string(),
+    };
+    let addr = match s.strip_prefix('[') {
+        Some(rest) => {
+            let (addr, after) = rest.split_once(']').ok_or_else(invalid)?;
+            let port_ok = after.is_empty()
+                || after.strip_prefix(':').is_some_and(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()));
+            if !port_ok {
+                return Err(invalid());
+            }
+            addr
+        }
+        None => s,
+    };
+    let addr = addr.split_once('%').map_or(addr, |(addr, _zone)| addr);
+    addr.parse::<IpAddr>().map(|ip| ip.to_canonical()).map_err(|_| invalid())
+}
+
+/// Map the many spellings of a severity (`warning`, `FATAL`, `err`, …) onto a level
+fn parse_level_lenient(s: &str) -> Option<LogLevel> {
+    match s.to_ascii_lowercase().as_str() {
+        "trace" => Some(LogLevel::Trace),
+        "debug" => Some(LogLevel::Debug),
+        "info" | "information" | "notice" => Some(LogLevel::Info),
+        "warn" | "warning" => Some(LogLevel::Warn),
+        "error" | "err" => Some(LogLevel::Error),
+        "fatal" | "critical" | "crit" | "alert" | "emerg" | "panic" => Some(LogLevel::Fatal),
+        _ => None,
+    }
+}
+
+/// Severity for formats without a level field: 5xx is an error, 4xx a warning
+fn level_for_status(status_code: u16) -> LogLevel {
+    match status_code {
+        500.. => LogLevel::Error,
+        400..=499 => LogLevel::Warn,
+        _ => LogLevel::Info,
+    }
+}
+
+/// Heuristically decide whether a line that failed to parse was meant to be an
+/// access-log line (and is therefore malformed) or is interleaved application
+/// output such as startup messages and stack traces.
+///
+/// A line counts as access-like when it carries both an HTTP method token and a
+/// token that looks like an HTTP status code.
+pub fn looks_like_access_line(line: &str) -> bool {
+    const METHODS: [&str; 7] = ["GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS"];
+
+    let mut has_method = false;
+    let mut has_status = false;
+    for token in line.split_whitespace() {
+        let token = token.trim_matches(|c: char| c == '"' || c == '[' || c == ']');
+        if METHODS.contains(&token) {
+            has_method = true;
+        } else if token.len() == 3
+            && token.bytes().all(|b| b.is_ascii_digit())
+            && (b'1'..=b'5').contains(&token.as_bytes()[0])
+        {
+            has_status = true;
+        }
+    }
+    has_method && has_status
+}
+
+/// Whether a line that failed to parse can continue the entry before it:
+/// indented lines (stack frames, wrapped messages) and lines without an HTTP
+/// request such as an exception message or Java's `Caused by:`.
+///
+/// Unindented near-miss access lines are not continuations, so they are still
+/// reported as malformed.
+pub fn is_continuation_line(line: &str) -> bool {
+    line.starts_with([' ', '\t']) || !looks_like_access_line(line)
+}
+
 fn parse_level(s: &str) -> Result<LogLevel, ParseError> {
     match s {
+        "TRACE" => Ok(LogLevel::Trace),
+        "DEBUG" => Ok(LogLevel::Debug),
         "INFO" => Ok(LogLevel::Info),
         "WARN" => Ok(LogLevel::Warn),
         "ERROR" => Ok(LogLevel::Error),
+        "FATAL" => Ok(LogLevel::Fatal),
         other => Err(ParseError::InvalidField {
             field: "level",
             value: other.to_string(),
@@ -167,15 +2534,695 @@ mod tests {
         "2024-01-15T10:30:00Z [INFO] 192.168.1.1 GET /api/users 200"
     }
 
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
     #[test]
     fn parses_valid_line() {
         let entry = parse_log_line(valid_line()).expect("should parse valid line");
-        assert_eq!(entry.timestamp, "2024-01-15T10:30:00Z");
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00Z"));
+        assert_eq!(entry.level, LogLevel::Info);
+        assert_eq!(entry.ip.to_string(), "192.168.1.1");
+        assert_eq!(entry.method, HttpMethod::Get);
+        assert_eq!(entry.endpoint, "/api/users");
+        assert_eq!(entry.status_code, Some(200));
+    }
+
+    #[test]
+    fn parses_timestamp_layouts() {
+        let expected = utc("2024-01-15T10:30:00Z");
+        for ts in ["2024-01-15T10:30:00Z", "2024-01-15T11:30:00+01:00", "15/Jan/2024:05:30:00 -0500", "1705314600"] {
+            assert_eq!(parse_timestamp(ts).unwrap(), expected, "{}", ts);
+        }
+        assert_eq!(parse_timestamp("1705314600.25").unwrap(), utc("2024-01-15T10:30:00.25Z"));
+
+        let entry = parse_log_line("15/Jan/2024:10:30:00 +0000 [INFO] 1.1.1.1 GET / 200").unwrap();
+        assert_eq!(entry.timestamp, expected);
+
+        for bad in ["yesterday", "2024-13-01T00:00:00Z", "-5", "1.2.3"] {
+            assert!(matches!(parse_timestamp(bad), Err(ParseError::InvalidTimestamp(_))), "{}", bad);
+        }
+        assert!(matches!(
+            parse_log_line("soon [INFO] 1.1.1.1 GET / 200"),
+            Err(ParseError::InvalidTimestamp(_))
+        ));
+        assert!(matches!(
+            parse_json_line(r#"{"ip":"1.1.1.1","method":"GET","path":"/","status":200}"#, &JsonKeys::default()),
+            Err(ParseError::MissingField("timestamp"))
+        ));
+    }
+
+    #[test]
+    fn parses_common_log_format() {
+        let line = r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326"#;
+        let entry = parse_line(line, &LogFormat::Clf).unwrap();
+        assert_eq!(entry.timestamp, utc("2000-10-10T20:55:36Z"));
+        assert_eq!(entry.ip.to_string(), "127.0.0.1");
+        assert_eq!(entry.method, HttpMethod::Get);
+        assert_eq!(entry.endpoint, "/apache_pb.gif");
+        assert_eq!(entry.status_code, Some(200));
+        assert_eq!(entry.bytes, Some(2326));
+        assert_eq!(entry.protocol.as_deref(), Some("HTTP/1.0"));
+        assert_eq!(entry.level, LogLevel::Info);
+        assert_eq!(entry.user.as_deref(), Some("frank"));
+
+        let line = r#"10.0.0.5 - - [10/Oct/2000:13:55:36 +0000] "POST /login HTTP/1.1" 503 -"#;
+        let entry = parse_line(line, &LogFormat::Clf).unwrap();
+        assert_eq!(entry.level, LogLevel::Error);
+        assert_eq!(entry.bytes, None);
+        assert_eq!(entry.user, None);
+    }
+
+    #[test]
+    fn recovers_near_miss_native_lines() {
+        let recover = |line: &str| recover_line(line, &LogFormat::Native, false, &LevelAliases::default());
+        for line in [
+            "2024-01-15T10:30:00Z  [ERROR]  10.0.0.1  GET  /cart  500",
+            "2024-01-15T10:30:00Z ERROR 10.0.0.1 GET /cart 500",
+            "2024-01-15T10:30:00Z [ error ] 10.0.0.1 get /cart 500",
+            "2024-01-15 10:30:00Z [Error] 10.0.0.1 GET /cart 500",
+        ] {
+            let entry = recover(line).unwrap_or_else(|| panic!("not recovered: {}", line));
+            assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00Z"));
+            assert_eq!((entry.level, entry.method, entry.status_code), (LogLevel::Error, HttpMethod::Get, Some(500)));
+            assert_eq!(entry.endpoint, "/cart");
+        }
+
+        // A missing level is only rescued in lenient mode
+        let line = "2024-01-15T10:30:00Z 10.0.0.1 post /login 401";
+        assert!(recover(line).is_none());
+        let entry = recover_line(line, &LogFormat::Native, true, &LevelAliases::default()).unwrap();
+        assert_eq!((entry.level, entry.method), (LogLevel::Warn, HttpMethod::Post));
+
+        assert!(recover("2024-01-15T10:30:00Z [NOTALEVEL] 10.0.0.1 GET / 200").is_none());
+        assert!(recover("2024-01-15T10:30:00Z [INFO 10.0.0.1 GET / 200").is_none());
+        assert!(recover("garbage").is_none());
+        assert!(recover_line("2024-01-15T10:30:00Z info 10.0.0.1 get / 200", &LogFormat::Clf, false, &LevelAliases::default()).is_none());
+    }
+
+    #[test]
+    fn maps_level_aliases() {
+        let levels: LevelAliases = [("NOTICE".to_string(), LogLevel::Info), ("crit".to_string(), LogLevel::Fatal)]
+            .into_iter()
+            .collect();
+        let native = "2024-01-15T10:30:00Z [NOTICE] 10.0.0.1 GET / 200";
+        assert!(matches!(
+            parse_line(native, &LogFormat::Native),
+            Err(ParseError::InvalidField { field: "level", .. })
+        ));
+        let entry = parse_line_with_levels(native, &LogFormat::Native, false, &levels).unwrap();
+        assert_eq!(entry.level, LogLevel::Info);
+
+        let json = r#"{"time":"2024-01-15T10:30:00Z","level":"Crit","ip":"10.0.0.1","method":"GET","path":"/","status":200}"#;
+        let keys = LogFormat::Json(JsonKeys::default());
+        assert_eq!(parse_line_with_levels(json, &keys, false, &levels).unwrap().level, LogLevel::Fatal);
+        // Aliases take precedence over the spellings a format already understands
+        let remapped: LevelAliases = [("crit".to_string(), LogLevel::Error)].into_iter().collect();
+        assert_eq!(parse_line_with_levels(json, &keys, false, &remapped).unwrap().level, LogLevel::Error);
+
+        let entry = recover_line("2024-01-15T10:30:00Z notice 10.0.0.1 get / 200", &LogFormat::Native, false, &levels);
+        assert_eq!(entry.unwrap().level, LogLevel::Info);
+        assert_eq!("Warn".parse::<LogLevel>().unwrap(), LogLevel::Warn);
+        assert!("warning".parse::<LogLevel>().is_err());
+    }
+
+    #[test]
+    fn lenient_parsing_keeps_lines_without_level_or_status() {
+        let line = "2024-01-15T10:30:00Z 10.0.0.1 GET /health";
+        assert!(parse_line(line, &LogFormat::Native).is_err());
+        let entry = parse_line_lenient(line, &LogFormat::Native).unwrap();
+        assert_eq!(entry.status_code, None);
         assert_eq!(entry.level, LogLevel::Info);
-        assert_eq!(entry.ip, "192.168.1.1");
+
+        let entry = parse_line_lenient("2024-01-15T10:30:00Z 10.0.0.1 GET /x 503 12", &LogFormat::Native).unwrap();
+        assert_eq!(entry.status_code, Some(503));
+        assert_eq!(entry.level, LogLevel::Error);
+        assert_eq!(entry.bytes, Some(12));
+        let entry = parse_line_lenient("2024-01-15T10:30:00Z [WARN] 10.0.0.1 GET /x", &LogFormat::Native).unwrap();
+        assert_eq!((entry.level, entry.status_code), (LogLevel::Warn, None));
+
+        let line = r#"{"ts":1705314600,"level":"error","ip":"10.0.0.1","method":"GET","path":"/"}"#;
+        let json = LogFormat::Json(JsonKeys::default());
+        assert!(matches!(parse_line(line, &json), Err(ParseError::MissingField("status"))));
+        let entry = parse_line_lenient(line, &json).unwrap();
+        assert_eq!((entry.level, entry.status_code), (LogLevel::Error, None));
+
+        // Formats whose status is part of the layout stay strict
+        let line = r#"1.2.3.4 - - [10/Oct/2000:13:55:36 +0000] "GET / HTTP/1.1" - 5"#;
+        assert!(parse_line_lenient(line, &LogFormat::Clf).is_err());
+    }
+
+    #[test]
+    fn parses_nginx_combined_format() {
+        let line = r#"10.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET /docs HTTP/1.1" 200 612 "https://example.com/" "Mozilla/5.0 (X11; Linux x86_64)""#;
+        let entry = parse_line(line, &LogFormat::NginxCombined).unwrap();
+        assert_eq!(entry.endpoint, "/docs");
+        assert_eq!(entry.referrer.as_deref(), Some("https://example.com/"));
+        assert_eq!(entry.user_agent.as_deref(), Some("Mozilla/5.0 (X11; Linux x86_64)"));
+
+        let line = r#"10.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET / HTTP/1.1" 304 0 "-" "-""#;
+        let entry = parse_line(line, &LogFormat::NginxCombined).unwrap();
+        assert_eq!(entry.referrer, None);
+        assert_eq!(entry.user_agent, None);
+        assert_eq!(entry.forwarded_for, None);
+
+        // nginx's `main` format appends X-Forwarded-For
+        let line = r#"10.0.0.2 - - [10/Oct/2000:13:55:36 +0000] "GET / HTTP/1.1" 200 5 "-" "curl/8.4.0" "203.0.113.7, 10.0.0.9""#;
+        let entry = parse_line(line, &LogFormat::NginxCombined).unwrap();
+        assert_eq!(entry.ip.to_string(), "10.0.0.2");
+        assert_eq!(entry.forwarded_for.as_deref(), Some("203.0.113.7, 10.0.0.9"));
+
+        // A plain CLF line lacks the trailing fields
+        assert!(parse_combined_line(r#"1.2.3.4 - - [10/Oct/2000:13:55:36 +0000] "GET / HTTP/1.1" 200 5"#).is_err());
+    }
+
+    #[test]
+    fn parses_json_lines_with_default_keys() {
+        let line = r#"{"time":"2024-01-15T10:30:00Z","level":"warning","remote_addr":"10.0.0.5","method":"post","path":"/login","status":"429","bytes":12}"#;
+        let entry = parse_line(line, &LogFormat::Json(JsonKeys::default())).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00Z"));
+        assert_eq!(entry.level, LogLevel::Warn);
+        assert_eq!(entry.ip.to_string(), "10.0.0.5");
+        assert_eq!(entry.method, HttpMethod::Post);
+        assert_eq!(entry.endpoint, "/login");
+        assert_eq!(entry.status_code, Some(429));
+        assert_eq!(entry.bytes, Some(12));
+        assert_eq!(entry.protocol, None);
+        assert_eq!(entry.user, None);
+
+        let line = r#"{"time":"2024-01-15T10:30:00Z","remote_addr":"10.0.0.5","method":"GET","path":"/","status":200,"http_version":"2.0","user":"svc-billing"}"#;
+        let entry = parse_line(line, &LogFormat::Json(JsonKeys::default())).unwrap();
+        assert_eq!(entry.protocol.as_deref(), Some("HTTP/2.0"));
+        assert_eq!(entry.user.as_deref(), Some("svc-billing"));
+
+        let line = r#"time=2024-01-15T10:30:00Z ip=10.0.0.5 method=GET path=/ status=200 user_id=42 request_id=req-7"#;
+        let entry = parse_line(line, &LogFormat::Logfmt(JsonKeys::default())).unwrap();
+        assert_eq!(entry.user.as_deref(), Some("42"));
+        assert_eq!(entry.trace_id.as_deref(), Some("req-7"));
+    }
+
+    #[test]
+    fn parses_json_lines_with_configured_keys() {
+        let mut keys = JsonKeys::default();
+        keys.set("ip", "client.address").unwrap();
+        keys.set("status", "http.status").unwrap();
+        assert!(keys.set("colour", "x").is_err());
+
+        let line = r#"{"ts":1705314600,"client":{"address":"1.2.3.4"},"method":"GET","uri":"/","http":{"status":503}}"#;
+        let entry = parse_json_line(line, &keys).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00Z"));
+        assert_eq!(entry.ip.to_string(), "1.2.3.4");
+        assert_eq!(entry.status_code, Some(503));
+        assert_eq!(entry.level, LogLevel::Error);
+
+        let missing = r#"{"method":"GET","path":"/","http":{"status":200}}"#;
+        assert!(matches!(parse_json_line(missing, &keys), Err(ParseError::MissingField("ip"))));
+        assert!(parse_json_line("[1, 2]", &keys).is_err());
+    }
+
+    #[test]
+    fn parses_caddy_access_lines() {
+        let line = r#"{"level":"info","ts":1705314600.5,"logger":"http.log.access","msg":"handled request","request":{"remote_ip":"172.16.0.2","remote_port":"41342","client_ip":"203.0.113.7","proto":"HTTP/2.0","method":"GET","host":"example.com","uri":"/docs?page=2","headers":{"User-Agent":["curl/8.4.0"],"Referer":["https://example.com/"]}},"bytes_read":0,"user_id":"","duration":0.0125,"size":10900,"status":200,"resp_headers":{"Server":["Caddy"]}}"#;
+        let entry = parse_line(line, &LogFormat::Caddy).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00.5Z"));
+        assert_eq!(entry.ip.to_string(), "203.0.113.7");
         assert_eq!(entry.method, HttpMethod::Get);
+        assert_eq!(entry.endpoint, "/docs?page=2");
+        assert_eq!(entry.status_code, Some(200));
+        assert_eq!(entry.protocol.as_deref(), Some("HTTP/2.0"));
+        assert_eq!(entry.bytes, Some(10900));
+        assert_eq!(entry.duration, Some(Duration::from_micros(12500)));
+        assert_eq!(entry.user_agent.as_deref(), Some("curl/8.4.0"));
+        assert_eq!(entry.referrer.as_deref(), Some("https://example.com/"));
+
+        // Older releases log no client_ip; formatted times and durations are accepted
+        let line = r#"{"level":"error","ts":"2024-01-15T10:30:01Z","request":{"remote_ip":"10.0.0.1","method":"POST","uri":"/api","headers":{}},"duration":"1.5s","size":0,"status":502}"#;
+        let entry = parse_caddy_line(line).unwrap();
+        assert_eq!(entry.ip.to_string(), "10.0.0.1");
+        assert_eq!(entry.level, LogLevel::Error);
+        assert_eq!(entry.duration, Some(Duration::from_millis(1500)));
+        assert_eq!(entry.user_agent, None);
+
+        let flat = r#"{"ts":1705314600,"ip":"10.0.0.1","method":"GET","path":"/","status":200}"#;
+        assert!(matches!(parse_caddy_line(flat), Err(ParseError::MissingField("request"))));
+    }
+
+    #[test]
+    fn parses_heroku_router_lines() {
+        let line = r#"2024-01-15T10:30:00.123456+00:00 heroku[router]: at=info method=GET path="/search?q=a b" host=app.herokuapp.com request_id=8601b555 fwd="203.0.113.7,10.1.2.3" dyno=web.1 connect=1ms service=18ms status=200 bytes=1543 protocol=https"#;
+        let entry = parse_line(line, &LogFormat::Heroku).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00.123456Z"));
+        assert_eq!(entry.ip.to_string(), "203.0.113.7");
+        assert_eq!(entry.endpoint, "/search?q=a b");
+        assert_eq!(entry.status_code, Some(200));
+        assert_eq!(entry.level, LogLevel::Info);
+        assert_eq!(entry.bytes, Some(1543));
+        assert_eq!(entry.duration, Some(Duration::from_millis(19)));
+        assert_eq!(entry.trace_id.as_deref(), Some("8601b555"));
+
+        let timeout = r#"2024-01-15T10:30:31+00:00 heroku[router]: at=error code=H12 desc="Request timeout" method=POST path="/upload" host=app.herokuapp.com fwd="10.0.0.1" dyno=web.2 connect=0ms service=30000ms status=503 bytes=0"#;
+        let entry = parse_heroku_line(timeout).unwrap();
+        assert_eq!(entry.level, LogLevel::Error);
+        assert_eq!(entry.method, HttpMethod::Post);
+
+        // Drains deliver the pairs alone in an RFC 5424 envelope
+        let drained = r#"<158>1 2024-01-15T10:30:00+00:00 host heroku router - - at=info method=GET path="/" fwd="10.0.0.1" connect=0ms service=3ms status=304 bytes=0"#;
+        let entry = parse_line(drained, &LogFormat::Syslog(Box::new(LogFormat::Heroku))).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00Z"));
+        assert_eq!(entry.status_code, Some(304));
+
+        let app = "2024-01-15T10:30:00+00:00 app[web.1]: Started GET \"/\"";
+        assert!(matches!(parse_heroku_line(app), Err(ParseError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn parses_traefik_common_and_json_lines() {
+        let line = r#"10.0.0.1 - - [15/Jan/2024:10:30:00 +0000] "GET /api/users HTTP/1.1" 502 11 "-" "curl/8.4.0" 42 "api@docker" "http://172.18.0.3:8080" 7ms"#;
+        let entry = parse_line(line, &LogFormat::Traefik).unwrap();
+        assert_eq!(entry.ip.to_string(), "10.0.0.1");
         assert_eq!(entry.endpoint, "/api/users");
-        assert_eq!(entry.status_code, 200);
+        assert_eq!(entry.status_code, Some(502));
+        assert_eq!(entry.level, LogLevel::Error);
+        assert_eq!(entry.user_agent.as_deref(), Some("curl/8.4.0"));
+        assert_eq!(entry.router.as_deref(), Some("api@docker"));
+        assert_eq!(entry.service, None);
+        assert_eq!(entry.upstream_host.as_deref(), Some("172.18.0.3:8080"));
+        assert_eq!(entry.duration, Some(Duration::from_millis(7)));
+
+        // Requests no router matched log `-` for the router and server
+        let unrouted = r#"10.0.0.1 - - [15/Jan/2024:10:30:01 +0000] "GET /favicon.ico HTTP/1.1" 404 19 "-" "-" 43 "-" "-" 0ms"#;
+        let entry = parse_traefik_line(unrouted).unwrap();
+        assert_eq!((entry.router, entry.upstream_host), (None, None));
+
+        let json = r#"{"ClientAddr":"10.0.0.2:54321","ClientHost":"10.0.0.2","DownstreamContentSize":512,"DownstreamStatus":200,"Duration":7093000,"OriginStatus":200,"RequestMethod":"POST","RequestPath":"/login","RequestProtocol":"HTTP/2.0","RouterName":"web@file","ServiceAddr":"172.18.0.4:80","ServiceName":"web@file","ServiceURL":"http://172.18.0.4:80","StartUTC":"2024-01-15T10:30:00.123456789Z","level":"info","msg":"","request_User-Agent":"Mozilla/5.0","time":"2024-01-15T10:30:00Z"}"#;
+        let entry = parse_line(json, &LogFormat::Traefik).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00.123456789Z"));
+        assert_eq!(entry.ip.to_string(), "10.0.0.2");
+        assert_eq!(entry.method, HttpMethod::Post);
+        assert_eq!(entry.protocol.as_deref(), Some("HTTP/2.0"));
+        assert_eq!(entry.bytes, Some(512));
+        assert_eq!(entry.duration, Some(Duration::from_micros(7093)));
+        assert_eq!(entry.backend_status, Some(200));
+        assert_eq!(entry.router.as_deref(), Some("web@file"));
+        assert_eq!(entry.service.as_deref(), Some("web@file"));
+        assert_eq!(entry.upstream_host.as_deref(), Some("172.18.0.4:80"));
+        assert_eq!(entry.user_agent.as_deref(), Some("Mozilla/5.0"));
+
+        assert!(matches!(parse_traefik_line(r#"{"ClientHost":"10.0.0.2"}"#), Err(ParseError::MissingField("DownstreamStatus"))));
+    }
+
+    #[test]
+    fn parses_gelf_messages() {
+        let line = r#"{"version":"1.1","host":"web1","short_message":"POST /api/orders 502","timestamp":1705314600.25,"level":6,"_http_client_ip":"10.0.0.1","_http_method":"POST","_http_url":"https://shop.example.com/api/orders?retry=1","_http_status":"502","_http_bytes":11,"_http_version":"HTTP/1.1","_http_user_agent":"curl/8.4.0","_http_duration_ms":12.5}"#;
+        let entry = parse_line(line, &LogFormat::Gelf).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00.25Z"));
+        assert_eq!(entry.ip.to_string(), "10.0.0.1");
+        assert_eq!(entry.method, HttpMethod::Post);
+        assert_eq!(entry.endpoint, "/api/orders?retry=1");
+        assert_eq!(entry.status_code, Some(502));
+        // The status outranks the informational GELF level
+        assert_eq!(entry.level, LogLevel::Error);
+        assert_eq!(entry.bytes, Some(11));
+        assert_eq!(entry.protocol.as_deref(), Some("HTTP/1.1"));
+        assert_eq!(entry.user_agent.as_deref(), Some("curl/8.4.0"));
+        assert_eq!(entry.duration, Some(Duration::from_micros(12_500)));
+
+        let warning = r#"{"version":"1.1","host":"web1","short_message":"slow","timestamp":1705314600,"level":4,"_http_remote_addr":"10.0.0.2","_http_method":"GET","_http_path":"/","_http_status_code":200}"#;
+        assert_eq!(parse_gelf_line(warning).unwrap().level, LogLevel::Warn);
+
+        let no_request = r#"{"version":"1.1","host":"web1","short_message":"started","timestamp":1705314600,"level":6}"#;
+        assert!(matches!(parse_gelf_line(no_request), Err(ParseError::MissingField("status"))));
+        assert_eq!(syslog_severity_level(3), Some(LogLevel::Error));
+        assert_eq!(syslog_severity_level(8), None);
+    }
+
+    #[test]
+    fn parses_cef_events() {
+        let line = r"CEF:0|Imperva Inc.|Secure\|Sphere|14.1|20001|SQL injection|8|rt=1705314600000 src=203.0.113.7 requestMethod=post request=https://shop.example.com/login?id=1 requestClientApplication=Mozilla/5.0 (X11; Linux x86_64) cs1Label=Rule cs1=a\=b outcome=blocked";
+        let entry = parse_line(line, &LogFormat::Cef).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00Z"));
+        assert_eq!(entry.ip.to_string(), "203.0.113.7");
+        assert_eq!(entry.method, HttpMethod::Post);
+        assert_eq!(entry.endpoint, "/login?id=1");
+        assert_eq!(entry.status_code, None);
+        assert_eq!(entry.level, LogLevel::Error);
+        assert_eq!(entry.user_agent.as_deref(), Some("Mozilla/5.0 (X11; Linux x86_64)"));
+
+        // A numeric outcome is the status; firewall events without a URL use the event name
+        let line = "<134>Jan 15 10:30:00 fw1 CEF:0|Fortinet|FortiGate|7.2|0000000013|deny|Low|end=Jan 15 2024 10:30:05 +0100 src=10.0.0.9 dst=10.0.1.1 outcome=403";
+        let entry = parse_cef_line(line).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T09:30:05Z"));
+        assert_eq!(entry.endpoint, "deny");
+        assert_eq!(entry.status_code, Some(403));
+        assert_eq!(entry.level, LogLevel::Warn);
+
+        let fields = cef_extension(r"msg=line one\nline two  cs2=C:\\temp act=allowed");
+        assert_eq!(fields["msg"], "line one\nline two");
+        assert_eq!(fields["cs2"], r"C:\temp");
+        assert_eq!(fields["act"], "allowed");
+
+        assert!(matches!(parse_cef_line("CEF:0|a|b|c|d"), Err(ParseError::InvalidFormat(_))));
+        let undated = "CEF:0|a|b|1|100|scan|5|src=10.0.0.1";
+        assert!(matches!(parse_cef_line(undated), Err(ParseError::MissingField("rt"))));
+        let entry = parse_line(
+            &format!("<134>1 2024-01-15T10:30:00Z fw1 cef - - - {}", undated),
+            &LogFormat::Syslog(Box::new(LogFormat::Cef)),
+        )
+        .unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00Z"));
+        assert_eq!(entry.level, LogLevel::Warn);
+    }
+
+    #[test]
+    fn parses_csv_rows_by_columns_or_header() {
+        let names: Vec<String> = ["timestamp", "level", "ip", "method", "endpoint", "status", "-", "User Agent"]
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+        let format = LogFormat::Csv(CsvColumns::new(b',', &names, JsonKeys::default()));
+        let line = r#"2024-01-15T10:30:00Z,warn,10.0.0.1,GET,"/search?q=a,b",404,web-1,"Mozilla/5.0 (X11; ""Linux"")""#;
+        let entry = parse_line(line, &format).unwrap();
+        assert_eq!(entry.level, LogLevel::Warn);
+        assert_eq!(entry.endpoint, "/search?q=a,b");
+        assert_eq!(entry.status_code, Some(404));
+        assert_eq!(entry.user_agent.as_deref(), Some(r#"Mozilla/5.0 (X11; "Linux")"#));
+
+        // Without --columns the header row lays out the rows that follow
+        let mut columns = CsvColumns::new(b'\t', &[], JsonKeys::default());
+        assert!(matches!(parse_line("x\ty", &LogFormat::Csv(columns.clone())), Err(ParseError::InvalidFormat(_))));
+        assert!(columns.read_header("Time\tClient IP\tHTTP Method\tURL\tStatus Code\tBytes Sent"));
+        assert!(!columns.read_header("2024-01-15T10:30:00Z\t10.0.0.2\tPOST\t/api\t201\t57"));
+        let entry = parse_line("2024-01-15T10:30:00Z\t10.0.0.2\tPOST\t/api\t201\t57", &LogFormat::Csv(columns)).unwrap();
+        assert_eq!(entry.ip.to_string(), "10.0.0.2");
+        assert_eq!(entry.endpoint, "/api");
+        assert_eq!(entry.bytes, Some(57));
+        assert_eq!(entry.level, LogLevel::Info);
+
+        assert_eq!(csv_field("Status-Code"), Some("status"));
+        assert_eq!(csv_field("endpoint"), Some("path"));
+        assert_eq!(csv_field("hostname"), None);
+    }
+
+    #[test]
+    fn parses_logfmt_with_quotes_and_aliases() {
+        let line = "ts=2024-01-15T10:30:00Z level=error ip=10.0.0.1 method=GET path=/x status=500 agent= debug";
+        let entry = parse_line(line, &LogFormat::Logfmt(JsonKeys::default())).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00Z"));
+        assert_eq!(entry.level, LogLevel::Error);
+        assert_eq!(entry.endpoint, "/x");
+        assert_eq!(entry.status_code, Some(500));
+        assert_eq!(entry.user_agent, None);
+
+        let mut keys = JsonKeys::default();
+        keys.set("user_agent", "ua").unwrap();
+        keys.set("ip", "client.ip").unwrap();
+        let line = r#"ts=1705314600 client.ip=10.0.0.2 method=post path=/y status=201 ua="curl/8.0 \"beta\"""#;
+        let entry = parse_line(line, &LogFormat::Logfmt(keys)).unwrap();
+        assert_eq!(entry.ip.to_string(), "10.0.0.2");
+        assert_eq!(entry.level, LogLevel::Info);
+        assert_eq!(entry.user_agent.as_deref(), Some("curl/8.0 \"beta\""));
+    }
+
+    #[test]
+    fn rejects_bad_logfmt_lines() {
+        let keys = JsonKeys::default();
+        assert!(matches!(parse_logfmt_line("just text", &keys), Err(ParseError::InvalidFormat(_))));
+        assert!(matches!(parse_logfmt_line(r#"ip="10.0.0.1"#, &keys), Err(ParseError::InvalidFormat(_))));
+        assert!(matches!(
+            parse_logfmt_line("ip=10.0.0.1 method=GET status=200", &keys),
+            Err(ParseError::MissingField("path"))
+        ));
+    }
+
+    #[test]
+    fn strips_rfc5424_envelope() {
+        let line = r#"<190>1 2024-01-15T10:30:00.123Z web1 nginx 4121 - [meta sequence="1" note="a \"]\" b"] 10.0.0.1 - - [15/Jan/2024:10:30:00 +0000] "GET /x HTTP/1.1" 200 12"#;
+        let message = parse_syslog(line).unwrap();
+        assert_eq!(message.hostname, Some("web1"));
+        assert_eq!(message.app_name, Some("nginx"));
+        assert!(message.message.starts_with("10.0.0.1 - - ["));
+
+        let entry = parse_line(line, &LogFormat::Syslog(Box::new(LogFormat::Clf))).unwrap();
+        assert_eq!(entry.ip.to_string(), "10.0.0.1");
+        assert_eq!(entry.status_code, Some(200));
+
+        // A JSON body without its own timestamp takes the envelope's
+        let line = r#"<14>1 2024-01-15T11:30:00+01:00 web1 api - - - {"ip":"10.0.0.2","method":"GET","path":"/","status":503}"#;
+        let entry = parse_line(line, &LogFormat::Syslog(Box::new(LogFormat::Json(JsonKeys::default())))).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00Z"));
+        assert_eq!(entry.level, LogLevel::Error);
+    }
+
+    #[test]
+    fn strips_rfc3164_envelope() {
+        let format = LogFormat::Syslog(Box::new(LogFormat::Native));
+        for line in [
+            "<34>Jan  5 10:30:00 web1 app[812]: 2024-01-05T10:30:00Z [WARN] 10.0.0.1 GET /x 404",
+            "Jan  5 10:30:00 web1 app: 2024-01-05T10:30:00Z [WARN] 10.0.0.1 GET /x 404",
+        ] {
+            let entry = parse_line(line, &format).unwrap();
+            assert_eq!(entry.level, LogLevel::Warn);
+            assert_eq!(entry.endpoint, "/x");
+        }
+        assert_eq!(parse_syslog("Jan  5 10:30:00 web1 app[812]: hi").unwrap().app_name, Some("app"));
+        assert!(matches!(parse_line(valid_line(), &format), Err(ParseError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn unwraps_docker_json_records() {
+        let line = r#"{"log":"10.0.0.1 - - [15/Jan/2024:10:30:00 +0000] \"GET /x HTTP/1.1\" 200 12\n","stream":"stdout","time":"2024-01-15T10:30:02.123456789Z"}"#;
+        let record = parse_docker_record(line).unwrap();
+        assert_eq!(record.stream.as_deref(), Some("stdout"));
+        assert!(record.log.ends_with('\n'));
+
+        let format = |use_time| LogFormat::Docker {
+            inner: Box::new(LogFormat::Clf),
+            use_time,
+        };
+        let entry = parse_line(line, &format(false)).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00Z"));
+        assert_eq!(entry.endpoint, "/x");
+        let entry = parse_line(line, &format(true)).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:02.123456789Z"));
+
+        // A JSON body without its own timestamp takes the record's
+        let line = r#"{"log":"{\"ip\":\"10.0.0.2\",\"method\":\"GET\",\"path\":\"/\",\"status\":503}\n","stream":"stderr","time":"2024-01-15T10:30:00Z"}"#;
+        let json = LogFormat::Docker {
+            inner: Box::new(LogFormat::Json(JsonKeys::default())),
+            use_time: false,
+        };
+        assert_eq!(parse_line(line, &json).unwrap().timestamp, utc("2024-01-15T10:30:00Z"));
+
+        assert!(matches!(parse_line(r#"{"stream":"stdout"}"#, &format(false)), Err(ParseError::MissingField("log"))));
+        assert!(matches!(parse_line(valid_line(), &format(false)), Err(ParseError::InvalidFormat(_))));
+        let untimed = r#"{"log":"10.0.0.1 - - [15/Jan/2024:10:30:00 +0000] \"GET /x HTTP/1.1\" 200 12"}"#;
+        assert!(parse_line(untimed, &format(false)).is_ok());
+        assert!(matches!(parse_line(untimed, &format(true)), Err(ParseError::MissingField("time"))));
+    }
+
+    #[test]
+    fn unwraps_journal_entries() {
+        let line = r#"{"__REALTIME_TIMESTAMP":"1705314602000000","_SOURCE_REALTIME_TIMESTAMP":"1705314601500000","PRIORITY":"3","_SYSTEMD_UNIT":"nginx.service","MESSAGE":"10.0.0.1 - - [15/Jan/2024:10:30:00 +0000] \"GET /x HTTP/1.1\" 200 12"}"#;
+        let record = parse_journal_record(line).unwrap();
+        assert_eq!(record.time, Some(utc("2024-01-15T10:30:01.5Z")));
+        assert_eq!(record.unit.as_deref(), Some("nginx.service"));
+
+        let format = LogFormat::Journal(Box::new(LogFormat::Clf));
+        let entry = parse_line(line, &format).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00Z"));
+        assert_eq!(entry.endpoint, "/x");
+        // PRIORITY 3 (err) outranks the level a 200 implies
+        assert_eq!(entry.level, LogLevel::Error);
+
+        // A JSON message without a timestamp takes the entry's; byte-array messages are decoded
+        let line = r#"{"__REALTIME_TIMESTAMP":"1705314600000000","PRIORITY":"6","MESSAGE":[123,34,105,112,34,58,34,49,48,46,48,46,48,46,50,34,44,34,109,101,116,104,111,100,34,58,34,71,69,84,34,44,34,112,97,116,104,34,58,34,47,34,44,34,115,116,97,116,117,115,34,58,52,48,52,125]}"#;
+        let json = LogFormat::Journal(Box::new(LogFormat::Json(JsonKeys::default())));
+        let entry = parse_line(line, &json).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00Z"));
+        assert_eq!(entry.level, LogLevel::Warn);
+
+        assert!(matches!(parse_line(r#"{"PRIORITY":"6"}"#, &format), Err(ParseError::MissingField("MESSAGE"))));
+        assert!(matches!(parse_journal_record(r#"{"MESSAGE":"x","PRIORITY":"9"}"#), Err(ParseError::InvalidField { .. })));
+    }
+
+    #[test]
+    fn parses_alb_lines() {
+        let line = r#"https 2024-01-15T10:30:00.186641Z app/my-lb/50dc6c495c0c9188 192.168.131.39:2817 10.0.0.1:80 0.001 0.250 0.000 200 200 34 366 "GET https://www.example.com:443/api/users?id=1 HTTP/1.1" "curl/8.4.0" ECDHE-RSA-AES128-GCM-SHA256 TLSv1.2 arn:aws:elasticloadbalancing:us-east-2:123456789012:targetgroup/my-targets/73e2d6bc24d8a067 "Root=1-58337262-36d228ad5d99923122bbe354" "www.example.com" "-" 0 2024-01-15T10:30:00.000000Z "forward" "-" "-" "10.0.0.1:80" "200" "-" "-""#;
+        let entry = parse_line(line, &LogFormat::Alb).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00.186641Z"));
+        assert_eq!(entry.ip.to_string(), "192.168.131.39");
+        assert_eq!(entry.endpoint, "/api/users?id=1");
+        assert_eq!(entry.bytes, Some(366));
+        assert_eq!(entry.duration, Some(Duration::from_millis(251)));
+        assert_eq!(entry.backend_status, Some(200));
+        assert_eq!(entry.protocol.as_deref(), Some("HTTP/1.1"));
+        assert_eq!(entry.user_agent.as_deref(), Some("curl/8.4.0"));
+        assert_eq!(entry.trace_id.as_deref(), Some("1-58337262-36d228ad5d99923122bbe354"));
+
+        // A target that never answered, in Classic ELB layout
+        let line = r#"2024-01-15T10:31:00.000000Z my-lb 2001:db8::1:51234 - -1 -1 -1 504 - 0 0 "POST http://www.example.com:80/checkout HTTP/1.1" "-" - -"#;
+        let entry = parse_alb_line(line).unwrap();
+        assert_eq!(entry.ip.to_string(), "2001:db8::1");
+        assert_eq!(entry.level, LogLevel::Error);
+        assert_eq!(entry.endpoint, "/checkout");
+        assert_eq!(entry.duration, None);
+        assert_eq!(entry.backend_status, None);
+        assert_eq!(entry.user_agent, None);
+        assert_eq!(entry.trace_id, None);
+    }
+
+    #[test]
+    fn parses_s3_access_lines() {
+        let line = r#"79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be amzn-s3-demo-bucket [06/Feb/2024:00:00:38 +0000] 192.0.2.3 arn:aws:iam::123456789012:user/alice 3E57427F3EXAMPLE REST.GET.OBJECT photos/2024/puppy.jpg "GET /amzn-s3-demo-bucket/photos/2024/puppy.jpg HTTP/1.1" 200 - 2662992 3462992 70 10 "-" "aws-cli/2.15.0 Python/3.11" - s9lzHYrFp76ZVxRcpX9+5cjAnEH2ROuNkd2BHfIa6UkFVdtjf5mKR3/eTPFvsiP/XV/VLi31234= SigV4 ECDHE-RSA-AES128-GCM-SHA256 AuthHeader amzn-s3-demo-bucket.s3.us-west-1.amazonaws.com TLSv1.2 - -"#;
+        let entry = parse_line(line, &LogFormat::S3Access).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-02-06T00:00:38Z"));
+        assert_eq!(entry.ip.to_string(), "192.0.2.3");
+        assert_eq!(entry.endpoint, "/photos/2024/puppy.jpg");
+        assert_eq!(entry.protocol.as_deref(), Some("HTTP/1.1"));
+        assert_eq!(entry.bytes, Some(2662992));
+        assert_eq!(entry.duration, Some(Duration::from_millis(10)));
+        assert_eq!(entry.user_agent.as_deref(), Some("aws-cli/2.15.0 Python/3.11"));
+
+        // Bucket-level operation: no key, no bytes, an error status
+        let line = r#"79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be amzn-s3-demo-bucket [06/Feb/2024:00:00:39 +0000] 192.0.2.9 - 891CE47D2EXAMPLE REST.GET.BUCKET - "GET /amzn-s3-demo-bucket?list-type=2 HTTP/1.1" 403 AccessDenied - - 5 - "-" "curl/8.4.0" -"#;
+        let entry = parse_s3_access_line(line).unwrap();
+        assert_eq!(entry.endpoint, "/amzn-s3-demo-bucket?list-type=2");
+        assert_eq!(entry.level, LogLevel::Warn);
+        assert_eq!(entry.bytes, None);
+        assert_eq!(entry.duration, None);
+    }
+
+    #[test]
+    fn parses_envoy_lines() {
+        let line = r#"[2024-01-15T10:30:00.310Z] "POST /api/v1/locations HTTP/2" 204 - 154 0 226 100 "10.0.35.28, 172.16.0.1" "nsq2http" "cc21d9b0-cf5c-432b-8c7e-98aeb7988cd2" "locations" "tcp://10.0.2.1:80""#;
+        let entry = parse_line(line, &LogFormat::Envoy).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00.310Z"));
+        assert_eq!(entry.ip.to_string(), "10.0.35.28");
+        assert_eq!(entry.method, HttpMethod::Post);
+        assert_eq!(entry.endpoint, "/api/v1/locations");
+        assert_eq!(entry.status_code, Some(204));
+        assert_eq!(entry.protocol.as_deref(), Some("HTTP/2"));
+        assert_eq!(entry.bytes, Some(0));
+        assert_eq!(entry.duration, Some(Duration::from_millis(226)));
+        assert_eq!(entry.response_flags.as_deref(), Some("-"));
+        assert_eq!(entry.upstream_host.as_deref(), Some("tcp://10.0.2.1:80"));
+        assert_eq!(entry.trace_id.as_deref(), Some("cc21d9b0-cf5c-432b-8c7e-98aeb7988cd2"));
+        assert_eq!(entry.forwarded_for.as_deref(), Some("10.0.35.28, 172.16.0.1"));
+
+        // No upstream could be reached
+        let line = r#"[2024-01-15T10:30:01.000Z] "GET /status HTTP/1.1" 503 UF,URX 0 91 30 - "192.0.2.8" "curl/8.4.0" "d1c7" "api" "-""#;
+        let entry = parse_envoy_line(line).unwrap();
+        assert_eq!(entry.level, LogLevel::Error);
+        assert_eq!(entry.response_flags.as_deref(), Some("UF,URX"));
+        assert_eq!(entry.upstream_host, None);
+        assert_eq!(entry.user_agent.as_deref(), Some("curl/8.4.0"));
+
+        let line = r#"[2024-01-15T10:30:01.500Z] "GET /cart HTTP/2" 0 DC 0 0 12 - "192.0.2.8" "-" "d1c8" "api" "10.0.2.1:80""#;
+        assert_eq!(parse_envoy_line(line).unwrap().status_code, None);
+
+        let line = r#"[2024-01-15T10:30:02.000Z] "GET / HTTP/1.1" 404 NR 0 0 0 - "-" "curl/8.4.0" "e2d8" "api" "-""#;
+        assert!(matches!(parse_envoy_line(line), Err(ParseError::MissingField("x-forwarded-for"))));
+    }
+
+    #[test]
+    fn parses_cloudfront_rows_with_declared_fields() {
+        let mut fields = W3cFields::cloudfront();
+        let row = "2024-03-12\t08:01:02\tIAD89-C1\t612\t203.0.113.7\tGET\td111111abcdef8.cloudfront.net\t/index.html\t200\t-\tMozilla/5.0%20(X11;%20Linux)\tlang=en\t-\tHit\tSOX4xwn4XV6Q4rgb7XiVGOHms_BGlTAC4KyHmureZmBNrjGdRLiNIQ==\texample.com\thttps\t23\t0.002\t-\tTLSv1.3\tTLS_AES_128_GCM_SHA256\tHit\tHTTP/2.0\t-\t-\t11040\t0.002\tHit\ttext/html\t78\t-\t-";
+        let entry = parse_line(row, &LogFormat::W3c(fields.clone())).unwrap();
+        assert_eq!(entry.timestamp, utc("2024-03-12T08:01:02Z"));
+        assert_eq!(entry.endpoint, "/index.html?lang=en");
+        assert_eq!(entry.bytes, Some(612));
+        assert_eq!(entry.duration, Some(Duration::from_millis(2)));
+        assert_eq!(entry.user_agent.as_deref(), Some("Mozilla/5.0 (X11; Linux)"));
+        assert_eq!(entry.protocol.as_deref(), Some("HTTP/2.0"));
+
+        assert!(fields.read_directive("#Version: 1.0"));
+        assert!(fields.read_directive("#Fields: date time c-ip cs-method cs-uri-stem sc-status"));
+        assert!(!fields.read_directive(row));
+        let entry = parse_w3c_line("2024-03-12\t08:02:00\t192.0.2.44\tPOST\t/api\t502", &fields).unwrap();
+        assert_eq!(entry.ip.to_string(), "192.0.2.44");
+        assert_eq!(entry.level, LogLevel::Error);
+        assert_eq!(entry.duration, None);
+
+        fields.read_directive("#Fields: date time c-ip");
+        assert!(matches!(parse_w3c_line("2024-03-12\t08:02:00\t192.0.2.44", &fields), Err(ParseError::MissingField(_))));
+    }
+
+    #[test]
+    fn parses_iis_rows() {
+        let mut fields = W3cFields::iis();
+        let row = "2024-03-12 08:01:02 10.0.0.5 GET /default.aspx id=7 443 - 203.0.113.7 Mozilla/5.0+(Windows+NT+10.0) - 200 0 0 46";
+        let entry = parse_line(row, &LogFormat::W3c(fields.clone())).unwrap();
+        assert_eq!(entry.ip.to_string(), "203.0.113.7");
+        assert_eq!(entry.endpoint, "/default.aspx?id=7");
+        assert_eq!(entry.user_agent.as_deref(), Some("Mozilla/5.0 (Windows NT 10.0)"));
+        assert_eq!(entry.duration, Some(Duration::from_millis(46)));
+
+        fields.read_directive("#Fields: date time c-ip cs-method cs-uri-stem sc-status sc-bytes time-taken");
+        let entry = parse_w3c_line("2024-03-12 08:02:00 192.0.2.44 POST /api 500 157 1874", &fields).unwrap();
+        assert_eq!(entry.bytes, Some(157));
+        assert_eq!(entry.duration, Some(Duration::from_millis(1874)));
+        assert_eq!(entry.level, LogLevel::Error);
+    }
+
+    #[test]
+    fn parses_with_custom_pattern() {
+        let pattern = CustomPattern::new(
+            r"^(?P<ip>\S+) \[(?P<timestamp>[^\]]+)\] (?P<level>\w+) (?P<method>\w+) (?P<endpoint>\S+) -> (?P<status>\d{3})$",
+        )
+        .unwrap();
+        let entry = parse_line("10.0.0.1 [2024-01-15T10:30:00Z] fatal get /x -> 502", &LogFormat::Pattern(pattern)).unwrap();
+        assert_eq!(entry.ip.to_string(), "10.0.0.1");
+        assert_eq!(entry.timestamp, utc("2024-01-15T10:30:00Z"));
+        assert_eq!(entry.level, LogLevel::Fatal);
+        assert_eq!(entry.method, HttpMethod::Get);
+        assert_eq!(entry.status_code, Some(502));
+    }
+
+    #[test]
+    fn rejects_patterns_missing_required_groups() {
+        let err = CustomPattern::new(r"(?P<timestamp>\S+) (?P<ip>\S+) (?P<method>\S+) (?P<status>\d+)").unwrap_err();
+        assert!(matches!(err, PatternError::MissingGroup("endpoint")));
+        assert!(err.to_string().contains("(?P<endpoint>...)"));
+        assert!(matches!(CustomPattern::new("(unclosed"), Err(PatternError::Regex(_))));
+    }
+
+    #[test]
+    fn rejects_bad_clf_lines() {
+        let bad_time = r#"1.2.3.4 - - [yesterday] "GET / HTTP/1.1" 200 5"#;
+        assert!(matches!(parse_clf_line(bad_time), Err(ParseError::InvalidTimestamp(_))));
+        let bad_request = r#"1.2.3.4 - - [10/Oct/2000:13:55:36 +0000] "-" 400 0"#;
+        assert!(matches!(
+            parse_clf_line(bad_request),
+            Err(ParseError::InvalidField { field: "request", .. })
+        ));
+        assert!(parse_clf_line(valid_line()).is_err());
+    }
+
+    #[test]
+    fn parses_optional_response_size() {
+        assert_eq!(parse_log_line(valid_line()).unwrap().bytes, None);
+        let entry = parse_log_line(&format!("{} 5120", valid_line())).unwrap();
+        assert_eq!(entry.bytes, Some(5120));
+        let entry = parse_log_line(&format!("{} -", valid_line())).unwrap();
+        assert_eq!(entry.bytes, None);
+        assert!(parse_log_line(&format!("{} 12k", valid_line())).is_err());
+    }
+
+    #[test]
+    fn parses_optional_response_time() {
+        let entry = parse_log_line(&format!("{} 123ms", valid_line())).unwrap();
+        assert_eq!(entry.bytes, None);
+        assert_eq!(entry.duration, Some(Duration::from_millis(123)));
+        let entry = parse_log_line(&format!("{} 5120 1.5s", valid_line())).unwrap();
+        assert_eq!(entry.bytes, Some(5120));
+        assert_eq!(entry.duration, Some(Duration::from_millis(1500)));
+        let entry = parse_log_line(&format!("{} - 850µs", valid_line())).unwrap();
+        assert_eq!(entry.duration, Some(Duration::from_micros(850)));
+        assert_eq!(parse_log_line(valid_line()).unwrap().duration, None);
+        assert!(parse_log_line(&format!("{} 12min", valid_line())).is_err());
+        assert!(parse_response_time("ms").is_err());
     }
 
     #[test]
@@ -184,7 +3231,7 @@ mod tests {
         let entry = parse_log_line(line).unwrap();
         assert_eq!(entry.level, LogLevel::Warn);
         assert_eq!(entry.method, HttpMethod::Post);
-        assert_eq!(entry.status_code, 429);
+        assert_eq!(entry.status_code, Some(429));
     }
 
     #[test]
@@ -193,7 +3240,18 @@ mod tests {
         let entry = parse_log_line(line).unwrap();
         assert_eq!(entry.level, LogLevel::Error);
         assert_eq!(entry.method, HttpMethod::Delete);
-        assert_eq!(entry.status_code, 500);
+        assert_eq!(entry.status_code, Some(500));
+    }
+
+    #[test]
+    fn parses_verbose_and_fatal_levels() {
+        for (tag, level) in [("TRACE", LogLevel::Trace), ("DEBUG", LogLevel::Debug), ("FATAL", LogLevel::Fatal)] {
+            let line = format!("2024-01-15T10:30:02Z [{}] 172.16.0.1 GET /health 200", tag);
+            assert_eq!(parse_log_line(&line).unwrap().level, level);
+        }
+        assert!(LogLevel::Fatal.is_error());
+        assert!(!LogLevel::Debug.is_error());
+        assert_eq!(parse_level_lenient("Emerg"), Some(LogLevel::Fatal));
     }
 
     #[test]
@@ -222,7 +3280,7 @@ mod tests {
 
     #[test]
     fn rejects_invalid_level() {
-        let bad_level = "2024-01-15T10:30:00Z [DEBUG] 192.168.1.1 GET /path 200";
+        let bad_level = "2024-01-15T10:30:00Z [VERBOSE] 192.168.1.1 GET /path 200";
         assert!(parse_log_line(bad_level).is_err());
     }
 
@@ -232,6 +3290,24 @@ mod tests {
         assert!(parse_log_line(bad_ip).is_err());
     }
 
+    #[test]
+    fn parses_ipv6_clients() {
+        for (raw, expected) in [
+            ("2001:db8::1", "2001:db8::1"),
+            ("[2001:db8::1]", "2001:db8::1"),
+            ("[2001:db8::1]:8443", "2001:db8::1"),
+            ("fe80::1%eth0", "fe80::1"),
+            ("[fe80::1%25eth0]:80", "fe80::1"),
+            ("::ffff:10.0.0.1", "10.0.0.1"),
+        ] {
+            let line = format!("2024-01-15T10:30:00Z [INFO] {} GET /path 200", raw);
+            assert_eq!(parse_log_line(&line).unwrap().ip.to_string(), expected, "{}", raw);
+        }
+        for bad in ["256.1.1.1", "[2001:db8::1", "[::1]:port", "2001:db8:::1"] {
+            assert!(matches!(parse_ip(bad), Err(ParseError::InvalidField { field: "ip", .. })), "{}", bad);
+        }
+    }
+
     #[test]
     fn rejects_empty_line() {
         assert!(parse_log_line("").is_err());
@@ -250,7 +3326,22 @@ mod tests {
         let line_with_spaces = "2024-01-15T10:30:00Z [INFO] 192.168.1.1 GET /api/users 200   ";
         // Trailing spaces after status: regex has \s*$ so this should still parse
         let entry = parse_log_line(line_with_spaces).expect("should handle trailing whitespace");
-        assert_eq!(entry.status_code, 200);
+        assert_eq!(entry.status_code, Some(200));
+    }
+
+    #[test]
+    fn classifies_access_like_lines() {
+        assert!(looks_like_access_line("2024-01-15 [INFO] host GET /path 200"));
+        assert!(looks_like_access_line("\"POST /login HTTP/1.1\" 500"));
+        assert!(!looks_like_access_line("Starting worker pool with 8 threads"));
+        assert!(!looks_like_access_line("    at com.example.Handler.get(Handler.java:42)"));
+        assert!(!looks_like_access_line("GET request failed"));
+
+        assert!(is_continuation_line("    at com.example.Handler.get(Handler.java:42)"));
+        assert!(is_continuation_line("\t... 12 more"));
+        assert!(is_continuation_line("Caused by: java.io.IOException: broken pipe"));
+        assert!(is_continuation_line("Traceback (most recent call last):"));
+        assert!(!is_continuation_line("2024-01-15T10:30:00Z [INFO] bad_ip GET / 200"));
     }
 
     #[test]
@@ -0,0 +1,123 @@
+use crate::parser::LogEntry;
+use std::borrow::Cow;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+/// Characters whose escapes are kept, since decoding them would change how a path
+/// or query string splits (`%2F` is not a path separator, `%26` not a parameter break)
+const RESERVED: &[u8] = b"/?#%&=+";
+
+/// Whether a decoded byte must stay escaped
+fn keep_escaped(b: u8) -> bool {
+    RESERVED.contains(&b) || b.is_ascii_control()
+}
+
+fn push_escape(out: &mut String, b: u8) {
+    out.push_str(&format!("%{:02X}", b));
+}
+
+/// Decode the `%XX` escapes of an endpoint so that `/search%20results` and
+/// `/search results` count as one, then bring the text to Unicode Normalization Form
+/// C so that a precomposed `é` (`/caf%C3%A9`) and an `e` followed by a combining
+/// accent (`/cafe%CC%81`) count as one too.
+///
+/// Escapes of reserved characters (`/`, `?`, `#`, `%`, `&`, `=`, `+`) and control
+/// characters are kept, as are escaped bytes that are not valid UTF-8; kept escapes
+/// are written with uppercase hex, so `%2f` and `%2F` agree as well. Returns the
+/// endpoint unchanged when it has nothing to decode or compose.
+pub fn normalize_path(endpoint: &str) -> Cow<'_, str> {
+    let decoded = decode_escapes(endpoint);
+    if is_nfc(&decoded) {
+        return decoded;
+    }
+    Cow::Owned(decoded.nfc().collect())
+}
+
+/// Decode the escapes [`normalize_path`] does not keep
+fn decode_escapes(endpoint: &str) -> Cow<'_, str> {
+    if !endpoint.contains('%') {
+        return Cow::Borrowed(endpoint);
+    }
+    let bytes = endpoint.as_bytes();
+    let mut out = String::with_capacity(endpoint.len());
+    let mut run = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        // Gather a run of escapes, which may spell one multi-byte character
+        while let Some(b) = escape_at(bytes, i) {
+            run.push(b);
+            i += 3;
+        }
+        for chunk in run.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                match u8::try_from(c) {
+                    Ok(b) if keep_escaped(b) => push_escape(&mut out, b),
+                    _ => out.push(c),
+                }
+            }
+            for &b in chunk.invalid() {
+                push_escape(&mut out, b);
+            }
+        }
+        run.clear();
+        // Copy up to the next escape verbatim
+        let next = endpoint[i..].find('%').map_or(bytes.len(), |at| i + at);
+        let next = if next == i { i + 1 } else { next };
+        out.push_str(&endpoint[i..next.min(bytes.len())]);
+        i = next;
+    }
+    if out == endpoint {
+        Cow::Borrowed(endpoint)
+    } else {
+        Cow::Owned(out)
+    }
+}
+
+/// The byte escaped at `i`, if a valid `%XX` escape starts there
+fn escape_at(bytes: &[u8], i: usize) -> Option<u8> {
+    if bytes.get(i) != Some(&b'%') {
+        return None;
+    }
+    let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+    u8::from_str_radix(hex, 16).ok()
+}
+
+/// Rewrite the endpoint of every entry with [`normalize_path`]
+pub fn normalize_entries(entries: &mut [LogEntry]) {
+    for entry in entries {
+        if let Cow::Owned(normalized) = normalize_path(&entry.endpoint) {
+            entry.endpoint = normalized;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_unreserved_escapes() {
+        assert_eq!(normalize_path("/search%20results"), "/search results");
+        assert_eq!(normalize_path("/caf%C3%A9"), "/café");
+        assert_eq!(normalize_path("/%e2%82%ac?q=%7Euser"), "/€?q=~user");
+        assert!(matches!(normalize_path("/plain/path"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn composes_to_nfc() {
+        assert_eq!(normalize_path("/cafe%CC%81"), normalize_path("/caf%C3%A9"));
+        assert_eq!(normalize_path("/cafe\u{301}"), "/café");
+        assert_eq!(normalize_path("/%E1%84%92%E1%85%A1%E1%86%AB"), "/\u{D55C}");
+        assert!(matches!(normalize_path("/café"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn keeps_reserved_and_invalid_escapes() {
+        assert_eq!(normalize_path("/files/a%2fb"), "/files/a%2Fb");
+        assert_eq!(normalize_path("/q?tag=a%26b&x=1%2B1"), "/q?tag=a%26b&x=1%2B1");
+        assert_eq!(normalize_path("/100%25"), "/100%25");
+        assert_eq!(normalize_path("/bad%FF%20x"), "/bad%FF x");
+        assert_eq!(normalize_path("/nul%00"), "/nul%00");
+        // Stray percent signs are not escapes
+        assert_eq!(normalize_path("/50%off%2"), "/50%off%2");
+    }
+}
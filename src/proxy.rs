@@ -0,0 +1,240 @@
+use crate::parser::{parse_ip, LogEntry};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// How the client is picked from an `X-Forwarded-For` chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientIpStrategy {
+    /// The leftmost hop that is not a private, loopback or link-local address.
+    /// Easy to spoof, since clients may send their own header, but right for
+    /// reporting on real visitors behind several layers of proxies.
+    FirstPublic,
+    /// Walking from the peer leftwards, the first hop that is not a trusted proxy.
+    /// Only addresses the trusted proxies appended are believed.
+    RightmostUntrusted,
+}
+
+/// An IP network in CIDR notation, e.g. `10.0.0.0/8` or `2001:db8::/32`; a bare
+/// address is a network of one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+/// Why a `--trusted-proxy` network did not parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetError {
+    InvalidAddress(String),
+    InvalidPrefix(String),
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::InvalidAddress(s) => write!(f, "invalid network address '{}'", s),
+            NetError::InvalidPrefix(s) => write!(f, "invalid prefix length '{}'", s),
+        }
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = NetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let addr = addr
+            .parse::<IpAddr>()
+            .map_err(|_| NetError::InvalidAddress(addr.to_string()))?
+            .to_canonical();
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p.parse::<u8>().ok().filter(|p| *p <= max).ok_or_else(|| NetError::InvalidPrefix(p.to_string()))?,
+            None => max,
+        };
+        Ok(IpNet { addr, prefix })
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl Serialize for IpNet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl IpNet {
+    /// Whether `ip` lies in this network; IPv4 networks never hold IPv6 addresses
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Whether `ip` is a private, loopback, link-local or shared (CGNAT) address, the
+/// kind proxies and load balancers sit on
+pub fn is_internal(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified() || (a == 100 && (64..128).contains(&b))
+        }
+        IpAddr::V6(v6) => v6.is_loopback() || v6.is_unspecified() || v6.is_unique_local() || v6.is_unicast_link_local(),
+    }
+}
+
+/// An address from a forwarding chain, which some proxies (such as Azure's) log
+/// with a port
+fn parse_hop(hop: &str) -> Option<IpAddr> {
+    parse_ip(hop).ok().or_else(|| {
+        let (addr, port) = hop.split_once(':')?;
+        let addr: std::net::Ipv4Addr = addr.parse().ok()?;
+        port.parse::<u16>().ok().map(|_| IpAddr::V4(addr))
+    })
+}
+
+/// Picks the real client out of each entry's `X-Forwarded-For` chain (`--trust-proxy`)
+#[derive(Debug, Clone)]
+pub struct ProxyPolicy {
+    strategy: ClientIpStrategy,
+    /// Proxies trusted by [`ClientIpStrategy::RightmostUntrusted`]; when empty,
+    /// every internal address (see [`is_internal`]) is trusted
+    trusted: Vec<IpNet>,
+}
+
+impl ProxyPolicy {
+    pub fn new(strategy: ClientIpStrategy, trusted: Vec<IpNet>) -> Self {
+        ProxyPolicy { strategy, trusted }
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        if self.trusted.is_empty() {
+            is_internal(ip)
+        } else {
+            self.trusted.iter().any(|net| net.contains(ip))
+        }
+    }
+
+    /// The client behind `peer`, the address that connected, given the
+    /// `X-Forwarded-For` value it sent.
+    ///
+    /// The peer is taken as the last hop, unless it is already the chain's first
+    /// hop (formats such as Envoy log no peer and take the first hop instead).
+    /// Hops that are not addresses (`unknown`, obfuscated identifiers) end the
+    /// search. Returns `peer` when no hop qualifies.
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: &str) -> IpAddr {
+        let mut hops: Vec<Option<IpAddr>> = forwarded_for
+            .split(',')
+            .map(str::trim)
+            .filter(|hop| !hop.is_empty())
+            .map(parse_hop)
+            .collect();
+        if hops.first() != Some(&Some(peer)) {
+            hops.push(Some(peer));
+        }
+
+        match self.strategy {
+            ClientIpStrategy::FirstPublic => hops
+                .iter()
+                .map_while(|hop| *hop)
+                .find(|ip| !is_internal(*ip))
+                .unwrap_or(peer),
+            ClientIpStrategy::RightmostUntrusted => {
+                let mut client = peer;
+                for hop in hops.iter().rev() {
+                    let Some(ip) = *hop else { break };
+                    client = ip;
+                    if !self.is_trusted(ip) {
+                        break;
+                    }
+                }
+                client
+            }
+        }
+    }
+
+    /// Replace the address of every entry that logs a forwarding chain with the
+    /// client picked from it, returning how many entries changed
+    pub fn resolve_clients(&self, entries: &mut [LogEntry]) -> usize {
+        let mut changed = 0;
+        for entry in entries {
+            let Some(chain) = entry.forwarded_for.as_deref() else { continue };
+            let client = self.client_ip(entry.ip, chain);
+            if client != entry.ip {
+                entry.ip = client;
+                changed += 1;
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_networks() {
+        let net: IpNet = "10.0.0.0/8".parse().unwrap();
+        assert!(net.contains(ip("10.20.30.40")));
+        assert!(!net.contains(ip("11.0.0.1")));
+        assert!(!net.contains(ip("::1")));
+        assert!("203.0.113.7".parse::<IpNet>().unwrap().contains(ip("::ffff:203.0.113.7")));
+        assert!("2001:db8::/32".parse::<IpNet>().unwrap().contains(ip("2001:db8:1::5")));
+        assert!("0.0.0.0/0".parse::<IpNet>().unwrap().contains(ip("8.8.8.8")));
+        assert_eq!("10.0.0.0/33".parse::<IpNet>(), Err(NetError::InvalidPrefix("33".to_string())));
+        assert!(matches!("lb.internal/8".parse::<IpNet>(), Err(NetError::InvalidAddress(_))));
+    }
+
+    #[test]
+    fn picks_the_first_public_hop() {
+        let policy = ProxyPolicy::new(ClientIpStrategy::FirstPublic, Vec::new());
+        let lb = ip("10.0.0.2");
+        assert_eq!(policy.client_ip(lb, "192.168.1.5, 203.0.113.7, 198.51.100.1"), ip("203.0.113.7"));
+        assert_eq!(policy.client_ip(lb, "10.1.1.1, 100.64.0.9"), lb);
+        assert_eq!(policy.client_ip(lb, "unknown, 203.0.113.7"), lb);
+        assert_eq!(policy.client_ip(lb, "[2001:db8::1]:443"), ip("2001:db8::1"));
+        assert_eq!(policy.client_ip(lb, "203.0.113.7:51234"), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn walks_back_over_trusted_proxies() {
+        let lb = ip("10.0.0.2");
+        let policy = ProxyPolicy::new(ClientIpStrategy::RightmostUntrusted, Vec::new());
+        // The spoofed leftmost hop is never reached
+        assert_eq!(policy.client_ip(lb, "1.2.3.4, 203.0.113.7, 10.0.0.9"), ip("203.0.113.7"));
+        assert_eq!(policy.client_ip(lb, "10.1.1.1"), ip("10.1.1.1"));
+        assert_eq!(policy.client_ip(lb, "203.0.113.7, garbage, 10.0.0.9"), ip("10.0.0.9"));
+
+        let cdn: Vec<IpNet> = vec!["198.51.100.0/24".parse().unwrap(), "10.0.0.0/8".parse().unwrap()];
+        let policy = ProxyPolicy::new(ClientIpStrategy::RightmostUntrusted, cdn);
+        assert_eq!(policy.client_ip(lb, "192.0.2.1, 203.0.113.7, 198.51.100.20"), ip("203.0.113.7"));
+        // A peer outside the trusted networks is the client itself
+        assert_eq!(policy.client_ip(ip("192.0.2.99"), "203.0.113.7"), ip("192.0.2.99"));
+        // Formats without a peer log the first hop as the address
+        assert_eq!(policy.client_ip(ip("203.0.113.7"), "203.0.113.7, 198.51.100.20"), ip("203.0.113.7"));
+    }
+}
@@ -0,0 +1,134 @@
+use crate::analyzer::RankedItem;
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+
+/// How query strings are treated when endpoints are compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueryMode {
+    /// Every distinct query string is a distinct endpoint
+    #[default]
+    Keep,
+    /// Drop the query string, counting only the path
+    Strip,
+    /// Keep parameter names but not values, so `/search?q=a` and `/search?q=b`
+    /// both become `/search?q=*`
+    Bucket,
+}
+
+/// Rewrite an endpoint's query string according to `mode`.
+///
+/// Bucketed parameter names are sorted and deduplicated, so parameter order
+/// does not split an endpoint.
+pub fn normalize_endpoint(endpoint: &str, mode: QueryMode) -> Cow<'_, str> {
+    let Some((path, query)) = endpoint.split_once('?') else {
+        return Cow::Borrowed(endpoint);
+    };
+    match mode {
+        QueryMode::Keep => Cow::Borrowed(endpoint),
+        QueryMode::Strip => Cow::Borrowed(path),
+        QueryMode::Bucket => {
+            let names = param_names(query);
+            if names.is_empty() {
+                return Cow::Borrowed(path);
+            }
+            let buckets: Vec<String> = names.into_iter().map(|name| format!("{}=*", name)).collect();
+            Cow::Owned(format!("{}?{}", path, buckets.join("&")))
+        }
+    }
+}
+
+/// Rewrite the endpoint of every entry according to `mode`
+pub fn normalize_entries(entries: &mut [LogEntry], mode: QueryMode) {
+    if mode == QueryMode::Keep {
+        return;
+    }
+    for entry in entries {
+        let normalized = normalize_endpoint(&entry.endpoint, mode);
+        if normalized != entry.endpoint.as_str() {
+            entry.endpoint = normalized.into_owned();
+        }
+    }
+}
+
+/// Distinct parameter names of a query string, e.g. `q` and `page` for `q=a&page=2`
+fn param_names(query: &str) -> BTreeSet<&str> {
+    query
+        .split('&')
+        .map(|pair| pair.split_once('=').map_or(pair, |(name, _)| name))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// How often each query parameter appears across requests
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryParamReport {
+    /// Requests whose endpoint carried a query string
+    pub requests: usize,
+    /// Parameter names by the number of requests using them; shares are of `requests`
+    pub top_params: Vec<RankedItem>,
+}
+
+/// Rank query parameter names, each counted once per request.
+///
+/// Returns `None` when no endpoint carries a query string, e.g. after stripping.
+pub fn analyze(entries: &[LogEntry], top_n: usize) -> Option<QueryParamReport> {
+    let mut requests = 0usize;
+    let mut params: HashMap<&str, usize> = HashMap::new();
+    for query in entries.iter().filter_map(|e| e.endpoint.split_once('?').map(|(_, q)| q)) {
+        requests += 1;
+        for name in param_names(query) {
+            *params.entry(name).or_insert(0) += 1;
+        }
+    }
+    if requests == 0 {
+        return None;
+    }
+
+    let mut top_params: Vec<RankedItem> = params
+        .into_iter()
+        .map(|(name, count)| RankedItem {
+            value: name.to_string(),
+            count,
+            percentage: (count as f64 / requests as f64 * 10000.0).round() / 100.0,
+            activity: None,
+        })
+        .collect();
+    top_params.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    top_params.truncate(top_n);
+
+    Some(QueryParamReport { requests, top_params })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_entry;
+
+    #[test]
+    fn strips_or_buckets_query_strings() {
+        assert_eq!(normalize_endpoint("/search?q=a", QueryMode::Keep), "/search?q=a");
+        assert_eq!(normalize_endpoint("/search?q=a", QueryMode::Strip), "/search");
+        assert_eq!(normalize_endpoint("/search?q=a&page=2", QueryMode::Bucket), "/search?page=*&q=*");
+        assert_eq!(normalize_endpoint("/search?page=3&q=b&q=c", QueryMode::Bucket), "/search?page=*&q=*");
+        assert_eq!(normalize_endpoint("/search?", QueryMode::Bucket), "/search");
+        assert_eq!(normalize_endpoint("/plain", QueryMode::Bucket), "/plain");
+    }
+
+    #[test]
+    fn ranks_parameters_once_per_request() {
+        let entries: Vec<LogEntry> = ["/search?q=a&q=b&page=2", "/search?q=c", "/list?debug", "/home"]
+            .iter()
+            .map(|endpoint| test_entry("2024-01-15T10:30:00Z", "1.1.1.1", endpoint, 200))
+            .collect();
+        let report = analyze(&entries, 10).unwrap();
+
+        assert_eq!(report.requests, 3);
+        assert_eq!(report.top_params[0].value, "q");
+        assert_eq!(report.top_params[0].count, 2);
+        assert_eq!(report.top_params[0].percentage, 66.67);
+        assert_eq!(report.top_params.len(), 3);
+        assert!(analyze(&entries[3..], 10).is_none());
+    }
+}
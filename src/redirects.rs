@@ -0,0 +1,206 @@
+use crate::parser::LogEntry;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::net::IpAddr;
+
+/// A redirect counts as followed when the same IP's next request arrives within this window
+pub const FOLLOW_WINDOW_SECS: i64 = 10;
+
+/// Chains with at least this many redirects are reported as long
+pub const LONG_CHAIN_REDIRECTS: usize = 3;
+
+const REDIRECT_STATUSES: [u16; 5] = [301, 302, 303, 307, 308];
+
+/// A followed redirect from one endpoint to the next, with how often it happened
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RedirectEdge {
+    pub source: String,
+    pub target: String,
+    pub count: usize,
+}
+
+/// A distinct sequence of endpoints visited through redirects
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RedirectChain {
+    /// Endpoints in visit order, ending at the first non-redirect (or the repeat, for loops)
+    pub path: Vec<String>,
+    pub occurrences: usize,
+    pub looped: bool,
+}
+
+impl RedirectChain {
+    pub fn redirects(&self) -> usize {
+        self.path.len() - 1
+    }
+}
+
+/// Redirect sources, their inferred targets, loops and long chains
+#[derive(Debug, Clone, Serialize)]
+pub struct RedirectReport {
+    /// 3xx redirect responses in the log
+    pub redirects: usize,
+    /// Redirects whose follow-up request could be correlated
+    pub followed: usize,
+    pub top_edges: Vec<RedirectEdge>,
+    pub loops: Vec<RedirectChain>,
+    pub long_chains: Vec<RedirectChain>,
+    /// Every correlated edge, for flow exports
+    #[serde(skip)]
+    pub edges: Vec<RedirectEdge>,
+}
+
+/// Correlate redirect responses with each client's next request to reconstruct chains.
+///
+/// Logs carry no `Location` header, so the target of a redirect is inferred as the
+/// next request from the same IP within [`FOLLOW_WINDOW_SECS`].
+pub fn analyze(entries: &[LogEntry], top_n: usize) -> RedirectReport {
+    let mut by_ip: HashMap<IpAddr, Vec<(DateTime<Utc>, &LogEntry)>> = HashMap::new();
+    for entry in entries {
+        by_ip.entry(entry.ip).or_default().push((entry.timestamp, entry));
+    }
+
+    let mut edges: HashMap<(&str, &str), usize> = HashMap::new();
+    let mut chains: HashMap<(Vec<&str>, bool), usize> = HashMap::new();
+    for requests in by_ip.values_mut() {
+        requests.sort_by_key(|r| r.0);
+        let mut i = 0;
+        while i < requests.len() {
+            if !is_redirect(requests[i].1) {
+                i += 1;
+                continue;
+            }
+            let mut path = vec![requests[i].1.endpoint.as_str()];
+            let mut seen: HashSet<&str> = path.iter().copied().collect();
+            let mut looped = false;
+            let mut j = i;
+            while j + 1 < requests.len()
+                && is_redirect(requests[j].1)
+                && (requests[j + 1].0 - requests[j].0).num_seconds() <= FOLLOW_WINDOW_SECS
+            {
+                let (from, to) = (requests[j].1.endpoint.as_str(), requests[j + 1].1.endpoint.as_str());
+                *edges.entry((from, to)).or_insert(0) += 1;
+                path.push(to);
+                j += 1;
+                if !seen.insert(to) {
+                    looped = true;
+                    break;
+                }
+            }
+            if path.len() > 1 {
+                *chains.entry((path, looped)).or_insert(0) += 1;
+            }
+            i = j + 1;
+        }
+    }
+
+    let mut edges: Vec<RedirectEdge> = edges
+        .into_iter()
+        .map(|((source, target), count)| RedirectEdge {
+            source: source.to_string(),
+            target: target.to_string(),
+            count,
+        })
+        .collect();
+    edges.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.source.cmp(&b.source))
+            .then_with(|| a.target.cmp(&b.target))
+    });
+
+    let mut chains: Vec<RedirectChain> = chains
+        .into_iter()
+        .map(|((path, looped), occurrences)| RedirectChain {
+            path: path.into_iter().map(String::from).collect(),
+            occurrences,
+            looped,
+        })
+        .collect();
+    chains.sort_by(|a, b| {
+        b.occurrences
+            .cmp(&a.occurrences)
+            .then_with(|| b.path.len().cmp(&a.path.len()))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    let (loops, rest): (Vec<_>, Vec<_>) = chains.into_iter().partition(|c| c.looped);
+    let long_chains = rest
+        .into_iter()
+        .filter(|c| c.redirects() >= LONG_CHAIN_REDIRECTS)
+        .take(top_n)
+        .collect();
+
+    RedirectReport {
+        redirects: entries.iter().filter(|e| is_redirect(e)).count(),
+        followed: edges.iter().map(|e| e.count).sum(),
+        top_edges: edges.iter().take(top_n).cloned().collect(),
+        loops: loops.into_iter().take(top_n).collect(),
+        long_chains,
+        edges,
+    }
+}
+
+fn is_redirect(entry: &LogEntry) -> bool {
+    entry.status_code.is_some_and(|code| REDIRECT_STATUSES.contains(&code))
+}
+
+/// Write redirect edges as `source,target,value` CSV, the shape sankey tools expect
+pub fn write_flow_csv<W: Write>(mut out: W, edges: &[RedirectEdge]) -> io::Result<()> {
+    writeln!(out, "source,target,value")?;
+    for edge in edges {
+        writeln!(out, "{},{},{}", csv_field(&edge.source), csv_field(&edge.target), edge.count)?;
+    }
+    Ok(())
+}
+
+/// Quote a field if it contains a delimiter or quote (query strings may)
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_entry;
+
+    fn entry(secs: u32, ip: &str, endpoint: &str, status: u16) -> LogEntry {
+        test_entry(&format!("2024-01-15T10:00:{:02}Z", secs), ip, endpoint, status)
+    }
+
+    #[test]
+    fn reconstructs_chains_and_loops() {
+        let entries = vec![
+            // long chain: /old → /new → /login → /home
+            entry(0, "1.1.1.1", "/old", 301),
+            entry(1, "1.1.1.1", "/new", 302),
+            entry(2, "1.1.1.1", "/login", 302),
+            entry(3, "1.1.1.1", "/home", 200),
+            // loop: /a → /b → /a
+            entry(0, "2.2.2.2", "/a", 302),
+            entry(1, "2.2.2.2", "/b", 302),
+            entry(2, "2.2.2.2", "/a", 302),
+            // redirect never followed within the window
+            entry(0, "3.3.3.3", "/old", 301),
+            entry(40, "3.3.3.3", "/elsewhere", 200),
+        ];
+        let report = analyze(&entries, 10);
+
+        assert_eq!(report.redirects, 7);
+        assert_eq!(report.followed, 5);
+        assert_eq!(report.long_chains.len(), 1);
+        assert_eq!(report.long_chains[0].path, ["/old", "/new", "/login", "/home"]);
+        assert_eq!(report.loops.len(), 1);
+        assert_eq!(report.loops[0].path, ["/a", "/b", "/a"]);
+
+        let mut csv = Vec::new();
+        write_flow_csv(&mut csv, &report.edges).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("source,target,value\n"));
+        assert!(csv.contains("/old,/new,1\n"));
+    }
+}
@@ -0,0 +1,342 @@
+use crate::analyzer::RankedItem;
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Search engines, by the domain label that names them: a referrer from
+/// `www.google.co.uk` or `search.yahoo.com` is a search
+const SEARCH_ENGINES: &[(&str, &str)] = &[
+    ("google", "Google"),
+    ("bing", "Bing"),
+    ("duckduckgo", "DuckDuckGo"),
+    ("yahoo", "Yahoo"),
+    ("yandex", "Yandex"),
+    ("baidu", "Baidu"),
+    ("ecosia", "Ecosia"),
+    ("brave", "Brave"),
+    ("startpage", "Startpage"),
+    ("qwant", "Qwant"),
+    ("naver", "Naver"),
+    ("seznam", "Seznam"),
+];
+
+/// Well-known referrer spam domains, which fake referrals to get their name into
+/// analytics reports; subdomains match. `--referrer-spam` adds to the list.
+const SPAM_DOMAINS: &[&str] = &[
+    "7makemoneyonline.com",
+    "best-seo-offer.com",
+    "blackhatworth.com",
+    "buttons-for-website.com",
+    "buttons-for-your-website.com",
+    "buy-cheap-online.info",
+    "darodar.com",
+    "floating-share-buttons.com",
+    "free-share-buttons.com",
+    "get-free-traffic-now.com",
+    "hulfingtonpost.com",
+    "ilovevitaly.com",
+    "kambasoft.com",
+    "o-o-6-o-o.com",
+    "priceg.com",
+    "ranksonic.info",
+    "savetubevideo.com",
+    "semalt.com",
+    "simple-share-buttons.com",
+    "social-buttons.com",
+    "trafficmonetize.org",
+    "webmonetizer.net",
+];
+
+/// Where a request came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReferrerClass {
+    /// No referrer: typed in, bookmarked, or sent by a client that drops the header
+    Direct,
+    /// A page of the site itself
+    Internal,
+    /// A search engine's results
+    Search,
+    /// Any other site
+    External,
+    /// A known referrer spam domain
+    Spam,
+}
+
+impl ReferrerClass {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReferrerClass::Direct => "direct",
+            ReferrerClass::Internal => "internal",
+            ReferrerClass::Search => "search",
+            ReferrerClass::External => "external",
+            ReferrerClass::Spam => "spam",
+        }
+    }
+}
+
+/// A referrer URL and how many requests it sent
+#[derive(Debug, Clone, Serialize)]
+pub struct ReferrerItem {
+    pub value: String,
+    pub class: ReferrerClass,
+    pub count: usize,
+    pub percentage: f64,
+}
+
+/// A site sending traffic: a search engine by name, or another site by host
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceItem {
+    pub source: String,
+    /// `search` or `external`
+    pub class: ReferrerClass,
+    pub count: usize,
+    pub percentage: f64,
+}
+
+/// Where requests came from, split into direct, internal, search, external and spam
+#[derive(Debug, Clone, Serialize)]
+pub struct ReferrerReport {
+    /// Requests whose line carried a referrer
+    pub requests: usize,
+    /// Requests without one
+    pub direct: usize,
+    pub internal: usize,
+    pub search: usize,
+    /// Requests from other sites, spam aside
+    pub external: usize,
+    pub spam: usize,
+    pub top_referrers: Vec<ReferrerItem>,
+    /// Search engines and external hosts by the traffic they sent
+    pub top_sources: Vec<SourceItem>,
+    /// Spam domains that showed up, by host
+    pub spam_hosts: Vec<RankedItem>,
+}
+
+/// The lowercase host of an absolute referrer URL; `None` for relative ones
+fn host(referrer: &str) -> Option<String> {
+    let (_, rest) = referrer.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let host = match authority.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(v6),
+        None => authority.split(':').next().unwrap_or(authority),
+    };
+    Some(host.to_ascii_lowercase())
+}
+
+/// Whether a referrer points at the site itself: a relative URL, or a host that is
+/// one of `site_hosts` or a subdomain of one
+pub fn is_internal(referrer: &str, site_hosts: &[String]) -> bool {
+    match host(referrer) {
+        None => referrer.starts_with('/'),
+        Some(host) => site_hosts.iter().any(|site| is_within(&host, site)),
+    }
+}
+
+/// Whether `host` is `domain` or a subdomain of it
+fn is_within(host: &str, domain: &str) -> bool {
+    let domain = domain.to_ascii_lowercase();
+    host == domain || host.strip_suffix(domain.as_str()).is_some_and(|sub| sub.ends_with('.'))
+}
+
+/// Whether `host` is on the built-in spam list or `extra`
+fn is_spam(host: &str, extra: &[String]) -> bool {
+    SPAM_DOMAINS
+        .iter()
+        .copied()
+        .chain(extra.iter().map(String::as_str))
+        .any(|domain| is_within(host, domain))
+}
+
+/// The search engine a host belongs to: its name label followed by a country or
+/// generic suffix of one or two labels (`google.com`, `google.co.uk`), after at most
+/// a `www`, `m`, `search` or two-letter country label
+pub fn search_engine(host: &str) -> Option<&'static str> {
+    let labels: Vec<&str> = host.split('.').collect();
+    let at = labels
+        .iter()
+        .position(|label| SEARCH_ENGINES.iter().any(|(name, _)| label == name))?;
+    let (prefix, suffix) = (&labels[..at], &labels[at + 1..]);
+    let plain_prefix = prefix
+        .iter()
+        .all(|label| matches!(*label, "www" | "m" | "search") || label.len() == 2);
+    let plain_suffix = match suffix {
+        [_] => true,
+        [second, _] => second.len() <= 3,
+        _ => false,
+    };
+    if !(plain_prefix && plain_suffix) {
+        return None;
+    }
+    SEARCH_ENGINES.iter().find(|(name, _)| *name == labels[at]).map(|(_, display)| *display)
+}
+
+/// Read a referrer spam list: one domain per line, `#` starts a comment
+pub fn load_spam_domains(path: &Path) -> io::Result<Vec<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .map(|domain| domain.trim_start_matches("*.").to_ascii_lowercase())
+        .collect())
+}
+
+/// Rank referrers and split requests into direct, internal, search, external and
+/// spam, then rank the search engines and external sites sending traffic.
+///
+/// Without `site_hosts` only relative referrers count as internal. `spam_domains`
+/// adds to the built-in spam list. Returns `None` when no entry carries a referrer.
+pub fn analyze(entries: &[LogEntry], site_hosts: &[String], spam_domains: &[String], top_n: usize) -> Option<ReferrerReport> {
+    let mut referrers: HashMap<&str, usize> = HashMap::new();
+    for referrer in entries.iter().filter_map(|e| e.referrer.as_deref()) {
+        *referrers.entry(referrer).or_insert(0) += 1;
+    }
+    let requests: usize = referrers.values().sum();
+    if requests == 0 {
+        return None;
+    }
+    let pct = |n: usize| (n as f64 / requests as f64 * 10000.0).round() / 100.0;
+
+    let mut classes: HashMap<ReferrerClass, usize> = HashMap::new();
+    let mut sources: HashMap<(String, ReferrerClass), usize> = HashMap::new();
+    let mut spam_hosts: HashMap<String, usize> = HashMap::new();
+    let mut ranked: Vec<ReferrerItem> = referrers
+        .into_iter()
+        .map(|(referrer, count)| {
+            let class = match host(referrer) {
+                _ if is_internal(referrer, site_hosts) => ReferrerClass::Internal,
+                Some(host) if is_spam(&host, spam_domains) => {
+                    *spam_hosts.entry(host).or_insert(0) += count;
+                    ReferrerClass::Spam
+                }
+                Some(host) => match search_engine(&host) {
+                    Some(engine) => {
+                        *sources.entry((engine.to_string(), ReferrerClass::Search)).or_insert(0) += count;
+                        ReferrerClass::Search
+                    }
+                    None => {
+                        *sources.entry((host, ReferrerClass::External)).or_insert(0) += count;
+                        ReferrerClass::External
+                    }
+                },
+                // Neither relative nor absolute, such as `android-app://…` without a host
+                None => ReferrerClass::External,
+            };
+            *classes.entry(class).or_insert(0) += count;
+            ReferrerItem {
+                value: referrer.to_string(),
+                class,
+                count,
+                percentage: pct(count),
+            }
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    ranked.truncate(top_n);
+
+    let mut top_sources: Vec<SourceItem> = sources
+        .into_iter()
+        .map(|((source, class), count)| SourceItem {
+            source,
+            class,
+            count,
+            percentage: pct(count),
+        })
+        .collect();
+    top_sources.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.source.cmp(&b.source)));
+    top_sources.truncate(top_n);
+
+    let mut spam_hosts: Vec<RankedItem> = spam_hosts
+        .into_iter()
+        .map(|(host, count)| RankedItem {
+            value: host,
+            count,
+            percentage: pct(count),
+            activity: None,
+        })
+        .collect();
+    spam_hosts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    spam_hosts.truncate(top_n);
+
+    let class = |class: ReferrerClass| classes.get(&class).copied().unwrap_or(0);
+    Some(ReferrerReport {
+        requests,
+        direct: entries.len() - requests,
+        internal: class(ReferrerClass::Internal),
+        search: class(ReferrerClass::Search),
+        external: class(ReferrerClass::External),
+        spam: class(ReferrerClass::Spam),
+        top_referrers: ranked,
+        top_sources,
+        spam_hosts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_line, LogFormat};
+
+    #[test]
+    fn matches_site_hosts_and_subdomains() {
+        let site = ["example.com".to_string()];
+        assert!(is_internal("https://example.com/cart", &site));
+        assert!(is_internal("https://shop.Example.com:8443/", &site));
+        assert!(is_internal("/relative/page", &[]));
+        assert!(!is_internal("https://notexample.com/", &site));
+        assert!(!is_internal("https://example.com.evil.io/", &site));
+        assert!(!is_internal("https://example.com/", &[]));
+    }
+
+    #[test]
+    fn recognizes_search_engines() {
+        for (host, engine) in [
+            ("www.google.com", "Google"),
+            ("www.google.co.uk", "Google"),
+            ("search.yahoo.com", "Yahoo"),
+            ("uk.search.yahoo.com", "Yahoo"),
+            ("duckduckgo.com", "DuckDuckGo"),
+            ("search.brave.com", "Brave"),
+        ] {
+            assert_eq!(search_engine(host), Some(engine), "{}", host);
+        }
+        for host in ["mail.google.com", "googleblog.example.com", "bing.example.co.uk", "example.com"] {
+            assert_eq!(search_engine(host), None, "{}", host);
+        }
+    }
+
+    #[test]
+    fn splits_internal_and_external_referrers() {
+        let line = |referrer: &str| {
+            let line = format!(r#"10.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET / HTTP/1.1" 200 5 "{}" "-""#, referrer);
+            parse_line(&line, &LogFormat::NginxCombined).unwrap()
+        };
+        let entries = vec![
+            line("https://www.google.com/search?q=shoes"),
+            line("https://www.google.de/"),
+            line("https://news.ycombinator.com/item?id=1"),
+            line("https://shop.example.com/cart"),
+            line("http://semalt.com/crawler.php?u=example.com"),
+            line("https://blog.ranking-tricks.net/"),
+            line("-"),
+        ];
+        let spam = ["ranking-tricks.net".to_string()];
+        let report = analyze(&entries, &["example.com".to_string()], &spam, 10).unwrap();
+
+        assert_eq!((report.requests, report.direct), (6, 1));
+        assert_eq!((report.internal, report.search, report.external, report.spam), (1, 2, 1, 2));
+        let sources: Vec<_> = report.top_sources.iter().map(|s| (s.source.as_str(), s.class, s.count)).collect();
+        assert_eq!(sources, [("Google", ReferrerClass::Search, 2), ("news.ycombinator.com", ReferrerClass::External, 1)]);
+        let spam_hosts: Vec<_> = report.spam_hosts.iter().map(|h| h.value.as_str()).collect();
+        assert_eq!(spam_hosts, ["blog.ranking-tricks.net", "semalt.com"]);
+        assert!(report
+            .top_referrers
+            .iter()
+            .any(|r| r.class == ReferrerClass::Internal && r.value == "https://shop.example.com/cart"));
+        assert!(analyze(&entries[6..], &[], &[], 10).is_none());
+    }
+}
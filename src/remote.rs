@@ -0,0 +1,148 @@
+use crate::inputs::{self, LogFile};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use std::fmt;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::time::Duration;
+
+/// Environment variable holding a bearer token, so it need not appear on the command line
+pub const TOKEN_ENV_VAR: &str = "LOG_ANALYZER_HTTP_TOKEN";
+
+/// How long the server may take to accept the connection, and then to start answering.
+/// The body itself streams for as long as it takes.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Credentials sent with the request for a log URL
+#[derive(Clone, PartialEq, Eq)]
+pub enum HttpAuth {
+    /// HTTP basic authentication
+    Basic { user: String, password: String },
+    /// An `Authorization: Bearer` token
+    Bearer(String),
+}
+
+impl HttpAuth {
+    /// Basic credentials from `USER:PASSWORD`; without a `:`, the password is empty
+    pub fn basic(credentials: &str) -> Self {
+        let (user, password) = credentials.split_once(':').unwrap_or((credentials, ""));
+        HttpAuth::Basic {
+            user: user.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    /// The value of the `Authorization` header
+    fn header(&self) -> String {
+        match self {
+            HttpAuth::Basic { user, password } => {
+                format!("Basic {}", BASE64_STANDARD.encode(format!("{}:{}", user, password)))
+            }
+            HttpAuth::Bearer(token) => format!("Bearer {}", token),
+        }
+    }
+}
+
+// Credentials stay out of debug output
+impl fmt::Debug for HttpAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpAuth::Basic { user, .. } => write!(f, "Basic {{ user: {:?}, password: \"***\" }}", user),
+            HttpAuth::Bearer(_) => write!(f, "Bearer(\"***\")"),
+        }
+    }
+}
+
+/// Fetch `url` and read its body as it arrives, decompressing it like a local file
+/// (by magic bytes, or the extension of the URL's path).
+///
+/// A response other than 2xx is an error; redirects are followed.
+pub fn open(url: &str, auth: Option<&HttpAuth>) -> io::Result<LogFile> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_connect(Some(RESPONSE_TIMEOUT))
+        .timeout_recv_response(Some(RESPONSE_TIMEOUT))
+        .user_agent(concat!("log_analyzer/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .into();
+    let mut request = agent.get(url);
+    if let Some(auth) = auth {
+        request = request.header("Authorization", auth.header());
+    }
+    let response = request.call().map_err(|e| match e {
+        ureq::Error::StatusCode(code) => io::Error::other(format!("server answered HTTP {}", code)),
+        other => io::Error::other(other),
+    })?;
+    let body = BufReader::new(response.into_body().into_reader());
+    inputs::open_stream(body, Path::new(url_path(url)))
+}
+
+/// The path of `url`, without its query string or fragment
+fn url_path(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn builds_authorization_headers() {
+        assert_eq!(HttpAuth::basic("ops:s3cret").header(), "Basic b3BzOnMzY3JldA==");
+        assert_eq!(HttpAuth::basic("ops").header(), "Basic b3BzOg==");
+        assert_eq!(HttpAuth::Bearer("abc".into()).header(), "Bearer abc");
+        assert!(!format!("{:?}", HttpAuth::basic("ops:s3cret")).contains("s3cret"));
+        assert_eq!(url_path("https://logs/app.log.gz?sig=x#top"), "https://logs/app.log.gz");
+    }
+
+    /// Answer one request on a local port with `status` and `body`, returning the
+    /// URL and a handle yielding the request's headers
+    fn serve_once(status: &'static str, body: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/logs/access.log", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                request.push_str(&line);
+            }
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+            request
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn streams_the_body_with_credentials() {
+        let (url, server) = serve_once("200 OK", "first line\nsecond line\n");
+        let mut body = String::new();
+        let mut file = open(&url, Some(&HttpAuth::Bearer("t0ken".into()))).unwrap();
+        assert!(matches!(file, LogFile::Stream(_)));
+        file.read_to_string(&mut body).unwrap();
+        assert_eq!(body, "first line\nsecond line\n");
+        let request = server.join().unwrap();
+        assert!(request.starts_with("GET /logs/access.log "));
+        assert!(request.to_ascii_lowercase().contains("authorization: bearer t0ken"));
+    }
+
+    #[test]
+    fn error_statuses_fail_to_open() {
+        let (url, server) = serve_once("401 Unauthorized", "");
+        let error = open(&url, None).err().unwrap();
+        assert_eq!(error.to_string(), "server answered HTTP 401");
+        server.join().unwrap();
+    }
+}
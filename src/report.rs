@@ -1,8 +1,40 @@
-use crate::analyzer::AnalysisStats;
+use crate::agents::{AgentClass, UserAgentReport};
+use crate::analyzer::{AnalysisStats, FlagBasis, RankedItem, SampleSummary, StatusClassRollup};
+use crate::anomalies::{AnomalyReport, BaselineSource, Metric};
+use crate::apdex::{ApdexReport, ApdexScore};
+use crate::asn::AsnReport;
+use crate::attacks::AttackReport;
+use crate::bandwidth::{BandwidthItem, BandwidthReport};
+use crate::bots::BotReport;
+use crate::browsers::{BrowserReport, FamilyShare};
+use crate::bursts::BurstReport;
+use crate::cohorts::{CohortComparison, CohortProfile};
+use crate::cost::{CostAttribution, CostLine};
+use crate::endpoint_errors::EndpointErrorReport;
+use crate::forecast::{SeriesForecast, TrafficForecast};
+use crate::geo::{CountryReport, TravelReport};
+use crate::inputs::FileSummary;
+use crate::query::QueryParamReport;
+use crate::redirects::{RedirectChain, RedirectReport};
+use crate::referrers::{ReferrerClass, ReferrerReport};
+use crate::response_flags::ResponseFlagReport;
+use crate::routers::RouterReport;
+use crate::robots::RobotsCompliance;
+use crate::scanners::ScannerReport;
+use crate::sessions::SessionReport;
+use crate::slo::SloReport;
+use crate::parser::LogLevel;
+use crate::routes::{EndpointCoverage, SpecCorrelation};
+use crate::timeline::{Peaks, RateStats, Timeline};
+use crate::traces::TraceReport;
+use crate::upstream::{EndpointLatency, UpstreamReport};
+use crate::units;
+use crate::users::UserReport;
 use colored::Colorize;
-use serde_json;
+use std::collections::HashMap;
 use std::io;
-use std::path::PathBuf;
+use std::path::Path;
+use std::time::Duration;
 
 const SEPARATOR: &str =
     "════════════════════════════════════════════════════════════════════";
@@ -10,7 +42,7 @@ const THIN_SEP: &str =
     "────────────────────────────────────────────────────────────────────";
 
 /// Print a fully formatted analysis report to stdout
-pub fn print_report(stats: &AnalysisStats, malformed: usize, source_file: &PathBuf) {
+pub fn print_report(stats: &AnalysisStats, malformed: usize, source_file: &Path) {
     println!("\n{}", SEPARATOR.cyan().bold());
     println!(
         "{}",
@@ -18,6 +50,20 @@ pub fn print_report(stats: &AnalysisStats, malformed: usize, source_file: &PathB
     );
     println!("{}", SEPARATOR.cyan().bold());
     println!("  Source : {}", source_file.display().to_string().yellow());
+    if let Some(detection) = &stats.detected_format {
+        println!(
+            "  Format : {} (detected, {}/{} sampled lines matched)",
+            detection.format.yellow(),
+            detection.matched,
+            detection.sampled
+        );
+    }
+    if let Some(reason) = &stats.incomplete {
+        println!("  {}", format!("⚠  PARTIAL RESULTS: {}", reason).yellow().bold());
+    }
+    if let Some(sample) = &stats.sample {
+        println!("  {}", format!("≈  SAMPLED: {}; counts are estimates", sample_note(sample)).yellow());
+    }
     println!();
 
     // ── Overview ──────────────────────────────────────────────────────────────
@@ -29,6 +75,26 @@ pub fn print_report(stats: &AnalysisStats, malformed: usize, source_file: &PathB
         stats.total_entries.to_string().green().bold(),
         width = total_width
     );
+    let estimated = if stats.cardinality_error.is_some() { "≈" } else { "" };
+    if let Some(unique_ips) = stats.unique_ips {
+        println!(
+            "  {:<28} {:>width$}",
+            "Unique client IPs:",
+            format!("{}{}", estimated, unique_ips),
+            width = total_width
+        );
+    }
+    if let Some(unique_endpoints) = stats.unique_endpoints {
+        println!(
+            "  {:<28} {:>width$}",
+            "Unique endpoints:",
+            format!("{}{}", estimated, unique_endpoints),
+            width = total_width
+        );
+    }
+    if let Some(error) = stats.cardinality_error {
+        println!("  {}", format!("(HyperLogLog estimates, ±{:.2}% standard error)", error * 100.0).dimmed());
+    }
     println!(
         "  {:<28} {:>width$}",
         "Malformed / skipped lines:",
@@ -39,18 +105,65 @@ pub fn print_report(stats: &AnalysisStats, malformed: usize, source_file: &PathB
         },
         width = total_width
     );
+    if let Some(non_access) = stats.non_access_lines {
+        println!(
+            "  {:<28} {:>width$}",
+            "Non-access lines (mixed):",
+            non_access.to_string().dimmed(),
+            width = total_width
+        );
+    }
+    if let Some(continuation) = stats.continuation_lines {
+        println!(
+            "  {:<28} {:>width$}",
+            "Continuation lines:",
+            continuation.to_string().dimmed(),
+            width = total_width
+        );
+    }
+    if let Some(partial) = stats.partial_entries {
+        println!(
+            "  {:<28} {:>width$}",
+            "Partial entries (lenient):",
+            partial.to_string().dimmed(),
+            width = total_width
+        );
+    }
+    if let Some(recovered) = stats.recovered_entries {
+        println!(
+            "  {:<28} {:>width$}",
+            "Recovered lines (fuzzy):",
+            recovered.to_string().dimmed(),
+            width = total_width
+        );
+    }
+    if let Some(forwarded) = stats.forwarded_clients {
+        println!(
+            "  {:<28} {:>width$}",
+            "Clients via X-Forwarded-For:",
+            forwarded.to_string().dimmed(),
+            width = total_width
+        );
+    }
     println!();
 
+    if let Some(files) = &stats.files {
+        print_files(files);
+        println!();
+    }
+
     // ── Log Level Breakdown ───────────────────────────────────────────────────
     section_header("LOG LEVEL BREAKDOWN");
-    for level_name in &["INFO", "WARN", "ERROR"] {
-        if let Some(lc) = stats.level_counts.get(*level_name) {
+    for level in &LogLevel::ALL {
+        let level_name = level.to_string();
+        if let Some(lc) = stats.level_counts.get(&level_name) {
             let bar = mini_bar(lc.percentage, 30);
-            let colored_level = match *level_name {
-                "INFO" => level_name.green(),
-                "WARN" => level_name.yellow(),
-                "ERROR" => level_name.red(),
-                _ => level_name.normal(),
+            let colored_level = match level {
+                LogLevel::Trace | LogLevel::Debug => level_name.dimmed(),
+                LogLevel::Info => level_name.green(),
+                LogLevel::Warn => level_name.yellow(),
+                LogLevel::Error => level_name.red(),
+                LogLevel::Fatal => level_name.red().bold(),
             };
             println!(
                 "  {:<6} {:>6}  ({:5.1}%)  {}",
@@ -77,22 +190,51 @@ pub fn print_report(stats: &AnalysisStats, malformed: usize, source_file: &PathB
             colored_code, count, pct, bar
         );
     }
+    let without_status = stats.total_entries - stats.status_code_distribution.values().sum::<usize>();
+    if without_status > 0 {
+        println!("  {}", format!("{} entries without a status code", without_status).dimmed());
+    }
     println!();
 
+    if let Some(classes) = &stats.status_classes {
+        print_status_classes(classes);
+        println!();
+    }
+
+    if !stats.method_distribution.is_empty() {
+        print_methods(&stats.method_distribution);
+        println!();
+    }
+
+    if let Some(protocols) = &stats.protocol_distribution {
+        print_protocols(protocols, stats.total_entries);
+        println!();
+    }
+
     // ── Top N IPs ─────────────────────────────────────────────────────────────
     section_header(&format!("TOP {} IP ADDRESSES BY REQUEST COUNT", stats.top_n));
     if stats.top_ips.is_empty() {
         println!("  (no data)");
     } else {
-        println!("  {:<3}  {:<17}  {:>8}  {:>8}", "#", "IP Address", "Requests", "Share");
+        let timed = has_activity(&stats.top_ips);
+        let w = ip_width(stats.top_ips.iter().map(|item| item.value.as_str()));
+        println!(
+            "  {:<3}  {:<w$}  {:>8}  {:>8}{}",
+            "#",
+            "IP Address",
+            "Requests",
+            "Share",
+            if timed { activity_header() } else { String::new() }
+        );
         println!("  {}", &THIN_SEP[..54]);
         for (i, item) in stats.top_ips.iter().enumerate() {
             println!(
-                "  {:<3}  {:<17}  {:>8}  {:>7.2}%",
+                "  {:<3}  {:<w$}  {:>8}  {:>7.2}%{}",
                 (i + 1).to_string().dimmed(),
                 item.value.cyan(),
                 item.count,
-                item.percentage
+                item.percentage,
+                if timed { activity_columns(item) } else { String::new() }
             );
         }
     }
@@ -103,29 +245,52 @@ pub fn print_report(stats: &AnalysisStats, malformed: usize, source_file: &PathB
     if stats.top_endpoints.is_empty() {
         println!("  (no data)");
     } else {
-        println!("  {:<3}  {:<40}  {:>8}  {:>8}", "#", "Endpoint", "Requests", "Share");
+        let timed = has_activity(&stats.top_endpoints);
+        println!(
+            "  {:<3}  {:<40}  {:>8}  {:>8}{}",
+            "#",
+            "Endpoint",
+            "Requests",
+            "Share",
+            if timed { activity_header() } else { String::new() }
+        );
         println!("  {}", &THIN_SEP[..66]);
         for (i, item) in stats.top_endpoints.iter().enumerate() {
-            let ep = if item.value.len() > 40 {
-                format!("{}…", &item.value[..39])
-            } else {
-                item.value.clone()
-            };
+            let ep = truncate(&item.value, 40);
             println!(
-                "  {:<3}  {:<40}  {:>8}  {:>7.2}%",
+                "  {:<3}  {:<40}  {:>8}  {:>7.2}%{}",
                 (i + 1).to_string().dimmed(),
                 ep.cyan(),
                 item.count,
-                item.percentage
+                item.percentage,
+                if timed { activity_columns(item) } else { String::new() }
             );
         }
     }
     println!();
 
+    if let Some(query_params) = &stats.query_params {
+        print_query_params(query_params);
+        println!();
+    }
+
+    if let Some(endpoint_errors) = &stats.endpoint_errors {
+        print_status_hotspots("4xx", endpoint_errors.client_errors, &endpoint_errors.top_4xx, stats.top_n);
+        println!();
+        print_status_hotspots("5xx", endpoint_errors.server_errors, &endpoint_errors.top_5xx, stats.top_n);
+        println!();
+        print_endpoint_errors(endpoint_errors);
+        println!();
+    }
+
     // ── Flagged IPs ───────────────────────────────────────────────────────────
+    let (errors_label, errors_column) = match stats.flag_on {
+        FlagBasis::Level => ("ERROR COUNT", "Errors"),
+        FlagBasis::ServerErrors => ("5xx COUNT", "5xx"),
+    };
     section_header(&format!(
-        "FLAGGED IPs — ERROR COUNT > {}",
-        stats.error_threshold
+        "FLAGGED IPs — {} > {}",
+        errors_label, stats.error_threshold
     ));
     if stats.flagged_ips.is_empty() {
         println!("  {} No IPs exceeded the error threshold.", "✓".green());
@@ -134,51 +299,1805 @@ pub fn print_report(stats: &AnalysisStats, malformed: usize, source_file: &PathB
             "  {} IPs flagged!\n",
             stats.flagged_ips.len().to_string().red().bold()
         );
+        let w = ip_width(stats.flagged_ips.iter().map(|item| item.ip.as_str()));
+        let networks = stats.networks.is_some();
         println!(
-            "  {:<3}  {:<17}  {:>8}  {:>8}  {:>10}",
-            "#", "IP Address", "Errors", "Total", "Error Rate"
+            "  {:<3}  {:<w$}  {:>8}  {:>8}  {:>10}{}",
+            "#",
+            "IP Address",
+            errors_column,
+            "Total",
+            "Error Rate",
+            if networks { "  Network" } else { "" }
         );
         println!("  {}", &THIN_SEP[..60]);
         for (i, item) in stats.flagged_ips.iter().enumerate() {
+            let network = match &item.network {
+                Some(network) => format!("  {}", truncate(&network.label(), 40)),
+                None if networks => "  -".to_string(),
+                None => String::new(),
+            };
             println!(
-                "  {:<3}  {:<17}  {:>8}  {:>8}  {:>9.1}%",
+                "  {:<3}  {:<w$}  {:>8}  {:>8}  {:>9.1}%{}",
                 (i + 1).to_string().dimmed(),
                 item.ip.red().bold(),
                 item.error_count.to_string().red(),
                 item.total_requests,
-                item.error_rate
+                item.error_rate,
+                network
             );
         }
     }
 
+    if let Some(bursts) = &stats.bursts {
+        println!();
+        print_bursts(bursts);
+    }
+
+    if let Some(attacks) = &stats.attacks {
+        println!();
+        print_attacks(attacks);
+    }
+
+    if let Some(scanners) = &stats.scanners {
+        println!();
+        print_scanners(scanners);
+    }
+
+    if let Some(upstream) = &stats.upstream {
+        println!();
+        print_upstream(upstream);
+        if !upstream.slowest_endpoints.is_empty() {
+            println!();
+            print_slowest_endpoints(&upstream.slowest_endpoints, stats.top_n);
+        }
+    }
+
+    if let Some(apdex) = &stats.apdex {
+        println!();
+        print_apdex(apdex);
+    }
+
+    if let Some(slo) = &stats.slo {
+        println!();
+        print_slo(slo);
+    }
+
+    if let Some(flags) = &stats.response_flags {
+        println!();
+        print_response_flags(flags);
+    }
+
+    if let Some(routers) = &stats.routers {
+        println!();
+        print_routers(routers);
+    }
+
+    if let Some(traces) = &stats.traces {
+        println!();
+        print_traces(traces);
+    }
+
+    if let Some(sessions) = &stats.sessions {
+        println!();
+        print_sessions(sessions);
+    }
+
+    if let Some(bandwidth) = &stats.bandwidth {
+        println!();
+        print_bandwidth(bandwidth);
+    }
+
+    if let Some(agents) = &stats.user_agents {
+        println!();
+        print_user_agents(agents);
+    }
+
+    if let Some(browsers) = &stats.browsers {
+        println!();
+        print_browsers(browsers);
+    }
+
+    if let Some(bots) = &stats.bots {
+        println!();
+        print_bots(bots);
+    }
+
+    if let Some(users) = &stats.users {
+        println!();
+        print_users(users);
+    }
+
+    if let Some(referrers) = &stats.referrers {
+        println!();
+        print_referrers(referrers);
+    }
+
+    if let Some(comparison) = &stats.cohort_comparison {
+        println!();
+        print_cohort_comparison(comparison);
+    }
+
+    if let Some(redirects) = &stats.redirects {
+        println!();
+        print_redirects(redirects);
+    }
+
+    if let Some(timeline) = &stats.timeline {
+        println!();
+        print_timeline(timeline, stats.requests_per_second.as_ref(), stats.top_n);
+        if let Some(anomalies) = &stats.anomalies {
+            println!();
+            print_anomalies(anomalies, timeline);
+        }
+    }
+
+    if let Some(forecast) = &stats.forecast {
+        println!();
+        print_forecast(forecast);
+    }
+
+    if let Some(costs) = &stats.cost_attribution {
+        println!();
+        print_cost_attribution(costs);
+    }
+
+    if let Some(coverage) = &stats.endpoint_coverage {
+        println!();
+        print_endpoint_coverage(coverage);
+    }
+
+    if let Some(correlation) = &stats.spec_correlation {
+        println!();
+        print_spec_correlation(correlation);
+    }
+
+    if let Some(robots) = &stats.robots_compliance {
+        println!();
+        print_robots_compliance(robots);
+    }
+
+    if let Some(countries) = &stats.countries {
+        println!();
+        print_countries(countries);
+    }
+
+    if let Some(travel) = &stats.travel {
+        println!();
+        print_travel(travel);
+    }
+
+    if let Some(networks) = &stats.networks {
+        println!();
+        print_networks(networks);
+    }
+
     println!("\n{}\n", SEPARATOR.cyan());
 }
 
-/// Export the analysis statistics as JSON to the given path
-pub fn export_json(stats: &AnalysisStats, path: &PathBuf) -> Result<(), io::Error> {
-    let json = serde_json::to_string_pretty(stats).map_err(|e| {
-        io::Error::new(io::ErrorKind::InvalidData, format!("serialization failed: {}", e))
-    })?;
-    std::fs::write(path, json)
+/// Flagged IPs next to everyone else, to judge whether flagged traffic is anomalous
+fn print_cohort_comparison(cmp: &CohortComparison) {
+    section_header("COHORT COMPARISON — FLAGGED vs OTHER IPs");
+    let row = |label: &str, value: &dyn Fn(&CohortProfile) -> String| {
+        println!(
+            "  {:<28}  {:>12}  {:>12}",
+            label,
+            value(&cmp.flagged).red(),
+            value(&cmp.others)
+        );
+    };
+    println!("  {:<28}  {:>12}  {:>12}", "", "Flagged", "Others");
+    println!("  {}", &THIN_SEP[..60]);
+    row("IPs", &|p| p.ips.to_string());
+    row("Requests", &|p| p.requests.to_string());
+    row("Requests per IP", &|p| format!("{:.1}", p.requests_per_ip));
+    row("Distinct endpoints per IP", &|p| format!("{:.1}", p.avg_distinct_endpoints));
+    row("2xx share", &|p| format!("{:.1}%", p.status_mix.success_2xx));
+    row("3xx share", &|p| format!("{:.1}%", p.status_mix.redirect_3xx));
+    row("4xx share", &|p| format!("{:.1}%", p.status_mix.client_error_4xx));
+    row("5xx share", &|p| format!("{:.1}%", p.status_mix.server_error_5xx));
+    row("Peak hour (UTC)", &|p| match p.peak_hour {
+        Some(h) => format!("{:02}:00", h),
+        None => "-".to_string(),
+    });
+    println!();
+    println!("  Requests by hour of day (UTC, 00 → 23)");
+    println!("  {:<8} {}", "Flagged", sparkline(&cmp.flagged.hourly_share).red());
+    println!("  {:<8} {}", "Others", sparkline(&cmp.others.hourly_share).green());
 }
 
-// ─── Helpers ─────────────────────────────────────────────────────────────────
+/// Latency percentiles, the slowest endpoints and what the backends answered
+fn print_upstream(upstream: &UpstreamReport) {
+    section_header("RESPONSE TIME & BACKEND STATUS");
+    if let Some(latency) = &upstream.latency {
+        println!(
+            "  {} timed requests: min {:.1} ms, mean {:.1} ms, p50 {:.1} ms, p90 {:.1} ms, p95 {} ms, p99 {:.1} ms, max {:.1} ms",
+            latency.requests,
+            latency.min_ms,
+            latency.mean_ms,
+            latency.p50_ms,
+            latency.p90_ms,
+            format!("{:.1}", latency.p95_ms).yellow().bold(),
+            latency.p99_ms,
+            latency.max_ms
+        );
+    }
+    if let Some(backend) = &upstream.backend_statuses {
+        if upstream.latency.is_some() {
+            println!();
+        }
+        let mut codes: Vec<(&String, &usize)> = backend.distribution.iter().collect();
+        codes.sort_by_key(|(code, _)| code.parse::<u16>().unwrap_or(0));
+        let codes: Vec<String> = codes
+            .iter()
+            .map(|(code, count)| format!("{}: {}", color_status(code.parse().unwrap_or(0), code), count))
+            .collect();
+        println!("  Backend statuses: {}", codes.join(", "));
+        if backend.no_response > 0 {
+            println!(
+                "  {} requests got no backend response and were answered by the proxy",
+                backend.no_response.to_string().red().bold()
+            );
+        }
+        if backend.rewritten > 0 {
+            println!(
+                "  {} responses reached the client with a different status than the backend sent",
+                backend.rewritten.to_string().yellow()
+            );
+        }
+    }
+}
 
-fn section_header(title: &str) {
-    println!("  {} {}", "▶".cyan(), title.white().bold());
-    println!("  {}", THIN_SEP);
+/// Endpoints with the highest p95 response time, with their other percentiles
+fn print_slowest_endpoints(endpoints: &[EndpointLatency], top_n: usize) {
+    section_header(&format!("TOP {} SLOWEST ENDPOINTS (p95)", top_n));
+    println!(
+        "  {:<3}  {:<40}  {:>8}  {:>9}  {:>9}  {:>9}  {:>9}  {:>9}",
+        "#", "Endpoint", "Requests", "p50 ms", "p90 ms", "p95 ms", "p99 ms", "Max ms"
+    );
+    println!("  {}", &THIN_SEP[..90]);
+    for (i, item) in endpoints.iter().enumerate() {
+        println!(
+            "  {:<3}  {:<40}  {:>8}  {:>9.1}  {:>9.1}  {:>9}  {:>9.1}  {:>9.1}",
+            (i + 1).to_string().dimmed(),
+            truncate(&item.endpoint, 40).cyan(),
+            item.latency.requests,
+            item.latency.p50_ms,
+            item.latency.p90_ms,
+            format!("{:.1}", item.latency.p95_ms).bold(),
+            item.latency.p99_ms,
+            item.latency.max_ms
+        );
+    }
 }
 
-/// Renders a compact ASCII progress bar of the given width
-fn mini_bar(pct: f64, width: usize) -> String {
-    let filled = ((pct / 100.0) * width as f64).round() as usize;
-    let filled = filled.min(width);
-    let empty = width - filled;
-    format!(
-        "{}{}",
-        "█".repeat(filled).green(),
-        "░".repeat(empty).dimmed()
-    )
+/// A score colored by its rating band
+fn apdex_score(apdex: &ApdexScore) -> colored::ColoredString {
+    let score = format!("{:.2}", apdex.score);
+    match apdex.rating() {
+        "excellent" | "good" => score.green(),
+        "fair" => score.yellow(),
+        _ => score.red().bold(),
+    }
+}
+
+/// The overall Apdex with its satisfied/tolerating/frustrated split, then the
+/// endpoints with the lowest scores
+fn print_apdex(apdex: &ApdexReport) {
+    section_header(&format!("APDEX — T = {} ms", apdex.target_ms));
+    let overall = &apdex.overall;
+    println!(
+        "  Apdex {} ({}) over {} timed requests: {} satisfied, {} tolerating, {} frustrated",
+        apdex_score(overall),
+        overall.rating(),
+        overall.requests,
+        overall.satisfied,
+        overall.tolerating.to_string().yellow(),
+        overall.frustrated.to_string().red()
+    );
+    println!();
+    println!(
+        "  {:<3}  {:<40}  {:>8}  {:>9}  {:>10}  {:>10}  {:>5}",
+        "#", "Endpoint", "Requests", "Satisfied", "Tolerating", "Frustrated", "Apdex"
+    );
+    println!("  {}", &THIN_SEP[..90]);
+    for (i, item) in apdex.endpoints.iter().enumerate() {
+        println!(
+            "  {:<3}  {:<40}  {:>8}  {:>9}  {:>10}  {:>10}  {:>5}",
+            (i + 1).to_string().dimmed(),
+            truncate(&item.endpoint, 40).cyan(),
+            item.apdex.requests,
+            item.apdex.satisfied,
+            item.apdex.tolerating,
+            item.apdex.frustrated,
+            apdex_score(&item.apdex)
+        );
+    }
+}
+
+/// Availability over the period against the objective, then the error budget spent
+fn print_slo(slo: &SloReport) {
+    section_header(&format!("SLO — {}% AVAILABILITY", slo.objective));
+    let span = (slo.period_end - slo.period_start).to_std().unwrap_or_default();
+    println!(
+        "  Period: {} → {} UTC ({})",
+        slo.period_start.format("%Y-%m-%d %H:%M:%S"),
+        slo.period_end.format("%Y-%m-%d %H:%M:%S"),
+        units::format_duration(span)
+    );
+    let availability = format!("{:.3}%", slo.availability);
+    println!(
+        "  Availability: {} over {} requests, {} of them 5xx — {}",
+        if slo.met { availability.green().bold() } else { availability.red().bold() },
+        slo.requests,
+        slo.failed,
+        if slo.met { format!("{} SLO met", "✓".green()) } else { format!("{} SLO missed", "✗".red()) }
+    );
+    let remaining = if !slo.met {
+        format!("overspent by {} ({:.1}% of the budget)", -slo.budget_remaining, -slo.budget_remaining_pct)
+    } else {
+        format!("{} left ({:.1}% of the budget)", slo.budget_remaining, slo.budget_remaining_pct)
+    };
+    println!(
+        "  Error budget: {} failed requests allowed, {} used, {}",
+        slo.budget,
+        slo.failed,
+        match slo.budget_remaining_pct {
+            _ if !slo.met => remaining.red().bold(),
+            pct if pct < 25.0 => remaining.yellow(),
+            _ => remaining.green(),
+        }
+    );
+}
+
+/// Response flags by frequency, then the upstream hosts requests went to
+fn print_response_flags(report: &ResponseFlagReport) {
+    section_header("PROXY RESPONSE FLAGS");
+    println!(
+        "  {} requests logged response flags: {} flagged ({:.1}%)",
+        report.requests,
+        report.flagged.to_string().yellow().bold(),
+        report.flagged as f64 / report.requests as f64 * 100.0
+    );
+    if !report.flags.is_empty() {
+        println!();
+        println!("  {:<6}  {:>8}  {:>8}  Meaning", "Flag", "Requests", "Share");
+        println!("  {}", &THIN_SEP[..75]);
+        for item in &report.flags {
+            println!(
+                "  {:<6}  {:>8}  {:>7.2}%  {}",
+                item.flag.red().bold(),
+                item.count,
+                item.percentage,
+                item.meaning.unwrap_or("").dimmed()
+            );
+        }
+    }
+    if !report.top_upstream_hosts.is_empty() {
+        println!();
+        println!("  Top upstream hosts");
+        for item in &report.top_upstream_hosts {
+            println!("    {:<40}  {:>8}", truncate(&item.value, 40).cyan(), item.count);
+        }
+    }
+}
+
+/// Routers by request count, with the service behind each and its server error rate
+fn print_routers(report: &RouterReport) {
+    section_header("REQUESTS BY ROUTER");
+    println!("  {} routed requests, {} without a router", report.routed, report.unrouted);
+    println!();
+    println!(
+        "  {:<3}  {:<30}  {:<24}  {:>8}  {:>7}  {:>6}",
+        "#", "Router", "Service", "Requests", "Share", "5xx"
+    );
+    println!("  {}", &THIN_SEP[..90]);
+    for (i, item) in report.routers.iter().enumerate() {
+        let errors = format!("{:.1}%", item.error_rate);
+        println!(
+            "  {:<3}  {:<30}  {:<24}  {:>8}  {:>6.2}%  {:>6}",
+            (i + 1).to_string().dimmed(),
+            truncate(&item.router, 30).cyan(),
+            truncate(item.service.as_deref().unwrap_or("-"), 24),
+            item.requests,
+            item.percentage,
+            if item.server_errors > 0 { errors.red() } else { errors.normal() }
+        );
+    }
+}
+
+/// Parsed and malformed lines of each merged input file
+fn print_files(files: &[FileSummary]) {
+    section_header("INPUT FILES");
+    println!("  {:<44}  {:<16}  {:>8}  {:>9}", "File", "Format", "Parsed", "Malformed");
+    println!("  {}", &THIN_SEP[..84]);
+    for file in files {
+        let malformed = file.malformed.to_string();
+        println!(
+            "  {:<44}  {:<16}  {:>8}  {:>9}{}",
+            truncate_start(&file.path, 44).cyan(),
+            file.format.as_deref().unwrap_or("-"),
+            file.parsed,
+            if file.malformed > 0 { malformed.yellow() } else { malformed.normal() },
+            if file.incomplete.is_some() { "  (partial)".yellow() } else { "".normal() }
+        );
+    }
+}
+
+/// Authenticated users by request count, with their error rates
+fn print_users(report: &UserReport) {
+    section_header("TOP AUTHENTICATED USERS");
+    println!(
+        "  {} authenticated requests, {} anonymous (distinct users: {})",
+        report.authenticated, report.anonymous, report.distinct
+    );
+    println!();
+    println!("  {:<3}  {:<32}  {:>8}  {:>7}  {:>6}  {:>6}", "#", "User", "Requests", "Share", "Errors", "Rate");
+    println!("  {}", &THIN_SEP[..75]);
+    for (i, item) in report.users.iter().enumerate() {
+        let rate = format!("{:.1}%", item.error_rate);
+        println!(
+            "  {:<3}  {:<32}  {:>8}  {:>6.2}%  {:>6}  {:>6}",
+            (i + 1).to_string().dimmed(),
+            truncate(&item.user, 32).cyan(),
+            item.requests,
+            item.percentage,
+            item.errors,
+            if item.errors > 0 { rate.red() } else { rate.normal() }
+        );
+    }
+}
+
+/// Endpoints with the most responses of one status class (`4xx` or `5xx`)
+fn print_status_hotspots(class: &str, total: usize, items: &[RankedItem], top_n: usize) {
+    section_header(&format!("TOP {} ENDPOINTS BY {} RESPONSES", top_n, class));
+    if items.is_empty() {
+        println!("  {} No {} responses.", "✓".green(), class);
+        return;
+    }
+    println!("  {} {} responses", total, class);
+    println!();
+    println!("  {:<3}  {:<40}  {:>8}  {:>8}", "#", "Endpoint", class, "Share");
+    println!("  {}", &THIN_SEP[..66]);
+    for (i, item) in items.iter().enumerate() {
+        let count = item.count.to_string();
+        println!(
+            "  {:<3}  {:<40}  {:>8}  {:>7.2}%",
+            (i + 1).to_string().dimmed(),
+            truncate(&item.value, 40).cyan(),
+            if class == "5xx" { count.red() } else { count.yellow() },
+            item.percentage
+        );
+    }
+}
+
+/// Endpoints with the highest share of 5xx responses among those with enough traffic
+fn print_endpoint_errors(report: &EndpointErrorReport) {
+    section_header(&format!("ENDPOINTS BY 5xx RATE ({}+ REQUESTS)", report.min_requests));
+    if report.ranked == 0 {
+        println!(
+            "  No endpoint has {} or more requests to rank ({} below the floor)",
+            report.min_requests, report.below_floor
+        );
+        return;
+    }
+    if report.endpoints.is_empty() {
+        println!(
+            "  {} No server errors on the {} endpoints with enough traffic ({} below the floor)",
+            "✓".green(),
+            report.ranked,
+            report.below_floor
+        );
+        return;
+    }
+    println!(
+        "  {} of {} endpoints with enough traffic returned 5xx ({} below the floor)",
+        report.endpoints.len(),
+        report.ranked,
+        report.below_floor
+    );
+    println!();
+    println!("  {:<3}  {:<40}  {:>8}  {:>6}  {:>7}", "#", "Endpoint", "Requests", "5xx", "Rate");
+    println!("  {}", &THIN_SEP[..90]);
+    for (i, item) in report.endpoints.iter().enumerate() {
+        println!(
+            "  {:<3}  {:<40}  {:>8}  {:>6}  {:>6.1}%  {}",
+            (i + 1).to_string().dimmed(),
+            truncate(&item.endpoint, 40).cyan(),
+            item.requests,
+            item.server_errors.to_string().red(),
+            item.error_rate,
+            mini_bar(item.error_rate, 15)
+        );
+    }
+}
+
+/// Traces with the most errors, with the endpoints that failed in each
+fn print_traces(report: &TraceReport) {
+    section_header("TRACES WITH ERRORS");
+    println!(
+        "  {} traced requests in {} traces ({} with several requests), {} without a trace id",
+        report.traced, report.traces, report.multi_request_traces, report.untraced
+    );
+    println!("  {} traces contained errors", report.error_traces);
+    if report.top_error_traces.is_empty() {
+        return;
+    }
+    println!();
+    println!(
+        "  {:<3}  {:<40}  {:>8}  {:>6}  {:<19}  Failed endpoints",
+        "#", "Trace id", "Requests", "Errors", "First seen"
+    );
+    println!("  {}", &THIN_SEP[..90]);
+    for (i, trace) in report.top_error_traces.iter().enumerate() {
+        let mut endpoints = truncate(&trace.failed_endpoints[0], 28);
+        if trace.failed_endpoints.len() > 1 {
+            endpoints.push_str(&format!(" (+{})", trace.failed_endpoints.len() - 1));
+        }
+        println!(
+            "  {:<3}  {:<40}  {:>8}  {:>6}  {:<19}  {}",
+            (i + 1).to_string().dimmed(),
+            truncate(&trace.trace_id, 40).cyan(),
+            trace.requests,
+            trace.errors.to_string().red(),
+            trace.first_seen.format("%Y-%m-%d %H:%M:%S"),
+            endpoints
+        );
+    }
+}
+
+/// Requests and errors per client country, then the busiest cities
+fn print_countries(report: &CountryReport) {
+    section_header("REQUESTS BY COUNTRY");
+    println!(
+        "  {} requests located, {} from IPs not in the database",
+        report.located.to_string().green().bold(),
+        report.unlocated
+    );
+    if report.countries.is_empty() {
+        return;
+    }
+    println!();
+    println!(
+        "  {:<3}  {:<32}  {:>8}  {:>8}  {:>7}  {:>7}  {:>7}",
+        "#", "Country", "Requests", "Share", "Errors", "Rate", "IPs"
+    );
+    println!("  {}", &THIN_SEP[..90]);
+    for (i, country) in report.countries.iter().enumerate() {
+        let errors = country.errors.to_string();
+        println!(
+            "  {:<3}  {:<32}  {:>8}  {:>7.2}%  {:>7}  {:>6.1}%  {:>7}",
+            (i + 1).to_string().dimmed(),
+            truncate(&format!("{} ({})", country.country, country.country_code), 32).cyan(),
+            country.requests,
+            country.percentage,
+            if country.errors > 0 { errors.red() } else { errors.normal() },
+            country.error_rate,
+            country.clients
+        );
+    }
+    if report.top_cities.is_empty() {
+        return;
+    }
+    println!();
+    println!("  {:<3}  {:<40}  {:>8}  {:>8}", "#", "City", "Requests", "Share");
+    println!("  {}", &THIN_SEP[..66]);
+    for (i, city) in report.top_cities.iter().enumerate() {
+        println!(
+            "  {:<3}  {:<40}  {:>8}  {:>7.2}%",
+            (i + 1).to_string().dimmed(),
+            truncate(&city.value, 40).cyan(),
+            city.count,
+            city.percentage
+        );
+    }
+}
+
+/// Hops between distant places faster than anyone could travel, fastest first
+fn print_travel(report: &TravelReport) {
+    section_header("IMPOSSIBLE TRAVEL");
+    println!(
+        "  {} implausible hops among {} authenticated users (faster than {} km/h over {} km or more)",
+        report.anomalies.len().to_string().red().bold(),
+        report.users,
+        report.max_speed_kmh,
+        report.min_distance_km
+    );
+    if report.anomalies.is_empty() {
+        return;
+    }
+    println!();
+    println!(
+        "  {:<3}  {:<16}  {:<15}  {:<15}  {:<19}  {:>8}  {:>10}  {:>9}",
+        "#", "User", "From", "To", "At", "Minutes", "km", "km/h"
+    );
+    println!("  {}", &THIN_SEP[..111]);
+    for (i, hop) in report.anomalies.iter().enumerate() {
+        let speed = if hop.speed_kmh.is_finite() { format!("{:.0}", hop.speed_kmh) } else { "instant".to_string() };
+        println!(
+            "  {:<3}  {:<16}  {:<15}  {:<15}  {:<19}  {:>8}  {:>10.1}  {:>9}",
+            (i + 1).to_string().dimmed(),
+            truncate(&hop.subject, 16).cyan(),
+            truncate(&hop.from_ip, 15),
+            truncate(&hop.to_ip, 15),
+            hop.to_time.format("%Y-%m-%d %H:%M:%S"),
+            (hop.to_time - hop.from_time).num_minutes(),
+            hop.distance_km,
+            speed.red()
+        );
+    }
+}
+
+/// Autonomous systems by requests, with how many of their clients were flagged
+fn print_networks(report: &AsnReport) {
+    section_header("TOP NETWORKS BY TRAFFIC");
+    println!(
+        "  {} requests resolved to a network, {} from IPs not in the database",
+        report.resolved.to_string().green().bold(),
+        report.unresolved
+    );
+    if report.top_networks.is_empty() {
+        return;
+    }
+    println!();
+    println!(
+        "  {:<3}  {:<9}  {:<28}  {:>8}  {:>7}  {:>6}  {:>6}  {:>7}",
+        "#", "ASN", "Organization", "Requests", "Share", "Errors", "IPs", "Flagged"
+    );
+    println!("  {}", &THIN_SEP[..90]);
+    for (i, network) in report.top_networks.iter().enumerate() {
+        let flagged = network.flagged_clients.to_string();
+        println!(
+            "  {:<3}  {:<9}  {:<28}  {:>8}  {:>6.2}%  {:>6}  {:>6}  {:>7}",
+            (i + 1).to_string().dimmed(),
+            format!("AS{}", network.asn).cyan(),
+            truncate(&network.organization, 28),
+            network.requests,
+            network.percentage,
+            network.errors,
+            network.clients,
+            if network.flagged_clients > 0 { flagged.red() } else { flagged.normal() }
+        );
+    }
+}
+
+/// Session counts and lengths, then the endpoints sessions started and ended on
+fn print_sessions(report: &SessionReport) {
+    let gap = units::format_duration(Duration::from_secs(report.gap_seconds));
+    section_header(&format!("SESSIONS — {} INACTIVITY GAP", gap));
+    println!(
+        "  {} sessions from {} client IPs ({:.2} per client)",
+        report.sessions.to_string().green().bold(),
+        report.clients,
+        report.sessions_per_client
+    );
+    println!(
+        "  Average session: {:.1} requests over {} (longest {})",
+        report.average_requests,
+        units::format_duration(Duration::from_secs(report.average_duration_seconds.round() as u64)),
+        units::format_duration(Duration::from_secs(report.longest_duration_seconds))
+    );
+    println!(
+        "  Single-request sessions: {} ({:.1}%)",
+        report.single_request_sessions,
+        report.single_request_sessions as f64 / report.sessions as f64 * 100.0
+    );
+    for (heading, items) in [("Entry endpoint", &report.top_entry_endpoints), ("Exit endpoint", &report.top_exit_endpoints)] {
+        println!();
+        println!("  {:<3}  {:<40}  {:>8}  {:>8}", "#", heading, "Sessions", "Share");
+        println!("  {}", &THIN_SEP[..66]);
+        for (i, item) in items.iter().enumerate() {
+            println!(
+                "  {:<3}  {:<40}  {:>8}  {:>7.2}%",
+                (i + 1).to_string().dimmed(),
+                truncate(&item.value, 40).cyan(),
+                item.count,
+                item.percentage
+            );
+        }
+    }
+}
+
+/// Requests per HTTP version, with the share of entries that did not log one
+/// Requests per status class, then the success and error rates
+fn print_status_classes(rollup: &StatusClassRollup) {
+    section_header("STATUS CLASSES");
+    for (class, lc) in &rollup.classes {
+        let label = match class.as_str() {
+            "2xx" => class.green(),
+            "3xx" => class.cyan(),
+            "4xx" => class.yellow(),
+            "5xx" => class.red(),
+            _ => class.normal(),
+        };
+        println!("  {}  {:>6}  ({:5.1}%)  {}", label, lc.count, lc.percentage, mini_bar(lc.percentage, 20));
+    }
+    println!(
+        "  Success rate {} (2xx and 3xx), error rate {} (4xx and 5xx)",
+        format!("{:.1}%", rollup.success_rate).green(),
+        format!("{:.1}%", rollup.error_rate).red()
+    );
+}
+
+/// Requests per HTTP method, most used first
+fn print_methods(methods: &[RankedItem]) {
+    section_header("HTTP METHODS");
+    for item in methods {
+        println!(
+            "  {:<10}  {:>6}  ({:5.1}%)  {}",
+            truncate(&item.value, 10).cyan(),
+            item.count,
+            item.percentage,
+            mini_bar(item.percentage, 20)
+        );
+    }
+}
+
+fn print_protocols(protocols: &HashMap<String, usize>, total_entries: usize) {
+    section_header("PROTOCOL VERSIONS");
+    let mut protocol_vec: Vec<(&String, &usize)> = protocols.iter().collect();
+    protocol_vec.sort();
+    let logged: usize = protocols.values().sum();
+    for (protocol, count) in protocol_vec {
+        let pct = *count as f64 / logged as f64 * 100.0;
+        println!("  {:<10}  {:>6}  ({:5.1}%)  {}", protocol.cyan(), count, pct, mini_bar(pct, 20));
+    }
+    if logged < total_entries {
+        println!("  {}", format!("{} entries without a protocol version", total_entries - logged).dimmed());
+    }
+}
+
+/// Parameter names by how many requests with a query string used them
+fn print_query_params(query_params: &QueryParamReport) {
+    section_header("TOP QUERY PARAMETERS");
+    println!("  {} requests with a query string", query_params.requests);
+    println!();
+    println!("  {:<3}  {:<30}  {:>8}  {:>8}", "#", "Parameter", "Requests", "Share");
+    println!("  {}", &THIN_SEP[..54]);
+    for (i, item) in query_params.top_params.iter().enumerate() {
+        println!(
+            "  {:<3}  {:<30}  {:>8}  {:>7.2}%",
+            (i + 1).to_string().dimmed(),
+            truncate(&item.value, 30).cyan(),
+            item.count,
+            item.percentage
+        );
+    }
+}
+
+/// Total bytes served, then the endpoints and clients that consumed the most
+fn print_bandwidth(bandwidth: &BandwidthReport) {
+    section_header("BANDWIDTH");
+    println!("  Total served: {}", binary_bytes(bandwidth.total_bytes).green().bold());
+    if bandwidth.requests_without_bytes > 0 {
+        println!(
+            "  {} requests had no response size and are not counted",
+            bandwidth.requests_without_bytes.to_string().yellow()
+        );
+    }
+    let table = |heading: &str, width: usize, items: &[BandwidthItem]| {
+        println!();
+        println!(
+            "  {:<3}  {:<width$}  {:>8}  {:>10}  {:>10}  {:>8}",
+            "#", heading, "Requests", "Bytes", "Per req", "Share"
+        );
+        println!("  {}", &THIN_SEP[..66]);
+        for (i, item) in items.iter().enumerate() {
+            println!(
+                "  {:<3}  {:<width$}  {:>8}  {:>10}  {:>10}  {:>7.2}%",
+                (i + 1).to_string().dimmed(),
+                truncate(&item.value, width).cyan(),
+                item.requests,
+                binary_bytes(item.bytes),
+                binary_bytes(item.bytes / item.requests.max(1) as u64),
+                item.percentage
+            );
+        }
+    };
+    table("Endpoint", 40, &bandwidth.top_endpoints);
+    table("IP Address", ip_width(bandwidth.top_ips.iter().map(|item| item.value.as_str())), &bandwidth.top_ips);
+}
+
+/// Clients that went over the request rate of `--burst-threshold`, highest peak first
+fn print_bursts(bursts: &BurstReport) {
+    let window = units::format_duration(Duration::from_secs(bursts.window_seconds));
+    section_header(&format!("REQUEST BURSTS — > {} requests in {}", bursts.threshold, window));
+    if bursts.flagged == 0 {
+        println!("  {} No IPs exceeded the request rate.", "✓".green());
+        return;
+    }
+    println!("  {} IPs sent bursts\n", bursts.flagged.to_string().yellow().bold());
+    let w = ip_width(bursts.ips.iter().map(|item| item.ip.as_str()));
+    println!(
+        "  {:<3}  {:<w$}  {:>6}  {:<19}  {:>6}  {:>8}  {:>6}",
+        "#", "IP Address", "Peak", "Peak window from", "Bursts", "Total", "Errors"
+    );
+    println!("  {}", &THIN_SEP[..90]);
+    for (i, item) in bursts.ips.iter().enumerate() {
+        println!(
+            "  {:<3}  {:<w$}  {:>6}  {:<19}  {:>6}  {:>8}  {:>6}",
+            (i + 1).to_string().dimmed(),
+            item.ip.yellow().bold(),
+            item.peak_requests.to_string().yellow(),
+            item.peak_start.format("%Y-%m-%d %H:%M:%S"),
+            item.bursts,
+            item.total_requests,
+            item.errors
+        );
+    }
+}
+
+/// Requests matching attack signatures by kind, then the IPs that sent the most
+fn print_attacks(attacks: &AttackReport) {
+    section_header("ATTACK SIGNATURES");
+    if attacks.requests == 0 {
+        println!("  {} No request matched an attack signature.", "✓".green());
+        return;
+    }
+    println!(
+        "  {} requests from {} IPs matched attack signatures; {} answered with a 2xx status",
+        attacks.requests.to_string().red().bold(),
+        attacks.ips,
+        if attacks.successful > 0 { attacks.successful.to_string().red().bold() } else { "0".normal() }
+    );
+    let kinds: Vec<String> = attacks
+        .by_kind
+        .iter()
+        .map(|kind| format!("{} {} ({} IPs)", kind.kind.as_str(), kind.requests, kind.ips))
+        .collect();
+    println!("  {}\n", kinds.join("  ·  "));
+    let w = ip_width(attacks.attackers.iter().map(|item| item.ip.as_str()));
+    println!("  {:<3}  {:<w$}  {:>8}  {:>4}  {:<40}  Example", "#", "IP Address", "Requests", "2xx", "Signatures");
+    println!("  {}", THIN_SEP);
+    for (i, item) in attacks.attackers.iter().enumerate() {
+        println!(
+            "  {:<3}  {:<w$}  {:>8}  {:>4}  {:<40}  {}",
+            (i + 1).to_string().dimmed(),
+            item.ip.red().bold(),
+            item.requests,
+            item.successful,
+            truncate(&item.signatures.join(", "), 40),
+            truncate(&item.example, 40).dimmed()
+        );
+    }
+}
+
+/// Clients that got 404s on many distinct paths, most paths first
+fn print_scanners(scanners: &ScannerReport) {
+    section_header(&format!(
+        "404 SCANNERS — ≥ {} DISTINCT PATHS, ≥ {}% NOT FOUND",
+        scanners.min_paths, scanners.min_share
+    ));
+    if scanners.flagged == 0 {
+        println!("  {} No IPs looked like 404 scanners.", "✓".green());
+        return;
+    }
+    println!("  {} IPs looked like scanners\n", scanners.flagged.to_string().yellow().bold());
+    let w = ip_width(scanners.ips.iter().map(|item| item.ip.as_str()));
+    println!(
+        "  {:<3}  {:<w$}  {:>14}  {:>8}  {:>6}  {:>8}",
+        "#", "IP Address", "Distinct paths", "Requests", "404s", "404 rate"
+    );
+    println!("  {}", &THIN_SEP[..198]);
+    for (i, item) in scanners.ips.iter().enumerate() {
+        println!(
+            "  {:<3}  {:<w$}  {:>14}  {:>8}  {:>6}  {:>7.1}%",
+            (i + 1).to_string().dimmed(),
+            item.ip.yellow().bold(),
+            item.distinct_paths.to_string().yellow(),
+            item.requests,
+            item.not_found,
+            item.not_found_share
+        );
+    }
+}
+
+/// Browser/crawler/bot split, then the most common user agents
+fn print_user_agents(agents: &UserAgentReport) {
+    section_header("USER AGENTS");
+    let classes: Vec<String> = agents
+        .classes
+        .iter()
+        .map(|share| format!("{} {} ({:.1}%)", agent_class_label(share.class), share.count, share.percentage))
+        .collect();
+    println!("  {} requests with a user agent: {}", agents.requests, classes.join(", "));
+    println!();
+    println!("  {:<3}  {:<50}  {:<8}  {:>8}  {:>8}", "#", "User Agent", "Class", "Requests", "Share");
+    println!("  {}", &THIN_SEP[..90]);
+    for (i, item) in agents.top_agents.iter().enumerate() {
+        println!(
+            "  {:<3}  {:<50}  {:<8}  {:>8}  {:>7.2}%",
+            (i + 1).to_string().dimmed(),
+            truncate(&item.value, 50).cyan(),
+            agent_class_label(item.class),
+            item.count,
+            item.percentage
+        );
+    }
+}
+
+/// Browser families, their major versions and the operating systems they run on
+fn print_browsers(browsers: &BrowserReport) {
+    section_header("BROWSERS AND OPERATING SYSTEMS");
+    println!(
+        "  {} browser requests; {} from crawlers and bots left out",
+        browsers.requests,
+        browsers.automated.to_string().dimmed()
+    );
+    if browsers.requests == 0 {
+        return;
+    }
+    let print_families = |title: &str, families: &[FamilyShare]| {
+        println!();
+        println!("  {:<3}  {:<30}  {:>8}  {:>8}", "#", title, "Requests", "Share");
+        println!("  {}", &THIN_SEP[..165]);
+        for (i, item) in families.iter().enumerate() {
+            println!(
+                "  {:<3}  {:<30}  {:>8}  {:>7.2}%",
+                (i + 1).to_string().dimmed(),
+                truncate(&item.name, 30).cyan(),
+                item.count,
+                item.percentage
+            );
+        }
+    };
+    print_families("Browser", &browsers.browsers);
+    if !browsers.versions.is_empty() {
+        println!();
+        println!("  {:<3}  {:<30}  {:>8}  {:>8}", "#", "Browser version", "Requests", "Share");
+        println!("  {}", &THIN_SEP[..165]);
+        for (i, item) in browsers.versions.iter().enumerate() {
+            println!(
+                "  {:<3}  {:<30}  {:>8}  {:>7.2}%",
+                (i + 1).to_string().dimmed(),
+                truncate(&format!("{} {}", item.browser, item.version), 30).cyan(),
+                item.count,
+                item.percentage
+            );
+        }
+    }
+    print_families("Operating system", &browsers.operating_systems);
+}
+
+/// Requests by where they came from, the top referrers, then the search engines and
+/// sites sending traffic and any referrer spam
+fn print_referrers(referrers: &ReferrerReport) {
+    section_header("TOP REFERRERS");
+    let total = referrers.requests + referrers.direct;
+    let share = |n: usize| n as f64 / total as f64 * 100.0;
+    println!(
+        "  {} requests: {} direct ({:.1}%), {} internal ({:.1}%), {} search ({:.1}%), {} external ({:.1}%), {} spam ({:.1}%)",
+        total,
+        referrers.direct,
+        share(referrers.direct),
+        referrers.internal,
+        share(referrers.internal),
+        referrers.search.to_string().cyan(),
+        share(referrers.search),
+        referrers.external.to_string().cyan(),
+        share(referrers.external),
+        if referrers.spam > 0 { referrers.spam.to_string().red() } else { "0".normal() },
+        share(referrers.spam)
+    );
+    println!();
+    println!("  {:<3}  {:<50}  {:<8}  {:>8}  {:>8}", "#", "Referrer", "Kind", "Requests", "Share");
+    println!("  {}", &THIN_SEP[..90]);
+    for (i, item) in referrers.top_referrers.iter().enumerate() {
+        println!(
+            "  {:<3}  {:<50}  {:<8}  {:>8}  {:>7.2}%",
+            (i + 1).to_string().dimmed(),
+            truncate(&item.value, 50).cyan(),
+            match item.class {
+                ReferrerClass::Internal | ReferrerClass::Direct => item.class.as_str().dimmed(),
+                ReferrerClass::Spam => item.class.as_str().red(),
+                ReferrerClass::Search | ReferrerClass::External => item.class.as_str().green(),
+            },
+            item.count,
+            item.percentage
+        );
+    }
+    if !referrers.top_sources.is_empty() {
+        println!();
+        println!("  Top external sources");
+        for item in &referrers.top_sources {
+            println!(
+                "    {:<40}  {:<8}  {:>8}  {:>7.2}%",
+                truncate(&item.source, 40).green(),
+                item.class.as_str(),
+                item.count,
+                item.percentage
+            );
+        }
+    }
+    if !referrers.spam_hosts.is_empty() {
+        println!();
+        println!("  {} Referrer spam", "⚠".red());
+        for item in &referrers.spam_hosts {
+            println!("    {:<40}  {:>8}", truncate(&item.value, 40).red(), item.count);
+        }
+    }
+}
+
+/// Bot share of the traffic, then the bots that sent the most requests
+fn print_bots(report: &BotReport) {
+    section_header("BOTS");
+    println!(
+        "  Bot traffic: {} of {} requests ({:.1}%) from {} of {} clients",
+        report.bot_requests.to_string().yellow().bold(),
+        report.requests,
+        report.bot_share,
+        report.bot_clients,
+        report.clients
+    );
+    if report.top_bots.is_empty() {
+        return;
+    }
+    println!(
+        "  Detected by user agent: {}, robots.txt: {}, cadence: {}",
+        report.by_user_agent, report.by_robots_txt, report.by_cadence
+    );
+    println!();
+    let w = ip_width(report.top_bots.iter().map(|bot| bot.ip.as_str()));
+    println!(
+        "  {:<3}  {:<w$}  {:<36}  {:<24}  {:>8}  {:>8}",
+        "#", "IP Address", "User Agent", "Signals", "Requests", "Share"
+    );
+    println!("  {}", &THIN_SEP[..90]);
+    for (i, bot) in report.top_bots.iter().enumerate() {
+        let signals: Vec<&str> = bot.signals.iter().map(|signal| signal.as_str()).collect();
+        println!(
+            "  {:<3}  {:<w$}  {:<36}  {:<24}  {:>8}  {:>7.2}%",
+            (i + 1).to_string().dimmed(),
+            bot.ip.cyan(),
+            truncate(bot.user_agent.as_deref().unwrap_or("-"), 36),
+            signals.join(", "),
+            bot.requests,
+            bot.percentage
+        );
+    }
+}
+
+fn agent_class_label(class: AgentClass) -> colored::ColoredString {
+    match class {
+        AgentClass::Browser => "browser".green(),
+        AgentClass::Crawler => "crawler".blue(),
+        AgentClass::Bot => "bot".yellow(),
+        AgentClass::Other => "other".normal(),
+    }
+}
+
+/// Most-followed redirects, then loops and chains that waste client round-trips
+fn print_redirects(report: &RedirectReport) {
+    section_header("REDIRECT CHAINS");
+    println!(
+        "  {} redirect responses, {} followed by the same client within {}s",
+        report.redirects.to_string().bold(),
+        report.followed,
+        crate::redirects::FOLLOW_WINDOW_SECS
+    );
+    if !report.top_edges.is_empty() {
+        println!();
+        println!("  {:<3}  {:<30}  {:<30}  {:>8}", "#", "Source", "Target", "Count");
+        println!("  {}", &THIN_SEP[..81]);
+        for (i, edge) in report.top_edges.iter().enumerate() {
+            println!(
+                "  {:<3}  {:<30}  {:<30}  {:>8}",
+                (i + 1).to_string().dimmed(),
+                truncate(&edge.source, 30).cyan(),
+                truncate(&edge.target, 30),
+                edge.count
+            );
+        }
+    }
+    let chains = |title: &str, list: &[RedirectChain]| {
+        if list.is_empty() {
+            return;
+        }
+        println!();
+        println!("  {}", title.yellow().bold());
+        for chain in list {
+            println!(
+                "    {:>5}×  {} {}",
+                chain.occurrences,
+                chain.path.join(" → "),
+                format!("({} redirects)", chain.redirects()).dimmed()
+            );
+        }
+    };
+    chains("⟳ Redirect loops", &report.loops);
+    chains(
+        &format!("⚠ Long chains ({}+ redirects)", crate::redirects::LONG_CHAIN_REDIRECTS),
+        &report.long_chains,
+    );
+}
+
+/// The peaks and per-bucket rates, requests per bucket as a sparkline, then a bar
+/// for each bucket, or for the busiest `top_n` when there are too many to list
+fn print_timeline(timeline: &Timeline, rates: Option<&RateStats>, top_n: usize) {
+    /// Most buckets listed one per row
+    const MAX_ROWS: usize = 48;
+    /// Sparkline characters per line
+    const SPARK_WIDTH: usize = 64;
+
+    section_header(&format!("REQUESTS OVER TIME ({} BUCKETS, UTC)", timeline.bucket));
+    let (Some(first), Some(last), Some(peak)) = (timeline.buckets.first(), timeline.buckets.last(), timeline.peak()) else {
+        println!("  No entries to chart");
+        return;
+    };
+    let time_format = bucket_time_format(timeline);
+    println!(
+        "  {} buckets from {} to {}; busiest {} with {} requests",
+        timeline.buckets.len(),
+        first.start.format(time_format),
+        last.start.format(time_format),
+        peak.start.format(time_format).to_string().yellow(),
+        peak.requests
+    );
+    if let Some(peaks) = &timeline.peaks {
+        print_peaks(peaks, &timeline.bucket);
+    }
+    if let Some(rates) = rates {
+        println!(
+            "  {:<17}mean {:.2}, max {:.2}, std dev {:.2}",
+            format!("Req/s per {}:", rates.bucket),
+            rates.mean,
+            rates.max,
+            rates.std_dev
+        );
+    }
+    let counted = timeline.buckets.iter().any(|b| b.clients.is_some());
+    let clients = timeline.buckets.iter().filter_map(|b| b.clients).map(|c| c.new_ips).sum::<usize>();
+    match (timeline.returning_ips, timeline.client_count_error) {
+        (Some(returning), _) => println!("  Client IPs:      {} unique, {} returning in a later bucket", clients, returning),
+        (None, Some(error)) => println!(
+            "  Client IPs:      ≈{} unique (HyperLogLog estimates, ±{:.2}% standard error)",
+            clients,
+            error * 100.0
+        ),
+        (None, None) if counted => println!("  Client IPs:      {} unique", clients),
+        (None, None) => {}
+    }
+    let requests: Vec<f64> = timeline.buckets.iter().map(|b| b.requests as f64).collect();
+    for line in sparkline(&requests).chars().collect::<Vec<_>>().chunks(SPARK_WIDTH) {
+        println!("  {}", line.iter().collect::<String>().cyan());
+    }
+
+    let mut rows: Vec<_> = timeline.buckets.iter().collect();
+    if rows.len() > MAX_ROWS {
+        rows.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.start.cmp(&b.start)));
+        rows.truncate(top_n);
+        rows.sort_by_key(|b| b.start);
+        println!();
+        println!("  Busiest {} buckets:", rows.len());
+    }
+    println!();
+    let w = first.start.format(time_format).to_string().len();
+    if counted {
+        println!("  {:<w$}  {:>8}  {:>7}  {:>6}  {:>6}", "Bucket", "Requests", "Errors", "IPs", "New");
+        println!("  {}", &THIN_SEP[..(w + 37) * 3]);
+    } else {
+        println!("  {:<w$}  {:>8}  {:>7}", "Bucket", "Requests", "Errors");
+        println!("  {}", &THIN_SEP[..(w + 45) * 3]);
+    }
+    for bucket in rows {
+        let errors = bucket.errors.to_string();
+        let clients = bucket
+            .clients
+            .map(|c| format!("{:>6}  {:>6}  ", c.unique_ips, c.new_ips))
+            .unwrap_or_default();
+        println!(
+            "  {:<w$}  {:>8}  {:>7}  {}{}",
+            bucket.start.format(time_format).to_string(),
+            bucket.requests,
+            if bucket.errors > 0 { errors.red() } else { errors.normal() },
+            clients,
+            mini_bar(bucket.requests as f64 / peak.requests.max(1) as f64 * 100.0, 25)
+        );
+    }
+}
+
+/// How bucket start times are shown: no finer than the bucket width
+fn bucket_time_format(timeline: &Timeline) -> &'static str {
+    match timeline.bucket_seconds {
+        s if s % 86_400 == 0 => "%Y-%m-%d",
+        s if s % 60 == 0 => "%Y-%m-%d %H:%M",
+        _ => "%Y-%m-%d %H:%M:%S",
+    }
+}
+
+/// Buckets whose requests or error rate strayed from the baseline, in time order
+fn print_anomalies(anomalies: &AnomalyReport, timeline: &Timeline) {
+    let against = match anomalies.baseline {
+        BaselineSource::Rolling { buckets } => format!("THE {} BUCKETS BEFORE", buckets),
+        BaselineSource::Stored { buckets } => format!("A SAVED BASELINE OF {} BUCKETS", buckets),
+    };
+    section_header(&format!("ANOMALIES — > {}σ FROM {}", anomalies.threshold, against));
+    if anomalies.checked == 0 {
+        println!("  Not enough buckets to compare against a baseline");
+        return;
+    }
+    if anomalies.total == 0 {
+        println!("  {} No bucket out of {} strayed from the baseline.", "✓".green(), anomalies.checked);
+        return;
+    }
+    println!(
+        "  {} anomalies in {} buckets compared\n",
+        anomalies.total.to_string().yellow().bold(),
+        anomalies.checked
+    );
+    let time_format = bucket_time_format(timeline);
+    let w = anomalies.anomalies[0].start.format(time_format).to_string().len();
+    println!(
+        "  {:<w$}  {:<10}  {:>9}  {:>9}  {:>8}  {:>9}",
+        "Bucket", "Metric", "Value", "Expected", "Std dev", "Deviation"
+    );
+    println!("  {}", THIN_SEP);
+    for anomaly in &anomalies.anomalies {
+        let shown = |value: f64| match anomaly.metric {
+            Metric::Requests => format!("{:.1}", value),
+            Metric::ErrorRate => format!("{:.1}%", value),
+        };
+        let deviation = format!("{:+.1}σ", anomaly.deviation);
+        println!(
+            "  {:<w$}  {:<10}  {:>9}  {:>9}  {:>8}  {:>9}",
+            anomaly.start.format(time_format).to_string(),
+            anomaly.metric.as_str(),
+            shown(anomaly.value),
+            shown(anomaly.expected),
+            shown(anomaly.std_dev),
+            if anomaly.deviation > 0.0 { deviation.red() } else { deviation.cyan() }
+        );
+    }
+}
+
+/// Busiest second, minute and hour, and the window with the most errors
+fn print_peaks(peaks: &Peaks, bucket: &str) {
+    const SECONDS: &str = "%Y-%m-%d %H:%M:%S";
+    println!(
+        "  Peak rate:       {} req/s at {} (average {:.2} req/s)",
+        peaks.second.requests.to_string().yellow(),
+        peaks.second.start.format(SECONDS),
+        peaks.average_per_second
+    );
+    println!(
+        "  Busiest minute:  {} with {} requests",
+        peaks.minute.start.format("%Y-%m-%d %H:%M"),
+        peaks.minute.requests
+    );
+    println!(
+        "  Busiest hour:    {} with {} requests",
+        peaks.hour.start.format("%Y-%m-%d %H:00"),
+        peaks.hour.requests
+    );
+    if let Some(window) = &peaks.error_window {
+        println!(
+            "  Most errors:     the {} from {}, {} of {} requests failed ({:.1}%)",
+            bucket,
+            window.start.format(SECONDS),
+            window.errors.to_string().red(),
+            window.requests,
+            window.error_rate
+        );
+    }
+}
+
+/// Next-day totals with ~95% bands, plus the expected hourly shape
+fn print_forecast(forecast: &TrafficForecast) {
+    section_header(&format!(
+        "NEXT {}H FORECAST (HOLT-WINTERS, FROM {}H OF HISTORY)",
+        forecast.horizon_hours, forecast.history_hours
+    ));
+    let row = |label: &str, f: &SeriesForecast| {
+        println!(
+            "  {:<10} {:>10}   95% band {:>10} – {:<10}  (α={} β={} γ={})",
+            label,
+            format!("{:.0}", f.total).green().bold(),
+            format!("{:.0}", f.total_lower),
+            format!("{:.0}", f.total_upper),
+            f.alpha,
+            f.beta,
+            f.gamma
+        );
+    };
+    row("Requests", &forecast.requests);
+    row("Errors", &forecast.errors);
+    let shape: Vec<f64> = forecast.requests.hourly.iter().map(|p| p.expected).collect();
+    if let Some(first) = forecast.requests.hourly.first() {
+        println!();
+        println!(
+            "  Expected requests per hour from {}",
+            first.hour.format("%Y-%m-%d %H:00 UTC")
+        );
+        println!("  {}", sparkline(&shape).cyan());
+    }
+}
+
+/// Estimated egress and request cost per endpoint and per tenant
+fn print_cost_attribution(costs: &CostAttribution) {
+    section_header("COST ATTRIBUTION (ESTIMATED)");
+    println!(
+        "  Unit costs: {:.4} per GB egress, {:.4} per million requests",
+        costs.unit_costs.per_gb_egress, costs.unit_costs.per_million_requests
+    );
+    println!(
+        "  Total: {} for {} requests and {}",
+        format!("{:.2}", costs.total.total_cost).green().bold(),
+        costs.total.requests,
+        human_bytes(costs.total.bytes)
+    );
+    if costs.requests_without_bytes > 0 {
+        println!(
+            "  {} requests had no response size and are costed per request only",
+            costs.requests_without_bytes.to_string().yellow()
+        );
+    }
+    let table = |heading: &str, lines: &[CostLine]| {
+        println!();
+        println!(
+            "  {:<3}  {:<40}  {:>8}  {:>10}  {:>10}  {:>10}",
+            "#", heading, "Requests", "Egress", "Req Cost", "Total"
+        );
+        println!("  {}", &THIN_SEP[..90]);
+        for (i, line) in lines.iter().enumerate() {
+            println!(
+                "  {:<3}  {:<40}  {:>8}  {:>10.2}  {:>10.2}  {:>10}",
+                (i + 1).to_string().dimmed(),
+                truncate(&line.key, 40).cyan(),
+                line.requests,
+                line.egress_cost,
+                line.request_cost,
+                format!("{:.2}", line.total_cost).bold()
+            );
+        }
+    };
+    table("Endpoint", &costs.top_endpoints);
+    table("Tenant (client IP)", &costs.top_tenants);
+}
+
+/// Crawlers (clients that fetched robots.txt) that went on to request disallowed paths
+fn print_robots_compliance(robots: &RobotsCompliance) {
+    section_header("ROBOTS.TXT COMPLIANCE");
+    println!(
+        "  {} crawlers fetched robots.txt ({} rules) and made {} further requests; {} were disallowed",
+        robots.crawlers,
+        robots.rules,
+        robots.crawler_requests,
+        if robots.violations > 0 {
+            robots.violations.to_string().red().bold()
+        } else {
+            "0".green()
+        }
+    );
+    if robots.top_violators.is_empty() {
+        println!("  {}", "✓ No crawler requested a disallowed path.".green());
+        return;
+    }
+    println!();
+    let w = ip_width(robots.top_violators.iter().map(|crawler| crawler.ip.as_str()));
+    println!("  {:<3}  {:<w$}  {:>10}  {:>8}  {:>9}", "#", "Crawler IP", "Violations", "Requests", "Rate");
+    println!("  {}", &THIN_SEP[..60]);
+    for (i, crawler) in robots.top_violators.iter().enumerate() {
+        println!(
+            "  {:<3}  {:<w$}  {:>10}  {:>8}  {:>8.1}%",
+            (i + 1).to_string().dimmed(),
+            crawler.ip.red(),
+            crawler.violations,
+            crawler.requests,
+            crawler.violation_rate
+        );
+    }
+    println!();
+    println!("  Most-requested disallowed paths");
+    for item in &robots.top_disallowed_paths {
+        println!("    {:<40}  {:>8}", truncate(&item.value, 40).yellow(), item.count);
+    }
+}
+
+/// Expected routes with no traffic, and logged endpoints missing from the spec
+fn print_endpoint_coverage(coverage: &EndpointCoverage) {
+    section_header("ENDPOINT COVERAGE — EXPECTED vs LOGGED");
+    println!(
+        "  {} of {} expected routes received no traffic",
+        coverage.idle_routes.len().to_string().yellow().bold(),
+        coverage.expected_routes
+    );
+    for route in &coverage.idle_routes {
+        println!("    {} {}", "∅".dimmed(), route.yellow());
+    }
+    println!();
+    println!(
+        "  {} requests hit endpoints not in the expected routes",
+        coverage.unknown_requests.to_string().yellow().bold()
+    );
+    if !coverage.top_unknown_endpoints.is_empty() {
+        println!("  {:<3}  {:<40}  {:>8}  {:>8}", "#", "Unknown Endpoint", "Requests", "Share");
+        println!("  {}", &THIN_SEP[..66]);
+        for (i, item) in coverage.top_unknown_endpoints.iter().enumerate() {
+            println!(
+                "  {:<3}  {:<40}  {:>8}  {:>7.2}%",
+                (i + 1).to_string().dimmed(),
+                truncate(&item.value, 40).cyan(),
+                item.count,
+                item.percentage
+            );
+        }
+    }
+}
+
+/// Per-operation traffic and error rates for an OpenAPI spec
+fn print_spec_correlation(correlation: &SpecCorrelation) {
+    section_header("OPENAPI OPERATIONS");
+    println!(
+        "  {:<3}  {:<40}  {:>8}  {:>6}  {:>6}  {:>8}",
+        "#", "Operation", "Requests", "4xx", "5xx", "Err Rate"
+    );
+    println!("  {}", &THIN_SEP[..84]);
+    for (i, op) in correlation.operations.iter().enumerate() {
+        let label = truncate(&op.operation, 40);
+        let label = if op.requests == 0 { label.dimmed() } else { label.cyan() };
+        println!(
+            "  {:<3}  {:<40}  {:>8}  {:>6}  {:>6}  {:>7.1}%",
+            (i + 1).to_string().dimmed(),
+            label,
+            op.requests,
+            op.client_errors,
+            op.server_errors,
+            op.error_rate
+        );
+    }
+    println!();
+    println!(
+        "  {} requests matched no operation in the spec",
+        correlation.unknown_requests.to_string().yellow().bold()
+    );
+    for item in &correlation.top_unknown_endpoints {
+        println!("    {:<40}  {:>8}", truncate(&item.value, 40).yellow(), item.count);
+    }
+}
+
+/// Export the analysis statistics as JSON to the given path
+pub fn export_json(stats: &AnalysisStats, path: &Path) -> Result<(), io::Error> {
+    let json = serde_json::to_string_pretty(stats).map_err(serialization_error)?;
+    std::fs::write(path, json)
+}
+
+/// Like [`export_json`], but with every object's keys sorted so that output is
+/// byte-for-byte reproducible across runs
+pub fn export_json_deterministic(stats: &AnalysisStats, path: &Path) -> Result<(), io::Error> {
+    // serde_json::Value stores objects in a BTreeMap, which sorts HashMap-backed fields
+    let value = serde_json::to_value(stats).map_err(serialization_error)?;
+    let json = serde_json::to_string_pretty(&value).map_err(serialization_error)?;
+    std::fs::write(path, json)
+}
+
+fn serialization_error(e: serde_json::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("serialization failed: {}", e))
+}
+
+/// Render the analysis statistics as a self-contained HTML page
+pub fn render_html(stats: &AnalysisStats, source: &str) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Log Analysis Report</title>\n<style>\n");
+    html.push_str(
+        "body { font-family: sans-serif; margin: 2em; color: #222; }\n\
+         table { border-collapse: collapse; margin-bottom: 1.5em; }\n\
+         th, td { border: 1px solid #ccc; padding: 4px 10px; text-align: left; }\n\
+         th { background: #f0f0f0; }\n\
+         td.num { text-align: right; }\n",
+    );
+    html.push_str("</style>\n</head>\n<body>\n");
+    html.push_str("<h1>Log Analysis Report</h1>\n");
+    html.push_str(&format!("<p>Source: <code>{}</code></p>\n", escape_html(source)));
+    if let Some(detection) = &stats.detected_format {
+        html.push_str(&format!(
+            "<p>Format: <code>{}</code> (detected, {}/{} sampled lines matched)</p>\n",
+            escape_html(&detection.format),
+            detection.matched,
+            detection.sampled
+        ));
+    }
+    if let Some(reason) = &stats.incomplete {
+        html.push_str(&format!("<p><strong>Partial results:</strong> {}</p>\n", escape_html(reason)));
+    }
+    if let Some(sample) = &stats.sample {
+        html.push_str(&format!(
+            "<p><strong>Sampled:</strong> {}; counts are estimates</p>\n",
+            escape_html(&sample_note(sample))
+        ));
+    }
+
+    // ── Overview ──────────────────────────────────────────────────────────────
+    html.push_str("<h2>Overview</h2>\n<table>\n");
+    html.push_str(&format!(
+        "<tr><th>Total entries parsed</th><td class=\"num\">{}</td></tr>\n",
+        stats.total_entries
+    ));
+    html.push_str(&format!(
+        "<tr><th>Malformed / skipped lines</th><td class=\"num\">{}</td></tr>\n",
+        stats.malformed_entries
+    ));
+    if let Some(non_access) = stats.non_access_lines {
+        html.push_str(&format!(
+            "<tr><th>Non-access lines (mixed)</th><td class=\"num\">{}</td></tr>\n",
+            non_access
+        ));
+    }
+    if let Some(continuation) = stats.continuation_lines {
+        html.push_str(&format!(
+            "<tr><th>Continuation lines</th><td class=\"num\">{}</td></tr>\n",
+            continuation
+        ));
+    }
+    if let Some(partial) = stats.partial_entries {
+        html.push_str(&format!(
+            "<tr><th>Partial entries (lenient)</th><td class=\"num\">{}</td></tr>\n",
+            partial
+        ));
+    }
+    if let Some(recovered) = stats.recovered_entries {
+        html.push_str(&format!(
+            "<tr><th>Recovered lines (fuzzy)</th><td class=\"num\">{}</td></tr>\n",
+            recovered
+        ));
+    }
+    if let Some(forwarded) = stats.forwarded_clients {
+        html.push_str(&format!(
+            "<tr><th>Clients via X-Forwarded-For</th><td class=\"num\">{}</td></tr>\n",
+            forwarded
+        ));
+    }
+    html.push_str("</table>\n");
+
+    if let Some(files) = &stats.files {
+        html.push_str("<h2>Input Files</h2>\n<table>\n");
+        html.push_str("<tr><th>File</th><th>Format</th><th>Parsed</th><th>Malformed</th></tr>\n");
+        for file in files {
+            html.push_str(&format!(
+                "<tr><td><code>{}</code></td><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>\n",
+                escape_html(&file.path),
+                escape_html(file.format.as_deref().unwrap_or("-")),
+                file.parsed,
+                file.malformed
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    // ── Log Level Breakdown ───────────────────────────────────────────────────
+    html.push_str("<h2>Log Level Breakdown</h2>\n<table>\n");
+    html.push_str("<tr><th>Level</th><th>Count</th><th>Share</th></tr>\n");
+    for level in &LogLevel::ALL {
+        let level_name = level.to_string();
+        if let Some(lc) = stats.level_counts.get(&level_name) {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{:.1}%</td></tr>\n",
+                level_name, lc.count, lc.percentage
+            ));
+        }
+    }
+    html.push_str("</table>\n");
+
+    // ── Status Code Distribution ──────────────────────────────────────────────
+    html.push_str("<h2>Status Code Distribution</h2>\n<table>\n");
+    html.push_str("<tr><th>Status</th><th>Count</th></tr>\n");
+    let mut status_vec: Vec<(&String, &usize)> = stats.status_code_distribution.iter().collect();
+    status_vec.sort_by_key(|(k, _)| k.parse::<u16>().unwrap_or(0));
+    for (code, count) in status_vec {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td class=\"num\">{}</td></tr>\n",
+            escape_html(code),
+            count
+        ));
+    }
+    html.push_str("</table>\n");
+
+    // ── Ranked tables ─────────────────────────────────────────────────────────
+    for (title, items) in [
+        (format!("Top {} IP Addresses", stats.top_n), &stats.top_ips),
+        (format!("Top {} Endpoints", stats.top_n), &stats.top_endpoints),
+    ] {
+        html.push_str(&format!("<h2>{}</h2>\n<table>\n", title));
+        html.push_str("<tr><th>#</th><th>Value</th><th>Requests</th><th>Share</th></tr>\n");
+        for (i, item) in items.iter().enumerate() {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td><code>{}</code></td><td class=\"num\">{}</td><td class=\"num\">{:.2}%</td></tr>\n",
+                i + 1,
+                escape_html(&item.value),
+                item.count,
+                item.percentage
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    // ── Flagged IPs ───────────────────────────────────────────────────────────
+    html.push_str(&format!(
+        "<h2>Flagged IPs — {} Count &gt; {}</h2>\n",
+        match stats.flag_on {
+            FlagBasis::Level => "Error",
+            FlagBasis::ServerErrors => "5xx",
+        },
+        stats.error_threshold
+    ));
+    if stats.flagged_ips.is_empty() {
+        html.push_str("<p>No IPs exceeded the error threshold.</p>\n");
+    } else {
+        let networks = stats.networks.is_some();
+        html.push_str(&format!(
+            "<table>\n<tr><th>#</th><th>IP Address</th><th>Errors</th><th>Total</th><th>Error Rate</th>{}</tr>\n",
+            if networks { "<th>Network</th>" } else { "" }
+        ));
+        for (i, item) in stats.flagged_ips.iter().enumerate() {
+            let network = match &item.network {
+                Some(network) => format!("<td>{}</td>", escape_html(&network.label())),
+                None if networks => "<td>-</td>".to_string(),
+                None => String::new(),
+            };
+            html.push_str(&format!(
+                "<tr><td>{}</td><td><code>{}</code></td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{:.1}%</td>{}</tr>\n",
+                i + 1,
+                escape_html(&item.ip),
+                item.error_count,
+                item.total_requests,
+                item.error_rate,
+                network
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+// ─── Helpers ─────────────────────────────────────────────────────────────────
+
+/// How much of the input a sampled report read, e.g. `1% of lines (1000 of 100000)`
+fn sample_note(sample: &SampleSummary) -> String {
+    let percent = format!("{:.2}", sample.rate * 100.0);
+    format!(
+        "{}% of lines ({} of {})",
+        percent.trim_end_matches('0').trim_end_matches('.'),
+        sample.lines_sampled,
+        sample.lines_read
+    )
+}
+
+fn section_header(title: &str) {
+    println!("  {} {}", "▶".cyan(), title.white().bold());
+    println!("  {}", THIN_SEP);
+}
+
+/// Renders a compact ASCII progress bar of the given width
+fn mini_bar(pct: f64, width: usize) -> String {
+    let filled = ((pct / 100.0) * width as f64).round() as usize;
+    let filled = filled.min(width);
+    let empty = width - filled;
+    format!(
+        "{}{}",
+        "█".repeat(filled).green(),
+        "░".repeat(empty).dimmed()
+    )
+}
+
+/// Width of an IP column: wide enough for any IPv4 address, growing for IPv6
+fn ip_width<'a>(ips: impl Iterator<Item = &'a str>) -> usize {
+    ips.map(str::len).max().unwrap_or(0).max(17)
+}
+
+fn has_activity(items: &[RankedItem]) -> bool {
+    items.iter().any(|item| item.activity.is_some())
+}
+
+/// Header for the optional first-seen / last-seen / peak-minute columns (UTC)
+fn activity_header() -> String {
+    format!(
+        "  {:<19}  {:<19}  {:<16}  {:>5}",
+        "First Seen (UTC)", "Last Seen (UTC)", "Peak Minute", "Peak"
+    )
+}
+
+fn activity_columns(item: &RankedItem) -> String {
+    const SECONDS: &str = "%Y-%m-%d %H:%M:%S";
+    const MINUTES: &str = "%Y-%m-%d %H:%M";
+    match &item.activity {
+        Some(a) => format!(
+            "  {:<19}  {:<19}  {:<16}  {:>5}",
+            a.first_seen.format(SECONDS).to_string(),
+            a.last_seen.format(SECONDS).to_string(),
+            a.peak_minute.format(MINUTES).to_string().yellow(),
+            a.peak_minute_requests
+        ),
+        None => format!("  {:<19}  {:<19}  {:<16}  {:>5}", "-", "-", "-", "-"),
+    }
+}
+
+/// Renders one block character per value, scaled to the largest value
+fn sparkline(values: &[f64]) -> String {
+    const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().cloned().fold(0.0_f64, f64::max);
+    values
+        .iter()
+        .map(|&v| {
+            if max <= 0.0 || v <= 0.0 {
+                ' '
+            } else {
+                let idx = ((v / max) * (LEVELS.len() - 1) as f64).round() as usize;
+                LEVELS[idx.min(LEVELS.len() - 1)]
+            }
+        })
+        .collect()
+}
+
+/// Format a byte count with a decimal unit suffix, e.g. `1.5 GB`
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Format a byte count with a binary unit suffix, e.g. `1.5 GiB`; bandwidth is
+/// reported this way, while costs stay in the decimal units they are priced in
+fn binary_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Shorten `s` to at most `max` characters, marking the cut with an ellipsis
+/// Like [`truncate`], but keep the end, which tells paths apart
+fn truncate_start(s: &str, max: usize) -> String {
+    let count = s.chars().count();
+    if count > max {
+        let cut: String = s.chars().skip(count - (max - 1)).collect();
+        format!("…{}", cut)
+    } else {
+        s.to_string()
+    }
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() > max {
+        let cut: String = s.chars().take(max - 1).collect();
+        format!("{}…", cut)
+    } else {
+        s.to_string()
+    }
+}
+
+/// Escape the characters that are significant in HTML text and attribute values
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
 }
 
 /// Colorize HTTP status code based on category
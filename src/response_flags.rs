@@ -0,0 +1,162 @@
+use crate::analyzer::RankedItem;
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Envoy response flags and what they mean
+const FLAG_MEANINGS: [(&str, &str); 27] = [
+    ("UH", "no healthy upstream host"),
+    ("UF", "upstream connection failure"),
+    ("UO", "upstream overflow (circuit breaker)"),
+    ("NR", "no route configured"),
+    ("URX", "upstream retry limit exceeded"),
+    ("NC", "upstream cluster not found"),
+    ("DT", "max connection duration reached"),
+    ("DC", "downstream connection termination"),
+    ("LH", "local service failed health check"),
+    ("UT", "upstream request timeout"),
+    ("LR", "connection local reset"),
+    ("UR", "upstream remote reset"),
+    ("UC", "upstream connection termination"),
+    ("DI", "delayed by fault injection"),
+    ("FI", "aborted by fault injection"),
+    ("RL", "rate limited locally"),
+    ("UAEX", "denied by external authorization"),
+    ("RLSE", "rate limit service error"),
+    ("IH", "invalid header value"),
+    ("SI", "stream idle timeout"),
+    ("DPE", "downstream protocol error"),
+    ("UPE", "upstream protocol error"),
+    ("UMSDR", "upstream max stream duration reached"),
+    ("OM", "overload manager"),
+    ("DF", "DNS resolution failed"),
+    ("DO", "dropped by overload"),
+    ("NFCF", "no filter chain found"),
+];
+
+/// What a response flag means, for the flags Envoy documents
+pub fn describe(flag: &str) -> Option<&'static str> {
+    FLAG_MEANINGS.iter().find(|(f, _)| *f == flag).map(|(_, meaning)| *meaning)
+}
+
+/// Requests carrying one response flag
+#[derive(Debug, Clone, Serialize)]
+pub struct FlagCount {
+    pub flag: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meaning: Option<&'static str>,
+    pub count: usize,
+    /// Share of requests that logged response flags
+    pub percentage: f64,
+}
+
+/// Why a proxy answered requests itself, and where it sent the others
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseFlagReport {
+    /// Requests whose line logged response flags (`-` included)
+    pub requests: usize,
+    /// Requests with at least one flag set
+    pub flagged: usize,
+    /// Flags by the number of requests carrying them; a request may carry several
+    pub flags: Vec<FlagCount>,
+    /// Upstream hosts by the number of requests forwarded to them
+    pub top_upstream_hosts: Vec<RankedItem>,
+}
+
+/// Count response flags and upstream hosts.
+///
+/// Returns `None` when no entry logs response flags, as for formats other than Envoy.
+pub fn analyze(entries: &[LogEntry], top_n: usize) -> Option<ResponseFlagReport> {
+    let mut requests = 0usize;
+    let mut flagged = 0usize;
+    let mut flags: HashMap<&str, usize> = HashMap::new();
+    let mut hosts: HashMap<&str, usize> = HashMap::new();
+    for entry in entries {
+        let Some(logged) = entry.response_flags.as_deref() else { continue };
+        requests += 1;
+        if logged != "-" {
+            flagged += 1;
+            for flag in logged.split(',').filter(|f| !f.is_empty()) {
+                *flags.entry(flag).or_insert(0) += 1;
+            }
+        }
+        if let Some(host) = entry.upstream_host.as_deref() {
+            *hosts.entry(host).or_insert(0) += 1;
+        }
+    }
+    if requests == 0 {
+        return None;
+    }
+    let pct = |n: usize| (n as f64 / requests as f64 * 10000.0).round() / 100.0;
+
+    let mut flags: Vec<FlagCount> = flags
+        .into_iter()
+        .map(|(flag, count)| FlagCount {
+            flag: flag.to_string(),
+            meaning: describe(flag),
+            count,
+            percentage: pct(count),
+        })
+        .collect();
+    flags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.flag.cmp(&b.flag)));
+
+    let mut top_upstream_hosts: Vec<RankedItem> = hosts
+        .into_iter()
+        .map(|(host, count)| RankedItem {
+            value: host.to_string(),
+            count,
+            percentage: pct(count),
+            activity: None,
+        })
+        .collect();
+    top_upstream_hosts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    top_upstream_hosts.truncate(top_n);
+
+    Some(ResponseFlagReport {
+        requests,
+        flagged,
+        flags,
+        top_upstream_hosts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_line, LogFormat};
+
+    #[test]
+    fn counts_each_flag_of_a_request() {
+        let line = |flags: &str, upstream: &str| {
+            let line = format!(
+                r#"[2024-01-15T10:30:00.000Z] "GET / HTTP/1.1" 503 {} 0 0 1 - "10.0.0.1" "-" "id" "api" "{}""#,
+                flags, upstream
+            );
+            parse_line(&line, &LogFormat::Envoy).unwrap()
+        };
+        let entries = vec![
+            line("UF,URX", "-"),
+            line("UF", "10.0.2.1:80"),
+            line("-", "10.0.2.1:80"),
+            line("-", "10.0.2.2:80"),
+        ];
+        let report = analyze(&entries, 10).unwrap();
+
+        assert_eq!(report.requests, 4);
+        assert_eq!(report.flagged, 2);
+        assert_eq!(report.flags[0].flag, "UF");
+        assert_eq!(report.flags[0].count, 2);
+        assert_eq!(report.flags[0].percentage, 50.0);
+        assert_eq!(report.flags[0].meaning, Some("upstream connection failure"));
+        assert_eq!(report.flags[1].flag, "URX");
+        assert_eq!(report.top_upstream_hosts[0].value, "10.0.2.1:80");
+        assert_eq!(report.top_upstream_hosts[0].count, 2);
+        assert_eq!(describe("XYZ"), None);
+    }
+
+    #[test]
+    fn omitted_without_response_flags() {
+        let entry = crate::parser::parse_log_line("2024-01-15T10:30:00Z [INFO] 1.1.1.1 GET / 200").unwrap();
+        assert!(analyze(&[entry], 10).is_none());
+    }
+}
@@ -0,0 +1,262 @@
+use crate::analyzer::RankedItem;
+use crate::parser::LogEntry;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// The path crawlers fetch before (politely) crawling a site
+const ROBOTS_PATH: &str = "/robots.txt";
+
+/// One `Allow`/`Disallow` line from the `User-agent: *` group
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// The rules a robots.txt file sets for all crawlers
+#[derive(Debug, Clone, Default)]
+pub struct RobotsRules {
+    rules: Vec<Rule>,
+    pub sitemaps: Vec<String>,
+}
+
+impl RobotsRules {
+    /// Parse robots.txt, keeping the rules of the `User-agent: *` group(s).
+    ///
+    /// Access logs carry no user agent here, so per-agent groups cannot be applied.
+    pub fn parse(text: &str) -> Self {
+        let mut robots = RobotsRules::default();
+        let mut in_wildcard_group = false;
+        let mut group_has_rules = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else { continue };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    // Consecutive user-agent lines share one group
+                    if group_has_rules {
+                        in_wildcard_group = false;
+                        group_has_rules = false;
+                    }
+                    in_wildcard_group |= value == "*";
+                }
+                field @ ("allow" | "disallow") => {
+                    group_has_rules = true;
+                    // An empty Disallow allows everything, so it adds no rule
+                    if in_wildcard_group && !value.is_empty() {
+                        robots.rules.push(Rule {
+                            allow: field == "allow",
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "sitemap" => robots.sitemaps.push(value.to_string()),
+                _ => {}
+            }
+        }
+        robots
+    }
+
+    /// Whether crawling `path` is allowed: the longest matching rule wins, and
+    /// `Allow` wins a tie
+    pub fn is_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|r| pattern_matches(&r.pattern, path))
+            .max_by_key(|r| (r.pattern.len(), r.allow))
+            .is_none_or(|r| r.allow)
+    }
+}
+
+/// Match a robots.txt path pattern supporting `*` wildcards and a trailing `$` anchor
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        let last = i == parts.len() - 1;
+        if last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+/// Errors that can occur while loading a robots.txt file
+#[derive(Debug)]
+pub struct RobotsError(std::io::Error);
+
+impl fmt::Display for RobotsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "could not read robots.txt: {}", self.0)
+    }
+}
+
+/// Read and parse a robots.txt file
+pub fn load_robots(path: &Path) -> Result<RobotsRules, RobotsError> {
+    fs::read_to_string(path).map(|text| RobotsRules::parse(&text)).map_err(RobotsError)
+}
+
+/// A crawler and how many of its requests ignored robots.txt
+#[derive(Debug, Clone, Serialize)]
+pub struct CrawlerCompliance {
+    pub ip: String,
+    /// Requests made after the crawler first fetched robots.txt
+    pub requests: usize,
+    pub violations: usize,
+    pub violation_rate: f64,
+}
+
+/// How well self-identified crawlers respected robots.txt
+#[derive(Debug, Clone, Serialize)]
+pub struct RobotsCompliance {
+    pub rules: usize,
+    pub sitemaps: Vec<String>,
+    /// Clients that fetched robots.txt, i.e. identified themselves as crawlers
+    pub crawlers: usize,
+    pub crawler_requests: usize,
+    pub violations: usize,
+    /// Crawlers with at least one violation, worst first
+    pub top_violators: Vec<CrawlerCompliance>,
+    pub top_disallowed_paths: Vec<RankedItem>,
+}
+
+/// Find crawler requests to disallowed paths.
+///
+/// Without user agents a crawler is recognised by behaviour: any IP that
+/// requested `/robots.txt`. Only its requests from that point on are checked,
+/// since it could not have known the rules before.
+pub fn compliance(entries: &[LogEntry], robots: &RobotsRules, top_n: usize) -> RobotsCompliance {
+    let mut first_fetch: HashMap<IpAddr, DateTime<Utc>> = HashMap::new();
+    for entry in entries.iter().filter(|e| is_robots_fetch(e)) {
+        first_fetch
+            .entry(entry.ip)
+            .and_modify(|t| *t = (*t).min(entry.timestamp))
+            .or_insert(entry.timestamp);
+    }
+
+    let mut per_crawler: HashMap<IpAddr, (usize, usize)> = HashMap::new();
+    let mut disallowed_paths: HashMap<&str, usize> = HashMap::new();
+    for entry in entries {
+        let Some(fetched) = first_fetch.get(&entry.ip) else { continue };
+        if is_robots_fetch(entry) || entry.timestamp < *fetched {
+            continue;
+        }
+        let slot = per_crawler.entry(entry.ip).or_default();
+        slot.0 += 1;
+        if !robots.is_allowed(&entry.endpoint) {
+            slot.1 += 1;
+            *disallowed_paths.entry(entry.endpoint.as_str()).or_insert(0) += 1;
+        }
+    }
+
+    let violations: usize = per_crawler.values().map(|c| c.1).sum();
+    let mut violators: Vec<(IpAddr, usize, usize)> = per_crawler
+        .iter()
+        .filter(|(_, c)| c.1 > 0)
+        .map(|(&ip, &(requests, violations))| (ip, requests, violations))
+        .collect();
+    violators.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    let top_violators = violators
+        .into_iter()
+        .take(top_n)
+        .map(|(ip, requests, violations)| CrawlerCompliance {
+            ip: ip.to_string(),
+            requests,
+            violations,
+            violation_rate: (violations as f64 / requests as f64 * 1000.0).round() / 10.0,
+        })
+        .collect();
+
+    let mut top_disallowed_paths: Vec<RankedItem> = disallowed_paths
+        .into_iter()
+        .map(|(path, count)| RankedItem {
+            value: path.to_string(),
+            count,
+            percentage: (count as f64 / violations as f64 * 10000.0).round() / 100.0,
+            activity: None,
+        })
+        .collect();
+    top_disallowed_paths.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    top_disallowed_paths.truncate(top_n);
+
+    RobotsCompliance {
+        rules: robots.rules.len(),
+        sitemaps: robots.sitemaps.clone(),
+        crawlers: first_fetch.len(),
+        crawler_requests: per_crawler.values().map(|c| c.0).sum(),
+        violations,
+        top_violators,
+        top_disallowed_paths,
+    }
+}
+
+fn is_robots_fetch(entry: &LogEntry) -> bool {
+    entry.endpoint.split('?').next() == Some(ROBOTS_PATH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_log_line;
+
+    const ROBOTS: &str = "\
+User-agent: Googlebot
+Disallow: /
+
+User-agent: *
+Disallow: /admin
+Disallow: /*.pdf$
+Allow: /admin/public
+Sitemap: https://example.com/sitemap.xml
+";
+
+    #[test]
+    fn applies_wildcard_group_with_longest_match() {
+        let robots = RobotsRules::parse(ROBOTS);
+        assert_eq!(robots.sitemaps, ["https://example.com/sitemap.xml"]);
+        assert!(robots.is_allowed("/"));
+        assert!(!robots.is_allowed("/admin/users"));
+        assert!(robots.is_allowed("/admin/public/faq"));
+        assert!(!robots.is_allowed("/docs/guide.pdf"));
+        assert!(robots.is_allowed("/docs/guide.pdf?download=1"));
+    }
+
+    #[test]
+    fn counts_violations_after_robots_fetch() {
+        let lines = [
+            "2024-01-15T10:00:00Z [INFO] 6.6.6.6 GET /admin 200", // before fetching robots.txt
+            "2024-01-15T10:00:01Z [INFO] 6.6.6.6 GET /robots.txt 200",
+            "2024-01-15T10:00:02Z [INFO] 6.6.6.6 GET /admin/users 200",
+            "2024-01-15T10:00:03Z [INFO] 6.6.6.6 GET /products 200",
+            "2024-01-15T10:00:04Z [INFO] 1.1.1.1 GET /admin 200", // not a crawler
+        ];
+        let entries: Vec<LogEntry> = lines.iter().map(|l| parse_log_line(l).unwrap()).collect();
+        let report = compliance(&entries, &RobotsRules::parse(ROBOTS), 10);
+
+        assert_eq!(report.crawlers, 1);
+        assert_eq!(report.crawler_requests, 2);
+        assert_eq!(report.violations, 1);
+        assert_eq!(report.top_violators[0].ip, "6.6.6.6");
+        assert_eq!(report.top_violators[0].violation_rate, 50.0);
+        assert_eq!(report.top_disallowed_paths[0].value, "/admin/users");
+    }
+}
@@ -0,0 +1,115 @@
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Requests matched by one proxy router
+#[derive(Debug, Clone, Serialize)]
+pub struct RouterCount {
+    pub router: String,
+    /// The service that received most of the router's requests, when logged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    pub requests: usize,
+    /// Share of routed requests
+    pub percentage: f64,
+    /// Requests answered with a 5xx status
+    pub server_errors: usize,
+    pub error_rate: f64,
+}
+
+/// Traffic per proxy router, for proxies (such as Traefik) that log which one matched
+#[derive(Debug, Clone, Serialize)]
+pub struct RouterReport {
+    /// Requests that logged a router
+    pub routed: usize,
+    /// Requests without one, e.g. those no router matched
+    pub unrouted: usize,
+    pub routers: Vec<RouterCount>,
+}
+
+/// Count requests per router, busiest first.
+///
+/// Returns `None` when no entry logs a router.
+pub fn analyze(entries: &[LogEntry], top_n: usize) -> Option<RouterReport> {
+    #[derive(Default)]
+    struct Tally<'a> {
+        requests: usize,
+        server_errors: usize,
+        services: HashMap<&'a str, usize>,
+    }
+
+    let mut routers: HashMap<&str, Tally> = HashMap::new();
+    for entry in entries {
+        let Some(router) = entry.router.as_deref() else { continue };
+        let tally = routers.entry(router).or_default();
+        tally.requests += 1;
+        if entry.status_code.is_some_and(|code| code >= 500) {
+            tally.server_errors += 1;
+        }
+        if let Some(service) = entry.service.as_deref() {
+            *tally.services.entry(service).or_insert(0) += 1;
+        }
+    }
+    let routed: usize = routers.values().map(|t| t.requests).sum();
+    if routed == 0 {
+        return None;
+    }
+    let pct = |n: usize, of: usize| (n as f64 / of as f64 * 10000.0).round() / 100.0;
+
+    let mut ranked: Vec<RouterCount> = routers
+        .into_iter()
+        .map(|(router, tally)| RouterCount {
+            router: router.to_string(),
+            service: tally
+                .services
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+                .map(|(service, _)| service.to_string()),
+            requests: tally.requests,
+            percentage: pct(tally.requests, routed),
+            server_errors: tally.server_errors,
+            error_rate: pct(tally.server_errors, tally.requests),
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.router.cmp(&b.router)));
+    ranked.truncate(top_n);
+
+    Some(RouterReport {
+        routed,
+        unrouted: entries.len() - routed,
+        routers: ranked,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_line, LogFormat};
+
+    #[test]
+    fn counts_requests_and_errors_per_router() {
+        let line = |router: &str, status: u16| {
+            let line = format!(
+                r#"10.0.0.1 - - [15/Jan/2024:10:30:00 +0000] "GET / HTTP/1.1" {} 5 "-" "-" 1 "{}" "-" 1ms"#,
+                status, router
+            );
+            parse_line(&line, &LogFormat::Traefik).unwrap()
+        };
+        let entries = vec![
+            line("api@docker", 200),
+            line("api@docker", 502),
+            line("web@docker", 200),
+            line("-", 404),
+        ];
+        let report = analyze(&entries, 10).unwrap();
+
+        assert_eq!((report.routed, report.unrouted), (3, 1));
+        assert_eq!(report.routers[0].router, "api@docker");
+        assert_eq!(report.routers[0].requests, 2);
+        assert_eq!(report.routers[0].percentage, 66.67);
+        assert_eq!(report.routers[0].server_errors, 1);
+        assert_eq!(report.routers[0].error_rate, 50.0);
+        assert_eq!(report.routers[1].service, None);
+        assert!(analyze(&entries[3..], 10).is_none());
+    }
+}
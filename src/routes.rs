@@ -0,0 +1,445 @@
+use crate::analyzer::RankedItem;
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// A single path segment of a route template
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// Must match the logged segment exactly
+    Literal(String),
+    /// `{id}` or `:id` — matches any single non-empty segment
+    Param,
+    /// Trailing `*` — matches the remainder of the path
+    Rest,
+}
+
+/// An expected endpoint, loaded from a routes file or an OpenAPI spec
+#[derive(Debug, Clone)]
+pub struct Route {
+    /// HTTP method the route is restricted to, or `None` for any method
+    pub method: Option<String>,
+    /// The route template as written in the source, e.g. `/users/{id}`
+    pub template: String,
+    /// `operationId` from the OpenAPI spec, when the route came from one
+    pub operation_id: Option<String>,
+    segments: Vec<Segment>,
+}
+
+impl Route {
+    pub fn new(method: Option<&str>, template: &str) -> Self {
+        let segments = split_path(template)
+            .map(|seg| {
+                if seg == "*" {
+                    Segment::Rest
+                } else if (seg.starts_with('{') && seg.ends_with('}')) || seg.starts_with(':') {
+                    Segment::Param
+                } else {
+                    Segment::Literal(seg.to_string())
+                }
+            })
+            .collect();
+        Route {
+            method: method.map(|m| m.to_ascii_uppercase()),
+            template: template.to_string(),
+            operation_id: None,
+            segments,
+        }
+    }
+
+    /// How specific the template is; literal segments outrank parameters, so
+    /// `/users/me` wins over `/users/{id}` when both match
+    fn specificity(&self) -> (usize, usize) {
+        let literals = self
+            .segments
+            .iter()
+            .filter(|s| matches!(s, Segment::Literal(_)))
+            .count();
+        let bounded = usize::from(!self.segments.contains(&Segment::Rest));
+        (literals, bounded)
+    }
+
+    /// Whether a logged endpoint (query string ignored) matches this route's path
+    pub fn matches_path(&self, endpoint: &str) -> bool {
+        let path = endpoint.split(['?', '#']).next().unwrap_or(endpoint);
+        let mut logged = split_path(path);
+        for segment in &self.segments {
+            match segment {
+                Segment::Rest => return true,
+                Segment::Param => {
+                    if logged.next().is_none() {
+                        return false;
+                    }
+                }
+                Segment::Literal(expected) => {
+                    if logged.next() != Some(expected.as_str()) {
+                        return false;
+                    }
+                }
+            }
+        }
+        logged.next().is_none()
+    }
+
+    /// Whether a log entry matches this route's method and path
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        let method_ok = match &self.method {
+            Some(m) => entry.method.to_string() == *m,
+            None => true,
+        };
+        method_ok && self.matches_path(&entry.endpoint)
+    }
+
+    /// Human-readable label, e.g. `GET /users/{id}`
+    pub fn label(&self) -> String {
+        match &self.method {
+            Some(m) => format!("{} {}", m, self.template),
+            None => self.template.clone(),
+        }
+    }
+}
+
+/// The most specific route whose path matches `endpoint`, whatever the method
+pub fn most_specific<'a>(routes: &'a [Route], endpoint: &str) -> Option<&'a Route> {
+    routes
+        .iter()
+        .filter(|route| route.matches_path(endpoint))
+        .max_by_key(|route| route.specificity())
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+/// Errors that can occur while loading expected routes
+#[derive(Debug)]
+pub enum RouteError {
+    Io(std::io::Error),
+    /// The file looked like an OpenAPI spec but could not be parsed
+    InvalidSpec(String),
+}
+
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteError::Io(e) => write!(f, "{}", e),
+            RouteError::InvalidSpec(msg) => write!(f, "invalid OpenAPI spec: {}", msg),
+        }
+    }
+}
+
+impl From<std::io::Error> for RouteError {
+    fn from(e: std::io::Error) -> Self {
+        RouteError::Io(e)
+    }
+}
+
+/// Load the operations of an OpenAPI/Swagger spec (`.json`, `.yaml` or `.yml`)
+pub fn load_spec(path: &Path) -> Result<Vec<Route>, RouteError> {
+    let contents = std::fs::read_to_string(path)?;
+    let is_json = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    let spec: serde_json::Value = if is_json {
+        serde_json::from_str(&contents).map_err(|e| RouteError::InvalidSpec(e.to_string()))?
+    } else {
+        parse_yaml(&contents)?
+    };
+    routes_from_spec(&spec)
+}
+
+#[cfg(feature = "yaml")]
+fn parse_yaml(contents: &str) -> Result<serde_json::Value, RouteError> {
+    serde_yaml::from_str(contents).map_err(|e| RouteError::InvalidSpec(e.to_string()))
+}
+
+#[cfg(not(feature = "yaml"))]
+fn parse_yaml(_contents: &str) -> Result<serde_json::Value, RouteError> {
+    Err(RouteError::InvalidSpec(
+        "YAML specs need the `yaml` feature; rebuild with it or convert the spec to JSON".to_string(),
+    ))
+}
+
+/// Load expected routes from `path`.
+///
+/// Files ending in `.json`, `.yaml` or `.yml` are read as OpenAPI/Swagger specs;
+/// anything else is a plain routes file with one `[METHOD] /path` per line and
+/// `#` comments.
+pub fn load_routes(path: &Path) -> Result<Vec<Route>, RouteError> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "json" | "yaml" | "yml" => load_spec(path),
+        _ => Ok(parse_routes_file(&std::fs::read_to_string(path)?)),
+    }
+}
+
+/// Parse a plain routes file: one `[METHOD] /path` per line, `#` starts a comment
+pub fn parse_routes_file(contents: &str) -> Vec<Route> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once(char::is_whitespace) {
+            Some((method, path)) => Route::new(Some(method), path.trim()),
+            None => Route::new(None, line),
+        })
+        .collect()
+}
+
+/// Extract routes from the `paths` object of an OpenAPI 3 / Swagger 2 document
+pub fn routes_from_spec(spec: &serde_json::Value) -> Result<Vec<Route>, RouteError> {
+    const METHODS: [&str; 8] = ["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+
+    let paths = spec
+        .get("paths")
+        .and_then(|p| p.as_object())
+        .ok_or_else(|| RouteError::InvalidSpec("missing `paths` object".to_string()))?;
+
+    let mut routes = Vec::new();
+    for (template, item) in paths {
+        let Some(ops) = item.as_object() else { continue };
+        for method in METHODS {
+            if let Some(op) = ops.get(method) {
+                let mut route = Route::new(Some(method), template);
+                route.operation_id = op
+                    .get("operationId")
+                    .and_then(|id| id.as_str())
+                    .map(str::to_string);
+                routes.push(route);
+            }
+        }
+    }
+    Ok(routes)
+}
+
+/// Comparison between the expected routes and the endpoints actually logged
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointCoverage {
+    pub expected_routes: usize,
+    /// Expected routes that received no traffic in the analyzed window
+    pub idle_routes: Vec<String>,
+    /// Total requests to endpoints that match no expected route
+    pub unknown_requests: usize,
+    /// The most-requested endpoints that match no expected route
+    pub top_unknown_endpoints: Vec<RankedItem>,
+}
+
+/// Match every entry against the expected routes, reporting dead and undocumented endpoints
+pub fn coverage(entries: &[LogEntry], routes: &[Route], top_n: usize) -> EndpointCoverage {
+    let mut hits = vec![0usize; routes.len()];
+    let mut unknown: HashMap<&str, usize> = HashMap::new();
+    let mut unknown_requests = 0usize;
+
+    for entry in entries {
+        let mut matched = false;
+        for (i, route) in routes.iter().enumerate() {
+            if route.matches(entry) {
+                hits[i] += 1;
+                matched = true;
+            }
+        }
+        if !matched {
+            unknown_requests += 1;
+            *unknown.entry(entry.endpoint.as_str()).or_insert(0) += 1;
+        }
+    }
+
+    let idle_routes = routes
+        .iter()
+        .zip(&hits)
+        .filter(|(_, &n)| n == 0)
+        .map(|(route, _)| route.label())
+        .collect();
+
+    EndpointCoverage {
+        expected_routes: routes.len(),
+        idle_routes,
+        unknown_requests,
+        top_unknown_endpoints: rank(unknown, entries.len(), top_n),
+    }
+}
+
+/// Traffic and error counts for a single spec operation
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationStats {
+    /// `METHOD /template`
+    pub operation: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation_id: Option<String>,
+    pub requests: usize,
+    pub client_errors: usize,
+    pub server_errors: usize,
+    /// Share of the operation's requests that returned 4xx or 5xx
+    pub error_rate: f64,
+}
+
+/// Logged traffic mapped onto the operations of an OpenAPI spec
+#[derive(Debug, Clone, Serialize)]
+pub struct SpecCorrelation {
+    /// Every spec operation, busiest first (idle operations included with zero requests)
+    pub operations: Vec<OperationStats>,
+    /// Requests that matched no operation in the spec
+    pub unknown_requests: usize,
+    pub top_unknown_endpoints: Vec<RankedItem>,
+}
+
+/// Map each entry to its most specific matching operation and aggregate per-operation stats
+pub fn correlate(entries: &[LogEntry], routes: &[Route], top_n: usize) -> SpecCorrelation {
+    let mut ops: Vec<OperationStats> = routes
+        .iter()
+        .map(|route| OperationStats {
+            operation: route.label(),
+            operation_id: route.operation_id.clone(),
+            requests: 0,
+            client_errors: 0,
+            server_errors: 0,
+            error_rate: 0.0,
+        })
+        .collect();
+    let mut unknown: HashMap<&str, usize> = HashMap::new();
+    let mut unknown_requests = 0usize;
+
+    for entry in entries {
+        let best = routes
+            .iter()
+            .enumerate()
+            .filter(|(_, route)| route.matches(entry))
+            .max_by_key(|(_, route)| route.specificity());
+        match best {
+            Some((i, _)) => {
+                let op = &mut ops[i];
+                op.requests += 1;
+                match entry.status_code {
+                    Some(400..=499) => op.client_errors += 1,
+                    Some(500..=599) => op.server_errors += 1,
+                    _ => {}
+                }
+            }
+            None => {
+                unknown_requests += 1;
+                *unknown.entry(entry.endpoint.as_str()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    for op in &mut ops {
+        if op.requests > 0 {
+            op.error_rate =
+                ((op.client_errors + op.server_errors) as f64 / op.requests as f64) * 100.0;
+        }
+    }
+    ops.sort_by(|a, b| b.requests.cmp(&a.requests).then(a.operation.cmp(&b.operation)));
+
+    SpecCorrelation {
+        operations: ops,
+        unknown_requests,
+        top_unknown_endpoints: rank(unknown, entries.len(), top_n),
+    }
+}
+
+/// Sort counts descending (ties alphabetically) and keep the top `top_n`
+fn rank(counts: HashMap<&str, usize>, total: usize, top_n: usize) -> Vec<RankedItem> {
+    let mut vec: Vec<(&str, usize)> = counts.into_iter().collect();
+    vec.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    vec.into_iter()
+        .take(top_n)
+        .map(|(ep, count)| RankedItem {
+            value: ep.to_string(),
+            count,
+            percentage: if total == 0 { 0.0 } else { (count as f64 / total as f64) * 100.0 },
+            activity: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{test_entry, HttpMethod};
+
+    const AT: &str = "2024-01-15T10:30:00Z";
+
+    #[test]
+    fn template_params_match_single_segments() {
+        let route = Route::new(None, "/users/{id}");
+        assert!(route.matches_path("/users/42"));
+        assert!(route.matches_path("/users/42?full=1"));
+        assert!(!route.matches_path("/users"));
+        assert!(!route.matches_path("/users/42/posts"));
+        assert!(Route::new(None, "/users/:id").matches_path("/users/7"));
+        assert!(Route::new(None, "/static/*").matches_path("/static/css/app.css"));
+    }
+
+    #[test]
+    fn parses_routes_file_with_methods_and_comments() {
+        let routes = parse_routes_file("# public API\nGET /api/users\n\n/health  # any method\n");
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].label(), "GET /api/users");
+        assert_eq!(routes[1].label(), "/health");
+    }
+
+    #[test]
+    fn reads_operations_from_openapi_paths() {
+        let spec = serde_json::json!({
+            "openapi": "3.0.0",
+            "paths": {
+                "/users/{id}": { "get": {}, "delete": {}, "parameters": [] }
+            }
+        });
+        let mut labels: Vec<String> =
+            routes_from_spec(&spec).unwrap().iter().map(Route::label).collect();
+        labels.sort();
+        assert_eq!(labels, vec!["DELETE /users/{id}", "GET /users/{id}"]);
+    }
+
+    #[test]
+    fn reports_idle_and_unknown_endpoints() {
+        let routes = parse_routes_file("GET /api/users\nGET /api/orders\n");
+        let entries = vec![
+            test_entry(AT, "1.2.3.4", "/api/users", 200),
+            test_entry(AT, "1.2.3.4", "/api/users", 200),
+            test_entry(AT, "1.2.3.4", "/debug", 200),
+            LogEntry { method: HttpMethod::Post, ..test_entry(AT, "1.2.3.4", "/api/users", 200) },
+        ];
+        let cov = coverage(&entries, &routes, 5);
+        assert_eq!(cov.expected_routes, 2);
+        assert_eq!(cov.idle_routes, vec!["GET /api/orders"]);
+        assert_eq!(cov.unknown_requests, 2);
+        assert_eq!(cov.top_unknown_endpoints.len(), 2);
+    }
+
+    #[test]
+    fn correlates_entries_with_most_specific_operation() {
+        let spec = serde_json::json!({
+            "paths": {
+                "/users/{id}": { "get": { "operationId": "getUser" } },
+                "/users/me": { "get": { "operationId": "getCurrentUser" } }
+            }
+        });
+        let routes = routes_from_spec(&spec).unwrap();
+        let entries = vec![
+            test_entry(AT, "1.2.3.4", "/users/me", 200),
+            test_entry(AT, "1.2.3.4", "/users/42", 404),
+            test_entry(AT, "1.2.3.4", "/users/43", 500),
+            test_entry(AT, "1.2.3.4", "/orders", 200),
+        ];
+        let corr = correlate(&entries, &routes, 5);
+        let get_user = corr
+            .operations
+            .iter()
+            .find(|op| op.operation_id.as_deref() == Some("getUser"))
+            .unwrap();
+        assert_eq!(get_user.requests, 2);
+        assert_eq!(get_user.client_errors, 1);
+        assert_eq!(get_user.server_errors, 1);
+        assert_eq!(get_user.error_rate, 100.0);
+        assert_eq!(corr.operations[1].requests, 1);
+        assert_eq!(corr.unknown_requests, 1);
+        assert_eq!(corr.top_unknown_endpoints[0].value, "/orders");
+    }
+}
@@ -0,0 +1,268 @@
+use crate::inputs::{self, GlobError, LogFile};
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use tokio::runtime::Runtime;
+
+/// An `s3://bucket/key` location; the key may be a prefix of several objects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Url {
+    pub bucket: String,
+    pub key: String,
+}
+
+impl S3Url {
+    /// Split `s3://bucket/key`, or `None` if `url` is not an S3 URL with a bucket
+    pub fn parse(url: &str) -> Option<S3Url> {
+        let rest = url.strip_prefix("s3://").or_else(|| url.strip_prefix("S3://"))?;
+        let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return None;
+        }
+        Some(S3Url {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        })
+    }
+
+    fn object(&self, key: &str) -> PathBuf {
+        PathBuf::from(format!("s3://{}/{}", self.bucket, key))
+    }
+}
+
+/// An S3 client and the runtime its requests run on; the rest of the tool is blocking
+pub struct S3 {
+    runtime: Arc<Runtime>,
+    client: Client,
+}
+
+impl S3 {
+    /// A client configured the way the AWS CLI is: credentials and region from the
+    /// environment, `~/.aws/config` and `~/.aws/credentials`, or the instance role
+    pub fn from_env() -> io::Result<S3> {
+        let runtime = runtime()?;
+        let config = runtime.block_on(aws_config::load_defaults(aws_config::BehaviorVersion::latest()));
+        Ok(S3 {
+            runtime: Arc::new(runtime),
+            client: Client::new(&config),
+        })
+    }
+
+    /// A client with an explicit configuration, such as for an S3-compatible store
+    pub fn with_config(config: aws_sdk_s3::Config) -> io::Result<S3> {
+        Ok(S3 {
+            runtime: Arc::new(runtime()?),
+            client: Client::from_conf(config),
+        })
+    }
+
+    /// The objects whose keys start with `url`'s key, as `s3://` URLs sorted by key.
+    ///
+    /// Keys ending in `/` (folder placeholders) are skipped, and so are objects that
+    /// `--include` and `--exclude` rule out; those patterns are matched against the
+    /// key below the prefix's last `/`, as for a directory (see [`inputs::walk`]).
+    pub fn list(&self, url: &S3Url, include: &[String], exclude: &[String]) -> Result<Vec<PathBuf>, GlobError> {
+        let listing_error = |error: io::Error| GlobError::Io {
+            path: url.object(&url.key),
+            error,
+        };
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&url.bucket)
+            .prefix(&url.key)
+            .into_paginator()
+            .send();
+        let base = url.key.rfind('/').map_or("", |at| &url.key[..=at]);
+        let mut keys = Vec::new();
+        while let Some(page) = self.runtime.block_on(pages.next()) {
+            let page = page.map_err(|e| listing_error(sdk_error(e)))?;
+            for object in page.contents() {
+                let Some(key) = object.key() else { continue };
+                let relative = Path::new(&key[base.len()..]);
+                if !key.ends_with('/') && inputs::is_included(relative, include, exclude) {
+                    keys.push(key.to_string());
+                }
+            }
+        }
+        if keys.is_empty() {
+            return Err(GlobError::NoMatch(url.object(&url.key).display().to_string()));
+        }
+        keys.sort();
+        Ok(keys.iter().map(|key| url.object(key)).collect())
+    }
+
+    /// Stream the object at `url`, decompressing it by its magic bytes or key suffix
+    pub fn open(&self, url: &S3Url) -> io::Result<LogFile> {
+        let object = self
+            .runtime
+            .block_on(self.client.get_object().bucket(&url.bucket).key(&url.key).send())
+            .map_err(sdk_error)?;
+        let reader = ObjectReader {
+            runtime: Arc::clone(&self.runtime),
+            body: object.body,
+            chunk: Vec::new(),
+            pos: 0,
+        };
+        inputs::open_stream(BufReader::new(reader), Path::new(&url.key))
+    }
+}
+
+fn runtime() -> io::Result<Runtime> {
+    tokio::runtime::Builder::new_current_thread().enable_all().build()
+}
+
+fn sdk_error(e: impl std::error::Error) -> io::Error {
+    io::Error::other(DisplayErrorContext(e).to_string())
+}
+
+/// The client shared by every `s3://` input, created on first use
+fn shared() -> io::Result<&'static S3> {
+    static CLIENT: OnceLock<Result<S3, String>> = OnceLock::new();
+    CLIENT
+        .get_or_init(|| S3::from_env().map_err(|e| e.to_string()))
+        .as_ref()
+        .map_err(|e| io::Error::other(e.clone()))
+}
+
+/// List the objects under an `s3://bucket/prefix` argument with the shared client
+pub fn list(url: &str, include: &[String], exclude: &[String]) -> Result<Vec<PathBuf>, GlobError> {
+    let parsed = S3Url::parse(url).ok_or_else(|| GlobError::NoMatch(url.to_string()))?;
+    let s3 = shared().map_err(|error| GlobError::Io {
+        path: PathBuf::from(url),
+        error,
+    })?;
+    s3.list(&parsed, include, exclude)
+}
+
+/// Open an `s3://bucket/key` object with the shared client
+pub fn open(url: &str) -> io::Result<LogFile> {
+    let parsed = S3Url::parse(url)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not an s3://bucket/key URL"))?;
+    shared()?.open(&parsed)
+}
+
+/// Reads an object's body as it downloads, blocking on each chunk
+struct ObjectReader {
+    runtime: Arc<Runtime>,
+    body: ByteStream,
+    chunk: Vec<u8>,
+    /// How much of `chunk` has been read
+    pos: usize,
+}
+
+impl Read for ObjectReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            match self.runtime.block_on(self.body.next()) {
+                Some(Ok(bytes)) => {
+                    self.chunk = bytes.to_vec();
+                    self.pos = 0;
+                }
+                Some(Err(e)) => return Err(io::Error::other(e)),
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn parses_bucket_and_prefix() {
+        assert_eq!(
+            S3Url::parse("s3://logs/nginx/access.log"),
+            Some(S3Url {
+                bucket: "logs".into(),
+                key: "nginx/access.log".into()
+            })
+        );
+        assert_eq!(S3Url::parse("s3://logs").unwrap().key, "");
+        assert!(S3Url::parse("s3:///key").is_none());
+        assert!(S3Url::parse("logs/s3://x").is_none());
+    }
+
+    /// Answer `responses.len()` requests on a local port, one response each,
+    /// returning the endpoint and a handle yielding the request lines
+    fn fake_s3(responses: Vec<String>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for body in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut first = String::new();
+                reader.read_line(&mut first).unwrap();
+                requests.push(first.trim().to_string());
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+            requests
+        });
+        (endpoint, server)
+    }
+
+    fn client(endpoint: &str) -> S3 {
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version_latest()
+            .endpoint_url(endpoint)
+            .region(aws_sdk_s3::config::Region::new("us-east-1"))
+            .credentials_provider(aws_sdk_s3::config::Credentials::new("AKID", "secret", None, None, "test"))
+            .force_path_style(true)
+            .build();
+        S3::with_config(config).unwrap()
+    }
+
+    #[test]
+    fn lists_and_streams_objects_under_a_prefix() {
+        let listing = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>logs</Name><Prefix>nginx/</Prefix><KeyCount>4</KeyCount><IsTruncated>false</IsTruncated>
+  <Contents><Key>nginx/access.log.2</Key><Size>10</Size></Contents>
+  <Contents><Key>nginx/</Key><Size>0</Size></Contents>
+  <Contents><Key>nginx/access.log.1</Key><Size>10</Size></Contents>
+  <Contents><Key>nginx/archive/access.log.9</Key><Size>10</Size></Contents>
+</ListBucketResult>"#;
+        let (endpoint, server) = fake_s3(vec![listing.to_string(), "first line\nsecond line\n".to_string()]);
+        let s3 = client(&endpoint);
+        let url = S3Url::parse("s3://logs/nginx/").unwrap();
+
+        let objects = s3.list(&url, &[], &["archive".to_string()]).unwrap();
+        assert_eq!(
+            objects,
+            [PathBuf::from("s3://logs/nginx/access.log.1"), PathBuf::from("s3://logs/nginx/access.log.2")]
+        );
+        let object = S3Url::parse(objects[0].to_str().unwrap()).unwrap();
+        let lines: Vec<String> = s3.open(&object).unwrap().lines().collect::<Result<_, _>>().unwrap();
+        assert_eq!(lines, ["first line", "second line"]);
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /logs"));
+        assert!(requests[0].contains("list-type=2") && requests[0].contains("prefix=nginx%2F"));
+        assert!(requests[1].starts_with("GET /logs/nginx/access.log.1"));
+    }
+}
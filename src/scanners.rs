@@ -0,0 +1,134 @@
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+/// Share of a client's requests, in percent, that must have been 404s, unless
+/// `--scanner-404-share` says otherwise
+pub const DEFAULT_NOT_FOUND_SHARE: f64 = 50.0;
+
+/// A client that asked for many paths that do not exist, mostly getting 404s back
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanningIp {
+    pub ip: String,
+    /// Requests with a status code
+    pub requests: usize,
+    pub not_found: usize,
+    /// 404s as a percentage of the client's requests
+    pub not_found_share: f64,
+    /// Distinct paths, query strings aside, that got a 404
+    pub distinct_paths: usize,
+}
+
+/// Clients whose traffic is dominated by 404s across many distinct paths, the mark of
+/// a vulnerability scanner (`--scanner-threshold`)
+#[derive(Debug, Clone, Serialize)]
+pub struct ScannerReport {
+    /// Distinct 404 paths a client needs
+    pub min_paths: usize,
+    /// Share of its requests that must be 404s
+    pub min_share: f64,
+    /// Clients flagged, of which the top ones are listed
+    pub flagged: usize,
+    /// Most distinct 404 paths first
+    pub ips: Vec<ScanningIp>,
+}
+
+/// Flag the clients that got a 404 on at least `min_paths` distinct paths, with 404s
+/// making up at least `min_share` percent of their requests. Only entries with a
+/// status code count; returns `None` when no entry has one.
+pub fn detect(entries: &[LogEntry], min_paths: usize, min_share: f64, top_n: usize) -> Option<ScannerReport> {
+    #[derive(Default)]
+    struct Tally<'a> {
+        requests: usize,
+        not_found: usize,
+        paths: HashSet<&'a str>,
+    }
+
+    let mut clients: HashMap<IpAddr, Tally> = HashMap::new();
+    for entry in entries {
+        let Some(status) = entry.status_code else { continue };
+        let client = clients.entry(entry.ip).or_default();
+        client.requests += 1;
+        if status == 404 {
+            client.not_found += 1;
+            client.paths.insert(entry.endpoint.split(['?', '#']).next().unwrap_or_default());
+        }
+    }
+    if clients.is_empty() {
+        return None;
+    }
+
+    let mut flagged: Vec<ScanningIp> = clients
+        .into_iter()
+        .filter_map(|(ip, tally)| {
+            let share = (tally.not_found as f64 / tally.requests as f64 * 10000.0).round() / 100.0;
+            (tally.paths.len() >= min_paths && share >= min_share).then(|| ScanningIp {
+                ip: ip.to_string(),
+                requests: tally.requests,
+                not_found: tally.not_found,
+                not_found_share: share,
+                distinct_paths: tally.paths.len(),
+            })
+        })
+        .collect();
+    flagged.sort_by(|a, b| {
+        b.distinct_paths
+            .cmp(&a.distinct_paths)
+            .then(b.not_found.cmp(&a.not_found))
+            .then_with(|| a.ip.cmp(&b.ip))
+    });
+    let count = flagged.len();
+    flagged.truncate(top_n);
+
+    Some(ScannerReport {
+        min_paths,
+        min_share,
+        flagged: count,
+        ips: flagged,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_entry;
+
+    #[test]
+    fn flags_clients_dominated_by_404s_on_distinct_paths() {
+        let at = "2024-01-15T10:00:00Z";
+        let mut entries = Vec::new();
+        // A scanner: four missing paths, one of them twice with another query string
+        for path in ["/.env", "/admin.php", "/backup.zip", "/.git/config", "/.env?x=1"] {
+            entries.push(test_entry(at, "10.0.0.1", path, 404));
+        }
+        entries.push(test_entry(at, "10.0.0.1", "/", 200));
+        // A broken link followed over and over
+        for _ in 0..10 {
+            entries.push(test_entry(at, "10.0.0.2", "/old-page", 404));
+        }
+        // Many 404s, but mostly ordinary traffic
+        for path in ["/a", "/b", "/c", "/d"] {
+            entries.push(test_entry(at, "10.0.0.3", path, 404));
+        }
+        for _ in 0..6 {
+            entries.push(test_entry(at, "10.0.0.3", "/", 200));
+        }
+
+        let report = detect(&entries, 4, DEFAULT_NOT_FOUND_SHARE, 10).unwrap();
+        assert_eq!(report.flagged, 1);
+        let ip = &report.ips[0];
+        assert_eq!((ip.ip.as_str(), ip.requests, ip.not_found, ip.distinct_paths), ("10.0.0.1", 6, 5, 4));
+        assert_eq!(ip.not_found_share, 83.33);
+
+        // A lower share lets the third client through
+        let report = detect(&entries, 4, 40.0, 10).unwrap();
+        let flagged: Vec<_> = report.ips.iter().map(|ip| ip.ip.as_str()).collect();
+        assert_eq!(flagged, ["10.0.0.1", "10.0.0.3"]);
+    }
+
+    #[test]
+    fn needs_status_codes() {
+        assert!(detect(&[], 1, DEFAULT_NOT_FOUND_SHARE, 10).is_none());
+    }
+}
@@ -0,0 +1,151 @@
+use crate::analyzer::RankedItem;
+use crate::parser::LogEntry;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Inactivity that ends a session, unless `--session-gap` says otherwise
+pub const DEFAULT_GAP: Duration = Duration::from_secs(30 * 60);
+
+/// Each client's requests split into sessions wherever it went quiet for longer
+/// than the gap (`--sessions`)
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionReport {
+    pub gap_seconds: u64,
+    pub sessions: usize,
+    pub clients: usize,
+    pub sessions_per_client: f64,
+    /// Mean requests per session
+    pub average_requests: f64,
+    /// Mean time from the first to the last request of a session
+    pub average_duration_seconds: f64,
+    /// Longest session, first to last request
+    pub longest_duration_seconds: u64,
+    /// Sessions of a single request, which last no time
+    pub single_request_sessions: usize,
+    /// Endpoints sessions most often started on; the share is of all sessions
+    pub top_entry_endpoints: Vec<RankedItem>,
+    /// Endpoints sessions most often ended on, likewise
+    pub top_exit_endpoints: Vec<RankedItem>,
+}
+
+/// Group each client's requests, in time order, into sessions: a request more than
+/// `gap` after the client's previous one starts a new session. Of requests logged at
+/// the same time, the one logged first comes first.
+///
+/// Returns `None` when there are no entries.
+pub fn analyze(entries: &[LogEntry], gap: Duration, top_n: usize) -> Option<SessionReport> {
+    let gap_delta = TimeDelta::from_std(gap).unwrap_or(TimeDelta::MAX);
+    let mut clients: HashMap<IpAddr, Vec<(DateTime<Utc>, &str)>> = HashMap::new();
+    for entry in entries {
+        clients.entry(entry.ip).or_default().push((entry.timestamp, entry.endpoint.as_str()));
+    }
+    if clients.is_empty() {
+        return None;
+    }
+
+    let mut sessions = 0usize;
+    let mut single_request = 0usize;
+    let mut total_seconds = 0i64;
+    let mut longest = 0i64;
+    let mut entry_counts: HashMap<&str, usize> = HashMap::new();
+    let mut exit_counts: HashMap<&str, usize> = HashMap::new();
+    for requests in clients.values_mut() {
+        requests.sort_by_key(|&(time, _)| time);
+        let mut start = 0;
+        for i in 1..=requests.len() {
+            if i < requests.len() && requests[i].0 - requests[i - 1].0 <= gap_delta {
+                continue;
+            }
+            let session = &requests[start..i];
+            let seconds = (session[session.len() - 1].0 - session[0].0).num_seconds();
+            sessions += 1;
+            if session.len() == 1 {
+                single_request += 1;
+            }
+            total_seconds += seconds;
+            longest = longest.max(seconds);
+            *entry_counts.entry(session[0].1).or_insert(0) += 1;
+            *exit_counts.entry(session[session.len() - 1].1).or_insert(0) += 1;
+            start = i;
+        }
+    }
+
+    let rank = |counts: HashMap<&str, usize>| {
+        let mut ranked: Vec<(&str, usize)> = counts.into_iter().collect();
+        ranked.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        ranked
+            .into_iter()
+            .take(top_n)
+            .map(|(endpoint, n)| RankedItem {
+                value: endpoint.to_string(),
+                count: n,
+                percentage: (n as f64 / sessions as f64 * 10000.0).round() / 100.0,
+                activity: None,
+            })
+            .collect()
+    };
+
+    Some(SessionReport {
+        gap_seconds: gap.as_secs(),
+        sessions,
+        clients: clients.len(),
+        sessions_per_client: sessions as f64 / clients.len() as f64,
+        average_requests: entries.len() as f64 / sessions as f64,
+        average_duration_seconds: total_seconds as f64 / sessions as f64,
+        longest_duration_seconds: longest as u64,
+        single_request_sessions: single_request,
+        top_entry_endpoints: rank(entry_counts),
+        top_exit_endpoints: rank(exit_counts),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_entry;
+
+    fn entry(ip: &str, minute: u32, endpoint: &str) -> LogEntry {
+        test_entry(&format!("2024-01-15T{:02}:{:02}:00Z", 10 + minute / 60, minute % 60), ip, endpoint, 200)
+    }
+
+    #[test]
+    fn splits_sessions_at_the_inactivity_gap() {
+        let entries = vec![
+            // One session of three requests over 20 minutes, then a second one
+            entry("10.0.0.1", 0, "/login"),
+            entry("10.0.0.1", 10, "/cart"),
+            entry("10.0.0.2", 5, "/"),
+            entry("10.0.0.1", 20, "/checkout"),
+            entry("10.0.0.1", 90, "/login"),
+            entry("10.0.0.1", 95, "/logout"),
+            // Exactly the gap apart stays in one session
+            entry("10.0.0.3", 40, "/"),
+            entry("10.0.0.3", 10, "/login"),
+        ];
+        let report = analyze(&entries, DEFAULT_GAP, 10).unwrap();
+        assert_eq!((report.sessions, report.clients, report.single_request_sessions), (4, 3, 1));
+        assert_eq!(report.average_requests, 2.0);
+        // 20 + 5 + 0 + 30 minutes over four sessions
+        assert_eq!(report.average_duration_seconds, 55.0 * 60.0 / 4.0);
+        assert_eq!(report.longest_duration_seconds, 30 * 60);
+
+        let entry_points: Vec<_> = report.top_entry_endpoints.iter().map(|e| (e.value.as_str(), e.count)).collect();
+        assert_eq!(entry_points, [("/login", 3), ("/", 1)]);
+        assert_eq!(report.top_entry_endpoints[0].percentage, 75.0);
+        let exits: Vec<_> = report.top_exit_endpoints.iter().map(|e| (e.value.as_str(), e.count)).collect();
+        assert_eq!(exits, [("/", 2), ("/checkout", 1), ("/logout", 1)]);
+
+        // A shorter gap splits the 10-minute pauses too
+        let report = analyze(&entries, Duration::from_secs(5 * 60), 1).unwrap();
+        assert_eq!((report.sessions, report.single_request_sessions), (7, 6));
+        assert_eq!(report.top_entry_endpoints.len(), 1);
+    }
+
+    #[test]
+    fn needs_entries() {
+        assert!(analyze(&[], DEFAULT_GAP, 10).is_none());
+    }
+}
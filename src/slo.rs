@@ -0,0 +1,108 @@
+use crate::parser::LogEntry;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Availability over the analyzed period against an SLO, and how much of the error
+/// budget it left (`--slo`)
+#[derive(Debug, Clone, Serialize)]
+pub struct SloReport {
+    /// Target availability, in percent
+    pub objective: f64,
+    /// First and last request of the period
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    /// Requests with a status code
+    pub requests: usize,
+    /// Of those, 5xx responses
+    pub failed: usize,
+    /// Non-5xx share of requests, in percent, to three decimals
+    pub availability: f64,
+    /// Whether the failures stayed within the budget, judged before any rounding
+    pub met: bool,
+    /// Failed requests the SLO allows over the period, to three decimals
+    pub budget: f64,
+    /// Allowed failures left, to three decimals; negative once the budget is overspent
+    pub budget_remaining: f64,
+    /// Remaining budget as a percentage of the whole budget
+    pub budget_remaining_pct: f64,
+}
+
+/// Measure availability as the share of requests not answered with a 5xx and set it
+/// against `objective`, a percentage below 100. Only entries with a status code
+/// count; returns `None` when no entry has one.
+pub fn evaluate(entries: &[LogEntry], objective: f64) -> Option<SloReport> {
+    let mut requests = 0;
+    let mut failed = 0;
+    let mut period: Option<(DateTime<Utc>, DateTime<Utc>)> = None;
+    for entry in entries {
+        let Some(status) = entry.status_code else { continue };
+        requests += 1;
+        if status >= 500 {
+            failed += 1;
+        }
+        period = Some(match period {
+            Some((start, end)) => (start.min(entry.timestamp), end.max(entry.timestamp)),
+            None => (entry.timestamp, entry.timestamp),
+        });
+    }
+    let (period_start, period_end) = period?;
+
+    let availability = ((requests - failed) as f64 / requests as f64 * 100_000.0).round() / 1000.0;
+    let budget = requests as f64 * (100.0 - objective) / 100.0;
+    let budget_remaining = budget - failed as f64;
+    Some(SloReport {
+        objective,
+        period_start,
+        period_end,
+        requests,
+        failed,
+        availability,
+        met: failed as f64 <= budget,
+        budget: (budget * 1000.0).round() / 1000.0,
+        budget_remaining: (budget_remaining * 1000.0).round() / 1000.0,
+        budget_remaining_pct: (budget_remaining / budget * 10000.0).round() / 100.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_entry;
+
+    fn entries(ok: usize, failed: usize) -> Vec<LogEntry> {
+        let line = |second: usize, status: u16| {
+            let (hour, minute) = (10 + second / 3600, second / 60 % 60);
+            test_entry(&format!("2024-01-15T{}:{:02}:{:02}Z", hour, minute, second % 60), "1.1.1.1", "/", status)
+        };
+        (0..ok + failed).map(|i| line(i, if i < ok { 200 } else { 503 })).collect()
+    }
+
+    #[test]
+    fn spends_the_error_budget() {
+        let report = evaluate(&entries(1996, 4), 99.9).unwrap();
+        assert_eq!((report.requests, report.failed), (2000, 4));
+        assert_eq!(report.availability, 99.8);
+        assert!(!report.met);
+        assert_eq!((report.budget, report.budget_remaining, report.budget_remaining_pct), (2.0, -2.0, -100.0));
+        assert_eq!(report.period_start.to_rfc3339(), "2024-01-15T10:00:00+00:00");
+        assert_eq!(report.period_end.to_rfc3339(), "2024-01-15T10:33:19+00:00");
+
+        let report = evaluate(&entries(1999, 1), 99.9).unwrap();
+        assert!(report.met);
+        assert_eq!((report.budget_remaining, report.budget_remaining_pct), (1.0, 50.0));
+    }
+
+    #[test]
+    fn judges_the_unrounded_availability() {
+        // 99.89999% rounds to 99.9, but 10 failures exceed the budget of 9.999
+        let report = evaluate(&entries(9989, 10), 99.9).unwrap();
+        assert_eq!(report.availability, 99.9);
+        assert!(!report.met);
+        assert_eq!((report.budget, report.budget_remaining), (9.999, -0.001));
+    }
+
+    #[test]
+    fn needs_status_codes() {
+        assert!(evaluate(&[], 99.9).is_none());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::analyzer::Counts;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Layout version of state files; a file from another version is refused
+pub const STATE_VERSION: u32 = 3;
+
+/// Bytes read at a time when looking for the last complete line
+const SCAN_CHUNK: u64 = 8 * 1024;
+
+/// What `--state-file` keeps between runs over a growing log: how far the file has
+/// been read, and the counts of everything read so far
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    /// The log file the checkpoint is for
+    pub path: PathBuf,
+    /// Device and inode of the file when it was last read, to notice rotation
+    pub identity: Option<(u64, u64)>,
+    /// Bytes of the file already read, where the next run starts
+    pub offset: u64,
+    /// Lines that failed to parse, over every run
+    pub malformed: usize,
+    pub counts: Counts,
+}
+
+/// Why a state file could not be used
+#[derive(Debug)]
+pub enum StateError {
+    Io { path: PathBuf, error: io::Error },
+    Invalid { path: PathBuf, error: serde_json::Error },
+    /// Written by a version of the tool with a different layout
+    Version { path: PathBuf, found: u32 },
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::Io { path, error } => write!(f, "state file '{}': {}", path.display(), error),
+            StateError::Invalid { path, error } => {
+                write!(f, "state file '{}' is not valid: {}", path.display(), error)
+            }
+            StateError::Version { path, found } => write!(
+                f,
+                "state file '{}' has version {}, but this version of log_analyzer writes version {}; delete it to start over",
+                path.display(),
+                found,
+                STATE_VERSION
+            ),
+        }
+    }
+}
+
+impl Checkpoint {
+    /// A checkpoint for `path` before anything has been read
+    pub fn new(path: &Path) -> Self {
+        Checkpoint {
+            version: STATE_VERSION,
+            path: path.to_path_buf(),
+            identity: None,
+            offset: 0,
+            malformed: 0,
+            counts: Counts::default(),
+        }
+    }
+
+    /// Read the checkpoint saved at `state`, or `None` if there is none yet
+    pub fn load(state: &Path) -> Result<Option<Checkpoint>, StateError> {
+        let text = match fs::read_to_string(state) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => {
+                return Err(StateError::Io {
+                    path: state.to_path_buf(),
+                    error,
+                })
+            }
+        };
+        let value: serde_json::Value = serde_json::from_str(&text).map_err(|error| StateError::Invalid {
+            path: state.to_path_buf(),
+            error,
+        })?;
+        let found = value.get("version").and_then(serde_json::Value::as_u64).unwrap_or(0) as u32;
+        if found != STATE_VERSION {
+            return Err(StateError::Version {
+                path: state.to_path_buf(),
+                found,
+            });
+        }
+        serde_json::from_value(value).map(Some).map_err(|error| StateError::Invalid {
+            path: state.to_path_buf(),
+            error,
+        })
+    }
+
+    /// Write the checkpoint to `state`, replacing the previous one only once the new
+    /// one is complete
+    pub fn save(&self, state: &Path) -> Result<(), StateError> {
+        let io_error = |error| StateError::Io {
+            path: state.to_path_buf(),
+            error,
+        };
+        let mut partial = state.as_os_str().to_owned();
+        partial.push(".tmp");
+        let json = serde_json::to_vec(self).map_err(|e| io_error(io::Error::other(e)))?;
+        fs::write(&partial, json).map_err(io_error)?;
+        fs::rename(&partial, state).map_err(io_error)
+    }
+
+    /// Where to resume reading the file, given its identity and size now: the saved
+    /// offset, or `None` if the file has since been rotated or truncated
+    pub fn resume_offset(&self, identity: Option<(u64, u64)>, size: u64) -> Option<u64> {
+        let same_file = self.identity.is_none() || identity.is_none() || self.identity == identity;
+        (same_file && size >= self.offset).then_some(self.offset)
+    }
+}
+
+/// The length of `file` up to the end of its last complete line at or after `from`,
+/// so a line still being written is left for the next run. `from` if there is none.
+pub fn complete_length(file: &mut File, from: u64, size: u64) -> io::Result<u64> {
+    let mut end = size;
+    let mut chunk = Vec::new();
+    while end > from {
+        let start = end.saturating_sub(SCAN_CHUNK).max(from);
+        chunk.resize((end - start) as usize, 0);
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut chunk)?;
+        if let Some(newline) = chunk.iter().rposition(|&b| b == b'\n') {
+            return Ok(start + newline as u64 + 1);
+        }
+        end = start;
+    }
+    Ok(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn saves_and_loads_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join("access.state");
+        assert!(Checkpoint::load(&state).unwrap().is_none());
+
+        let mut checkpoint = Checkpoint::new(Path::new("/var/log/access.log"));
+        checkpoint.offset = 512;
+        checkpoint.identity = Some((1, 42));
+        checkpoint.counts.entries = 3;
+        checkpoint.counts.statuses.insert(404, 3);
+        checkpoint.counts.levels.insert(crate::parser::LogLevel::Warn, 3);
+        checkpoint.counts.ips.insert("10.0.0.1".parse().unwrap(), Default::default());
+        checkpoint.save(&state).unwrap();
+        assert_eq!(Checkpoint::load(&state).unwrap().unwrap(), checkpoint);
+
+        fs::write(&state, r#"{"version":99}"#).unwrap();
+        assert!(matches!(Checkpoint::load(&state), Err(StateError::Version { found: 99, .. })));
+    }
+
+    #[test]
+    fn resumes_only_the_same_untruncated_file() {
+        let mut checkpoint = Checkpoint::new(Path::new("access.log"));
+        checkpoint.offset = 100;
+        checkpoint.identity = Some((1, 42));
+        assert_eq!(checkpoint.resume_offset(Some((1, 42)), 150), Some(100));
+        assert_eq!(checkpoint.resume_offset(Some((1, 42)), 50), None);
+        assert_eq!(checkpoint.resume_offset(Some((1, 43)), 150), None);
+    }
+
+    #[test]
+    fn stops_before_a_partial_last_line() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"first\nsecond\nthi").unwrap();
+        assert_eq!(complete_length(&mut file, 0, 16).unwrap(), 13);
+        assert_eq!(complete_length(&mut file, 13, 16).unwrap(), 13);
+        file.write_all(b"rd\n").unwrap();
+        assert_eq!(complete_length(&mut file, 13, 19).unwrap(), 19);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::ingest::Windowed;
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+/// Largest message accepted, over UDP (a whole datagram) or TCP (one frame)
+pub const MAX_MESSAGE: usize = 65_535;
+
+/// Longest the reader waits for a message at a time while a window is open
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Receives syslog messages forwarded by web servers (or a relay such as rsyslog) over
+/// UDP, TCP or both, and reads them as a stream of log lines.
+///
+/// Over UDP each datagram is a message. Over TCP, messages are framed as RFC 6587
+/// allows: either terminated by a newline, or prefixed with their length in octets
+/// and a space; each connection may use either. Reading ends at the end of the
+/// current window (see [`Windowed`]).
+pub struct SyslogListener {
+    messages: Receiver<Vec<u8>>,
+    udp_addr: Option<SocketAddr>,
+    tcp_addr: Option<SocketAddr>,
+    /// Received messages, newline-terminated, and how much of them has been read
+    pending: Vec<u8>,
+    consumed: usize,
+    window_end: Option<Instant>,
+}
+
+impl SyslogListener {
+    /// Listen on a UDP address, a TCP address, or both. Messages are received in the
+    /// background from then on.
+    pub fn bind(udp: Option<SocketAddr>, tcp: Option<SocketAddr>) -> io::Result<Self> {
+        let (sender, messages) = mpsc::channel();
+        let udp_addr = match udp {
+            Some(addr) => {
+                let socket = UdpSocket::bind(addr)?;
+                let addr = socket.local_addr()?;
+                let sender = sender.clone();
+                std::thread::spawn(move || receive_datagrams(socket, sender));
+                Some(addr)
+            }
+            None => None,
+        };
+        let tcp_addr = match tcp {
+            Some(addr) => {
+                let listener = TcpListener::bind(addr)?;
+                let addr = listener.local_addr()?;
+                std::thread::spawn(move || accept_connections(listener, sender));
+                Some(addr)
+            }
+            None => None,
+        };
+        Ok(SyslogListener {
+            messages,
+            udp_addr,
+            tcp_addr,
+            pending: Vec::new(),
+            consumed: 0,
+            window_end: None,
+        })
+    }
+
+    /// The UDP address listened on, e.g. to learn the port after binding port 0
+    pub fn udp_addr(&self) -> Option<SocketAddr> {
+        self.udp_addr
+    }
+
+    /// The TCP address listened on
+    pub fn tcp_addr(&self) -> Option<SocketAddr> {
+        self.tcp_addr
+    }
+
+    /// Wait up to `timeout` for a message and buffer it
+    fn poll(&mut self, timeout: Duration) -> io::Result<()> {
+        match self.messages.recv_timeout(timeout) {
+            Ok(message) => {
+                if self.consumed == self.pending.len() {
+                    self.pending.clear();
+                    self.consumed = 0;
+                }
+                self.pending.extend_from_slice(&message);
+                if !message.ends_with(b"\n") {
+                    self.pending.push(b'\n');
+                }
+                Ok(())
+            }
+            Err(RecvTimeoutError::Timeout) => Ok(()),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::other("the syslog listener stopped")),
+        }
+    }
+
+    fn unread(&self) -> &[u8] {
+        &self.pending[self.consumed..]
+    }
+}
+
+impl Windowed for SyslogListener {
+    fn end_window_at(&mut self, end: Instant) {
+        self.window_end = Some(end);
+    }
+
+    fn peek_lines(&mut self, limit: usize, wait: Duration) -> Vec<String> {
+        let until = Instant::now() + wait;
+        loop {
+            let lines = self.unread().iter().filter(|&&b| b == b'\n').count();
+            let left = until.saturating_duration_since(Instant::now());
+            if lines >= limit || left.is_zero() || self.poll(left.min(POLL_INTERVAL)).is_err() {
+                break;
+            }
+        }
+        String::from_utf8_lossy(self.unread())
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .take(limit)
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+impl Read for SyslogListener {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = buf.len().min(available.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for SyslogListener {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.consumed == self.pending.len() {
+            let left = match self.window_end {
+                Some(end) => end.saturating_duration_since(Instant::now()),
+                None => POLL_INTERVAL,
+            };
+            if left.is_zero() {
+                return Ok(&[]);
+            }
+            self.poll(left.min(POLL_INTERVAL))?;
+        }
+        Ok(self.unread())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.consumed = (self.consumed + amt).min(self.pending.len());
+    }
+}
+
+/// Forward every datagram until the listener is dropped
+fn receive_datagrams(socket: UdpSocket, sender: Sender<Vec<u8>>) {
+    let mut datagram = vec![0u8; MAX_MESSAGE];
+    loop {
+        match socket.recv_from(&mut datagram) {
+            Ok((len, _)) => {
+                let message = trim_message(&datagram[..len]);
+                if !message.is_empty() && sender.send(message.to_vec()).is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                tracing::warn!("syslog: stopped receiving over UDP: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Accept TCP connections, reading each on its own thread
+fn accept_connections(listener: TcpListener, sender: Sender<Vec<u8>>) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let sender = sender.clone();
+                std::thread::spawn(move || receive_stream(stream, sender));
+            }
+            Err(e) => tracing::warn!("syslog: could not accept a TCP connection: {}", e),
+        }
+    }
+}
+
+/// Forward every message of a TCP connection until it is closed
+fn receive_stream(stream: TcpStream, sender: Sender<Vec<u8>>) {
+    let from = stream.peer_addr().ok();
+    let mut reader = BufReader::new(stream);
+    loop {
+        match read_frame(&mut reader) {
+            Ok(Some(message)) => {
+                let message = trim_message(&message);
+                if !message.is_empty() && sender.send(message.to_vec()).is_err() {
+                    return;
+                }
+            }
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!(from = ?from, "syslog: dropping TCP connection: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Read the next message of a TCP stream, octet-counted (`LEN SP MSG`) or terminated
+/// by a newline, or `None` once the stream ends.
+///
+/// A frame is taken to be octet-counted when it opens with digits and a space, so
+/// newline-terminated lines without a syslog header (`10.0.0.1 - - [...`) still work.
+fn read_frame(reader: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let buf = reader.fill_buf()?;
+    if buf.is_empty() {
+        return Ok(None);
+    }
+    let digits = buf.iter().take_while(|b| b.is_ascii_digit()).count();
+    if digits > 0 && buf.get(digits) == Some(&b' ') {
+        let len = std::str::from_utf8(&buf[..digits]).ok().and_then(|d| d.parse::<usize>().ok());
+        let len = match len {
+            Some(len) if len <= MAX_MESSAGE => len,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid message length {}", String::from_utf8_lossy(&buf[..digits])),
+                ))
+            }
+        };
+        reader.consume(digits + 1);
+        let mut message = vec![0u8; len];
+        reader.read_exact(&mut message)?;
+        return Ok(Some(message));
+    }
+    let mut message = Vec::new();
+    reader.by_ref().take(MAX_MESSAGE as u64 + 1).read_until(b'\n', &mut message)?;
+    if message.len() > MAX_MESSAGE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long"));
+    }
+    Ok(Some(message))
+}
+
+/// A message without the trailing line break or NUL some senders append
+fn trim_message(message: &[u8]) -> &[u8] {
+    let end = message
+        .iter()
+        .rposition(|&b| !matches!(b, b'\n' | b'\r' | b'\0'))
+        .map_or(0, |last| last + 1);
+    &message[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const LINE: &str = r#"<134>1 2024-01-15T10:30:00Z web1 nginx - - - 10.0.0.1 - - [15/Jan/2024:10:30:00 +0000] "GET / HTTP/1.1" 200 512"#;
+
+    #[test]
+    fn reads_octet_counted_and_newline_framed_messages() {
+        let stream = format!("{} {}first\r\nsecond\n", LINE.len(), LINE);
+        let mut reader = io::Cursor::new(stream.into_bytes());
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), LINE.as_bytes());
+        assert_eq!(trim_message(&read_frame(&mut reader).unwrap().unwrap()), b"first");
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"second\n");
+        assert!(read_frame(&mut reader).unwrap().is_none());
+
+        // A line without a syslog header is not mistaken for a length
+        let mut reader = io::Cursor::new(b"10.0.0.1 - - GET\n".to_vec());
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"10.0.0.1 - - GET\n");
+
+        let mut reader = io::Cursor::new(b"99999999 x".to_vec());
+        assert!(read_frame(&mut reader).is_err());
+    }
+
+    #[test]
+    fn receives_over_udp_and_tcp_until_the_window_ends() {
+        let local = Some("127.0.0.1:0".parse().unwrap());
+        let mut listener = SyslogListener::bind(local, local).unwrap();
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.send_to(b"over udp\n", listener.udp_addr().unwrap()).unwrap();
+        let mut stream = TcpStream::connect(listener.tcp_addr().unwrap()).unwrap();
+        stream.write_all(b"9 over tcp1over tcp2\n").unwrap();
+        drop(stream);
+
+        assert_eq!(listener.peek_lines(3, Duration::from_secs(5)).len(), 3);
+        listener.end_window_at(Instant::now() + Duration::from_millis(200));
+        let mut lines: Vec<String> = (&mut listener).lines().collect::<Result<_, _>>().unwrap();
+        lines.sort();
+        assert_eq!(lines, ["over tcp1", "over tcp2", "over udp"]);
+    }
+}
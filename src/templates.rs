@@ -0,0 +1,116 @@
+use crate::parser::LogEntry;
+use crate::routes::{self, Route};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Shortest run of hex digits taken for a hash or opaque id (a 64-bit value in hex)
+const MIN_HASH_LEN: usize = 16;
+
+/// Placeholder for a path segment that looks generated rather than part of a route:
+/// `:id` for numbers, `:uuid` for UUIDs and `:hash` for long hex strings such as
+/// digests and object ids
+pub fn placeholder(segment: &str) -> Option<&'static str> {
+    let bytes = segment.as_bytes();
+    if !bytes.is_empty() && bytes.iter().all(u8::is_ascii_digit) {
+        Some(":id")
+    } else if is_uuid(bytes) {
+        Some(":uuid")
+    } else if bytes.len() >= MIN_HASH_LEN
+        && bytes.iter().all(u8::is_ascii_hexdigit)
+        && bytes.iter().any(u8::is_ascii_digit)
+    {
+        Some(":hash")
+    } else {
+        None
+    }
+}
+
+/// `8-4-4-4-12` hex digits, in either case
+fn is_uuid(bytes: &[u8]) -> bool {
+    bytes.len() == 36
+        && bytes.iter().enumerate().all(|(i, b)| match i {
+            8 | 13 | 18 | 23 => *b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        })
+}
+
+/// Replace the generated-looking segments of an endpoint's path with placeholders,
+/// so `/users/123/orders/9` becomes `/users/:id/orders/:id`; the query string is
+/// kept as it is. Returns the endpoint unchanged when nothing collapses.
+pub fn collapse_path(endpoint: &str) -> Cow<'_, str> {
+    let (path, query) = match endpoint.find(['?', '#']) {
+        Some(at) => endpoint.split_at(at),
+        None => (endpoint, ""),
+    };
+    if !path.split('/').any(|segment| placeholder(segment).is_some()) {
+        return Cow::Borrowed(endpoint);
+    }
+    let collapsed: Vec<&str> = path.split('/').map(|segment| placeholder(segment).unwrap_or(segment)).collect();
+    Cow::Owned(collapsed.join("/") + query)
+}
+
+/// Rewrite each endpoint to the template of the most specific route it matches
+/// (`--route-patterns`), keeping its query string, and with `collapse`
+/// (`--collapse-paths`) collapse the endpoints no route matches with
+/// [`collapse_path`]. Endpoints are looked up once each, however often they repeat.
+pub fn template_entries(entries: &mut [LogEntry], routes: &[Route], collapse: bool) {
+    let mut templates: HashMap<String, Option<String>> = HashMap::new();
+    for entry in entries {
+        let template = templates.entry(entry.endpoint.clone()).or_insert_with_key(|endpoint| {
+            if let Some(route) = routes::most_specific(routes, endpoint) {
+                let query = endpoint.find(['?', '#']).map_or("", |at| &endpoint[at..]);
+                return Some(format!("{}{}", route.template, query));
+            }
+            match collapse_path(endpoint) {
+                Cow::Owned(collapsed) if collapse => Some(collapsed),
+                _ => None,
+            }
+        });
+        if let Some(template) = template {
+            entry.endpoint.clone_from(template);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_log_line;
+    use crate::routes::parse_routes_file;
+
+    #[test]
+    fn collapses_ids_uuids_and_hashes() {
+        assert_eq!(collapse_path("/users/123/orders/9"), "/users/:id/orders/:id");
+        assert_eq!(collapse_path("/carts/3F2504E0-4F89-11D3-9A0C-0305E82C3301?x=1"), "/carts/:uuid?x=1");
+        assert_eq!(collapse_path("/blobs/d41d8cd98f00b204e9800998ecf8427e"), "/blobs/:hash");
+        assert_eq!(collapse_path("/objects/507f1f77bcf86cd799439011/"), "/objects/:hash/");
+        // Words, versions and short hex stay
+        for kept in ["/api/v2/users", "/decade/facade", "/colors/ff0000", "/search?page=2", "/"] {
+            assert!(matches!(collapse_path(kept), Cow::Borrowed(_)), "{}", kept);
+        }
+    }
+
+    #[test]
+    fn prefers_route_patterns_then_collapses_the_rest() {
+        let routes = parse_routes_file("GET /users/{id}\n/users/me\n/files/*\n");
+        let lines = [
+            "2024-01-15T10:00:00Z [INFO] 1.1.1.1 GET /users/alice?tab=posts 200",
+            "2024-01-15T10:00:00Z [INFO] 1.1.1.1 DELETE /users/me 200",
+            "2024-01-15T10:00:00Z [INFO] 1.1.1.1 GET /files/a/b.txt 200",
+            "2024-01-15T10:00:00Z [INFO] 1.1.1.1 GET /orders/42 200",
+        ];
+        let parse = || lines.iter().map(|l| parse_log_line(l).unwrap()).collect::<Vec<_>>();
+        let endpoints = |entries: &[LogEntry]| entries.iter().map(|e| e.endpoint.clone()).collect::<Vec<_>>();
+
+        let mut entries = parse();
+        template_entries(&mut entries, &routes, true);
+        assert_eq!(endpoints(&entries), ["/users/{id}?tab=posts", "/users/me", "/files/*", "/orders/:id"]);
+
+        let mut entries = parse();
+        template_entries(&mut entries, &routes, false);
+        assert_eq!(endpoints(&entries)[3], "/orders/42");
+        let mut entries = parse();
+        template_entries(&mut entries, &[], true);
+        assert_eq!(endpoints(&entries), ["/users/alice?tab=posts", "/users/me", "/files/a/b.txt", "/orders/:id"]);
+    }
+}
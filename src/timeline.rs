@@ -0,0 +1,500 @@
+use crate::hll::{self, HyperLogLog};
+use crate::parser::LogEntry;
+use crate::units;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::fmt;
+use std::time::Duration;
+
+/// Most buckets a timeline may have, so a narrow bucket over a long log is refused
+/// rather than filling the report
+pub const MAX_BUCKETS: usize = 100_000;
+
+/// Requests in one bucket of time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimeBucket {
+    /// When the bucket starts (UTC)
+    pub start: DateTime<Utc>,
+    pub requests: usize,
+    /// Requests logged as `ERROR` or `FATAL`
+    pub errors: usize,
+    /// Distinct and new clients, when they are counted (`--unique`, `--approx`)
+    #[serde(flatten)]
+    pub clients: Option<BucketClients>,
+}
+
+/// Clients of one bucket of time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BucketClients {
+    /// Distinct client IPs
+    pub unique_ips: usize,
+    /// Client IPs not seen in an earlier bucket
+    pub new_ips: usize,
+}
+
+/// How the clients of each bucket are counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientCounting {
+    /// Not at all
+    Off,
+    /// Exactly, remembering every client (`--unique`)
+    Exact,
+    /// With HyperLogLog sketches, leaving returning clients uncounted (`--approx`)
+    Approx,
+}
+
+/// Requests over time in buckets of one width, from the first entry's bucket to the
+/// last one's, empty buckets included
+#[derive(Debug, Clone, Serialize)]
+pub struct Timeline {
+    /// Width of each bucket, e.g. `5m`
+    pub bucket: String,
+    pub bucket_seconds: u64,
+    pub buckets: Vec<TimeBucket>,
+    /// Client IPs seen in more than one bucket, when clients are counted exactly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub returning_ips: Option<usize>,
+    /// Relative standard error of the client counts, when they are estimates (`--approx`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_count_error: Option<f64>,
+    /// Busiest second, minute and hour and the worst run of errors; `None` without entries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peaks: Option<Peaks>,
+}
+
+/// The busiest stretches of the log
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Peaks {
+    /// The busiest second, whose requests are the peak requests per second
+    pub second: Peak,
+    pub minute: Peak,
+    pub hour: Peak,
+    /// Requests per second from the first entry to the last
+    pub average_per_second: f64,
+    /// The bucket-wide window with the most errors, `None` if nothing failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_window: Option<ErrorWindow>,
+}
+
+/// Requests in the busiest whole second, minute or hour (UTC)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Peak {
+    pub start: DateTime<Utc>,
+    pub requests: usize,
+}
+
+/// A window one bucket wide, starting at an error rather than on a bucket boundary,
+/// so a burst split across two buckets is still seen whole
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ErrorWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub requests: usize,
+    pub errors: usize,
+    /// Errors as a percentage of the window's requests
+    pub error_rate: f64,
+}
+
+/// Requests per second across the buckets of a timeline, empty ones included, for
+/// sizing capacity from a typical bucket rather than the log as a whole
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RateStats {
+    /// Width of the buckets the rates are over, e.g. `5m`
+    pub bucket: String,
+    pub mean: f64,
+    pub max: f64,
+    /// Population standard deviation
+    pub std_dev: f64,
+}
+
+impl Timeline {
+    /// The bucket with the most requests (the earliest of equals)
+    pub fn peak(&self) -> Option<&TimeBucket> {
+        self.buckets.iter().rev().max_by_key(|b| b.requests)
+    }
+
+    /// Mean, highest and standard deviation of each bucket's requests per second;
+    /// `None` without buckets
+    pub fn rate_stats(&self) -> Option<RateStats> {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        let rates: Vec<f64> = self.buckets.iter().map(|b| b.requests as f64 / self.bucket_seconds as f64).collect();
+        let mean = rates.iter().sum::<f64>() / rates.len() as f64;
+        let variance = rates.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / rates.len() as f64;
+        Some(RateStats {
+            bucket: self.bucket.clone(),
+            mean,
+            max: rates.iter().copied().fold(0.0, f64::max),
+            std_dev: variance.sqrt(),
+        })
+    }
+}
+
+/// Why requests could not be put in buckets of the width asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimelineError {
+    /// A bucket must be at least a second wide
+    ZeroWidth,
+    /// The entries span more than [`MAX_BUCKETS`] buckets of this width
+    TooManyBuckets { bucket: String, buckets: usize },
+}
+
+impl fmt::Display for TimelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimelineError::ZeroWidth => write!(f, "buckets must be at least 1s wide"),
+            TimelineError::TooManyBuckets { bucket, buckets } => write!(
+                f,
+                "the entries span {} buckets of {}, more than {}; choose a wider bucket",
+                buckets, bucket, MAX_BUCKETS
+            ),
+        }
+    }
+}
+
+/// Count requests, errors and, as `clients` says, clients per bucket of `width`
+/// (`--bucket`). Buckets are aligned to whole multiples of the width since the Unix
+/// epoch, so a `1h` bucket starts on the hour and a `1d` one at midnight, UTC.
+///
+/// Approximate counting estimates the clients of each bucket with HyperLogLog
+/// sketches instead of remembering them, and does not count returning clients.
+pub fn bucket(entries: &[LogEntry], width: Duration, clients: ClientCounting) -> Result<Timeline, TimelineError> {
+    let seconds = width.as_secs();
+    if seconds == 0 {
+        return Err(TimelineError::ZeroWidth);
+    }
+    let label = units::format_duration(width);
+    let index = |at: DateTime<Utc>| at.timestamp().div_euclid(seconds as i64);
+    let (Some(first), Some(last)) = (
+        entries.iter().map(|e| index(e.timestamp)).min(),
+        entries.iter().map(|e| index(e.timestamp)).max(),
+    ) else {
+        return Ok(Timeline {
+            bucket: label,
+            bucket_seconds: seconds,
+            buckets: Vec::new(),
+            returning_ips: (clients == ClientCounting::Exact).then_some(0),
+            client_count_error: (clients == ClientCounting::Approx).then_some(hll::STANDARD_ERROR),
+            peaks: None,
+        });
+    };
+    let len = (last - first) as usize + 1;
+    if len > MAX_BUCKETS {
+        return Err(TimelineError::TooManyBuckets { bucket: label, buckets: len });
+    }
+
+    let mut buckets: Vec<TimeBucket> = (0..len as i64)
+        .map(|i| TimeBucket {
+            start: DateTime::UNIX_EPOCH + TimeDelta::seconds((first + i) * seconds as i64),
+            requests: 0,
+            errors: 0,
+            clients: None,
+        })
+        .collect();
+    let slot = |entry: &LogEntry| (index(entry.timestamp) - first) as usize;
+    for entry in entries {
+        let bucket = &mut buckets[slot(entry)];
+        bucket.requests += 1;
+        if entry.level.is_error() {
+            bucket.errors += 1;
+        }
+    }
+    let mut counts = vec![BucketClients::default(); len];
+    let returning_ips = match clients {
+        ClientCounting::Off => None,
+        ClientCounting::Exact => Some(count_clients(entries, &mut counts, slot)),
+        ClientCounting::Approx => {
+            estimate_clients(entries, &buckets, &mut counts, slot);
+            None
+        }
+    };
+    if clients != ClientCounting::Off {
+        for (bucket, counts) in buckets.iter_mut().zip(counts) {
+            bucket.clients = Some(counts);
+        }
+    }
+    Ok(Timeline {
+        bucket: label,
+        bucket_seconds: seconds,
+        buckets,
+        returning_ips,
+        client_count_error: (clients == ClientCounting::Approx).then_some(hll::STANDARD_ERROR),
+        peaks: peaks(entries, width),
+    })
+}
+
+/// Count each bucket's distinct and new clients exactly; returns the number of
+/// clients seen in more than one bucket
+fn count_clients(entries: &[LogEntry], buckets: &mut [BucketClients], slot: impl Fn(&LogEntry) -> usize) -> usize {
+    // Each client's first and last bucket, and the buckets it was seen in
+    let mut clients: HashMap<IpAddr, (usize, usize)> = HashMap::new();
+    let mut seen: HashSet<(IpAddr, usize)> = HashSet::new();
+    for entry in entries {
+        let i = slot(entry);
+        if seen.insert((entry.ip, i)) {
+            buckets[i].unique_ips += 1;
+        }
+        let span = clients.entry(entry.ip).or_insert((i, i));
+        *span = (span.0.min(i), span.1.max(i));
+    }
+    let mut returning_ips = 0;
+    for (first, last) in clients.into_values() {
+        buckets[first].new_ips += 1;
+        if last > first {
+            returning_ips += 1;
+        }
+    }
+    returning_ips
+}
+
+/// Buckets sketched at once, on each pass over entries that are out of time order
+const SKETCH_WINDOW: usize = 64;
+
+/// A sketch of the clients of every bucket so far, fed the buckets in time order
+#[derive(Default)]
+struct ClientGrowth {
+    so_far: HyperLogLog,
+    seen_before: usize,
+}
+
+impl ClientGrowth {
+    /// Estimate the distinct clients of `bucket` from `sketch`, and its new ones as how
+    /// much the sketch of every earlier bucket grows by adding it
+    fn add(&mut self, bucket: &mut BucketClients, sketch: &HyperLogLog) {
+        self.so_far.merge(sketch);
+        let seen = self.so_far.estimate();
+        bucket.unique_ips = sketch.estimate();
+        // Estimates can wobble down by a little; a bucket never has more new clients
+        // than clients
+        bucket.new_ips = seen.saturating_sub(self.seen_before).min(bucket.unique_ips);
+        self.seen_before = self.seen_before.max(seen);
+    }
+}
+
+/// Estimate each bucket's distinct and new clients with sketches.
+///
+/// Entries in time order take one pass holding two sketches at a time; otherwise each
+/// pass sketches the next [`SKETCH_WINDOW`] buckets that saw requests.
+fn estimate_clients(
+    entries: &[LogEntry],
+    buckets: &[TimeBucket],
+    clients: &mut [BucketClients],
+    slot: impl Fn(&LogEntry) -> usize,
+) {
+    let mut growth = ClientGrowth::default();
+    if entries.is_sorted_by_key(&slot) {
+        for run in entries.chunk_by(|a, b| slot(a) == slot(b)) {
+            let mut sketch = HyperLogLog::new();
+            for entry in run {
+                sketch.insert(&entry.ip);
+            }
+            growth.add(&mut clients[slot(&run[0])], &sketch);
+        }
+        return;
+    }
+    for start in (0..buckets.len()).step_by(SKETCH_WINDOW) {
+        let window = start..(start + SKETCH_WINDOW).min(buckets.len());
+        if buckets[window.clone()].iter().all(|b| b.requests == 0) {
+            continue;
+        }
+        let mut sketches: Vec<Option<HyperLogLog>> = vec![None; window.len()];
+        for entry in entries {
+            let i = slot(entry);
+            if window.contains(&i) {
+                sketches[i - start].get_or_insert_with(HyperLogLog::new).insert(&entry.ip);
+            }
+        }
+        for (i, sketch) in window.zip(sketches) {
+            if let Some(sketch) = sketch {
+                growth.add(&mut clients[i], &sketch);
+            }
+        }
+    }
+}
+
+/// Find the busiest second, minute and hour, and the window of `width` with the most
+/// errors (the earliest of equals); `None` without entries
+pub fn peaks(entries: &[LogEntry], width: Duration) -> Option<Peaks> {
+    let busiest = |seconds: i64| {
+        let mut counts: HashMap<i64, usize> = HashMap::new();
+        for entry in entries {
+            *counts.entry(entry.timestamp.timestamp().div_euclid(seconds)).or_insert(0) += 1;
+        }
+        counts
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+            .map(|(index, requests)| Peak {
+                start: DateTime::UNIX_EPOCH + TimeDelta::seconds(index * seconds),
+                requests,
+            })
+    };
+    let second = busiest(1)?;
+    let minute = busiest(60)?;
+    let hour = busiest(3600)?;
+
+    let mut times: Vec<DateTime<Utc>> = entries.iter().map(|e| e.timestamp).collect();
+    times.sort_unstable();
+    let span = (times[times.len() - 1] - times[0]).num_milliseconds() as f64 / 1000.0;
+    let average_per_second = entries.len() as f64 / span.max(1.0);
+
+    Some(Peaks {
+        second,
+        minute,
+        hour,
+        average_per_second,
+        error_window: error_window(entries, &times, width),
+    })
+}
+
+/// Slide a window of `width` from each error to the next and keep the one that
+/// catches the most errors; `times` are all entries' timestamps, sorted
+fn error_window(entries: &[LogEntry], times: &[DateTime<Utc>], width: Duration) -> Option<ErrorWindow> {
+    let width = TimeDelta::from_std(width).ok()?;
+    let mut errors: Vec<DateTime<Utc>> = entries.iter().filter(|e| e.level.is_error()).map(|e| e.timestamp).collect();
+    errors.sort_unstable();
+
+    let (mut best_start, mut best_errors) = (*errors.first()?, 0);
+    let mut end = 0;
+    for (i, &start) in errors.iter().enumerate() {
+        while end < errors.len() && errors[end] < start + width {
+            end += 1;
+        }
+        if end - i > best_errors {
+            (best_start, best_errors) = (start, end - i);
+        }
+    }
+    let best_end = best_start + width;
+    let requests = times.partition_point(|&t| t < best_end) - times.partition_point(|&t| t < best_start);
+    Some(ErrorWindow {
+        start: best_start,
+        end: best_end,
+        requests,
+        errors: best_errors,
+        error_rate: best_errors as f64 / requests as f64 * 100.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{test_entry, LogLevel};
+
+    fn entry(time: &str, level: LogLevel) -> LogEntry {
+        LogEntry { level, ..test_entry(time, "1.2.3.4", "/", 200) }
+    }
+
+    fn visit(time: &str, ip: &str) -> LogEntry {
+        test_entry(time, ip, "/", 200)
+    }
+
+    #[test]
+    fn counts_entries_per_aligned_bucket_with_gaps() {
+        let entries = [
+            entry("2024-01-15T10:31:00Z", LogLevel::Info),
+            entry("2024-01-15T10:34:59Z", LogLevel::Error),
+            entry("2024-01-15T10:47:00Z", LogLevel::Info),
+            entry("2024-01-15T10:35:00Z", LogLevel::Info),
+        ];
+        let timeline = bucket(&entries, Duration::from_secs(300), ClientCounting::Off).unwrap();
+        assert_eq!(timeline.bucket, "5m");
+        let counts: Vec<_> = timeline.buckets.iter().map(|b| (b.start.format("%H:%M").to_string(), b.requests, b.errors)).collect();
+        assert_eq!(
+            counts,
+            [
+                ("10:30".to_string(), 2, 1),
+                ("10:35".to_string(), 1, 0),
+                ("10:40".to_string(), 0, 0),
+                ("10:45".to_string(), 1, 0)
+            ]
+        );
+        assert_eq!(timeline.peak().unwrap().start.format("%H:%M").to_string(), "10:30");
+
+        // 2, 1, 0 and 1 requests in 300s buckets
+        let rates = timeline.rate_stats().unwrap();
+        assert!((rates.mean - 1.0 / 300.0).abs() < 1e-12);
+        assert!((rates.max - 2.0 / 300.0).abs() < 1e-12);
+        assert!((rates.std_dev - 0.5f64.sqrt() / 300.0).abs() < 1e-12);
+
+        let daily = bucket(&entries, Duration::from_secs(86_400), ClientCounting::Off).unwrap();
+        assert_eq!(daily.buckets.len(), 1);
+        assert_eq!(daily.buckets[0].start.to_rfc3339(), "2024-01-15T00:00:00+00:00");
+    }
+
+    #[test]
+    fn finds_the_busiest_periods_and_worst_run_of_errors() {
+        let entries = [
+            entry("2024-01-15T10:31:00Z", LogLevel::Info),
+            entry("2024-01-15T10:31:00Z", LogLevel::Info),
+            entry("2024-01-15T10:34:30Z", LogLevel::Error),
+            entry("2024-01-15T10:35:10Z", LogLevel::Info),
+            entry("2024-01-15T10:35:20Z", LogLevel::Fatal),
+            entry("2024-01-15T10:36:00Z", LogLevel::Info),
+            entry("2024-01-15T10:50:00Z", LogLevel::Error),
+            entry("2024-01-15T11:02:00Z", LogLevel::Info),
+        ];
+        let peaks = bucket(&entries, Duration::from_secs(300), ClientCounting::Off).unwrap().peaks.unwrap();
+        assert_eq!((peaks.second.start.format("%H:%M:%S").to_string(), peaks.second.requests), ("10:31:00".to_string(), 2));
+        // 10:35 ties 10:31 with two requests; the earlier minute wins
+        assert_eq!((peaks.minute.start.format("%H:%M").to_string(), peaks.minute.requests), ("10:31".to_string(), 2));
+        assert_eq!((peaks.hour.start.format("%H:%M").to_string(), peaks.hour.requests), ("10:00".to_string(), 7));
+        assert!((peaks.average_per_second - 8.0 / 1860.0).abs() < 1e-9);
+
+        // The two errors straddle the 10:35 bucket boundary but share a window
+        let window = peaks.error_window.unwrap();
+        assert_eq!(window.start.format("%H:%M:%S").to_string(), "10:34:30");
+        assert_eq!(window.end.format("%H:%M:%S").to_string(), "10:39:30");
+        assert_eq!((window.errors, window.requests), (2, 4));
+        assert_eq!(window.error_rate, 50.0);
+
+        assert!(super::peaks(&entries[..2], Duration::from_secs(60)).unwrap().error_window.is_none());
+        assert!(super::peaks(&[], Duration::from_secs(60)).is_none());
+    }
+
+    #[test]
+    fn counts_unique_new_and_returning_clients() {
+        let entries = [
+            visit("2024-01-15T10:00:00Z", "10.0.0.1"),
+            visit("2024-01-15T10:10:00Z", "10.0.0.1"),
+            visit("2024-01-15T10:20:00Z", "10.0.0.2"),
+            visit("2024-01-15T11:05:00Z", "10.0.0.3"),
+            visit("2024-01-15T11:10:00Z", "10.0.0.1"),
+            visit("2024-01-15T12:30:00Z", "10.0.0.2"),
+        ];
+        let timeline = bucket(&entries, Duration::from_secs(3600), ClientCounting::Exact).unwrap();
+        let visitors: Vec<_> = timeline.buckets.iter().map(|b| b.clients.map(|c| (c.unique_ips, c.new_ips))).collect();
+        assert_eq!(visitors, [Some((2, 2)), Some((2, 1)), Some((1, 0))]);
+        // 10.0.0.1 and 10.0.0.2 came back in a later hour; 10.0.0.3 did not
+        assert_eq!(timeline.returning_ips, Some(2));
+
+        // Sketches of a handful of clients are exact
+        let approx = bucket(&entries, Duration::from_secs(3600), ClientCounting::Approx).unwrap();
+        let estimated: Vec<_> = approx.buckets.iter().map(|b| b.clients.map(|c| (c.unique_ips, c.new_ips))).collect();
+        assert_eq!(estimated, visitors);
+        assert_eq!(approx.returning_ips, None);
+
+        // Out of time order, buckets are sketched a window at a time
+        let mut shuffled = entries.clone();
+        shuffled.reverse();
+        let approx = bucket(&shuffled, Duration::from_secs(3600), ClientCounting::Approx).unwrap();
+        let estimated: Vec<_> = approx.buckets.iter().map(|b| b.clients.map(|c| (c.unique_ips, c.new_ips))).collect();
+        assert_eq!(estimated, visitors);
+
+        // Unless asked for, clients are not counted at all
+        let uncounted = bucket(&entries, Duration::from_secs(3600), ClientCounting::Off).unwrap();
+        assert!(uncounted.buckets.iter().all(|b| b.clients.is_none()));
+        assert_eq!((uncounted.returning_ips, uncounted.client_count_error), (None, None));
+    }
+
+    #[test]
+    fn refuses_zero_width_and_too_many_buckets() {
+        let entries = [entry("2024-01-15T10:30:00Z", LogLevel::Info), entry("2024-12-15T10:30:00Z", LogLevel::Info)];
+        assert_eq!(bucket(&entries, Duration::ZERO, ClientCounting::Off).unwrap_err(), TimelineError::ZeroWidth);
+        assert!(matches!(bucket(&entries, Duration::from_secs(60), ClientCounting::Off), Err(TimelineError::TooManyBuckets { .. })));
+        let empty = bucket(&[], Duration::from_secs(60), ClientCounting::Off).unwrap();
+        assert!(empty.buckets.is_empty());
+        assert!(empty.rate_stats().is_none());
+    }
+}
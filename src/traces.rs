@@ -0,0 +1,140 @@
+use crate::parser::LogEntry;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The requests logged under one trace (or request) id that contained errors
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorTrace {
+    pub trace_id: String,
+    pub requests: usize,
+    /// Requests logged at error level or above
+    pub errors: usize,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Distinct endpoints of the failed requests, in the order they failed
+    pub failed_endpoints: Vec<String>,
+}
+
+/// Entries grouped by trace id, for formats that log one
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceReport {
+    /// Requests that logged a trace id
+    pub traced: usize,
+    /// Requests without one
+    pub untraced: usize,
+    /// Distinct trace ids
+    pub traces: usize,
+    /// Traces spanning more than one request
+    pub multi_request_traces: usize,
+    /// Traces with at least one error
+    pub error_traces: usize,
+    /// The traces with the most errors
+    pub top_error_traces: Vec<ErrorTrace>,
+}
+
+/// Group entries by trace id and rank the traces that contained errors, most
+/// errors first, then earliest.
+///
+/// Returns `None` when no entry logs a trace id.
+pub fn analyze(entries: &[LogEntry], top_n: usize) -> Option<TraceReport> {
+    struct Tally<'a> {
+        requests: usize,
+        errors: usize,
+        first_seen: DateTime<Utc>,
+        last_seen: DateTime<Utc>,
+        failed_endpoints: Vec<&'a str>,
+    }
+
+    let mut traces: HashMap<&str, Tally> = HashMap::new();
+    for entry in entries {
+        let Some(trace_id) = entry.trace_id.as_deref() else { continue };
+        let tally = traces.entry(trace_id).or_insert(Tally {
+            requests: 0,
+            errors: 0,
+            first_seen: entry.timestamp,
+            last_seen: entry.timestamp,
+            failed_endpoints: Vec::new(),
+        });
+        tally.requests += 1;
+        tally.first_seen = tally.first_seen.min(entry.timestamp);
+        tally.last_seen = tally.last_seen.max(entry.timestamp);
+        if entry.level.is_error() {
+            tally.errors += 1;
+            if !tally.failed_endpoints.contains(&entry.endpoint.as_str()) {
+                tally.failed_endpoints.push(&entry.endpoint);
+            }
+        }
+    }
+    let traced: usize = traces.values().map(|t| t.requests).sum();
+    if traced == 0 {
+        return None;
+    }
+
+    let distinct = traces.len();
+    let multi_request_traces = traces.values().filter(|t| t.requests > 1).count();
+    let mut failed: Vec<ErrorTrace> = traces
+        .into_iter()
+        .filter(|(_, tally)| tally.errors > 0)
+        .map(|(trace_id, tally)| ErrorTrace {
+            trace_id: trace_id.to_string(),
+            requests: tally.requests,
+            errors: tally.errors,
+            first_seen: tally.first_seen,
+            last_seen: tally.last_seen,
+            failed_endpoints: tally.failed_endpoints.into_iter().map(String::from).collect(),
+        })
+        .collect();
+    let error_traces = failed.len();
+    failed.sort_by(|a, b| {
+        b.errors
+            .cmp(&a.errors)
+            .then_with(|| a.first_seen.cmp(&b.first_seen))
+            .then_with(|| a.trace_id.cmp(&b.trace_id))
+    });
+    failed.truncate(top_n);
+
+    Some(TraceReport {
+        traced,
+        untraced: entries.len() - traced,
+        traces: distinct,
+        multi_request_traces,
+        error_traces,
+        top_error_traces: failed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_line, JsonKeys, LogFormat};
+
+    #[test]
+    fn groups_entries_and_ranks_traces_with_errors() {
+        let line = |second: u32, trace: &str, path: &str, status: u16| {
+            let line = format!(
+                r#"{{"time":"2024-01-15T10:30:{:02}Z","ip":"10.0.0.1","method":"GET","path":"{}","status":{},"trace_id":"{}"}}"#,
+                second, path, status, trace
+            );
+            parse_line(&line, &LogFormat::Json(JsonKeys::default())).unwrap()
+        };
+        let mut entries = vec![
+            line(1, "a", "/checkout", 200),
+            line(2, "a", "/payment", 502),
+            line(3, "a", "/payment", 503),
+            line(4, "b", "/cart", 500),
+            line(5, "c", "/", 200),
+        ];
+        entries[4].trace_id = None;
+        let report = analyze(&entries, 10).unwrap();
+
+        assert_eq!((report.traced, report.untraced, report.traces), (4, 1, 2));
+        assert_eq!((report.multi_request_traces, report.error_traces), (1, 2));
+        let worst = &report.top_error_traces[0];
+        assert_eq!((worst.trace_id.as_str(), worst.requests, worst.errors), ("a", 3, 2));
+        assert_eq!(worst.failed_endpoints, ["/payment"]);
+        assert_eq!((worst.last_seen - worst.first_seen).num_seconds(), 2);
+        assert_eq!(report.top_error_traces[1].trace_id, "b");
+        assert!(analyze(&entries[4..], 10).is_none());
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+/// Parse a human duration such as `90s`, `10m`, `1h30m` or `2d`.
+///
+/// A bare number is taken as seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err("empty duration".to_string());
+    }
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total = 0u64;
+    let mut digits = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3_600,
+            'd' => 86_400,
+            _ => return Err(format!("invalid duration '{}': unknown unit '{}' (use s, m, h or d)", s, c)),
+        };
+        let value: u64 = digits
+            .parse()
+            .map_err(|_| format!("invalid duration '{}': expected a number before '{}'", s, c))?;
+        total += value * unit;
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        return Err(format!("invalid duration '{}': missing unit after '{}'", s, digits));
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Format a duration compactly to whole seconds, e.g. `1h30m` or `45s`
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs == 0 {
+        return "0s".to_string();
+    }
+    let parts = [(secs / 86_400, 'd'), (secs / 3_600 % 24, 'h'), (secs / 60 % 60, 'm'), (secs % 60, 's')];
+    parts
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, unit)| format!("{}{}", n, unit))
+        .collect()
+}
+
+/// Parse a response time such as `500ms`, `1.5s` or `2s`, down to the millisecond.
+///
+/// Unlike [`parse_duration`] a unit is required, since latencies are as often given
+/// in milliseconds as in seconds.
+pub fn parse_latency(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (number, scale) = if let Some(ms) = s.strip_suffix("ms") {
+        (ms, 1.0)
+    } else if let Some(secs) = s.strip_suffix('s') {
+        (secs, 1000.0)
+    } else {
+        return Err(format!("invalid latency '{}': missing unit (use ms or s, e.g. 500ms)", s));
+    };
+    match number.parse::<f64>() {
+        Ok(value) if value >= 0.0 && value.is_finite() => Ok(Duration::from_millis((value * scale).round() as u64)),
+        _ => Err(format!("invalid latency '{}': expected a number before the unit", s)),
+    }
+}
+
+/// Format a response time as [`parse_latency`] reads it: whole seconds as `2s`,
+/// anything else in milliseconds, e.g. `500ms`
+pub fn format_latency(d: Duration) -> String {
+    match d.as_millis() {
+        ms if ms > 0 && ms % 1000 == 0 => format!("{}s", ms / 1000),
+        ms => format!("{}ms", ms),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_unit_suffixes() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5_400)));
+        assert_eq!(parse_duration("2d"), Ok(Duration::from_secs(172_800)));
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5m3").is_err());
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        for s in ["45s", "10m", "1h30m", "2d3h"] {
+            assert_eq!(format_duration(parse_duration(s).unwrap()), s);
+        }
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+    }
+
+    #[test]
+    fn parses_latencies() {
+        assert_eq!(parse_latency("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_latency("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_latency("2s"), Ok(Duration::from_secs(2)));
+        assert!(parse_latency("500").is_err());
+        assert!(parse_latency("-1s").is_err());
+        assert!(parse_latency("ms").is_err());
+        for s in ["500ms", "2s", "1500ms"] {
+            assert_eq!(format_latency(parse_latency(s).unwrap()), s);
+        }
+    }
+}
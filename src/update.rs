@@ -0,0 +1,284 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// GitHub API endpoint describing the most recent release
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/Hollowbh/log_analyzer/releases/latest";
+
+/// Environment variable that opts in to the new-version notice after each run
+pub const CHECK_ENV_VAR: &str = "LOG_ANALYZER_CHECK_UPDATES";
+
+/// A published release and its downloadable assets
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    /// The release version without a leading `v`
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    /// The asset built for the given architecture and OS.
+    ///
+    /// Release binaries are named `log_analyzer-<arch>-<os>` with an optional
+    /// extension, e.g. `log_analyzer-x86_64-linux` or `log_analyzer-aarch64-macos`.
+    pub fn asset_for(&self, arch: &str, os: &str) -> Option<&Asset> {
+        const SIDECARS: [&str; 3] = [".sha256", ".asc", ".sig"];
+        let stem = format!("log_analyzer-{}-{}", arch, os);
+        self.assets.iter().find(|a| a.name == stem).or_else(|| {
+            self.assets.iter().find(|a| {
+                a.name.strip_prefix(&stem).is_some_and(|rest| rest.starts_with('.'))
+                    && !SIDECARS.iter().any(|s| a.name.ends_with(s))
+            })
+        })
+    }
+
+    /// The `<asset>.sha256` sidecar published alongside `asset`
+    pub fn checksum_for(&self, asset: &Asset) -> Option<&Asset> {
+        let name = format!("{}.sha256", asset.name);
+        self.assets.iter().find(|a| a.name == name)
+    }
+}
+
+/// Errors that can occur while checking for or installing an update
+#[derive(Debug)]
+pub enum UpdateError {
+    Http(String),
+    Io(io::Error),
+    /// The latest release has no binary for this platform
+    NoAsset { arch: &'static str, os: &'static str },
+    /// The release publishes no `.sha256` sidecar for the binary
+    NoChecksum(String),
+    /// The downloaded binary does not match its published SHA-256 digest
+    ChecksumMismatch { asset: String, expected: String, actual: String },
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateError::Http(msg) => write!(f, "request failed: {}", msg),
+            UpdateError::Io(e) => write!(f, "{}", e),
+            UpdateError::NoAsset { arch, os } => {
+                write!(f, "the latest release has no binary for {}-{}", arch, os)
+            }
+            UpdateError::NoChecksum(asset) => {
+                write!(f, "the latest release publishes no checksum for {}; refusing to install it", asset)
+            }
+            UpdateError::ChecksumMismatch { asset, expected, actual } => write!(
+                f,
+                "{} does not match its published checksum (expected SHA-256 {}, got {}); refusing to install it",
+                asset, expected, actual
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for UpdateError {
+    fn from(e: io::Error) -> Self {
+        UpdateError::Io(e)
+    }
+}
+
+impl From<ureq::Error> for UpdateError {
+    fn from(e: ureq::Error) -> Self {
+        UpdateError::Http(e.to_string())
+    }
+}
+
+fn agent(timeout: Duration) -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .user_agent(concat!("log_analyzer/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .into()
+}
+
+/// Fetch metadata for the most recent release
+pub fn latest_release(timeout: Duration) -> Result<Release, UpdateError> {
+    let body = agent(timeout)
+        .get(LATEST_RELEASE_URL)
+        .header("Accept", "application/vnd.github+json")
+        .call()?
+        .body_mut()
+        .read_to_string()?;
+    serde_json::from_str(&body).map_err(|e| UpdateError::Http(format!("unexpected response: {}", e)))
+}
+
+/// Whether `candidate` is a strictly newer dotted version than `current`
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    fn parts(v: &str) -> Vec<u64> {
+        v.trim_start_matches('v')
+            .split(['.', '-', '+'])
+            .map_while(|p| p.parse().ok())
+            .collect()
+    }
+    parts(candidate) > parts(current)
+}
+
+/// The lowercase hex digest a `.sha256` sidecar names, in `sha256sum` output form
+/// (`<digest>  <file>`) or bare
+fn parse_checksum(sidecar: &str) -> Option<String> {
+    let digest = sidecar.split_whitespace().next()?;
+    (digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit())).then(|| digest.to_ascii_lowercase())
+}
+
+/// Lowercase hex SHA-256 digest of the file at `path`
+fn file_digest(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Download `asset`, check it against the SHA-256 digest in its `.sha256` sidecar from
+/// `release`, and atomically replace the running executable with it.
+///
+/// Refuses to install when the release publishes no sidecar or the digests differ.
+/// Returns the path of the replaced executable.
+pub fn install(release: &Release, asset: &Asset, timeout: Duration) -> Result<PathBuf, UpdateError> {
+    let sidecar = release
+        .checksum_for(asset)
+        .ok_or_else(|| UpdateError::NoChecksum(asset.name.clone()))?;
+    let agent = agent(timeout);
+    let body = agent.get(&sidecar.browser_download_url).call()?.body_mut().read_to_string()?;
+    let expected = parse_checksum(&body)
+        .ok_or_else(|| UpdateError::Http(format!("{} does not hold a SHA-256 digest", sidecar.name)))?;
+
+    let exe = std::env::current_exe()?;
+    let staged = exe.with_extension("download");
+
+    let mut response = agent.get(&asset.browser_download_url).call()?;
+    let mut reader = response.body_mut().as_reader();
+    let mut out = File::create(&staged)?;
+    io::copy(&mut reader, &mut out)?;
+    out.sync_all()?;
+    drop(out);
+
+    let actual = file_digest(&staged)?;
+    if actual != expected {
+        let _ = fs::remove_file(&staged);
+        return Err(UpdateError::ChecksumMismatch { asset: asset.name.clone(), expected, actual });
+    }
+
+    make_executable(&staged)?;
+    replace_executable(&exe, &staged)?;
+    Ok(exe)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Swap the staged binary into place. Windows cannot overwrite a running
+/// executable, so the old one is moved aside first.
+fn replace_executable(exe: &Path, staged: &Path) -> io::Result<()> {
+    if cfg!(windows) {
+        let old = exe.with_extension("old");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old)?;
+    }
+    fs::rename(staged, exe)
+}
+
+/// Print a one-line notice on stderr if the user opted in and a newer release exists.
+///
+/// Any failure is silently ignored so that an offline machine never breaks a run.
+pub fn notify_if_outdated() {
+    if std::env::var_os(CHECK_ENV_VAR).is_none_or(|v| v.is_empty() || v == "0") {
+        return;
+    }
+    if let Ok(release) = latest_release(Duration::from_secs(3)) {
+        if is_newer(release.version(), env!("CARGO_PKG_VERSION")) {
+            tracing::warn!(
+                latest = release.version(),
+                current = env!("CARGO_PKG_VERSION"),
+                "a newer log_analyzer is available; run `log_analyzer self-update`"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(names: &[&str]) -> Release {
+        Release {
+            tag_name: "v1.2.0".to_string(),
+            assets: names
+                .iter()
+                .map(|n| Asset {
+                    name: n.to_string(),
+                    browser_download_url: format!("https://example.invalid/{}", n),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn compares_versions_numerically() {
+        assert!(is_newer("0.10.0", "0.9.3"));
+        assert!(is_newer("v1.0.0", "0.1.0"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("0.0.9", "0.1.0"));
+    }
+
+    #[test]
+    fn selects_asset_for_platform() {
+        let r = release(&[
+            "log_analyzer-x86_64-linux.tar.gz.sha256",
+            "log_analyzer-x86_64-linux",
+            "log_analyzer-x86_64-windows.exe",
+        ]);
+        assert_eq!(r.version(), "1.2.0");
+        assert_eq!(r.asset_for("x86_64", "linux").unwrap().name, "log_analyzer-x86_64-linux");
+        assert_eq!(
+            r.asset_for("x86_64", "windows").unwrap().name,
+            "log_analyzer-x86_64-windows.exe"
+        );
+        assert!(r.asset_for("aarch64", "macos").is_none());
+    }
+
+    #[test]
+    fn finds_checksum_sidecar() {
+        let r = release(&["log_analyzer-x86_64-linux", "log_analyzer-x86_64-linux.sha256", "log_analyzer-x86_64-windows.exe"]);
+        let linux = r.asset_for("x86_64", "linux").unwrap();
+        assert_eq!(r.checksum_for(linux).unwrap().name, "log_analyzer-x86_64-linux.sha256");
+        assert!(r.checksum_for(r.asset_for("x86_64", "windows").unwrap()).is_none());
+    }
+
+    #[test]
+    fn verifies_downloaded_digest() {
+        const HELLO: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert_eq!(parse_checksum(&format!("{}  log_analyzer-x86_64-linux\n", HELLO.to_uppercase())).as_deref(), Some(HELLO));
+        assert_eq!(parse_checksum(HELLO).as_deref(), Some(HELLO));
+        assert!(parse_checksum("not a digest").is_none());
+        assert!(parse_checksum("").is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log_analyzer.download");
+        fs::write(&path, "hello").unwrap();
+        assert_eq!(file_digest(&path).unwrap(), HELLO);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::histogram::LatencyHistogram;
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Response-time distribution over a set of requests, in milliseconds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub requests: usize,
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    /// Nearest-rank percentiles, exact for up to [`crate::histogram::EXACT_SAMPLES`]
+    /// requests and within 0.8% beyond; `None` for an empty histogram
+    fn from_histogram(mut histogram: LatencyHistogram) -> Option<Self> {
+        if histogram.is_empty() {
+            return None;
+        }
+        let mut percentile = |p: f64| millis(histogram.percentile(p));
+        let (p50_ms, p90_ms, p95_ms, p99_ms) = (percentile(0.50), percentile(0.90), percentile(0.95), percentile(0.99));
+        Some(LatencyStats {
+            requests: histogram.len(),
+            min_ms: millis(histogram.min()),
+            mean_ms: histogram.mean().round() / 1000.0,
+            p50_ms,
+            p90_ms,
+            p95_ms,
+            p99_ms,
+            max_ms: millis(histogram.max()),
+        })
+    }
+}
+
+/// Latency of a single endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointLatency {
+    pub endpoint: String,
+    #[serde(flatten)]
+    pub latency: LatencyStats,
+}
+
+/// Statuses the backend returned, as opposed to what the proxy sent the client
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendStatuses {
+    pub distribution: HashMap<String, usize>,
+    /// Requests the proxy answered itself because no backend status was logged
+    pub no_response: usize,
+    /// Requests where the client got a different status than the backend returned
+    pub rewritten: usize,
+}
+
+/// How long requests took and, behind a proxy or load balancer, what the backends returned
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency: Option<LatencyStats>,
+    /// Per-endpoint latency, highest p95 first
+    pub slowest_endpoints: Vec<EndpointLatency>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend_statuses: Option<BackendStatuses>,
+}
+
+/// Summarise response times and backend statuses, for formats that log them.
+///
+/// Returns `None` when no entry carries either, so plain access logs get no section.
+pub fn analyze(entries: &[LogEntry], top_n: usize) -> Option<UpstreamReport> {
+    let mut overall = LatencyHistogram::new();
+    let mut by_endpoint: HashMap<&str, LatencyHistogram> = HashMap::new();
+    let mut distribution: HashMap<String, usize> = HashMap::new();
+    let mut no_response = 0usize;
+    let mut rewritten = 0usize;
+
+    for entry in entries {
+        if let Some(duration) = entry.duration {
+            let micros = duration.as_micros() as u64;
+            overall.record(micros);
+            by_endpoint.entry(entry.endpoint.as_str()).or_default().record(micros);
+        }
+        match entry.backend_status {
+            Some(code) => {
+                *distribution.entry(code.to_string()).or_insert(0) += 1;
+                if Some(code) != entry.status_code {
+                    rewritten += 1;
+                }
+            }
+            None => no_response += 1,
+        }
+    }
+    if overall.is_empty() && distribution.is_empty() {
+        return None;
+    }
+
+    let mut slowest_endpoints: Vec<EndpointLatency> = by_endpoint
+        .into_iter()
+        .filter_map(|(endpoint, histogram)| {
+            Some(EndpointLatency {
+                endpoint: endpoint.to_string(),
+                latency: LatencyStats::from_histogram(histogram)?,
+            })
+        })
+        .collect();
+    slowest_endpoints.sort_by(|a, b| {
+        b.latency
+            .p95_ms
+            .total_cmp(&a.latency.p95_ms)
+            .then_with(|| a.endpoint.cmp(&b.endpoint))
+    });
+    slowest_endpoints.truncate(top_n);
+
+    Some(UpstreamReport {
+        latency: LatencyStats::from_histogram(overall),
+        slowest_endpoints,
+        backend_statuses: (!distribution.is_empty()).then_some(BackendStatuses {
+            distribution,
+            no_response,
+            rewritten,
+        }),
+    })
+}
+
+/// Milliseconds from the microseconds parsers produce
+fn millis(micros: u64) -> f64 {
+    micros as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_entry;
+    use std::time::Duration;
+
+    fn entry(endpoint: &str, status: u16, latency_ms: Option<f64>, backend_status: Option<u16>) -> LogEntry {
+        LogEntry {
+            duration: latency_ms.map(|ms| Duration::from_micros((ms * 1000.0) as u64)),
+            backend_status,
+            ..test_entry("2024-01-15T10:00:00Z", "1.1.1.1", endpoint, status)
+        }
+    }
+
+    #[test]
+    fn summarises_latency_and_backend_statuses() {
+        let mut entries: Vec<LogEntry> = (1..=100)
+            .map(|ms| entry("/fast", 200, Some(ms as f64), Some(200)))
+            .collect();
+        entries.push(entry("/slow", 200, Some(900.0), Some(200)));
+        entries.push(entry("/slow", 504, None, None));
+        entries.push(entry("/slow", 502, Some(3.0), Some(500)));
+
+        let report = analyze(&entries, 10).unwrap();
+        let latency = report.latency.unwrap();
+        assert_eq!(latency.requests, 102);
+        assert_eq!(latency.min_ms, 1.0);
+        assert_eq!(latency.p50_ms, 50.0);
+        assert_eq!(latency.p90_ms, 91.0);
+        assert_eq!(latency.max_ms, 900.0);
+        assert_eq!(report.slowest_endpoints[0].endpoint, "/slow");
+        assert_eq!(report.slowest_endpoints[0].latency.p50_ms, 3.0);
+
+        let backend = report.backend_statuses.unwrap();
+        assert_eq!(backend.distribution["200"], 101);
+        assert_eq!(backend.no_response, 1);
+        assert_eq!(backend.rewritten, 1);
+    }
+
+    #[test]
+    fn omitted_for_plain_access_logs() {
+        assert!(analyze(&[entry("/", 200, None, None)], 10).is_none());
+    }
+}
@@ -0,0 +1,98 @@
+use crate::parser::LogEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Requests made by one authenticated user
+#[derive(Debug, Clone, Serialize)]
+pub struct UserCount {
+    pub user: String,
+    pub requests: usize,
+    /// Share of authenticated requests
+    pub percentage: f64,
+    /// Requests logged at error level or above, as for flagged IPs
+    pub errors: usize,
+    pub error_rate: f64,
+}
+
+/// Top authenticated users, for formats that log one (`%u` in CLF, `user=` keys)
+#[derive(Debug, Clone, Serialize)]
+pub struct UserReport {
+    /// Requests that logged a user
+    pub authenticated: usize,
+    /// Requests without one
+    pub anonymous: usize,
+    /// Distinct users seen
+    pub distinct: usize,
+    pub users: Vec<UserCount>,
+}
+
+/// Count requests and errors per user, busiest first.
+///
+/// Returns `None` when no entry logs a user.
+pub fn analyze(entries: &[LogEntry], top_n: usize) -> Option<UserReport> {
+    let mut users: HashMap<&str, (usize, usize)> = HashMap::new();
+    for entry in entries {
+        let Some(user) = entry.user.as_deref() else { continue };
+        let tally = users.entry(user).or_insert((0, 0));
+        tally.0 += 1;
+        if entry.level.is_error() {
+            tally.1 += 1;
+        }
+    }
+    let authenticated: usize = users.values().map(|t| t.0).sum();
+    if authenticated == 0 {
+        return None;
+    }
+    let pct = |n: usize, of: usize| (n as f64 / of as f64 * 10000.0).round() / 100.0;
+
+    let distinct = users.len();
+    let mut ranked: Vec<UserCount> = users
+        .into_iter()
+        .map(|(user, (requests, errors))| UserCount {
+            user: user.to_string(),
+            requests,
+            percentage: pct(requests, authenticated),
+            errors,
+            error_rate: pct(errors, requests),
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.user.cmp(&b.user)));
+    ranked.truncate(top_n);
+
+    Some(UserReport {
+        authenticated,
+        anonymous: entries.len() - authenticated,
+        distinct,
+        users: ranked,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_line, LogFormat};
+
+    #[test]
+    fn counts_requests_and_errors_per_user() {
+        let line = |user: &str, status: u16| {
+            let line = format!(r#"10.0.0.1 - {} [15/Jan/2024:10:30:00 +0000] "GET /api HTTP/1.1" {} 5"#, user, status);
+            parse_line(&line, &LogFormat::Clf).unwrap()
+        };
+        let entries = vec![
+            line("alice", 200),
+            line("alice", 500),
+            line("bob", 200),
+            line("-", 200),
+        ];
+        let report = analyze(&entries, 10).unwrap();
+
+        assert_eq!((report.authenticated, report.anonymous, report.distinct), (3, 1, 2));
+        assert_eq!(report.users[0].user, "alice");
+        assert_eq!(report.users[0].requests, 2);
+        assert_eq!(report.users[0].percentage, 66.67);
+        assert_eq!(report.users[0].errors, 1);
+        assert_eq!(report.users[0].error_rate, 50.0);
+        assert_eq!(report.users[1].errors, 0);
+        assert!(analyze(&entries[3..], 10).is_none());
+    }
+}
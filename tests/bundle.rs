@@ -0,0 +1,128 @@
+//! End-to-end test of the `bundle` subcommand: with every section of the report
+//! turned on, no client IP or user name from the log may reach the bundle, and the
+//! evidence is sampled whatever the log format.
+
+#![cfg(feature = "bundle")]
+
+use flate2::read::GzDecoder;
+use log_analyzer::parser::{parse_line, LogFormat};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+
+/// Options that turn on every optional section
+const EVERY_SECTION: &[&str] = &[
+    "-e", "0",
+    "--unique",
+    "--status-classes",
+    "--methods",
+    "--endpoint-errors",
+    "--browsers",
+    "--bots",
+    "--burst-threshold", "1",
+    "--attacks",
+    "--scanner-threshold", "1",
+    "--scanner-404-share", "1",
+    "--apdex-t", "10ms",
+    "--slo", "99.9",
+    "--time-columns",
+    "--compare-cohorts",
+    "--redirects",
+    "--by-router",
+    "--bucket", "1m",
+    "--anomalies",
+    "--by-trace",
+    "--sessions",
+    "--forecast",
+    "--site-host", "shop.example.com",
+    "--robots", "tests/fixtures/robots.txt",
+    "--cost-per-gb", "0.09",
+    "--cost-per-million-requests", "0.4",
+    "--min-endpoint-requests", "1",
+    "-n", "50",
+];
+
+/// Read a file out of the bundle at `path`
+fn bundled_file(path: &Path, name: &str) -> String {
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(path).unwrap()));
+    for file in archive.entries().unwrap() {
+        let mut file = file.unwrap();
+        if file.path().unwrap().ends_with(name) {
+            let mut contents = String::new();
+            file.read_to_string(&mut contents).unwrap();
+            return contents;
+        }
+    }
+    panic!("{} is not in the bundle", name);
+}
+
+/// Bundle `fixture` with every section on and check that none of its client IPs or
+/// users appear in `stats.json` or `evidence.log`. Returns the evidence.
+fn assert_redacted(fixture: &str, format: &str, log_format: LogFormat) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(fixture);
+    let mut identifiers = BTreeSet::new();
+    for line in fs::read_to_string(&path).unwrap().lines() {
+        let Ok(entry) = parse_line(line, &log_format) else { continue };
+        identifiers.insert(entry.ip.to_string());
+        identifiers.extend(entry.user);
+    }
+    assert!(!identifiers.is_empty(), "{} has no client to look for", fixture);
+
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = dir.path().join("bundle.tar.gz");
+    let mut command = Command::new(env!("CARGO_BIN_EXE_log_analyzer"));
+    command
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg("bundle")
+        .arg(&path)
+        .arg("-o")
+        .arg(&bundle)
+        .args(["--quiet", "--format", format])
+        .args(EVERY_SECTION);
+    if cfg!(feature = "geoip") {
+        command.args(["--geoip", "tests/fixtures/geoip.mmdb", "--asn", "tests/fixtures/asn.mmdb", "--impossible-travel"]);
+    }
+    let output = command.output().expect("failed to run log_analyzer");
+    assert!(output.status.success(), "bundle failed on {}: {}", fixture, String::from_utf8_lossy(&output.stderr));
+
+    for name in ["stats.json", "evidence.log"] {
+        let contents = bundled_file(&bundle, name);
+        for identifier in &identifiers {
+            assert!(!contents.contains(identifier.as_str()), "{} from {} leaked into {}", identifier, fixture, name);
+        }
+    }
+    bundled_file(&bundle, "evidence.log")
+}
+
+#[test]
+fn native_log_bundle_is_redacted() {
+    assert_redacted("native.log", "native", LogFormat::Native);
+}
+
+#[test]
+fn clf_bundle_redacts_users() {
+    let evidence = assert_redacted("clf.log", "clf", LogFormat::Clf);
+    assert!(!evidence.is_empty());
+    assert_redacted("travel.log", "clf", LogFormat::Clf);
+}
+
+#[test]
+fn combined_bundles_are_redacted() {
+    for fixture in ["nginx_combined.log", "attacks.log", "referrers.log", "browsers.log"] {
+        assert_redacted(fixture, "nginx-combined", LogFormat::NginxCombined);
+    }
+}
+
+#[test]
+fn detected_format_bundle_samples_evidence() {
+    let evidence = assert_redacted("nginx_combined.log", "auto", LogFormat::NginxCombined);
+    assert!(!evidence.is_empty());
+}
+
+#[test]
+fn envoy_bundle_with_traces_is_redacted() {
+    let evidence = assert_redacted("envoy.log", "envoy", LogFormat::Envoy);
+    assert!(!evidence.is_empty());
+}
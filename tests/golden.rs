@@ -0,0 +1,628 @@
+//! End-to-end tests: run the binary over fixture logs and compare the terminal
+//! report and JSON export against known-good output in `tests/golden/`.
+//!
+//! After an intentional output change, regenerate the golden files with
+//! `UPDATE_GOLDEN=1 cargo test --test golden` and review the diff.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Placeholder for the temporary JSON path, which differs between runs
+const JSON_PATH_MARKER: &str = "<json-output>";
+
+fn manifest_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Remove ANSI escape sequences, in case color slips through
+fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Run the analyzer on `fixture` with `args`, returning (terminal report, JSON export)
+fn run(fixture: &str, args: &[&str]) -> (String, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let json_path: PathBuf = dir.path().join("report.json");
+    let output = Command::new(env!("CARGO_BIN_EXE_log_analyzer"))
+        .current_dir(manifest_dir())
+        .arg(Path::new("tests/fixtures").join(fixture))
+        .args(["--deterministic", "--quiet", "--json-output"])
+        .arg(&json_path)
+        .args(args)
+        .output()
+        .expect("failed to run log_analyzer");
+    assert!(
+        output.status.success(),
+        "log_analyzer failed on {}: {}",
+        fixture,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = strip_ansi(&String::from_utf8(output.stdout).unwrap())
+        .replace(&json_path.display().to_string(), JSON_PATH_MARKER);
+    let json = fs::read_to_string(&json_path).unwrap();
+    (stdout, json)
+}
+
+/// Compare `actual` with the golden file, or rewrite it when `UPDATE_GOLDEN` is set
+fn assert_golden(name: &str, actual: &str) {
+    let path = manifest_dir().join("tests/golden").join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("missing golden file {} ({}); run with UPDATE_GOLDEN=1", path.display(), e));
+    if expected != actual {
+        let first_diff = expected
+            .lines()
+            .zip(actual.lines())
+            .position(|(e, a)| e != a)
+            .unwrap_or(expected.lines().count().min(actual.lines().count()));
+        panic!(
+            "{} differs from golden output at line {}:\n  expected: {:?}\n  actual:   {:?}\n\
+             (run with UPDATE_GOLDEN=1 to accept the new output)",
+            name,
+            first_diff + 1,
+            expected.lines().nth(first_diff),
+            actual.lines().nth(first_diff)
+        );
+    }
+}
+
+fn check(case: &str, fixture: &str, args: &[&str]) {
+    let (report, json) = run(fixture, args);
+    assert_golden(&format!("{}.txt", case), &report);
+    assert_golden(&format!("{}.json", case), &json);
+}
+
+/// Like [`check`], but compare only the report sections whose titles start with one of
+/// `titles` and the JSON export's `keys`, so that a feature's golden files follow its
+/// own output and not the rest of the report
+fn check_sections(case: &str, fixture: &str, args: &[&str], titles: &[&str], keys: &[&str]) {
+    let (report, json) = run(fixture, args);
+    assert_golden(&format!("{}.txt", case), &sections(&report, titles));
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let picked: serde_json::Map<String, serde_json::Value> = keys
+        .iter()
+        .map(|&key| {
+            let value = json.get(key).unwrap_or_else(|| panic!("the JSON export of {} has no {}", case, key));
+            (key.to_string(), value.clone())
+        })
+        .collect();
+    assert_golden(&format!("{}.json", case), &(serde_json::to_string_pretty(&picked).unwrap() + "\n"));
+}
+
+/// The sections of a terminal report whose titles start with one of `titles`, from
+/// their `▶` heading to the next heading or the closing separator
+fn sections(report: &str, titles: &[&str]) -> String {
+    for title in titles {
+        assert!(report.contains(&format!("  ▶ {}", title)), "no {} section in:\n{}", title, report);
+    }
+    let mut kept = String::new();
+    let mut keep = false;
+    for line in report.lines() {
+        if let Some(title) = line.strip_prefix("  ▶ ") {
+            keep = titles.iter().any(|t| title.starts_with(t));
+        } else if line.starts_with('═') {
+            keep = false;
+        }
+        if keep {
+            kept.push_str(line);
+            kept.push('\n');
+        }
+    }
+    kept
+}
+
+#[test]
+fn native_format_default_report() {
+    check("native", "native.log", &[]);
+}
+
+#[test]
+fn native_format_with_time_columns_and_cohorts() {
+    check("native_sections", "native.log", &["--top", "3", "--time-columns", "--compare-cohorts"]);
+}
+
+#[test]
+fn multiple_files_and_globs() {
+    // Each file is detected on its own; the pattern is expanded by the tool, not a shell
+    check("multiple_files", "clf.log", &["tests/fixtures/nginx_*.log"]);
+}
+
+#[test]
+fn manifest_lists_files_like_arguments() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("manifest.txt");
+    fs::write(&manifest, "# handed off by the nightly batch\ntests/fixtures/nginx_*.log\n\n").unwrap();
+    assert_eq!(
+        run("clf.log", &["--files-from", manifest.to_str().unwrap()]),
+        run("clf.log", &["tests/fixtures/nginx_*.log"])
+    );
+}
+
+#[test]
+fn endpoints_collapsed_into_templates() {
+    let dir = tempfile::tempdir().unwrap();
+    let patterns = dir.path().join("routes.txt");
+    fs::write(&patterns, "/api/sessions/{session}\n").unwrap();
+    check_sections(
+        "path_templates",
+        "native.log",
+        &["--collapse-paths", "--route-patterns", patterns.to_str().unwrap(), "-n", "20"],
+        &["TOP 20 ENDPOINTS"],
+        &["top_endpoints"],
+    );
+}
+
+#[test]
+fn anomalies_against_a_rolling_baseline() {
+    check_sections(
+        "anomalies",
+        "native.log",
+        &["--bucket", "1s", "--anomalies", "--baseline-window", "3"],
+        &["REQUESTS OVER TIME", "ANOMALIES"],
+        &["timeline", "anomalies"],
+    );
+}
+
+#[test]
+fn anomalies_against_a_saved_baseline() {
+    let dir = tempfile::tempdir().unwrap();
+    let baseline = dir.path().join("baseline.json");
+    let baseline = baseline.to_str().unwrap();
+    run("native.log", &["--bucket", "5s", "--save-baseline", baseline]);
+    check_sections(
+        "anomalies_baseline",
+        "nginx_combined.log",
+        &["--bucket", "5s", "--anomalies", "--baseline", baseline],
+        &["REQUESTS OVER TIME", "ANOMALIES"],
+        &["timeline", "anomalies"],
+    );
+}
+
+#[test]
+fn attack_signatures() {
+    check_sections("attacks", "attacks.log", &["--attacks"], &["ATTACK SIGNATURES"], &["attacks"]);
+}
+
+#[test]
+fn not_found_scanners() {
+    check_sections(
+        "scanners",
+        "attacks.log",
+        &["--scanner-threshold", "3", "--scanner-404-share", "80"],
+        &["404 SCANNERS"],
+        &["scanners"],
+    );
+}
+
+#[test]
+fn referrer_sources_and_spam() {
+    let dir = tempfile::tempdir().unwrap();
+    let spam = dir.path().join("spam.txt");
+    fs::write(&spam, "# seen in February\ntraffic-boost.example\n").unwrap();
+    check_sections(
+        "referrers",
+        "referrers.log",
+        &["--site-host", "shop.example.com", "--referrer-spam", spam.to_str().unwrap()],
+        &["TOP REFERRERS"],
+        &["referrers"],
+    );
+}
+
+#[test]
+fn browser_and_os_breakdown() {
+    check_sections("browsers", "browsers.log", &["--browsers"], &["BROWSERS AND OPERATING SYSTEMS"], &["browsers"]);
+}
+
+#[test]
+fn bots_by_agent_robots_txt_and_cadence() {
+    check_sections("bots", "browsers.log", &["--bots"], &["BOTS"], &["bots"]);
+}
+
+#[test]
+fn apdex_against_target() {
+    check_sections("apdex", "alb.log", &["--format", "alb", "--apdex-t", "10ms"], &["APDEX"], &["apdex"]);
+}
+
+#[test]
+fn slo_error_budget() {
+    check_sections("slo", "native.log", &["--slo", "95"], &["SLO"], &["slo"]);
+}
+
+#[test]
+fn unique_clients_overall_and_per_bucket() {
+    check_sections(
+        "unique",
+        "native.log",
+        &["--unique", "--bucket", "5s"],
+        &["OVERVIEW", "REQUESTS OVER TIME"],
+        &["unique_ips", "unique_endpoints", "timeline"],
+    );
+}
+
+#[test]
+fn status_classes_with_success_and_error_rates() {
+    check_sections(
+        "status_classes",
+        "envoy.log",
+        &["--format", "envoy", "--status-classes"],
+        &["STATUS CLASSES"],
+        &["status_classes"],
+    );
+}
+
+#[test]
+fn http_methods_overall_and_per_endpoint() {
+    check_sections("methods", "native.log", &["--methods"], &["HTTP METHODS"], &["method_distribution", "endpoint_methods"]);
+}
+
+#[test]
+fn approximate_unique_counts_without_buckets() {
+    let (stdout, json) = run("native.log", &["--approx"]);
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    // Sketches of a handful of values are exact
+    assert_eq!((json["unique_ips"].as_u64(), json["unique_endpoints"].as_u64()), (Some(10), Some(14)));
+    assert_eq!(json["cardinality_error"].as_f64(), Some(0.008125));
+    assert!(stdout.contains("Unique client IPs:              ≈10"), "{}", stdout);
+    assert!(stdout.contains("(HyperLogLog estimates, ±0.81% standard error)"));
+}
+
+#[test]
+fn every_nth_line_sample() {
+    check("every_nth", "clf.log", &["--every-nth", "3", "-e", "1"]);
+}
+
+#[test]
+fn last_lines_after_w3c_directives() {
+    // The first of the last lines is laid out by the #Fields directive at the top of the file
+    check("last_lines", "iis.log", &["--last-lines", "6", "-e", "1"]);
+}
+
+#[test]
+fn include_rotated_copies() {
+    // access.log.2 and access.log.1 are read before access.log; access.log.bak is not a copy
+    check("include_rotated", "rotated/access.log", &["--include-rotated"]);
+}
+
+#[test]
+fn directory_with_include_and_exclude() {
+    // The fixtures directory itself, narrowed to a few files of different formats
+    check(
+        "directory",
+        "",
+        &["--include", "nginx_*.log", "--include", "traefik*", "--exclude", "*_json.log"],
+    );
+}
+
+#[test]
+fn state_file_runs_add_up_to_a_full_run() {
+    let plain = fs::read(manifest_dir().join("tests/fixtures/clf.log")).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let (log, state) = (dir.path().join("clf.log"), dir.path().join("clf.state"));
+    let state_args = ["--state-file", state.to_str().unwrap()];
+
+    // The first run stops mid-line; the rest of that line is read with the appended lines
+    let middle = plain.len() / 2;
+    fs::write(&log, &plain[..middle]).unwrap();
+    run(log.to_str().unwrap(), &state_args);
+    fs::write(&log, &plain).unwrap();
+    let (_, incremental) = run(log.to_str().unwrap(), &state_args);
+
+    let (_, full) = run("clf.log", &[]);
+    let incremental: serde_json::Value = serde_json::from_str(&incremental).unwrap();
+    let full: serde_json::Value = serde_json::from_str(&full).unwrap();
+    for key in ["total_entries", "malformed_entries", "level_counts", "top_ips", "top_endpoints", "flagged_ips", "status_code_distribution"] {
+        assert_eq!(incremental[key], full[key], "{}", key);
+    }
+}
+
+#[test]
+fn compressed_inputs_match_the_plain_file() {
+    // Each fixture holds two members or frames, as left by appending to an archive
+    let (plain_report, plain_json) = run("clf.log", &[]);
+    let compressed = [
+        ("clf.log.gz", cfg!(feature = "gzip")),
+        ("clf.log.zst", cfg!(feature = "zstd")),
+        ("clf.log.bz2", cfg!(feature = "bzip2")),
+        ("clf.log.xz", cfg!(feature = "xz")),
+    ];
+    for (fixture, _) in compressed.iter().filter(|(_, enabled)| *enabled) {
+        let (report, json) = run(fixture, &[]);
+        assert_eq!(report.replace(fixture, "clf.log"), plain_report, "{}", fixture);
+        assert_eq!(json.replace(fixture, "clf.log"), plain_json, "{}", fixture);
+    }
+}
+
+#[cfg(feature = "geoip")]
+#[test]
+fn requests_by_country_from_a_geoip_database() {
+    check_sections("geoip", "native.log", &["--geoip", "tests/fixtures/geoip.mmdb"], &["REQUESTS BY COUNTRY"], &["countries"]);
+}
+
+#[cfg(feature = "geoip")]
+#[test]
+fn impossible_travel_of_authenticated_users() {
+    check_sections(
+        "impossible_travel",
+        "travel.log",
+        &["--format", "clf", "--geoip", "tests/fixtures/geoip.mmdb", "--impossible-travel"],
+        &["IMPOSSIBLE TRAVEL"],
+        &["travel"],
+    );
+}
+
+#[cfg(feature = "geoip")]
+#[test]
+fn network_owners_from_an_asn_database() {
+    check_sections(
+        "asn",
+        "native.log",
+        &["--asn", "tests/fixtures/asn.mmdb", "-e", "2"],
+        &["FLAGGED IPs", "TOP NETWORKS"],
+        &["flagged_ips", "networks"],
+    );
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn memory_mapped_input_matches_reading_it() {
+    for (fixture, args) in [("native.log", &["--compare-cohorts"][..]), ("iis.log", &[]), ("clf.log", &["--every-nth", "2"])] {
+        let mut mapped = args.to_vec();
+        mapped.push("--mmap");
+        assert_eq!(run(fixture, &mapped), run(fixture, args), "{}", fixture);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn piped_input_is_read_once_like_the_file() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let dir = tempfile::tempdir().unwrap();
+    let json_path = dir.path().join("report.json");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_log_analyzer"))
+        .current_dir(manifest_dir())
+        .args(["/dev/stdin", "--deterministic", "--quiet", "-e", "1", "--json-output"])
+        .arg(&json_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("failed to run log_analyzer");
+    let log = fs::read(manifest_dir().join("tests/fixtures/clf.log")).unwrap();
+    child.stdin.take().unwrap().write_all(&log).unwrap();
+    assert!(child.wait().unwrap().success());
+    // The lines sampled to detect the format are parsed too
+    assert_eq!(fs::read_to_string(&json_path).unwrap(), run("clf.log", &["-e", "1"]).1);
+}
+
+#[test]
+fn common_log_format() {
+    check("clf", "clf.log", &["--format", "clf", "--error-threshold", "0"]);
+}
+
+#[test]
+fn nginx_combined_format() {
+    check("nginx_combined", "nginx_combined.log", &["--format", "nginx-combined", "--redirects"]);
+}
+
+#[test]
+fn aws_alb_format() {
+    check("alb", "alb.log", &["--format", "alb", "-e", "1"]);
+}
+
+#[test]
+fn s3_access_format() {
+    check("s3_access", "s3_access.log", &["--format", "s3-access", "-e", "1"]);
+}
+
+#[test]
+fn nginx_main_format_behind_proxy() {
+    check(
+        "nginx_main_trust_proxy",
+        "nginx_main.log",
+        &["--format", "nginx", "-e", "1", "--trust-proxy", "rightmost-untrusted", "--trusted-proxy", "10.0.0.0/8"],
+    );
+}
+
+#[test]
+fn envoy_format() {
+    check("envoy", "envoy.log", &["--format", "envoy", "-e", "1"]);
+}
+
+#[test]
+fn envoy_format_by_trace() {
+    check("envoy_traces", "envoy.log", &["--format", "envoy", "-e", "1", "--by-trace"]);
+}
+
+#[test]
+fn cloudfront_w3c_format() {
+    check("cloudfront", "cloudfront.log", &["--format", "cloudfront", "-e", "1"]);
+}
+
+#[test]
+fn iis_w3c_format() {
+    check("iis", "iis.log", &["--format", "iis", "-e", "1"]);
+}
+
+#[test]
+fn caddy_json_format() {
+    check("caddy", "caddy.log", &["--format", "caddy", "-e", "1"]);
+}
+
+#[test]
+fn heroku_router_format() {
+    check("heroku", "heroku.log", &["--format", "heroku", "-e", "1"]);
+}
+
+#[test]
+fn traefik_common_format() {
+    check("traefik", "traefik.log", &["--format", "traefik", "-e", "1"]);
+}
+
+#[test]
+fn traefik_json_format_by_router() {
+    check("traefik_json", "traefik_json.log", &["--format", "traefik", "-e", "1", "--by-router"]);
+}
+
+#[test]
+fn cef_security_events() {
+    check("cef", "cef.log", &["--format", "cef", "-e", "1"]);
+}
+
+#[test]
+fn gelf_messages() {
+    check("gelf", "gelf.log", &["--format", "gelf", "-e", "1"]);
+}
+
+#[test]
+fn journal_export_entries() {
+    check("journal", "journal.export", &["--journal", "--format", "nginx-combined", "-e", "1"]);
+}
+
+#[test]
+fn csv_export_with_header_row() {
+    check("csv", "export.csv", &["--format", "csv", "-e", "1"]);
+}
+
+#[test]
+fn json_lines_with_configured_keys() {
+    check(
+        "json_lines",
+        "json_lines.log",
+        &["--format", "json", "--json-key", "ip=client.ip", "--json-key", "bytes=bytes_sent", "-e", "1"],
+    );
+}
+
+#[test]
+fn logfmt_with_key_alias() {
+    check("logfmt", "logfmt.log", &["--format", "logfmt", "--json-key", "ip=remote", "-e", "1"]);
+}
+
+#[test]
+fn declarative_format_file() {
+    check("format_file", "gateway.log", &["--format-file", "tests/fixtures/gateway.toml", "-e", "1"]);
+}
+
+#[test]
+fn syslog_wrapped_clf() {
+    check("syslog", "syslog.log", &["--syslog", "--format", "clf", "-e", "1"]);
+}
+
+#[test]
+fn docker_wrapped_nginx_combined() {
+    check("docker", "docker.log", &["--docker", "--format", "nginx-combined", "-e", "1"]);
+}
+
+#[test]
+fn flags_ips_on_5xx_responses() {
+    check_sections(
+        "flag_on_5xx",
+        "envoy.log",
+        &["--format", "envoy", "--flag-on", "5xx", "-e", "0"],
+        &["FLAGGED IPs"],
+        &["flag_on", "flagged_ips"],
+    );
+}
+
+#[test]
+fn endpoints_ranked_by_error_rate() {
+    check_sections(
+        "endpoint_errors",
+        "envoy.log",
+        &["--format", "envoy", "--endpoint-errors", "--min-endpoint-requests", "1"],
+        &["TOP 10 ENDPOINTS BY 4xx", "TOP 10 ENDPOINTS BY 5xx", "ENDPOINTS BY 5xx RATE"],
+        &["endpoint_errors"],
+    );
+}
+
+#[test]
+fn request_bursts_per_ip() {
+    check_sections("bursts", "native.log", &["--burst-threshold", "2", "--burst-window", "10s"], &["REQUEST BURSTS"], &["bursts"]);
+}
+
+#[test]
+fn sessions_split_at_the_inactivity_gap() {
+    check_sections("sessions", "native.log", &["--sessions", "--session-gap", "5s"], &["SESSIONS"], &["sessions"]);
+}
+
+#[test]
+fn requests_over_time_in_buckets() {
+    check("timeline", "envoy.log", &["--format", "envoy", "--bucket", "1m", "-e", "1"]);
+}
+
+#[test]
+fn mixed_input_with_sizes_redirects_and_robots() {
+    check(
+        "mixed",
+        "mixed.log",
+        &[
+            "--mixed",
+            "--error-threshold",
+            "1",
+            "--redirects",
+            "--robots",
+            "tests/fixtures/robots.txt",
+            "--cost-per-gb",
+            "0.09",
+            "--cost-per-million-requests",
+            "0.4",
+        ],
+    );
+}
+
+#[test]
+fn deterministic_output_is_stable_across_runs() {
+    let args = ["--compare-cohorts"];
+    assert_eq!(run("native.log", &args), run("native.log", &args));
+}
+
+#[test]
+fn auto_detects_fixture_formats() {
+    let cases: [(&str, &str, &[&str]); 19] = [
+        ("clf.log", "clf", &[]),
+        ("nginx_combined.log", "nginx-combined", &[]),
+        ("nginx_main.log", "nginx-combined", &[]),
+        ("alb.log", "alb", &[]),
+        ("s3_access.log", "s3-access", &[]),
+        ("envoy.log", "envoy", &[]),
+        ("cloudfront.log", "cloudfront", &[]),
+        ("iis.log", "iis", &[]),
+        ("caddy.log", "caddy", &[]),
+        ("heroku.log", "heroku", &[]),
+        ("traefik.log", "traefik", &[]),
+        ("traefik_json.log", "traefik", &[]),
+        ("cef.log", "cef", &[]),
+        ("gelf.log", "gelf", &[]),
+        ("export.csv", "csv", &[]),
+        ("logfmt.log", "logfmt", &["--json-key", "ip=remote"]),
+        ("syslog.log", "clf", &["--syslog"]),
+        ("docker.log", "nginx-combined", &["--docker"]),
+        ("journal.export", "nginx-combined", &["--journal"]),
+    ];
+    for (fixture, format, args) in cases {
+        let (report, _) = run(fixture, args);
+        assert!(
+            report.contains(&format!("Format : {} (detected", format)),
+            "{} was not detected as {}",
+            fixture,
+            format
+        );
+    }
+}